@@ -0,0 +1,579 @@
+// Runtime of programs compiled with --target=jvm, the counterpart of runtime.c.
+// The program is run with:
+//
+//   java -cp <dir of the program> <program> [args]...
+//
+// Strings hold bytes as characters 0-255, they are read and written as ISO-8859-1.
+// Lists are ArrayLists of boxed elements, maps are LatteMaps. The program runs in
+// a thread with a large stack, since deep recursion is common in Latte.
+
+import java.io.*;
+import java.lang.reflect.InvocationTargetException;
+import java.lang.reflect.Method;
+import java.math.BigDecimal;
+import java.math.BigInteger;
+import java.math.RoundingMode;
+import java.nio.charset.StandardCharsets;
+import java.nio.file.Files;
+import java.nio.file.InvalidPathException;
+import java.nio.file.Paths;
+import java.util.ArrayList;
+
+public class LatteRuntime {
+    private static final long STACK_SIZE = 1L << 30;
+
+    // File and process I/O, files are identified by handles: 0, 1 and 2 are the
+    // standard streams, other handles are indices in files. Failures are reported
+    // through the returned values.
+    private static final LatteFile[] files = new LatteFile[256];
+
+    private static int exitCode = 0;
+
+    static {
+        files[0] = new LatteFile(new FileInputStream(FileDescriptor.in), null);
+        files[1] = new LatteFile(null, new BufferedOutputStream(new FileOutputStream(FileDescriptor.out), 1 << 16));
+        files[2] = new LatteFile(null, new FileOutputStream(FileDescriptor.err));
+    }
+
+    // Called by main of the program class, runs main of the program (named $main), which
+    // may take its arguments as an array of strings. Its result is the exit code.
+    public static void start(String program, String[] args) {
+        Thread thread = new Thread(null, () -> run(program, args), "main", STACK_SIZE);
+        thread.start();
+        while (thread.isAlive()) {
+            try {
+                thread.join();
+            } catch (InterruptedException e) {
+                // the program is still running
+            }
+        }
+        exit(exitCode);
+    }
+
+    private static void run(String program, String[] args) {
+        try {
+            for (Method main : Class.forName(program).getMethods()) {
+                if (!main.getName().equals("$main")) {
+                    continue;
+                }
+                Object res = main.getParameterCount() == 0 ? main.invoke(null) : main.invoke(null, (Object) toLatte(args));
+                exitCode = (Integer) res;
+            }
+        } catch (InvocationTargetException e) {
+            crash(e.getCause());
+        } catch (ReflectiveOperationException e) {
+            crash(e);
+        }
+    }
+
+    // Exceptions are failures of the program, e.g. division by zero or a null object.
+    private static void crash(Throwable t) {
+        flush();
+        System.err.println(t);
+        exit(1);
+    }
+
+    private static String[] toLatte(String[] args) {
+        String[] res = new String[args.length];
+        for (int i = 0; i < args.length; i++) {
+            res[i] = new String(args[i].getBytes(), StandardCharsets.ISO_8859_1);
+        }
+        return res;
+    }
+
+    // Buffered output is written before foreign code runs and at the exit.
+    public static void flush() {
+        for (LatteFile file : files) {
+            if (file != null && file.out != null) {
+                try {
+                    file.out.flush();
+                } catch (IOException e) {
+                    // nothing to be done at this point
+                }
+            }
+        }
+    }
+
+    private static void exit(int code) {
+        flush();
+        System.exit(code);
+    }
+
+    public static void error() {
+        write(files[1], "runtime error\n");
+        exit(1);
+    }
+
+    // arithmetic, error() on overflow
+
+    public static int checkedAdd(int lhs, int rhs) {
+        return (int) checkInt((long) lhs + rhs);
+    }
+
+    public static int checkedSub(int lhs, int rhs) {
+        return (int) checkInt((long) lhs - rhs);
+    }
+
+    public static int checkedMul(int lhs, int rhs) {
+        return (int) checkInt((long) lhs * rhs);
+    }
+
+    private static long checkInt(long res) {
+        if (res < Integer.MIN_VALUE || res > Integer.MAX_VALUE) {
+            error();
+        }
+        return res;
+    }
+
+    public static long checkedAdd(long lhs, long rhs) {
+        try {
+            return Math.addExact(lhs, rhs);
+        } catch (ArithmeticException e) {
+            error();
+            return 0;
+        }
+    }
+
+    public static long checkedSub(long lhs, long rhs) {
+        try {
+            return Math.subtractExact(lhs, rhs);
+        } catch (ArithmeticException e) {
+            error();
+            return 0;
+        }
+    }
+
+    public static long checkedMul(long lhs, long rhs) {
+        try {
+            return Math.multiplyExact(lhs, rhs);
+        } catch (ArithmeticException e) {
+            error();
+            return 0;
+        }
+    }
+
+    // lists, indices are checked as in runtime.c
+
+    public static Object listGet(ArrayList<Object> l, int idx) {
+        checkIndex(idx, l.size() - 1);
+        return l.get(idx);
+    }
+
+    public static void listSet(ArrayList<Object> l, int idx, Object v) {
+        checkIndex(idx, l.size() - 1);
+        l.set(idx, v);
+    }
+
+    public static Object listPop(ArrayList<Object> l) {
+        checkIndex(0, l.size() - 1);
+        return l.remove(l.size() - 1);
+    }
+
+    public static void listInsert(ArrayList<Object> l, int idx, Object v) {
+        checkIndex(idx, l.size());
+        l.add(idx, v);
+    }
+
+    public static Object listRemove(ArrayList<Object> l, int idx) {
+        checkIndex(idx, l.size() - 1);
+        return l.remove(idx);
+    }
+
+    private static void checkIndex(int idx, int max) {
+        if (idx < 0 || idx > max) {
+            error();
+        }
+    }
+
+    // standard input and output
+
+    public static void printInt(int x) {
+        write(files[1], x + "\n");
+    }
+
+    public static void printDouble(double x) {
+        write(files[1], formatDouble(x) + "\n");
+    }
+
+    public static void printString(String s) {
+        write(files[1], s + "\n");
+    }
+
+    // printf("%f"), the exact value of the double is rounded
+    private static String formatDouble(double x) {
+        if (Double.isNaN(x)) {
+            return Double.doubleToRawLongBits(x) < 0 ? "-nan" : "nan";
+        }
+        if (Double.isInfinite(x)) {
+            return x > 0 ? "inf" : "-inf";
+        }
+        String res = new BigDecimal(x).setScale(6, RoundingMode.HALF_EVEN).toPlainString();
+        boolean isNegative = x < 0 || Double.doubleToRawLongBits(x) < 0;
+        return isNegative && !res.startsWith("-") ? "-" + res : res;
+    }
+
+    // scanf("%d") and getchar()
+    public static int readInt() {
+        String num = files[0].readNumber(false);
+        files[0].read();
+        if (!num.matches("[+-]?[0-9]+")) {
+            return 0;
+        }
+        BigInteger x = new BigInteger(num);
+        x = x.max(BigInteger.valueOf(Long.MIN_VALUE)).min(BigInteger.valueOf(Long.MAX_VALUE));
+        return x.intValue();
+    }
+
+    // scanf("%lf") and getchar()
+    public static double readDouble() {
+        String num = files[0].readNumber(true);
+        files[0].read();
+        try {
+            return Double.parseDouble(num);
+        } catch (NumberFormatException e) {
+            return 0;
+        }
+    }
+
+    public static String readString() {
+        String line = files[0].readLine();
+        return line == null ? "" : line;
+    }
+
+    // Whether both strings are the same object
+    public static boolean sameString(String lhs, String rhs) {
+        return lhs == rhs;
+    }
+
+    // Returns the handle of the file, -1 if it can't be opened
+    public static int openFile(String path, String mode) {
+        LatteFile file;
+        try {
+            switch (mode.replace("b", "").replace("+", "")) {
+                case "r":
+                    file = new LatteFile(new FileInputStream(path), null);
+                    break;
+                case "w":
+                    file = new LatteFile(null, new BufferedOutputStream(new FileOutputStream(path)));
+                    break;
+                case "a":
+                    file = new LatteFile(null, new BufferedOutputStream(new FileOutputStream(path, true)));
+                    break;
+                default:
+                    return -1;
+            }
+        } catch (IOException e) {
+            return -1;
+        }
+        for (int f = 3; f < 256; f++) {
+            if (files[f] == null) {
+                files[f] = file;
+                return f;
+            }
+        }
+        file.close();
+        return -1;
+    }
+
+    // The standard streams are not closed
+    public static boolean closeFile(int f) {
+        if (f < 3 || getFile(f) == null) {
+            return false;
+        }
+        LatteFile file = files[f];
+        files[f] = null;
+        return file.close();
+    }
+
+    public static boolean writeFile(int f, String s) {
+        return write(getFile(f), s);
+    }
+
+    // The next line without the newline, empty at the end of the file
+    public static String readLine(int f) {
+        LatteFile file = getFile(f);
+        String line = file == null ? null : file.readLine();
+        return line == null ? "" : line;
+    }
+
+    // True if there's nothing more to read, or the file is not open
+    public static boolean endOfFile(int f) {
+        LatteFile file = getFile(f);
+        return file == null || file.peek() == -1;
+    }
+
+    // The whole contents of the file, empty if it can't be read
+    public static String readFile(String path) {
+        try {
+            return new String(Files.readAllBytes(Paths.get(path)), StandardCharsets.ISO_8859_1);
+        } catch (IOException | InvalidPathException e) {
+            return "";
+        }
+    }
+
+    public static void printError(String s) {
+        write(files[2], s + "\n");
+    }
+
+    public static void exitWith(int code) {
+        exit(code);
+    }
+
+    // Empty if the variable is not set
+    public static String getEnv(String name) {
+        String value = System.getenv(name);
+        return value == null ? "" : new String(value.getBytes(), StandardCharsets.ISO_8859_1);
+    }
+
+    // Strings returned by extern functions, null is the empty string
+    public static String nonNull(String s) {
+        return s == null ? "" : s;
+    }
+
+    // The stream of a handle, null if it's not open
+    private static LatteFile getFile(int f) {
+        return f >= 0 && f < 256 ? files[f] : null;
+    }
+
+    private static boolean write(LatteFile file, String s) {
+        if (file == null || file.out == null) {
+            return false;
+        }
+        try {
+            file.out.write(s.getBytes(StandardCharsets.ISO_8859_1));
+            return true;
+        } catch (IOException e) {
+            return false;
+        }
+    }
+}
+
+// An open file, which can be read or written
+class LatteFile {
+    final PushbackInputStream in;
+    final OutputStream out;
+
+    LatteFile(InputStream in, OutputStream out) {
+        this.in = in == null ? null : new PushbackInputStream(new BufferedInputStream(in), 3);
+        this.out = out;
+    }
+
+    // the next byte, -1 at the end of the file
+    int read() {
+        try {
+            return in == null ? -1 : in.read();
+        } catch (IOException e) {
+            return -1;
+        }
+    }
+
+    int peek() {
+        int c = read();
+        unread(c);
+        return c;
+    }
+
+    void unread(int c) {
+        try {
+            if (c != -1) {
+                in.unread(c);
+            }
+        } catch (IOException e) {
+            // there is always room for the pushed back bytes
+        }
+    }
+
+    // The next line without the newline, null at the end of the file
+    String readLine() {
+        StringBuilder line = new StringBuilder();
+        int c;
+        while ((c = read()) != -1 && c != '\n') {
+            line.append((char) c);
+        }
+        if (c == -1 && line.length() == 0) {
+            return null;
+        }
+        return line.toString();
+    }
+
+    // Characters of a number as scanf reads them, after whitespace. A double may have
+    // a fraction and an exponent, which is read only if a digit follows.
+    String readNumber(boolean isDouble) {
+        int c;
+        while ((c = read()) == ' ' || (c >= '\t' && c <= '\r')) {
+            // whitespace is skipped
+        }
+        StringBuilder num = new StringBuilder();
+        if (c == '+' || c == '-') {
+            num.append((char) c);
+            c = read();
+        }
+        c = readDigits(c, num);
+        if (isDouble && c == '.') {
+            num.append('.');
+            c = readDigits(read(), num);
+        }
+        if (isDouble && (c == 'e' || c == 'E')) {
+            int next = read();
+            int sign = -1;
+            if (next == '+' || next == '-') {
+                sign = next;
+                next = read();
+            }
+            boolean hasExponent = next >= '0' && next <= '9';
+            unread(next);
+            if (hasExponent) {
+                num.append((char) c);
+                if (sign != -1) {
+                    num.append((char) sign);
+                }
+                c = readDigits(read(), num);
+            } else {
+                if (sign != -1) {
+                    unread(sign);
+                }
+            }
+        }
+        unread(c);
+        return num.toString();
+    }
+
+    // Appends the digits starting with c, returns the character after them
+    private int readDigits(int c, StringBuilder num) {
+        while (c >= '0' && c <= '9') {
+            num.append((char) c);
+            c = read();
+        }
+        return c;
+    }
+
+    boolean close() {
+        try {
+            if (in != null) {
+                in.close();
+            }
+            if (out != null) {
+                out.close();
+            }
+            return true;
+        } catch (IOException e) {
+            return false;
+        }
+    }
+}
+
+// Maps with separate chaining, the hashes and the order of keys are the ones of
+// runtime.c. Keys are Integers or Strings, values are boxed.
+class LatteMap {
+    public int size = 0;
+    private Entry[] buckets = new Entry[16];
+
+    private static class Entry {
+        Object key;
+        Object value;
+        Entry next;
+
+        Entry(Object key, Object value) {
+            this.key = key;
+            this.value = value;
+        }
+    }
+
+    private int bucket(Object key) {
+        long h;
+        if (key instanceof String) {
+            h = 0xcbf29ce484222325L;
+            String s = (String) key;
+            for (int i = 0; i < s.length(); i++) {
+                h = (h ^ s.charAt(i)) * 0x100000001b3L;
+            }
+        } else {
+            h = ((Integer) key).longValue() * 0x9e3779b97f4a7c15L;
+            h ^= h >>> 32;
+        }
+        return (int) Long.remainderUnsigned(h, buckets.length);
+    }
+
+    private Entry find(Object key) {
+        Entry entry = buckets[bucket(key)];
+        while (entry != null && !entry.key.equals(key)) {
+            entry = entry.next;
+        }
+        return entry;
+    }
+
+    private void rehash() {
+        Entry[] old = buckets;
+        buckets = new Entry[old.length * 2];
+        for (Entry entry : old) {
+            while (entry != null) {
+                Entry next = entry.next;
+                int b = bucket(entry.key);
+                entry.next = buckets[b];
+                buckets[b] = entry;
+                entry = next;
+            }
+        }
+    }
+
+    public void put(Object key, Object value) {
+        Entry entry = find(key);
+        if (entry != null) {
+            entry.value = value;
+            return;
+        }
+        int b = bucket(key);
+        if (buckets[b] == null) {
+            buckets[b] = new Entry(key, value);
+        } else {
+            Entry last = buckets[b];
+            while (last.next != null) {
+                last = last.next;
+            }
+            last.next = new Entry(key, value);
+        }
+        size++;
+        if (size > buckets.length) {
+            rehash();
+        }
+    }
+
+    public Object get(Object key) {
+        Entry entry = find(key);
+        if (entry == null) {
+            LatteRuntime.error();
+        }
+        return entry.value;
+    }
+
+    public boolean contains(Object key) {
+        return find(key) != null;
+    }
+
+    public void remove(Object key) {
+        int b = bucket(key);
+        Entry prev = null;
+        for (Entry entry = buckets[b]; entry != null; prev = entry, entry = entry.next) {
+            if (!entry.key.equals(key)) {
+                continue;
+            }
+            if (prev == null) {
+                buckets[b] = entry.next;
+            } else {
+                prev.next = entry.next;
+            }
+            size--;
+            return;
+        }
+    }
+
+    public ArrayList<Object> keys() {
+        ArrayList<Object> keys = new ArrayList<>();
+        for (Entry entry : buckets) {
+            for (; entry != null; entry = entry.next) {
+                keys.add(entry.key);
+            }
+        }
+        return keys;
+    }
+}
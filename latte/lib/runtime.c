@@ -1,52 +1,661 @@
+// Runtime of programs compiled with --target=c, the counterpart of runtime.ll.
+// The types must match the ones declared in the generated code.
+
+#include <stdbool.h>
+#include <stdint.h>
 #include <stdio.h>
 #include <stdlib.h>
 #include <string.h>
 
-void printInt(int x) { printf("%d\n", x); }
+typedef struct {
+  int32_t ref_count;
+  char *chars;
+  bool is_const;
+} string_t;
+
+typedef struct {
+  int32_t length;
+  void *elems;
+} array_t;
+
+// elements are stored in 64-bit slots, strings as pointers
+typedef struct {
+  int32_t size;
+  int32_t capacity;
+  int64_t *elems;
+  bool is_str;
+} list_t;
 
-void printString(char *s) { puts(s); }
+typedef struct map_entry_t {
+  int64_t key;
+  int64_t value;
+  struct map_entry_t *next;
+} map_entry_t;
+
+typedef struct {
+  int32_t size;
+  int32_t buckets_cnt;
+  map_entry_t **buckets;
+  bool str_keys;
+  bool str_values;
+} map_t;
+
+// characters end with a zero
+typedef struct {
+  int32_t length;
+  int32_t capacity;
+  char *chars;
+} builder_t;
 
 void error() {
   puts("runtime error");
   exit(EXIT_FAILURE);
 }
 
-int readInt() {
-  int x;
-  scanf("%d", &x);
-  getchar();
+void _check_overflow(bool overflow) {
+  if (overflow) {
+    error();
+  }
+}
+
+// strings
+
+string_t *_alloc_str() {
+  string_t *s = malloc(sizeof(string_t));
+  s->ref_count = 0;
+  s->chars = NULL;
+  s->is_const = false;
+  return s;
+}
+
+void _retain_str(string_t *s) { s->ref_count++; }
+
+void _release_str(string_t *s) {
+  s->ref_count--;
+  if (s->ref_count == 0) {
+    if (!s->is_const) {
+      free(s->chars);
+    }
+    free(s);
+  }
+}
+
+// Takes the ownership of the characters
+string_t *_new_str(char *chars) {
+  string_t *s = _alloc_str();
+  s->ref_count = 1;
+  s->chars = chars;
+  return s;
+}
+
+// NULL is the empty string, e.g. returned by an extern function
+string_t *_copy_str(char *chars) {
+  if (chars == NULL) {
+    chars = "";
+  }
+  size_t size = strlen(chars) + 1;
+  char *copy = malloc(size);
+  memcpy(copy, chars, size);
+  return _new_str(copy);
+}
+
+string_t *_empty_str() { return _copy_str(""); }
+
+char *_str_chars(string_t *s) { return s->chars == NULL ? "" : s->chars; }
+
+// all elements are the same empty string
+void _init_str_arr(array_t *arr) {
+  if (arr->length <= 0) {
+    return;
+  }
+  string_t *empty = _alloc_str();
+  empty->ref_count = arr->length;
+  for (int32_t i = 0; i < arr->length; i++) {
+    ((string_t **)arr->elems)[i] = empty;
+  }
+}
+
+string_t *_concatenate(string_t *lhs, string_t *rhs) {
+  char *lhs_chars = _str_chars(lhs);
+  char *rhs_chars = _str_chars(rhs);
+  size_t lhs_len = strlen(lhs_chars);
+  char *chars = malloc(lhs_len + strlen(rhs_chars) + 1);
+  strcpy(chars, lhs_chars);
+  strcpy(chars + lhs_len, rhs_chars);
+  return _new_str(chars);
+}
+
+// Compares contents of two strings, used by == and switch statements
+bool _str_eq(string_t *lhs, string_t *rhs) { return strcmp(_str_chars(lhs), _str_chars(rhs)) == 0; }
+
+// Lexicographic order of strings, negative if lhs is less than rhs
+int32_t _str_cmp(string_t *lhs, string_t *rhs) { return strcmp(_str_chars(lhs), _str_chars(rhs)); }
+
+// Whether both strings are the same object
+bool sameString(string_t *lhs, string_t *rhs) { return lhs == rhs; }
+
+// arithmetic, error() on overflow
+
+int32_t _checked_add_i32(int32_t lhs, int32_t rhs) {
+  int64_t res = (int64_t)lhs + rhs;
+  _check_overflow(res < INT32_MIN || res > INT32_MAX);
+  return (int32_t)res;
+}
+
+int32_t _checked_sub_i32(int32_t lhs, int32_t rhs) {
+  int64_t res = (int64_t)lhs - rhs;
+  _check_overflow(res < INT32_MIN || res > INT32_MAX);
+  return (int32_t)res;
+}
+
+int32_t _checked_mul_i32(int32_t lhs, int32_t rhs) {
+  int64_t res = (int64_t)lhs * rhs;
+  _check_overflow(res < INT32_MIN || res > INT32_MAX);
+  return (int32_t)res;
+}
+
+int64_t _checked_add_i64(int64_t lhs, int64_t rhs) {
+  _check_overflow((rhs > 0 && lhs > INT64_MAX - rhs) || (rhs < 0 && lhs < INT64_MIN - rhs));
+  return lhs + rhs;
+}
+
+int64_t _checked_sub_i64(int64_t lhs, int64_t rhs) {
+  _check_overflow((rhs < 0 && lhs > INT64_MAX + rhs) || (rhs > 0 && lhs < INT64_MIN + rhs));
+  return lhs - rhs;
+}
+
+int64_t _checked_mul_i64(int64_t lhs, int64_t rhs) {
+  if (lhs == 0 || rhs == 0) {
+    return 0;
+  }
+  _check_overflow((lhs == -1 && rhs == INT64_MIN) || (rhs == -1 && lhs == INT64_MIN));
+  int64_t res = (int64_t)((uint64_t)lhs * (uint64_t)rhs);
+  _check_overflow(res / rhs != lhs);
+  return res;
+}
+
+int32_t _checked_div_i32(int32_t lhs, int32_t rhs) {
+  _check_overflow(lhs == INT32_MIN && rhs == -1);
+  return lhs / rhs;
+}
+
+int32_t _checked_mod_i32(int32_t lhs, int32_t rhs) {
+  _check_overflow(lhs == INT32_MIN && rhs == -1);
+  return lhs % rhs;
+}
+
+int64_t _checked_div_i64(int64_t lhs, int64_t rhs) {
+  _check_overflow(lhs == INT64_MIN && rhs == -1);
+  return lhs / rhs;
+}
+
+int64_t _checked_mod_i64(int64_t lhs, int64_t rhs) {
+  _check_overflow(lhs == INT64_MIN && rhs == -1);
+  return lhs % rhs;
+}
+
+// conversions of doubles, truncated, NaN is 0 and values out of range saturate
+
+int32_t _double_to_i32(double x) {
+  if (x != x) {
+    return 0;
+  }
+  if (x <= INT32_MIN) {
+    return INT32_MIN;
+  }
+  if (x >= INT32_MAX) {
+    return INT32_MAX;
+  }
+  return (int32_t)x;
+}
+
+int64_t _double_to_i64(double x) {
+  if (x != x) {
+    return 0;
+  }
+  if (x <= (double)INT64_MIN) {
+    return INT64_MIN;
+  }
+  if (x >= (double)INT64_MAX) {
+    return INT64_MAX;
+  }
+  return (int64_t)x;
+}
+
+// objects, initialized by the generated code
+
+void *_new_object(size_t size) {
+  return malloc(size);
+}
+
+// arrays, the elements are zeroed
+
+array_t *_new_array(int32_t length, size_t elem_size) {
+  array_t *arr = malloc(sizeof(array_t));
+  arr->length = length;
+  arr->elems = calloc(length, elem_size);
+  return arr;
+}
+
+// slots
+
+int64_t _double_to_slot(double x) {
+  int64_t slot;
+  memcpy(&slot, &x, sizeof(slot));
+  return slot;
+}
+
+double _slot_to_double(int64_t slot) {
+  double x;
+  memcpy(&x, &slot, sizeof(x));
   return x;
 }
 
-char *readString() {
-  char *buf = NULL;
-  size_t size = 0;
+static void retain_slot(int64_t slot, bool is_str) {
+  if (is_str) {
+    _retain_str((string_t *)(intptr_t)slot);
+  }
+}
+
+static void release_slot(int64_t slot, bool is_str) {
+  if (is_str) {
+    _release_str((string_t *)(intptr_t)slot);
+  }
+}
+
+static char *slot_chars(int64_t slot) { return _str_chars((string_t *)(intptr_t)slot); }
+
+// lists
+
+list_t *_list_new(bool is_str) {
+  list_t *l = malloc(sizeof(list_t));
+  l->size = 0;
+  l->capacity = 4;
+  l->elems = malloc(4 * sizeof(int64_t));
+  l->is_str = is_str;
+  return l;
+}
+
+// Makes room for one more element
+static void list_reserve(list_t *l) {
+  if (l->size == l->capacity) {
+    l->capacity *= 2;
+    l->elems = realloc(l->elems, l->capacity * sizeof(int64_t));
+  }
+}
+
+int64_t *_list_at(list_t *l, int32_t idx) {
+  if ((uint32_t)idx >= (uint32_t)l->size) {
+    error();
+  }
+  return &l->elems[idx];
+}
+
+void _list_push(list_t *l, int64_t v) {
+  list_reserve(l);
+  l->elems[l->size++] = v;
+  retain_slot(v, l->is_str);
+}
+
+// The popped element is owned by the caller
+int64_t _list_pop(list_t *l) {
+  if (l->size == 0) {
+    error();
+  }
+  return l->elems[--l->size];
+}
+
+void _list_insert(list_t *l, int32_t idx, int64_t v) {
+  if ((uint32_t)idx > (uint32_t)l->size) {
+    error();
+  }
+  list_reserve(l);
+  memmove(&l->elems[idx + 1], &l->elems[idx], (l->size - idx) * sizeof(int64_t));
+  l->elems[idx] = v;
+  l->size++;
+  retain_slot(v, l->is_str);
+}
+
+// The removed element is owned by the caller
+int64_t _list_remove(list_t *l, int32_t idx) {
+  int64_t *dst = _list_at(l, idx);
+  int64_t v = *dst;
+  l->size--;
+  memmove(dst, dst + 1, (l->size - idx) * sizeof(int64_t));
+  return v;
+}
+
+// maps, separate chaining
 
-  getline(&buf, &size, stdin);
-  if (buf == NULL || strlen(buf) == 0) {
-    if (buf == NULL) {
-      buf = malloc(1);
+map_t *_map_new(bool str_keys, bool str_values) {
+  map_t *m = malloc(sizeof(map_t));
+  m->size = 0;
+  m->buckets_cnt = 16;
+  m->buckets = calloc(16, sizeof(map_entry_t *));
+  m->str_keys = str_keys;
+  m->str_values = str_values;
+  return m;
+}
+
+static map_entry_t **map_bucket(map_t *m, int64_t key) {
+  uint64_t h;
+  if (m->str_keys) {
+    h = 14695981039347156037u;
+    for (unsigned char *c = (unsigned char *)slot_chars(key); *c != 0; c++) {
+      h = (h ^ *c) * 1099511628211u;
     }
-    if (buf != NULL) {
-      buf[0] = 0;
+  } else {
+    h = (uint64_t)key * 11400714819323198485u;
+    h ^= h >> 32;
+  }
+  return &m->buckets[h % (uint64_t)m->buckets_cnt];
+}
+
+static bool map_key_eq(map_t *m, int64_t lhs, int64_t rhs) {
+  if (m->str_keys) {
+    return strcmp(slot_chars(lhs), slot_chars(rhs)) == 0;
+  }
+  return lhs == rhs;
+}
+
+// Returns the address of the link pointing to the entry with the given key,
+// or to null if there is no such entry
+static map_entry_t **map_find(map_t *m, int64_t key) {
+  map_entry_t **link = map_bucket(m, key);
+  while (*link != NULL && !map_key_eq(m, (*link)->key, key)) {
+    link = &(*link)->next;
+  }
+  return link;
+}
+
+static void map_rehash(map_t *m) {
+  int32_t buckets_cnt = m->buckets_cnt;
+  map_entry_t **buckets = m->buckets;
+  m->buckets_cnt *= 2;
+  m->buckets = calloc(m->buckets_cnt, sizeof(map_entry_t *));
+  for (int32_t i = 0; i < buckets_cnt; i++) {
+    map_entry_t *entry = buckets[i];
+    while (entry != NULL) {
+      map_entry_t *next = entry->next;
+      map_entry_t **bucket = map_bucket(m, entry->key);
+      entry->next = *bucket;
+      *bucket = entry;
+      entry = next;
     }
-    return buf;
   }
+  free(buckets);
+}
+
+void _map_put(map_t *m, int64_t key, int64_t value) {
+  retain_slot(value, m->str_values);
+  map_entry_t **link = map_find(m, key);
+  if (*link != NULL) {
+    int64_t old_value = (*link)->value;
+    (*link)->value = value;
+    release_slot(old_value, m->str_values);
+    return;
+  }
+  retain_slot(key, m->str_keys);
+  map_entry_t *entry = malloc(sizeof(map_entry_t));
+  entry->key = key;
+  entry->value = value;
+  entry->next = NULL;
+  *link = entry;
+  m->size++;
+  if ((uint32_t)m->size > (uint32_t)m->buckets_cnt) {
+    map_rehash(m);
+  }
+}
 
-  size = strlen(buf);
-  if (buf[size - 1] == '\n') {
-    buf[size - 1] = 0;
+// The returned value is borrowed from the map
+int64_t _map_get(map_t *m, int64_t key) {
+  map_entry_t *entry = *map_find(m, key);
+  if (entry == NULL) {
+    error();
   }
-  return buf;
+  return entry->value;
+}
+
+bool _map_contains(map_t *m, int64_t key) { return *map_find(m, key) != NULL; }
+
+void _map_remove(map_t *m, int64_t key) {
+  map_entry_t **link = map_find(m, key);
+  map_entry_t *entry = *link;
+  if (entry == NULL) {
+    return;
+  }
+  *link = entry->next;
+  release_slot(entry->key, m->str_keys);
+  release_slot(entry->value, m->str_values);
+  free(entry);
+  m->size--;
+}
+
+list_t *_map_keys(map_t *m) {
+  list_t *keys = _list_new(m->str_keys);
+  for (int32_t i = 0; i < m->buckets_cnt; i++) {
+    for (map_entry_t *entry = m->buckets[i]; entry != NULL; entry = entry->next) {
+      _list_push(keys, entry->key);
+    }
+  }
+  return keys;
+}
+
+// string builders
+
+builder_t *_builder_new() {
+  builder_t *b = malloc(sizeof(builder_t));
+  b->length = 0;
+  b->capacity = 16;
+  b->chars = malloc(16);
+  b->chars[0] = 0;
+  return b;
+}
+
+// Appends n characters, the capacity is doubled until they fit
+void _builder_append_chars(builder_t *b, char *src, int32_t n) {
+  int32_t capacity = b->capacity;
+  while (b->length + n + 1 > capacity) {
+    capacity *= 2;
+  }
+  if (capacity != b->capacity) {
+    b->chars = realloc(b->chars, capacity);
+    b->capacity = capacity;
+  }
+  memmove(b->chars + b->length, src, n);
+  b->length += n;
+  b->chars[b->length] = 0;
+}
+
+void _builder_append(builder_t *b, string_t *s) {
+  char *src = _str_chars(s);
+  _builder_append_chars(b, src, strlen(src));
+}
+
+void _builder_append_char(builder_t *b, uint8_t c) {
+  char src = (char)c;
+  _builder_append_chars(b, &src, 1);
+}
+
+// Copies the characters to a new string owned by the caller
+string_t *_builder_to_string(builder_t *b) {
+  char *chars = malloc(b->length + 1);
+  memcpy(chars, b->chars, b->length + 1);
+  return _new_str(chars);
+}
+
+// Concatenates n strings at once, the result is owned by the caller
+string_t *_concatenate_n(string_t **parts, int32_t n) {
+  builder_t *b = _builder_new();
+  for (int32_t i = 0; i < n; i++) {
+    _builder_append(b, parts[i]);
+  }
+  string_t *res = _builder_to_string(b);
+  free(b->chars);
+  free(b);
+  return res;
+}
+
+// standard input and output
+
+void printInt(int32_t x) { printf("%d\n", x); }
+
+void printDouble(double x) { printf("%f\n", x); }
+
+void printString(string_t *s) { puts(_str_chars(s)); }
+
+int32_t readInt() {
+  int32_t x = 0;
+  scanf("%d", &x);
+  getchar();
+  return x;
+}
+
+double readDouble() {
+  double x = 0;
+  scanf("%lf", &x);
+  getchar();
+  return x;
 }
 
-char *concatenate(char *lhs, char *rhs) {
-  size_t lsize = strlen(lhs);
-  char *buf = malloc(lsize + strlen(rhs) + 1);
-  if (buf == NULL) {
+// The next line without the newline, null at the end of the file
+static char *read_line(FILE *file) {
+  size_t len = 0;
+  size_t capacity = 16;
+  char *buf = malloc(capacity);
+  int c;
+  while ((c = fgetc(file)) != EOF && c != '\n') {
+    if (len + 1 == capacity) {
+      capacity *= 2;
+      buf = realloc(buf, capacity);
+    }
+    buf[len++] = (char)c;
+  }
+  if (c == EOF && len == 0) {
+    free(buf);
     return NULL;
   }
-  strcpy(buf, lhs);
-  strcpy(buf + lsize, rhs);
+  buf[len] = 0;
   return buf;
 }
+
+string_t *readString() {
+  char *line = read_line(stdin);
+  return line == NULL ? _empty_str() : _new_str(line);
+}
+
+// File and process I/O, files are identified by handles: 0, 1 and 2 are the
+// standard streams, other handles are indices in files. Failures are reported
+// through the returned values.
+static FILE *files[256];
+
+// The stream of a handle, null if it's not open
+static FILE *get_file(int32_t f) {
+  switch (f) {
+    case 0:
+      return stdin;
+    case 1:
+      return stdout;
+    case 2:
+      return stderr;
+    default:
+      return (uint32_t)f < 256 ? files[f] : NULL;
+  }
+}
+
+// Returns the handle of the file, -1 if it can't be opened
+int32_t openFile(string_t *path, string_t *mode) {
+  FILE *file = fopen(_str_chars(path), _str_chars(mode));
+  if (file == NULL) {
+    return -1;
+  }
+  for (int32_t f = 3; f < 256; f++) {
+    if (files[f] == NULL) {
+      files[f] = file;
+      return f;
+    }
+  }
+  fclose(file);
+  return -1;
+}
+
+// The standard streams are not closed
+bool closeFile(int32_t f) {
+  if ((uint32_t)f < 3 || get_file(f) == NULL) {
+    return false;
+  }
+  FILE *file = files[f];
+  files[f] = NULL;
+  return fclose(file) == 0;
+}
+
+bool writeFile(int32_t f, string_t *s) {
+  FILE *file = get_file(f);
+  return file != NULL && fputs(_str_chars(s), file) >= 0;
+}
+
+// The next line without the newline, empty at the end of the file
+string_t *readLine(int32_t f) {
+  FILE *file = get_file(f);
+  char *line = file == NULL ? NULL : read_line(file);
+  return line == NULL ? _empty_str() : _new_str(line);
+}
+
+// True if there's nothing more to read, or the file is not open
+bool endOfFile(int32_t f) {
+  FILE *file = get_file(f);
+  if (file == NULL) {
+    return true;
+  }
+  int c = fgetc(file);
+  if (c == EOF) {
+    return true;
+  }
+  ungetc(c, file);
+  return false;
+}
+
+// The whole contents of the file, empty if it can't be read
+string_t *readFile(string_t *path) {
+  FILE *file = fopen(_str_chars(path), "rb");
+  if (file == NULL) {
+    return _empty_str();
+  }
+  fseek(file, 0, SEEK_END);
+  long size = ftell(file);
+  if (size < 0) {
+    fclose(file);
+    return _empty_str();
+  }
+  fseek(file, 0, SEEK_SET);
+  char *buf = malloc(size + 1);
+  size_t n = fread(buf, 1, size, file);
+  buf[n] = 0;
+  fclose(file);
+  return _new_str(buf);
+}
+
+void printError(string_t *s) {
+  fputs(_str_chars(s), stderr);
+  fputc('\n', stderr);
+}
+
+void exitWith(int32_t code) { exit(code); }
+
+// Empty if the variable is not set
+string_t *getEnv(string_t *name) {
+  char *value = getenv(_str_chars(name));
+  return value == NULL ? _empty_str() : _copy_str(value);
+}
+
+// Arguments of the program for main(string[] args), without the program name.
+// The characters are not freed.
+array_t *_new_args(int32_t argc, char **argv) {
+  array_t *args = _new_array(argc - 1, sizeof(string_t *));
+  for (int32_t i = 1; i < argc; i++) {
+    string_t *s = _new_str(argv[i]);
+    s->is_const = true;
+    ((string_t **)args->elems)[i - 1] = s;
+  }
+  return args;
+}
@@ -0,0 +1,393 @@
+// Host of programs compiled with --target=wasm, it provides the builtin functions
+// imported by runtime.wat and runs the module:
+//
+//   node lib/runtime.js [--extern=file.js]... program.wasm [args]...
+//
+// Extern functions are taken from the exports of the given CommonJS modules.
+// They are called with JS values: numbers, BigInts for longs, booleans and
+// strings, a returned null is the empty string. In a browser createHost can be
+// used with other streams.
+'use strict';
+
+const fs = require('fs');
+const path = require('path');
+const v8 = require('v8');
+const workerThreads = require('worker_threads');
+
+// calls in tail position are compiled to return_call
+v8.setFlagsFromString('--experimental-wasm-return-call');
+
+// thrown to stop the program
+class Exit {
+  constructor(code) {
+    this.code = code;
+  }
+}
+
+// Reads bytes of a file descriptor on demand
+class Reader {
+  constructor(fd) {
+    this.fd = fd;
+    this.buf = Buffer.alloc(0);
+    this.pos = 0;
+  }
+
+  // the next byte, -1 at the end of the file
+  peek() {
+    if (this.pos == this.buf.length) {
+      const chunk = Buffer.alloc(65536);
+      let n = 0;
+      try {
+        n = fs.readSync(this.fd, chunk, 0, chunk.length, null);
+      } catch (e) {
+        n = 0;
+      }
+      this.buf = chunk.subarray(0, n);
+      this.pos = 0;
+      if (n == 0) {
+        return -1;
+      }
+    }
+    return this.buf[this.pos];
+  }
+
+  next() {
+    const c = this.peek();
+    if (c != -1) {
+      this.pos++;
+    }
+    return c;
+  }
+
+  // The next line without the newline, null at the end of the file
+  readLine() {
+    const bytes = [];
+    let c;
+    while ((c = this.next()) != -1 && c != 10) {
+      bytes.push(c);
+    }
+    if (c == -1 && bytes.length == 0) {
+      return null;
+    }
+    return Buffer.from(bytes);
+  }
+
+  // bytes of a number matching the pattern, as scanf reads them
+  readNumber(pattern) {
+    while (isSpace(this.peek())) {
+      this.next();
+    }
+    let s = '';
+    while (this.peek() != -1 && pattern.test(s + String.fromCharCode(this.peek()))) {
+      s += String.fromCharCode(this.next());
+    }
+    return s;
+  }
+}
+
+function isSpace(c) {
+  return c == 32 || (c >= 9 && c <= 13);
+}
+
+// printf("%f")
+function formatDouble(x) {
+  if (Number.isNaN(x)) {
+    const bits = new DataView(new Float64Array([x]).buffer);
+    return bits.getUint8(7) & 0x80 ? '-nan' : 'nan';
+  }
+  if (!Number.isFinite(x)) {
+    return x > 0 ? 'inf' : '-inf';
+  }
+  if (Object.is(x, -0)) {
+    return '-0.000000';
+  }
+  if (Math.abs(x) < 1e21) {
+    return x.toFixed(6);
+  }
+  return BigInt(x).toString() + '.000000';
+}
+
+// Imports of the module. The exports are needed to create strings and the
+// streams are objects with write(bytes), stdin is a Reader.
+function createHost(getExports, options) {
+  const stdout = options.stdout;
+  const stderr = options.stderr;
+  const stdin = options.stdin;
+  const files = [];
+
+  function memory() {
+    return new Uint8Array(getExports().memory.buffer);
+  }
+
+  function strBytes(s) {
+    const view = new DataView(getExports().memory.buffer);
+    const len = view.getInt32(s + 8, true);
+    return Buffer.from(memory().subarray(s + 12, s + 12 + len));
+  }
+
+  // a new string owned by the caller
+  function newStr(bytes) {
+    const s = getExports()._new_str_len(bytes.length);
+    memory().set(bytes, s + 12);
+    return s;
+  }
+
+  function stream(f) {
+    switch (f) {
+      case 0:
+        return {reader: stdin};
+      case 1:
+        return {writer: stdout};
+      case 2:
+        return {writer: stderr};
+      default:
+        return files[f] || null;
+    }
+  }
+
+  function readInt() {
+    const s = stdin.readNumber(/^[+-]?[0-9]*$/);
+    stdin.next();
+    return /[0-9]/.test(s) ? Number(BigInt.asIntN(32, BigInt(s))) : 0;
+  }
+
+  function readDouble() {
+    const s = stdin.readNumber(/^[+-]?([0-9]+\.?[0-9]*|\.[0-9]*)([eE][+-]?[0-9]*)?$/);
+    stdin.next();
+    return /[0-9]/.test(s) ? parseFloat(s) : 0;
+  }
+
+  const latte = {
+    printInt: x => stdout.write(Buffer.from(x + '\n')),
+    printDouble: x => stdout.write(Buffer.from(formatDouble(x) + '\n')),
+    printString: s => stdout.write(Buffer.concat([strBytes(s), Buffer.from('\n')])),
+    error: () => {
+      stdout.write(Buffer.from('runtime error\n'));
+      throw new Exit(1);
+    },
+    readInt: readInt,
+    readDouble: readDouble,
+    readString: () => newStr(stdin.readLine() || Buffer.alloc(0)),
+    openFile: (pathStr, mode) => {
+      let fd;
+      try {
+        const flags = strBytes(mode).toString().replace('b', '');
+        fd = fs.openSync(strBytes(pathStr).toString(), flags);
+      } catch (e) {
+        return -1;
+      }
+      for (let f = 3; f < 256; f++) {
+        if (!files[f]) {
+          files[f] = {fd: fd, reader: new Reader(fd), writer: {write: b => fs.writeSync(fd, b)}};
+          return f;
+        }
+      }
+      fs.closeSync(fd);
+      return -1;
+    },
+    closeFile: f => {
+      if (f < 3 || !files[f]) {
+        return 0;
+      }
+      const fd = files[f].fd;
+      files[f] = null;
+      try {
+        fs.closeSync(fd);
+        return 1;
+      } catch (e) {
+        return 0;
+      }
+    },
+    readLine: f => {
+      const s = stream(f);
+      const line = s && s.reader ? s.reader.readLine() : null;
+      return newStr(line || Buffer.alloc(0));
+    },
+    endOfFile: f => {
+      const s = stream(f);
+      return !s || !s.reader || s.reader.peek() == -1 ? 1 : 0;
+    },
+    writeFile: (f, str) => {
+      const s = stream(f);
+      if (!s || !s.writer) {
+        return 0;
+      }
+      try {
+        s.writer.write(strBytes(str));
+        return 1;
+      } catch (e) {
+        return 0;
+      }
+    },
+    readFile: pathStr => {
+      try {
+        return newStr(fs.readFileSync(strBytes(pathStr).toString()));
+      } catch (e) {
+        return newStr(Buffer.alloc(0));
+      }
+    },
+    printError: s => stderr.write(Buffer.concat([strBytes(s), Buffer.from('\n')])),
+    exitWith: code => {
+      throw new Exit(code);
+    },
+    getEnv: name => newStr(Buffer.from(process.env[strBytes(name).toString()] || '')),
+    argCount: () => options.args.length,
+    arg: i => newStr(Buffer.from(options.args[i])),
+  };
+
+  const flush = () => {
+    if (stdout.flush) {
+      stdout.flush();
+    }
+  };
+  return {latte: latte, extern: createExterns(options.module, options.externs, () => getExports().memory, strBytes, newStr, flush)};
+}
+
+// Extern functions are imported as name:args:result, with a letter for each type
+// (i, l, d, b, c, s or v, arrays of them in upper case), the values are converted
+// to JS and back. Arrays are passed as typed arrays over their elements, so their
+// changes are visible to the program. The buffered output is flushed before each
+// call, since foreign code can write to stdout on its own.
+function createExterns(module, externs, memory, strBytes, newStr, flush) {
+  function arrayView(arr, letter) {
+    const buffer = memory().buffer;
+    const length = new DataView(buffer).getInt32(arr, true);
+    switch (letter) {
+      case 'L':
+        return new BigInt64Array(buffer, arr + 8, length);
+      case 'D':
+        return new Float64Array(buffer, arr + 8, length);
+      default:
+        return new Int32Array(buffer, arr + 8, length);
+    }
+  }
+
+  const imports = {};
+  for (const imp of WebAssembly.Module.imports(module)) {
+    if (imp.module != 'extern') {
+      continue;
+    }
+    const [name, args, result] = imp.name.split(':');
+    const f = externs[name];
+    if (typeof f != 'function') {
+      throw new Error('Missing extern function: ' + name);
+    }
+    imports[imp.name] = (...vals) => {
+      const jsVals = vals.map((v, i) => {
+        switch (args[i]) {
+          case 's':
+            return strBytes(v).toString();
+          case 'b':
+            return v != 0;
+          case 'L':
+          case 'D':
+          case 'I':
+          case 'B':
+          case 'C':
+            return arrayView(v, args[i]);
+          default:
+            return v;
+        }
+      });
+      flush();
+      const res = f(...jsVals);
+      switch (result) {
+        case 's':
+          return newStr(Buffer.from(res == null ? '' : String(res)));
+        case 'b':
+          return res ? 1 : 0;
+        case 'l':
+          return BigInt.asIntN(64, BigInt(res));
+        case 'v':
+          return undefined;
+        case 'd':
+          return Number(res);
+        default:
+          return Number(res) | 0;
+      }
+    };
+  }
+  return imports;
+}
+
+// output is written at once, as with the buffered stdout of C
+class Output {
+  constructor(fd) {
+    this.fd = fd;
+    this.chunks = [];
+    this.size = 0;
+  }
+
+  write(bytes) {
+    this.chunks.push(bytes);
+    this.size += bytes.length;
+    if (this.size > 65536) {
+      this.flush();
+    }
+  }
+
+  flush() {
+    fs.writeSync(this.fd, Buffer.concat(this.chunks));
+    this.chunks = [];
+    this.size = 0;
+  }
+}
+
+function run(argv) {
+  let i = 0;
+  const externs = {};
+  while (i < argv.length && argv[i].startsWith('--extern=')) {
+    Object.assign(externs, require(path.resolve(argv[i].slice(9))));
+    i++;
+  }
+  if (i == argv.length) {
+    process.stderr.write('Usage: node runtime.js [--extern=file.js]... program.wasm [args]...\n');
+    return 2;
+  }
+  const module = new WebAssembly.Module(fs.readFileSync(argv[i]));
+  const stdout = new Output(1);
+  const stderr = {write: bytes => fs.writeSync(2, bytes)};
+  let instance = null;
+  const imports = createHost(() => instance.exports, {
+    module: module,
+    externs: externs,
+    args: argv.slice(i + 1),
+    stdin: new Reader(0),
+    stdout: stdout,
+    stderr: stderr,
+  });
+  instance = new WebAssembly.Instance(module, imports);
+  let code;
+  try {
+    code = instance.exports._start();
+  } catch (e) {
+    stdout.flush();
+    if (e instanceof Exit) {
+      code = e.code;
+    } else if (e instanceof WebAssembly.RuntimeError || e instanceof RangeError) {
+      // traps, e.g. division by zero or exhausted stack
+      process.stderr.write('runtime error: ' + e.message + '\n');
+      code = 1;
+    } else {
+      throw e;
+    }
+  }
+  stdout.flush();
+  return code;
+}
+
+module.exports = {createHost: createHost, Exit: Exit, Reader: Reader};
+
+// The program runs in a worker, whose stack is large enough for deep recursion
+// as in native programs.
+if (require.main === module) {
+  if (workerThreads.isMainThread) {
+    const worker = new workerThreads.Worker(__filename, {
+      argv: process.argv.slice(2),
+      resourceLimits: {stackSizeMb: 256},
+    });
+    worker.on('exit', code => process.exit(code));
+  } else {
+    process.exit(run(process.argv.slice(2)));
+  }
+}
@@ -5,6 +5,8 @@
 @.str = private unnamed_addr constant [4 x i8] c"%d\0A\00", align 1
 @.str.1 = private unnamed_addr constant [14 x i8] c"runtime error\00", align 1
 @.str.2 = private unnamed_addr constant [3 x i8] c"%d\00", align 1
+@.str.3 = private unnamed_addr constant [4 x i8] c"%f\0A\00", align 1
+@.str.4 = private unnamed_addr constant [4 x i8] c"%lf\00", align 1
 @__stdinp = external global %struct.__sFILE*, align 8
 
 ; Function Attrs: nounwind ssp uwtable
@@ -18,6 +20,15 @@ define void @printInt(i32) #0 {
 
 declare i32 @printf(i8*, ...) #1
 
+; Function Attrs: nounwind ssp uwtable
+define void @printDouble(double) #0 {
+  %2 = alloca double, align 8
+  store double %0, double* %2, align 8
+  %3 = load double, double* %2, align 8
+  %4 = call i32 (i8*, ...) @printf(i8* getelementptr inbounds ([4 x i8], [4 x i8]* @.str.3, i32 0, i32 0), double %3)
+  ret void
+}
+
 declare i32 @puts(i8*) #1
 
 ; Function Attrs: nounwind ssp uwtable
@@ -43,6 +54,15 @@ define i32 @readInt() #0 {
 
 declare i32 @scanf(i8*, ...) #1
 
+; Function Attrs: nounwind ssp uwtable
+define double @readDouble() #0 {
+  %1 = alloca double, align 8
+  %2 = call i32 (i8*, ...) @scanf(i8* getelementptr inbounds ([4 x i8], [4 x i8]* @.str.4, i32 0, i32 0), double* %1)
+  %3 = call i32 @getchar()
+  %4 = load double, double* %1, align 8
+  ret double %4
+}
+
 declare i32 @getchar() #1
 
 ; Function Attrs: nounwind ssp uwtable
@@ -292,6 +312,964 @@ end:
   ret void
 }
 
+define void @._check_overflow(i1 %overflow) {
+  br i1 %overflow, label %fail, label %ok
+
+fail:
+  call void @error()
+  unreachable
+
+ok:
+  ret void
+}
+
+; Collections. Elements are stored in i64 slots, strings as %string_t pointers.
+
+%list_t = type { i32, i32, i64*, i1 }
+%map_entry_t = type { i64, i64, %map_entry_t* }
+%map_t = type { i32, i32, %map_entry_t**, i1, i1 }
+
+@.empty_str = private unnamed_addr constant [1 x i8] zeroinitializer
+
+declare i8* @calloc(i64, i64)
+declare i8* @realloc(i8*, i64)
+declare i32 @strcmp(i8*, i8*)
+declare void @llvm.memmove.p0i8.p0i8.i64(i8*, i8*, i64, i1)
+
+define void @._retain_slot(i64 %slot, i1 %is_str) {
+  br i1 %is_str, label %retain, label %end
+
+retain:
+  %s = inttoptr i64 %slot to %string_t*
+  call void @._retain_str(%string_t* %s)
+  br label %end
+
+end:
+  ret void
+}
+
+define void @._release_slot(i64 %slot, i1 %is_str) {
+  br i1 %is_str, label %release, label %end
+
+release:
+  %s = inttoptr i64 %slot to %string_t*
+  call void @._release_str(%string_t* %s)
+  br label %end
+
+end:
+  ret void
+}
+
+define %list_t* @._list_new(i1 %is_str) {
+  %size_ptr = getelementptr %list_t, %list_t* null, i32 1
+  %size = ptrtoint %list_t* %size_ptr to i64
+  %raw = call i8* @malloc(i64 %size)
+  %l = bitcast i8* %raw to %list_t*
+  %raw_elems = call i8* @malloc(i64 32)
+  %elems = bitcast i8* %raw_elems to i64*
+  %l_1 = insertvalue %list_t undef, i32 0, 0
+  %l_2 = insertvalue %list_t %l_1, i32 4, 1
+  %l_3 = insertvalue %list_t %l_2, i64* %elems, 2
+  %l_4 = insertvalue %list_t %l_3, i1 %is_str, 3
+  store %list_t %l_4, %list_t* %l
+  ret %list_t* %l
+}
+
+; Makes room for one more element
+define void @._list_reserve(%list_t* %l) {
+  %size_ptr = getelementptr %list_t, %list_t* %l, i32 0, i32 0
+  %size = load i32, i32* %size_ptr
+  %cap_ptr = getelementptr %list_t, %list_t* %l, i32 0, i32 1
+  %cap = load i32, i32* %cap_ptr
+  %is_full = icmp eq i32 %size, %cap
+  br i1 %is_full, label %grow, label %end
+
+grow:
+  %new_cap = mul i32 %cap, 2
+  %elems_ptr = getelementptr %list_t, %list_t* %l, i32 0, i32 2
+  %elems = load i64*, i64** %elems_ptr
+  %raw_elems = bitcast i64* %elems to i8*
+  %new_cap_64 = zext i32 %new_cap to i64
+  %bytes = mul i64 %new_cap_64, 8
+  %raw_new_elems = call i8* @realloc(i8* %raw_elems, i64 %bytes)
+  %new_elems = bitcast i8* %raw_new_elems to i64*
+  store i64* %new_elems, i64** %elems_ptr
+  store i32 %new_cap, i32* %cap_ptr
+  br label %end
+
+end:
+  ret void
+}
+
+define i64* @._list_at(%list_t* %l, i32 %idx) {
+  %size_ptr = getelementptr %list_t, %list_t* %l, i32 0, i32 0
+  %size = load i32, i32* %size_ptr
+  %out_of_range = icmp uge i32 %idx, %size
+  br i1 %out_of_range, label %fail, label %ok
+
+fail:
+  call void @error()
+  unreachable
+
+ok:
+  %elems_ptr = getelementptr %list_t, %list_t* %l, i32 0, i32 2
+  %elems = load i64*, i64** %elems_ptr
+  %elem = getelementptr i64, i64* %elems, i32 %idx
+  ret i64* %elem
+}
+
+define void @._list_push(%list_t* %l, i64 %v) {
+  call void @._list_reserve(%list_t* %l)
+  %size_ptr = getelementptr %list_t, %list_t* %l, i32 0, i32 0
+  %size = load i32, i32* %size_ptr
+  %elems_ptr = getelementptr %list_t, %list_t* %l, i32 0, i32 2
+  %elems = load i64*, i64** %elems_ptr
+  %elem = getelementptr i64, i64* %elems, i32 %size
+  store i64 %v, i64* %elem
+  %new_size = add i32 %size, 1
+  store i32 %new_size, i32* %size_ptr
+  %is_str_ptr = getelementptr %list_t, %list_t* %l, i32 0, i32 3
+  %is_str = load i1, i1* %is_str_ptr
+  call void @._retain_slot(i64 %v, i1 %is_str)
+  ret void
+}
+
+; The popped element is owned by the caller
+define i64 @._list_pop(%list_t* %l) {
+  %size_ptr = getelementptr %list_t, %list_t* %l, i32 0, i32 0
+  %size = load i32, i32* %size_ptr
+  %is_empty = icmp eq i32 %size, 0
+  br i1 %is_empty, label %fail, label %ok
+
+fail:
+  call void @error()
+  unreachable
+
+ok:
+  %new_size = sub i32 %size, 1
+  %elems_ptr = getelementptr %list_t, %list_t* %l, i32 0, i32 2
+  %elems = load i64*, i64** %elems_ptr
+  %elem = getelementptr i64, i64* %elems, i32 %new_size
+  %v = load i64, i64* %elem
+  store i32 %new_size, i32* %size_ptr
+  ret i64 %v
+}
+
+define void @._list_insert(%list_t* %l, i32 %idx, i64 %v) {
+  %size_ptr = getelementptr %list_t, %list_t* %l, i32 0, i32 0
+  %size = load i32, i32* %size_ptr
+  %out_of_range = icmp ugt i32 %idx, %size
+  br i1 %out_of_range, label %fail, label %ok
+
+fail:
+  call void @error()
+  unreachable
+
+ok:
+  call void @._list_reserve(%list_t* %l)
+  %elems_ptr = getelementptr %list_t, %list_t* %l, i32 0, i32 2
+  %elems = load i64*, i64** %elems_ptr
+  %src = getelementptr i64, i64* %elems, i32 %idx
+  %dst = getelementptr i64, i64* %src, i32 1
+  %to_move = sub i32 %size, %idx
+  %to_move_64 = zext i32 %to_move to i64
+  %bytes = mul i64 %to_move_64, 8
+  %raw_src = bitcast i64* %src to i8*
+  %raw_dst = bitcast i64* %dst to i8*
+  call void @llvm.memmove.p0i8.p0i8.i64(i8* %raw_dst, i8* %raw_src, i64 %bytes, i1 false)
+  store i64 %v, i64* %src
+  %new_size = add i32 %size, 1
+  store i32 %new_size, i32* %size_ptr
+  %is_str_ptr = getelementptr %list_t, %list_t* %l, i32 0, i32 3
+  %is_str = load i1, i1* %is_str_ptr
+  call void @._retain_slot(i64 %v, i1 %is_str)
+  ret void
+}
+
+; The removed element is owned by the caller
+define i64 @._list_remove(%list_t* %l, i32 %idx) {
+  %dst = call i64* @._list_at(%list_t* %l, i32 %idx)
+  %v = load i64, i64* %dst
+  %size_ptr = getelementptr %list_t, %list_t* %l, i32 0, i32 0
+  %size = load i32, i32* %size_ptr
+  %new_size = sub i32 %size, 1
+  %src = getelementptr i64, i64* %dst, i32 1
+  %to_move = sub i32 %new_size, %idx
+  %to_move_64 = zext i32 %to_move to i64
+  %bytes = mul i64 %to_move_64, 8
+  %raw_src = bitcast i64* %src to i8*
+  %raw_dst = bitcast i64* %dst to i8*
+  call void @llvm.memmove.p0i8.p0i8.i64(i8* %raw_dst, i8* %raw_src, i64 %bytes, i1 false)
+  store i32 %new_size, i32* %size_ptr
+  ret i64 %v
+}
+
+define %map_t* @._map_new(i1 %str_keys, i1 %str_values) {
+  %size_ptr = getelementptr %map_t, %map_t* null, i32 1
+  %size = ptrtoint %map_t* %size_ptr to i64
+  %raw = call i8* @malloc(i64 %size)
+  %m = bitcast i8* %raw to %map_t*
+  %raw_buckets = call i8* @calloc(i64 16, i64 8)
+  %buckets = bitcast i8* %raw_buckets to %map_entry_t**
+  %m_1 = insertvalue %map_t undef, i32 0, 0
+  %m_2 = insertvalue %map_t %m_1, i32 16, 1
+  %m_3 = insertvalue %map_t %m_2, %map_entry_t** %buckets, 2
+  %m_4 = insertvalue %map_t %m_3, i1 %str_keys, 3
+  %m_5 = insertvalue %map_t %m_4, i1 %str_values, 4
+  store %map_t %m_5, %map_t* %m
+  ret %map_t* %m
+}
+
+define i8* @._slot_chars(i64 %slot) {
+  %s = inttoptr i64 %slot to %string_t*
+  %chars_ptr = getelementptr %string_t, %string_t* %s, i32 0, i32 1
+  %chars = load i8*, i8** %chars_ptr
+  %is_null = icmp eq i8* %chars, null
+  br i1 %is_null, label %empty, label %end
+
+empty:
+  %empty_chars = getelementptr [1 x i8], [1 x i8]* @.empty_str, i32 0, i32 0
+  ret i8* %empty_chars
+
+end:
+  ret i8* %chars
+}
+
+define %map_entry_t** @._map_bucket(%map_t* %m, i64 %key) {
+  %str_keys_ptr = getelementptr %map_t, %map_t* %m, i32 0, i32 3
+  %str_keys = load i1, i1* %str_keys_ptr
+  br i1 %str_keys, label %str_hash, label %int_hash
+
+int_hash:
+  %int_h_1 = mul i64 %key, -7046029254386353131
+  %int_h_2 = lshr i64 %int_h_1, 32
+  %int_h = xor i64 %int_h_1, %int_h_2
+  br label %end
+
+str_hash:
+  %chars = call i8* @._slot_chars(i64 %key)
+  br label %str_loop
+
+str_loop:
+  %str_h = phi i64 [-3750763034362895579, %str_hash], [%next_str_h, %str_step]
+  %idx = phi i64 [0, %str_hash], [%next_idx, %str_step]
+  %c_ptr = getelementptr i8, i8* %chars, i64 %idx
+  %c = load i8, i8* %c_ptr
+  %is_end = icmp eq i8 %c, 0
+  br i1 %is_end, label %end, label %str_step
+
+str_step:
+  %c_64 = zext i8 %c to i64
+  %str_h_xor = xor i64 %str_h, %c_64
+  %next_str_h = mul i64 %str_h_xor, 1099511628211
+  %next_idx = add i64 %idx, 1
+  br label %str_loop
+
+end:
+  %h = phi i64 [%int_h, %int_hash], [%str_h, %str_loop]
+  %buckets_cnt_ptr = getelementptr %map_t, %map_t* %m, i32 0, i32 1
+  %buckets_cnt = load i32, i32* %buckets_cnt_ptr
+  %buckets_cnt_64 = zext i32 %buckets_cnt to i64
+  %bucket_idx = urem i64 %h, %buckets_cnt_64
+  %buckets_ptr = getelementptr %map_t, %map_t* %m, i32 0, i32 2
+  %buckets = load %map_entry_t**, %map_entry_t*** %buckets_ptr
+  %bucket = getelementptr %map_entry_t*, %map_entry_t** %buckets, i64 %bucket_idx
+  ret %map_entry_t** %bucket
+}
+
+define i1 @._map_key_eq(%map_t* %m, i64 %lhs, i64 %rhs) {
+  %str_keys_ptr = getelementptr %map_t, %map_t* %m, i32 0, i32 3
+  %str_keys = load i1, i1* %str_keys_ptr
+  br i1 %str_keys, label %str_eq, label %int_eq
+
+int_eq:
+  %int_res = icmp eq i64 %lhs, %rhs
+  ret i1 %int_res
+
+str_eq:
+  %lhs_chars = call i8* @._slot_chars(i64 %lhs)
+  %rhs_chars = call i8* @._slot_chars(i64 %rhs)
+  %cmp = call i32 @strcmp(i8* %lhs_chars, i8* %rhs_chars)
+  %str_res = icmp eq i32 %cmp, 0
+  ret i1 %str_res
+}
+
+; Compares contents of two strings, used by == and switch statements
+define i1 @._str_eq(%string_t* %lhs, %string_t* %rhs) {
+  %cmp = call i32 @._str_cmp(%string_t* %lhs, %string_t* %rhs)
+  %res = icmp eq i32 %cmp, 0
+  ret i1 %res
+}
+
+; Lexicographic order of strings, negative if lhs is less than rhs
+define i32 @._str_cmp(%string_t* %lhs, %string_t* %rhs) {
+  %lhs_slot = ptrtoint %string_t* %lhs to i64
+  %rhs_slot = ptrtoint %string_t* %rhs to i64
+  %lhs_chars = call i8* @._slot_chars(i64 %lhs_slot)
+  %rhs_chars = call i8* @._slot_chars(i64 %rhs_slot)
+  %cmp = call i32 @strcmp(i8* %lhs_chars, i8* %rhs_chars)
+  ret i32 %cmp
+}
+
+; Whether both strings are the same object
+define i1 @sameString(%string_t* %lhs, %string_t* %rhs) {
+  %res = icmp eq %string_t* %lhs, %rhs
+  ret i1 %res
+}
+
+; Returns the address of the link pointing to the entry with the given key,
+; or to null if there is no such entry
+define %map_entry_t** @._map_find(%map_t* %m, i64 %key) {
+  %bucket = call %map_entry_t** @._map_bucket(%map_t* %m, i64 %key)
+  br label %loop
+
+loop:
+  %link = phi %map_entry_t** [%bucket, %0], [%next_link, %next]
+  %entry = load %map_entry_t*, %map_entry_t** %link
+  %is_null = icmp eq %map_entry_t* %entry, null
+  br i1 %is_null, label %end, label %check
+
+check:
+  %entry_key_ptr = getelementptr %map_entry_t, %map_entry_t* %entry, i32 0, i32 0
+  %entry_key = load i64, i64* %entry_key_ptr
+  %found = call i1 @._map_key_eq(%map_t* %m, i64 %entry_key, i64 %key)
+  br i1 %found, label %end, label %next
+
+next:
+  %next_link = getelementptr %map_entry_t, %map_entry_t* %entry, i32 0, i32 2
+  br label %loop
+
+end:
+  ret %map_entry_t** %link
+}
+
+define void @._map_rehash(%map_t* %m) {
+  %buckets_cnt_ptr = getelementptr %map_t, %map_t* %m, i32 0, i32 1
+  %buckets_cnt = load i32, i32* %buckets_cnt_ptr
+  %buckets_ptr = getelementptr %map_t, %map_t* %m, i32 0, i32 2
+  %buckets = load %map_entry_t**, %map_entry_t*** %buckets_ptr
+  %new_buckets_cnt = mul i32 %buckets_cnt, 2
+  %new_buckets_cnt_64 = zext i32 %new_buckets_cnt to i64
+  %raw_new_buckets = call i8* @calloc(i64 %new_buckets_cnt_64, i64 8)
+  %new_buckets = bitcast i8* %raw_new_buckets to %map_entry_t**
+  store i32 %new_buckets_cnt, i32* %buckets_cnt_ptr
+  store %map_entry_t** %new_buckets, %map_entry_t*** %buckets_ptr
+  br label %buckets_loop
+
+buckets_loop:
+  %idx = phi i32 [0, %0], [%next_idx, %bucket_end]
+  %is_done = icmp eq i32 %idx, %buckets_cnt
+  br i1 %is_done, label %end, label %bucket_begin
+
+bucket_begin:
+  %bucket = getelementptr %map_entry_t*, %map_entry_t** %buckets, i32 %idx
+  %first = load %map_entry_t*, %map_entry_t** %bucket
+  br label %entries_loop
+
+entries_loop:
+  %entry = phi %map_entry_t* [%first, %bucket_begin], [%next_entry, %move_entry]
+  %is_null = icmp eq %map_entry_t* %entry, null
+  br i1 %is_null, label %bucket_end, label %move_entry
+
+move_entry:
+  %next_ptr = getelementptr %map_entry_t, %map_entry_t* %entry, i32 0, i32 2
+  %next_entry = load %map_entry_t*, %map_entry_t** %next_ptr
+  %key_ptr = getelementptr %map_entry_t, %map_entry_t* %entry, i32 0, i32 0
+  %key = load i64, i64* %key_ptr
+  %new_bucket = call %map_entry_t** @._map_bucket(%map_t* %m, i64 %key)
+  %new_next = load %map_entry_t*, %map_entry_t** %new_bucket
+  store %map_entry_t* %new_next, %map_entry_t** %next_ptr
+  store %map_entry_t* %entry, %map_entry_t** %new_bucket
+  br label %entries_loop
+
+bucket_end:
+  %next_idx = add i32 %idx, 1
+  br label %buckets_loop
+
+end:
+  %raw_buckets = bitcast %map_entry_t** %buckets to i8*
+  call void @free(i8* %raw_buckets)
+  ret void
+}
+
+define void @._map_put(%map_t* %m, i64 %key, i64 %value) {
+  %str_keys_ptr = getelementptr %map_t, %map_t* %m, i32 0, i32 3
+  %str_keys = load i1, i1* %str_keys_ptr
+  %str_values_ptr = getelementptr %map_t, %map_t* %m, i32 0, i32 4
+  %str_values = load i1, i1* %str_values_ptr
+  call void @._retain_slot(i64 %value, i1 %str_values)
+  %link = call %map_entry_t** @._map_find(%map_t* %m, i64 %key)
+  %entry = load %map_entry_t*, %map_entry_t** %link
+  %is_null = icmp eq %map_entry_t* %entry, null
+  br i1 %is_null, label %new_entry, label %update
+
+update:
+  %value_ptr = getelementptr %map_entry_t, %map_entry_t* %entry, i32 0, i32 1
+  %old_value = load i64, i64* %value_ptr
+  store i64 %value, i64* %value_ptr
+  call void @._release_slot(i64 %old_value, i1 %str_values)
+  ret void
+
+new_entry:
+  call void @._retain_slot(i64 %key, i1 %str_keys)
+  %entry_size_ptr = getelementptr %map_entry_t, %map_entry_t* null, i32 1
+  %entry_size = ptrtoint %map_entry_t* %entry_size_ptr to i64
+  %raw_entry = call i8* @malloc(i64 %entry_size)
+  %new = bitcast i8* %raw_entry to %map_entry_t*
+  %new_1 = insertvalue %map_entry_t undef, i64 %key, 0
+  %new_2 = insertvalue %map_entry_t %new_1, i64 %value, 1
+  %new_3 = insertvalue %map_entry_t %new_2, %map_entry_t* null, 2
+  store %map_entry_t %new_3, %map_entry_t* %new
+  store %map_entry_t* %new, %map_entry_t** %link
+
+  %size_ptr = getelementptr %map_t, %map_t* %m, i32 0, i32 0
+  %size = load i32, i32* %size_ptr
+  %new_size = add i32 %size, 1
+  store i32 %new_size, i32* %size_ptr
+  %buckets_cnt_ptr = getelementptr %map_t, %map_t* %m, i32 0, i32 1
+  %buckets_cnt = load i32, i32* %buckets_cnt_ptr
+  %too_full = icmp ugt i32 %new_size, %buckets_cnt
+  br i1 %too_full, label %rehash, label %end
+
+rehash:
+  call void @._map_rehash(%map_t* %m)
+  br label %end
+
+end:
+  ret void
+}
+
+; The returned value is borrowed from the map
+define i64 @._map_get(%map_t* %m, i64 %key) {
+  %link = call %map_entry_t** @._map_find(%map_t* %m, i64 %key)
+  %entry = load %map_entry_t*, %map_entry_t** %link
+  %is_null = icmp eq %map_entry_t* %entry, null
+  br i1 %is_null, label %fail, label %ok
+
+fail:
+  call void @error()
+  unreachable
+
+ok:
+  %value_ptr = getelementptr %map_entry_t, %map_entry_t* %entry, i32 0, i32 1
+  %value = load i64, i64* %value_ptr
+  ret i64 %value
+}
+
+define i1 @._map_contains(%map_t* %m, i64 %key) {
+  %link = call %map_entry_t** @._map_find(%map_t* %m, i64 %key)
+  %entry = load %map_entry_t*, %map_entry_t** %link
+  %res = icmp ne %map_entry_t* %entry, null
+  ret i1 %res
+}
+
+define void @._map_remove(%map_t* %m, i64 %key) {
+  %link = call %map_entry_t** @._map_find(%map_t* %m, i64 %key)
+  %entry = load %map_entry_t*, %map_entry_t** %link
+  %is_null = icmp eq %map_entry_t* %entry, null
+  br i1 %is_null, label %end, label %remove
+
+remove:
+  %entry_val = load %map_entry_t, %map_entry_t* %entry
+  %entry_key = extractvalue %map_entry_t %entry_val, 0
+  %entry_value = extractvalue %map_entry_t %entry_val, 1
+  %next = extractvalue %map_entry_t %entry_val, 2
+  store %map_entry_t* %next, %map_entry_t** %link
+  %raw_entry = bitcast %map_entry_t* %entry to i8*
+  call void @free(i8* %raw_entry)
+
+  %str_keys_ptr = getelementptr %map_t, %map_t* %m, i32 0, i32 3
+  %str_keys = load i1, i1* %str_keys_ptr
+  call void @._release_slot(i64 %entry_key, i1 %str_keys)
+  %str_values_ptr = getelementptr %map_t, %map_t* %m, i32 0, i32 4
+  %str_values = load i1, i1* %str_values_ptr
+  call void @._release_slot(i64 %entry_value, i1 %str_values)
+
+  %size_ptr = getelementptr %map_t, %map_t* %m, i32 0, i32 0
+  %size = load i32, i32* %size_ptr
+  %new_size = sub i32 %size, 1
+  store i32 %new_size, i32* %size_ptr
+  br label %end
+
+end:
+  ret void
+}
+
+define %list_t* @._map_keys(%map_t* %m) {
+  %str_keys_ptr = getelementptr %map_t, %map_t* %m, i32 0, i32 3
+  %str_keys = load i1, i1* %str_keys_ptr
+  %keys = call %list_t* @._list_new(i1 %str_keys)
+  %buckets_cnt_ptr = getelementptr %map_t, %map_t* %m, i32 0, i32 1
+  %buckets_cnt = load i32, i32* %buckets_cnt_ptr
+  %buckets_ptr = getelementptr %map_t, %map_t* %m, i32 0, i32 2
+  %buckets = load %map_entry_t**, %map_entry_t*** %buckets_ptr
+  br label %buckets_loop
+
+buckets_loop:
+  %idx = phi i32 [0, %0], [%next_idx, %bucket_end]
+  %is_done = icmp eq i32 %idx, %buckets_cnt
+  br i1 %is_done, label %end, label %bucket_begin
+
+bucket_begin:
+  %bucket = getelementptr %map_entry_t*, %map_entry_t** %buckets, i32 %idx
+  %first = load %map_entry_t*, %map_entry_t** %bucket
+  br label %entries_loop
+
+entries_loop:
+  %entry = phi %map_entry_t* [%first, %bucket_begin], [%next_entry, %add_key]
+  %is_null = icmp eq %map_entry_t* %entry, null
+  br i1 %is_null, label %bucket_end, label %add_key
+
+add_key:
+  %entry_val = load %map_entry_t, %map_entry_t* %entry
+  %key = extractvalue %map_entry_t %entry_val, 0
+  call void @._list_push(%list_t* %keys, i64 %key)
+  %next_entry = extractvalue %map_entry_t %entry_val, 2
+  br label %entries_loop
+
+bucket_end:
+  %next_idx = add i32 %idx, 1
+  br label %buckets_loop
+
+end:
+  ret %list_t* %keys
+}
+
+; String builders: length, capacity and characters with the terminating zero
+%builder_t = type { i32, i32, i8* }
+
+define %builder_t* @._builder_new() {
+  %size_ptr = getelementptr %builder_t, %builder_t* null, i32 1
+  %size = ptrtoint %builder_t* %size_ptr to i64
+  %raw = call i8* @malloc(i64 %size)
+  %b = bitcast i8* %raw to %builder_t*
+  %chars = call i8* @malloc(i64 16)
+  store i8 0, i8* %chars
+  %b_1 = insertvalue %builder_t undef, i32 0, 0
+  %b_2 = insertvalue %builder_t %b_1, i32 16, 1
+  %b_3 = insertvalue %builder_t %b_2, i8* %chars, 2
+  store %builder_t %b_3, %builder_t* %b
+  ret %builder_t* %b
+}
+
+; Appends n characters, the capacity is doubled until they fit
+define void @._builder_append_chars(%builder_t* %b, i8* %src, i32 %n) {
+  %len_ptr = getelementptr %builder_t, %builder_t* %b, i32 0, i32 0
+  %len = load i32, i32* %len_ptr
+  %cap_ptr = getelementptr %builder_t, %builder_t* %b, i32 0, i32 1
+  %cap = load i32, i32* %cap_ptr
+  %chars_ptr = getelementptr %builder_t, %builder_t* %b, i32 0, i32 2
+  %new_len = add i32 %len, %n
+  %needed = add i32 %new_len, 1
+  br label %grow_loop
+
+grow_loop:
+  %new_cap = phi i32 [%cap, %0], [%doubled, %grow_step]
+  %fits = icmp sle i32 %needed, %new_cap
+  br i1 %fits, label %grown, label %grow_step
+
+grow_step:
+  %doubled = mul i32 %new_cap, 2
+  br label %grow_loop
+
+grown:
+  %is_same = icmp eq i32 %new_cap, %cap
+  br i1 %is_same, label %copy, label %realloc
+
+realloc:
+  %old_chars = load i8*, i8** %chars_ptr
+  %new_cap_64 = zext i32 %new_cap to i64
+  %new_chars = call i8* @realloc(i8* %old_chars, i64 %new_cap_64)
+  store i8* %new_chars, i8** %chars_ptr
+  store i32 %new_cap, i32* %cap_ptr
+  br label %copy
+
+copy:
+  %chars = load i8*, i8** %chars_ptr
+  %dst = getelementptr i8, i8* %chars, i32 %len
+  %n_64 = zext i32 %n to i64
+  call void @llvm.memmove.p0i8.p0i8.i64(i8* %dst, i8* %src, i64 %n_64, i1 false)
+  %end = getelementptr i8, i8* %chars, i32 %new_len
+  store i8 0, i8* %end
+  store i32 %new_len, i32* %len_ptr
+  ret void
+}
+
+define void @._builder_append(%builder_t* %b, %string_t* %s) {
+  %slot = ptrtoint %string_t* %s to i64
+  %src = call i8* @._slot_chars(i64 %slot)
+  %n_64 = call i64 @strlen(i8* %src)
+  %n = trunc i64 %n_64 to i32
+  call void @._builder_append_chars(%builder_t* %b, i8* %src, i32 %n)
+  ret void
+}
+
+define void @._builder_append_char(%builder_t* %b, i8 %c) {
+  %src = alloca i8
+  store i8 %c, i8* %src
+  call void @._builder_append_chars(%builder_t* %b, i8* %src, i32 1)
+  ret void
+}
+
+; Copies the characters to a new string owned by the caller
+define %string_t* @._builder_to_string(%builder_t* %b) {
+  %len_ptr = getelementptr %builder_t, %builder_t* %b, i32 0, i32 0
+  %len = load i32, i32* %len_ptr
+  %chars_ptr = getelementptr %builder_t, %builder_t* %b, i32 0, i32 2
+  %chars = load i8*, i8** %chars_ptr
+  %size = add i32 %len, 1
+  %size_64 = zext i32 %size to i64
+  %res_chars = call i8* @malloc(i64 %size_64)
+  call void @llvm.memmove.p0i8.p0i8.i64(i8* %res_chars, i8* %chars, i64 %size_64, i1 false)
+  %res = call %string_t* @._alloc_str()
+  call void @._retain_str(%string_t* %res)
+  %res_chars_ptr = getelementptr %string_t, %string_t* %res, i32 0, i32 1
+  store i8* %res_chars, i8** %res_chars_ptr
+  ret %string_t* %res
+}
+
+; Concatenates n strings at once, the result is owned by the caller
+define %string_t* @._concatenate_n(%string_t** %parts, i32 %n) {
+  %b = call %builder_t* @._builder_new()
+  br label %loop
+
+loop:
+  %idx = phi i32 [0, %0], [%next_idx, %append]
+  %is_done = icmp eq i32 %idx, %n
+  br i1 %is_done, label %end, label %append
+
+append:
+  %part_ptr = getelementptr %string_t*, %string_t** %parts, i32 %idx
+  %part = load %string_t*, %string_t** %part_ptr
+  call void @._builder_append(%builder_t* %b, %string_t* %part)
+  %next_idx = add i32 %idx, 1
+  br label %loop
+
+end:
+  %res = call %string_t* @._builder_to_string(%builder_t* %b)
+  %chars_ptr = getelementptr %builder_t, %builder_t* %b, i32 0, i32 2
+  %chars = load i8*, i8** %chars_ptr
+  call void @free(i8* %chars)
+  %raw = bitcast %builder_t* %b to i8*
+  call void @free(i8* %raw)
+  ret %string_t* %res
+}
+
+; File and process I/O, files are identified by handles: 0, 1 and 2 are the
+; standard streams, other handles are indices in @._files. Failures are
+; reported through the returned values.
+@__stdoutp = external global %struct.__sFILE*, align 8
+@__stderrp = external global %struct.__sFILE*, align 8
+@._files = global [256 x %struct.__sFILE*] zeroinitializer
+@.read_mode = private unnamed_addr constant [3 x i8] c"rb\00"
+
+; Takes the ownership of the characters
+define %string_t* @._new_str(i8* %chars) {
+  %s = call %string_t* @._alloc_str()
+  call void @._retain_str(%string_t* %s)
+  %chars_ptr = getelementptr %string_t, %string_t* %s, i32 0, i32 1
+  store i8* %chars, i8** %chars_ptr
+  ret %string_t* %s
+}
+
+; null is the empty string, e.g. returned by an extern function
+define %string_t* @._copy_str(i8* %ptr) {
+  %is_null = icmp eq i8* %ptr, null
+  %empty = getelementptr [1 x i8], [1 x i8]* @.empty_str, i32 0, i32 0
+  %chars = select i1 %is_null, i8* %empty, i8* %ptr
+  %len = call i64 @strlen(i8* %chars)
+  %size = add i64 %len, 1
+  %copy = call i8* @malloc(i64 %size)
+  call void @llvm.memmove.p0i8.p0i8.i64(i8* %copy, i8* %chars, i64 %size, i1 false)
+  %s = call %string_t* @._new_str(i8* %copy)
+  ret %string_t* %s
+}
+
+define %string_t* @._empty_str() {
+  %empty = getelementptr [1 x i8], [1 x i8]* @.empty_str, i32 0, i32 0
+  %s = call %string_t* @._copy_str(i8* %empty)
+  ret %string_t* %s
+}
+
+define i8* @._str_chars(%string_t* %s) {
+  %slot = ptrtoint %string_t* %s to i64
+  %chars = call i8* @._slot_chars(i64 %slot)
+  ret i8* %chars
+}
+
+; The stream of a handle, null if it's not open
+define %struct.__sFILE* @._get_file(i32 %f) {
+  switch i32 %f, label %opened [ i32 0, label %std_in
+                                 i32 1, label %std_out
+                                 i32 2, label %std_err ]
+
+std_in:
+  %in = load %struct.__sFILE*, %struct.__sFILE** @__stdinp
+  ret %struct.__sFILE* %in
+
+std_out:
+  %out = load %struct.__sFILE*, %struct.__sFILE** @__stdoutp
+  ret %struct.__sFILE* %out
+
+std_err:
+  %err = load %struct.__sFILE*, %struct.__sFILE** @__stderrp
+  ret %struct.__sFILE* %err
+
+opened:
+  %in_range = icmp ult i32 %f, 256
+  br i1 %in_range, label %lookup, label %invalid
+
+lookup:
+  %file_ptr = getelementptr [256 x %struct.__sFILE*], [256 x %struct.__sFILE*]* @._files, i32 0, i32 %f
+  %file = load %struct.__sFILE*, %struct.__sFILE** %file_ptr
+  ret %struct.__sFILE* %file
+
+invalid:
+  ret %struct.__sFILE* null
+}
+
+; Returns the handle of the file, -1 if it can't be opened
+define i32 @openFile(%string_t* %path, %string_t* %mode) {
+  %path_chars = call i8* @._str_chars(%string_t* %path)
+  %mode_chars = call i8* @._str_chars(%string_t* %mode)
+  %file = call %struct.__sFILE* @fopen(i8* %path_chars, i8* %mode_chars)
+  %is_null = icmp eq %struct.__sFILE* %file, null
+  br i1 %is_null, label %fail, label %loop
+
+loop:
+  %idx = phi i32 [3, %0], [%next_idx, %next]
+  %is_full = icmp eq i32 %idx, 256
+  br i1 %is_full, label %full, label %check
+
+check:
+  %file_ptr = getelementptr [256 x %struct.__sFILE*], [256 x %struct.__sFILE*]* @._files, i32 0, i32 %idx
+  %current = load %struct.__sFILE*, %struct.__sFILE** %file_ptr
+  %is_free = icmp eq %struct.__sFILE* %current, null
+  br i1 %is_free, label %found, label %next
+
+next:
+  %next_idx = add i32 %idx, 1
+  br label %loop
+
+found:
+  store %struct.__sFILE* %file, %struct.__sFILE** %file_ptr
+  ret i32 %idx
+
+full:
+  call i32 @fclose(%struct.__sFILE* %file)
+  br label %fail
+
+fail:
+  ret i32 -1
+}
+
+; The standard streams are not closed
+define i1 @closeFile(i32 %f) {
+  %is_std = icmp ult i32 %f, 3
+  br i1 %is_std, label %fail, label %get
+
+get:
+  %file = call %struct.__sFILE* @._get_file(i32 %f)
+  %is_null = icmp eq %struct.__sFILE* %file, null
+  br i1 %is_null, label %fail, label %close
+
+close:
+  %file_ptr = getelementptr [256 x %struct.__sFILE*], [256 x %struct.__sFILE*]* @._files, i32 0, i32 %f
+  store %struct.__sFILE* null, %struct.__sFILE** %file_ptr
+  %res = call i32 @fclose(%struct.__sFILE* %file)
+  %ok = icmp eq i32 %res, 0
+  ret i1 %ok
+
+fail:
+  ret i1 false
+}
+
+define i1 @writeFile(i32 %f, %string_t* %s) {
+  %file = call %struct.__sFILE* @._get_file(i32 %f)
+  %is_null = icmp eq %struct.__sFILE* %file, null
+  br i1 %is_null, label %fail, label %write
+
+write:
+  %chars = call i8* @._str_chars(%string_t* %s)
+  %res = call i32 @fputs(i8* %chars, %struct.__sFILE* %file)
+  %ok = icmp sge i32 %res, 0
+  ret i1 %ok
+
+fail:
+  ret i1 false
+}
+
+; The next line without the newline, empty at the end of the file
+define %string_t* @readLine(i32 %f) {
+  %buf_ptr = alloca i8*
+  %size_ptr = alloca i64
+  store i8* null, i8** %buf_ptr
+  store i64 0, i64* %size_ptr
+  %file = call %struct.__sFILE* @._get_file(i32 %f)
+  %is_null = icmp eq %struct.__sFILE* %file, null
+  br i1 %is_null, label %empty, label %read
+
+read:
+  %len = call i64 @getline(i8** %buf_ptr, i64* %size_ptr, %struct.__sFILE* %file)
+  %failed = icmp slt i64 %len, 1
+  br i1 %failed, label %empty, label %strip
+
+strip:
+  %buf = load i8*, i8** %buf_ptr
+  %last_idx = sub i64 %len, 1
+  %last_ptr = getelementptr i8, i8* %buf, i64 %last_idx
+  %last = load i8, i8* %last_ptr
+  %is_newline = icmp eq i8 %last, 10
+  br i1 %is_newline, label %cut, label %done
+
+cut:
+  store i8 0, i8* %last_ptr
+  br label %done
+
+done:
+  %res = call %string_t* @._new_str(i8* %buf)
+  ret %string_t* %res
+
+empty:
+  %unused = load i8*, i8** %buf_ptr
+  call void @free(i8* %unused)
+  %empty_res = call %string_t* @._empty_str()
+  ret %string_t* %empty_res
+}
+
+; True if there's nothing more to read, or the file is not open
+define i1 @endOfFile(i32 %f) {
+  %file = call %struct.__sFILE* @._get_file(i32 %f)
+  %is_null = icmp eq %struct.__sFILE* %file, null
+  br i1 %is_null, label %end, label %peek
+
+peek:
+  %c = call i32 @fgetc(%struct.__sFILE* %file)
+  %is_eof = icmp eq i32 %c, -1
+  br i1 %is_eof, label %end, label %unget
+
+unget:
+  call i32 @ungetc(i32 %c, %struct.__sFILE* %file)
+  ret i1 false
+
+end:
+  ret i1 true
+}
+
+; The whole contents of the file, empty if it can't be read
+define %string_t* @readFile(%string_t* %path) {
+  %path_chars = call i8* @._str_chars(%string_t* %path)
+  %mode = getelementptr [3 x i8], [3 x i8]* @.read_mode, i32 0, i32 0
+  %file = call %struct.__sFILE* @fopen(i8* %path_chars, i8* %mode)
+  %is_null = icmp eq %struct.__sFILE* %file, null
+  br i1 %is_null, label %empty, label %measure
+
+measure:
+  call i32 @fseek(%struct.__sFILE* %file, i64 0, i32 2)
+  %size = call i64 @ftell(%struct.__sFILE* %file)
+  %is_invalid = icmp slt i64 %size, 0
+  br i1 %is_invalid, label %close_empty, label %read
+
+read:
+  call i32 @fseek(%struct.__sFILE* %file, i64 0, i32 0)
+  %buf_size = add i64 %size, 1
+  %buf = call i8* @malloc(i64 %buf_size)
+  %n = call i64 @fread(i8* %buf, i64 1, i64 %size, %struct.__sFILE* %file)
+  %end = getelementptr i8, i8* %buf, i64 %n
+  store i8 0, i8* %end
+  call i32 @fclose(%struct.__sFILE* %file)
+  %res = call %string_t* @._new_str(i8* %buf)
+  ret %string_t* %res
+
+close_empty:
+  call i32 @fclose(%struct.__sFILE* %file)
+  br label %empty
+
+empty:
+  %empty_res = call %string_t* @._empty_str()
+  ret %string_t* %empty_res
+}
+
+define void @printError(%string_t* %s) {
+  %chars = call i8* @._str_chars(%string_t* %s)
+  %err = load %struct.__sFILE*, %struct.__sFILE** @__stderrp
+  call i32 @fputs(i8* %chars, %struct.__sFILE* %err)
+  call i32 @fputc(i32 10, %struct.__sFILE* %err)
+  ret void
+}
+
+define void @exitWith(i32 %code) {
+  call void @exit(i32 %code)
+  unreachable
+}
+
+; Empty if the variable is not set
+define %string_t* @getEnv(%string_t* %name) {
+  %name_chars = call i8* @._str_chars(%string_t* %name)
+  %value = call i8* @getenv(i8* %name_chars)
+  %is_null = icmp eq i8* %value, null
+  br i1 %is_null, label %empty, label %copy
+
+copy:
+  %res = call %string_t* @._copy_str(i8* %value)
+  ret %string_t* %res
+
+empty:
+  %empty_res = call %string_t* @._empty_str()
+  ret %string_t* %empty_res
+}
+
+; Arguments of the program for main(string[] args), without the program name.
+; The characters are not freed.
+define { i32, %string_t** }* @._new_args(i32 %argc, i8** %argv) {
+  %n = sub i32 %argc, 1
+  %arr_size_ptr = getelementptr { i32, %string_t** }, { i32, %string_t** }* null, i32 1
+  %arr_size = ptrtoint { i32, %string_t** }* %arr_size_ptr to i64
+  %arr_raw = call i8* @malloc(i64 %arr_size)
+  %arr = bitcast i8* %arr_raw to { i32, %string_t** }*
+  %elem_size_ptr = getelementptr %string_t*, %string_t** null, i32 1
+  %elem_size = ptrtoint %string_t** %elem_size_ptr to i64
+  %n_64 = sext i32 %n to i64
+  %elems_size = mul i64 %n_64, %elem_size
+  %elems_raw = call i8* @malloc(i64 %elems_size)
+  %elems = bitcast i8* %elems_raw to %string_t**
+  %arr_1 = insertvalue { i32, %string_t** } undef, i32 %n, 0
+  %arr_2 = insertvalue { i32, %string_t** } %arr_1, %string_t** %elems, 1
+  store { i32, %string_t** } %arr_2, { i32, %string_t** }* %arr
+  br label %loop
+
+loop:
+  %idx = phi i32 [0, %0], [%next_idx, %body]
+  %is_done = icmp eq i32 %idx, %n
+  br i1 %is_done, label %end, label %body
+
+body:
+  %arg_idx = add i32 %idx, 1
+  %arg_ptr = getelementptr i8*, i8** %argv, i32 %arg_idx
+  %arg = load i8*, i8** %arg_ptr
+  %s = call %string_t* @._new_str(i8* %arg)
+  %is_const_ptr = getelementptr %string_t, %string_t* %s, i32 0, i32 2
+  store i1 true, i1* %is_const_ptr
+  %elem_ptr = getelementptr %string_t*, %string_t** %elems, i32 %idx
+  store %string_t* %s, %string_t** %elem_ptr
+  %next_idx = add i32 %idx, 1
+  br label %loop
+
+end:
+  ret { i32, %string_t** }* %arr
+}
+
+declare %struct.__sFILE* @fopen(i8*, i8*)
+declare i32 @fclose(%struct.__sFILE*)
+declare i32 @fputs(i8*, %struct.__sFILE*)
+declare i32 @fputc(i32, %struct.__sFILE*)
+declare i32 @fgetc(%struct.__sFILE*)
+declare i32 @ungetc(i32, %struct.__sFILE*)
+declare i64 @fread(i8*, i64, i64, %struct.__sFILE*)
+declare i32 @fseek(%struct.__sFILE*, i64, i32)
+declare i64 @ftell(%struct.__sFILE*)
+declare i8* @getenv(i8*)
+
 ; Function Attrs: nounwind
 declare i8* @__strcpy_chk(i8*, i8*, i64) #3
 
@@ -0,0 +1,1420 @@
+;; Runtime of programs compiled with --target=wasm, the counterpart of runtime.ll.
+;; These fields are spliced into the generated module, which defines $_data_end,
+;; the end of its static data. Input and output are provided by the host, see
+;; runtime.js.
+;;
+;; Memory layout, pointers are i32 and null is 0:
+;;   string:  ref_count, is_const, length, characters from offset 12
+;;   array:   length, elements from offset 8
+;;   list:    size, capacity, elems, is_str; elements are stored in i64 slots
+;;   map:     size, buckets_cnt, buckets, str_keys, str_values
+;;   entry:   key (i64), value (i64), next at offset 16
+;;   builder: length, capacity, chars
+;; Addresses below 1024 are reserved for the runtime.
+
+(import "latte" "printInt" (func $printInt (param i32)))
+(import "latte" "printDouble" (func $printDouble (param f64)))
+(import "latte" "printString" (func $printString (param i32)))
+(import "latte" "error" (func $error))
+(import "latte" "readInt" (func $readInt (result i32)))
+(import "latte" "readDouble" (func $readDouble (result f64)))
+(import "latte" "readString" (func $readString (result i32)))
+(import "latte" "openFile" (func $openFile (param i32 i32) (result i32)))
+(import "latte" "closeFile" (func $closeFile (param i32) (result i32)))
+(import "latte" "readLine" (func $readLine (param i32) (result i32)))
+(import "latte" "endOfFile" (func $endOfFile (param i32) (result i32)))
+(import "latte" "writeFile" (func $writeFile (param i32 i32) (result i32)))
+(import "latte" "readFile" (func $readFile (param i32) (result i32)))
+(import "latte" "printError" (func $printError (param i32)))
+(import "latte" "exitWith" (func $exitWith (param i32)))
+(import "latte" "getEnv" (func $getEnv (param i32) (result i32)))
+(import "latte" "argCount" (func $_arg_count (result i32)))
+(import "latte" "arg" (func $_arg (param i32) (result i32)))
+
+(memory 2)
+(export "memory" (memory 0))
+(export "_new_str_len" (func $_new_str_len))
+
+;; memory allocation
+
+(global $heap_top (mut i32) (i32.const 0))
+
+;; Blocks have an 8-byte header with their size class, a block of class k has
+;; 2^(k+4) bytes. Freed blocks are kept in lists of their classes, the heads of
+;; the lists are at 64. The memory is zeroed.
+(func $_malloc (param $size i32) (result i32)
+  (local $class i32) (local $block_size i32) (local $ptr i32) (local $pages i32)
+  local.get $size
+  i32.const 1073741824
+  i32.gt_u
+  if
+    call $error
+  end
+  i32.const 16
+  local.set $block_size
+  block $found
+    loop $next
+      local.get $block_size
+      local.get $size
+      i32.const 8
+      i32.add
+      i32.ge_u
+      br_if $found
+      local.get $block_size
+      i32.const 1
+      i32.shl
+      local.set $block_size
+      local.get $class
+      i32.const 1
+      i32.add
+      local.set $class
+      br $next
+    end
+  end
+  local.get $class
+  i32.const 2
+  i32.shl
+  i32.load offset=64
+  local.tee $ptr
+  if
+    ;; reuse a freed block
+    local.get $class
+    i32.const 2
+    i32.shl
+    local.get $ptr
+    i32.load
+    i32.store offset=64
+  else
+    ;; the heap starts after the static data
+    global.get $heap_top
+    i32.eqz
+    if
+      global.get $_data_end
+      i32.const 7
+      i32.add
+      i32.const -8
+      i32.and
+      global.set $heap_top
+    end
+    global.get $heap_top
+    i32.const 8
+    i32.add
+    local.set $ptr
+    global.get $heap_top
+    local.get $block_size
+    i32.add
+    global.set $heap_top
+    global.get $heap_top
+    memory.size
+    i32.const 16
+    i32.shl
+    i32.gt_u
+    if
+      ;; at least doubles the memory, so that it grows rarely
+      global.get $heap_top
+      memory.size
+      i32.const 16
+      i32.shl
+      i32.sub
+      i32.const 65535
+      i32.add
+      i32.const 16
+      i32.shr_u
+      local.tee $pages
+      memory.size
+      local.get $pages
+      memory.size
+      i32.gt_u
+      select
+      memory.grow
+      i32.const -1
+      i32.eq
+      if
+        call $error
+      end
+    end
+    local.get $ptr
+    i32.const 8
+    i32.sub
+    local.get $class
+    i32.store
+  end
+  local.get $ptr
+  i32.const 0
+  local.get $block_size
+  i32.const 8
+  i32.sub
+  memory.fill
+  local.get $ptr
+)
+
+(func $_free (param $ptr i32)
+  (local $head i32)
+  local.get $ptr
+  i32.eqz
+  if
+    return
+  end
+  local.get $ptr
+  i32.const 8
+  i32.sub
+  i32.load
+  i32.const 2
+  i32.shl
+  i32.const 64
+  i32.add
+  local.set $head
+  local.get $ptr
+  local.get $head
+  i32.load
+  i32.store
+  local.get $head
+  local.get $ptr
+  i32.store
+)
+
+(func $_realloc (param $ptr i32) (param $size i32) (result i32)
+  (local $new i32) (local $old_size i32)
+  local.get $size
+  call $_malloc
+  local.set $new
+  i32.const 16
+  local.get $ptr
+  i32.const 8
+  i32.sub
+  i32.load
+  i32.shl
+  i32.const 8
+  i32.sub
+  local.set $old_size
+  local.get $new
+  local.get $ptr
+  local.get $old_size
+  local.get $size
+  local.get $old_size
+  local.get $size
+  i32.lt_u
+  select
+  memory.copy
+  local.get $ptr
+  call $_free
+  local.get $new
+)
+
+;; strings
+
+;; A string with uninitialized characters, owned by the caller
+(func $_new_str_len (param $len i32) (result i32)
+  (local $s i32)
+  local.get $len
+  i32.const 12
+  i32.add
+  call $_malloc
+  local.tee $s
+  i32.const 1
+  i32.store
+  local.get $s
+  local.get $len
+  i32.store offset=8
+  local.get $s
+)
+
+(func $_alloc_str (result i32)
+  (local $s i32)
+  i32.const 0
+  call $_new_str_len
+  local.tee $s
+  i32.const 0
+  i32.store
+  local.get $s
+)
+
+(func $_retain_str (param $s i32)
+  local.get $s
+  local.get $s
+  i32.load
+  i32.const 1
+  i32.add
+  i32.store
+)
+
+;; Constant strings are in the static data and they are never freed
+(func $_release_str (param $s i32)
+  (local $ref_count i32)
+  local.get $s
+  local.get $s
+  i32.load
+  i32.const 1
+  i32.sub
+  local.tee $ref_count
+  i32.store
+  local.get $ref_count
+  i32.eqz
+  if
+    local.get $s
+    i32.load offset=4
+    i32.eqz
+    if
+      local.get $s
+      call $_free
+    end
+  end
+)
+
+;; all elements are the same empty string
+(func $_init_str_arr (param $arr i32)
+  (local $empty i32) (local $i i32)
+  local.get $arr
+  i32.load
+  i32.const 0
+  i32.le_s
+  if
+    return
+  end
+  call $_alloc_str
+  local.tee $empty
+  local.get $arr
+  i32.load
+  i32.store
+  block $done
+    loop $next
+      local.get $i
+      local.get $arr
+      i32.load
+      i32.ge_s
+      br_if $done
+      local.get $arr
+      local.get $i
+      i32.const 2
+      i32.shl
+      i32.add
+      local.get $empty
+      i32.store offset=8
+      local.get $i
+      i32.const 1
+      i32.add
+      local.set $i
+      br $next
+    end
+  end
+)
+
+(func $_concatenate (param $lhs i32) (param $rhs i32) (result i32)
+  (local $lhs_len i32) (local $s i32)
+  local.get $lhs
+  i32.load offset=8
+  local.tee $lhs_len
+  local.get $rhs
+  i32.load offset=8
+  i32.add
+  call $_new_str_len
+  local.tee $s
+  i32.const 12
+  i32.add
+  local.get $lhs
+  i32.const 12
+  i32.add
+  local.get $lhs_len
+  memory.copy
+  local.get $s
+  i32.const 12
+  i32.add
+  local.get $lhs_len
+  i32.add
+  local.get $rhs
+  i32.const 12
+  i32.add
+  local.get $rhs
+  i32.load offset=8
+  memory.copy
+  local.get $s
+)
+
+;; Concatenates n strings at once, parts is an array of n pointers
+(func $_concatenate_n (param $parts i32) (param $n i32) (result i32)
+  (local $len i32) (local $i i32) (local $s i32) (local $dst i32) (local $part i32)
+  block $counted
+    loop $count
+      local.get $i
+      local.get $n
+      i32.ge_s
+      br_if $counted
+      local.get $len
+      local.get $parts
+      local.get $i
+      i32.const 2
+      i32.shl
+      i32.add
+      i32.load
+      i32.load offset=8
+      i32.add
+      local.set $len
+      local.get $i
+      i32.const 1
+      i32.add
+      local.set $i
+      br $count
+    end
+  end
+  local.get $len
+  call $_new_str_len
+  local.tee $s
+  i32.const 12
+  i32.add
+  local.set $dst
+  i32.const 0
+  local.set $i
+  block $copied
+    loop $copy
+      local.get $i
+      local.get $n
+      i32.ge_s
+      br_if $copied
+      local.get $parts
+      local.get $i
+      i32.const 2
+      i32.shl
+      i32.add
+      i32.load
+      local.set $part
+      local.get $dst
+      local.get $part
+      i32.const 12
+      i32.add
+      local.get $part
+      i32.load offset=8
+      memory.copy
+      local.get $dst
+      local.get $part
+      i32.load offset=8
+      i32.add
+      local.set $dst
+      local.get $i
+      i32.const 1
+      i32.add
+      local.set $i
+      br $copy
+    end
+  end
+  local.get $s
+)
+
+;; Lexicographic order of the characters, negative if lhs is less than rhs
+(func $_str_cmp (param $lhs i32) (param $rhs i32) (result i32)
+  (local $n i32) (local $i i32) (local $diff i32)
+  local.get $lhs
+  i32.load offset=8
+  local.get $rhs
+  i32.load offset=8
+  local.get $lhs
+  i32.load offset=8
+  local.get $rhs
+  i32.load offset=8
+  i32.lt_u
+  select
+  local.set $n
+  block $done
+    loop $next
+      local.get $i
+      local.get $n
+      i32.ge_u
+      br_if $done
+      local.get $lhs
+      local.get $i
+      i32.add
+      i32.load8_u offset=12
+      local.get $rhs
+      local.get $i
+      i32.add
+      i32.load8_u offset=12
+      i32.sub
+      local.tee $diff
+      if
+        local.get $diff
+        return
+      end
+      local.get $i
+      i32.const 1
+      i32.add
+      local.set $i
+      br $next
+    end
+  end
+  local.get $lhs
+  i32.load offset=8
+  local.get $rhs
+  i32.load offset=8
+  i32.sub
+)
+
+;; Compares contents of two strings, used by == and switch statements
+(func $_str_eq (param $lhs i32) (param $rhs i32) (result i32)
+  local.get $lhs
+  local.get $rhs
+  call $_str_cmp
+  i32.eqz
+)
+
+;; Whether both strings are the same object
+(func $sameString (param $lhs i32) (param $rhs i32) (result i32)
+  local.get $lhs
+  local.get $rhs
+  i32.eq
+)
+
+;; arithmetic, error() on overflow
+
+(func $_check_overflow (param $overflow i32)
+  local.get $overflow
+  if
+    call $error
+  end
+)
+
+;; the result must be the same after truncation to 32 bits
+(func $_check_i32 (param $res i64) (result i32)
+  local.get $res
+  local.get $res
+  i32.wrap_i64
+  i64.extend_i32_s
+  i64.ne
+  call $_check_overflow
+  local.get $res
+  i32.wrap_i64
+)
+
+(func $_checked_add_i32 (param $lhs i32) (param $rhs i32) (result i32)
+  local.get $lhs
+  i64.extend_i32_s
+  local.get $rhs
+  i64.extend_i32_s
+  i64.add
+  call $_check_i32
+)
+
+(func $_checked_sub_i32 (param $lhs i32) (param $rhs i32) (result i32)
+  local.get $lhs
+  i64.extend_i32_s
+  local.get $rhs
+  i64.extend_i32_s
+  i64.sub
+  call $_check_i32
+)
+
+(func $_checked_mul_i32 (param $lhs i32) (param $rhs i32) (result i32)
+  local.get $lhs
+  i64.extend_i32_s
+  local.get $rhs
+  i64.extend_i32_s
+  i64.mul
+  call $_check_i32
+)
+
+;; overflow if both operands have a different sign than the result
+(func $_checked_add_i64 (param $lhs i64) (param $rhs i64) (result i64)
+  (local $res i64)
+  local.get $lhs
+  local.get $rhs
+  i64.add
+  local.set $res
+  local.get $lhs
+  local.get $res
+  i64.xor
+  local.get $rhs
+  local.get $res
+  i64.xor
+  i64.and
+  i64.const 0
+  i64.lt_s
+  call $_check_overflow
+  local.get $res
+)
+
+;; overflow if the operands have different signs and the result has the sign of rhs
+(func $_checked_sub_i64 (param $lhs i64) (param $rhs i64) (result i64)
+  (local $res i64)
+  local.get $lhs
+  local.get $rhs
+  i64.sub
+  local.set $res
+  local.get $lhs
+  local.get $rhs
+  i64.xor
+  local.get $lhs
+  local.get $res
+  i64.xor
+  i64.and
+  i64.const 0
+  i64.lt_s
+  call $_check_overflow
+  local.get $res
+)
+
+(func $_checked_mul_i64 (param $lhs i64) (param $rhs i64) (result i64)
+  (local $res i64)
+  local.get $lhs
+  i64.eqz
+  local.get $rhs
+  i64.eqz
+  i32.or
+  if
+    i64.const 0
+    return
+  end
+  local.get $lhs
+  i64.const -1
+  i64.eq
+  local.get $rhs
+  i64.const -9223372036854775808
+  i64.eq
+  i32.and
+  local.get $rhs
+  i64.const -1
+  i64.eq
+  local.get $lhs
+  i64.const -9223372036854775808
+  i64.eq
+  i32.and
+  i32.or
+  call $_check_overflow
+  local.get $lhs
+  local.get $rhs
+  i64.mul
+  local.tee $res
+  local.get $rhs
+  i64.div_s
+  local.get $lhs
+  i64.ne
+  call $_check_overflow
+  local.get $res
+)
+
+;; MIN / -1 traps in div_s, it wraps around to MIN instead, as in LLVM IR
+(func $_div_i32 (param $lhs i32) (param $rhs i32) (result i32)
+  local.get $rhs
+  i32.const -1
+  i32.eq
+  if (result i32)
+    i32.const 0
+    local.get $lhs
+    i32.sub
+  else
+    local.get $lhs
+    local.get $rhs
+    i32.div_s
+  end
+)
+
+(func $_div_i64 (param $lhs i64) (param $rhs i64) (result i64)
+  local.get $rhs
+  i64.const -1
+  i64.eq
+  if (result i64)
+    i64.const 0
+    local.get $lhs
+    i64.sub
+  else
+    local.get $lhs
+    local.get $rhs
+    i64.div_s
+  end
+)
+
+;; MIN / -1 and MIN % -1 overflow
+(func $_checked_div_i32 (param $lhs i32) (param $rhs i32) (result i32)
+  local.get $lhs
+  i32.const -2147483648
+  i32.eq
+  local.get $rhs
+  i32.const -1
+  i32.eq
+  i32.and
+  call $_check_overflow
+  local.get $lhs
+  local.get $rhs
+  i32.div_s
+)
+
+(func $_checked_mod_i32 (param $lhs i32) (param $rhs i32) (result i32)
+  local.get $lhs
+  i32.const -2147483648
+  i32.eq
+  local.get $rhs
+  i32.const -1
+  i32.eq
+  i32.and
+  call $_check_overflow
+  local.get $lhs
+  local.get $rhs
+  i32.rem_s
+)
+
+(func $_checked_div_i64 (param $lhs i64) (param $rhs i64) (result i64)
+  local.get $lhs
+  i64.const -9223372036854775808
+  i64.eq
+  local.get $rhs
+  i64.const -1
+  i64.eq
+  i32.and
+  call $_check_overflow
+  local.get $lhs
+  local.get $rhs
+  i64.div_s
+)
+
+(func $_checked_mod_i64 (param $lhs i64) (param $rhs i64) (result i64)
+  local.get $lhs
+  i64.const -9223372036854775808
+  i64.eq
+  local.get $rhs
+  i64.const -1
+  i64.eq
+  i32.and
+  call $_check_overflow
+  local.get $lhs
+  local.get $rhs
+  i64.rem_s
+)
+
+;; objects, initialized by the generated code
+
+(func $_new_object (param $size i32) (result i32)
+  local.get $size
+  call $_malloc
+)
+
+;; arrays, the elements are zeroed
+
+(func $_new_array (param $length i32) (param $elem_size i32) (result i32)
+  (local $arr i32)
+  local.get $length
+  i32.const 0
+  i32.lt_s
+  if
+    call $error
+  end
+  local.get $length
+  local.get $elem_size
+  i32.mul
+  i32.const 8
+  i32.add
+  call $_malloc
+  local.tee $arr
+  local.get $length
+  i32.store
+  local.get $arr
+)
+
+;; Arguments of the program for main(string[] args), without the program name
+(func $_new_args (result i32)
+  (local $args i32) (local $i i32)
+  call $_arg_count
+  i32.const 4
+  call $_new_array
+  local.set $args
+  block $done
+    loop $next
+      local.get $i
+      local.get $args
+      i32.load
+      i32.ge_s
+      br_if $done
+      local.get $args
+      local.get $i
+      i32.const 2
+      i32.shl
+      i32.add
+      local.get $i
+      call $_arg
+      i32.store offset=8
+      local.get $i
+      i32.const 1
+      i32.add
+      local.set $i
+      br $next
+    end
+  end
+  local.get $args
+)
+
+;; slots of lists and maps holding strings
+
+(func $retain_slot (param $slot i64) (param $is_str i32)
+  local.get $is_str
+  if
+    local.get $slot
+    i32.wrap_i64
+    call $_retain_str
+  end
+)
+
+(func $release_slot (param $slot i64) (param $is_str i32)
+  local.get $is_str
+  if
+    local.get $slot
+    i32.wrap_i64
+    call $_release_str
+  end
+)
+
+;; lists
+
+(func $_list_new (param $is_str i32) (result i32)
+  (local $l i32)
+  i32.const 16
+  call $_malloc
+  local.tee $l
+  i32.const 4
+  i32.store offset=4
+  local.get $l
+  i32.const 32
+  call $_malloc
+  i32.store offset=8
+  local.get $l
+  local.get $is_str
+  i32.store offset=12
+  local.get $l
+)
+
+;; Makes room for one more element
+(func $list_reserve (param $l i32)
+  local.get $l
+  i32.load
+  local.get $l
+  i32.load offset=4
+  i32.eq
+  if
+    local.get $l
+    local.get $l
+    i32.load offset=4
+    i32.const 1
+    i32.shl
+    i32.store offset=4
+    local.get $l
+    local.get $l
+    i32.load offset=8
+    local.get $l
+    i32.load offset=4
+    i32.const 3
+    i32.shl
+    call $_realloc
+    i32.store offset=8
+  end
+)
+
+;; address of the element
+(func $_list_at (param $l i32) (param $idx i32) (result i32)
+  local.get $idx
+  local.get $l
+  i32.load
+  i32.ge_u
+  if
+    call $error
+  end
+  local.get $l
+  i32.load offset=8
+  local.get $idx
+  i32.const 3
+  i32.shl
+  i32.add
+)
+
+(func $_list_push (param $l i32) (param $v i64)
+  local.get $l
+  call $list_reserve
+  local.get $l
+  i32.load offset=8
+  local.get $l
+  i32.load
+  i32.const 3
+  i32.shl
+  i32.add
+  local.get $v
+  i64.store
+  local.get $l
+  local.get $l
+  i32.load
+  i32.const 1
+  i32.add
+  i32.store
+  local.get $v
+  local.get $l
+  i32.load offset=12
+  call $retain_slot
+)
+
+;; The popped element is owned by the caller
+(func $_list_pop (param $l i32) (result i64)
+  local.get $l
+  i32.load
+  i32.eqz
+  if
+    call $error
+  end
+  local.get $l
+  local.get $l
+  i32.load
+  i32.const 1
+  i32.sub
+  i32.store
+  local.get $l
+  i32.load offset=8
+  local.get $l
+  i32.load
+  i32.const 3
+  i32.shl
+  i32.add
+  i64.load
+)
+
+(func $_list_insert (param $l i32) (param $idx i32) (param $v i64)
+  (local $src i32)
+  local.get $idx
+  local.get $l
+  i32.load
+  i32.gt_u
+  if
+    call $error
+  end
+  local.get $l
+  call $list_reserve
+  local.get $l
+  i32.load offset=8
+  local.get $idx
+  i32.const 3
+  i32.shl
+  i32.add
+  local.tee $src
+  i32.const 8
+  i32.add
+  local.get $src
+  local.get $l
+  i32.load
+  local.get $idx
+  i32.sub
+  i32.const 3
+  i32.shl
+  memory.copy
+  local.get $src
+  local.get $v
+  i64.store
+  local.get $l
+  local.get $l
+  i32.load
+  i32.const 1
+  i32.add
+  i32.store
+  local.get $v
+  local.get $l
+  i32.load offset=12
+  call $retain_slot
+)
+
+;; The removed element is owned by the caller
+(func $_list_remove (param $l i32) (param $idx i32) (result i64)
+  (local $dst i32) (local $v i64)
+  local.get $l
+  local.get $idx
+  call $_list_at
+  local.tee $dst
+  i64.load
+  local.set $v
+  local.get $l
+  local.get $l
+  i32.load
+  i32.const 1
+  i32.sub
+  i32.store
+  local.get $dst
+  local.get $dst
+  i32.const 8
+  i32.add
+  local.get $l
+  i32.load
+  local.get $idx
+  i32.sub
+  i32.const 3
+  i32.shl
+  memory.copy
+  local.get $v
+)
+
+;; maps, separate chaining
+
+(func $_map_new (param $str_keys i32) (param $str_values i32) (result i32)
+  (local $m i32)
+  i32.const 20
+  call $_malloc
+  local.tee $m
+  i32.const 16
+  i32.store offset=4
+  local.get $m
+  i32.const 64
+  call $_malloc
+  i32.store offset=8
+  local.get $m
+  local.get $str_keys
+  i32.store offset=12
+  local.get $m
+  local.get $str_values
+  i32.store offset=16
+  local.get $m
+)
+
+;; address of the bucket of the key, the hashes are the same as in runtime.ll
+(func $map_bucket (param $m i32) (param $key i64) (result i32)
+  (local $h i64) (local $s i32) (local $i i32)
+  local.get $m
+  i32.load offset=12
+  if
+    i64.const 14695981039347156037
+    local.set $h
+    local.get $key
+    i32.wrap_i64
+    local.set $s
+    block $done
+      loop $next
+        local.get $i
+        local.get $s
+        i32.load offset=8
+        i32.ge_u
+        br_if $done
+        local.get $h
+        local.get $s
+        local.get $i
+        i32.add
+        i32.load8_u offset=12
+        i64.extend_i32_u
+        i64.xor
+        i64.const 1099511628211
+        i64.mul
+        local.set $h
+        local.get $i
+        i32.const 1
+        i32.add
+        local.set $i
+        br $next
+      end
+    end
+  else
+    local.get $key
+    i64.const 11400714819323198485
+    i64.mul
+    local.tee $h
+    local.get $h
+    i64.const 32
+    i64.shr_u
+    i64.xor
+    local.set $h
+  end
+  local.get $m
+  i32.load offset=8
+  local.get $h
+  local.get $m
+  i32.load offset=4
+  i64.extend_i32_u
+  i64.rem_u
+  i32.wrap_i64
+  i32.const 2
+  i32.shl
+  i32.add
+)
+
+(func $map_key_eq (param $m i32) (param $lhs i64) (param $rhs i64) (result i32)
+  local.get $m
+  i32.load offset=12
+  if
+    local.get $lhs
+    i32.wrap_i64
+    local.get $rhs
+    i32.wrap_i64
+    call $_str_eq
+    return
+  end
+  local.get $lhs
+  local.get $rhs
+  i64.eq
+)
+
+;; Returns the address of the link pointing to the entry with the given key,
+;; or to null if there is no such entry
+(func $map_find (param $m i32) (param $key i64) (result i32)
+  (local $link i32) (local $entry i32)
+  local.get $m
+  local.get $key
+  call $map_bucket
+  local.set $link
+  block $done
+    loop $next
+      local.get $link
+      i32.load
+      local.tee $entry
+      i32.eqz
+      br_if $done
+      local.get $m
+      local.get $entry
+      i64.load
+      local.get $key
+      call $map_key_eq
+      br_if $done
+      local.get $entry
+      i32.const 16
+      i32.add
+      local.set $link
+      br $next
+    end
+  end
+  local.get $link
+)
+
+(func $map_rehash (param $m i32)
+  (local $buckets_cnt i32) (local $buckets i32) (local $i i32) (local $entry i32)
+  (local $next i32) (local $bucket i32)
+  local.get $m
+  i32.load offset=4
+  local.set $buckets_cnt
+  local.get $m
+  i32.load offset=8
+  local.set $buckets
+  local.get $m
+  local.get $buckets_cnt
+  i32.const 1
+  i32.shl
+  i32.store offset=4
+  local.get $m
+  local.get $buckets_cnt
+  i32.const 3
+  i32.shl
+  call $_malloc
+  i32.store offset=8
+  block $done
+    loop $next_bucket
+      local.get $i
+      local.get $buckets_cnt
+      i32.ge_s
+      br_if $done
+      local.get $buckets
+      local.get $i
+      i32.const 2
+      i32.shl
+      i32.add
+      i32.load
+      local.set $entry
+      block $moved
+        loop $next_entry
+          local.get $entry
+          i32.eqz
+          br_if $moved
+          local.get $entry
+          i32.load offset=16
+          local.set $next
+          local.get $m
+          local.get $entry
+          i64.load
+          call $map_bucket
+          local.set $bucket
+          local.get $entry
+          local.get $bucket
+          i32.load
+          i32.store offset=16
+          local.get $bucket
+          local.get $entry
+          i32.store
+          local.get $next
+          local.set $entry
+          br $next_entry
+        end
+      end
+      local.get $i
+      i32.const 1
+      i32.add
+      local.set $i
+      br $next_bucket
+    end
+  end
+  local.get $buckets
+  call $_free
+)
+
+(func $_map_put (param $m i32) (param $key i64) (param $value i64)
+  (local $link i32) (local $entry i32) (local $old_value i64)
+  local.get $value
+  local.get $m
+  i32.load offset=16
+  call $retain_slot
+  local.get $m
+  local.get $key
+  call $map_find
+  local.tee $link
+  i32.load
+  local.tee $entry
+  if
+    local.get $entry
+    i64.load offset=8
+    local.set $old_value
+    local.get $entry
+    local.get $value
+    i64.store offset=8
+    local.get $old_value
+    local.get $m
+    i32.load offset=16
+    call $release_slot
+    return
+  end
+  local.get $key
+  local.get $m
+  i32.load offset=12
+  call $retain_slot
+  i32.const 24
+  call $_malloc
+  local.tee $entry
+  local.get $key
+  i64.store
+  local.get $entry
+  local.get $value
+  i64.store offset=8
+  local.get $link
+  local.get $entry
+  i32.store
+  local.get $m
+  local.get $m
+  i32.load
+  i32.const 1
+  i32.add
+  i32.store
+  local.get $m
+  i32.load
+  local.get $m
+  i32.load offset=4
+  i32.gt_u
+  if
+    local.get $m
+    call $map_rehash
+  end
+)
+
+;; The returned value is borrowed from the map
+(func $_map_get (param $m i32) (param $key i64) (result i64)
+  (local $entry i32)
+  local.get $m
+  local.get $key
+  call $map_find
+  i32.load
+  local.tee $entry
+  i32.eqz
+  if
+    call $error
+  end
+  local.get $entry
+  i64.load offset=8
+)
+
+(func $_map_contains (param $m i32) (param $key i64) (result i32)
+  local.get $m
+  local.get $key
+  call $map_find
+  i32.load
+  i32.const 0
+  i32.ne
+)
+
+(func $_map_remove (param $m i32) (param $key i64)
+  (local $link i32) (local $entry i32)
+  local.get $m
+  local.get $key
+  call $map_find
+  local.tee $link
+  i32.load
+  local.tee $entry
+  i32.eqz
+  if
+    return
+  end
+  local.get $link
+  local.get $entry
+  i32.load offset=16
+  i32.store
+  local.get $entry
+  i64.load
+  local.get $m
+  i32.load offset=12
+  call $release_slot
+  local.get $entry
+  i64.load offset=8
+  local.get $m
+  i32.load offset=16
+  call $release_slot
+  local.get $entry
+  call $_free
+  local.get $m
+  local.get $m
+  i32.load
+  i32.const 1
+  i32.sub
+  i32.store
+)
+
+(func $_map_keys (param $m i32) (result i32)
+  (local $keys i32) (local $i i32) (local $entry i32)
+  local.get $m
+  i32.load offset=12
+  call $_list_new
+  local.set $keys
+  block $done
+    loop $next_bucket
+      local.get $i
+      local.get $m
+      i32.load offset=4
+      i32.ge_s
+      br_if $done
+      local.get $m
+      i32.load offset=8
+      local.get $i
+      i32.const 2
+      i32.shl
+      i32.add
+      i32.load
+      local.set $entry
+      block $bucket_done
+        loop $next_entry
+          local.get $entry
+          i32.eqz
+          br_if $bucket_done
+          local.get $keys
+          local.get $entry
+          i64.load
+          call $_list_push
+          local.get $entry
+          i32.load offset=16
+          local.set $entry
+          br $next_entry
+        end
+      end
+      local.get $i
+      i32.const 1
+      i32.add
+      local.set $i
+      br $next_bucket
+    end
+  end
+  local.get $keys
+)
+
+;; string builders
+
+(func $_builder_new (result i32)
+  (local $b i32)
+  i32.const 12
+  call $_malloc
+  local.tee $b
+  i32.const 16
+  i32.store offset=4
+  local.get $b
+  i32.const 16
+  call $_malloc
+  i32.store offset=8
+  local.get $b
+)
+
+;; Makes room for n more characters, the capacity is doubled until they fit
+(func $builder_reserve (param $b i32) (param $n i32)
+  (local $capacity i32)
+  local.get $b
+  i32.load offset=4
+  local.set $capacity
+  block $done
+    loop $next
+      local.get $b
+      i32.load
+      local.get $n
+      i32.add
+      i32.const 1
+      i32.add
+      local.get $capacity
+      i32.le_s
+      br_if $done
+      local.get $capacity
+      i32.const 1
+      i32.shl
+      local.set $capacity
+      br $next
+    end
+  end
+  local.get $capacity
+  local.get $b
+  i32.load offset=4
+  i32.ne
+  if
+    local.get $b
+    local.get $b
+    i32.load offset=8
+    local.get $capacity
+    call $_realloc
+    i32.store offset=8
+    local.get $b
+    local.get $capacity
+    i32.store offset=4
+  end
+)
+
+(func $_builder_append (param $b i32) (param $s i32)
+  local.get $b
+  local.get $s
+  i32.load offset=8
+  call $builder_reserve
+  local.get $b
+  i32.load offset=8
+  local.get $b
+  i32.load
+  i32.add
+  local.get $s
+  i32.const 12
+  i32.add
+  local.get $s
+  i32.load offset=8
+  memory.copy
+  local.get $b
+  local.get $b
+  i32.load
+  local.get $s
+  i32.load offset=8
+  i32.add
+  i32.store
+)
+
+(func $_builder_append_char (param $b i32) (param $c i32)
+  local.get $b
+  i32.const 1
+  call $builder_reserve
+  local.get $b
+  i32.load offset=8
+  local.get $b
+  i32.load
+  i32.add
+  local.get $c
+  i32.store8
+  local.get $b
+  local.get $b
+  i32.load
+  i32.const 1
+  i32.add
+  i32.store
+)
+
+;; Copies the characters to a new string owned by the caller
+(func $_builder_to_string (param $b i32) (result i32)
+  (local $s i32)
+  local.get $b
+  i32.load
+  call $_new_str_len
+  local.tee $s
+  i32.const 12
+  i32.add
+  local.get $b
+  i32.load offset=8
+  local.get $b
+  i32.load
+  memory.copy
+  local.get $s
+)
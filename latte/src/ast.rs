@@ -5,6 +5,23 @@ pub struct Program(pub Vec<Def>);
 pub enum Def {
     DClass(Class),
     DFunc(Func),
+    DGlobal(Global),
+    DEnum(Enum),
+    DExtern(BuiltinFunc), // implemented outside of the program, e.g. in C
+}
+
+#[derive(Debug, Clone)]
+pub struct Global {
+    pub t: Type,
+    pub decls: Vec<VarDecl>,
+    pub is_const: bool,
+    pub access: Access,
+}
+
+#[derive(Debug, Clone)]
+pub struct Enum {
+    pub name: Ident,
+    pub variants: Vec<Ident>,
 }
 
 #[derive(Debug, Clone)]
@@ -13,6 +30,8 @@ pub struct Class {
     pub superclass: Option<Ident>,
     pub vars: Vec<Var>,
     pub methods: Vec<Func>,
+    pub static_vars: Vec<Global>,
+    pub static_methods: Vec<Func>,
 }
 
 #[derive(Debug, Clone)]
@@ -21,6 +40,8 @@ pub struct Func {
     pub args: Vec<Var>,
     pub ret_type: Type,
     pub body: Vec<Stmt>,
+    pub access: Access,
+    pub line: i32,
 }
 
 impl Func {
@@ -47,6 +68,15 @@ impl BuiltinFunc {
 pub struct Var {
     pub t: Type,
     pub ident: Ident,
+    pub access: Access,
+}
+
+// ordered from the most to the least restrictive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Access {
+    Private,
+    Protected,
+    Public,
 }
 
 impl Var {
@@ -56,7 +86,22 @@ impl Var {
 }
 
 #[derive(Debug, Clone)]
-pub enum Stmt {
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub line: i32, // in the source, 0 for statements added by optimizations
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind, line: i32) -> Stmt {
+        Stmt {
+            kind,
+            line,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum StmtKind {
     SEmpty,
     SBlock(Vec<Stmt>),
     SDecl(Type, Vec<VarDecl>),
@@ -70,6 +115,13 @@ pub enum Stmt {
     SIfElse(Expr, Box<Stmt>, Box<Stmt>),
     SWhile(Expr, Box<Stmt>),
     SFor(Type, Ident, Expr, Box<Stmt>),
+    SSwitch(Expr, Vec<Case>, Option<Vec<Stmt>> /* default */),
+}
+
+#[derive(Debug, Clone)]
+pub struct Case {
+    pub labels: Vec<Expr>,
+    pub body: Vec<Stmt>,
 }
 
 #[derive(Debug, Clone)]
@@ -78,6 +130,15 @@ pub enum VarDecl {
     NoInit(Type, Ident),
 }
 
+impl VarDecl {
+    pub fn get_ident(&self) -> &Ident {
+        match *self {
+            VarDecl::Init(_, ref ident, _) |
+            VarDecl::NoInit(_, ref ident) => ident,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Expr {
     EVar(FieldGet),
@@ -85,14 +146,21 @@ pub enum Expr {
     ECall(FieldGet, Vec<Expr>),
     ENeg(Box<Expr>),
     ENot(Box<Expr>),
+    EBitNot(Box<Expr>),
     EBinOp(Box<Expr>, Operator, Box<Expr>),
     ENew(Type),
-    ENewArray(Type, Box<Expr>),
+    ENewArray(Type, Vec<Expr>), // element type, sizes of subsequent dimensions
+    EArrayLit(Vec<Expr>),
+    ECast(Type, Box<Expr>),
+    ECond(Box<Expr>, Box<Expr>, Box<Expr>), // cond ? <expr> : <expr>
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Lit {
     LInt(i32),
+    LLong(i64),
+    LDouble(f64),
+    LChar(u8),
     LTrue,
     LFalse,
     LString(String),
@@ -114,6 +182,11 @@ pub enum Operator {
     OpNEq,
     OpAnd,
     OpOr,
+    OpBitAnd,
+    OpBitOr,
+    OpBitXor,
+    OpShl,
+    OpShr,
 }
 
 #[derive(Debug, Clone)]
@@ -129,11 +202,18 @@ pub struct Ident(pub String);
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Type {
     TInt,
+    TLong,
+    TDouble,
+    TChar,
     TString,
+    TStringBuilder,
     TBool,
     TVoid,
     TFunc(Vec<Type>, Box<Type>),
     TArray(Box<Type>),
+    TList(Box<Type>),
+    TMap(Box<Type> /* key */, Box<Type> /* value */),
     TObject(Ident /* class name */),
+    TEnum(Ident),
     TNull,
 }
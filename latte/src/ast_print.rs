@@ -47,10 +47,49 @@ impl Display for Def {
         match *self {
             Def::DFunc(ref func) => func.print(indent, dst),
             Def::DClass(ref class) => class.print(indent, dst),
+            Def::DGlobal(ref global) => global.print(indent, dst),
+            Def::DEnum(ref e) => e.print(indent, dst),
+            Def::DExtern(ref f) => {
+                writeln!(dst,
+                         "{}extern {} {}({});",
+                         indent,
+                         f.ret_type,
+                         f.ident,
+                         print_vec(&f.args))
+                    .expect(FERR)
+            }
         }
     }
 }
 
+impl fmt::Display for Global {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.print0(f);
+        Ok(())
+    }
+}
+
+impl Display for Global {
+    fn print(&self, indent: &String, dst: &mut dyn fmt::Write) {
+        let modifier = match self.is_const {
+            true => "const ".to_string(),
+            false => String::new(),
+        };
+        writeln!(dst, "{}{}{} {};", indent, modifier, self.t, print_vec(&self.decls)).expect(FERR);
+    }
+}
+
+impl Display for Enum {
+    fn print(&self, indent: &String, dst: &mut dyn fmt::Write) {
+        writeln!(dst,
+                 "{}enum {} {{ {} }}",
+                 indent,
+                 self.name,
+                 print_vec(&self.variants))
+            .expect(FERR);
+    }
+}
+
 impl Display for Class {
     fn print(&self, indent: &String, dst: &mut fmt::Write) {
         let inner_indent = Self::next_indent(indent);
@@ -59,23 +98,61 @@ impl Display for Class {
             None => format!(""),
         };
         writeln!(dst, "{}class {} {}{}", indent, self.name, extends, '{').expect(FERR);
+        for var in &self.static_vars {
+            write!(dst, "{}{}static ", &inner_indent, print_access(var.access)).expect(FERR);
+            var.print(&String::new(), dst);
+        }
         for var in &self.vars {
-            writeln!(dst, "{}{};", &inner_indent, var).expect(FERR);
+            writeln!(dst, "{}{}{};", &inner_indent, print_access(var.access), var).expect(FERR);
         }
-        if !self.vars.is_empty() && !self.methods.is_empty() {
+        let has_vars = !self.vars.is_empty() || !self.static_vars.is_empty();
+        let has_methods = !self.methods.is_empty() || !self.static_methods.is_empty();
+        if has_vars && has_methods {
             writeln!(dst, "").expect(FERR);
         }
-        self.methods.print(&inner_indent, dst);
+        for method in &self.static_methods {
+            let modifier = format!("{}static ", print_access(method.access));
+            method.print_with_modifier(&modifier, &inner_indent, dst);
+        }
+        for method in &self.methods {
+            method.print_with_modifier(&print_access(method.access), &inner_indent, dst);
+        }
         writeln!(dst, "{}{}", indent, '}').expect(FERR);
     }
 }
 
+// public is the default, so it is omitted
+fn print_access(access: Access) -> String {
+    match access {
+        Access::Public => String::new(),
+        _ => format!("{} ", access),
+    }
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match *self {
+            Access::Private => "private",
+            Access::Protected => "protected",
+            Access::Public => "public",
+        };
+        write!(f, "{}", s)
+    }
+}
+
 impl Display for Func {
     fn print(&self, indent: &String, dst: &mut fmt::Write) {
+        self.print_with_modifier("", indent, dst);
+    }
+}
+
+impl Func {
+    fn print_with_modifier(&self, modifier: &str, indent: &String, dst: &mut dyn fmt::Write) {
         let inner_indent = Self::next_indent(indent);
         writeln!(dst,
-                 "{}{} {}({}) {}",
+                 "{}{}{} {}({}) {}",
                  indent,
+                 modifier,
                  self.ret_type,
                  self.ident,
                  print_vec(&self.args),
@@ -97,59 +174,75 @@ impl fmt::Display for Stmt {
 impl Display for Stmt {
     fn print(&self, indent: &String, dst: &mut fmt::Write) {
         let inner_indent = Self::next_indent(indent);
-        match *self {
-            Stmt::SEmpty => {}
-            Stmt::SBlock(ref stmts) => {
+        match self.kind {
+            StmtKind::SEmpty => {}
+            StmtKind::SBlock(ref stmts) => {
                 writeln!(dst, "{}{}", indent, '{').expect(FERR);
                 stmts.print(&inner_indent, dst);
                 writeln!(dst, "{}{}", indent, '}').expect(FERR);
             }
-            Stmt::SDecl(ref t, ref inits) => {
+            StmtKind::SDecl(ref t, ref inits) => {
                 writeln!(dst, "{}{} {};", indent, t, print_vec(inits)).expect(FERR)
             }
-            Stmt::SAssign(ref i, ref e) => writeln!(dst, "{}{} = {};", indent, i, e).expect(FERR),
-            Stmt::SInc(ref i) => writeln!(dst, "{}{}++;", indent, i).expect(FERR),
-            Stmt::SDec(ref i) => writeln!(dst, "{}{}--;", indent, i).expect(FERR),
-            Stmt::SReturnE(ref e) => writeln!(dst, "{}return {};", indent, e).expect(FERR),
-            Stmt::SReturn => writeln!(dst, "{}return;", indent).expect(FERR),
-            Stmt::SExpr(ref e) => writeln!(dst, "{}{};", indent, e).expect(FERR),
-            Stmt::SIf(ref cond, ref stmt) => {
+            StmtKind::SAssign(ref i, ref e) => {
+                writeln!(dst, "{}{} = {};", indent, i, e).expect(FERR)
+            }
+            StmtKind::SInc(ref i) => writeln!(dst, "{}{}++;", indent, i).expect(FERR),
+            StmtKind::SDec(ref i) => writeln!(dst, "{}{}--;", indent, i).expect(FERR),
+            StmtKind::SReturnE(ref e) => writeln!(dst, "{}return {};", indent, e).expect(FERR),
+            StmtKind::SReturn => writeln!(dst, "{}return;", indent).expect(FERR),
+            StmtKind::SExpr(ref e) => writeln!(dst, "{}{};", indent, e).expect(FERR),
+            StmtKind::SIf(ref cond, ref stmt) => {
                 writeln!(dst, "{}if ({}) {}", indent, cond, '{').expect(FERR);
-                match **stmt {
-                    Stmt::SBlock(ref stmts) => stmts.print(&inner_indent, dst),
+                match stmt.kind {
+                    StmtKind::SBlock(ref stmts) => stmts.print(&inner_indent, dst),
                     _ => stmt.print(&inner_indent, dst),
                 };
                 writeln!(dst, "{}{}", indent, '}').expect(FERR);
             }
-            Stmt::SIfElse(ref cond, ref if_t, ref if_f) => {
+            StmtKind::SIfElse(ref cond, ref if_t, ref if_f) => {
                 writeln!(dst, "{}if ({}) {}", indent, cond, '{').expect(FERR);
-                match **if_t {
-                    Stmt::SBlock(ref stmts) => stmts.print(&inner_indent, dst),
+                match if_t.kind {
+                    StmtKind::SBlock(ref stmts) => stmts.print(&inner_indent, dst),
                     _ => if_t.print(&inner_indent, dst),	
                 };
                 writeln!(dst, "{}{} else {}", indent, '}', '{').expect(FERR);
-                match **if_f {
-                    Stmt::SBlock(ref stmts) => stmts.print(&inner_indent, dst),
+                match if_f.kind {
+                    StmtKind::SBlock(ref stmts) => stmts.print(&inner_indent, dst),
                     _ => if_f.print(&inner_indent, dst),	
                 };
                 writeln!(dst, "{}{}", indent, '}').expect(FERR);
             }
-            Stmt::SWhile(ref cond, ref stmt) => {
+            StmtKind::SWhile(ref cond, ref stmt) => {
                 writeln!(dst, "{}while ({}) {}", indent, cond, '{').expect(FERR);
-                match **stmt {
-                    Stmt::SBlock(ref stmts) => stmts.print(&inner_indent, dst),
+                match stmt.kind {
+                    StmtKind::SBlock(ref stmts) => stmts.print(&inner_indent, dst),
                     _ => stmt.print(&inner_indent, dst),	
                 };
                 writeln!(dst, "{}{}", indent, '}').expect(FERR);
             }
-            Stmt::SFor(ref t, ref ident, ref e, ref stmt) => {
+            StmtKind::SFor(ref t, ref ident, ref e, ref stmt) => {
                 writeln!(dst, "{}for ({} {} : {}) {}", indent, t, ident, e, '{').expect(FERR);
-                match **stmt {
-                    Stmt::SBlock(ref stmts) => stmts.print(&inner_indent, dst),
+                match stmt.kind {
+                    StmtKind::SBlock(ref stmts) => stmts.print(&inner_indent, dst),
                     _ => stmt.print(&inner_indent, dst),
                 }
                 writeln!(dst, "{}{}", indent, '}').expect(FERR);
             }
+            StmtKind::SSwitch(ref e, ref cases, ref default) => {
+                writeln!(dst, "{}switch ({}) {{", indent, e).expect(FERR);
+                let body_indent = Self::next_indent(&inner_indent);
+                for case in cases {
+                    writeln!(dst, "{}case {}:", inner_indent, print_vec(&case.labels))
+                        .expect(FERR);
+                    case.body.print(&body_indent, dst);
+                }
+                if let Some(ref stmts) = *default {
+                    writeln!(dst, "{}default:", inner_indent).expect(FERR);
+                    stmts.print(&body_indent, dst);
+                }
+                writeln!(dst, "{}}}", indent).expect(FERR);
+            }
         };
     }
 }
@@ -188,9 +281,22 @@ impl fmt::Display for Expr {
             Expr::ECall(ref f, ref args) => format!("{}({})", f, print_vec(args)),
             Expr::ENeg(ref e) => format!("-{}", *e),
             Expr::ENot(ref e) => format!("!{}", *e),
+            Expr::EBitNot(ref e) => format!("~{}", *e),
             Expr::EBinOp(ref lhs, ref op, ref rhs) => format!("({} {} {})", *lhs, op, *rhs),
             Expr::ENew(ref t) => format!("new {}", t),
-            Expr::ENewArray(ref t, ref size) => format!("new {}[{}]", t, *size),
+            Expr::ENewArray(ref t, ref sizes) => {
+                let mut elem_t = t;
+                let mut brackets = String::new();
+                while let Type::TArray(ref inner) = *elem_t {
+                    elem_t = inner;
+                    brackets.push_str("[]");
+                }
+                let dims: Vec<String> = sizes.iter().map(|e| format!("[{}]", e)).collect();
+                format!("new {}{}{}", elem_t, dims.concat(), brackets)
+            }
+            Expr::EArrayLit(ref elems) => format!("{{{}}}", print_vec(elems)),
+            Expr::ECast(ref t, ref e) => format!("({}){}", t, *e),
+            Expr::ECond(ref cond, ref e1, ref e2) => format!("({} ? {} : {})", *cond, *e1, *e2),
         };
         write!(f, "{}", s)
     }
@@ -200,6 +306,9 @@ impl fmt::Display for Lit {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let s = match *self {
             Lit::LInt(x) => format!("{}", x),
+            Lit::LLong(x) => format!("{}L", x),
+            Lit::LDouble(x) => format!("{:?}", x),
+            Lit::LChar(c) => format!("'{}'", (c as char).escape_default()),
             Lit::LTrue => format!("true"),
             Lit::LFalse => format!("false"),
             Lit::LString(ref s) => format!("\"{}\"", s),
@@ -232,12 +341,19 @@ impl fmt::Display for Type {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let s = match *self {
             Type::TInt => format!("int"),
+            Type::TLong => "long".to_string(),
+            Type::TDouble => "double".to_string(),
+            Type::TChar => "char".to_string(),
             Type::TString => format!("string"),
+            Type::TStringBuilder => "StringBuilder".to_string(),
             Type::TBool => format!("boolean"),
             Type::TVoid => format!("void"),
             Type::TFunc(ref args, ref ret_type) => format!("({}) -> {}", print_vec(args), ret_type),
             Type::TArray(ref t) => format!("{}[]", t),
-            Type::TObject(ref cname) => format!("{}", cname),
+            Type::TList(ref t) => format!("list<{}>", t),
+            Type::TMap(ref k, ref v) => format!("map<{}, {}>", k, v),
+            Type::TObject(ref cname) |
+            Type::TEnum(ref cname) => format!("{}", cname),
             Type::TNull => format!("<null_type>"),
         };
         write!(f, "{}", s)
@@ -260,6 +376,11 @@ impl fmt::Display for Operator {
             Operator::OpNEq => "!=",
             Operator::OpAnd => "&&",
             Operator::OpOr => "||",
+            Operator::OpBitAnd => "&",
+            Operator::OpBitOr => "|",
+            Operator::OpBitXor => "^",
+            Operator::OpShl => "<<",
+            Operator::OpShr => ">>",
         };
         write!(f, "{}", s)
     }
@@ -1,7 +1,23 @@
 use ast::{BuiltinFunc, Ident, Type};
 
 pub fn get_builtin_functions() -> Vec<BuiltinFunc> {
-    vec![get_print_int(), get_print_string(), get_error(), get_read_int(), get_read_string()]
+    vec![get_print_int(),
+         get_print_double(),
+         get_print_string(),
+         get_error(),
+         get_read_int(),
+         get_read_double(),
+         get_read_string(),
+         get_same_string(),
+         get_open_file(),
+         get_close_file(),
+         get_read_line(),
+         get_end_of_file(),
+         get_write_file(),
+         get_read_file(),
+         get_print_error(),
+         get_exit_with(),
+         get_get_env()]
 }
 
 fn get_print_int() -> BuiltinFunc {
@@ -12,6 +28,14 @@ fn get_print_int() -> BuiltinFunc {
     }
 }
 
+fn get_print_double() -> BuiltinFunc {
+    BuiltinFunc {
+        ident: Ident(String::from("printDouble")),
+        args: vec![Type::TDouble],
+        ret_type: Type::TVoid,
+    }
+}
+
 fn get_print_string() -> BuiltinFunc {
     BuiltinFunc {
         ident: Ident(String::from("printString")),
@@ -36,6 +60,14 @@ fn get_read_int() -> BuiltinFunc {
     }
 }
 
+fn get_read_double() -> BuiltinFunc {
+    BuiltinFunc {
+        ident: Ident(String::from("readDouble")),
+        args: vec![],
+        ret_type: Type::TDouble,
+    }
+}
+
 fn get_read_string() -> BuiltinFunc {
     BuiltinFunc {
         ident: Ident(String::from("readString")),
@@ -43,3 +75,124 @@ fn get_read_string() -> BuiltinFunc {
         ret_type: Type::TString,
     }
 }
+
+// == compares contents of strings, this compares references
+fn get_same_string() -> BuiltinFunc {
+    BuiltinFunc {
+        ident: Ident(String::from("sameString")),
+        args: vec![Type::TString, Type::TString],
+        ret_type: Type::TBool,
+    }
+}
+
+// files are identified by int handles, 0, 1 and 2 are stdin, stdout and stderr.
+// Returns -1 if the file can't be opened.
+fn get_open_file() -> BuiltinFunc {
+    BuiltinFunc {
+        ident: Ident(String::from("openFile")),
+        args: vec![Type::TString, Type::TString],
+        ret_type: Type::TInt,
+    }
+}
+
+fn get_close_file() -> BuiltinFunc {
+    BuiltinFunc {
+        ident: Ident(String::from("closeFile")),
+        args: vec![Type::TInt],
+        ret_type: Type::TBool,
+    }
+}
+
+// without the newline, empty at the end of the file
+fn get_read_line() -> BuiltinFunc {
+    BuiltinFunc {
+        ident: Ident(String::from("readLine")),
+        args: vec![Type::TInt],
+        ret_type: Type::TString,
+    }
+}
+
+fn get_end_of_file() -> BuiltinFunc {
+    BuiltinFunc {
+        ident: Ident(String::from("endOfFile")),
+        args: vec![Type::TInt],
+        ret_type: Type::TBool,
+    }
+}
+
+fn get_write_file() -> BuiltinFunc {
+    BuiltinFunc {
+        ident: Ident(String::from("writeFile")),
+        args: vec![Type::TInt, Type::TString],
+        ret_type: Type::TBool,
+    }
+}
+
+// the whole contents of the file at the path, empty if it can't be read
+fn get_read_file() -> BuiltinFunc {
+    BuiltinFunc {
+        ident: Ident(String::from("readFile")),
+        args: vec![Type::TString],
+        ret_type: Type::TString,
+    }
+}
+
+fn get_print_error() -> BuiltinFunc {
+    BuiltinFunc {
+        ident: Ident(String::from("printError")),
+        args: vec![Type::TString],
+        ret_type: Type::TVoid,
+    }
+}
+
+fn get_exit_with() -> BuiltinFunc {
+    BuiltinFunc {
+        ident: Ident(String::from("exitWith")),
+        args: vec![Type::TInt],
+        ret_type: Type::TVoid,
+    }
+}
+
+// empty if the environment variable is not set
+fn get_get_env() -> BuiltinFunc {
+    BuiltinFunc {
+        ident: Ident(String::from("getEnv")),
+        args: vec![Type::TString],
+        ret_type: Type::TString,
+    }
+}
+
+// methods of built-in collections
+
+pub fn get_list_method(elem_t: &Type, method: &Ident) -> Option<Type> {
+    let (args, ret_type) = match method.0.as_ref() {
+        "push" => (vec![elem_t.clone()], Type::TVoid),
+        "pop" => (vec![], elem_t.clone()),
+        "insert" => (vec![Type::TInt, elem_t.clone()], Type::TVoid),
+        "remove" => (vec![Type::TInt], elem_t.clone()),
+        _ => return None,
+    };
+    Some(Type::TFunc(args, Box::new(ret_type)))
+}
+
+pub fn get_map_method(key_t: &Type, value_t: &Type, method: &Ident) -> Option<Type> {
+    let (args, ret_type) = match method.0.as_ref() {
+        "put" => (vec![key_t.clone(), value_t.clone()], Type::TVoid),
+        "get" => (vec![key_t.clone()], value_t.clone()),
+        "contains" => (vec![key_t.clone()], Type::TBool),
+        "remove" => (vec![key_t.clone()], Type::TVoid),
+        "keys" => (vec![], Type::TList(Box::new(key_t.clone()))),
+        _ => return None,
+    };
+    Some(Type::TFunc(args, Box::new(ret_type)))
+}
+
+pub fn get_string_builder_method(method: &Ident) -> Option<Type> {
+    let (args, ret_type) = match method.0.as_ref() {
+        "append" => (vec![Type::TString], Type::TVoid),
+        "appendChar" => (vec![Type::TChar], Type::TVoid),
+        "toString" => (vec![], Type::TString),
+        _ => return None,
+    };
+    Some(Type::TFunc(args, Box::new(ret_type)))
+}
@@ -0,0 +1,81 @@
+use ast::Type;
+
+use c_generation::context::Context;
+
+// Enums are represented by the index of the variant, chars are unsigned.
+pub fn c_type(t: &Type, ctx: &Context) -> String {
+    match *t {
+        Type::TInt |
+        Type::TEnum(..) => "int32_t".to_string(),
+        Type::TLong => "int64_t".to_string(),
+        Type::TDouble => "double".to_string(),
+        Type::TChar => "uint8_t".to_string(),
+        Type::TBool => "bool".to_string(),
+        Type::TVoid => "void".to_string(),
+        Type::TString => "string_t *".to_string(),
+        Type::TStringBuilder => "builder_t *".to_string(),
+        Type::TArray(..) => "array_t *".to_string(),
+        Type::TList(..) => "list_t *".to_string(),
+        Type::TMap(..) => "map_t *".to_string(),
+        Type::TObject(ref cname) => format!("struct class_{} *", ctx.get_class_id(cname)),
+        Type::TNull => "void *".to_string(),
+        Type::TFunc(..) => unreachable!(),
+    }
+}
+
+// declaration of a variable or a parameter
+pub fn c_decl(t: &Type, name: &str, ctx: &Context) -> String {
+    raw_decl(&c_type(t, ctx), name)
+}
+
+pub fn raw_decl(c_type: &str, name: &str) -> String {
+    match c_type.ends_with('*') {
+        true => format!("{}{}", c_type, name),
+        false => format!("{} {}", c_type, name),
+    }
+}
+
+pub fn is_ptr(t: &Type) -> bool {
+    matches!(*t, Type::TString |
+        Type::TStringBuilder |
+        Type::TArray(..) |
+        Type::TList(..) |
+        Type::TMap(..) |
+        Type::TObject(..) |
+        Type::TNull)
+}
+
+// Elements of lists and maps are stored in 64-bit slots, as in the LLVM runtime.
+pub fn to_slot(val: &str, t: &Type) -> String {
+    match *t {
+        Type::TLong => val.to_string(),
+        Type::TDouble => format!("_double_to_slot({})", val),
+        _ if is_ptr(t) => format!("(int64_t)(intptr_t){}", val),
+        _ => format!("(int64_t){}", val),
+    }
+}
+
+pub fn from_slot(slot: &str, t: &Type, ctx: &Context) -> String {
+    match *t {
+        Type::TLong => slot.to_string(),
+        Type::TDouble => format!("_slot_to_double({})", slot),
+        _ if is_ptr(t) => format!("({})(intptr_t){}", c_type(t, ctx), slot),
+        _ => format!("({}){}", c_type(t, ctx), slot),
+    }
+}
+
+// characters of a C string literal, with trigraphs and non-printable characters escaped
+pub fn escape_str(s: &str) -> String {
+    let mut res = String::new();
+    for c in s.bytes() {
+        match c {
+            b'"' | b'\\' | b'?' => {
+                res.push('\\');
+                res.push(c as char);
+            }
+            _ if c.is_ascii_graphic() || c == b' ' => res.push(c as char),
+            _ => res.push_str(&format!("\\{:03o}", c)),
+        }
+    }
+    res
+}
@@ -0,0 +1,88 @@
+use ast::{Class, Type};
+use mangling::*;
+
+use c_generation::class_data::*;
+use c_generation::context::Context;
+use c_generation::generate::*;
+
+impl GenerateCode<()> for Class {
+    fn generate_code(&self, ctx: &mut Context) {
+        ctx.in_new_scope(|ctx| {
+            let id = ctx.get_class_id(&self.name);
+            generate_new(id, ctx);
+            generate_init(id, ctx);
+
+            ctx.class = Some(id);
+            ctx.static_class = Some(id);
+            for m in &self.methods {
+                m.generate_code(ctx);
+            }
+            ctx.class = None;
+            for m in &self.static_methods {
+                m.generate_code(ctx);
+            }
+            ctx.static_class = None;
+        });
+    }
+}
+
+fn generate_new(id: ClassId, ctx: &mut Context) {
+    let class = ctx.get_class_data(id).ident.clone();
+    ctx.begin_block(format!("static struct class_{} *{}(void)", id, new_symbol(&class)));
+    ctx.add_line(format!("struct class_{0} *obj = _new_object(sizeof(struct class_{0}));", id));
+    ctx.add_line(format!("{}(obj);", init_symbol(&class)));
+    ctx.add_line("return obj;".to_string());
+    ctx.end_block();
+    ctx.add_empty_line();
+}
+
+fn generate_init(id: ClassId, ctx: &mut Context) {
+    let init = init_symbol(&ctx.get_class_data(id).ident);
+    ctx.begin_block(format!("static void {}(struct class_{} *obj)", init, id));
+    if let Some(super_id) = ctx.get_class_data(id).super_id {
+        let super_init = init_symbol(&ctx.get_class_data(super_id).ident);
+        ctx.add_line(format!("{}((struct class_{} *)obj);", super_init, super_id));
+    }
+
+    match ctx.get_class_data(id).vtable.fs.is_empty() {
+        true => ctx.add_line("*(vfunc_t **)obj = NULL;".to_string()),
+        false => ctx.add_line(format!("*(vfunc_t **)obj = vtable_{};", id)),
+    }
+
+    init_strings(id, ctx);
+    init_vars(id, ctx);
+
+    ctx.end_block();
+    ctx.add_empty_line();
+}
+
+// all string fields share one empty string
+fn init_strings(id: ClassId, ctx: &mut Context) {
+    let fields = ctx.get_class_data(id).fields.clone();
+    if !fields.iter().any(|(_, t)| *t == Type::TString) {
+        return;
+    }
+
+    ctx.add_line("string_t *empty_str = _alloc_str();".to_string());
+    for (field, t) in fields {
+        if t != Type::TString {
+            continue;
+        }
+        ctx.add_line(format!("obj->m_{} = empty_str;", field));
+        ctx.add_line("_retain_str(empty_str);".to_string());
+    }
+}
+
+fn init_vars(id: ClassId, ctx: &mut Context) {
+    let fields = ctx.get_class_data(id).fields.clone();
+    for (field, t) in fields {
+        let val = match t {
+            Type::TString => continue,
+            Type::TInt | Type::TLong | Type::TChar | Type::TEnum(..) => "0",
+            Type::TDouble => "0.0",
+            Type::TBool => "false",
+            _ => "NULL",
+        };
+        ctx.add_line(format!("obj->m_{} = {};", field, val));
+    }
+}
@@ -0,0 +1,145 @@
+use ast::{Expr, Ident, Type};
+
+use c_generation::c_type::*;
+use c_generation::context::*;
+use c_generation::expr::*;
+use c_generation::generate::*;
+
+pub fn generate_new_collection(t: &Type, ctx: &mut Context) -> String {
+    let val = match *t {
+        Type::TStringBuilder => "_builder_new()".to_string(),
+        Type::TList(ref elem_t) => format!("_list_new({})", **elem_t == Type::TString),
+        Type::TMap(ref key_t, ref value_t) => {
+            format!("_map_new({}, {})",
+                    **key_t == Type::TString,
+                    **value_t == Type::TString)
+        }
+        _ => unreachable!(),
+    };
+    ctx.add_tmp(t, val)
+}
+
+pub fn generate_collection_call(obj: &str,
+                                obj_t: &Type,
+                                method: &Ident,
+                                args: &[Expr],
+                                ctx: &mut Context)
+                                -> (String, Type) {
+    match *obj_t {
+        Type::TStringBuilder => generate_builder_call(obj, method, args, ctx),
+        Type::TList(ref elem_t) => generate_list_call(obj, elem_t, method, args, ctx),
+        Type::TMap(ref key_t, ref value_t) => {
+            generate_map_call(obj, key_t, value_t, method, args, ctx)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// Strings passed to collections are retained by the runtime. Elements returned by pop
+// and remove are owned by the caller.
+fn generate_list_call(list: &str,
+                      elem_t: &Type,
+                      method: &Ident,
+                      args: &[Expr],
+                      ctx: &mut Context)
+                      -> (String, Type) {
+    match method.0.as_ref() {
+        "push" => {
+            let elem = generate_slot(&args[0], elem_t, ctx);
+            add_call(format!("_list_push({}, {})", list, elem), &Type::TVoid, ctx)
+        }
+        "pop" => {
+            let elem = from_slot(&format!("_list_pop({})", list), elem_t, ctx);
+            (ctx.add_tmp(elem_t, elem), elem_t.clone())
+        }
+        "insert" => {
+            let (idx, _) = args[0].generate_code(ctx);
+            let elem = generate_slot(&args[1], elem_t, ctx);
+            add_call(format!("_list_insert({}, {}, {})", list, idx, elem),
+                     &Type::TVoid,
+                     ctx)
+        }
+        "remove" => {
+            let (idx, _) = args[0].generate_code(ctx);
+            let elem = from_slot(&format!("_list_remove({}, {})", list, idx), elem_t, ctx);
+            (ctx.add_tmp(elem_t, elem), elem_t.clone())
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn generate_map_call(map: &str,
+                     key_t: &Type,
+                     value_t: &Type,
+                     method: &Ident,
+                     args: &[Expr],
+                     ctx: &mut Context)
+                     -> (String, Type) {
+    match method.0.as_ref() {
+        "put" => {
+            let key = generate_slot(&args[0], key_t, ctx);
+            let value = generate_slot(&args[1], value_t, ctx);
+            add_call(format!("_map_put({}, {}, {})", map, key, value),
+                     &Type::TVoid,
+                     ctx)
+        }
+        "get" => {
+            let key = generate_slot(&args[0], key_t, ctx);
+            let value = from_slot(&format!("_map_get({}, {})", map, key), value_t, ctx);
+            let value = ctx.add_tmp(value_t, value);
+            if *value_t == Type::TString {
+                ctx.add_line(format!("_retain_str({});", value));
+            }
+            (value, value_t.clone())
+        }
+        "contains" => {
+            let key = generate_slot(&args[0], key_t, ctx);
+            add_call(format!("_map_contains({}, {})", map, key), &Type::TBool, ctx)
+        }
+        "remove" => {
+            let key = generate_slot(&args[0], key_t, ctx);
+            add_call(format!("_map_remove({}, {})", map, key), &Type::TVoid, ctx)
+        }
+        "keys" => generate_map_keys(map, key_t, ctx),
+        _ => unreachable!(),
+    }
+}
+
+// The characters of appended strings are copied, the result of toString is owned by
+// the caller.
+fn generate_builder_call(builder: &str,
+                         method: &Ident,
+                         args: &[Expr],
+                         ctx: &mut Context)
+                         -> (String, Type) {
+    match method.0.as_ref() {
+        "append" => {
+            let (s, _) = args[0].generate_code(ctx);
+            add_call(format!("_builder_append({}, {})", builder, s),
+                     &Type::TVoid,
+                     ctx)
+        }
+        "appendChar" => {
+            let (c, _) = args[0].generate_code(ctx);
+            add_call(format!("_builder_append_char({}, {})", builder, c),
+                     &Type::TVoid,
+                     ctx)
+        }
+        "toString" => {
+            add_call(format!("_builder_to_string({})", builder),
+                     &Type::TString,
+                     ctx)
+        }
+        _ => unreachable!(),
+    }
+}
+
+pub fn generate_map_keys(map: &str, key_t: &Type, ctx: &mut Context) -> (String, Type) {
+    let keys_t = Type::TList(Box::new(key_t.clone()));
+    (ctx.add_tmp(&keys_t, format!("_map_keys({})", map)), keys_t)
+}
+
+fn generate_slot(e: &Expr, t: &Type, ctx: &mut Context) -> String {
+    let val = generate_init_expr(e, t, ctx);
+    to_slot(&val, t)
+}
@@ -0,0 +1,454 @@
+use std::collections::{HashMap, HashSet};
+
+use ast::{Expr, Ident, Type};
+use mangling::demangle;
+use options::Options;
+
+use c_generation::c_type::*;
+use c_generation::class_data::*;
+
+#[derive(Debug)]
+pub struct Context {
+    vars: HashMap<Ident, (String, Type)>,
+    globals: HashMap<Ident, (String, Type)>,
+    static_fields: HashMap<(ClassId, Ident), (String, Type)>,
+    static_methods: HashMap<(ClassId, Ident), Ident>, // to their symbols
+    func_types: HashMap<Ident, (Vec<Type>, Type)>,
+    c_funcs: HashSet<Ident>, // builtins and externs, called by their own names
+    externs: HashSet<Ident>,
+    string_lits: HashMap<String, String>,
+    pub ret_type: Type,
+    pub class: Option<ClassId>,
+    pub static_class: Option<ClassId>, // class whose static members are accessible directly
+    pub opts: Options,
+    pub current_func: String,
+    pub tail_target: Option<TailTarget>,
+    devirtualized: Vec<String>, // report of calls that don't use vtables
+
+    classes: HashMap<ClassId, ClassData>,
+    class_ids: HashMap<Ident, ClassId>,
+    enum_ids: HashMap<Ident, EnumId>,
+    enum_variants: Vec<Vec<Ident>>,
+
+    string_tmps: Vec<String>,
+    local_string_tmps: Vec<String>,
+    string_vars: Vec<String>,
+    local_string_vars: Vec<String>,
+
+    local_names: HashMap<Ident, usize>, // declarations of each name in the current function
+    tmps: usize,
+    indent: usize,
+    out: Vec<String>,
+}
+
+// The function being generated, self-recursive tail calls set the params
+// and jump to the beginning of the body.
+#[derive(Debug, Clone)]
+pub struct TailTarget {
+    pub symbol: String,
+    pub params: Vec<(String, Type)>, // with self first for methods
+    pub body_label: Option<String>,
+}
+
+impl Context {
+    pub fn new(opts: &Options) -> Context {
+        Context {
+            vars: HashMap::new(),
+            globals: HashMap::new(),
+            static_fields: HashMap::new(),
+            static_methods: HashMap::new(),
+            func_types: HashMap::new(),
+            c_funcs: HashSet::new(),
+            externs: HashSet::new(),
+            string_lits: HashMap::new(),
+            ret_type: Type::TVoid,
+            class: None,
+            static_class: None,
+            opts: opts.clone(),
+            current_func: String::new(),
+            tail_target: None,
+            devirtualized: Vec::new(),
+
+            classes: HashMap::new(),
+            class_ids: HashMap::new(),
+            enum_ids: HashMap::new(),
+            enum_variants: Vec::new(),
+
+            string_tmps: Vec::new(),
+            local_string_tmps: Vec::new(),
+            string_vars: Vec::new(),
+            local_string_vars: Vec::new(),
+
+            local_names: HashMap::new(),
+            tmps: 0,
+            indent: 0,
+            out: Vec::new(),
+        }
+    }
+
+    pub fn in_new_scope<F, R>(&mut self, f: F) -> R
+        where F: Fn(&mut Context) -> R
+    {
+        let old_vars = self.vars.clone();
+        let old_string_tmps = self.string_tmps.clone();
+        let old_local_string_tmps = self.local_string_tmps.clone();
+        let old_string_vars = self.string_vars.clone();
+        let old_local_string_vars = self.local_string_vars.clone();
+
+        self.local_string_tmps.clear();
+        self.local_string_vars.clear();
+        let res = f(self);
+
+        self.local_string_vars = old_local_string_vars;
+        self.string_vars = old_string_vars;
+        self.local_string_tmps = old_local_string_tmps;
+        self.string_tmps = old_string_tmps;
+        self.vars = old_vars;
+        res
+    }
+
+    // Each scope is a C block, temporaries are declared where they are computed.
+    pub fn in_new_block<F, R>(&mut self, header: String, f: F) -> R
+        where F: Fn(&mut Context) -> R
+    {
+        self.begin_block(header);
+        let res = self.in_new_scope(f);
+        self.end_block();
+        res
+    }
+
+    pub fn begin_block(&mut self, header: String) {
+        match header.is_empty() {
+            true => self.add_line("{".to_string()),
+            false => self.add_line(format!("{} {{", header)),
+        }
+        self.indent += 1;
+    }
+
+    pub fn end_block(&mut self) {
+        self.indent -= 1;
+        self.add_line("}".to_string());
+    }
+
+    // ends the block of a struct or an initializer
+    pub fn end_decl_block(&mut self) {
+        self.indent -= 1;
+        self.add_line("};".to_string());
+    }
+
+    // ends a block and begins the next one, e.g. else
+    pub fn next_block(&mut self, header: String) {
+        self.indent -= 1;
+        self.add_line(format!("}} {} {{", header));
+        self.indent += 1;
+    }
+
+    pub fn func_exists(&self, ident: &Ident) -> bool {
+        self.func_types.contains_key(ident)
+    }
+
+    pub fn get_arg_types(&self, ident: &Ident) -> Vec<Type> {
+        self.func_types.get(ident).unwrap().0.clone()
+    }
+
+    pub fn get_ret_type(&self, ident: &Ident) -> Type {
+        self.func_types.get(ident).unwrap().1.clone()
+    }
+
+    // Methods are named by their mangled symbols in the context, as in LLVM,
+    // other functions are prefixed, so that they can't clash with them.
+    pub fn get_func_symbol(&self, ident: &Ident) -> String {
+        if self.c_funcs.contains(ident) || demangle(&ident.0).is_some() {
+            ident.0.clone()
+        } else {
+            format!("f_{}", ident)
+        }
+    }
+
+    pub fn get_str_const(&self, s: &String) -> String {
+        self.string_lits.get(s).unwrap().clone()
+    }
+
+    pub fn set_str_const(&mut self, s: String, name: String) {
+        self.string_lits.insert(s, name);
+    }
+
+    pub fn var_exists(&self, ident: &Ident) -> bool {
+        self.vars.contains_key(ident)
+    }
+
+    pub fn get_var(&self, ident: &Ident) -> (String, Type) {
+        self.vars.get(ident).unwrap().clone()
+    }
+
+    pub fn set_var(&mut self, ident: Ident, name: String, t: Type) {
+        self.vars.insert(ident, (name.clone(), t.clone()));
+        if t == Type::TString {
+            self.string_vars.push(name.clone());
+            self.local_string_vars.push(name);
+        }
+    }
+
+    // Names of locals are unique in a function, an initializer can refer to
+    // a variable of an outer block with the same name.
+    pub fn new_local_name(&mut self, ident: &Ident) -> String {
+        let cnt = self.local_names.entry(ident.clone()).or_insert(0);
+        *cnt += 1;
+        // variables introduced by optimizations start with a dot
+        let (prefix, name) = match ident.0.starts_with('.') {
+            true => ("h", &ident.0[1..]),
+            false => ("l", &ident.0[..]),
+        };
+        match *cnt {
+            1 => format!("{}_{}", prefix, name),
+            n => format!("{}{}_{}", prefix, n, name),
+        }
+    }
+
+    pub fn has_globals(&self) -> bool {
+        !self.globals.is_empty() || !self.static_fields.is_empty()
+    }
+
+    pub fn global_exists(&self, ident: &Ident) -> bool {
+        self.globals.contains_key(ident)
+    }
+
+    pub fn get_global(&self, ident: &Ident) -> (String, Type) {
+        self.globals.get(ident).unwrap().clone()
+    }
+
+    pub fn add_global(&mut self, ident: &Ident, t: &Type) {
+        let name = format!("g_{}", ident);
+        let decl = c_decl(t, &name, self);
+        self.add_line(format!("static {};", decl));
+        self.globals.insert(ident.clone(), (name, t.clone()));
+    }
+
+    pub fn add_static_field(&mut self, class: ClassId, ident: &Ident, t: &Type) {
+        let name = format!("static{}_{}", class, ident);
+        let decl = c_decl(t, &name, self);
+        self.add_line(format!("static {};", decl));
+        self.static_fields.insert((class, ident.clone()), (name, t.clone()));
+    }
+
+    pub fn add_static_method(&mut self, class: ClassId, ident: &Ident, symbol: &Ident) {
+        self.static_methods.insert((class, ident.clone()), symbol.clone());
+    }
+
+    // static members are inherited
+
+    pub fn find_static_field(&self, mut id: ClassId, ident: &Ident) -> Option<(String, Type)> {
+        loop {
+            if let Some(field) = self.static_fields.get(&(id, ident.clone())) {
+                return Some(field.clone());
+            }
+            id = self.get_class_data(id).super_id?;
+        }
+    }
+
+    // returns the name of the generated function
+    pub fn find_static_method(&self, mut id: ClassId, ident: &Ident) -> Option<Ident> {
+        loop {
+            if let Some(symbol) = self.static_methods.get(&(id, ident.clone())) {
+                return Some(symbol.clone());
+            }
+            id = self.get_class_data(id).super_id?;
+        }
+    }
+
+    pub fn add_func(&mut self, ident: &Ident, arg_types: Vec<Type>, ret_type: Type) {
+        self.func_types.insert(ident.clone(), (arg_types, ret_type));
+    }
+
+    pub fn add_builtin_func(&mut self, ident: &Ident, arg_types: Vec<Type>, ret_type: Type) {
+        self.add_func(ident, arg_types, ret_type);
+        self.c_funcs.insert(ident.clone());
+    }
+
+    pub fn add_extern_func(&mut self, ident: &Ident, arg_types: Vec<Type>, ret_type: Type) {
+        self.add_builtin_func(ident, arg_types, ret_type);
+        self.externs.insert(ident.clone());
+    }
+
+    pub fn is_extern(&self, ident: &Ident) -> bool {
+        self.externs.contains(ident)
+    }
+
+    // string reference counting
+    pub fn add_string_tmp(&mut self, name: String) {
+        self.string_tmps.push(name.clone());
+        self.local_string_tmps.push(name);
+    }
+
+    pub fn release_local_strings(&mut self) {
+        let strs = self.local_string_tmps.clone();
+        self.release_strings(strs);
+        let strs = self.local_string_vars.clone();
+        self.release_strings(strs);
+    }
+
+    pub fn release_all_strings(&mut self) {
+        let strs = self.string_tmps.clone();
+        self.release_strings(strs);
+        let strs = self.string_vars.clone();
+        self.release_strings(strs);
+    }
+
+    fn release_strings(&mut self, names: Vec<String>) {
+        for name in names {
+            self.add_line(format!("_release_str({});", name));
+        }
+    }
+
+    // class
+    pub fn add_class_id(&mut self, cname: &Ident, id: ClassId) {
+        self.class_ids.insert(cname.clone(), id);
+    }
+
+    pub fn add_class(&mut self, id: ClassId, cdata: ClassData) {
+        self.classes.insert(id, cdata);
+    }
+
+    pub fn set_vtable(&mut self, id: ClassId, vtable: VTable) {
+        self.classes.get_mut(&id).unwrap().vtable = vtable;
+    }
+
+    pub fn class_exists(&self, cname: &Ident) -> bool {
+        self.class_ids.contains_key(cname)
+    }
+
+    pub fn get_class_id(&self, cname: &Ident) -> ClassId {
+        *self.class_ids.get(cname).unwrap()
+    }
+
+    pub fn get_class_data(&self, id: ClassId) -> &ClassData {
+        self.classes.get(&id).unwrap()
+    }
+
+    pub fn has_field(&self, mut id: ClassId, field: &Ident) -> bool {
+        while !self.get_class_data(id).has_field(field) {
+            match self.get_class_data(id).super_id {
+                Some(super_id) => id = super_id,
+                None => return false,
+            }
+        }
+        true
+    }
+
+    // Class hierarchy analysis: whether a subclass of the class replaces
+    // the method at the given position of its vtable.
+    pub fn is_overridden(&self, id: ClassId, vtable_pos: usize) -> bool {
+        let real_ident = &self.get_class_data(id).vtable.fs[vtable_pos].real_ident;
+        self.classes.iter().any(|(&sub_id, cdata)| {
+            sub_id != id && self.is_subclass_of(sub_id, id) &&
+            cdata.vtable.fs[vtable_pos].real_ident != *real_ident
+        })
+    }
+
+    pub fn add_devirtualized(&mut self, call: &Expr, method_class: ClassId, method: &Ident) {
+        let msg = format!("{}: {} calls {}.{}",
+                          self.current_func,
+                          call,
+                          self.get_class_data(method_class).ident,
+                          method);
+        self.devirtualized.push(msg);
+    }
+
+    pub fn take_devirtualized(&mut self) -> Vec<String> {
+        self.devirtualized.drain(..).collect()
+    }
+
+    pub fn is_subclass_of(&self, mut id: ClassId, super_id: ClassId) -> bool {
+        while self.get_class_data(id).super_id.is_some() && id != super_id {
+            id = self.get_class_data(id).get_super();
+        }
+        id == super_id
+    }
+
+    // pointer to the object as the given class, which is the object or its superclass
+    pub fn cast_object(&self, obj: &str, from: ClassId, to: ClassId) -> String {
+        match from == to {
+            true => obj.to_string(),
+            false => format!("(struct class_{} *){}", to, obj),
+        }
+    }
+
+    // enums
+    pub fn add_enum(&mut self, name: &Ident, variants: &[Ident]) -> EnumId {
+        let id = self.enum_variants.len();
+        self.enum_ids.insert(name.clone(), id);
+        self.enum_variants.push(variants.to_owned());
+        id
+    }
+
+    pub fn enum_exists(&self, name: &Ident) -> bool {
+        self.enum_ids.contains_key(name)
+    }
+
+    pub fn get_enum_id(&self, name: &Ident) -> EnumId {
+        *self.enum_ids.get(name).unwrap()
+    }
+
+    pub fn get_variant_tag(&self, id: EnumId, variant: &Ident) -> i32 {
+        self.enum_variants[id].iter().position(|v| v == variant).unwrap() as i32
+    }
+
+    // output
+
+    // Stores the value in a new temporary, so that it's evaluated in order.
+    pub fn add_tmp(&mut self, t: &Type, val: String) -> String {
+        let c_type = c_type(t, self);
+        self.add_raw_tmp(&c_type, val)
+    }
+
+    pub fn add_raw_tmp(&mut self, c_type: &str, val: String) -> String {
+        let name = self.next_tmp();
+        self.add_line(format!("{} = {};", raw_decl(c_type, &name), val));
+        name
+    }
+
+    pub fn next_tmp(&mut self) -> String {
+        self.tmps += 1;
+        format!("t{}", self.tmps)
+    }
+
+    // the names of temporaries and locals start over in each function
+    pub fn reset(&mut self) {
+        self.tmps = 0;
+        self.local_names.clear();
+    }
+
+    // A line written once the rest of the code is generated, e.g. a declaration
+    // of a variable whose type is known only later.
+    pub fn reserve_line(&mut self) -> usize {
+        let indent = "    ".repeat(self.indent);
+        self.out.push(indent);
+        self.out.len() - 1
+    }
+
+    pub fn fill_line(&mut self, idx: usize, s: String) {
+        self.out[idx].push_str(&s);
+    }
+
+    // lines reserved after it must already be filled
+    pub fn remove_line(&mut self, idx: usize) {
+        self.out.remove(idx);
+    }
+
+    pub fn add_line(&mut self, s: String) {
+        let indent = "    ".repeat(self.indent);
+        self.out.push(format!("{}{}", indent, s));
+    }
+
+    pub fn add_comment(&mut self, s: String) {
+        self.add_line(format!("// {}", s));
+    }
+
+    pub fn add_empty_line(&mut self) {
+        self.out.push(String::new());
+    }
+
+    pub fn get_out(&self) -> &Vec<String> {
+        &self.out
+    }
+}
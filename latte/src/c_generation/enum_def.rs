@@ -0,0 +1,28 @@
+use ast::Enum;
+use mangling::*;
+
+use c_generation::context::Context;
+use c_generation::generate::*;
+
+// Generates name() of the enum, which maps tags to names of the variants.
+// Tags out of range are not possible, so the first variant is the default.
+impl GenerateCode<()> for Enum {
+    fn generate_code(&self, ctx: &mut Context) {
+        let name = enum_name_symbol(&self.name);
+        ctx.begin_block(format!("static string_t *{}(int32_t tag)", name));
+        let first = ctx.get_str_const(&self.variants[0].0);
+        ctx.add_line(format!("string_t *name = &{};", first));
+        ctx.begin_block("switch (tag)".to_string());
+        for (tag, variant) in self.variants.iter().enumerate().skip(1) {
+            let name = ctx.get_str_const(&variant.0);
+            ctx.add_line(format!("case {}:", tag));
+            ctx.add_line(format!("    name = &{};", name));
+            ctx.add_line("    break;".to_string());
+        }
+        ctx.end_block();
+        ctx.add_line("_retain_str(name);".to_string());
+        ctx.add_line("return name;".to_string());
+        ctx.end_block();
+        ctx.add_empty_line();
+    }
+}
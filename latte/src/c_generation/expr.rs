@@ -0,0 +1,603 @@
+use ast::*;
+use mangling::*;
+
+use c_generation::c_type::*;
+use c_generation::collection::*;
+use c_generation::context::*;
+use c_generation::extern_func::*;
+use c_generation::field_get::*;
+use c_generation::generate::*;
+
+// Every value which isn't a constant or a local is stored in a temporary,
+// so that the order of evaluation is the same as in Latte.
+impl GenerateCode<(String, Type)> for Expr {
+    fn generate_code(&self, ctx: &mut Context) -> (String, Type) {
+        let (val, t) = match *self {
+            Expr::EVar(ref ident) if as_enum_variant(ident, ctx).is_some() => {
+                let (tag, t) = as_enum_variant(ident, ctx).unwrap();
+                (format!("{}", tag), t)
+            }
+            // locals can't be changed by calls, strings are retained
+            Expr::EVar(FieldGet::Direct(ref ident)) if ctx.var_exists(ident) &&
+                                                       ctx.get_var(ident).1 != Type::TString => {
+                ctx.get_var(ident)
+            }
+            Expr::EVar(ref ident) => {
+                let (lvalue, t) = ident.generate_code(ctx);
+                let val = ctx.add_tmp(&t, lvalue);
+                if t == Type::TString {
+                    ctx.add_line(format!("_retain_str({});", val));
+                }
+                (val, t)
+            }
+            Expr::ELit(ref lit) => lit.generate_code(ctx),
+            Expr::ECall(ref ident, ref args) => generate_call(ident, args, ctx),
+            Expr::ENeg(ref e) => {
+                let (val, t) = e.generate_code(ctx);
+                if t == Type::TDouble {
+                    (ctx.add_tmp(&t, format!("-{}", val)), t)
+                } else {
+                    (generate_arith_op(&t, "0", Operator::OpSub, &val, ctx), t)
+                }
+            }
+            Expr::ENot(ref e) => {
+                let (val, t) = e.generate_code(ctx);
+                (ctx.add_tmp(&t, format!("!{}", val)), t)
+            }
+            Expr::EBitNot(ref e) => {
+                let (val, t) = e.generate_code(ctx);
+                (ctx.add_tmp(&t, format!("~{}", val)), t)
+            }
+            Expr::EBinOp(ref lhs, Operator::OpOr, ref rhs) => generate_or(lhs, rhs, ctx),
+            Expr::EBinOp(ref lhs, Operator::OpAnd, ref rhs) => generate_and(lhs, rhs, ctx),
+            Expr::EBinOp(ref lhs, Operator::OpNEq, ref rhs) => generate_eq(lhs, rhs, true, ctx),
+            Expr::EBinOp(ref lhs, Operator::OpEq, ref rhs) => generate_eq(lhs, rhs, false, ctx),
+            Expr::EBinOp(ref lhs, Operator::OpAdd, ref rhs) => generate_add(lhs, rhs, ctx),
+            Expr::EBinOp(ref lhs, ref op, ref rhs) => {
+                let (lhs_val, lhs_t) = lhs.generate_code(ctx);
+                let (mut rhs_val, _) = rhs.generate_code(ctx);
+                if lhs_t == Type::TString {
+                    return generate_str_cmp(&lhs_val, *op, &rhs_val, ctx);
+                }
+                let t = match *op {
+                    Operator::OpLess | Operator::OpLessE | Operator::OpGreater |
+                    Operator::OpGreaterE => Type::TBool,
+                    Operator::OpAdd | Operator::OpSub | Operator::OpMul | Operator::OpDiv |
+                    Operator::OpMod | Operator::OpBitAnd | Operator::OpBitOr |
+                    Operator::OpBitXor => lhs_t.clone(),
+                    Operator::OpShl | Operator::OpShr => {
+                        // only the lowest 5 bits of the shift count are used
+                        rhs_val = format!("({} & 31)", rhs_val);
+                        lhs_t.clone()
+                    }
+                    _ => unreachable!(),
+                };
+                (generate_arith_op(&lhs_t, &lhs_val, *op, &rhs_val, ctx), t)
+            }
+            Expr::ENew(ref t) => {
+                match *t {
+                    Type::TObject(ref cname) => {
+                        (ctx.add_tmp(t, format!("{}()", new_symbol(cname))), t.clone())
+                    }
+                    _ => (generate_new_collection(t, ctx), t.clone()),
+                }
+            }
+            Expr::ENewArray(ref t, ref sizes) => {
+                let mut arr_t = t.clone();
+                let mut size_vals: Vec<String> = Vec::new();
+                for size in sizes {
+                    size_vals.push(size.generate_code(ctx).0);
+                    arr_t = Type::TArray(Box::new(arr_t));
+                }
+                (new_arr(&arr_t, &size_vals, ctx), arr_t)
+            }
+            Expr::EArrayLit(..) => unreachable!("array literal without destination type"),
+            Expr::ECast(ref t, ref e) => {
+                let (val, e_t) = e.generate_code(ctx);
+                match e_t == *t {
+                    true => (val, e_t),
+                    false => (ctx.add_tmp(t, cast(&val, &e_t, t, ctx)), t.clone()),
+                }
+            }
+            Expr::ECond(ref cond, ref e1, ref e2) => generate_cond(cond, e1, e2, ctx),
+        };
+        if t == Type::TString {
+            ctx.add_string_tmp(val.clone());
+        }
+        (val, t)
+    }
+}
+
+fn generate_call(ident: &FieldGet, args: &[Expr], ctx: &mut Context) -> (String, Type) {
+    let call = match prepare_call(ident, args, ctx) {
+        PreparedCall::Generated(val, t) => return (val, t),
+        PreparedCall::Call(call) => call,
+    };
+    let call_str = format!("{}({})", call.func, call.args.join(", "));
+    add_call(call_str, &call.ret_type, ctx)
+}
+
+// the result of a void call is empty
+pub fn add_call(call: String, ret_type: &Type, ctx: &mut Context) -> (String, Type) {
+    match *ret_type {
+        Type::TVoid => {
+            ctx.add_line(format!("{};", call));
+            (String::new(), Type::TVoid)
+        }
+        _ => (ctx.add_tmp(ret_type, call), ret_type.clone()),
+    }
+}
+
+pub struct Call {
+    pub func: String, // name of the function or a function pointer from the vtable
+    pub ret_type: Type,
+    pub args: Vec<String>, // evaluated, with self first for methods
+}
+
+pub enum PreparedCall {
+    Generated(String, Type), // methods of collections and enums are generated in place
+    Call(Call),
+}
+
+// Evaluates the object and args of the call.
+pub fn prepare_call(ident: &FieldGet, args: &[Expr], ctx: &mut Context) -> PreparedCall {
+    let (obj, func_name): (Option<(String, Type)>, Ident) = ident.generate_code(ctx);
+    let obj = match obj {
+        Some((val, Type::TObject(ref cname))) => Some((val, ctx.get_class_id(cname))),
+        // the only method of enums is name()
+        Some((val, Type::TEnum(ref name))) => {
+            let func = format!("{}({})", enum_name_symbol(name), val);
+            let (val, t) = add_call(func, &Type::TString, ctx);
+            return PreparedCall::Generated(val, t);
+        }
+        Some((val, obj_t)) => {
+            let (val, t) = generate_collection_call(&val, &obj_t, &func_name, args, ctx);
+            return PreparedCall::Generated(val, t);
+        }
+        None if ctx.is_extern(&func_name) => {
+            let (val, t) = generate_extern_call(&func_name, args, ctx);
+            return PreparedCall::Generated(val, t);
+        }
+        None => None,
+    };
+    let ret_type;
+    let arg_types;
+    let func;
+    let mut final_args: Vec<String> = Vec::new();
+    if let Some((obj, id)) = obj {
+        let vtable_pos = *ctx.get_class_data(id).vtable.idxs.get(&func_name).unwrap();
+        let f_info = ctx.get_class_data(id).vtable.fs[vtable_pos].clone();
+
+        ret_type = f_info.ret_type.clone();
+        arg_types = f_info.arg_types.clone();
+        if ctx.is_overridden(id, vtable_pos) {
+            // the vtable pointer is at the beginning of every object
+            let mut types = vec![format!("struct class_{} *", f_info.class_id)];
+            types.extend(arg_types.iter().map(|t| c_type(t, ctx)));
+            func = format!("(({} (*)({}))(*(vfunc_t **){})[{}])",
+                           c_type(&ret_type, ctx),
+                           types.join(", "),
+                           obj,
+                           vtable_pos);
+        } else {
+            if ctx.opts.report_devirt {
+                let call = Expr::ECall(ident.clone(), args.to_owned());
+                ctx.add_devirtualized(&call, f_info.class_id, &func_name);
+            }
+            func = f_info.real_ident.clone();
+        }
+        final_args.push(ctx.cast_object(&obj, id, f_info.class_id));
+    } else {
+        ret_type = ctx.get_ret_type(&func_name);
+        arg_types = ctx.get_arg_types(&func_name);
+        func = ctx.get_func_symbol(&func_name);
+    }
+
+    for (arg, arg_dst_t) in args.iter().zip(arg_types) {
+        final_args.push(generate_init_expr(arg, &arg_dst_t, ctx));
+    }
+
+    PreparedCall::Call(Call {
+        func,
+        ret_type,
+        args: final_args,
+    })
+}
+
+// Generates value to be stored in a location of type t (variable, argument etc.)
+pub fn generate_init_expr(e: &Expr, t: &Type, ctx: &mut Context) -> String {
+    let (val, expr_t) = match *e {
+        Expr::EArrayLit(ref elems) => (generate_array_lit(elems, t, ctx), t.clone()),
+        _ => e.generate_code(ctx),
+    };
+    convert(&val, &expr_t, t, ctx)
+}
+
+// Conversions are the same as in LLVM: doubles are truncated and saturated, which
+// C casts don't do, chars are unsigned and narrowing keeps the lowest bits.
+fn cast(val: &str, from: &Type, to: &Type, ctx: &Context) -> String {
+    match (from, to) {
+        (&Type::TDouble, &Type::TLong) => format!("_double_to_i64({})", val),
+        (&Type::TDouble, &Type::TInt) => format!("_double_to_i32({})", val),
+        (&Type::TDouble, _) => format!("({})_double_to_i32({})", c_type(to, ctx), val),
+        _ => format!("({}){}", c_type(to, ctx), val),
+    }
+}
+
+// objects of subclasses are cast to the superclass
+pub fn convert(val: &str, from: &Type, to: &Type, ctx: &Context) -> String {
+    match (from, to) {
+        (Type::TObject(from), Type::TObject(to)) => {
+            ctx.cast_object(val, ctx.get_class_id(from), ctx.get_class_id(to))
+        }
+        _ => val.to_string(),
+    }
+}
+
+pub fn arr_elem_t(arr_t: &Type) -> Type {
+    match *arr_t {
+        Type::TArray(ref elem_t) => *elem_t.clone(),
+        _ => unreachable!(),
+    }
+}
+
+// elements are zeroed
+fn alloc_arr(arr_t: &Type, size: &str, ctx: &mut Context) -> String {
+    let elem_t = c_type(&arr_elem_t(arr_t), ctx);
+    ctx.add_tmp(arr_t, format!("_new_array({}, sizeof({}))", size, elem_t))
+}
+
+// sizes of subsequent dimensions, subarrays of missing dimensions are null
+fn new_arr(arr_t: &Type, sizes: &[String], ctx: &mut Context) -> String {
+    let arr = alloc_arr(arr_t, &sizes[0], ctx);
+    let elem_t = arr_elem_t(arr_t);
+    if sizes.len() > 1 {
+        let idx = ctx.next_tmp();
+        let header = format!("for (int32_t {0} = 0; {0} < {1}; {0}++)", idx, sizes[0]);
+        ctx.in_new_block(header, |ctx| {
+            let subarr = new_arr(&elem_t, &sizes[1..], ctx);
+            let elems = format!("(({}){}->elems)", ptr_to(&elem_t, ctx), arr);
+            ctx.add_line(format!("{}[{}] = {};", elems, idx, subarr));
+        });
+    } else if elem_t == Type::TString {
+        ctx.add_line(format!("_init_str_arr({});", arr));
+    }
+    arr
+}
+
+fn generate_array_lit(elems: &[Expr], arr_t: &Type, ctx: &mut Context) -> String {
+    let elem_t = arr_elem_t(arr_t);
+    let arr = alloc_arr(arr_t, &format!("{}", elems.len()), ctx);
+    for (idx, e) in elems.iter().enumerate() {
+        let val = generate_init_expr(e, &elem_t, ctx);
+        if elem_t == Type::TString {
+            ctx.add_line(format!("_retain_str({});", val));
+        }
+        let elems = format!("(({}){}->elems)", ptr_to(&elem_t, ctx), arr);
+        ctx.add_line(format!("{}[{}] = {};", elems, idx, val));
+    }
+    arr
+}
+
+fn generate_or(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> (String, Type) {
+    generate_short_circuit(lhs, rhs, "!", ctx)
+}
+
+fn generate_and(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> (String, Type) {
+    generate_short_circuit(lhs, rhs, "", ctx)
+}
+
+// rhs is evaluated if lhs (negated for ||) is true
+fn generate_short_circuit(lhs: &Expr,
+                          rhs: &Expr,
+                          negation: &str,
+                          ctx: &mut Context)
+                          -> (String, Type) {
+    let lhs_val = ctx.in_new_scope(|ctx| {
+        let (lhs_val, _) = lhs.generate_code(ctx);
+        ctx.release_local_strings();
+        lhs_val
+    });
+    let res = ctx.add_tmp(&Type::TBool, lhs_val);
+    ctx.in_new_block(format!("if ({}{})", negation, res), |ctx| {
+        let (rhs_val, _) = rhs.generate_code(ctx);
+        ctx.release_local_strings();
+        ctx.add_line(format!("{} = {};", res, rhs_val));
+    });
+    (res, Type::TBool)
+}
+
+// Each branch is converted to the common type of both branches, which is known
+// after both of them are generated.
+fn generate_cond(cond: &Expr, e1: &Expr, e2: &Expr, ctx: &mut Context) -> (String, Type) {
+    let (cond_val, _) = cond.generate_code(ctx);
+    let res = ctx.next_tmp();
+    let decl_line = ctx.reserve_line();
+
+    ctx.begin_block(format!("if ({})", cond_val));
+    let (e1_val, e1_t, e1_line) = ctx.in_new_scope(|ctx| generate_cond_branch(e1, ctx));
+    ctx.next_block("else".to_string());
+    let (e2_val, e2_t, e2_line) = ctx.in_new_scope(|ctx| generate_cond_branch(e2, ctx));
+    ctx.end_block();
+
+    let t = common_type(&e1_t, &e2_t, ctx);
+    if t == Type::TVoid || t == Type::TNull {
+        ctx.remove_line(e2_line);
+        ctx.remove_line(e1_line);
+        ctx.remove_line(decl_line);
+        return match t == Type::TVoid {
+            true => (String::new(), t),
+            false => ("NULL".to_string(), t),
+        };
+    }
+    let e2_val = convert(&e2_val, &e2_t, &t, ctx);
+    ctx.fill_line(e2_line, format!("{} = {};", res, e2_val));
+    let e1_val = convert(&e1_val, &e1_t, &t, ctx);
+    ctx.fill_line(e1_line, format!("{} = {};", res, e1_val));
+    let decl = c_decl(&t, &res, ctx);
+    ctx.fill_line(decl_line, format!("{};", decl));
+    (res, t)
+}
+
+fn generate_cond_branch(e: &Expr, ctx: &mut Context) -> (String, Type, usize) {
+    let (val, t) = e.generate_code(ctx);
+    if t == Type::TString {
+        ctx.add_line(format!("_retain_str({});", val));
+    }
+    ctx.release_local_strings();
+    (val, t, ctx.reserve_line())
+}
+
+// least common supertype of types accepted by the type checker
+fn common_type(t1: &Type, t2: &Type, ctx: &Context) -> Type {
+    if t1 == t2 || *t2 == Type::TNull {
+        return t1.clone();
+    }
+    if *t1 == Type::TNull {
+        return t2.clone();
+    }
+    let (mut id, id2) = match (t1, t2) {
+        (Type::TObject(c1), Type::TObject(c2)) => {
+            (ctx.get_class_id(c1), ctx.get_class_id(c2))
+        }
+        _ => unreachable!(),
+    };
+    while !ctx.is_subclass_of(id2, id) {
+        id = ctx.get_class_data(id).get_super();
+    }
+    Type::TObject(ctx.get_class_data(id).ident.clone())
+}
+
+enum AddOperand {
+    Strings(Vec<String>), // parts of a concatenation, in order
+    Value(String, Type),
+}
+
+// Concatenations of more than two strings are done at once, the types are known
+// only here, so the chains are flattened during generation.
+fn generate_add(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> (String, Type) {
+    let parts = match generate_add_operands(lhs, rhs, ctx) {
+        AddOperand::Strings(parts) => parts,
+        AddOperand::Value(val, t) => return (val, t),
+    };
+    let val = match parts.len() {
+        2 => format!("_concatenate({}, {})", parts[0], parts[1]),
+        n => {
+            let arr = ctx.next_tmp();
+            ctx.add_line(format!("string_t *{}[] = {{{}}};", arr, parts.join(", ")));
+            format!("_concatenate_n({}, {})", arr, n)
+        }
+    };
+    (ctx.add_tmp(&Type::TString, val), Type::TString)
+}
+
+fn generate_add_operands(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> AddOperand {
+    match generate_add_operand(lhs, ctx) {
+        AddOperand::Strings(mut parts) => {
+            match generate_add_operand(rhs, ctx) {
+                AddOperand::Strings(rhs_parts) => parts.extend(rhs_parts),
+                AddOperand::Value(val, _) => parts.push(val),
+            }
+            AddOperand::Strings(parts)
+        }
+        AddOperand::Value(lhs_val, t) => {
+            let (rhs_val, _) = rhs.generate_code(ctx);
+            let val = generate_arith_op(&t, &lhs_val, Operator::OpAdd, &rhs_val, ctx);
+            AddOperand::Value(val, t)
+        }
+    }
+}
+
+fn generate_add_operand(e: &Expr, ctx: &mut Context) -> AddOperand {
+    match *e {
+        Expr::EBinOp(ref lhs, Operator::OpAdd, ref rhs) => generate_add_operands(lhs, rhs, ctx),
+        _ => {
+            let (val, t) = e.generate_code(ctx);
+            match t == Type::TString {
+                true => AddOperand::Strings(vec![val]),
+                false => AddOperand::Value(val, t),
+            }
+        }
+    }
+}
+
+// Integer arithmetic wraps around on overflow, unless overflow checking is enabled.
+// Wrapping operations are done on unsigned integers, signed overflow is undefined in C.
+// MIN / -1 is undefined too, it's MIN as in LLVM IR, and MIN % -1 is 0.
+pub fn generate_arith_op(t: &Type,
+                         lhs: &str,
+                         op: Operator,
+                         rhs: &str,
+                         ctx: &mut Context)
+                         -> String {
+    let is_div = op == Operator::OpDiv || op == Operator::OpMod;
+    let is_checked_op = op == Operator::OpAdd || op == Operator::OpSub || op == Operator::OpMul ||
+                        (is_div && may_be_minus_one(rhs));
+    let (signed_t, unsigned_t, suffix) = match *t {
+        Type::TInt => ("int32_t", "uint32_t", "i32"),
+        Type::TLong => ("int64_t", "uint64_t", "i64"),
+        _ => ("", "", ""),
+    };
+    let is_int = !signed_t.is_empty();
+    let res_t = match op {
+        Operator::OpLess | Operator::OpLessE | Operator::OpGreater | Operator::OpGreaterE |
+        Operator::OpEq => Type::TBool,
+        _ => t.clone(),
+    };
+    let val = if ctx.opts.check_overflow && is_checked_op && is_int {
+        format!("_checked_{}_{}({}, {})", checked_op_name(op), suffix, lhs, rhs)
+    } else if is_int && is_div && is_checked_op {
+        match op {
+            Operator::OpDiv => {
+                format!("{} == -1 ? ({})(({})0 - ({}){}) : {} / {}",
+                        rhs,
+                        signed_t,
+                        unsigned_t,
+                        unsigned_t,
+                        lhs,
+                        lhs,
+                        rhs)
+            }
+            _ => format!("{} == -1 ? 0 : {} % {}", rhs, lhs, rhs),
+        }
+    } else if is_int && (is_checked_op || op == Operator::OpShl) {
+        format!("({})(({}){} {} ({}){})",
+                signed_t,
+                unsigned_t,
+                lhs,
+                op_str(op),
+                unsigned_t,
+                rhs)
+    } else {
+        format!("{} {} {}", lhs, op_str(op), rhs)
+    };
+    ctx.add_tmp(&res_t, val)
+}
+
+fn checked_op_name(op: Operator) -> &'static str {
+    match op {
+        Operator::OpAdd => "add",
+        Operator::OpSub => "sub",
+        Operator::OpMul => "mul",
+        Operator::OpDiv => "div",
+        Operator::OpMod => "mod",
+        _ => unreachable!(),
+    }
+}
+
+// unless it's a literal
+fn may_be_minus_one(val: &str) -> bool {
+    match val.parse::<i64>() {
+        Ok(x) => x == -1,
+        Err(_) => true,
+    }
+}
+
+fn op_str(op: Operator) -> &'static str {
+    match op {
+        Operator::OpAdd => "+",
+        Operator::OpSub => "-",
+        Operator::OpMul => "*",
+        Operator::OpDiv => "/",
+        Operator::OpMod => "%",
+        Operator::OpEq => "==",
+        Operator::OpLess => "<",
+        Operator::OpLessE => "<=",
+        Operator::OpGreater => ">",
+        Operator::OpGreaterE => ">=",
+        Operator::OpBitAnd => "&",
+        Operator::OpBitOr => "|",
+        Operator::OpBitXor => "^",
+        Operator::OpShl => "<<",
+        Operator::OpShr => ">>",
+        _ => unreachable!(),
+    }
+}
+
+fn generate_eq(lhs: &Expr, rhs: &Expr, negate: bool, ctx: &mut Context) -> (String, Type) {
+    let (lhs_val, t1) = lhs.generate_code(ctx);
+    let (rhs_val, t2) = rhs.generate_code(ctx);
+    let (eq, neq) = match negate {
+        false => ("", "=="),
+        true => ("!", "!="),
+    };
+    let res = if t1 == Type::TNull && t2 == Type::TNull {
+        format!("{}true", eq)
+    } else if t1 == Type::TString && t2 == Type::TString {
+        format!("{}_str_eq({}, {})", eq, lhs_val, rhs_val)
+    } else if is_object(&t1) && is_object(&t2) {
+        // objects of different classes are compared as pointers to their beginnings
+        format!("(void *){} {} (void *){}", lhs_val, neq, rhs_val)
+    } else {
+        format!("{} {} {}", lhs_val, neq, rhs_val)
+    };
+    (ctx.add_tmp(&Type::TBool, res), Type::TBool)
+}
+
+fn is_object(t: &Type) -> bool {
+    matches!(*t, Type::TObject(..))
+}
+
+// lexicographic order of the contents
+fn generate_str_cmp(lhs: &str, op: Operator, rhs: &str, ctx: &mut Context) -> (String, Type) {
+    let cmp = format!("_str_cmp({}, {}) {} 0", lhs, rhs, op_str(op));
+    (ctx.add_tmp(&Type::TBool, cmp), Type::TBool)
+}
+
+impl GenerateCode<(String, Type)> for Lit {
+    fn generate_code(&self, ctx: &mut Context) -> (String, Type) {
+        match *self {
+            Lit::LInt(x) => (int_lit(x), Type::TInt),
+            Lit::LLong(x) => (long_lit(x), Type::TLong),
+            Lit::LDouble(x) => (double_lit(x), Type::TDouble),
+            Lit::LChar(c) => (format!("{}", c), Type::TChar),
+            Lit::LTrue => ("true".to_string(), Type::TBool),
+            Lit::LFalse => ("false".to_string(), Type::TBool),
+            Lit::LString(ref s) => {
+                let name = ctx.get_str_const(s);
+                let val = ctx.add_tmp(&Type::TString, format!("&{}", name));
+                ctx.add_line(format!("_retain_str({});", val));
+                (val, Type::TString)
+            }
+            Lit::LNull(None) => ("NULL".to_string(), Type::TNull),
+            Lit::LNull(Some(ref cname)) => ("NULL".to_string(), Type::TObject(cname.clone())),
+        }
+    }
+}
+
+fn int_lit(x: i32) -> String {
+    if x == i32::MIN {
+        "INT32_MIN".to_string()
+    } else if x < 0 {
+        format!("({})", x)
+    } else {
+        format!("{}", x)
+    }
+}
+
+fn long_lit(x: i64) -> String {
+    if x == i64::MIN {
+        "INT64_MIN".to_string()
+    } else if x < 0 {
+        format!("(-INT64_C({}))", -x)
+    } else {
+        format!("INT64_C({})", x)
+    }
+}
+
+// the shortest representation which is read back as the same number
+fn double_lit(x: f64) -> String {
+    if x.is_nan() {
+        "(0.0 / 0.0)".to_string()
+    } else if x.is_infinite() {
+        match x > 0.0 {
+            true => "(1.0 / 0.0)".to_string(),
+            false => "(-1.0 / 0.0)".to_string(),
+        }
+    } else {
+        match format!("{:?}", x) {
+            ref s if s.starts_with('-') => format!("({})", s),
+            s => s,
+        }
+    }
+}
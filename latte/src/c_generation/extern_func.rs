@@ -0,0 +1,42 @@
+use ast::{BuiltinFunc, Expr, Ident, Type};
+
+use c_generation::c_type::*;
+use c_generation::context::Context;
+use c_generation::expr::*;
+
+// Extern functions are declared with C types, as in LLVM. Strings are passed as char *,
+// valid only during the call, and returned characters are copied.
+pub fn add_extern_func(f: &BuiltinFunc, ctx: &mut Context) {
+    let args: Vec<String> = f.args.iter().map(|t| extern_type(t, ctx)).collect();
+    let args = match args.is_empty() {
+        true => "void".to_string(),
+        false => args.join(", "),
+    };
+    let func = raw_decl(&extern_type(&f.ret_type, ctx), &f.ident.0);
+    ctx.add_line(format!("{}({});", func, args));
+    ctx.add_extern_func(&f.ident, f.args.clone(), f.ret_type.clone());
+}
+
+pub fn generate_extern_call(ident: &Ident, args: &[Expr], ctx: &mut Context) -> (String, Type) {
+    let mut c_args: Vec<String> = Vec::new();
+    for (arg, t) in args.iter().zip(ctx.get_arg_types(ident)) {
+        let val = generate_init_expr(arg, &t, ctx);
+        match t == Type::TString {
+            true => c_args.push(format!("_str_chars({})", val)),
+            false => c_args.push(val),
+        }
+    }
+    let call = format!("{}({})", ident, c_args.join(", "));
+    match ctx.get_ret_type(ident) {
+        Type::TString => add_call(format!("_copy_str({})", call), &Type::TString, ctx),
+        ret_type => add_call(call, &ret_type, ctx),
+    }
+}
+
+fn extern_type(t: &Type, ctx: &Context) -> String {
+    match *t {
+        Type::TString => "char *".to_string(),
+        Type::TChar => "char".to_string(),
+        _ => c_type(t, ctx),
+    }
+}
@@ -0,0 +1,82 @@
+use ast::{Func, Ident, Type};
+
+use code_generation::has_self_tail_call;
+use mangling::*;
+use static_analysis::has_return::*;
+
+use c_generation::c_type::*;
+use c_generation::context::*;
+use c_generation::generate::*;
+
+impl GenerateCode<()> for Func {
+    fn generate_code(&self, ctx: &mut Context) {
+        ctx.reset();
+        let mut name = ctx.get_func_symbol(&self.ident);
+        ctx.current_func = self.ident.0.clone();
+        if let Some(id) = ctx.static_class {
+            let class = ctx.get_class_data(id).ident.clone();
+            ctx.current_func = format!("{}.{}", class, self.ident);
+            name = match ctx.class {
+                Some(_) => method_symbol(&class, self).to_string(),
+                None => static_method_symbol(&class, self).to_string(),
+            };
+        }
+
+        // variable, its name in C and type
+        let mut params: Vec<(Ident, String, Type)> = Vec::new();
+        if let Some(id) = ctx.class {
+            let self_t = Type::TObject(ctx.get_class_data(id).ident.clone());
+            params.push((Ident("self".to_string()), "self".to_string(), self_t));
+        }
+        for arg in &self.args {
+            let arg_name = ctx.new_local_name(&arg.ident);
+            params.push((arg.ident.clone(), arg_name, arg.t.clone()));
+        }
+        let decls = params.iter().map(|(_, name, t)| c_decl(t, name, ctx)).collect();
+
+        ctx.begin_block(func_header(&self.ret_type, &name, decls, ctx));
+        let is_main = ctx.static_class.is_none() && self.ident.0 == "main";
+        if is_main && ctx.has_globals() {
+            ctx.add_line("init_globals();".to_string());
+        }
+        let body_label = match has_self_tail_call(self) {
+            true => Some("body".to_string()),
+            false => None,
+        };
+        ctx.tail_target = Some(TailTarget {
+            symbol: name.clone(),
+            params: params.iter().map(|(_, name, t)| (name.clone(), t.clone())).collect(),
+            body_label: body_label.clone(),
+        });
+        ctx.in_new_scope(|ctx| {
+            // args are set in the function scope, so that string args
+            // don't leak into the functions generated later
+            for (ident, name, t) in &params {
+                ctx.set_var(ident.clone(), name.clone(), t.clone());
+                if *t == Type::TString {
+                    ctx.add_line(format!("_retain_str({});", name));
+                }
+            }
+            if let Some(ref label) = body_label {
+                ctx.add_line(format!("{}:;", label));
+            }
+            ctx.ret_type = self.ret_type.clone();
+            self.body.generate_code(ctx);
+            if !self.body.has_return() {
+                ctx.release_all_strings();
+            }
+        });
+        ctx.tail_target = None;
+        ctx.end_block();
+        ctx.add_empty_line();
+    }
+}
+
+// All generated functions are static, main of the program is called by the C main.
+pub fn func_header(ret_type: &Type, name: &str, params: Vec<String>, ctx: &Context) -> String {
+    let params = match params.is_empty() {
+        true => "void".to_string(),
+        false => params.join(", "),
+    };
+    format!("static {}({})", c_decl(ret_type, name, ctx), params)
+}
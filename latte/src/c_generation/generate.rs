@@ -0,0 +1,5 @@
+use c_generation::context::*;
+
+pub trait GenerateCode<T> {
+    fn generate_code(&self, ctx: &mut Context) -> T;
+}
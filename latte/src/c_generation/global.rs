@@ -0,0 +1,58 @@
+use ast::*;
+
+use code_generation::default_value;
+
+use c_generation::class_data::ClassId;
+use c_generation::context::Context;
+use c_generation::generate::*;
+
+// Generates init_globals, which is called at the beginning of main.
+// All globals get their default values first, because initializers can call
+// functions which use globals declared later.
+// Static fields are initialized together with globals, in the order of definitions.
+pub fn generate_init(p: &Program, ctx: &mut Context) {
+    let mut globals: Vec<(Option<ClassId>, &Global)> = Vec::new();
+    for def in &p.0 {
+        match *def {
+            Def::DGlobal(ref g) => globals.push((None, g)),
+            Def::DClass(ref c) => {
+                let id = ctx.get_class_id(&c.name);
+                globals.extend(c.static_vars.iter().map(|g| (Some(id), g)));
+            }
+            Def::DFunc(..) |
+            Def::DEnum(..) |
+            Def::DExtern(..) => {}
+        }
+    }
+
+    ctx.reset();
+    ctx.begin_block("static void init_globals(void)".to_string());
+    ctx.in_new_scope(|ctx| {
+        for &(class, g) in &globals {
+            for decl in &g.decls {
+                let (name, t) = match class {
+                    Some(id) => ctx.find_static_field(id, decl.get_ident()).unwrap(),
+                    None => ctx.get_global(decl.get_ident()),
+                };
+                let (val, _) = Expr::ELit(default_value(&g.t)).generate_code(ctx);
+                if t == Type::TString {
+                    ctx.add_line(format!("_retain_str({});", val));
+                }
+                ctx.add_line(format!("{} = {};", name, val));
+            }
+        }
+        for &(class, g) in &globals {
+            ctx.static_class = class;
+            for decl in &g.decls {
+                if let VarDecl::Init(_, ref ident, ref e) = *decl {
+                    let assign = StmtKind::SAssign(FieldGet::Direct(ident.clone()), e.clone());
+                    Stmt::new(assign, 0).generate_code(ctx);
+                }
+            }
+        }
+        ctx.static_class = None;
+        ctx.release_all_strings();
+    });
+    ctx.end_block();
+    ctx.add_empty_line();
+}
@@ -0,0 +1,409 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use std::fs::File;
+
+use ast::*;
+use builtins::*;
+use mangling::*;
+use options::Options;
+use static_analysis::collect_string_lit::*;
+
+mod c_type;
+mod class;
+mod class_data;
+mod collection;
+mod context;
+mod enum_def;
+mod expr;
+mod extern_func;
+mod field_get;
+mod func;
+mod generate;
+mod global;
+mod stmt;
+mod tail_call;
+
+use self::c_type::*;
+use self::class_data::*;
+use self::context::*;
+use self::extern_func::*;
+use self::func::func_header;
+use self::generate::*;
+
+// Types of the runtime (lib/runtime.c) and its functions used by the generated code.
+// Nothing else is included, so extern functions can be declared with any types.
+const PRELUDE: &str = "#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+typedef struct {
+    int32_t ref_count;
+    char *chars;
+    bool is_const;
+} string_t;
+
+typedef struct {
+    int32_t length;
+    void *elems;
+} array_t;
+
+typedef struct {
+    int32_t size;
+    int32_t capacity;
+    int64_t *elems;
+    bool is_str;
+} list_t;
+
+typedef struct map_entry_t {
+    int64_t key;
+    int64_t value;
+    struct map_entry_t *next;
+} map_entry_t;
+
+typedef struct {
+    int32_t size;
+    int32_t buckets_cnt;
+    map_entry_t **buckets;
+    bool str_keys;
+    bool str_values;
+} map_t;
+
+typedef struct {
+    int32_t length;
+    int32_t capacity;
+    char *chars;
+} builder_t;
+
+typedef void (*vfunc_t)(void);
+
+string_t *_alloc_str(void);
+void _retain_str(string_t *s);
+void _release_str(string_t *s);
+string_t *_copy_str(char *chars);
+char *_str_chars(string_t *s);
+void _init_str_arr(array_t *arr);
+string_t *_concatenate(string_t *lhs, string_t *rhs);
+string_t *_concatenate_n(string_t **parts, int32_t n);
+bool _str_eq(string_t *lhs, string_t *rhs);
+int32_t _str_cmp(string_t *lhs, string_t *rhs);
+int32_t _checked_add_i32(int32_t lhs, int32_t rhs);
+int32_t _checked_sub_i32(int32_t lhs, int32_t rhs);
+int32_t _checked_mul_i32(int32_t lhs, int32_t rhs);
+int64_t _checked_add_i64(int64_t lhs, int64_t rhs);
+int64_t _checked_sub_i64(int64_t lhs, int64_t rhs);
+int64_t _checked_mul_i64(int64_t lhs, int64_t rhs);
+int32_t _checked_div_i32(int32_t lhs, int32_t rhs);
+int32_t _checked_mod_i32(int32_t lhs, int32_t rhs);
+int64_t _checked_div_i64(int64_t lhs, int64_t rhs);
+int64_t _checked_mod_i64(int64_t lhs, int64_t rhs);
+int32_t _double_to_i32(double x);
+int64_t _double_to_i64(double x);
+void *_new_object(size_t size);
+array_t *_new_array(int32_t length, size_t elem_size);
+array_t *_new_args(int32_t argc, char **argv);
+int64_t _double_to_slot(double x);
+double _slot_to_double(int64_t slot);
+list_t *_list_new(bool is_str);
+int64_t *_list_at(list_t *l, int32_t idx);
+void _list_push(list_t *l, int64_t v);
+int64_t _list_pop(list_t *l);
+void _list_insert(list_t *l, int32_t idx, int64_t v);
+int64_t _list_remove(list_t *l, int32_t idx);
+map_t *_map_new(bool str_keys, bool str_values);
+void _map_put(map_t *m, int64_t key, int64_t value);
+int64_t _map_get(map_t *m, int64_t key);
+bool _map_contains(map_t *m, int64_t key);
+void _map_remove(map_t *m, int64_t key);
+list_t *_map_keys(map_t *m);
+builder_t *_builder_new(void);
+void _builder_append(builder_t *b, string_t *s);
+void _builder_append_char(builder_t *b, uint8_t c);
+string_t *_builder_to_string(builder_t *b);";
+
+// Generates C code which has the same layout of objects and the same reference
+// counting of strings as the LLVM code. Returns the report of devirtualized calls.
+pub fn gen_c(p: &Program, opts: &Options, out_file: &mut File) -> Result<Vec<String>, io::Error> {
+    let mut ctx = create_context(p, opts);
+
+    ctx.add_comment("string literals".to_string());
+    let mut lits: Vec<String> = collect_string_lit(p).into_iter().collect();
+    lits.sort();
+    for (id, lit) in lits.into_iter().enumerate() {
+        let name = format!("str_{}", id);
+        ctx.add_line(format!("static string_t {} = {{1, \"{}\", true}};", name, escape_str(&lit)));
+        ctx.set_str_const(lit, name);
+    }
+    ctx.add_empty_line();
+
+    for def in &p.0 {
+        match *def {
+            Def::DClass(ref c) => c.generate_code(&mut ctx),
+            Def::DFunc(ref f) => f.generate_code(&mut ctx),
+            Def::DEnum(ref e) => e.generate_code(&mut ctx),
+            Def::DGlobal(..) |
+            Def::DExtern(..) => {}
+        }
+    }
+    if ctx.has_globals() {
+        global::generate_init(p, &mut ctx);
+    }
+    generate_main(p, &mut ctx);
+
+    for line in ctx.get_out() {
+        writeln!(out_file, "{}", line)?;
+    }
+    Ok(ctx.take_devirtualized())
+}
+
+fn create_context(p: &Program, opts: &Options) -> Context {
+    let mut ctx = Context::new(opts);
+    for line in PRELUDE.lines() {
+        ctx.add_line(line.to_string());
+    }
+    ctx.add_empty_line();
+
+    ctx.add_comment("builtin functions".to_string());
+    add_builtins(&mut ctx);
+    ctx.add_empty_line();
+
+    for def in &p.0 {
+        if let Def::DEnum(ref e) = *def {
+            ctx.add_enum(&e.name, &e.variants);
+        }
+    }
+
+    add_classes(p, &mut ctx);
+    add_funcs(p, &mut ctx);
+    add_vtables(p, &mut ctx);
+
+    ctx.add_comment("globals".to_string());
+    add_globals(p, &mut ctx);
+    ctx.add_empty_line();
+
+    ctx.add_comment("extern functions".to_string());
+    for def in &p.0 {
+        if let Def::DExtern(ref f) = *def {
+            add_extern_func(f, &mut ctx);
+        }
+    }
+    ctx.add_empty_line();
+    ctx
+}
+
+fn add_classes(p: &Program, ctx: &mut Context) {
+    let mut classes: Vec<&Class> = Vec::new();
+    for def in &p.0 {
+        if let Def::DClass(ref c) = *def {
+            classes.push(c);
+        }
+    }
+
+    for (id, class) in classes.iter().enumerate() {
+        ctx.add_class_id(&class.name, id);
+    }
+
+    ctx.add_comment("classes".to_string());
+    for class in &classes {
+        let id = ctx.get_class_id(&class.name);
+        ctx.add_line(format!("struct class_{};", id));
+        let mut class_data = ClassData::new(&class.name);
+        if let Some(ref super_name) = class.superclass {
+            class_data.set_super(ctx.get_class_id(super_name));
+        }
+        for v in &class.vars {
+            class_data.add_field(&v.ident, &v.t);
+        }
+        ctx.add_class(id, class_data);
+    }
+    ctx.add_empty_line();
+
+    // superclasses are defined before their subclasses, which contain them
+    let mut defined: HashSet<ClassId> = HashSet::new();
+    for id in 0..classes.len() {
+        add_class_def(id, &mut defined, ctx);
+    }
+
+    let mut class_map: HashMap<Ident, &Class> = HashMap::new();
+    for class in &classes {
+        class_map.insert(class.name.clone(), class);
+    }
+    for class in &classes {
+        let vtable = get_vtable(class, &class_map, ctx);
+        let id = ctx.get_class_id(&class.name);
+        ctx.set_vtable(id, vtable);
+    }
+}
+
+fn add_class_def(id: ClassId, defined: &mut HashSet<ClassId>, ctx: &mut Context) {
+    if defined.contains(&id) {
+        return;
+    }
+    let cdata = ctx.get_class_data(id).clone();
+    if let Some(super_id) = cdata.super_id {
+        add_class_def(super_id, defined, ctx);
+    }
+    defined.insert(id);
+
+    ctx.add_comment(format!("class {}", cdata.ident));
+    ctx.begin_block(format!("struct class_{}", id));
+    match cdata.super_id {
+        Some(super_id) => ctx.add_line(format!("struct class_{} super;", super_id)),
+        None => ctx.add_line("vfunc_t *vtable;".to_string()),
+    }
+    for (field, t) in cdata.fields {
+        let decl = c_decl(&t, &format!("m_{}", field), ctx);
+        ctx.add_line(format!("{};", decl));
+    }
+    ctx.end_decl_block();
+    ctx.add_empty_line();
+}
+
+fn get_vtable(c: &Class, classes: &HashMap<Ident, &Class>, ctx: &Context) -> VTable {
+    let mut vtable = VTable::new();
+    if let Some(ref super_ident) = c.superclass {
+        let superclass = classes.get(super_ident).unwrap();
+        vtable = get_vtable(superclass, classes, ctx);
+    }
+
+    let id = ctx.get_class_id(&c.name);
+    for f in &c.methods {
+        let entry = VTableEntry {
+            real_ident: method_symbol(&c.name, f),
+            class_id: id,
+            ret_type: f.ret_type.clone(),
+            arg_types: f.args.iter().map(|v| v.t.clone()).collect(),
+        };
+        vtable.set_func(&f.ident, entry);
+    }
+
+    vtable
+}
+
+fn add_vtables(p: &Program, ctx: &mut Context) {
+    ctx.add_comment("vtables".to_string());
+    for def in &p.0 {
+        if let Def::DClass(ref c) = *def {
+            let id = ctx.get_class_id(&c.name);
+            let vtable = ctx.get_class_data(id).vtable.clone();
+            if vtable.fs.is_empty() {
+                continue;
+            }
+            ctx.begin_block(format!("static vfunc_t vtable_{}[] =", id));
+            for f in &vtable.fs {
+                ctx.add_line(format!("(vfunc_t){},", f.real_ident));
+            }
+            ctx.end_decl_block();
+        }
+    }
+    ctx.add_empty_line();
+}
+
+// Registers functions and methods and declares them, together with the
+// constructors of classes and names of enums.
+fn add_funcs(p: &Program, ctx: &mut Context) {
+    ctx.add_comment("functions".to_string());
+    for def in &p.0 {
+        match *def {
+            Def::DFunc(ref f) => {
+                let arg_types = f.args.iter().map(|arg| arg.t.clone()).collect();
+                ctx.add_func(&f.ident, arg_types, f.ret_type.clone());
+                let name = ctx.get_func_symbol(&f.ident);
+                add_prototype(&name, f, None, ctx);
+            }
+            Def::DClass(ref c) => {
+                let id = ctx.get_class_id(&c.name);
+                let new = new_symbol(&c.name);
+                ctx.add_line(format!("static struct class_{} *{}(void);", id, new));
+                let init = init_symbol(&c.name);
+                ctx.add_line(format!("static void {}(struct class_{} *obj);", init, id));
+                for f in &c.methods {
+                    let obj_t = Type::TObject(c.name.clone());
+                    let mut arg_types: Vec<Type> = f.args.iter().map(|arg| arg.t.clone()).collect();
+                    arg_types.insert(0, obj_t.clone());
+                    let ident = Ident(method_symbol(&c.name, f));
+                    ctx.add_func(&ident, arg_types, f.ret_type.clone());
+                    add_prototype(&ident.0, f, Some(&obj_t), ctx);
+                }
+                for f in &c.static_methods {
+                    let arg_types = f.args.iter().map(|arg| arg.t.clone()).collect();
+                    let ident = Ident(static_method_symbol(&c.name, f));
+                    ctx.add_func(&ident, arg_types, f.ret_type.clone());
+                    ctx.add_static_method(id, &f.ident, &ident);
+                    add_prototype(&ident.0, f, None, ctx);
+                }
+            }
+            Def::DEnum(ref e) => {
+                let name = enum_name_symbol(&e.name);
+                ctx.add_line(format!("static string_t *{}(int32_t tag);", name));
+            }
+            Def::DGlobal(..) |
+            Def::DExtern(..) => {}
+        }
+    }
+    ctx.add_empty_line();
+}
+
+fn add_prototype(name: &str, f: &Func, self_t: Option<&Type>, ctx: &mut Context) {
+    let arg_types = self_t.into_iter().chain(f.args.iter().map(|arg| &arg.t));
+    let params = arg_types.map(|t| c_type(t, ctx)).collect();
+    let header = func_header(&f.ret_type, name, params, ctx);
+    ctx.add_line(format!("{};", header));
+}
+
+fn add_globals(p: &Program, ctx: &mut Context) {
+    for def in &p.0 {
+        match *def {
+            Def::DGlobal(ref g) => {
+                for decl in &g.decls {
+                    ctx.add_global(decl.get_ident(), &g.t);
+                }
+            }
+            Def::DClass(ref c) => {
+                let id = ctx.get_class_id(&c.name);
+                for g in &c.static_vars {
+                    for decl in &g.decls {
+                        ctx.add_static_field(id, decl.get_ident(), &g.t);
+                    }
+                }
+            }
+            Def::DFunc(..) |
+            Def::DEnum(..) |
+            Def::DExtern(..) => {}
+        }
+    }
+    if ctx.has_globals() {
+        ctx.add_line("static void init_globals(void);".to_string());
+    }
+}
+
+fn add_builtins(ctx: &mut Context) {
+    for f in get_builtin_functions() {
+        let params: Vec<String> = f.args.iter().map(|t| c_type(t, ctx)).collect();
+        let params = match params.is_empty() {
+            true => "void".to_string(),
+            false => params.join(", "),
+        };
+        let header = c_decl(&f.ret_type, &f.ident.0, ctx);
+        ctx.add_line(format!("{}({});", header, params));
+        ctx.add_builtin_func(&f.ident, f.args.clone(), f.ret_type.clone());
+    }
+}
+
+// main of the program may take its arguments as an array of strings
+fn generate_main(p: &Program, ctx: &mut Context) {
+    let takes_args = p.0.iter().any(|def| match *def {
+        Def::DFunc(ref f) => f.ident.0 == "main" && !f.args.is_empty(),
+        _ => false,
+    });
+    let main_name = ctx.get_func_symbol(&Ident("main".to_string()));
+    ctx.begin_block("int main(int argc, char **argv)".to_string());
+    match takes_args {
+        true => ctx.add_line(format!("return {}(_new_args(argc, argv));", main_name)),
+        false => {
+            ctx.add_line("(void)argc;".to_string());
+            ctx.add_line("(void)argv;".to_string());
+            ctx.add_line(format!("return {}();", main_name));
+        }
+    }
+    ctx.end_block();
+}
@@ -0,0 +1,293 @@
+use ast::*;
+
+use code_generation::default_value;
+use static_analysis::has_return::*;
+
+use c_generation::c_type::*;
+use c_generation::collection::*;
+use c_generation::context::*;
+use c_generation::expr::*;
+use c_generation::field_get::*;
+use c_generation::generate::*;
+use c_generation::tail_call::*;
+
+impl GenerateCode<()> for Vec<Stmt> {
+    fn generate_code(&self, ctx: &mut Context) {
+        for stmt in self {
+            stmt.generate_code(ctx);
+            if stmt.has_return() {
+                break;
+            }
+        }
+    }
+}
+
+impl GenerateCode<()> for Stmt {
+    fn generate_code(&self, ctx: &mut Context) {
+        match self.kind {
+            StmtKind::SEmpty => {}
+            StmtKind::SBlock(ref stmts) => {
+                ctx.in_new_block(String::new(), |ctx| {
+                    stmts.generate_code(ctx);
+                    if !stmts.has_return() {
+                        ctx.release_local_strings();
+                    }
+                })
+            }
+            StmtKind::SDecl(_, ref decls) => {
+                decls.generate_code(ctx);
+            }
+            StmtKind::SAssign(ref ident, ref e) => {
+                let (lvalue, t) = ident.generate_code(ctx);
+                let val = generate_init_expr(e, &t, ctx);
+                if t == Type::TString {
+                    let old_val = ctx.add_tmp(&t, lvalue.clone());
+                    ctx.add_line(format!("_retain_str({});", val));
+                    ctx.add_line(format!("_release_str({});", old_val));
+                }
+                ctx.add_line(format!("{} = {};", lvalue, val));
+            }
+            StmtKind::SInc(ref ident) => {
+                let (lvalue, t) = ident.generate_code(ctx);
+                let val = generate_arith_op(&t, &lvalue, Operator::OpAdd, "1", ctx);
+                ctx.add_line(format!("{} = {};", lvalue, val));
+            }
+            StmtKind::SDec(ref ident) => {
+                let (lvalue, t) = ident.generate_code(ctx);
+                let val = generate_arith_op(&t, &lvalue, Operator::OpSub, "1", ctx);
+                ctx.add_line(format!("{} = {};", lvalue, val));
+            }
+            StmtKind::SReturnE(Expr::ECall(ref ident, ref args)) => {
+                generate_tail_call(ident, args, ctx);
+            }
+            StmtKind::SReturnE(ref e) => {
+                let t = ctx.ret_type.clone();
+                let val = generate_init_expr(e, &t, ctx);
+                if t == Type::TString {
+                    ctx.add_line(format!("_retain_str({});", val));
+                }
+                ctx.release_all_strings();
+                ctx.add_line(format!("return {};", val));
+            }
+            StmtKind::SReturn => {
+                ctx.release_all_strings();
+                ctx.add_line("return;".to_string());
+            }
+            StmtKind::SExpr(ref e) => {
+                e.generate_code(ctx);
+            }
+            StmtKind::SIf(ref cond, ref s) => {
+                let (cond_val, _) = cond.generate_code(ctx);
+                ctx.in_new_block(format!("if ({})", cond_val), |ctx| {
+                    s.generate_code(ctx);
+                    if !s.has_return() {
+                        ctx.release_local_strings();
+                    }
+                });
+            }
+            StmtKind::SIfElse(ref cond, ref if_true, ref if_false) => {
+                let (cond_val, _) = cond.generate_code(ctx);
+                ctx.begin_block(format!("if ({})", cond_val));
+                ctx.in_new_scope(|ctx| {
+                    if_true.generate_code(ctx);
+                    if !if_true.has_return() {
+                        ctx.release_local_strings();
+                    }
+                });
+                ctx.next_block("else".to_string());
+                ctx.in_new_scope(|ctx| {
+                    if_false.generate_code(ctx);
+                    if !if_false.has_return() {
+                        ctx.release_local_strings();
+                    }
+                });
+                ctx.end_block();
+            }
+            StmtKind::SWhile(ref cond, ref s) => {
+                // the condition is evaluated at the beginning of each iteration
+                ctx.in_new_block("while (true)".to_string(), |ctx| {
+                    let cond_val = ctx.in_new_scope(|ctx| {
+                        let (cond_val, _) = cond.generate_code(ctx);
+                        ctx.release_local_strings();
+                        cond_val
+                    });
+                    ctx.add_line(format!("if (!{}) break;", cond_val));
+                    ctx.in_new_scope(|ctx| {
+                        s.generate_code(ctx);
+                        if !s.has_return() {
+                            ctx.release_local_strings();
+                        }
+                    });
+                });
+            }
+            StmtKind::SSwitch(ref e, ref cases, ref default) => {
+                let (val, t) = e.generate_code(ctx);
+                match jump_table(cases, ctx) {
+                    Some(ref table) if t != Type::TString => {
+                        ctx.begin_block(format!("switch ({})", val));
+                        for (idx, case) in cases.iter().enumerate() {
+                            let labels: Vec<String> = table.iter()
+                                .filter(|&&(_, case)| case == idx)
+                                .map(|&(v, _)| format!("case {}:", v))
+                                .collect();
+                            // all labels of the case are taken by previous cases
+                            if labels.is_empty() {
+                                continue;
+                            }
+                            generate_case(labels, &case.body, ctx);
+                        }
+                        let empty = Vec::new();
+                        let default = default.as_ref().unwrap_or(&empty);
+                        generate_case(vec![format!("default:")], default, ctx);
+                        ctx.end_block();
+                    }
+                    _ => {
+                        // chain of comparisons, in the order of labels, which selects the case;
+                        // labels are evaluated first, so that their strings can be released
+                        // on every path
+                        let mut label_vals: Vec<(String, usize)> = Vec::new();
+                        for (idx, case) in cases.iter().enumerate() {
+                            for label in &case.labels {
+                                label_vals.push((label.generate_code(ctx).0, idx));
+                            }
+                        }
+                        let selected = ctx.add_tmp(&Type::TInt, "-1".to_string());
+                        for (i, (label_val, idx)) in label_vals.into_iter().enumerate() {
+                            let is_eq = match t == Type::TString {
+                                true => format!("_str_eq({}, {})", val, label_val),
+                                false => format!("{} == {}", val, label_val),
+                            };
+                            let else_str = if i > 0 { "else " } else { "" };
+                            ctx.add_line(format!("{}if ({}) {} = {};",
+                                                 else_str,
+                                                 is_eq,
+                                                 selected,
+                                                 idx));
+                        }
+                        ctx.begin_block(format!("switch ({})", selected));
+                        for (idx, case) in cases.iter().enumerate() {
+                            generate_case(vec![format!("case {}:", idx)], &case.body, ctx);
+                        }
+                        let empty = Vec::new();
+                        let default = default.as_ref().unwrap_or(&empty);
+                        generate_case(vec![format!("default:")], default, ctx);
+                        ctx.end_block();
+                    }
+                }
+            }
+            StmtKind::SFor(ref t, ref ident, ref arr, ref stmt) => {
+                let (arr, arr_t) = match *arr {
+                    Expr::EArrayLit(..) => {
+                        let arr_t = Type::TArray(Box::new(t.clone()));
+                        (generate_init_expr(arr, &arr_t, ctx), arr_t)
+                    }
+                    _ => arr.generate_code(ctx),
+                };
+                // Maps are iterated over a snapshot of their keys.
+                let (arr, arr_t) = match arr_t {
+                    Type::TMap(ref key_t, _) => generate_map_keys(&arr, key_t, ctx),
+                    _ => (arr, arr_t),
+                };
+                let size = match arr_t {
+                    Type::TList(..) => format!("{}->size", arr),
+                    _ => format!("{}->length", arr),
+                };
+                let size = ctx.add_tmp(&Type::TInt, size);
+                let idx = ctx.next_tmp();
+
+                let header = format!("for (int32_t {0} = 0; {0} < {1}; {0}++)", idx, size);
+                ctx.in_new_block(header, |ctx| {
+                    let (elem, elem_t) = match arr_t {
+                        Type::TList(ref elem_t) => {
+                            (format!("*({})_list_at({}, {})", ptr_to(elem_t, ctx), arr, idx),
+                             *elem_t.clone())
+                        }
+                        _ => {
+                            let elem_t = arr_elem_t(&arr_t);
+                            (format!("(({}){}->elems)[{}]", ptr_to(&elem_t, ctx), arr, idx),
+                             elem_t)
+                        }
+                    };
+                    let name = ctx.new_local_name(ident);
+                    let decl = c_decl(&elem_t, &name, ctx);
+                    ctx.add_line(format!("{} = {};", decl, elem));
+                    ctx.set_var(ident.clone(), name.clone(), elem_t.clone());
+
+                    if elem_t == Type::TString {
+                        ctx.add_line(format!("_retain_str({});", name));
+                    }
+
+                    stmt.generate_code(ctx);
+                    if !stmt.has_return() {
+                        ctx.release_local_strings();
+                    }
+                });
+            }
+        }
+    }
+}
+
+// Cases don't fall through, each one is a block.
+fn generate_case(labels: Vec<String>, body: &Vec<Stmt>, ctx: &mut Context) {
+    for label in labels {
+        ctx.add_line(label);
+    }
+    ctx.in_new_block(String::new(), |ctx| {
+        body.generate_code(ctx);
+        if !body.has_return() {
+            ctx.release_local_strings();
+            ctx.add_line("break;".to_string());
+        }
+    });
+}
+
+impl GenerateCode<()> for Vec<VarDecl> {
+    fn generate_code(&self, ctx: &mut Context) {
+        for var_decl in self {
+            var_decl.generate_code(ctx);
+        }
+    }
+}
+
+impl GenerateCode<()> for VarDecl {
+    fn generate_code(&self, ctx: &mut Context) {
+        let (t, ident, val) = match *self {
+            VarDecl::Init(ref t, ref ident, ref e) => (t, ident, generate_init_expr(e, t, ctx)),
+            VarDecl::NoInit(ref t, ref ident) => {
+                let (val, _) = Expr::ELit(default_value(t)).generate_code(ctx);
+                (t, ident, val)
+            }
+        };
+        if *t == Type::TString {
+            ctx.add_line(format!("_retain_str({});", val));
+        }
+        let name = ctx.new_local_name(ident);
+        let decl = c_decl(t, &name, ctx);
+        ctx.add_line(format!("{} = {};", decl, val));
+        ctx.set_var(ident.clone(), name, t.clone());
+    }
+}
+
+// Values of the labels with indexes of their cases, if all of them are known integers.
+// The first occurrence of a value wins.
+fn jump_table(cases: &[Case], ctx: &Context) -> Option<Vec<(i32, usize)>> {
+    let mut table: Vec<(i32, usize)> = Vec::new();
+    for (idx, case) in cases.iter().enumerate() {
+        for label in &case.labels {
+            let v = label_value(label, ctx)?;
+            if table.iter().all(|&(other, _)| other != v) {
+                table.push((v, idx));
+            }
+        }
+    }
+    Some(table)
+}
+
+fn label_value(label: &Expr, ctx: &Context) -> Option<i32> {
+    match *label {
+        Expr::ELit(Lit::LInt(v)) => Some(v),
+        Expr::ENeg(ref e) => label_value(e, ctx).map(|v| v.wrapping_neg()),
+        Expr::EVar(ref field) => as_enum_variant(field, ctx).map(|(tag, _)| tag),
+        _ => None,
+    }
+}
@@ -0,0 +1,50 @@
+use ast::*;
+
+use c_generation::context::*;
+use c_generation::expr::*;
+
+// Generates `return f(args)`, as in LLVM. Self-recursive calls set the params and
+// jump to the body of the function, the strings of the caller are released before
+// the jump. Other calls return the result of a normal call, the function owns it
+// if it's a string.
+pub fn generate_tail_call(ident: &FieldGet, args: &[Expr], ctx: &mut Context) {
+    let call = match prepare_call(ident, args, ctx) {
+        PreparedCall::Generated(val, t) => return generate_return(&val, &t, ctx),
+        PreparedCall::Call(call) => call,
+    };
+
+    let target = ctx.tail_target.clone().unwrap();
+    let is_self_call = call.func == target.symbol;
+    if let (true, Some(body_label)) = (is_self_call, target.body_label) {
+        ctx.add_comment("tail recursion".to_string());
+        // args are copied before the params are set, they can refer to the params
+        let mut vals: Vec<String> = Vec::new();
+        for (arg, (_, t)) in call.args.iter().zip(&target.params) {
+            if *t == Type::TString {
+                ctx.add_line(format!("_retain_str({});", arg));
+            }
+            vals.push(ctx.add_tmp(t, arg.clone()));
+        }
+        ctx.release_all_strings();
+        for ((param, _), val) in target.params.iter().zip(vals) {
+            ctx.add_line(format!("{} = {};", param, val));
+        }
+        ctx.add_line(format!("goto {};", body_label));
+    } else {
+        let call_str = format!("{}({})", call.func, call.args.join(", "));
+        let (val, t) = add_call(call_str, &call.ret_type, ctx);
+        generate_return(&val, &t, ctx);
+    }
+}
+
+fn generate_return(val: &str, t: &Type, ctx: &mut Context) {
+    ctx.release_all_strings();
+    match *t {
+        Type::TVoid => ctx.add_line("return;".to_string()),
+        _ => {
+            let ret_type = ctx.ret_type.clone();
+            let val = convert(val, t, &ret_type, ctx);
+            ctx.add_line(format!("return {};", val));
+        }
+    }
+}
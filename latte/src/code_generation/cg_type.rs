@@ -3,21 +3,30 @@ use std::fmt;
 use ast::Type;
 
 pub type ClassId = usize;
+pub type CollectionId = usize; // index in the list or map types of the context
+pub type EnumId = usize;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct CGType {
-    is_arr: bool,
+    arr_depth: usize, // 0 for non-array types
     t: RawType,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RawType {
     TInt,
+    TLong,
+    TDouble,
+    TChar,
     TBool,
     TVoid,
     TString,
+    TBuilder,
     TRawPtr,
     TObject(ClassId),
+    TList(CollectionId),
+    TMap(CollectionId),
+    TEnum(EnumId), // represented by the index of the variant
     TNull,
 }
 
@@ -32,6 +41,18 @@ impl CGType {
         Self::new(RawType::TInt)
     }
 
+    pub fn long_t() -> CGType {
+        Self::new(RawType::TLong)
+    }
+
+    pub fn double_t() -> CGType {
+        Self::new(RawType::TDouble)
+    }
+
+    pub fn char_t() -> CGType {
+        Self::new(RawType::TChar)
+    }
+
     pub fn bool_t() -> CGType {
         Self::new(RawType::TBool)
     }
@@ -44,6 +65,10 @@ impl CGType {
         Self::new(RawType::TString)
     }
 
+    pub fn builder_t() -> CGType {
+        Self::new(RawType::TBuilder)
+    }
+
     pub fn ptr_t() -> CGType {
         Self::new(RawType::TRawPtr)
     }
@@ -56,16 +81,25 @@ impl CGType {
         Self::new(RawType::TObject(id))
     }
 
+    pub fn list_t(id: CollectionId) -> CGType {
+        Self::new(RawType::TList(id))
+    }
+
+    pub fn map_t(id: CollectionId) -> CGType {
+        Self::new(RawType::TMap(id))
+    }
+
+    pub fn enum_t(id: EnumId) -> CGType {
+        Self::new(RawType::TEnum(id))
+    }
+
     pub fn arr_t(t: RawType) -> CGType {
-        CGType {
-            is_arr: true,
-            t: t,
-        }
+        Self::new(t).arr_of()
     }
 
     fn new(t: RawType) -> CGType {
         CGType {
-            is_arr: false,
+            arr_depth: 0,
             t: t,
         }
     }
@@ -74,8 +108,14 @@ impl CGType {
         self.t
     }
 
+    pub fn arr_of(mut self) -> CGType {
+        self.arr_depth += 1;
+        self
+    }
+
     pub fn arr_elem_t(mut self) -> CGType {
-        self.is_arr = false;
+        assert!(self.arr_depth > 0);
+        self.arr_depth -= 1;
         self
     }
 
@@ -86,13 +126,55 @@ impl CGType {
         }
     }
 
+    pub fn get_collection_id(self) -> CollectionId {
+        match self.t {
+            RawType::TList(id) | RawType::TMap(id) if !self.is_arr() => id,
+            _ => panic!(),
+        }
+    }
+
+    pub fn get_enum_id(self) -> EnumId {
+        match self.t {
+            RawType::TEnum(id) if !self.is_arr() => id,
+            _ => panic!(),
+        }
+    }
+
+    pub fn is_list(self) -> bool {
+        if let RawType::TList(_) = self.t {
+            !self.is_arr()
+        } else {
+            false
+        }
+    }
+
+    pub fn is_map(self) -> bool {
+        if let RawType::TMap(_) = self.t {
+            !self.is_arr()
+        } else {
+            false
+        }
+    }
+
+    pub fn is_builder(self) -> bool {
+        self.t == RawType::TBuilder && !self.is_arr()
+    }
+
+    pub fn is_enum(self) -> bool {
+        if let RawType::TEnum(_) = self.t {
+            !self.is_arr()
+        } else {
+            false
+        }
+    }
+
     pub fn is_arr(self) -> bool {
-        self.is_arr
+        self.arr_depth > 0
     }
 
     pub fn is_obj(self) -> bool {
         if let RawType::TObject(_) = self.t {
-            true
+            !self.is_arr()
         } else {
             false
         }
@@ -100,22 +182,26 @@ impl CGType {
 
     pub fn from(t: &Type) -> CGType {
         match *t {
-            Type::TArray(ref t) => Self::arr_t(RawType::from(t)),
+            Type::TArray(ref t) => Self::from(t).arr_of(),
             _ => Self::new(RawType::from(t)),
         }
     }
 
+    pub fn in_arr_type(&self) -> String {
+        format!("{}*", self.arr_elem_t().user_type())
+    }
+
     pub fn user_type(&self) -> String {
-        if self.is_arr {
-            format!("{{ i32, {} }}*", self.t.in_arr_type())
+        if self.is_arr() {
+            format!("{}*", self.native_type())
         } else {
             format!("{}", self.t.user_type())
         }
     }
 
     pub fn native_type(&self) -> String {
-        if self.is_arr {
-            format!("{{ i32, {} }}", self.t.in_arr_type())
+        if self.is_arr() {
+            format!("{{ i32, {} }}", self.in_arr_type())
         } else {
             format!("{}", self.t.native_type())
         }
@@ -126,33 +212,43 @@ impl RawType {
     fn from(t: &Type) -> RawType {
         match *t {
             Type::TInt => RawType::TInt,
+            Type::TLong => RawType::TLong,
+            Type::TDouble => RawType::TDouble,
+            Type::TChar => RawType::TChar,
             Type::TBool => RawType::TBool,
             Type::TString => RawType::TString,
+            Type::TStringBuilder => RawType::TBuilder,
             Type::TVoid => RawType::TVoid,
             _ => unreachable!(),
         }
     }
 
-    pub fn in_arr_type(self) -> String {
-        format!("{}*", self.user_type())
-    }
-
     pub fn user_type(&self) -> String {
         match *self {
             RawType::TString |
-            RawType::TObject(_) => format!("{}*", self.native_type()),
+            RawType::TBuilder |
+            RawType::TObject(_) |
+            RawType::TList(_) |
+            RawType::TMap(_) => format!("{}*", self.native_type()),
             _ => format!("{}", self.native_type()),
         }
     }
 
     pub fn native_type(&self) -> String {
         match *self {
-            RawType::TInt => format!("i32"),
+            RawType::TInt |
+            RawType::TEnum(_) => "i32".to_string(),
+            RawType::TLong => "i64".to_string(),
+            RawType::TDouble => "double".to_string(),
+            RawType::TChar => "i8".to_string(),
             RawType::TBool => format!("i1"),
             RawType::TString => format!("%string_t"), // ref_count, char*, is_const
+            RawType::TBuilder => "%builder_t".to_string(),
             RawType::TVoid => format!("void"),
             RawType::TRawPtr => format!("i8*"),
             RawType::TObject(x) => format!("%class_{}", x),
+            RawType::TList(_) => "%list_t".to_string(),
+            RawType::TMap(_) => "%map_t".to_string(),
             RawType::TNull => panic!("null is not a valid type"),
         }
     }
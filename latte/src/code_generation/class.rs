@@ -1,4 +1,5 @@
 use ast::Class;
+use mangling::*;
 
 use code_generation::generate::*;
 use code_generation::context::Context;
@@ -14,19 +15,25 @@ impl GenerateCode<()> for Class {
             ctx.cg.reset();
 
             ctx.class = Some(id);
+            ctx.static_class = Some(id);
             for m in &self.methods {
                 m.generate_code(ctx);
             }
             ctx.class = None;
+            for m in &self.static_methods {
+                m.generate_code(ctx);
+            }
+            ctx.static_class = None;
         });
     }
 }
 
 fn generate_new(id: ClassId, ctx: &mut Context) {
     let t = CGType::obj_t(id);
-    ctx.cg.add_func_begin(t, &format!("._new_{}", id), &vec![]);
+    let class = ctx.get_class_data(id).ident.clone();
+    ctx.cg.add_func_begin(t, &new_symbol(&class), &vec![]);
     let obj = ctx.cg.new_object(t);
-    ctx.cg.add_call(CGType::void_t(), format!("@._init_{}", id), &vec![(obj, t)]);
+    ctx.cg.add_call(CGType::void_t(), format!("@{}", init_symbol(&class)), &vec![(obj, t)]);
     ctx.cg.add_ret(t, obj);
     ctx.cg.add_func_end(t);
 }
@@ -34,14 +41,14 @@ fn generate_new(id: ClassId, ctx: &mut Context) {
 fn generate_init(id: ClassId, ctx: &mut Context) {
     let t = CGType::obj_t(id);
     let ret_type = CGType::void_t();
-    let (obj_addr, _) = ctx.cg.add_func_begin(ret_type, &format!("._init_{}", id), &vec![t])[0];
+    let symbol = init_symbol(&ctx.get_class_data(id).ident);
+    let (obj_addr, _) = ctx.cg.add_func_begin(ret_type, &symbol, &vec![t])[0];
     let obj = ctx.cg.add_load(obj_addr, t);
     if let Some(super_id) = ctx.get_class_data(id).super_id {
         let super_t = CGType::obj_t(super_id);
         let super_obj = ctx.cg.bitcast_object(obj, t, super_t);
-        ctx.cg.add_call(ret_type,
-                        format!("@._init_{}", super_id),
-                        &vec![(super_obj, super_t)]);
+        let super_init = init_symbol(&ctx.get_class_data(super_id).ident);
+        ctx.cg.add_call(ret_type, format!("@{}", super_init), &vec![(super_obj, super_t)]);
     }
 
     {
@@ -85,16 +92,22 @@ fn init_vars(obj: Val, id: ClassId, ctx: &mut Context) {
 
     for field in fields {
         let field_t = ctx.get_class_data(id).get_field_type(&field);
-        if field_t.is_arr() || field_t == str_t {
+        if field_t == str_t {
             continue;
         }
 
         let field_id = ctx.get_class_data(id).get_field_id(&field);
         let dst_addr = ctx.cg.get_field_addr(obj, t, field_id);
-        let val = if field_t.is_obj() {
+        let val = if field_t.is_obj() || field_t.is_arr() || field_t.is_list() ||
+                     field_t.is_map() || field_t.is_builder() {
             Val::Null
-        } else if field_t == CGType::int_t() {
+        } else if field_t == CGType::int_t() || field_t == CGType::char_t() ||
+                  field_t.is_enum() {
             Val::Int(0)
+        } else if field_t == CGType::long_t() {
+            Val::Long(0)
+        } else if field_t == CGType::double_t() {
+            Val::Double(0.0)
         } else if field_t == CGType::bool_t() {
             Val::Int(0)
         } else {
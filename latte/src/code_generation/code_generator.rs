@@ -1,8 +1,10 @@
 use std::fmt;
+use std::path::Path;
 
 use ast::Operator;
 
 use code_generation::cg_type::*;
+use code_generation::debug_info::*;
 use code_generation::utils::*;
 
 #[derive(Debug)]
@@ -12,6 +14,8 @@ pub struct CodeGenerator {
     last_label: i32,
     last_str_const: i32,
     current_label: Label,
+    alloca_pos: usize, // end of allocas in the entry block of the current function
+    debug_info: Option<DebugInfo>,
 }
 
 impl CodeGenerator {
@@ -22,15 +26,38 @@ impl CodeGenerator {
             last_label: 0,
             last_str_const: 0,
             current_label: Label(-1),
+            alloca_pos: 0,
+            debug_info: None,
         };
 
         cg.add_line_no_indent(format!("%string_t = type {{ i32, i8*, i1 }}"));
+        // size, capacity, elements, has string elements
+        cg.add_line_no_indent("%list_t = type { i32, i32, i64*, i1 }".to_string());
+        // size, number of buckets, buckets, has string keys, has string values
+        cg.add_line_no_indent("%map_t = type { i32, i32, %map_entry_t**, i1, i1 }".to_string());
+        cg.add_line_no_indent("%map_entry_t = type opaque".to_string());
+        // length, capacity, characters
+        cg.add_line_no_indent("%builder_t = type { i32, i32, i8* }".to_string());
         cg.add_empty_line();
 
         cg.add_comment(format!("internal functions"));
         for (ret_type, name, args) in Self::internal_functions() {
             cg.add_func_declare(ret_type, &name, &args);
         }
+        for (ret_type, name, args) in Self::collection_functions() {
+            cg.add_line_no_indent(format!("declare {} @{}({})", ret_type, name, args));
+        }
+        cg.add_line_no_indent("declare void @._check_overflow(i1)".to_string());
+        for t in &[CGType::int_t(), CGType::long_t()] {
+            cg.add_line_no_indent(format!("declare {0} @llvm.fptosi.sat.{0}.f64(double)", t));
+        }
+        for t in &[CGType::int_t(), CGType::long_t()] {
+            for op in &[Operator::OpAdd, Operator::OpSub, Operator::OpMul] {
+                cg.add_line_no_indent(format!("declare {{ {0}, i1 }} @{1}({0}, {0})",
+                                              t,
+                                              Self::overflow_intrinsic(*t, *op)));
+            }
+        }
         cg.add_empty_line();
         cg
     }
@@ -41,8 +68,37 @@ impl CodeGenerator {
             (CGType::str_t(), format!("._alloc_str"), vec![]),
             (CGType::void_t(), format!("._retain_str"), vec![CGType::str_t()]),
             (CGType::void_t(), format!("._release_str"), vec![CGType::str_t()]),
+            (CGType::bool_t(), format!("._str_eq"), vec![CGType::str_t(), CGType::str_t()]),
+            (CGType::int_t(), format!("._str_cmp"), vec![CGType::str_t(), CGType::str_t()]),
             (CGType::void_t(), format!("._init_str_arr"), vec![CGType::arr_t(RawType::TString)]),
             (CGType::ptr_t(), format!("malloc"), vec![CGType::int_t()]),
+            (CGType::ptr_t(), format!("calloc"), vec![CGType::int_t(), CGType::int_t()]),
+        ]
+    }
+
+    // collections, elements are passed as i64 slots, string builders and the args of main
+    fn collection_functions() -> Vec<(&'static str, &'static str, &'static str)> {
+        vec![
+            ("%list_t*", "._list_new", "i1"),
+            ("void", "._list_push", "%list_t*, i64"),
+            ("i64", "._list_pop", "%list_t*"),
+            ("void", "._list_insert", "%list_t*, i32, i64"),
+            ("i64", "._list_remove", "%list_t*, i32"),
+            ("i64*", "._list_at", "%list_t*, i32"),
+            ("%map_t*", "._map_new", "i1, i1"),
+            ("void", "._map_put", "%map_t*, i64, i64"),
+            ("i64", "._map_get", "%map_t*, i64"),
+            ("i1", "._map_contains", "%map_t*, i64"),
+            ("void", "._map_remove", "%map_t*, i64"),
+            ("%list_t*", "._map_keys", "%map_t*"),
+            ("%builder_t*", "._builder_new", ""),
+            ("void", "._builder_append", "%builder_t*, %string_t*"),
+            ("void", "._builder_append_char", "%builder_t*, i8"),
+            ("%string_t*", "._builder_to_string", "%builder_t*"),
+            ("%string_t*", "._concatenate_n", "%string_t**, i32"),
+            ("{ i32, %string_t** }*", "._new_args", "i32, i8**"),
+            ("i8*", "._str_chars", "%string_t*"),
+            ("%string_t*", "._copy_str", "i8*"),
         ]
     }
 
@@ -55,6 +111,54 @@ impl CodeGenerator {
         &self.out
     }
 
+    // debug information, instructions of functions begun with begin_debug_func get
+    // their locations
+    pub fn enable_debug_info(&mut self, input: &Path) {
+        self.debug_info = Some(DebugInfo::new(input));
+        let declare = "declare void @llvm.dbg.declare(metadata, metadata, metadata)";
+        self.add_line_no_indent(declare.to_string());
+        self.add_empty_line();
+    }
+
+    pub fn begin_debug_func(&mut self, name: &str, symbol: &str, line: i32) {
+        if let Some(ref mut info) = self.debug_info {
+            info.begin_func(name, symbol, line);
+        }
+    }
+
+    pub fn set_debug_line(&mut self, line: i32) {
+        if let Some(ref mut info) = self.debug_info {
+            info.set_line(line);
+        }
+    }
+
+    pub fn declare_debug_var(&mut self, name: &str, addr_reg: Val, t: CGType, arg: Option<usize>) {
+        let var = match self.debug_info {
+            Some(ref mut info) if info.get_location().is_some() => info.add_var(name, t, arg),
+            _ => return,
+        };
+        self.add_line(format!("call void @llvm.dbg.declare(metadata {}* {}, metadata !{}, \
+                               metadata !DIExpression())",
+                              t,
+                              addr_reg,
+                              var));
+    }
+
+    pub fn add_debug_metadata(&mut self) {
+        let lines = match self.debug_info {
+            Some(ref info) => info.get_metadata(),
+            None => return,
+        };
+        self.add_empty_line();
+        for line in lines {
+            self.add_line_no_indent(line);
+        }
+    }
+
+    fn debug_location(&self) -> Option<usize> {
+        self.debug_info.as_ref().and_then(|info| info.get_location())
+    }
+
     pub fn add_phi(&mut self, t: CGType, op1: (Val, Label), op2: (Val, Label)) -> Val {
         self.new_reg(format!("phi {} [{}, %{}], [{}, %{}]", t, op1.0, op1.1, op2.0, op2.1))
     }
@@ -64,7 +168,16 @@ impl CodeGenerator {
     }
 
     pub fn add_op(&mut self, t: CGType, lhs: Val, op: Operator, rhs: Val) -> Val {
-        let op_str = match op {
+        let op_str = match t.as_raw() {
+            RawType::TDouble => Self::double_op(op),
+            RawType::TChar => Self::char_op(op),
+            _ => Self::int_op(op),
+        };
+        self.new_reg(format!("{} {} {}, {}", op_str, t, lhs, rhs))
+    }
+
+    fn int_op(op: Operator) -> &'static str {
+        match op {
             Operator::OpAdd => "add",
             Operator::OpSub => "sub",
             Operator::OpMul => "mul",
@@ -75,13 +188,135 @@ impl CodeGenerator {
             Operator::OpLessE => "icmp sle",
             Operator::OpGreater => "icmp sgt",
             Operator::OpGreaterE => "icmp sge",
+            Operator::OpBitAnd => "and",
+            Operator::OpBitOr => "or",
+            Operator::OpBitXor => "xor",
+            Operator::OpShl => "shl",
+            Operator::OpShr => "ashr",
+            _ => unreachable!(),
+        }
+    }
+
+    fn double_op(op: Operator) -> &'static str {
+        match op {
+            Operator::OpAdd => "fadd",
+            Operator::OpSub => "fsub",
+            Operator::OpMul => "fmul",
+            Operator::OpDiv => "fdiv",
+            Operator::OpEq => "fcmp oeq",
+            Operator::OpLess => "fcmp olt",
+            Operator::OpLessE => "fcmp ole",
+            Operator::OpGreater => "fcmp ogt",
+            Operator::OpGreaterE => "fcmp oge",
+            _ => unreachable!(),
+        }
+    }
+
+    // chars are unsigned
+    fn char_op(op: Operator) -> &'static str {
+        match op {
+            Operator::OpEq => "icmp eq",
+            Operator::OpLess => "icmp ult",
+            Operator::OpLessE => "icmp ule",
+            Operator::OpGreater => "icmp ugt",
+            Operator::OpGreaterE => "icmp uge",
+            _ => unreachable!(),
+        }
+    }
+
+    // calls error() on overflow
+    pub fn add_checked_op(&mut self, t: CGType, lhs: Val, op: Operator, rhs: Val) -> Val {
+        let res_t = format!("{{ {}, i1 }}", t);
+        let res = self.new_reg(format!("call {} @{}({} {}, {} {})",
+                                       res_t,
+                                       Self::overflow_intrinsic(t, op),
+                                       t,
+                                       lhs,
+                                       t,
+                                       rhs));
+        let val = self.new_reg(format!("extractvalue {} {}, 0", res_t, res));
+        let overflow = self.new_reg(format!("extractvalue {} {}, 1", res_t, res));
+        self.add_line(format!("call void @._check_overflow(i1 {})", overflow));
+        val
+    }
+
+    // sdiv and srem trap on MIN / -1, so -1 is replaced with 1 and the quotient
+    // is negated, which wraps to MIN, the remainder is 0. With check, MIN / -1 and
+    // MIN % -1 call error() as other overflows.
+    pub fn add_div_op(&mut self, t: CGType, lhs: Val, op: Operator, rhs: Val, check: bool) -> Val {
+        let minus_one = self.new_reg(format!("icmp eq {} {}, -1", t, rhs));
+        if check {
+            let min = match t == CGType::int_t() {
+                true => Val::Int(i32::MIN),
+                false => Val::Long(i64::MIN),
+            };
+            let is_min = self.new_reg(format!("icmp eq {} {}, {}", t, lhs, min));
+            let overflow = self.new_reg(format!("and i1 {}, {}", minus_one, is_min));
+            self.add_line(format!("call void @._check_overflow(i1 {})", overflow));
+        }
+        let divisor = self.new_reg(format!("select i1 {}, {} 1, {} {}", minus_one, t, t, rhs));
+        let res = self.add_op(t, lhs, op, divisor);
+        match op {
+            Operator::OpDiv => {
+                let neg = self.add_neg(t, lhs);
+                self.new_reg(format!("select i1 {}, {} {}, {} {}", minus_one, t, neg, t, res))
+            }
+            _ => res,
+        }
+    }
+
+    fn overflow_intrinsic(t: CGType, op: Operator) -> String {
+        let op_str = match op {
+            Operator::OpAdd => "sadd",
+            Operator::OpSub => "ssub",
+            Operator::OpMul => "smul",
             _ => unreachable!(),
         };
-        self.new_reg(format!("{} {} {}, {}", op_str, t, lhs, rhs))
+        format!("llvm.{}.with.overflow.{}", op_str, t)
+    }
+
+    pub fn add_neg(&mut self, t: CGType, val: Val) -> Val {
+        if t == CGType::double_t() {
+            self.new_reg(format!("fsub double -0.0, {}", val))
+        } else {
+            self.new_reg(format!("sub {} 0, {}", t, val))
+        }
+    }
+
+    // Doubles are truncated and saturated (NaN is 0), as in the optimizer, since
+    // fptosi gives poison for values out of range. Chars are unsigned.
+    pub fn add_cast(&mut self, val: Val, from: CGType, to: CGType) -> Val {
+        let (double_t, char_t) = (CGType::double_t(), CGType::char_t());
+        let instr = if from == to {
+            return val;
+        } else if from == double_t {
+            let int_t = match to == CGType::long_t() {
+                true => to,
+                false => CGType::int_t(),
+            };
+            let int = self.new_reg(format!("call {0} @llvm.fptosi.sat.{0}.f64(double {1})",
+                                           int_t,
+                                           val));
+            return self.add_cast(int, int_t, to);
+        } else if to == double_t {
+            if from == char_t { "uitofp" } else { "sitofp" }
+        } else if Self::int_width(from) > Self::int_width(to) {
+            "trunc"
+        } else if from == char_t {
+            "zext"
+        } else {
+            "sext"
+        };
+        self.new_reg(format!("{} {} {} to {}", instr, from, val, to))
     }
 
-    pub fn add_neg(&mut self, val: Val) -> Val {
-        self.new_reg(format!("sub i32 0, {}", val))
+    fn int_width(t: CGType) -> usize {
+        match t.as_raw() {
+            RawType::TChar => 8,
+            RawType::TInt => 32,
+            RawType::TLong => 64,
+            _ => unreachable!(),
+        }
     }
 
     pub fn add_not(&mut self, val: Val) -> Val {
@@ -93,6 +328,25 @@ impl CodeGenerator {
         self.new_reg(format!("call {0} @._concatenate({0} {1}, {0} {2})", str_t, lhs, rhs))
     }
 
+    // the parts are passed in an array, the result is built at once
+    pub fn concatenate_strs(&mut self, parts: &[Val]) -> Val {
+        let str_t = CGType::str_t();
+        let arr_t = format!("[{} x {}]", parts.len(), str_t);
+        let arr = self.add_raw_alloca(arr_t.clone());
+        for (idx, part) in parts.iter().enumerate() {
+            let addr = self.new_reg(format!("getelementptr {0}, {0}* {1}, i32 0, i32 {2}",
+                                            arr_t,
+                                            arr,
+                                            idx));
+            self.add_store(addr, str_t, *part);
+        }
+        let first = self.new_reg(format!("getelementptr {0}, {0}* {1}, i32 0, i32 0", arr_t, arr));
+        self.new_reg(format!("call {0} @._concatenate_n({0}* {1}, i32 {2})",
+                             str_t,
+                             first,
+                             parts.len()))
+    }
+
     pub fn add_loop_step(&mut self, new_idx: Register, old_idx: Val) {
         self.add_line(format!("%{} = add i32 1, {}", new_idx, old_idx));
     }
@@ -123,6 +377,12 @@ impl CodeGenerator {
                                         fields_str));
     }
 
+    pub fn add_global_declare(&mut self, id: usize, t: CGType) -> Val {
+        let global = GlobalVar(id);
+        self.add_line_no_indent(format!("@{} = private global {} zeroinitializer", global, t));
+        Val::Global(global)
+    }
+
     pub fn add_vtable_declare(&mut self,
                               class_id: ClassId,
                               size: usize,
@@ -188,6 +448,11 @@ impl CodeGenerator {
         self.add_line_no_indent(format!("declare {} @{}({})", ret_type, func_name, args_str));
     }
 
+    pub fn add_raw_func_declare(&mut self, ret_type: &str, func_name: &str, args: &[String]) {
+        let args_str = args.join(", ");
+        self.add_line_no_indent(format!("declare {} @{}({})", ret_type, func_name, args_str));
+    }
+
     pub fn add_func_begin(&mut self,
                           ret_type: CGType,
                           func_name: &String,
@@ -202,11 +467,17 @@ impl CodeGenerator {
         let args_str = join(&arg_regs,
                             ',',
                             |&(reg, arg_t)| format!("{} %{}", arg_t, reg));
-        self.add_line_no_indent(format!("define {} @{}({}) {}",
+        let dbg = match self.debug_info.as_ref().and_then(|info| info.get_subprogram()) {
+            Some(subprogram) => format!(" !dbg !{}", subprogram),
+            None => String::new(),
+        };
+        self.add_line_no_indent(format!("define {} @{}({}){} {}",
                                         ret_type,
                                         func_name,
                                         args_str,
+                                        dbg,
                                         '{'));
+        self.alloca_pos = self.out.len();
 
         let mut arg_addrs: Vec<(Val, CGType)> = Vec::new();
         for arg in &arg_regs {
@@ -220,12 +491,26 @@ impl CodeGenerator {
         arg_addrs
     }
 
+    // the C entry point, passes the arguments of the program to the Latte main
+    pub fn add_main_with_args(&mut self, main_name: &String) {
+        let args_t = CGType::arr_t(RawType::TString);
+        self.add_line_no_indent(format!("define i32 @main(i32 %argc, i8** %argv) {}", '{'));
+        let args = self.new_reg(format!("call {} @._new_args(i32 %argc, i8** %argv)", args_t));
+        let res = self.add_call(CGType::int_t(), format!("@{}", main_name), &vec![(args, args_t)]);
+        self.add_ret(CGType::int_t(), res);
+        self.add_func_end(CGType::int_t());
+        self.reset();
+    }
+
     pub fn add_func_end(&mut self, ret_type: CGType) {
         if ret_type == CGType::void_t() {
             self.add_line(format!("ret void"));
         }
         self.add_line_no_indent(format!("{}", '}'));
         self.add_line_no_indent(format!(""));
+        if let Some(ref mut info) = self.debug_info {
+            info.end_func();
+        }
     }
 
     pub fn add_call(&mut self,
@@ -233,8 +518,29 @@ impl CodeGenerator {
                     func_name: String,
                     args: &Vec<(Val, CGType)>)
                     -> Val {
+        self.add_marked_call("", ret_type, func_name, args)
+    }
+
+    // Call which must be followed by ret of its result. With musttail, the
+    // types of the args and the result must be the same as in the caller.
+    pub fn add_tail_call(&mut self,
+                         must: bool,
+                         ret_type: CGType,
+                         func_name: String,
+                         args: &Vec<(Val, CGType)>)
+                         -> Val {
+        let marker = if must { "musttail " } else { "tail " };
+        self.add_marked_call(marker, ret_type, func_name, args)
+    }
+
+    fn add_marked_call(&mut self,
+                       marker: &str,
+                       ret_type: CGType,
+                       func_name: String,
+                       args: &Vec<(Val, CGType)>)
+                       -> Val {
         let args_str = join(args, ',', |&(val, val_t)| format!("{} {}", val_t, val));
-        let call_str = format!("call {} {}({})", ret_type, func_name, args_str);
+        let call_str = format!("{}call {} {}({})", marker, ret_type, func_name, args_str);
         if ret_type == CGType::void_t() {
             self.add_line(call_str);
             Val::Reg(self.dummy_reg())
@@ -244,30 +550,30 @@ impl CodeGenerator {
     }
 
     // memory
+    // Every literal has a single string struct, its reference owned by the program
+    // keeps it from being freed.
     pub fn add_string_constant(&mut self, s: &String) -> StrConstant {
         self.last_str_const += 1;
         let reg = StrConstant(self.last_str_const);
-        self.add_line_no_indent(format!("@{} = private unnamed_addr constant [{} x i8] \
-                                         c\"{}\\00\"",
+        let arr_t = format!("[{} x i8]", s.len() + 1);
+        self.add_line_no_indent(format!("@{} = private unnamed_addr constant {} c\"{}\\00\"",
                                         reg,
-                                        s.len() + 1,
+                                        arr_t,
                                         string_to_hex(s)));
+        self.add_line_no_indent(format!("@{} = private global %string_t {{ i32 1, i8* \
+                                         getelementptr ({1}, {1}* @{2}, i64 0, i64 0), i1 true }}",
+                                        reg.struct_name(),
+                                        arr_t,
+                                        reg));
         reg
     }
 
-    pub fn add_str_load(&mut self, str_size: usize, str_const: StrConstant) -> Val {
-        let str_ptr = self.new_reg(format!("getelementptr [{} x i8], [{} x i8]* @{}, i64 0, i64 \
-                                            0",
-                                           str_size + 1,
-                                           str_size + 1,
-                                           str_const));
+    pub fn add_str_load(&mut self, str_const: StrConstant) -> Val {
         let str_t = CGType::str_t();
-        let struct_ptr = self.alloc_string();
+        let struct_ptr = self.new_reg(format!("getelementptr {0}, {0}* @{1}, i64 0",
+                                              str_t.native_type(),
+                                              str_const.struct_name()));
         self.retain_string(struct_ptr);
-        let addr = self.get_field_addr(struct_ptr, str_t, 2);
-        self.add_store(addr, CGType::bool_t(), Val::Int(1)); // is_const = 1
-        let addr = self.get_field_addr(struct_ptr, str_t, 1);
-        self.add_store(addr, CGType::ptr_t(), str_ptr);
         struct_ptr
     }
 
@@ -287,25 +593,63 @@ impl CodeGenerator {
                       &vec![(struct_ptr, CGType::str_t())]);
     }
 
-    pub fn new_arr(&mut self, arr_t: CGType, size: Val) -> Val {
+    // sizes of subsequent dimensions, subarrays of missing dimensions are null
+    pub fn new_arr(&mut self, arr_t: CGType, sizes: &[Val]) -> Val {
+        let struct_ptr = self.alloc_arr(arr_t, sizes[0]);
+        let elem_t = arr_t.arr_elem_t();
+        if sizes.len() > 1 {
+            let before_loop = self.next_label();
+            let loop_begin = self.next_label();
+            let loop_body = self.next_label();
+            let loop_end = self.next_label();
+            let after_loop = self.next_label();
+
+            self.add_comment("Allocating subarrays".to_string());
+            self.add_jump(before_loop);
+            self.add_label(before_loop);
+            self.add_jump(loop_begin);
+
+            self.add_label(loop_begin);
+            let new_idx_reg = self.next_reg();
+            let idx_reg = self.add_phi(CGType::int_t(),
+                                       (Val::Int(0), before_loop),
+                                       (Val::Reg(new_idx_reg), loop_end));
+            let valid_idx = self.add_int_op(idx_reg, Operator::OpLess, sizes[0]);
+            self.add_cond_jump(valid_idx, loop_body, after_loop);
+
+            self.add_label(loop_body);
+            let subarr = self.new_arr(elem_t, &sizes[1..]);
+            let (elem_ptr, _) = self.get_nth_arr_elem(struct_ptr, arr_t, idx_reg);
+            self.add_store(elem_ptr, elem_t, subarr);
+            self.add_jump(loop_end);
+
+            self.add_label(loop_end);
+            self.add_loop_step(new_idx_reg, idx_reg);
+            self.add_jump(loop_begin);
+
+            self.add_label(after_loop);
+        } else if elem_t == CGType::str_t() {
+            self.add_call(CGType::void_t(),
+                          "@._init_str_arr".to_string(),
+                          &vec![(struct_ptr, arr_t)]);
+        }
+
+        struct_ptr
+    }
+
+    // elements are zeroed
+    pub fn alloc_arr(&mut self, arr_t: CGType, size: Val) -> Val {
         let struct_ptr = self.add_malloc1(arr_t.native_type());
-        let arr_ptr = self.add_malloc(arr_t.arr_elem_t().user_type(), size);
+        let arr_ptr = self.add_calloc(arr_t.arr_elem_t().user_type(), size);
 
         let reg =
             self.new_reg(format!("insertvalue {} undef, i32 {}, 0", arr_t.native_type(), size));
-        let reg = self.new_reg(format!("insertvalue {} {}, {}* {}, 1",
+        let reg = self.new_reg(format!("insertvalue {} {}, {} {}, 1",
                                        arr_t.native_type(),
                                        reg,
-                                       arr_t.arr_elem_t().user_type(),
+                                       arr_t.in_arr_type(),
                                        arr_ptr));
         self.add_raw_store(struct_ptr, arr_t.native_type(), reg);
-
-        if arr_t.arr_elem_t() == CGType::str_t() {
-            self.add_call(CGType::void_t(),
-                          format!("@._init_str_arr"),
-                          &vec![(struct_ptr, arr_t)]);
-        }
-
         struct_ptr
     }
 
@@ -324,6 +668,13 @@ impl CodeGenerator {
         cast_addr
     }
 
+    fn add_calloc(&mut self, t: String, size: Val) -> Val {
+        let size_of = self.get_sizeof(t.clone(), Val::Int(1));
+        let void_addr = self.new_reg(format!("call i8* @calloc(i32 {}, i32 {})", size, size_of));
+        let cast_addr = self.new_reg(format!("bitcast i8* {} to {}*", void_addr, t));
+        cast_addr
+    }
+
     fn get_sizeof(&mut self, t: String, size: Val) -> Val {
         let size_of = self.new_reg(format!("getelementptr {}, {}* null, i32 {}", t, t, size));
         let res = self.new_reg(format!("ptrtoint {}* {} to i32", t, size_of));
@@ -335,14 +686,65 @@ impl CodeGenerator {
         let elem0_ptr = self.new_reg(format!("extractvalue {} {}, 1", t.native_type(), struct_val));
         let elem_ptr = self.new_reg(format!("getelementptr {}, {} {}, i32 {}",
                                             t.arr_elem_t().user_type(),
-                                            t.as_raw().in_arr_type(),
+                                            t.in_arr_type(),
                                             elem0_ptr,
                                             idx));
         (elem_ptr, t.arr_elem_t())
     }
 
+    // collections
+
+    pub fn add_raw_call(&mut self, ret_type: &str, func_name: &str, args: Vec<String>) -> Val {
+        let call_str = format!("call {} @{}({})", ret_type, func_name, args.join(", "));
+        if ret_type == "void" {
+            self.add_line(call_str);
+            Val::Reg(self.dummy_reg())
+        } else {
+            self.new_reg(call_str)
+        }
+    }
+
+    pub fn add_to_slot(&mut self, val: Val, t: CGType) -> Val {
+        if t == CGType::int_t() || t.is_enum() {
+            self.new_reg(format!("sext {} {} to i64", t, val))
+        } else if t == CGType::bool_t() || t == CGType::char_t() {
+            self.new_reg(format!("zext {} {} to i64", t, val))
+        } else if t == CGType::long_t() || t == CGType::double_t() {
+            self.new_reg(format!("bitcast {} {} to i64", t, val))
+        } else {
+            self.new_reg(format!("ptrtoint {} {} to i64", t, val))
+        }
+    }
+
+    pub fn add_from_slot(&mut self, slot: Val, t: CGType) -> Val {
+        if t == CGType::int_t() || t == CGType::bool_t() || t == CGType::char_t() ||
+           t.is_enum() {
+            self.new_reg(format!("trunc i64 {} to {}", slot, t))
+        } else if t == CGType::long_t() || t == CGType::double_t() {
+            self.new_reg(format!("bitcast i64 {} to {}", slot, t))
+        } else {
+            self.new_reg(format!("inttoptr i64 {} to {}", slot, t))
+        }
+    }
+
+    pub fn get_nth_list_elem(&mut self, list: Val, elem_t: CGType, idx: Val) -> Val {
+        let slot_ptr = self.new_reg(format!("call i64* @._list_at(%list_t* {}, i32 {})",
+                                            list,
+                                            idx));
+        self.new_reg(format!("bitcast i64* {} to {}*", slot_ptr, elem_t))
+    }
+
+    // allocas are put in the entry block, so that loops don't grow the stack
     pub fn add_alloca(&mut self, t: CGType) -> Val {
-        self.new_reg(format!("alloca {}", t))
+        self.add_raw_alloca(format!("{}", t))
+    }
+
+    fn add_raw_alloca(&mut self, t: String) -> Val {
+        let reg = self.next_reg();
+        let line = self.with_location(format!("\t%{} = alloca {}", reg, t));
+        self.out.insert(self.alloca_pos, line);
+        self.alloca_pos += 1;
+        Val::Reg(reg)
     }
 
     pub fn add_load(&mut self, addr_reg: Val, t: CGType) -> Val {
@@ -408,13 +810,32 @@ impl CodeGenerator {
         self.add_line(format!("br label %{}", l));
     }
 
+    pub fn add_switch(&mut self, t: CGType, val: Val, default: Label, cases: &[(i32, Label)]) {
+        let cases_str: Vec<String> = cases.iter()
+            .map(|&(case_val, l)| format!("{} {}, label %{}", t, case_val, l))
+            .collect();
+        self.add_line(format!("switch {} {}, label %{} [ {} ]",
+                              t,
+                              val,
+                              default,
+                              cases_str.join(" ")));
+    }
+
     // core functions
     pub fn add_comment(&mut self, s: String) {
         self.add_line_no_indent(format!("; {}", s));
     }
 
     fn add_line(&mut self, s: String) {
-        self.out.push(format!("\t{}", s));
+        let line = self.with_location(format!("\t{}", s));
+        self.out.push(line);
+    }
+
+    fn with_location(&self, line: String) -> String {
+        match self.debug_location() {
+            Some(location) => format!("{}, !dbg !{}", line, location),
+            None => line,
+        }
     }
 
     fn add_line_no_indent(&mut self, s: String) {
@@ -426,10 +847,13 @@ impl CodeGenerator {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Val {
     Reg(Register),
     Int(i32),
+    Long(i64),
+    Double(f64),
+    Global(GlobalVar),
     Null,
 }
 
@@ -442,6 +866,9 @@ pub struct StrConstant(i32);
 #[derive(Debug, Clone, Copy)]
 pub struct VTableConstant(pub ClassId);
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlobalVar(usize);
+
 #[derive(Debug, Clone, Copy)]
 pub struct Label(i32);
 
@@ -450,6 +877,10 @@ impl fmt::Display for Val {
         match *self {
             Val::Reg(ref r) => write!(f, "%{}", r),
             Val::Int(x) => write!(f, "{}", x),
+            Val::Long(x) => write!(f, "{}", x),
+            // hexadecimal form is exact for every double
+            Val::Double(x) => write!(f, "0x{:016X}", x.to_bits()),
+            Val::Global(ref g) => write!(f, "@{}", g),
             Val::Null => write!(f, "null"),
         }
     }
@@ -461,6 +892,12 @@ impl fmt::Display for Register {
     }
 }
 
+impl StrConstant {
+    fn struct_name(&self) -> String {
+        format!(".str_obj_{}", self.0)
+    }
+}
+
 impl fmt::Display for StrConstant {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, ".str_const_{}", self.0)
@@ -473,6 +910,12 @@ impl fmt::Display for VTableConstant {
     }
 }
 
+impl fmt::Display for GlobalVar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, ".global_{}", self.0)
+    }
+}
+
 impl fmt::Display for Label {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "label_{}", self.0)
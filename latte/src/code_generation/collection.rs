@@ -0,0 +1,157 @@
+use ast::{Expr, Ident};
+
+use code_generation::cg_type::*;
+use code_generation::code_generator::*;
+use code_generation::context::*;
+use code_generation::expr::*;
+use code_generation::generate::*;
+
+pub fn generate_new_collection(t: CGType, ctx: &mut Context) -> Val {
+    let str_t = CGType::str_t();
+    if t.is_builder() {
+        ctx.cg.add_raw_call("%builder_t*", "._builder_new", vec![])
+    } else if t.is_list() {
+        let is_str = ctx.get_list_elem_t(t) == str_t;
+        ctx.cg.add_raw_call("%list_t*", "._list_new", vec![format!("i1 {}", is_str as i32)])
+    } else {
+        let (key_t, value_t) = ctx.get_map_types(t);
+        ctx.cg.add_raw_call("%map_t*",
+                            "._map_new",
+                            vec![format!("i1 {}", (key_t == str_t) as i32),
+                                 format!("i1 {}", (value_t == str_t) as i32)])
+    }
+}
+
+pub fn generate_collection_call(obj: Val,
+                                obj_t: CGType,
+                                method: &Ident,
+                                args: &[Expr],
+                                ctx: &mut Context)
+                                -> (Val, CGType) {
+    if obj_t.is_builder() {
+        generate_builder_call(obj, method, args, ctx)
+    } else if obj_t.is_list() {
+        generate_list_call(obj, obj_t, method, args, ctx)
+    } else {
+        generate_map_call(obj, obj_t, method, args, ctx)
+    }
+}
+
+// Strings passed to collections are retained by the runtime. Elements returned by pop
+// and remove are owned by the caller.
+fn generate_list_call(list: Val,
+                      list_t: CGType,
+                      method: &Ident,
+                      args: &[Expr],
+                      ctx: &mut Context)
+                      -> (Val, CGType) {
+    let elem_t = ctx.get_list_elem_t(list_t);
+    let list_arg = format!("{} {}", list_t, list);
+    match method.0.as_ref() {
+        "push" => {
+            let elem = generate_slot(&args[0], elem_t, ctx);
+            let res = ctx.cg.add_raw_call("void", "._list_push", vec![list_arg, elem]);
+            (res, CGType::void_t())
+        }
+        "pop" => {
+            let slot = ctx.cg.add_raw_call("i64", "._list_pop", vec![list_arg]);
+            (ctx.cg.add_from_slot(slot, elem_t), elem_t)
+        }
+        "insert" => {
+            let (idx, _) = args[0].generate_code(ctx);
+            let elem = generate_slot(&args[1], elem_t, ctx);
+            let res = ctx.cg.add_raw_call("void",
+                                          "._list_insert",
+                                          vec![list_arg, format!("i32 {}", idx), elem]);
+            (res, CGType::void_t())
+        }
+        "remove" => {
+            let (idx, _) = args[0].generate_code(ctx);
+            let slot = ctx.cg
+                .add_raw_call("i64", "._list_remove", vec![list_arg, format!("i32 {}", idx)]);
+            (ctx.cg.add_from_slot(slot, elem_t), elem_t)
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn generate_map_call(map: Val,
+                     map_t: CGType,
+                     method: &Ident,
+                     args: &[Expr],
+                     ctx: &mut Context)
+                     -> (Val, CGType) {
+    let (key_t, value_t) = ctx.get_map_types(map_t);
+    let map_arg = format!("{} {}", map_t, map);
+    match method.0.as_ref() {
+        "put" => {
+            let key = generate_slot(&args[0], key_t, ctx);
+            let value = generate_slot(&args[1], value_t, ctx);
+            let res = ctx.cg.add_raw_call("void", "._map_put", vec![map_arg, key, value]);
+            (res, CGType::void_t())
+        }
+        "get" => {
+            let key = generate_slot(&args[0], key_t, ctx);
+            let slot = ctx.cg.add_raw_call("i64", "._map_get", vec![map_arg, key]);
+            let value = ctx.cg.add_from_slot(slot, value_t);
+            if value_t == CGType::str_t() {
+                ctx.cg.retain_string(value);
+            }
+            (value, value_t)
+        }
+        "contains" => {
+            let key = generate_slot(&args[0], key_t, ctx);
+            let res = ctx.cg.add_raw_call("i1", "._map_contains", vec![map_arg, key]);
+            (res, CGType::bool_t())
+        }
+        "remove" => {
+            let key = generate_slot(&args[0], key_t, ctx);
+            let res = ctx.cg.add_raw_call("void", "._map_remove", vec![map_arg, key]);
+            (res, CGType::void_t())
+        }
+        "keys" => generate_map_keys(map, map_t, ctx),
+        _ => unreachable!(),
+    }
+}
+
+// The characters of appended strings are copied, the result of toString is owned by
+// the caller.
+fn generate_builder_call(builder: Val,
+                         method: &Ident,
+                         args: &[Expr],
+                         ctx: &mut Context)
+                         -> (Val, CGType) {
+    let builder_arg = format!("{} {}", CGType::builder_t(), builder);
+    match method.0.as_ref() {
+        "append" => {
+            let (s, t) = args[0].generate_code(ctx);
+            let res = ctx.cg.add_raw_call("void",
+                                          "._builder_append",
+                                          vec![builder_arg, format!("{} {}", t, s)]);
+            (res, CGType::void_t())
+        }
+        "appendChar" => {
+            let (c, t) = args[0].generate_code(ctx);
+            let res = ctx.cg.add_raw_call("void",
+                                          "._builder_append_char",
+                                          vec![builder_arg, format!("{} {}", t, c)]);
+            (res, CGType::void_t())
+        }
+        "toString" => {
+            let res = ctx.cg.add_raw_call("%string_t*", "._builder_to_string", vec![builder_arg]);
+            (res, CGType::str_t())
+        }
+        _ => unreachable!(),
+    }
+}
+
+pub fn generate_map_keys(map: Val, map_t: CGType, ctx: &mut Context) -> (Val, CGType) {
+    let (key_t, _) = ctx.get_map_types(map_t);
+    let keys = ctx.cg.add_raw_call("%list_t*", "._map_keys", vec![format!("{} {}", map_t, map)]);
+    (keys, ctx.get_list_t(key_t))
+}
+
+fn generate_slot(e: &Expr, t: CGType, ctx: &mut Context) -> String {
+    let val = generate_init_expr(e, t, ctx);
+    format!("i64 {}", ctx.cg.add_to_slot(val, t))
+}
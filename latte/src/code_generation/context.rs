@@ -1,22 +1,41 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use ast::{Type, Ident};
+use ast::{Expr, Func, Type, Ident};
+use options::Options;
 
 use code_generation::cg_type::*;
 use code_generation::class_data::*;
 use code_generation::code_generator::*;
+use code_generation::tail_call::TailTarget;
 use code_generation::vtable::*;
 
 #[derive(Debug)]
 pub struct Context {
     vars: HashMap<Ident, (Val, CGType)>,
+    globals: HashMap<Ident, (Val, CGType)>,
+    static_fields: HashMap<(ClassId, Ident), (Val, CGType)>,
+    static_methods: HashMap<(ClassId, Ident), Ident>, // to their symbols
     func_types: HashMap<Ident, (Vec<CGType>, CGType)>,
+    externs: HashSet<Ident>,
     string_lits: HashMap<String, StrConstant>,
     pub ret_type: CGType,
     pub class: Option<ClassId>,
+    pub static_class: Option<ClassId>, // class whose static members are accessible directly
+    pub opts: Options,
+
+    inline_funcs: HashMap<Ident, (Func, Option<ClassId>, bool)>, // class, whether it's a method
+    inline_stack: Vec<Ident>,
+    pub inline_ret: Option<(Label, Val)>, // end of the inlined body and address of the result
+    pub current_func: String,
+    pub tail_target: Option<TailTarget>,
+    devirtualized: Vec<String>, // report of calls that don't use vtables
 
     classes: HashMap<ClassId, ClassData>,
     class_ids: HashMap<Ident, ClassId>,
+    list_types: Vec<CGType>,          // element types
+    map_types: Vec<(CGType, CGType)>, // key and value types
+    enum_ids: HashMap<Ident, EnumId>,
+    enum_variants: Vec<Vec<Ident>>,
 
     string_tmps: Vec<Val>,
     local_string_tmps: Vec<Val>,
@@ -27,16 +46,33 @@ pub struct Context {
 }
 
 impl Context {
-    pub fn new() -> Context {
+    pub fn new(opts: &Options) -> Context {
         Context {
             vars: HashMap::new(),
+            globals: HashMap::new(),
+            static_fields: HashMap::new(),
+            static_methods: HashMap::new(),
             func_types: HashMap::new(),
+            externs: HashSet::new(),
             string_lits: HashMap::new(),
             ret_type: CGType::void_t(),
             class: None,
+            static_class: None,
+            opts: opts.clone(),
+
+            inline_funcs: HashMap::new(),
+            inline_stack: Vec::new(),
+            inline_ret: None,
+            current_func: String::new(),
+            tail_target: None,
+            devirtualized: Vec::new(),
 
             classes: HashMap::new(),
             class_ids: HashMap::new(),
+            list_types: Vec::new(),
+            map_types: Vec::new(),
+            enum_ids: HashMap::new(),
+            enum_variants: Vec::new(),
 
             string_tmps: Vec::new(),
             local_string_tmps: Vec::new(),
@@ -80,6 +116,14 @@ impl Context {
         self.func_types.get(ident).unwrap().1
     }
 
+    // main taking the arguments of the program is called by the generated main
+    pub fn get_func_symbol(&self, ident: &Ident) -> String {
+        match ident.0 == "main" && !self.get_arg_types(ident).is_empty() {
+            true => "._main".to_string(),
+            false => ident.0.clone(),
+        }
+    }
+
     pub fn get_str_const(&self, s: &String) -> StrConstant {
         *self.string_lits.get(s).unwrap()
     }
@@ -97,6 +141,27 @@ impl Context {
     }
 
     pub fn set_var(&mut self, ident: Ident, addr_reg: Val, t: CGType) {
+        self.add_var(ident, addr_reg, t, None);
+    }
+
+    // args are numbered from 1
+    pub fn set_arg(&mut self, ident: Ident, addr_reg: Val, t: CGType, arg: usize) {
+        self.add_var(ident, addr_reg, t, Some(arg));
+    }
+
+    // Statements of inlined bodies keep the location of the call, statements
+    // added by optimizations the location of the previous one.
+    pub fn set_line(&mut self, line: i32) {
+        if line != 0 && self.inline_stack.is_empty() {
+            self.cg.set_debug_line(line);
+        }
+    }
+
+    // variables of inlined bodies are not described in the debug information
+    fn add_var(&mut self, ident: Ident, addr_reg: Val, t: CGType, arg: Option<usize>) {
+        if self.inline_stack.is_empty() {
+            self.cg.declare_debug_var(&ident.0, addr_reg, t, arg);
+        }
         self.vars.insert(ident.clone(), (addr_reg, t));
         if t == CGType::str_t() {
             self.string_vars.push(addr_reg);
@@ -104,10 +169,132 @@ impl Context {
         }
     }
 
+    pub fn has_globals(&self) -> bool {
+        !self.globals.is_empty() || !self.static_fields.is_empty()
+    }
+
+    pub fn global_exists(&self, ident: &Ident) -> bool {
+        self.globals.contains_key(ident)
+    }
+
+    pub fn get_global(&self, ident: &Ident) -> (Val, CGType) {
+        *self.globals.get(ident).unwrap()
+    }
+
+    pub fn add_global(&mut self, ident: &Ident, t: CGType) {
+        let id = self.globals.len() + self.static_fields.len();
+        let addr = self.cg.add_global_declare(id, t);
+        self.globals.insert(ident.clone(), (addr, t));
+    }
+
+    // static fields are stored as globals
+    pub fn add_static_field(&mut self, class: ClassId, ident: &Ident, t: CGType) {
+        let id = self.globals.len() + self.static_fields.len();
+        let addr = self.cg.add_global_declare(id, t);
+        self.static_fields.insert((class, ident.clone()), (addr, t));
+    }
+
+    pub fn add_static_method(&mut self, class: ClassId, ident: &Ident, symbol: &Ident) {
+        self.static_methods.insert((class, ident.clone()), symbol.clone());
+    }
+
+    // static members are inherited
+
+    pub fn find_static_field(&self, mut id: ClassId, ident: &Ident) -> Option<(Val, CGType)> {
+        loop {
+            if let Some(field) = self.static_fields.get(&(id, ident.clone())) {
+                return Some(*field);
+            }
+            id = self.get_class_data(id).super_id?;
+        }
+    }
+
+    // returns the name of the generated function
+    pub fn find_static_method(&self, mut id: ClassId, ident: &Ident) -> Option<Ident> {
+        loop {
+            if let Some(symbol) = self.static_methods.get(&(id, ident.clone())) {
+                return Some(symbol.clone());
+            }
+            id = self.get_class_data(id).super_id?;
+        }
+    }
+
     pub fn add_func(&mut self, ident: &Ident, arg_types: Vec<CGType>, ret_type: CGType) {
         self.func_types.insert(ident.clone(), (arg_types, ret_type));
     }
 
+    pub fn add_extern_func(&mut self, ident: &Ident, arg_types: Vec<CGType>, ret_type: CGType) {
+        self.add_func(ident, arg_types, ret_type);
+        self.externs.insert(ident.clone());
+    }
+
+    pub fn is_extern(&self, ident: &Ident) -> bool {
+        self.externs.contains(ident)
+    }
+
+    // inlining
+
+    pub fn add_inline_func(&mut self,
+                           ident: Ident,
+                           f: &Func,
+                           class: Option<ClassId>,
+                           is_method: bool) {
+        self.inline_funcs.insert(ident, (f.clone(), class, is_method));
+    }
+
+    // a function is not inlined into itself
+    pub fn get_inline_func(&self, ident: &Ident) -> Option<(Func, Option<ClassId>, bool)> {
+        match self.inline_stack.contains(ident) {
+            true => None,
+            false => self.inline_funcs.get(ident).cloned(),
+        }
+    }
+
+    // Runs f as if it generated a new function: only globals are visible
+    // and returns release only the strings of the inlined body.
+    pub fn in_inlined_body<F>(&mut self,
+                              ident: &Ident,
+                              class: Option<ClassId>,
+                              is_method: bool,
+                              ret_type: CGType,
+                              ret: (Label, Val),
+                              f: F)
+        where F: FnOnce(&mut Context)
+    {
+        let old_vars = self.vars.clone();
+        let old_string_tmps = self.string_tmps.clone();
+        let old_local_string_tmps = self.local_string_tmps.clone();
+        let old_string_vars = self.string_vars.clone();
+        let old_local_string_vars = self.local_string_vars.clone();
+        let old_ret_type = self.ret_type;
+        let old_class = self.class;
+        let old_static_class = self.static_class;
+        let old_inline_ret = self.inline_ret;
+
+        self.vars.clear();
+        self.string_tmps.clear();
+        self.local_string_tmps.clear();
+        self.string_vars.clear();
+        self.local_string_vars.clear();
+        self.ret_type = ret_type;
+        self.class = if is_method { class } else { None };
+        self.static_class = class;
+        self.inline_ret = Some(ret);
+        self.inline_stack.push(ident.clone());
+        f(self);
+        self.inline_stack.pop();
+
+        self.inline_ret = old_inline_ret;
+        self.static_class = old_static_class;
+        self.class = old_class;
+        self.ret_type = old_ret_type;
+        self.local_string_vars = old_local_string_vars;
+        self.string_vars = old_string_vars;
+        self.local_string_tmps = old_local_string_tmps;
+        self.string_tmps = old_string_tmps;
+        self.vars = old_vars;
+    }
+
     // string reference counting
     pub fn add_string_tmp(&mut self, reg: Val) {
         self.string_tmps.push(reg);
@@ -144,6 +331,10 @@ impl Context {
     }
 
     // class
+    pub fn add_class_id(&mut self, cname: &Ident, id: ClassId) {
+        self.class_ids.insert(cname.clone(), id);
+    }
+
     pub fn add_class(&mut self, id: ClassId, cdata: ClassData) {
         let cname = cdata.ident.clone();
         self.cg.add_comment(format!("class {}", cname));
@@ -163,6 +354,10 @@ impl Context {
         self.classes.get_mut(&id).unwrap().vtable_addr = vtable_addr;
     }
 
+    pub fn class_exists(&self, cname: &Ident) -> bool {
+        self.class_ids.contains_key(cname)
+    }
+
     pub fn get_class_id(&self, cname: &Ident) -> ClassId {
         *self.class_ids.get(cname).unwrap()
     }
@@ -171,6 +366,39 @@ impl Context {
         self.classes.get(&id).unwrap()
     }
 
+    pub fn has_field(&self, mut id: ClassId, field: &Ident) -> bool {
+        while !self.get_class_data(id).has_field(field) {
+            match self.get_class_data(id).super_id {
+                Some(super_id) => id = super_id,
+                None => return false,
+            }
+        }
+        true
+    }
+
+    // Class hierarchy analysis: whether a subclass of the class replaces
+    // the method at the given position of its vtable.
+    pub fn is_overridden(&self, id: ClassId, vtable_pos: usize) -> bool {
+        let real_ident = &self.get_class_data(id).vtable.fs[vtable_pos].real_ident;
+        self.classes.iter().any(|(&sub_id, cdata)| {
+            sub_id != id && self.is_subclass_of(sub_id, id) &&
+            cdata.vtable.fs[vtable_pos].real_ident != *real_ident
+        })
+    }
+
+    pub fn add_devirtualized(&mut self, call: &Expr, method_class: ClassId, method: &Ident) {
+        let msg = format!("{}: {} calls {}.{}",
+                          self.current_func,
+                          call,
+                          self.get_class_data(method_class).ident,
+                          method);
+        self.devirtualized.push(msg);
+    }
+
+    pub fn take_devirtualized(&mut self) -> Vec<String> {
+        self.devirtualized.drain(..).collect()
+    }
+
     pub fn is_subclass_of(&self, mut id: ClassId, super_id: ClassId) -> bool {
         while self.get_class_data(id).super_id.is_some() && id != super_id {
             id = self.get_class_data(id).get_super();
@@ -178,11 +406,68 @@ impl Context {
         id == super_id
     }
 
-    pub fn to_cgtype(&self, t: &Type) -> CGType {
+    // enums
+    pub fn add_enum(&mut self, name: &Ident, variants: &[Ident]) -> EnumId {
+        let id = self.enum_variants.len();
+        self.enum_ids.insert(name.clone(), id);
+        self.enum_variants.push(variants.to_owned());
+        id
+    }
+
+    pub fn enum_exists(&self, name: &Ident) -> bool {
+        self.enum_ids.contains_key(name)
+    }
+
+    pub fn get_enum_id(&self, name: &Ident) -> EnumId {
+        *self.enum_ids.get(name).unwrap()
+    }
+
+    pub fn get_enum_name(&self, id: EnumId) -> &Ident {
+        self.enum_ids.iter().find(|&(_, &enum_id)| enum_id == id).unwrap().0
+    }
+
+    pub fn get_variant_tag(&self, id: EnumId, variant: &Ident) -> i32 {
+        self.enum_variants[id].iter().position(|v| v == variant).unwrap() as i32
+    }
+
+    // collections
+
+    pub fn get_list_elem_t(&self, list_t: CGType) -> CGType {
+        self.list_types[list_t.get_collection_id()]
+    }
+
+    pub fn get_list_t(&mut self, elem_t: CGType) -> CGType {
+        CGType::list_t(get_or_insert(&mut self.list_types, elem_t))
+    }
+
+    pub fn get_map_types(&self, map_t: CGType) -> (CGType, CGType) {
+        self.map_types[map_t.get_collection_id()]
+    }
+
+    pub fn get_cgtype(&mut self, t: &Type) -> CGType {
         match *t {
             Type::TObject(ref cname) => CGType::obj_t(*self.class_ids.get(cname).unwrap()),
-            Type::TArray(ref elem_t) => CGType::arr_t(self.to_cgtype(elem_t).as_raw()),
+            Type::TEnum(ref name) => CGType::enum_t(self.get_enum_id(name)),
+            Type::TArray(ref elem_t) => self.get_cgtype(elem_t).arr_of(),
+            Type::TList(ref elem_t) => {
+                let elem_t = self.get_cgtype(elem_t);
+                self.get_list_t(elem_t)
+            }
+            Type::TMap(ref key_t, ref value_t) => {
+                let types = (self.get_cgtype(key_t), self.get_cgtype(value_t));
+                CGType::map_t(get_or_insert(&mut self.map_types, types))
+            }
             _ => CGType::from(t),
         }
     }
 }
+
+fn get_or_insert<T: PartialEq>(v: &mut Vec<T>, elem: T) -> usize {
+    match v.iter().position(|e| *e == elem) {
+        Some(idx) => idx,
+        None => {
+            v.push(elem);
+            v.len() - 1
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use code_generation::cg_type::*;
+
+// Debug information (DWARF) emitted with -g. Instructions get the location of
+// their statement, variables the line of their declaration.
+#[derive(Debug)]
+pub struct DebugInfo {
+    nodes: Vec<String>, // node !N is nodes[N]
+    unit: usize,
+    file: usize,
+    func_type: usize,
+    flags: Vec<usize>,
+    types: Vec<(CGType, usize)>,
+    scope: Option<(usize, usize)>, // subprogram of the current function and its location
+    locations: HashMap<i32, usize>, // of lines in the current function
+    line: i32,
+}
+
+impl DebugInfo {
+    pub fn new(input: &Path) -> DebugInfo {
+        let mut info = DebugInfo {
+            nodes: Vec::new(),
+            unit: 0,
+            file: 0,
+            func_type: 0,
+            flags: Vec::new(),
+            types: Vec::new(),
+            scope: None,
+            locations: HashMap::new(),
+            line: 0,
+        };
+
+        let filename = input.file_name().map(|f| f.to_string_lossy()).unwrap_or_default();
+        let directory = input.parent().map(|d| d.to_string_lossy()).unwrap_or_default();
+        info.file = info.add_node(format!("!DIFile(filename: \"{}\", directory: \"{}\")",
+                                          escape(&filename),
+                                          escape(&directory)));
+        info.unit = info.add_node(format!("distinct !DICompileUnit(language: DW_LANG_C99, \
+                                           file: !{}, producer: \"latte\", \
+                                           isOptimized: false, runtimeVersion: 0, \
+                                           emissionKind: FullDebug)",
+                                          info.file));
+        info.func_type = info.add_node("!DISubroutineType(types: !{null})".to_string());
+        let dwarf_version = info.add_node("!{i32 7, !\"Dwarf Version\", i32 4}".to_string());
+        let info_version = info.add_node("!{i32 2, !\"Debug Info Version\", i32 3}".to_string());
+        info.flags = vec![dwarf_version, info_version];
+        info
+    }
+
+    // Starts a function with the given source name, defined at the line.
+    pub fn begin_func(&mut self, name: &str, symbol: &str, line: i32) {
+        let subprogram = self.add_node(format!("distinct !DISubprogram(name: \"{}\", \
+                                                linkageName: \"{}\", scope: !{2}, \
+                                                file: !{2}, line: {5}, type: !{3}, \
+                                                scopeLine: {5}, spFlags: DISPFlagDefinition, \
+                                                unit: !{4})",
+                                               escape(name),
+                                               escape(symbol),
+                                               self.file,
+                                               self.func_type,
+                                               self.unit,
+                                               line));
+        self.scope = Some((subprogram, 0));
+        self.locations.clear();
+        self.set_line(line);
+    }
+
+    // the following instructions are at the line, until it's set again
+    pub fn set_line(&mut self, line: i32) {
+        let subprogram = match self.scope {
+            Some((subprogram, _)) => subprogram,
+            None => return,
+        };
+        let location = match self.locations.get(&line) {
+            Some(&location) => location,
+            None => {
+                let location = self.add_node(format!("!DILocation(line: {}, scope: !{})",
+                                                     line,
+                                                     subprogram));
+                self.locations.insert(line, location);
+                location
+            }
+        };
+        self.scope = Some((subprogram, location));
+        self.line = line;
+    }
+
+    pub fn end_func(&mut self) {
+        self.scope = None;
+    }
+
+    pub fn get_subprogram(&self) -> Option<usize> {
+        self.scope.map(|(subprogram, _)| subprogram)
+    }
+
+    // location of instructions, only in functions with a subprogram
+    pub fn get_location(&self) -> Option<usize> {
+        self.scope.map(|(_, location)| location)
+    }
+
+    // Describes a variable of the current function declared at the current line,
+    // args are numbered from 1.
+    pub fn add_var(&mut self, name: &str, t: CGType, arg: Option<usize>) -> usize {
+        let subprogram = match self.scope {
+            Some((subprogram, _)) => subprogram,
+            None => panic!("Variable outside of a function"),
+        };
+        let arg_str = match arg {
+            Some(n) => format!("arg: {}, ", n),
+            None => String::new(),
+        };
+        let type_node = self.get_type(t);
+        self.add_node(format!("!DILocalVariable(name: \"{}\", {}scope: !{}, file: !{}, \
+                               line: {}, type: !{})",
+                              escape(name),
+                              arg_str,
+                              subprogram,
+                              self.file,
+                              self.line,
+                              type_node))
+    }
+
+    // Strings, objects, arrays and collections are described as untyped pointers.
+    fn get_type(&mut self, t: CGType) -> usize {
+        if let Some(&(_, node)) = self.types.iter().find(|&&(t2, _)| t2 == t) {
+            return node;
+        }
+        let basic = |name: &str, size: i32, encoding: &str| {
+            format!("!DIBasicType(name: \"{}\", size: {}, encoding: {})", name, size, encoding)
+        };
+        let node = match t.as_raw() {
+            _ if t.is_arr() => self.pointer_type(t),
+            RawType::TInt | RawType::TEnum(_) => basic("int", 32, "DW_ATE_signed"),
+            RawType::TLong => basic("long", 64, "DW_ATE_signed"),
+            RawType::TDouble => basic("double", 64, "DW_ATE_float"),
+            RawType::TChar => basic("char", 8, "DW_ATE_unsigned_char"),
+            RawType::TBool => basic("boolean", 8, "DW_ATE_boolean"),
+            _ => self.pointer_type(t),
+        };
+        let node = self.add_node(node);
+        self.types.push((t, node));
+        node
+    }
+
+    fn pointer_type(&self, t: CGType) -> String {
+        format!("!DIDerivedType(tag: DW_TAG_pointer_type, name: \"{}\", baseType: null, \
+                 size: 64)",
+                escape(&t.user_type()))
+    }
+
+    // named metadata and the nodes, put at the end of the module
+    pub fn get_metadata(&self) -> Vec<String> {
+        let flags: Vec<String> = self.flags.iter().map(|n| format!("!{}", n)).collect();
+        let mut lines = vec![format!("!llvm.dbg.cu = !{{!{}}}", self.unit),
+                             format!("!llvm.module.flags = !{{{}}}", flags.join(", "))];
+        for (n, node) in self.nodes.iter().enumerate() {
+            lines.push(format!("!{} = {}", n, node));
+        }
+        lines
+    }
+
+    fn add_node(&mut self, node: String) -> usize {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+}
+
+// metadata strings escape quotes and backslashes with hex codes
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\5C").replace('"', "\\22")
+}
@@ -0,0 +1,37 @@
+use ast::Enum;
+use mangling::*;
+
+use code_generation::cg_type::*;
+use code_generation::code_generator::*;
+use code_generation::context::Context;
+use code_generation::generate::*;
+
+// Generates name() of the enum, which maps tags to names of the variants.
+// Tags out of range are not possible, so the first variant is the default case.
+impl GenerateCode<()> for Enum {
+    fn generate_code(&self, ctx: &mut Context) {
+        let id = ctx.get_enum_id(&self.name);
+        let enum_t = CGType::enum_t(id);
+        let ret_type = CGType::str_t();
+        let symbol = enum_name_symbol(&self.name);
+        let args = ctx.cg.add_func_begin(ret_type, &symbol, &vec![enum_t]);
+        let tag = ctx.cg.add_load(args[0].0, enum_t);
+
+        let labels: Vec<Label> = self.variants.iter().map(|_| ctx.cg.next_label()).collect();
+        let cases: Vec<(i32, Label)> = labels.iter()
+            .enumerate()
+            .skip(1)
+            .map(|(tag, l)| (tag as i32, *l))
+            .collect();
+        ctx.cg.add_switch(enum_t, tag, labels[0], &cases);
+
+        for (variant, label) in self.variants.iter().zip(labels) {
+            ctx.cg.add_label(label);
+            let name = ctx.get_str_const(&variant.0);
+            let name = ctx.cg.add_str_load(name);
+            ctx.cg.add_ret(ret_type, name);
+        }
+        ctx.cg.add_func_end(ret_type);
+        ctx.cg.reset();
+    }
+}
@@ -1,13 +1,21 @@
 use ast::*;
+use mangling::*;
 
 use code_generation::cg_type::*;
 use code_generation::code_generator::*;
+use code_generation::collection::*;
 use code_generation::context::*;
+use code_generation::extern_func::*;
+use code_generation::field_get::as_enum_variant;
 use code_generation::generate::*;
+use code_generation::inline::*;
 
 impl GenerateCode<(Val, CGType)> for Expr {
     fn generate_code(&self, ctx: &mut Context) -> (Val, CGType) {
         let (reg, t) = match *self {
+            Expr::EVar(ref ident) if as_enum_variant(ident, ctx).is_some() => {
+                as_enum_variant(ident, ctx).unwrap()
+            }
             Expr::EVar(ref ident) => {
                 let (addr_reg, t) = ident.generate_code(ctx);
                 let reg = ctx.cg.add_load(addr_reg, t);
@@ -20,40 +28,73 @@ impl GenerateCode<(Val, CGType)> for Expr {
             Expr::ECall(ref ident, ref args) => generate_call(ident, args, ctx),
             Expr::ENeg(ref e) => {
                 let (val, t) = e.generate_code(ctx);
-                (ctx.cg.add_neg(val), t)
+                if t == CGType::double_t() {
+                    (ctx.cg.add_neg(t, val), t)
+                } else {
+                    (generate_arith_op(t, Val::Int(0), Operator::OpSub, val, ctx), t)
+                }
             }
             Expr::ENot(ref e) => {
                 let (val, t) = e.generate_code(ctx);
                 (ctx.cg.add_not(val), t)
             }
+            Expr::EBitNot(ref e) => {
+                let (val, t) = e.generate_code(ctx);
+                (ctx.cg.add_op(t, val, Operator::OpBitXor, Val::Int(-1)), t)
+            }
             Expr::EBinOp(ref lhs, Operator::OpOr, ref rhs) => generate_or(lhs, rhs, ctx),
             Expr::EBinOp(ref lhs, Operator::OpAnd, ref rhs) => generate_and(lhs, rhs, ctx),
             Expr::EBinOp(ref lhs, Operator::OpNEq, ref rhs) => generate_neq(lhs, rhs, ctx),
             Expr::EBinOp(ref lhs, Operator::OpEq, ref rhs) => generate_eq(lhs, rhs, ctx),
             Expr::EBinOp(ref lhs, Operator::OpAdd, ref rhs) => generate_add(lhs, rhs, ctx),
             Expr::EBinOp(ref lhs, ref op, ref rhs) => {
-                let (lhs_val, _) = lhs.generate_code(ctx);
-                let (rhs_val, _) = rhs.generate_code(ctx);
+                let (lhs_val, lhs_t) = lhs.generate_code(ctx);
+                let (mut rhs_val, _) = rhs.generate_code(ctx);
+                if lhs_t == CGType::str_t() {
+                    return generate_str_cmp(lhs_val, *op, rhs_val, ctx);
+                }
                 let t = match *op {
                     Operator::OpLess | Operator::OpLessE | Operator::OpGreater |
                     Operator::OpGreaterE => CGType::bool_t(),
                     Operator::OpAdd | Operator::OpSub | Operator::OpMul | Operator::OpDiv |
-                    Operator::OpMod => CGType::int_t(),
+                    Operator::OpMod | Operator::OpBitAnd | Operator::OpBitOr |
+                    Operator::OpBitXor => lhs_t,
+                    Operator::OpShl | Operator::OpShr => {
+                        // only the lowest 5 bits of the shift count are used
+                        rhs_val = ctx.cg.add_int_op(rhs_val, Operator::OpBitAnd, Val::Int(31));
+                        lhs_t
+                    }
                     _ => unreachable!(),
                 };
-                (ctx.cg.add_int_op(lhs_val, *op, rhs_val), t)
+                (generate_arith_op(lhs_t, lhs_val, *op, rhs_val, ctx), t)
             }
             Expr::ENew(ref t) => {
-                let t = ctx.to_cgtype(t);
-                let obj = ctx.cg.add_call(t, format!("@._new_{}", t.get_id()), &vec![]);
-                (obj, t)
+                let t = ctx.get_cgtype(t);
+                if t.is_list() || t.is_map() || t.is_builder() {
+                    (generate_new_collection(t, ctx), t)
+                } else {
+                    let class = ctx.get_class_data(t.get_id()).ident.clone();
+                    let obj = ctx.cg.add_call(t, format!("@{}", new_symbol(&class)), &vec![]);
+                    (obj, t)
+                }
             }
-            Expr::ENewArray(ref t, ref size) => {
-                let (size_val, _) = size.generate_code(ctx);
-                let arr_t = CGType::arr_t(ctx.to_cgtype(t).as_raw());
-                let reg = ctx.cg.new_arr(arr_t, size_val);
+            Expr::ENewArray(ref t, ref sizes) => {
+                let mut arr_t = ctx.get_cgtype(t);
+                let mut size_vals: Vec<Val> = Vec::new();
+                for size in sizes {
+                    size_vals.push(size.generate_code(ctx).0);
+                    arr_t = arr_t.arr_of();
+                }
+                let reg = ctx.cg.new_arr(arr_t, &size_vals);
                 (reg, arr_t)
             }
+            Expr::EArrayLit(..) => unreachable!("array literal without destination type"),
+            Expr::ECast(ref t, ref e) => {
+                let t = ctx.get_cgtype(t);
+                let (val, e_t) = e.generate_code(ctx);
+                (ctx.cg.add_cast(val, e_t, t), t)
+            }
+            Expr::ECond(ref cond, ref e1, ref e2) => generate_cond(cond, e1, e2, ctx),
         };
         if t == CGType::str_t() {
             ctx.add_string_tmp(reg);
@@ -63,41 +104,127 @@ impl GenerateCode<(Val, CGType)> for Expr {
 }
 
 fn generate_call(ident: &FieldGet, args: &Vec<Expr>, ctx: &mut Context) -> (Val, CGType) {
-    let (obj, func_name): (Option<(Val, ClassId)>, Ident) = ident.generate_code(ctx);
+    let call = match prepare_call(ident, args, ctx) {
+        PreparedCall::Generated(val, t) => return (val, t),
+        PreparedCall::Call(call) => call,
+    };
+    if let Some(ref func_name) = call.direct {
+        if let Some(res) = generate_inlined_call(func_name, &call.args, ctx) {
+            return res;
+        }
+    }
+    (ctx.cg.add_call(call.ret_type, call.func, &call.args), call.ret_type)
+}
+
+pub struct Call {
+    pub func: String,
+    pub direct: Option<Ident>, // called function, if not virtual
+    pub ret_type: CGType,
+    pub args: Vec<(Val, CGType)>, // evaluated, with self first for methods
+}
+
+pub enum PreparedCall {
+    Generated(Val, CGType), // methods of collections and enums are generated in place
+    Call(Call),
+}
+
+// Evaluates the object and args of the call.
+pub fn prepare_call(ident: &FieldGet, args: &Vec<Expr>, ctx: &mut Context) -> PreparedCall {
+    let (obj, func_name): (Option<(Val, CGType)>, Ident) = ident.generate_code(ctx);
+    let obj = match obj {
+        Some((val, obj_t)) if obj_t.is_list() || obj_t.is_map() || obj_t.is_builder() => {
+            let (val, t) = generate_collection_call(val, obj_t, &func_name, args, ctx);
+            return PreparedCall::Generated(val, t);
+        }
+        // the only method of enums is name()
+        Some((val, obj_t)) if obj_t.is_enum() => {
+            let func = format!("@{}", enum_name_symbol(ctx.get_enum_name(obj_t.get_enum_id())));
+            let ret_type = CGType::str_t();
+            let val = ctx.cg.add_call(ret_type, func, &vec![(val, obj_t)]);
+            return PreparedCall::Generated(val, ret_type);
+        }
+        Some((val, obj_t)) => Some((val, obj_t.get_id())),
+        None if ctx.is_extern(&func_name) => {
+            let (val, t) = generate_extern_call(&func_name, args, ctx);
+            return PreparedCall::Generated(val, t);
+        }
+        None => None,
+    };
     let ret_type;
     let arg_types;
     let func;
-    if obj.is_some() {
-        let id = obj.unwrap().1;
-        ctx.cg.add_comment(format!("Accessing vtable begin"));
+    let mut direct: Option<Ident> = None;
+    if let Some((obj_val, id)) = obj {
         let vtable_pos = *ctx.get_class_data(id).vtable.idxs.get(&func_name).unwrap();
         let f_info = ctx.get_class_data(id).vtable.fs[vtable_pos].clone();
 
         ret_type = f_info.ret_type;
         arg_types = f_info.arg_types.clone();
-        func = format!("{}",
-                       ctx.cg.load_vtable_entry(obj.unwrap().0, id, f_info.as_ptr(), vtable_pos));
-        ctx.cg.add_comment(format!("Accessing vtable end"));
+        if ctx.is_overridden(id, vtable_pos) {
+            ctx.cg.add_comment("Accessing vtable begin".to_string());
+            func = format!("{}",
+                           ctx.cg.load_vtable_entry(obj_val, id, f_info.as_ptr(), vtable_pos));
+            ctx.cg.add_comment("Accessing vtable end".to_string());
+        } else {
+            if ctx.opts.report_devirt {
+                let call = Expr::ECall(ident.clone(), args.clone());
+                ctx.add_devirtualized(&call, f_info.class_id, &func_name);
+            }
+            func = format!("@{}", f_info.real_ident);
+            direct = Some(f_info.real_ident);
+        }
     } else {
         ret_type = ctx.get_ret_type(&func_name);
         arg_types = ctx.get_arg_types(&func_name);
-        func = format!("@{}", func_name);
-    }
-
-    let mut arg_vals: Vec<(Val, CGType)> = args.iter().map(|a| a.generate_code(ctx)).collect();
-    if let Some((val, id)) = obj {
-        arg_vals.insert(0, (val, CGType::obj_t(id)));
+        func = format!("@{}", ctx.get_func_symbol(&func_name));
+        direct = Some(func_name);
     }
 
+    let mut arg_types = arg_types.into_iter();
     let mut final_args: Vec<(Val, CGType)> = Vec::new();
-    for ((mut arg_val, arg_t), arg_dst_t) in arg_vals.into_iter().zip(arg_types) {
-        if arg_t != arg_dst_t && arg_t != CGType::null_t() {
-            arg_val = ctx.cg.bitcast_object(arg_val, arg_t, arg_dst_t);
+    if let Some((mut val, id)) = obj {
+        let (obj_t, self_t) = (CGType::obj_t(id), arg_types.next().unwrap());
+        if obj_t != self_t {
+            val = ctx.cg.bitcast_object(val, obj_t, self_t);
         }
-        final_args.push((arg_val, arg_dst_t));
+        final_args.push((val, self_t));
+    }
+    for (arg, arg_dst_t) in args.iter().zip(arg_types) {
+        final_args.push((generate_init_expr(arg, arg_dst_t, ctx), arg_dst_t));
     }
 
-    (ctx.cg.add_call(ret_type, func, &final_args), ret_type)
+    PreparedCall::Call(Call {
+        func,
+        direct,
+        ret_type,
+        args: final_args,
+    })
+}
+
+// Generates value to be stored in a location of type t (variable, argument etc.)
+pub fn generate_init_expr(e: &Expr, t: CGType, ctx: &mut Context) -> Val {
+    let (mut val, expr_t) = match *e {
+        Expr::EArrayLit(ref elems) => (generate_array_lit(elems, t, ctx), t),
+        _ => e.generate_code(ctx),
+    };
+    if t != expr_t && expr_t != CGType::null_t() {
+        val = ctx.cg.bitcast_object(val, expr_t, t);
+    }
+    val
+}
+
+fn generate_array_lit(elems: &[Expr], arr_t: CGType, ctx: &mut Context) -> Val {
+    let elem_t = arr_t.arr_elem_t();
+    let arr = ctx.cg.alloc_arr(arr_t, Val::Int(elems.len() as i32));
+    for (idx, e) in elems.iter().enumerate() {
+        let val = generate_init_expr(e, elem_t, ctx);
+        if elem_t == CGType::str_t() {
+            ctx.cg.retain_string(val);
+        }
+        let (elem_addr, _) = ctx.cg.get_nth_arr_elem(arr, arr_t, Val::Int(idx as i32));
+        ctx.cg.add_store(elem_addr, elem_t, val);
+    }
+    arr
 }
 
 fn generate_or(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> (Val, CGType) {
@@ -158,15 +285,142 @@ fn generate_and(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> (Val, CGType) {
     (res_reg, CGType::bool_t())
 }
 
+// Each branch is converted to the common type of both branches. The conversion
+// of the first one is emitted after the second one has been generated.
+fn generate_cond(cond: &Expr, e1: &Expr, e2: &Expr, ctx: &mut Context) -> (Val, CGType) {
+    let e1_label = ctx.cg.next_label();
+    let e1_end_label = ctx.cg.next_label();
+    let e2_label = ctx.cg.next_label();
+    let end_label = ctx.cg.next_label();
+
+    let (cond_val, _) = cond.generate_code(ctx);
+    ctx.cg.add_cond_jump(cond_val, e1_label, e2_label);
+
+    ctx.cg.add_label(e1_label);
+    let (e1_val, e1_t) = ctx.in_new_scope(|ctx| generate_cond_branch(e1, ctx));
+    ctx.cg.add_jump(e1_end_label);
+
+    ctx.cg.add_label(e2_label);
+    let (e2_val, e2_t) = ctx.in_new_scope(|ctx| generate_cond_branch(e2, ctx));
+    let t = common_cgtype(e1_t, e2_t, ctx);
+    let e2_val = convert_cond_branch(e2_val, e2_t, t, ctx);
+    let e2_block = ctx.cg.get_current_label();
+    ctx.cg.add_jump(end_label);
+
+    ctx.cg.add_label(e1_end_label);
+    let e1_val = convert_cond_branch(e1_val, e1_t, t, ctx);
+    ctx.cg.add_jump(end_label);
+
+    ctx.cg.add_label(end_label);
+    if t == CGType::void_t() {
+        return (Val::Reg(ctx.cg.dummy_reg()), t);
+    }
+    if t == CGType::null_t() {
+        return (Val::Null, t);
+    }
+    (ctx.cg.add_phi(t, (e1_val, e1_end_label), (e2_val, e2_block)), t)
+}
+
+fn generate_cond_branch(e: &Expr, ctx: &mut Context) -> (Val, CGType) {
+    let (val, t) = e.generate_code(ctx);
+    if t == CGType::str_t() {
+        ctx.cg.retain_string(val);
+    }
+    ctx.release_local_strings();
+    (val, t)
+}
+
+fn convert_cond_branch(val: Val, from: CGType, to: CGType, ctx: &mut Context) -> Val {
+    if from != to && from != CGType::null_t() {
+        ctx.cg.bitcast_object(val, from, to)
+    } else {
+        val
+    }
+}
+
+// least common supertype of types accepted by the type checker
+fn common_cgtype(t1: CGType, t2: CGType, ctx: &Context) -> CGType {
+    if t1 == t2 || t2 == CGType::null_t() {
+        return t1;
+    }
+    if t1 == CGType::null_t() {
+        return t2;
+    }
+    let mut id = t1.get_id();
+    while !ctx.is_subclass_of(t2.get_id(), id) {
+        id = ctx.get_class_data(id).get_super();
+    }
+    CGType::obj_t(id)
+}
+
+enum AddOperand {
+    Strings(Vec<Val>), // parts of a concatenation, in order
+    Value(Val, CGType),
+}
+
+// Concatenations of more than two strings are done at once, the types are known
+// only here, so the chains are flattened during generation.
 fn generate_add(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> (Val, CGType) {
-    let (lhs_val, t) = lhs.generate_code(ctx);
-    let (rhs_val, _) = rhs.generate_code(ctx);
-    let val = match t.as_raw() {
-        RawType::TInt => ctx.cg.add_int_op(lhs_val, Operator::OpAdd, rhs_val),
-        RawType::TString => ctx.cg.concatenate_str(lhs_val, rhs_val),
-        _ => unreachable!(),
+    let parts = match generate_add_operands(lhs, rhs, ctx) {
+        AddOperand::Strings(parts) => parts,
+        AddOperand::Value(val, t) => return (val, t),
     };
-    (val, t)
+    let val = match parts.len() {
+        2 => ctx.cg.concatenate_str(parts[0], parts[1]),
+        _ => ctx.cg.concatenate_strs(&parts),
+    };
+    (val, CGType::str_t())
+}
+
+fn generate_add_operands(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> AddOperand {
+    match generate_add_operand(lhs, ctx) {
+        AddOperand::Strings(mut parts) => {
+            match generate_add_operand(rhs, ctx) {
+                AddOperand::Strings(rhs_parts) => parts.extend(rhs_parts),
+                AddOperand::Value(val, _) => parts.push(val),
+            }
+            AddOperand::Strings(parts)
+        }
+        AddOperand::Value(lhs_val, t) => {
+            let (rhs_val, _) = rhs.generate_code(ctx);
+            AddOperand::Value(generate_arith_op(t, lhs_val, Operator::OpAdd, rhs_val, ctx), t)
+        }
+    }
+}
+
+fn generate_add_operand(e: &Expr, ctx: &mut Context) -> AddOperand {
+    match *e {
+        Expr::EBinOp(ref lhs, Operator::OpAdd, ref rhs) => generate_add_operands(lhs, rhs, ctx),
+        _ => {
+            let (val, t) = e.generate_code(ctx);
+            match t == CGType::str_t() {
+                true => AddOperand::Strings(vec![val]),
+                false => AddOperand::Value(val, t),
+            }
+        }
+    }
+}
+
+// Integer arithmetic wraps around on overflow, unless overflow checking is enabled
+pub fn generate_arith_op(t: CGType, lhs: Val, op: Operator, rhs: Val, ctx: &mut Context) -> Val {
+    let is_checked_op = op == Operator::OpAdd || op == Operator::OpSub || op == Operator::OpMul;
+    let is_div = op == Operator::OpDiv || op == Operator::OpMod;
+    let is_int = t == CGType::int_t() || t == CGType::long_t();
+    if ctx.opts.check_overflow && is_checked_op && is_int {
+        ctx.cg.add_checked_op(t, lhs, op, rhs)
+    } else if is_div && is_int && may_be_minus_one(rhs) {
+        ctx.cg.add_div_op(t, lhs, op, rhs, ctx.opts.check_overflow)
+    } else {
+        ctx.cg.add_op(t, lhs, op, rhs)
+    }
+}
+
+fn may_be_minus_one(val: Val) -> bool {
+    match val {
+        Val::Int(x) => x == -1,
+        Val::Long(x) => x == -1,
+        _ => true,
+    }
 }
 
 fn generate_neq(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> (Val, CGType) {
@@ -182,8 +436,15 @@ fn generate_eq(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> (Val, CGType) {
         return (Val::Int(1), CGType::bool_t());
     }
 
-    if let (RawType::TObject(lhs_id), RawType::TObject(rhs_id)) = (t1.as_raw(), t2.as_raw()) {
-        return generate_objects_eq(lhs_val, lhs_id, rhs_val, rhs_id, ctx);
+    if t1.is_obj() && t2.is_obj() {
+        return generate_objects_eq(lhs_val, t1.get_id(), rhs_val, t2.get_id(), ctx);
+    }
+
+    if t1 == CGType::str_t() && t2 == CGType::str_t() {
+        let result = ctx.cg.add_call(CGType::bool_t(),
+                                     "@._str_eq".to_string(),
+                                     &vec![(lhs_val, t1), (rhs_val, t2)]);
+        return (result, CGType::bool_t());
     }
 
     let t = if t1 != null_t { t1 } else { t2 };
@@ -191,6 +452,15 @@ fn generate_eq(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> (Val, CGType) {
     (result, CGType::bool_t())
 }
 
+// lexicographic order of the contents
+fn generate_str_cmp(lhs: Val, op: Operator, rhs: Val, ctx: &mut Context) -> (Val, CGType) {
+    let str_t = CGType::str_t();
+    let cmp = ctx.cg.add_call(CGType::int_t(),
+                              "@._str_cmp".to_string(),
+                              &vec![(lhs, str_t), (rhs, str_t)]);
+    (ctx.cg.add_int_op(cmp, op, Val::Int(0)), CGType::bool_t())
+}
+
 fn generate_objects_eq(mut lhs: Val,
                        lhs_id: usize,
                        mut rhs: Val,
@@ -218,16 +488,19 @@ impl GenerateCode<(Val, CGType)> for Lit {
     fn generate_code(&self, ctx: &mut Context) -> (Val, CGType) {
         match *self {
             Lit::LInt(x) => (Val::Int(x), CGType::int_t()),
+            Lit::LLong(x) => (Val::Long(x), CGType::long_t()),
+            Lit::LDouble(x) => (Val::Double(x), CGType::double_t()),
+            Lit::LChar(c) => (Val::Int(c as i8 as i32), CGType::char_t()),
             Lit::LTrue => (Val::Int(1), CGType::bool_t()),
             Lit::LFalse => (Val::Int(0), CGType::bool_t()),
             Lit::LString(ref s) => {
                 let reg = ctx.get_str_const(s);
-                let val = ctx.cg.add_str_load(s.len(), reg);
+                let val = ctx.cg.add_str_load(reg);
                 (val, CGType::str_t())
             }
             Lit::LNull(None) => (Val::Null, CGType::null_t()),
             Lit::LNull(Some(ref cname)) => {
-                (Val::Null, ctx.to_cgtype(&Type::TObject(cname.clone())))
+                (Val::Null, ctx.get_cgtype(&Type::TObject(cname.clone())))
             }
         }
     }
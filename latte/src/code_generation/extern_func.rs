@@ -0,0 +1,63 @@
+use ast::{BuiltinFunc, Expr, Ident};
+
+use code_generation::cg_type::*;
+use code_generation::code_generator::*;
+use code_generation::context::Context;
+use code_generation::expr::*;
+
+// Extern functions are called with C types. Strings are passed as char*, valid only
+// during the call, and returned characters are copied, so they stay owned by the
+// called function. A returned NULL is the empty string. Arrays are passed as pointers
+// to struct { int32_t length; T *elems; }, booleans as bool and chars as char.
+pub fn add_extern_func(f: &BuiltinFunc, ctx: &mut Context) {
+    let ret_type = ctx.get_cgtype(&f.ret_type);
+    let arg_types: Vec<CGType> = f.args.iter().map(|t| ctx.get_cgtype(t)).collect();
+    let args_str: Vec<String> = arg_types.iter().map(|t| c_arg_type(*t)).collect();
+    ctx.cg.add_raw_func_declare(&c_ret_type(ret_type), &f.ident.0, &args_str);
+    ctx.add_extern_func(&f.ident, arg_types, ret_type);
+}
+
+pub fn generate_extern_call(ident: &Ident, args: &[Expr], ctx: &mut Context) -> (Val, CGType) {
+    let str_t = CGType::str_t();
+    let mut c_args: Vec<String> = Vec::new();
+    for (arg, t) in args.iter().zip(ctx.get_arg_types(ident)) {
+        let mut val = generate_init_expr(arg, t, ctx);
+        if t == str_t {
+            val = ctx.cg.add_raw_call("i8*", "._str_chars", vec![format!("{} {}", t, val)]);
+        }
+        c_args.push(format!("{} {}", c_arg_type(t), val));
+    }
+    let ret_type = ctx.get_ret_type(ident);
+    let res = ctx.cg.add_raw_call(&c_ret_type(ret_type), &ident.0, c_args);
+    match ret_type == str_t {
+        true => {
+            let s = ctx.cg.add_raw_call("%string_t*", "._copy_str", vec![format!("i8* {}", res)]);
+            (s, str_t)
+        }
+        false => (res, ret_type),
+    }
+}
+
+fn c_arg_type(t: CGType) -> String {
+    if t == CGType::str_t() {
+        "i8*".to_string()
+    } else if t == CGType::bool_t() {
+        "i1 zeroext".to_string()
+    } else if t == CGType::char_t() {
+        "i8 signext".to_string()
+    } else {
+        format!("{}", t)
+    }
+}
+
+fn c_ret_type(t: CGType) -> String {
+    if t == CGType::str_t() {
+        "i8*".to_string()
+    } else if t == CGType::bool_t() {
+        "zeroext i1".to_string()
+    } else if t == CGType::char_t() {
+        "signext i8".to_string()
+    } else {
+        format!("{}", t)
+    }
+}
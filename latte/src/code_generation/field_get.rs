@@ -11,6 +11,10 @@ impl GenerateCode<(Val, CGType)> for FieldGet {
             FieldGet::Direct(ref ident) => {
                 if ctx.var_exists(ident) {
                     ctx.get_var(ident)
+                } else if let Some(field) = get_static_field(ident, ctx) {
+                    field
+                } else if ctx.global_exists(ident) && !is_self_field(ident, ctx) {
+                    ctx.get_global(ident)
                 } else {
                     self_access(ident).generate_code(ctx)
                 }
@@ -18,11 +22,21 @@ impl GenerateCode<(Val, CGType)> for FieldGet {
             FieldGet::IdxAccess(ref arr, ref idx) => {
                 let (struct_ptr, arr_t) = arr.generate_code(ctx);
                 let (idx_val, _) = idx.generate_code(ctx);
-                ctx.cg.get_nth_arr_elem(struct_ptr, arr_t, idx_val)
+                if arr_t.is_list() {
+                    let elem_t = ctx.get_list_elem_t(arr_t);
+                    (ctx.cg.get_nth_list_elem(struct_ptr, elem_t, idx_val), elem_t)
+                } else {
+                    ctx.cg.get_nth_arr_elem(struct_ptr, arr_t, idx_val)
+                }
             }
             FieldGet::Indirect(ref expr, ref field) => {
+                if let Some(id) = as_class_id(expr, ctx) {
+                    return ctx.find_static_field(id, field).unwrap();
+                }
                 let (mut struct_addr, mut struct_type) = expr.generate_code(ctx);
-                if struct_type.is_arr() {
+                if struct_type.is_arr() || struct_type.is_list() || struct_type.is_map() ||
+                   struct_type.is_builder() {
+                    // size is the first field
                     (ctx.cg.get_field_addr(struct_addr, struct_type, 0), CGType::int_t())
                 } else {
                     let mut id = struct_type.get_id();
@@ -45,25 +59,79 @@ impl GenerateCode<(Val, CGType)> for FieldGet {
     }
 }
 
-impl GenerateCode<(Option<(Val, usize)>, Ident)> for FieldGet {
-    fn generate_code(&self, ctx: &mut Context) -> (Option<(Val, usize)>, Ident) {
+impl GenerateCode<(Option<(Val, CGType)>, Ident)> for FieldGet {
+    fn generate_code(&self, ctx: &mut Context) -> (Option<(Val, CGType)>, Ident) {
         match *self {
             FieldGet::Direct(ref ident) => {
-                if ctx.func_exists(ident) {
+                if let Some(func) = get_static_method(ident, ctx) {
+                    (None, func)
+                } else if ctx.func_exists(ident) {
                     (None, ident.clone())
                 } else {
                     self_access(ident).generate_code(ctx)
                 }
             }
             FieldGet::Indirect(ref expr, ref field) => {
+                if let Some(id) = as_class_id(expr, ctx) {
+                    return (None, ctx.find_static_method(id, field).unwrap());
+                }
                 let (val, obj_t) = expr.generate_code(ctx);
-                (Some((val, obj_t.get_id())), field.clone())
+                (Some((val, obj_t)), field.clone())
             }
             _ => unreachable!(),
         }
     }
 }
 
+fn is_self_field(ident: &Ident, ctx: &Context) -> bool {
+    match ctx.class {
+        Some(id) => ctx.has_field(id, ident),
+        None => false,
+    }
+}
+
+fn get_static_field(ident: &Ident, ctx: &Context) -> Option<(Val, CGType)> {
+    ctx.static_class.and_then(|id| ctx.find_static_field(id, ident))
+}
+
+fn get_static_method(ident: &Ident, ctx: &Context) -> Option<Ident> {
+    ctx.static_class.and_then(|id| ctx.find_static_method(id, ident))
+}
+
+// <class name>.<static member>, unless the name is hidden by another identifier
+fn as_class_id(e: &Expr, ctx: &Context) -> Option<ClassId> {
+    match *e {
+        Expr::EVar(FieldGet::Direct(ref ident)) if !is_hidden(ident, ctx) &&
+                                                   ctx.class_exists(ident) => {
+            Some(ctx.get_class_id(ident))
+        }
+        _ => None,
+    }
+}
+
+// <enum name>.<variant>, unless the name is hidden by another identifier
+pub fn as_enum_variant(field: &FieldGet, ctx: &Context) -> Option<(Val, CGType)> {
+    match *field {
+        FieldGet::Indirect(ref e, ref variant) => {
+            match **e {
+                Expr::EVar(FieldGet::Direct(ref ident)) if !is_hidden(ident, ctx) &&
+                                                           ctx.enum_exists(ident) => {
+                    let id = ctx.get_enum_id(ident);
+                    Some((Val::Int(ctx.get_variant_tag(id, variant)), CGType::enum_t(id)))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn is_hidden(ident: &Ident, ctx: &Context) -> bool {
+    ctx.var_exists(ident) || ctx.global_exists(ident) || ctx.func_exists(ident) ||
+    is_self_field(ident, ctx) || get_static_field(ident, ctx).is_some() ||
+    get_static_method(ident, ctx).is_some()
+}
+
 fn self_access(ident: &Ident) -> FieldGet {
     let self_fg = FieldGet::Direct(Ident(format!("self")));
     FieldGet::Indirect(Box::new(Expr::EVar(self_fg)), ident.clone())
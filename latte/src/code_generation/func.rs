@@ -1,37 +1,76 @@
 use ast::{Func, Ident};
 
+use mangling::*;
 use static_analysis::has_return::*;
 
 use code_generation::cg_type::*;
 use code_generation::context::Context;
 use code_generation::generate::*;
+use code_generation::tail_call::*;
 
 impl GenerateCode<()> for Func {
     fn generate_code(&self, ctx: &mut Context) {
-        let mut arg_types: Vec<CGType> = self.args.iter().map(|a| ctx.to_cgtype(&a.t)).collect();
+        let mut arg_types: Vec<CGType> = self.args.iter().map(|a| ctx.get_cgtype(&a.t)).collect();
         let mut arg_idents: Vec<Ident> = self.args.iter().map(|a| a.ident.clone()).collect();
         let mut name = self.ident.0.clone();
-        if ctx.class.is_some() {
-            arg_types.insert(0, CGType::obj_t(ctx.class.unwrap()));
+        ctx.current_func = name.clone();
+        if let Some(id) = ctx.static_class {
+            let class = ctx.get_class_data(id).ident.clone();
+            ctx.current_func = format!("{}.{}", class, name);
+            name = match ctx.class {
+                Some(_) => method_symbol(&class, self),
+                None => static_method_symbol(&class, self),
+            };
+        } else if ctx.class.is_none() {
+            name = ctx.get_func_symbol(&self.ident);
+        }
+        if let Some(id) = ctx.class {
+            arg_types.insert(0, CGType::obj_t(id));
             arg_idents.insert(0, Ident(format!("self")));
-            name = format!("class{}.{}", ctx.class.unwrap(), name);
         }
         let arg_types = arg_types;
         let arg_idents = arg_idents;
 
-        let ret_type = ctx.to_cgtype(&self.ret_type);
+        let ret_type = ctx.get_cgtype(&self.ret_type);
+        let current_func = ctx.current_func.clone();
+        ctx.cg.begin_debug_func(&current_func, &name, self.line);
         let arg_addr_regs = ctx.cg.add_func_begin(ret_type, &name, &arg_types);
-        for (ident, (arg_addr, t)) in arg_idents.into_iter().zip(arg_addr_regs) {
-            ctx.set_var(ident, arg_addr, t);
+        let is_main = ctx.static_class.is_none() && self.ident.0 == "main";
+        if is_main && ctx.has_globals() {
+            ctx.cg.add_call(CGType::void_t(), "@._init_globals".to_string(), &vec![]);
         }
+        let body_label = match has_self_tail_call(self) {
+            true => {
+                let l = ctx.cg.next_label();
+                ctx.cg.add_jump(l);
+                ctx.cg.add_label(l);
+                Some(l)
+            }
+            false => None,
+        };
+        ctx.tail_target = Some(TailTarget {
+            symbol: name.clone(),
+            args: arg_addr_regs.clone(),
+            body_label,
+        });
         ctx.in_new_scope(|ctx| {
+            // args are set in the function scope, so that string args
+            // don't leak into the functions generated later
+            let args = arg_idents.iter().zip(&arg_addr_regs);
+            for (i, (ident, &(arg_addr, t))) in args.enumerate() {
+                ctx.set_arg(ident.clone(), arg_addr, t, i + 1);
+            }
             ctx.ret_type = ret_type;
             self.body.generate_code(ctx);
             if !self.body.has_return() {
                 ctx.release_all_strings();
             }
         });
+        ctx.tail_target = None;
         ctx.cg.add_func_end(ret_type);
         ctx.cg.reset();
+        if is_main && name != "main" {
+            ctx.cg.add_main_with_args(&name);
+        }
     }
 }
@@ -0,0 +1,60 @@
+use ast::Ident;
+
+use static_analysis::has_return::*;
+
+use code_generation::cg_type::*;
+use code_generation::code_generator::*;
+use code_generation::context::Context;
+use code_generation::generate::*;
+
+// Generates the body of the called function in place of the call, args are
+// already evaluated (with self first for methods). Returns store the result
+// and jump to the end of the body, the result is loaded there.
+pub fn generate_inlined_call(func_name: &Ident,
+                             args: &Vec<(Val, CGType)>,
+                             ctx: &mut Context)
+                             -> Option<(Val, CGType)> {
+    let (f, class, is_method) = ctx.get_inline_func(func_name)?;
+    let ret_type = ctx.get_ret_type(func_name);
+    let mut arg_idents: Vec<Ident> = f.args.iter().map(|a| a.ident.clone()).collect();
+    if is_method {
+        arg_idents.insert(0, Ident("self".to_string()));
+    }
+    let arg_idents = arg_idents;
+
+    ctx.cg.add_comment(format!("Inlined {} begin", func_name));
+    let end_label = ctx.cg.next_label();
+    let ret_addr = match ret_type == CGType::void_t() {
+        true => Val::Reg(ctx.cg.dummy_reg()),
+        false => ctx.cg.add_alloca(ret_type),
+    };
+    ctx.in_inlined_body(func_name,
+                        class,
+                        is_method,
+                        ret_type,
+                        (end_label, ret_addr),
+                        |ctx| {
+        ctx.in_new_scope(|ctx| {
+            for (ident, &(val, t)) in arg_idents.iter().zip(args) {
+                let addr = ctx.cg.add_alloca(t);
+                ctx.cg.add_store(addr, t, val);
+                if t == CGType::str_t() {
+                    ctx.cg.retain_string(val);
+                }
+                ctx.set_var(ident.clone(), addr, t);
+            }
+            f.body.generate_code(ctx);
+            if !f.body.has_return() {
+                ctx.release_all_strings();
+                ctx.cg.add_jump(end_label);
+            }
+        });
+    });
+    ctx.cg.add_label(end_label);
+    ctx.cg.add_comment(format!("Inlined {} end", func_name));
+
+    match ret_type == CGType::void_t() {
+        true => Some((ret_addr, ret_type)),
+        false => Some((ctx.cg.add_load(ret_addr, ret_type), ret_type)),
+    }
+}
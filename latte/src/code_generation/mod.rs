@@ -1,32 +1,56 @@
 use std::collections::HashMap;
 use std::io::{self, Write};
 use std::fs::File;
+use std::path::Path;
 
 use ast::*;
 use builtins::*;
+use mangling::*;
+use optimization;
+use options::Options;
 use static_analysis::collect_string_lit::*;
 
+pub use self::stmt::default_value;
+pub use self::tail_call::has_self_tail_call;
+
 mod cg_type;
 mod class;
 mod class_data;
+mod collection;
 mod code_generator;
 mod context;
+mod debug_info;
+mod enum_def;
 mod expr;
+mod extern_func;
 mod field_get;
 mod func;
 mod generate;
+mod global;
+mod inline;
 mod stmt;
+mod tail_call;
 mod utils;
 mod vtable;
 
 use self::cg_type::*;
 use self::class_data::*;
 use self::context::*;
+use self::extern_func::*;
 use self::generate::*;
 use self::vtable::*;
 
-pub fn gen_llvm(p: &Program, out_file: &mut File) -> Result<(), io::Error> {
-    let mut ctx = create_context(p);
+// Returns the report of devirtualized calls, empty unless --report-devirt is given.
+// The input file is referred to by the debug information emitted with -g.
+pub fn gen_llvm(p: &Program,
+                opts: &Options,
+                input: &Path,
+                out_file: &mut File)
+                -> Result<Vec<String>, io::Error> {
+    let mut ctx = create_context(p, opts);
+    if opts.debug_info {
+        ctx.cg.enable_debug_info(input);
+    }
 
     for lit in collect_string_lit(p) {
         let reg = ctx.cg.add_string_constant(&lit);
@@ -38,26 +62,51 @@ pub fn gen_llvm(p: &Program, out_file: &mut File) -> Result<(), io::Error> {
         match *def {
             Def::DClass(ref c) => c.generate_code(&mut ctx),
             Def::DFunc(ref f) => f.generate_code(&mut ctx),
+            Def::DEnum(ref e) => e.generate_code(&mut ctx),
+            Def::DGlobal(..) |
+            Def::DExtern(..) => {}
         }
     }
+    if ctx.has_globals() {
+        global::generate_init(p, &mut ctx);
+    }
+    ctx.cg.add_debug_metadata();
 
     for line in ctx.cg.get_out() {
         writeln!(out_file, "{}", line)?;
     }
-    Ok(())
+    Ok(ctx.take_devirtualized())
 }
 
-fn create_context(p: &Program) -> Context {
-    let mut ctx = Context::new();
+fn create_context(p: &Program, opts: &Options) -> Context {
+    let mut ctx = Context::new(opts);
+
+    for def in &p.0 {
+        if let Def::DEnum(ref e) = *def {
+            ctx.add_enum(&e.name, &e.variants);
+        }
+    }
 
     add_classes(p, &mut ctx);
     ctx.cg.add_empty_line();
 
     add_funcs(p, &mut ctx);
+    add_inline_funcs(p, &mut ctx);
+
+    add_globals(p, &mut ctx);
+    ctx.cg.add_empty_line();
 
     ctx.cg.add_comment(format!("builtin functions"));
     add_builtins(&mut ctx);
     ctx.cg.add_empty_line();
+
+    ctx.cg.add_comment("extern functions".to_string());
+    for def in &p.0 {
+        if let Def::DExtern(ref f) = *def {
+            add_extern_func(f, &mut ctx);
+        }
+    }
+    ctx.cg.add_empty_line();
     ctx
 }
 
@@ -75,6 +124,10 @@ fn add_classes(p: &Program, ctx: &mut Context) {
         class_ids.insert(class.name.clone(), id);
     }
 
+    for (cname, id) in &class_ids {
+        ctx.add_class_id(cname, *id);
+    }
+
     for class in &classes {
         let id = class_ids.get(&class.name).unwrap();
         let mut class_data = ClassData::new(*id, &class.name);
@@ -83,14 +136,7 @@ fn add_classes(p: &Program, ctx: &mut Context) {
         }
 
         for v in &class.vars {
-            let t = match v.t {
-                Type::TObject(ref cname) => {
-                    let id = class_ids.get(cname).unwrap();
-                    CGType::obj_t(*id)
-                }
-                _ => CGType::from(&v.t),
-            };
-            class_data.add_field(&v.ident, t);
+            class_data.add_field(&v.ident, ctx.get_cgtype(&v.t));
         }
 
         ctx.add_class(*id, class_data);
@@ -108,19 +154,20 @@ fn add_classes(p: &Program, ctx: &mut Context) {
     }
 }
 
-fn get_vtable(c: &Class, classes: &HashMap<Ident, &Class>, ctx: &Context) -> VTable {
+fn get_vtable(c: &Class, classes: &HashMap<Ident, &Class>, ctx: &mut Context) -> VTable {
     let mut vtable = VTable::new();
     if let Some(ref super_ident) = c.superclass {
         let superclass = classes.get(super_ident).unwrap();
         vtable = get_vtable(superclass, classes, ctx);
     }
 
+    let id = ctx.get_class_id(&c.name);
     for f in &c.methods {
-        let real_ident = Ident(format!("class{}.{}", ctx.get_class_id(&c.name), f.ident));
-        let ret_type = ctx.to_cgtype(&f.ret_type);
-        let mut arg_types: Vec<CGType> = f.args.iter().map(|v| ctx.to_cgtype(&v.t)).collect();
-        arg_types.insert(0, ctx.to_cgtype(&Type::TObject(c.name.clone())));
-        vtable.set_func(&f.ident, VTableEntry::new(real_ident, ret_type, arg_types));
+        let real_ident = Ident(method_symbol(&c.name, f));
+        let ret_type = ctx.get_cgtype(&f.ret_type);
+        let mut arg_types: Vec<CGType> = f.args.iter().map(|v| ctx.get_cgtype(&v.t)).collect();
+        arg_types.insert(0, ctx.get_cgtype(&Type::TObject(c.name.clone())));
+        vtable.set_func(&f.ident, VTableEntry::new(real_ident, id, ret_type, arg_types));
     }
 
     vtable
@@ -130,31 +177,97 @@ fn add_funcs(p: &Program, ctx: &mut Context) {
     for def in &p.0 {
         match *def {
             Def::DFunc(ref f) => {
-                let ret_type = ctx.to_cgtype(&f.ret_type);
-                let arg_types = f.args.iter().map(|arg| ctx.to_cgtype(&arg.t)).collect();
+                let ret_type = ctx.get_cgtype(&f.ret_type);
+                let arg_types = f.args.iter().map(|arg| ctx.get_cgtype(&arg.t)).collect();
                 ctx.add_func(&f.ident, arg_types, ret_type);
             }
             Def::DClass(ref c) => {
-                let obj_t = ctx.to_cgtype(&Type::TObject(c.name.clone()));
+                let obj_t = ctx.get_cgtype(&Type::TObject(c.name.clone()));
                 for f in &c.methods {
-                    let ret_type = ctx.to_cgtype(&f.ret_type);
+                    let ret_type = ctx.get_cgtype(&f.ret_type);
                     let mut arg_types: Vec<CGType> =
-                        f.args.iter().map(|arg| ctx.to_cgtype(&arg.t)).collect();
+                        f.args.iter().map(|arg| ctx.get_cgtype(&arg.t)).collect();
                     arg_types.insert(0, obj_t);
                     let arg_types = arg_types;
-                    ctx.add_func(&Ident(format!("class{}.{}", obj_t.get_id(), f.ident)),
-                                 arg_types,
-                                 ret_type);
+                    ctx.add_func(&Ident(method_symbol(&c.name, f)), arg_types, ret_type);
+                }
+                for f in &c.static_methods {
+                    let ret_type = ctx.get_cgtype(&f.ret_type);
+                    let arg_types = f.args.iter().map(|arg| ctx.get_cgtype(&arg.t)).collect();
+                    let symbol = Ident(static_method_symbol(&c.name, f));
+                    ctx.add_func(&symbol, arg_types, ret_type);
+                    ctx.add_static_method(obj_t.get_id(), &f.ident, &symbol);
+                }
+            }
+            Def::DGlobal(..) |
+            Def::DEnum(..) |
+            Def::DExtern(..) => {}
+        }
+    }
+}
+
+fn add_inline_funcs(p: &Program, ctx: &mut Context) {
+    let candidates = optimization::inline_candidates(p);
+    for def in &p.0 {
+        match *def {
+            Def::DFunc(ref f) => {
+                if candidates.contains(&optimization::Node::Func(f.ident.clone())) {
+                    ctx.add_inline_func(f.ident.clone(), f, None, false);
+                }
+            }
+            Def::DClass(ref c) => {
+                let id = ctx.get_class_id(&c.name);
+                for f in &c.methods {
+                    let node = optimization::Node::Method(c.name.clone(), f.ident.clone());
+                    if candidates.contains(&node) {
+                        let ident = Ident(method_symbol(&c.name, f));
+                        ctx.add_inline_func(ident, f, Some(id), true);
+                    }
+                }
+                for f in &c.static_methods {
+                    let node = optimization::Node::Method(c.name.clone(), f.ident.clone());
+                    if candidates.contains(&node) {
+                        let ident = Ident(static_method_symbol(&c.name, f));
+                        ctx.add_inline_func(ident, f, Some(id), false);
+                    }
+                }
+            }
+            Def::DGlobal(..) |
+            Def::DEnum(..) |
+            Def::DExtern(..) => {}
+        }
+    }
+}
+
+fn add_globals(p: &Program, ctx: &mut Context) {
+    for def in &p.0 {
+        match *def {
+            Def::DGlobal(ref g) => {
+                let t = ctx.get_cgtype(&g.t);
+                for decl in &g.decls {
+                    ctx.add_global(decl.get_ident(), t);
+                }
+            }
+            Def::DClass(ref c) => {
+                let id = ctx.get_class_id(&c.name);
+                for g in &c.static_vars {
+                    let t = ctx.get_cgtype(&g.t);
+                    for decl in &g.decls {
+                        ctx.add_static_field(id, decl.get_ident(), t);
+                    }
                 }
             }
+            Def::DFunc(..) |
+            Def::DEnum(..) |
+            Def::DExtern(..) => {}
         }
     }
 }
 
 fn add_builtins(ctx: &mut Context) {
     for f in get_builtin_functions() {
-        let ret_type = ctx.to_cgtype(&f.ret_type);
-        let arg_types = f.args.iter().map(|t| ctx.to_cgtype(&t)).collect();
+        let ret_type = ctx.get_cgtype(&f.ret_type);
+        let arg_types = f.args.iter().map(|t| ctx.get_cgtype(t)).collect();
         ctx.cg.add_func_declare(ret_type, &f.ident.0, &arg_types);
         ctx.add_func(&f.ident, arg_types, ret_type);
     }
@@ -4,8 +4,12 @@ use static_analysis::has_return::*;
 
 use code_generation::cg_type::*;
 use code_generation::code_generator::*;
+use code_generation::collection::*;
 use code_generation::context::*;
+use code_generation::expr::*;
+use code_generation::field_get::as_enum_variant;
 use code_generation::generate::*;
+use code_generation::tail_call::*;
 
 impl GenerateCode<()> for Vec<Stmt> {
     fn generate_code(&self, ctx: &mut Context) {
@@ -20,9 +24,10 @@ impl GenerateCode<()> for Vec<Stmt> {
 
 impl GenerateCode<()> for Stmt {
     fn generate_code(&self, ctx: &mut Context) {
-        match *self {
-            Stmt::SEmpty => {}
-            Stmt::SBlock(ref stmts) => {
+        ctx.set_line(self.line);
+        match self.kind {
+            StmtKind::SEmpty => {}
+            StmtKind::SBlock(ref stmts) => {
                 ctx.in_new_scope(|ctx| {
                     stmts.generate_code(ctx);
                     if !stmts.has_return() {
@@ -30,54 +35,60 @@ impl GenerateCode<()> for Stmt {
                     }
                 })
             }
-            Stmt::SDecl(_, ref decls) => {
+            StmtKind::SDecl(_, ref decls) => {
                 decls.generate_code(ctx);
             }
-            Stmt::SAssign(ref ident, ref e) => {
+            StmtKind::SAssign(ref ident, ref e) => {
                 let (addr_reg, t) = ident.generate_code(ctx);
-                let (mut val_reg, expr_t) = e.generate_code(ctx);
+                let val_reg = generate_init_expr(e, t, ctx);
                 if t == CGType::str_t() {
                     let old_val_reg = ctx.cg.add_load(addr_reg, t);
                     ctx.cg.retain_string(val_reg);
                     ctx.cg.release_string(old_val_reg);
                 }
-                if t != expr_t && expr_t != CGType::null_t() {
-                    val_reg = ctx.cg.bitcast_object(val_reg, expr_t, t);
-                }
                 ctx.cg.add_store(addr_reg, t, val_reg);
             }
-            Stmt::SInc(ref ident) => {
+            StmtKind::SInc(ref ident) => {
                 let (addr_reg, t) = ident.generate_code(ctx);
                 let mut val_reg = ctx.cg.add_load(addr_reg, t);
-                val_reg = ctx.cg.add_int_op(val_reg, Operator::OpAdd, Val::Int(1));
+                val_reg = generate_arith_op(t, val_reg, Operator::OpAdd, Val::Int(1), ctx);
                 ctx.cg.add_store(addr_reg, t, val_reg);
             }
-            Stmt::SDec(ref ident) => {
+            StmtKind::SDec(ref ident) => {
                 let (addr_reg, t) = ident.generate_code(ctx);
                 let mut val_reg = ctx.cg.add_load(addr_reg, t);
-                val_reg = ctx.cg.add_int_op(val_reg, Operator::OpSub, Val::Int(1));
+                val_reg = generate_arith_op(t, val_reg, Operator::OpSub, Val::Int(1), ctx);
                 ctx.cg.add_store(addr_reg, t, val_reg);
             }
-            Stmt::SReturnE(ref e) => {
-                let (mut val_reg, expr_t) = e.generate_code(ctx);
+            StmtKind::SReturnE(Expr::ECall(ref ident, ref args)) if ctx.inline_ret.is_none() => {
+                generate_tail_call(ident, args, ctx);
+            }
+            StmtKind::SReturnE(ref e) => {
                 let t = ctx.ret_type;
+                let val_reg = generate_init_expr(e, t, ctx);
                 if t == CGType::str_t() {
                     ctx.cg.retain_string(val_reg);
                 }
-                if t != expr_t && expr_t != CGType::null_t() {
-                    val_reg = ctx.cg.bitcast_object(val_reg, expr_t, t);
-                }
                 ctx.release_all_strings();
-                ctx.cg.add_ret(t, val_reg);
+                match ctx.inline_ret {
+                    Some((end_label, ret_addr)) => {
+                        ctx.cg.add_store(ret_addr, t, val_reg);
+                        ctx.cg.add_jump(end_label);
+                    }
+                    None => ctx.cg.add_ret(t, val_reg),
+                }
             }
-            Stmt::SReturn => {
+            StmtKind::SReturn => {
                 ctx.release_all_strings();
-                ctx.cg.add_ret_void();
+                match ctx.inline_ret {
+                    Some((end_label, _)) => ctx.cg.add_jump(end_label),
+                    None => ctx.cg.add_ret_void(),
+                }
             }
-            Stmt::SExpr(ref e) => {
+            StmtKind::SExpr(ref e) => {
                 e.generate_code(ctx);
             }
-            Stmt::SIf(ref cond, ref s) => {
+            StmtKind::SIf(ref cond, ref s) => {
                 let if_label = ctx.cg.next_label();
                 let end_label = ctx.cg.next_label();
 
@@ -95,7 +106,7 @@ impl GenerateCode<()> for Stmt {
 
                 ctx.cg.add_label(end_label);
             }
-            Stmt::SIfElse(ref cond, ref if_true, ref if_false) => {
+            StmtKind::SIfElse(ref cond, ref if_true, ref if_false) => {
                 let if_label = ctx.cg.next_label();
                 let else_label = ctx.cg.next_label();
                 let end_label = ctx.cg.next_label();
@@ -127,7 +138,7 @@ impl GenerateCode<()> for Stmt {
                     ctx.cg.add_label(end_label);
                 }
             }
-            Stmt::SWhile(ref cond, ref s) => {
+            StmtKind::SWhile(ref cond, ref s) => {
                 let cond_label = ctx.cg.next_label();
                 let body_label = ctx.cg.next_label();
                 let end_label = ctx.cg.next_label();
@@ -152,8 +163,80 @@ impl GenerateCode<()> for Stmt {
 
                 ctx.cg.add_label(end_label);
             }
-            Stmt::SFor(_, ref ident, ref arr, ref stmt) => {
-                let (arr, arr_t) = arr.generate_code(ctx);
+            StmtKind::SSwitch(ref e, ref cases, ref default) => {
+                let (val, t) = e.generate_code(ctx);
+                let case_labels: Vec<Label> = cases.iter().map(|_| ctx.cg.next_label()).collect();
+                let default_label = ctx.cg.next_label();
+                let end_label = ctx.cg.next_label();
+                let has_return = self.has_return();
+
+                match jump_table(cases, ctx) {
+                    Some(ref table) if t != CGType::str_t() && is_dense(table) => {
+                        let table: Vec<(i32, Label)> =
+                            table.iter().map(|&(v, case)| (v, case_labels[case])).collect();
+                        ctx.cg.add_switch(t, val, default_label, &table);
+                    }
+                    _ => {
+                        // chain of comparisons, in the order of labels;
+                        // labels are evaluated first, so that their strings can be released
+                        // on every path
+                        let mut label_vals: Vec<(Val, Label)> = Vec::new();
+                        for (case, &case_label) in cases.iter().zip(&case_labels) {
+                            for label in &case.labels {
+                                label_vals.push((label.generate_code(ctx).0, case_label));
+                            }
+                        }
+                        for (label_val, case_label) in label_vals {
+                            let is_eq = if t == CGType::str_t() {
+                                ctx.cg.add_call(CGType::bool_t(),
+                                                "@._str_eq".to_string(),
+                                                &vec![(val, t), (label_val, t)])
+                            } else {
+                                ctx.cg.add_op(t, val, Operator::OpEq, label_val)
+                            };
+                            let next_label = ctx.cg.next_label();
+                            ctx.cg.add_cond_jump(is_eq, case_label, next_label);
+                            ctx.cg.add_label(next_label);
+                        }
+                        ctx.cg.add_jump(default_label);
+                    }
+                }
+
+                let empty = Vec::new();
+                let bodies = cases.iter()
+                    .map(|case| &case.body)
+                    .zip(case_labels)
+                    .chain(Some((default.as_ref().unwrap_or(&empty), default_label)));
+                for (body, label) in bodies {
+                    ctx.cg.add_label(label);
+                    ctx.in_new_scope(|ctx| {
+                        body.generate_code(ctx);
+                        if !body.has_return() {
+                            ctx.release_local_strings();
+                        }
+                    });
+                    if !body.has_return() {
+                        ctx.cg.add_jump(end_label);
+                    }
+                }
+                if !has_return {
+                    ctx.cg.add_label(end_label);
+                }
+            }
+            StmtKind::SFor(ref t, ref ident, ref arr, ref stmt) => {
+                let (arr, arr_t) = match *arr {
+                    Expr::EArrayLit(..) => {
+                        let arr_t = ctx.get_cgtype(t).arr_of();
+                        (generate_init_expr(arr, arr_t, ctx), arr_t)
+                    }
+                    _ => arr.generate_code(ctx),
+                };
+                // Maps are iterated over a snapshot of their keys.
+                let (arr, arr_t) = if arr_t.is_map() {
+                    generate_map_keys(arr, arr_t, ctx)
+                } else {
+                    (arr, arr_t)
+                };
                 let before_loop = ctx.cg.next_label();
                 let loop_begin = ctx.cg.next_label();
                 let loop_body = ctx.cg.next_label();
@@ -177,8 +260,12 @@ impl GenerateCode<()> for Stmt {
 
                 ctx.cg.add_label(loop_body);
                 ctx.in_new_scope(|mut ctx| {
-                    let (elem_addr, elem_t) = ctx.cg
-                        .get_nth_arr_elem(arr, arr_t, idx_reg);
+                    let (elem_addr, elem_t) = if arr_t.is_list() {
+                        let elem_t = ctx.get_list_elem_t(arr_t);
+                        (ctx.cg.get_nth_list_elem(arr, elem_t, idx_reg), elem_t)
+                    } else {
+                        ctx.cg.get_nth_arr_elem(arr, arr_t, idx_reg)
+                    };
                     let loop_var_addr = ctx.cg.add_alloca(elem_t);
                     let val = ctx.cg.add_load(elem_addr, elem_t);
                     ctx.cg.add_store(loop_var_addr, elem_t, val);
@@ -217,28 +304,18 @@ impl GenerateCode<()> for VarDecl {
     fn generate_code(&self, ctx: &mut Context) {
         match *self {
             VarDecl::Init(ref t, ref ident, ref e) => {
-                let t = ctx.to_cgtype(t);
+                let t = ctx.get_cgtype(t);
                 let addr_reg = ctx.cg.add_alloca(t);
-                let (mut val_reg, expr_t) = e.generate_code(ctx);
+                let val_reg = generate_init_expr(e, t, ctx);
                 if t == CGType::str_t() {
                     ctx.cg.retain_string(val_reg);
                 }
-                if t != expr_t && expr_t != CGType::null_t() {
-                    val_reg = ctx.cg.bitcast_object(val_reg, expr_t, t);
-                }
                 ctx.cg.add_store(addr_reg, t, val_reg);
                 ctx.set_var(ident.clone(), addr_reg, t);
             }
             VarDecl::NoInit(ref t, ref ident) => {
-                let default_lit = match *t {
-                    Type::TInt => Lit::LInt(0),
-                    Type::TBool => Lit::LFalse,
-                    Type::TString => Lit::LString(String::new()),
-                    Type::TObject(..) |
-                    Type::TArray(..) => Lit::LNull(None),
-                    _ => unreachable!(),
-                };
-                let t = ctx.to_cgtype(t);
+                let default_lit = default_value(t);
+                let t = ctx.get_cgtype(t);
                 let addr_reg = ctx.cg.add_alloca(t);
                 let (val_reg, _) = Expr::ELit(default_lit).generate_code(ctx);
                 if t == CGType::str_t() {
@@ -250,3 +327,57 @@ impl GenerateCode<()> for VarDecl {
         }
     }
 }
+
+// Values of the labels with indexes of their cases, if all of them are known integers.
+// The first occurrence of a value wins.
+fn jump_table(cases: &[Case], ctx: &Context) -> Option<Vec<(i32, usize)>> {
+    let mut table: Vec<(i32, usize)> = Vec::new();
+    for (idx, case) in cases.iter().enumerate() {
+        for label in &case.labels {
+            let v = label_value(label, ctx)?;
+            if table.iter().all(|&(other, _)| other != v) {
+                table.push((v, idx));
+            }
+        }
+    }
+    Some(table)
+}
+
+fn label_value(label: &Expr, ctx: &Context) -> Option<i32> {
+    match *label {
+        Expr::ELit(Lit::LInt(v)) => Some(v),
+        Expr::ENeg(ref e) => label_value(e, ctx).map(|v| v.wrapping_neg()),
+        Expr::EVar(ref field) => {
+            match as_enum_variant(field, ctx) {
+                Some((Val::Int(tag), _)) => Some(tag),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+// Sparse values would make the jump table mostly empty.
+fn is_dense(table: &[(i32, usize)]) -> bool {
+    let min = table.iter().map(|&(v, _)| v as i64).min().unwrap_or(0);
+    let max = table.iter().map(|&(v, _)| v as i64).max().unwrap_or(0);
+    !table.is_empty() && max - min < 2 * table.len() as i64
+}
+
+pub fn default_value(t: &Type) -> Lit {
+    match *t {
+        Type::TInt |
+        Type::TEnum(..) => Lit::LInt(0),
+        Type::TLong => Lit::LLong(0),
+        Type::TDouble => Lit::LDouble(0.0),
+        Type::TChar => Lit::LChar(0),
+        Type::TBool => Lit::LFalse,
+        Type::TString => Lit::LString(String::new()),
+        Type::TObject(..) |
+        Type::TStringBuilder |
+        Type::TArray(..) |
+        Type::TList(..) |
+        Type::TMap(..) => Lit::LNull(None),
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,97 @@
+use ast::*;
+
+use code_generation::cg_type::*;
+use code_generation::code_generator::*;
+use code_generation::context::Context;
+use code_generation::expr::*;
+use code_generation::inline::*;
+
+// The function being generated, self-recursive tail calls store the new args
+// in the addresses of the args and jump to the body.
+#[derive(Debug, Clone)]
+pub struct TailTarget {
+    pub symbol: String,
+    pub args: Vec<(Val, CGType)>, // addresses of args, with self first for methods
+    pub body_label: Option<Label>,
+}
+
+// Whether the function returns the result of its own call, the label of the
+// body is generated only then. Methods calls on other objects are included.
+pub fn has_self_tail_call(f: &Func) -> bool {
+    f.body.iter().any(|s| has_tail_call_to(s, &f.ident))
+}
+
+fn has_tail_call_to(s: &Stmt, ident: &Ident) -> bool {
+    match s.kind {
+        StmtKind::SReturnE(Expr::ECall(FieldGet::Direct(ref called), _)) |
+        StmtKind::SReturnE(Expr::ECall(FieldGet::Indirect(_, ref called), _)) => called == ident,
+        StmtKind::SBlock(ref stmts) => stmts.iter().any(|s| has_tail_call_to(s, ident)),
+        StmtKind::SIf(_, ref s) |
+        StmtKind::SWhile(_, ref s) |
+        StmtKind::SFor(_, _, _, ref s) => has_tail_call_to(s, ident),
+        StmtKind::SIfElse(_, ref s1, ref s2) => {
+            has_tail_call_to(s1, ident) || has_tail_call_to(s2, ident)
+        }
+        StmtKind::SSwitch(_, ref cases, ref default) => {
+            cases.iter().flat_map(|case| &case.body).any(|s| has_tail_call_to(s, ident)) ||
+            default.iter().flatten().any(|s| has_tail_call_to(s, ident))
+        }
+        _ => false,
+    }
+}
+
+// Generates `return f(args)`. Self-recursive calls become jumps to the body
+// of the function. Other calls are marked tail if the strings of the function
+// can be released before the call, i.e. no string is passed to it. The result
+// of such a call is returned without retaining it.
+pub fn generate_tail_call(ident: &FieldGet, args: &Vec<Expr>, ctx: &mut Context) {
+    let call = match prepare_call(ident, args, ctx) {
+        PreparedCall::Generated(val, t) => return generate_return(val, t, ctx),
+        PreparedCall::Call(call) => call,
+    };
+    if let Some(ref func_name) = call.direct {
+        if let Some((val, t)) = generate_inlined_call(func_name, &call.args, ctx) {
+            return generate_return(val, t, ctx);
+        }
+    }
+
+    let target = ctx.tail_target.clone().unwrap();
+    let is_self_call = call.direct.as_ref().map(|ident| &ident.0) == Some(&target.symbol);
+    if let (true, Some(body_label)) = (is_self_call, target.body_label) {
+        ctx.cg.add_comment("Tail recursion".to_string());
+        for &(val, t) in &call.args {
+            if t == CGType::str_t() {
+                ctx.cg.retain_string(val);
+            }
+        }
+        ctx.release_all_strings();
+        for (&(addr, t), &(val, _)) in target.args.iter().zip(&call.args) {
+            ctx.cg.add_store(addr, t, val);
+        }
+        ctx.cg.add_jump(body_label);
+    } else if call.args.iter().all(|&(_, t)| t != CGType::str_t()) {
+        ctx.release_all_strings();
+        let arg_types: Vec<CGType> = call.args.iter().map(|&(_, t)| t).collect();
+        let target_arg_types: Vec<CGType> = target.args.iter().map(|&(_, t)| t).collect();
+        let must = arg_types == target_arg_types && call.ret_type == ctx.ret_type;
+        let val = ctx.cg.add_tail_call(must, call.ret_type, call.func, &call.args);
+        generate_ret(val, call.ret_type, ctx);
+    } else {
+        let val = ctx.cg.add_call(call.ret_type, call.func, &call.args);
+        generate_return(val, call.ret_type, ctx);
+    }
+}
+
+// Returns the result of the call, the function owns it if it's a string.
+fn generate_return(val: Val, t: CGType, ctx: &mut Context) {
+    ctx.release_all_strings();
+    generate_ret(val, t, ctx);
+}
+
+fn generate_ret(mut val: Val, t: CGType, ctx: &mut Context) {
+    let ret_type = ctx.ret_type;
+    if t != ret_type && t != CGType::null_t() {
+        val = ctx.cg.bitcast_object(val, t, ret_type);
+    }
+    ctx.cg.add_ret(ret_type, val);
+}
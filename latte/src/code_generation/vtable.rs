@@ -45,14 +45,20 @@ impl VTable {
 #[derive(Debug, Clone)]
 pub struct VTableEntry {
     pub real_ident: Ident,
+    pub class_id: ClassId, // class which defines the method
     pub ret_type: CGType,
     pub arg_types: Vec<CGType>,
 }
 
 impl VTableEntry {
-    pub fn new(real_ident: Ident, ret_type: CGType, arg_types: Vec<CGType>) -> VTableEntry {
+    pub fn new(real_ident: Ident,
+               class_id: ClassId,
+               ret_type: CGType,
+               arg_types: Vec<CGType>)
+               -> VTableEntry {
         VTableEntry {
             real_ident: real_ident,
+            class_id,
             ret_type: ret_type,
             arg_types: arg_types,
         }
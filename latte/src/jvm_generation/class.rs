@@ -0,0 +1,76 @@
+use ast::{Class, Type};
+
+use jvm_generation::class_data::*;
+use jvm_generation::context::*;
+use jvm_generation::generate::*;
+use jvm_generation::jvm_type::*;
+
+// Each class is a JVM class with the same name, its static fields and methods
+// are static members of it.
+impl GenerateCode<()> for Class {
+    fn generate_code(&self, ctx: &mut Context) {
+        let id = ctx.get_class_id(&self.name);
+        ctx.in_new_class(&self.name.0, |ctx| {
+            ctx.in_new_scope(|ctx| {
+                generate_header(self, id, ctx);
+                generate_init(id, ctx);
+
+                ctx.class = Some(id);
+                ctx.static_class = Some(id);
+                for m in &self.methods {
+                    m.generate_code(ctx);
+                }
+                ctx.class = None;
+                for m in &self.static_methods {
+                    m.generate_code(ctx);
+                }
+                ctx.static_class = None;
+            })
+        });
+    }
+}
+
+fn generate_header(c: &Class, id: ClassId, ctx: &mut Context) {
+    let super_name = get_super_name(id, ctx);
+    ctx.add_directive(format!(".class public {}", quote(&c.name.0)));
+    ctx.add_directive(format!(".super {}", quote(&super_name)));
+    ctx.add_empty_line();
+    for v in &c.vars {
+        ctx.add_directive(format!(".field public {} {}", quote(&v.ident.0), descriptor(&v.t)));
+    }
+    for g in &c.static_vars {
+        for decl in &g.decls {
+            let name = quote(&decl.get_ident().0);
+            let field = format!(".field public static {} {}", name, descriptor(&g.t));
+            ctx.add_directive(field);
+        }
+    }
+    ctx.add_empty_line();
+}
+
+// Fields of other types are zeroed by the JVM, strings are empty.
+fn generate_init(id: ClassId, ctx: &mut Context) {
+    let super_name = get_super_name(id, ctx);
+    let limits_line = ctx.begin_method(".method public <init>()V".to_string());
+    let obj_t = Type::TObject(ctx.get_class_data(id).ident.clone());
+    let obj = load_instr(&obj_t, ctx.add_local(&obj_t));
+    ctx.add_line(obj.clone());
+    ctx.add_line(format!("invokespecial {}", quote(&format!("{}/<init>()V", super_name))));
+
+    let fields = ctx.get_class_data(id).fields.clone();
+    for (field, t) in fields {
+        if t == Type::TString {
+            let field_ref = ctx.get_class_data(id).get_field_ref(&field);
+            ctx.add_store(&LValue::Field(obj.clone(), field_ref), &string_const(b""));
+        }
+    }
+    ctx.add_line("return".to_string());
+    ctx.end_method(limits_line);
+}
+
+fn get_super_name(id: ClassId, ctx: &Context) -> String {
+    match ctx.get_class_data(id).super_id {
+        Some(super_id) => ctx.get_class_data(super_id).ident.0.clone(),
+        None => "java/lang/Object".to_string(),
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use ast::{Ident, Type};
+
+use jvm_generation::jvm_type::descriptor;
+
+pub type ClassId = usize;
+pub type EnumId = usize;
+
+// Classes are JVM classes with the same names, the fields of a class are declared
+// in it and inherited by its subclasses.
+#[derive(Debug, Clone)]
+pub struct ClassData {
+    pub super_id: Option<ClassId>,
+    pub ident: Ident,
+    pub fields: Vec<(Ident, Type)>,
+    field_ids: HashMap<Ident, usize>,
+    pub vtable: VTable,
+}
+
+impl ClassData {
+    pub fn new(ident: &Ident) -> ClassData {
+        ClassData {
+            super_id: None,
+            ident: ident.clone(),
+            fields: Vec::new(),
+            field_ids: HashMap::new(),
+            vtable: VTable::new(),
+        }
+    }
+
+    pub fn set_super(&mut self, id: ClassId) {
+        self.super_id = Some(id);
+    }
+
+    pub fn get_super(&self) -> ClassId {
+        self.super_id.unwrap()
+    }
+
+    pub fn has_field(&self, ident: &Ident) -> bool {
+        self.field_ids.contains_key(ident)
+    }
+
+    pub fn add_field(&mut self, ident: &Ident, t: &Type) {
+        let id = self.fields.len();
+        self.field_ids.insert(ident.clone(), id);
+        self.fields.push((ident.clone(), t.clone()));
+    }
+
+    pub fn get_field_type(&self, ident: &Ident) -> Type {
+        self.fields[*self.field_ids.get(ident).unwrap()].1.clone()
+    }
+
+    // <class>/<field> <descriptor>, as used by getfield and putfield
+    pub fn get_field_ref(&self, ident: &Ident) -> String {
+        let t = self.get_field_type(ident);
+        format!("'{}/{}' {}", self.ident, ident, descriptor(&t))
+    }
+}
+
+// Methods are called with invokevirtual, the table is used to find their types
+// and to check whether they are overridden.
+#[derive(Debug, Clone)]
+pub struct VTable {
+    pub fs: Vec<VTableEntry>,
+    pub idxs: HashMap<Ident, usize>,
+}
+
+impl VTable {
+    pub fn new() -> VTable {
+        VTable {
+            fs: Vec::new(),
+            idxs: HashMap::new(),
+        }
+    }
+
+    pub fn set_func(&mut self, ident: &Ident, f: VTableEntry) {
+        let cur_idx = self.idxs.get(ident).copied();
+        match cur_idx {
+            None => {
+                let idx = self.fs.len();
+                self.idxs.insert(ident.clone(), idx);
+                self.fs.push(f);
+            }
+            Some(idx) => {
+                self.fs[idx] = f;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VTableEntry {
+    pub real_ident: String, // <class>/<method>, as in invokevirtual
+    pub class_id: ClassId,  // class which defines the method
+    pub ret_type: Type,
+    pub arg_types: Vec<Type>, // without self
+}
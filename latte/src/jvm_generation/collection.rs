@@ -0,0 +1,160 @@
+use ast::{Expr, Ident, Type};
+
+use jvm_generation::context::*;
+use jvm_generation::expr::*;
+use jvm_generation::generate::*;
+use jvm_generation::jvm_type::*;
+
+pub fn generate_new_collection(t: &Type, ctx: &mut Context) -> String {
+    let class = class_name(t);
+    ctx.add_line(format!("new {}", class));
+    ctx.add_line("dup".to_string());
+    ctx.add_line(format!("invokespecial {}/<init>()V", class));
+    ctx.add_tmp(t)
+}
+
+pub fn generate_collection_call(obj: &str,
+                                obj_t: &Type,
+                                method: &Ident,
+                                args: &[Expr],
+                                ctx: &mut Context)
+                                -> (String, Type) {
+    match *obj_t {
+        Type::TStringBuilder => generate_builder_call(obj, method, args, ctx),
+        Type::TList(ref elem_t) => generate_list_call(obj, elem_t, method, args, ctx),
+        Type::TMap(ref key_t, ref value_t) => {
+            generate_map_call(obj, key_t, value_t, method, args, ctx)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// Elements are boxed, indexes are checked by the runtime.
+fn generate_list_call(list: &str,
+                      elem_t: &Type,
+                      method: &Ident,
+                      args: &[Expr],
+                      ctx: &mut Context)
+                      -> (String, Type) {
+    match method.0.as_ref() {
+        "push" => {
+            let elem = generate_init_expr(&args[0], elem_t, ctx);
+            ctx.add_line(list.to_string());
+            push_boxed(&elem, elem_t, ctx);
+            ctx.add_line("invokevirtual java/util/ArrayList/add(Ljava/lang/Object;)Z".to_string());
+            ctx.add_line("pop".to_string());
+            (String::new(), Type::TVoid)
+        }
+        "pop" => {
+            ctx.add_line(list.to_string());
+            ctx.call_runtime("listPop(Ljava/util/ArrayList;)Ljava/lang/Object;");
+            (unbox_tmp(elem_t, ctx), elem_t.clone())
+        }
+        "insert" => {
+            let (idx, _) = args[0].generate_code(ctx);
+            let elem = generate_init_expr(&args[1], elem_t, ctx);
+            ctx.add_line(list.to_string());
+            ctx.add_line(idx);
+            push_boxed(&elem, elem_t, ctx);
+            ctx.call_runtime("listInsert(Ljava/util/ArrayList;ILjava/lang/Object;)V");
+            (String::new(), Type::TVoid)
+        }
+        "remove" => {
+            let (idx, _) = args[0].generate_code(ctx);
+            ctx.add_line(list.to_string());
+            ctx.add_line(idx);
+            ctx.call_runtime("listRemove(Ljava/util/ArrayList;I)Ljava/lang/Object;");
+            (unbox_tmp(elem_t, ctx), elem_t.clone())
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn generate_map_call(map: &str,
+                     key_t: &Type,
+                     value_t: &Type,
+                     method: &Ident,
+                     args: &[Expr],
+                     ctx: &mut Context)
+                     -> (String, Type) {
+    match method.0.as_ref() {
+        "put" => {
+            let key = generate_init_expr(&args[0], key_t, ctx);
+            let value = generate_init_expr(&args[1], value_t, ctx);
+            ctx.add_line(map.to_string());
+            push_boxed(&key, key_t, ctx);
+            push_boxed(&value, value_t, ctx);
+            add_call(map_method("put(Ljava/lang/Object;Ljava/lang/Object;)V"), &Type::TVoid, ctx)
+        }
+        "get" => {
+            let key = generate_init_expr(&args[0], key_t, ctx);
+            ctx.add_line(map.to_string());
+            push_boxed(&key, key_t, ctx);
+            ctx.add_line(map_method("get(Ljava/lang/Object;)Ljava/lang/Object;"));
+            (unbox_tmp(value_t, ctx), value_t.clone())
+        }
+        "contains" => {
+            let key = generate_init_expr(&args[0], key_t, ctx);
+            ctx.add_line(map.to_string());
+            push_boxed(&key, key_t, ctx);
+            add_call(map_method("contains(Ljava/lang/Object;)Z"), &Type::TBool, ctx)
+        }
+        "remove" => {
+            let key = generate_init_expr(&args[0], key_t, ctx);
+            ctx.add_line(map.to_string());
+            push_boxed(&key, key_t, ctx);
+            add_call(map_method("remove(Ljava/lang/Object;)V"), &Type::TVoid, ctx)
+        }
+        "keys" => generate_map_keys(map, key_t, ctx),
+        _ => unreachable!(),
+    }
+}
+
+// Chars are appended as single characters, toString returns a new string.
+fn generate_builder_call(builder: &str,
+                         method: &Ident,
+                         args: &[Expr],
+                         ctx: &mut Context)
+                         -> (String, Type) {
+    match method.0.as_ref() {
+        "append" | "appendChar" => {
+            let (val, t) = args[0].generate_code(ctx);
+            ctx.add_line(builder.to_string());
+            ctx.add_line(val);
+            ctx.add_line(format!("invokevirtual java/lang/StringBuilder/append({})\
+                                  Ljava/lang/StringBuilder;",
+                                 descriptor(&t)));
+            ctx.add_line("pop".to_string());
+            (String::new(), Type::TVoid)
+        }
+        "toString" => {
+            ctx.add_line(builder.to_string());
+            let call = "invokevirtual java/lang/StringBuilder/toString()Ljava/lang/String;";
+            add_call(call.to_string(), &Type::TString, ctx)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// the keys in the order of iteration of the map
+pub fn generate_map_keys(map: &str, key_t: &Type, ctx: &mut Context) -> (String, Type) {
+    let keys_t = Type::TList(Box::new(key_t.clone()));
+    ctx.add_line(map.to_string());
+    ctx.add_line(map_method("keys()Ljava/util/ArrayList;"));
+    (ctx.add_tmp(&keys_t), keys_t)
+}
+
+fn map_method(method: &str) -> String {
+    format!("invokevirtual {}/{}", MAP_CLASS, method)
+}
+
+fn push_boxed(val: &str, t: &Type, ctx: &mut Context) {
+    ctx.add_line(val.to_string());
+    ctx.add_lines(box_instrs(t));
+}
+
+// the object on top of the stack as a value of the given type
+fn unbox_tmp(t: &Type, ctx: &mut Context) -> String {
+    ctx.add_lines(unbox_instrs(t));
+    ctx.add_tmp(t)
+}
@@ -0,0 +1,497 @@
+use std::cmp::max;
+use std::collections::{HashMap, HashSet};
+
+use ast::{Ident, Type};
+use options::Options;
+
+use jvm_generation::class_data::*;
+use jvm_generation::jvm_type::*;
+
+// Location of a value: a local, a static field, a field of an object or an element
+// of an array or a list. Fields are given as <class>/<field> <descriptor>.
+// Sizes of arrays and collections can only be read.
+#[derive(Debug, Clone)]
+pub enum LValue {
+    Local(usize, Type),
+    Static(String),
+    Field(String, String), // object and field
+    Elem(String, String, Type), // array and index
+    ListElem(String, String, Type), // list and index
+    Size(String, Type), // object and its type
+}
+
+#[derive(Debug)]
+pub struct Context {
+    vars: HashMap<Ident, (usize, Type)>,
+    globals: HashMap<Ident, (String, Type)>,
+    static_fields: HashMap<(ClassId, Ident), (String, Type)>,
+    static_methods: HashSet<(ClassId, Ident)>,
+    func_types: HashMap<Ident, (Vec<Type>, Type)>,
+    builtins: HashSet<Ident>, // static methods of the runtime class
+    externs: HashSet<Ident>,
+    pub program: String, // class of functions and globals
+    pub ret_type: Type,
+    pub class: Option<ClassId>,
+    pub static_class: Option<ClassId>, // class whose static members are accessible directly
+    pub opts: Options,
+    pub tail_target: Option<TailTarget>,
+
+    classes: HashMap<ClassId, ClassData>,
+    class_ids: HashMap<Ident, ClassId>,
+    enum_ids: HashMap<Ident, EnumId>,
+    enum_variants: Vec<Vec<Ident>>,
+
+    locals: usize,
+    max_locals: usize,
+    stack: i32,
+    max_stack: i32,
+    labels: usize,
+    out: Vec<String>,
+    class_files: Vec<(String, Vec<String>)>, // finished classes and their lines
+}
+
+// The method being generated, self-recursive tail calls set the params
+// and jump to the beginning of the body.
+#[derive(Debug, Clone)]
+pub struct TailTarget {
+    pub symbol: String,
+    pub params: Vec<(usize, Type)>, // with self first for methods
+    pub body_label: Option<usize>,
+}
+
+impl Context {
+    pub fn new(opts: &Options, program: &str) -> Context {
+        Context {
+            vars: HashMap::new(),
+            globals: HashMap::new(),
+            static_fields: HashMap::new(),
+            static_methods: HashSet::new(),
+            func_types: HashMap::new(),
+            builtins: HashSet::new(),
+            externs: HashSet::new(),
+            program: program.to_string(),
+            ret_type: Type::TVoid,
+            class: None,
+            static_class: None,
+            opts: opts.clone(),
+            tail_target: None,
+
+            classes: HashMap::new(),
+            class_ids: HashMap::new(),
+            enum_ids: HashMap::new(),
+            enum_variants: Vec::new(),
+
+            locals: 0,
+            max_locals: 0,
+            stack: 0,
+            max_stack: 0,
+            labels: 0,
+            out: Vec::new(),
+            class_files: Vec::new(),
+        }
+    }
+
+    pub fn in_new_scope<F, R>(&mut self, f: F) -> R
+        where F: Fn(&mut Context) -> R
+    {
+        let old_vars = self.vars.clone();
+        let res = f(self);
+        self.vars = old_vars;
+        res
+    }
+
+    // Lines of another class, the current one is continued afterwards.
+    pub fn in_new_class<F>(&mut self, name: &str, f: F)
+        where F: Fn(&mut Context)
+    {
+        let old_out = self.out.drain(..).collect();
+        f(self);
+        let lines = self.out.drain(..).collect();
+        self.class_files.push((name.to_string(), lines));
+        self.out = old_out;
+    }
+
+    // Loop over indexes from 0 to size - 1, the index is a new int local.
+    pub fn in_new_loop<F, R>(&mut self, idx: usize, size: &str, f: F) -> R
+        where F: Fn(&mut Context) -> R
+    {
+        let (cond_label, end_label) = (self.next_label(), self.next_label());
+        self.add_line("iconst_0".to_string());
+        self.add_line(store_instr(&Type::TInt, idx));
+        self.add_label(cond_label);
+        self.add_line(load_instr(&Type::TInt, idx));
+        self.add_line(size.to_string());
+        self.add_line(format!("if_icmpge L{}", end_label));
+        let res = self.in_new_scope(f);
+        self.add_line(format!("iinc {} 1", idx));
+        self.add_line(format!("goto L{}", cond_label));
+        self.add_label(end_label);
+        res
+    }
+
+    pub fn func_exists(&self, ident: &Ident) -> bool {
+        self.func_types.contains_key(ident)
+    }
+
+    pub fn get_arg_types(&self, ident: &Ident) -> Vec<Type> {
+        self.func_types.get(ident).unwrap().0.clone()
+    }
+
+    pub fn get_ret_type(&self, ident: &Ident) -> Type {
+        self.func_types.get(ident).unwrap().1.clone()
+    }
+
+    // Static methods are named <class>.<name> in the context, so that they can't clash
+    // with functions. Returns <class>/<method><descriptor>, as in invokestatic.
+    pub fn get_func_symbol(&self, ident: &Ident) -> String {
+        let desc = method_descriptor(&self.get_arg_types(ident), &self.get_ret_type(ident));
+        if self.builtins.contains(ident) {
+            format!("{}/{}{}", RUNTIME_CLASS, ident, desc)
+        } else if self.externs.contains(ident) {
+            format!("{}/{}{}", EXTERN_CLASS, ident, desc)
+        } else if ident.0.contains('.') {
+            format!("{}{}", ident.0.replace('.', "/"), desc)
+        } else {
+            format!("{}/{}{}", self.program, get_method_name(ident), desc)
+        }
+    }
+
+    pub fn var_exists(&self, ident: &Ident) -> bool {
+        self.vars.contains_key(ident)
+    }
+
+    pub fn get_var(&self, ident: &Ident) -> (usize, Type) {
+        self.vars.get(ident).unwrap().clone()
+    }
+
+    pub fn set_var(&mut self, ident: Ident, slot: usize, t: Type) {
+        self.vars.insert(ident, (slot, t));
+    }
+
+    pub fn has_globals(&self) -> bool {
+        !self.globals.is_empty() || !self.static_fields.is_empty()
+    }
+
+    pub fn global_exists(&self, ident: &Ident) -> bool {
+        self.globals.contains_key(ident)
+    }
+
+    pub fn get_global(&self, ident: &Ident) -> (String, Type) {
+        self.globals.get(ident).unwrap().clone()
+    }
+
+    // globals are static fields of the program class
+    pub fn add_global(&mut self, ident: &Ident, t: &Type) {
+        self.add_directive(format!(".field public static {} {}", quote(&ident.0), descriptor(t)));
+        let name = format!("'{}/{}' {}", self.program, ident, descriptor(t));
+        self.globals.insert(ident.clone(), (name, t.clone()));
+    }
+
+    // declared in the class, see class.rs
+    pub fn add_static_field(&mut self, class: ClassId, ident: &Ident, t: &Type) {
+        let cname = self.get_class_data(class).ident.clone();
+        let name = format!("'{}/{}' {}", cname, ident, descriptor(t));
+        self.static_fields.insert((class, ident.clone()), (name, t.clone()));
+    }
+
+    pub fn add_static_method(&mut self, class: ClassId, ident: &Ident) {
+        self.static_methods.insert((class, ident.clone()));
+    }
+
+    // static members are inherited
+
+    pub fn find_static_field(&self, mut id: ClassId, ident: &Ident) -> Option<(String, Type)> {
+        loop {
+            if let Some(field) = self.static_fields.get(&(id, ident.clone())) {
+                return Some(field.clone());
+            }
+            id = self.get_class_data(id).super_id?;
+        }
+    }
+
+    // returns the name of the method in the context
+    pub fn find_static_method(&self, mut id: ClassId, ident: &Ident) -> Option<Ident> {
+        loop {
+            if self.static_methods.contains(&(id, ident.clone())) {
+                return Some(Ident(format!("{}.{}", self.get_class_data(id).ident, ident)));
+            }
+            id = self.get_class_data(id).super_id?;
+        }
+    }
+
+    pub fn add_func(&mut self, ident: &Ident, arg_types: Vec<Type>, ret_type: Type) {
+        self.func_types.insert(ident.clone(), (arg_types, ret_type));
+    }
+
+    pub fn add_builtin_func(&mut self, ident: &Ident, arg_types: Vec<Type>, ret_type: Type) {
+        self.add_func(ident, arg_types, ret_type);
+        self.builtins.insert(ident.clone());
+    }
+
+    pub fn add_extern_func(&mut self, ident: &Ident, arg_types: Vec<Type>, ret_type: Type) {
+        self.add_func(ident, arg_types, ret_type);
+        self.externs.insert(ident.clone());
+    }
+
+    pub fn is_extern(&self, ident: &Ident) -> bool {
+        self.externs.contains(ident)
+    }
+
+    // class
+    pub fn add_class_id(&mut self, cname: &Ident, id: ClassId) {
+        self.class_ids.insert(cname.clone(), id);
+    }
+
+    pub fn add_class(&mut self, id: ClassId, cdata: ClassData) {
+        self.classes.insert(id, cdata);
+    }
+
+    pub fn set_vtable(&mut self, id: ClassId, vtable: VTable) {
+        self.classes.get_mut(&id).unwrap().vtable = vtable;
+    }
+
+    pub fn class_exists(&self, cname: &Ident) -> bool {
+        self.class_ids.contains_key(cname)
+    }
+
+    pub fn get_class_id(&self, cname: &Ident) -> ClassId {
+        *self.class_ids.get(cname).unwrap()
+    }
+
+    pub fn get_class_data(&self, id: ClassId) -> &ClassData {
+        self.classes.get(&id).unwrap()
+    }
+
+    pub fn has_field(&self, mut id: ClassId, field: &Ident) -> bool {
+        while !self.get_class_data(id).has_field(field) {
+            match self.get_class_data(id).super_id {
+                Some(super_id) => id = super_id,
+                None => return false,
+            }
+        }
+        true
+    }
+
+    // Class hierarchy analysis: whether a subclass of the class replaces
+    // the method at the given position of its vtable.
+    pub fn is_overridden(&self, id: ClassId, vtable_pos: usize) -> bool {
+        let real_ident = &self.get_class_data(id).vtable.fs[vtable_pos].real_ident;
+        self.classes.iter().any(|(&sub_id, cdata)| {
+            sub_id != id && self.is_subclass_of(sub_id, id) &&
+            cdata.vtable.fs[vtable_pos].real_ident != *real_ident
+        })
+    }
+
+    pub fn is_subclass_of(&self, mut id: ClassId, super_id: ClassId) -> bool {
+        while self.get_class_data(id).super_id.is_some() && id != super_id {
+            id = self.get_class_data(id).get_super();
+        }
+        id == super_id
+    }
+
+    // enums
+    pub fn add_enum(&mut self, name: &Ident, variants: &[Ident]) -> EnumId {
+        let id = self.enum_variants.len();
+        self.enum_ids.insert(name.clone(), id);
+        self.enum_variants.push(variants.to_owned());
+        id
+    }
+
+    pub fn enum_exists(&self, name: &Ident) -> bool {
+        self.enum_ids.contains_key(name)
+    }
+
+    pub fn get_enum_id(&self, name: &Ident) -> EnumId {
+        *self.enum_ids.get(name).unwrap()
+    }
+
+    pub fn get_variant_tag(&self, id: EnumId, variant: &Ident) -> i32 {
+        self.enum_variants[id].iter().position(|v| v == variant).unwrap() as i32
+    }
+
+    // the static method which maps tags of the enum to names of its variants
+    pub fn get_enum_name_func(&self, id: EnumId) -> String {
+        quote(&format!("{}/$enum{}_name(I)Ljava/lang/String;", self.program, id))
+    }
+
+    // lvalues
+
+    // pushes the value
+    pub fn add_load(&mut self, lvalue: &LValue) {
+        match *lvalue {
+            LValue::Local(slot, ref t) => self.add_line(load_instr(t, slot)),
+            LValue::Static(ref field) => self.add_line(format!("getstatic {}", field)),
+            LValue::Field(ref obj, ref field) => {
+                self.add_line(obj.to_string());
+                self.add_line(format!("getfield {}", field));
+            }
+            LValue::Elem(ref arr, ref idx, ref t) => {
+                self.add_line(arr.to_string());
+                self.add_line(idx.to_string());
+                self.add_line(format!("{}aload", array_prefix(t)));
+            }
+            LValue::ListElem(ref list, ref idx, ref t) => {
+                self.add_line(list.to_string());
+                self.add_line(idx.to_string());
+                self.call_runtime("listGet(Ljava/util/ArrayList;I)Ljava/lang/Object;");
+                self.add_lines(unbox_instrs(t));
+            }
+            LValue::Size(ref obj, ref obj_t) => {
+                self.add_line(obj.to_string());
+                match *obj_t {
+                    Type::TArray(..) => self.add_line("arraylength".to_string()),
+                    Type::TList(..) => {
+                        self.add_line("invokevirtual java/util/ArrayList/size()I".to_string())
+                    }
+                    Type::TMap(..) => self.add_line(format!("getfield {}/size I", MAP_CLASS)),
+                    Type::TStringBuilder => {
+                        self.add_line("invokevirtual java/lang/StringBuilder/length()I".to_string())
+                    }
+                    _ => unreachable!(),
+                }
+            }
+        }
+    }
+
+    pub fn add_store(&mut self, lvalue: &LValue, val: &str) {
+        match *lvalue {
+            LValue::Local(slot, ref t) => {
+                self.add_line(val.to_string());
+                self.add_line(store_instr(t, slot));
+            }
+            LValue::Static(ref field) => {
+                self.add_line(val.to_string());
+                self.add_line(format!("putstatic {}", field));
+            }
+            LValue::Field(ref obj, ref field) => {
+                self.add_line(obj.to_string());
+                self.add_line(val.to_string());
+                self.add_line(format!("putfield {}", field));
+            }
+            LValue::Elem(ref arr, ref idx, ref t) => {
+                self.add_line(arr.to_string());
+                self.add_line(idx.to_string());
+                self.add_line(val.to_string());
+                self.add_line(format!("{}astore", array_prefix(t)));
+            }
+            LValue::ListElem(ref list, ref idx, ref t) => {
+                self.add_line(list.to_string());
+                self.add_line(idx.to_string());
+                self.add_line(val.to_string());
+                self.add_lines(box_instrs(t));
+                self.call_runtime("listSet(Ljava/util/ArrayList;ILjava/lang/Object;)V");
+            }
+            LValue::Size(..) => unreachable!(),
+        }
+    }
+
+    // output
+
+    // the method of the runtime class, given with its descriptor
+    pub fn call_runtime(&mut self, method: &str) {
+        self.add_line(format!("invokestatic {}/{}", RUNTIME_CLASS, method));
+    }
+
+    // Stores the value on top of the stack in a new temporary, so that it's
+    // evaluated in order. Returns the instruction which pushes it.
+    pub fn add_tmp(&mut self, t: &Type) -> String {
+        let slot = self.add_local(t);
+        self.add_line(store_instr(t, slot));
+        load_instr(t, slot)
+    }
+
+    // A boolean which is true if the jump (e.g. if_icmplt) is taken. The stack is
+    // empty at every label.
+    pub fn add_cond_tmp(&mut self, jump: &str) -> String {
+        let (true_label, end_label) = (self.next_label(), self.next_label());
+        let slot = self.add_local(&Type::TBool);
+        self.add_line(format!("{} L{}", jump, true_label));
+        self.add_line("iconst_0".to_string());
+        self.add_line(store_instr(&Type::TBool, slot));
+        self.add_line(format!("goto L{}", end_label));
+        self.add_label(true_label);
+        self.add_line("iconst_1".to_string());
+        self.add_line(store_instr(&Type::TBool, slot));
+        self.add_label(end_label);
+        load_instr(&Type::TBool, slot)
+    }
+
+    pub fn next_label(&mut self) -> usize {
+        self.labels += 1;
+        self.labels
+    }
+
+    // returns the first slot of the new local
+    pub fn add_local(&mut self, t: &Type) -> usize {
+        let slot = self.locals;
+        self.locals += slot_size(t);
+        self.max_locals = max(self.max_locals, self.locals);
+        slot
+    }
+
+    // The header of a method, its limits are filled once its body is generated.
+    // Returns the index of their line.
+    pub fn begin_method(&mut self, header: String) -> usize {
+        self.locals = 0;
+        self.max_locals = 0;
+        self.stack = 0;
+        self.max_stack = 0;
+        self.labels = 0;
+        self.out.push(header);
+        self.out.push(String::new());
+        self.out.len() - 1
+    }
+
+    pub fn end_method(&mut self, limits_line: usize) {
+        let limits = vec![format!("  .limit stack {}", self.max_stack),
+                          format!("  .limit locals {}", self.max_locals)];
+        self.out.splice(limits_line..limits_line + 1, limits);
+        self.out.push(".end method".to_string());
+        self.add_empty_line();
+    }
+
+    pub fn add_line(&mut self, s: String) {
+        self.stack += stack_effect(&s);
+        self.max_stack = max(self.max_stack, self.stack);
+        self.out.push(format!("  {}", s));
+    }
+
+    pub fn add_lines(&mut self, lines: Vec<String>) {
+        for line in lines {
+            self.add_line(line);
+        }
+    }
+
+    pub fn add_label(&mut self, label: usize) {
+        self.out.push(format!("L{}:", label));
+    }
+
+    // a line outside of methods
+    pub fn add_directive(&mut self, s: String) {
+        self.out.push(s);
+    }
+
+    pub fn add_comment(&mut self, s: String) {
+        self.out.push(format!("; {}", s));
+    }
+
+    pub fn add_empty_line(&mut self) {
+        self.out.push(String::new());
+    }
+
+    // the program class followed by the other classes
+    pub fn take_class_files(&mut self) -> Vec<(String, Vec<String>)> {
+        let mut files = vec![(self.program.clone(), self.out.drain(..).collect())];
+        files.append(&mut self.class_files);
+        files
+    }
+}
+
+// Functions are methods of the program class with the same names, except for main,
+// which is taken by the entry point of the JVM.
+pub fn get_method_name(ident: &Ident) -> String {
+    match ident.0.as_ref() {
+        "main" => "$main".to_string(),
+        _ => ident.0.clone(),
+    }
+}
@@ -0,0 +1,28 @@
+use ast::{Enum, Type};
+
+use jvm_generation::context::Context;
+use jvm_generation::generate::*;
+use jvm_generation::jvm_type::*;
+
+// Generates $enum{id}_name, which maps tags to names of the variants.
+// Tags out of range are not possible, so the first variant is the default.
+impl GenerateCode<()> for Enum {
+    fn generate_code(&self, ctx: &mut Context) {
+        let id = ctx.get_enum_id(&self.name);
+        let header = format!(".method public static $enum{}_name(I)Ljava/lang/String;", id);
+        let limits_line = ctx.begin_method(header);
+        let tag = load_instr(&Type::TInt, ctx.add_local(&Type::TInt));
+        for (idx, variant) in self.variants.iter().enumerate().skip(1) {
+            let next_label = ctx.next_label();
+            ctx.add_line(tag.clone());
+            ctx.add_line(int_const(idx as i32));
+            ctx.add_line(format!("if_icmpne L{}", next_label));
+            ctx.add_line(string_const(variant.0.as_bytes()));
+            ctx.add_line("areturn".to_string());
+            ctx.add_label(next_label);
+        }
+        ctx.add_line(string_const(self.variants[0].0.as_bytes()));
+        ctx.add_line("areturn".to_string());
+        ctx.end_method(limits_line);
+    }
+}
@@ -0,0 +1,574 @@
+use ast::*;
+
+use jvm_generation::collection::*;
+use jvm_generation::context::*;
+use jvm_generation::extern_func::*;
+use jvm_generation::field_get::*;
+use jvm_generation::generate::*;
+use jvm_generation::jvm_type::*;
+
+// Values are instructions which push them. Every value which isn't a constant
+// or a local is stored in a temporary, so that the order of evaluation is the same
+// as in Latte and the stack is empty between expressions.
+impl GenerateCode<(String, Type)> for Expr {
+    fn generate_code(&self, ctx: &mut Context) -> (String, Type) {
+        match *self {
+            Expr::EVar(ref ident) if as_enum_variant(ident, ctx).is_some() => {
+                let (tag, t) = as_enum_variant(ident, ctx).unwrap();
+                (int_const(tag), t)
+            }
+            // locals can't be changed by calls
+            Expr::EVar(FieldGet::Direct(ref ident)) if ctx.var_exists(ident) => {
+                let (slot, t) = ctx.get_var(ident);
+                (load_instr(&t, slot), t)
+            }
+            Expr::EVar(ref ident) => {
+                let (lvalue, t) = ident.generate_code(ctx);
+                ctx.add_load(&lvalue);
+                (ctx.add_tmp(&t), t)
+            }
+            Expr::ELit(ref lit) => lit.generate_code(ctx),
+            Expr::ECall(ref ident, ref args) => generate_call(ident, args, ctx),
+            Expr::ENeg(ref e) => {
+                let (val, t) = e.generate_code(ctx);
+                if t == Type::TDouble {
+                    ctx.add_line(val);
+                    ctx.add_line("dneg".to_string());
+                    (ctx.add_tmp(&t), t)
+                } else {
+                    (generate_arith_op(&t, &zero(&t), Operator::OpSub, &val, ctx), t)
+                }
+            }
+            Expr::ENot(ref e) => {
+                let (val, t) = e.generate_code(ctx);
+                ctx.add_line(val);
+                ctx.add_line("iconst_1".to_string());
+                ctx.add_line("ixor".to_string());
+                (ctx.add_tmp(&t), t)
+            }
+            Expr::EBitNot(ref e) => {
+                let (val, t) = e.generate_code(ctx);
+                ctx.add_line(val);
+                match t == Type::TLong {
+                    true => ctx.add_line(long_const(-1)),
+                    false => ctx.add_line(int_const(-1)),
+                }
+                ctx.add_line(format!("{}xor", type_prefix(&t)));
+                (ctx.add_tmp(&t), t)
+            }
+            Expr::EBinOp(ref lhs, Operator::OpOr, ref rhs) => generate_or(lhs, rhs, ctx),
+            Expr::EBinOp(ref lhs, Operator::OpAnd, ref rhs) => generate_and(lhs, rhs, ctx),
+            Expr::EBinOp(ref lhs, Operator::OpNEq, ref rhs) => generate_eq(lhs, rhs, true, ctx),
+            Expr::EBinOp(ref lhs, Operator::OpEq, ref rhs) => generate_eq(lhs, rhs, false, ctx),
+            Expr::EBinOp(ref lhs, Operator::OpAdd, ref rhs) => generate_add(lhs, rhs, ctx),
+            Expr::EBinOp(ref lhs, ref op, ref rhs) => {
+                let (lhs_val, lhs_t) = lhs.generate_code(ctx);
+                let (rhs_val, _) = rhs.generate_code(ctx);
+                if lhs_t == Type::TString {
+                    return generate_str_cmp(&lhs_val, *op, &rhs_val, ctx);
+                }
+                // shifts use only the lowest 5 bits of the count, as ishl does
+                match *op {
+                    Operator::OpLess | Operator::OpLessE | Operator::OpGreater |
+                    Operator::OpGreaterE => {
+                        ctx.add_line(lhs_val);
+                        ctx.add_line(rhs_val);
+                        let jump = cmp_jump(&lhs_t, *op, ctx);
+                        (ctx.add_cond_tmp(&jump), Type::TBool)
+                    }
+                    Operator::OpSub | Operator::OpMul | Operator::OpDiv | Operator::OpMod |
+                    Operator::OpBitAnd | Operator::OpBitOr | Operator::OpBitXor |
+                    Operator::OpShl | Operator::OpShr => {
+                        (generate_arith_op(&lhs_t, &lhs_val, *op, &rhs_val, ctx), lhs_t)
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            Expr::ENew(ref t) => {
+                match *t {
+                    Type::TObject(ref cname) => {
+                        ctx.add_line(format!("new {}", quote(&cname.0)));
+                        ctx.add_line("dup".to_string());
+                        let init = format!("{}/<init>()V", cname);
+                        ctx.add_line(format!("invokespecial {}", quote(&init)));
+                        (ctx.add_tmp(t), t.clone())
+                    }
+                    _ => (generate_new_collection(t, ctx), t.clone()),
+                }
+            }
+            Expr::ENewArray(ref t, ref sizes) => {
+                let mut arr_t = t.clone();
+                let mut size_vals: Vec<String> = Vec::new();
+                for size in sizes {
+                    size_vals.push(size.generate_code(ctx).0);
+                    arr_t = Type::TArray(Box::new(arr_t));
+                }
+                (new_arr(&arr_t, &size_vals, ctx), arr_t)
+            }
+            Expr::EArrayLit(..) => unreachable!("array literal without destination type"),
+            Expr::ECast(ref t, ref e) => {
+                let (val, e_t) = e.generate_code(ctx);
+                match e_t == *t {
+                    true => (val, e_t),
+                    false => (generate_cast(&val, &e_t, t, ctx), t.clone()),
+                }
+            }
+            Expr::ECond(ref cond, ref e1, ref e2) => generate_cond(cond, e1, e2, ctx),
+        }
+    }
+}
+
+fn generate_call(ident: &FieldGet, args: &[Expr], ctx: &mut Context) -> (String, Type) {
+    let call = match prepare_call(ident, args, ctx) {
+        PreparedCall::Generated(val, t) => return (val, t),
+        PreparedCall::Call(call) => call,
+    };
+    let call_instr = push_call(&call, ctx);
+    add_call(call_instr, &call.ret_type, ctx)
+}
+
+// Adds the call instruction, whose args are already pushed. The result of a void
+// call is empty.
+pub fn add_call(call: String, ret_type: &Type, ctx: &mut Context) -> (String, Type) {
+    ctx.add_line(call);
+    match *ret_type {
+        Type::TVoid => (String::new(), Type::TVoid),
+        _ => (ctx.add_tmp(ret_type), ret_type.clone()),
+    }
+}
+
+pub struct Call {
+    pub func: String, // <class>/<method><descriptor>
+    pub is_method: bool,
+    pub is_overridden: bool, // whether invokevirtual can call another method
+    pub ret_type: Type,
+    pub args: Vec<(String, Type)>, // evaluated, with self first for methods
+}
+
+pub enum PreparedCall {
+    Generated(String, Type), // methods of collections and enums are generated in place
+    Call(Call),
+}
+
+// Pushes the args, returns the call instruction. Methods are always virtual.
+pub fn push_call(call: &Call, ctx: &mut Context) -> String {
+    for (arg, _) in &call.args {
+        ctx.add_line(arg.clone());
+    }
+    match call.is_method {
+        true => format!("invokevirtual {}", quote(&call.func)),
+        false => format!("invokestatic {}", quote(&call.func)),
+    }
+}
+
+// Evaluates the object and args of the call.
+pub fn prepare_call(ident: &FieldGet, args: &[Expr], ctx: &mut Context) -> PreparedCall {
+    let (obj, func_name): (Option<(String, Type)>, Ident) = ident.generate_code(ctx);
+    let obj = match obj {
+        Some((val, Type::TObject(ref cname))) => Some((val, ctx.get_class_id(cname))),
+        // the only method of enums is name()
+        Some((val, Type::TEnum(ref name))) => {
+            ctx.add_line(val);
+            let func = format!("invokestatic {}", ctx.get_enum_name_func(ctx.get_enum_id(name)));
+            let (val, t) = add_call(func, &Type::TString, ctx);
+            return PreparedCall::Generated(val, t);
+        }
+        Some((val, obj_t)) => {
+            let (val, t) = generate_collection_call(&val, &obj_t, &func_name, args, ctx);
+            return PreparedCall::Generated(val, t);
+        }
+        None if ctx.is_extern(&func_name) => {
+            let (val, t) = generate_extern_call(&func_name, args, ctx);
+            return PreparedCall::Generated(val, t);
+        }
+        None => None,
+    };
+    let ret_type;
+    let arg_types;
+    let func;
+    let mut is_overridden = false;
+    let mut final_args: Vec<(String, Type)> = Vec::new();
+    if let Some((obj, id)) = obj {
+        let pos = *ctx.get_class_data(id).vtable.idxs.get(&func_name).unwrap();
+        let f_info = ctx.get_class_data(id).vtable.fs[pos].clone();
+
+        ret_type = f_info.ret_type.clone();
+        arg_types = f_info.arg_types.clone();
+        is_overridden = ctx.is_overridden(id, pos);
+        func = format!("{}{}", f_info.real_ident, method_descriptor(&arg_types, &ret_type));
+        let obj_t = Type::TObject(ctx.get_class_data(f_info.class_id).ident.clone());
+        final_args.push((obj, obj_t));
+    } else {
+        ret_type = ctx.get_ret_type(&func_name);
+        arg_types = ctx.get_arg_types(&func_name);
+        func = ctx.get_func_symbol(&func_name);
+    }
+
+    let is_method = !final_args.is_empty();
+    for (arg, arg_dst_t) in args.iter().zip(arg_types) {
+        final_args.push((generate_init_expr(arg, &arg_dst_t, ctx), arg_dst_t));
+    }
+
+    PreparedCall::Call(Call {
+        func,
+        is_method,
+        is_overridden,
+        ret_type,
+        args: final_args,
+    })
+}
+
+// Generates value to be stored in a location of type t (variable, argument etc.),
+// objects of subclasses are values of their superclasses.
+pub fn generate_init_expr(e: &Expr, t: &Type, ctx: &mut Context) -> String {
+    match *e {
+        Expr::EArrayLit(ref elems) => generate_array_lit(elems, t, ctx),
+        _ => e.generate_code(ctx).0,
+    }
+}
+
+pub fn arr_elem_t(arr_t: &Type) -> Type {
+    match *arr_t {
+        Type::TArray(ref elem_t) => *elem_t.clone(),
+        _ => unreachable!(),
+    }
+}
+
+// elements are zeroed, or null for objects
+fn alloc_arr(arr_t: &Type, size: &str, ctx: &mut Context) -> String {
+    let elem_t = arr_elem_t(arr_t);
+    ctx.add_line(size.to_string());
+    match elem_t {
+        Type::TInt | Type::TEnum(..) => ctx.add_line("newarray int".to_string()),
+        Type::TLong => ctx.add_line("newarray long".to_string()),
+        Type::TDouble => ctx.add_line("newarray double".to_string()),
+        Type::TBool => ctx.add_line("newarray boolean".to_string()),
+        Type::TChar => ctx.add_line("newarray char".to_string()),
+        _ => ctx.add_line(format!("anewarray {}", quote(&class_name(&elem_t)))),
+    }
+    ctx.add_tmp(arr_t)
+}
+
+// sizes of subsequent dimensions, subarrays of missing dimensions are null
+fn new_arr(arr_t: &Type, sizes: &[String], ctx: &mut Context) -> String {
+    let arr = alloc_arr(arr_t, &sizes[0], ctx);
+    let elem_t = arr_elem_t(arr_t);
+    if sizes.len() > 1 {
+        let idx = ctx.add_local(&Type::TInt);
+        ctx.in_new_loop(idx, &sizes[0], |ctx| {
+            let subarr = new_arr(&elem_t, &sizes[1..], ctx);
+            let elem = LValue::Elem(arr.clone(), load_instr(&Type::TInt, idx), elem_t.clone());
+            ctx.add_store(&elem, &subarr);
+        });
+    } else if elem_t == Type::TString {
+        // all elements are the same empty string
+        ctx.add_line(arr.to_string());
+        ctx.add_line(string_const(b""));
+        let fill = "invokestatic java/util/Arrays/fill([Ljava/lang/Object;Ljava/lang/Object;)V";
+        ctx.add_line(fill.to_string());
+    }
+    arr
+}
+
+fn generate_array_lit(elems: &[Expr], arr_t: &Type, ctx: &mut Context) -> String {
+    let elem_t = arr_elem_t(arr_t);
+    let arr = alloc_arr(arr_t, &int_const(elems.len() as i32), ctx);
+    for (idx, e) in elems.iter().enumerate() {
+        let val = generate_init_expr(e, &elem_t, ctx);
+        let elem = LValue::Elem(arr.clone(), int_const(idx as i32), elem_t.clone());
+        ctx.add_store(&elem, &val);
+    }
+    arr
+}
+
+fn generate_or(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> (String, Type) {
+    generate_short_circuit(lhs, rhs, true, ctx)
+}
+
+fn generate_and(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> (String, Type) {
+    generate_short_circuit(lhs, rhs, false, ctx)
+}
+
+// rhs is evaluated if lhs (negated for ||) is true
+fn generate_short_circuit(lhs: &Expr,
+                          rhs: &Expr,
+                          negate: bool,
+                          ctx: &mut Context)
+                          -> (String, Type) {
+    let lhs_val = ctx.in_new_scope(|ctx| lhs.generate_code(ctx).0);
+    let slot = ctx.add_local(&Type::TBool);
+    let res = LValue::Local(slot, Type::TBool);
+    ctx.add_store(&res, &lhs_val);
+    let end_label = ctx.next_label();
+    ctx.add_load(&res);
+    match negate {
+        true => ctx.add_line(format!("ifne L{}", end_label)),
+        false => ctx.add_line(format!("ifeq L{}", end_label)),
+    }
+    ctx.in_new_scope(|ctx| {
+        let (rhs_val, _) = rhs.generate_code(ctx);
+        ctx.add_store(&res, &rhs_val);
+    });
+    ctx.add_label(end_label);
+    (load_instr(&Type::TBool, slot), Type::TBool)
+}
+
+// Both branches store their values in the same local, null and objects of all
+// classes are references.
+fn generate_cond(cond: &Expr, e1: &Expr, e2: &Expr, ctx: &mut Context) -> (String, Type) {
+    let (cond_val, _) = cond.generate_code(ctx);
+    let (else_label, end_label) = (ctx.next_label(), ctx.next_label());
+
+    ctx.add_line(cond_val);
+    ctx.add_line(format!("ifeq L{}", else_label));
+    let (e1_t, res) = ctx.in_new_scope(|ctx| generate_cond_branch(e1, None, ctx));
+    ctx.add_line(format!("goto L{}", end_label));
+    ctx.add_label(else_label);
+    let (e2_t, _) = ctx.in_new_scope(|ctx| generate_cond_branch(e2, res, ctx));
+    ctx.add_label(end_label);
+
+    let t = common_type(&e1_t, &e2_t, ctx);
+    match res {
+        Some(slot) => (load_instr(&t, slot), t),
+        None => (String::new(), t),
+    }
+}
+
+// Stores the value in the given local or a new one, returns its type and the local.
+fn generate_cond_branch(e: &Expr, res: Option<usize>, ctx: &mut Context) -> (Type, Option<usize>) {
+    let (val, t) = e.generate_code(ctx);
+    if t == Type::TVoid {
+        return (t, None);
+    }
+    let slot = res.unwrap_or_else(|| ctx.add_local(&t));
+    ctx.add_store(&LValue::Local(slot, t.clone()), &val);
+    (t, Some(slot))
+}
+
+// least common supertype of types accepted by the type checker
+fn common_type(t1: &Type, t2: &Type, ctx: &Context) -> Type {
+    if t1 == t2 || *t2 == Type::TNull {
+        return t1.clone();
+    }
+    if *t1 == Type::TNull {
+        return t2.clone();
+    }
+    let (mut id, id2) = match (t1, t2) {
+        (Type::TObject(c1), Type::TObject(c2)) => {
+            (ctx.get_class_id(c1), ctx.get_class_id(c2))
+        }
+        _ => unreachable!(),
+    };
+    while !ctx.is_subclass_of(id2, id) {
+        id = ctx.get_class_data(id).get_super();
+    }
+    Type::TObject(ctx.get_class_data(id).ident.clone())
+}
+
+enum AddOperand {
+    Strings(Vec<String>), // parts of a concatenation, in order
+    Value(String, Type),
+}
+
+// Concatenations of more than two strings are done at once, the types are known
+// only here, so the chains are flattened during generation. The parts are appended
+// to a StringBuilder, so that the result is always a new string, as in LLVM.
+fn generate_add(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> (String, Type) {
+    let parts = match generate_add_operands(lhs, rhs, ctx) {
+        AddOperand::Strings(parts) => parts,
+        AddOperand::Value(val, t) => return (val, t),
+    };
+    ctx.add_line("new java/lang/StringBuilder".to_string());
+    ctx.add_line("dup".to_string());
+    ctx.add_line("invokespecial java/lang/StringBuilder/<init>()V".to_string());
+    for part in parts {
+        ctx.add_line(part);
+        ctx.add_line("invokevirtual java/lang/StringBuilder/append(Ljava/lang/String;)\
+                      Ljava/lang/StringBuilder;"
+            .to_string());
+    }
+    ctx.add_line("invokevirtual java/lang/StringBuilder/toString()Ljava/lang/String;".to_string());
+    (ctx.add_tmp(&Type::TString), Type::TString)
+}
+
+fn generate_add_operands(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> AddOperand {
+    match generate_add_operand(lhs, ctx) {
+        AddOperand::Strings(mut parts) => {
+            match generate_add_operand(rhs, ctx) {
+                AddOperand::Strings(rhs_parts) => parts.extend(rhs_parts),
+                AddOperand::Value(val, _) => parts.push(val),
+            }
+            AddOperand::Strings(parts)
+        }
+        AddOperand::Value(lhs_val, t) => {
+            let (rhs_val, _) = rhs.generate_code(ctx);
+            let val = generate_arith_op(&t, &lhs_val, Operator::OpAdd, &rhs_val, ctx);
+            AddOperand::Value(val, t)
+        }
+    }
+}
+
+fn generate_add_operand(e: &Expr, ctx: &mut Context) -> AddOperand {
+    match *e {
+        Expr::EBinOp(ref lhs, Operator::OpAdd, ref rhs) => generate_add_operands(lhs, rhs, ctx),
+        _ => {
+            let (val, t) = e.generate_code(ctx);
+            match t == Type::TString {
+                true => AddOperand::Strings(vec![val]),
+                false => AddOperand::Value(val, t),
+            }
+        }
+    }
+}
+
+// Integer arithmetic wraps around on overflow, unless overflow checking is enabled.
+pub fn generate_arith_op(t: &Type,
+                         lhs: &str,
+                         op: Operator,
+                         rhs: &str,
+                         ctx: &mut Context)
+                         -> String {
+    let is_checked_op = op == Operator::OpAdd || op == Operator::OpSub || op == Operator::OpMul;
+    let is_int = *t == Type::TInt || *t == Type::TLong;
+    ctx.add_line(lhs.to_string());
+    ctx.add_line(rhs.to_string());
+    let op_name = match op {
+        Operator::OpAdd => "add",
+        Operator::OpSub => "sub",
+        Operator::OpMul => "mul",
+        Operator::OpDiv => "div",
+        Operator::OpMod => "rem",
+        Operator::OpBitAnd => "and",
+        Operator::OpBitOr => "or",
+        Operator::OpBitXor => "xor",
+        Operator::OpShl => "shl",
+        Operator::OpShr => "shr",
+        _ => unreachable!(),
+    };
+    if ctx.opts.check_overflow && is_checked_op && is_int {
+        let name = format!("checked{}{}", op_name[..1].to_uppercase(), &op_name[1..]);
+        let desc = method_descriptor(&[t.clone(), t.clone()], t);
+        ctx.call_runtime(&format!("{}{}", name, desc));
+    } else {
+        ctx.add_line(format!("{}{}", type_prefix(t), op_name));
+    }
+    ctx.add_tmp(t)
+}
+
+// Emits the comparison of the two values on the stack, returns the jump taken
+// if it's true. Comparisons of doubles with NaN are false, except !=.
+pub fn cmp_jump(t: &Type, op: Operator, ctx: &mut Context) -> String {
+    let cond = match op {
+        Operator::OpEq => "eq",
+        Operator::OpNEq => "ne",
+        Operator::OpLess => "lt",
+        Operator::OpLessE => "le",
+        Operator::OpGreater => "gt",
+        Operator::OpGreaterE => "ge",
+        _ => unreachable!(),
+    };
+    match type_prefix(t) {
+        "i" => format!("if_icmp{}", cond),
+        "l" => {
+            ctx.add_line("lcmp".to_string());
+            format!("if{}", cond)
+        }
+        "d" => {
+            match op {
+                Operator::OpLess | Operator::OpLessE => ctx.add_line("dcmpg".to_string()),
+                _ => ctx.add_line("dcmpl".to_string()),
+            }
+            format!("if{}", cond)
+        }
+        _ => format!("if_acmp{}", cond),
+    }
+}
+
+fn generate_eq(lhs: &Expr, rhs: &Expr, negate: bool, ctx: &mut Context) -> (String, Type) {
+    let (lhs_val, t1) = lhs.generate_code(ctx);
+    let (rhs_val, t2) = rhs.generate_code(ctx);
+    if t1 == Type::TNull && t2 == Type::TNull {
+        return (int_const(!negate as i32), Type::TBool);
+    }
+    ctx.add_line(lhs_val);
+    ctx.add_line(rhs_val);
+    if t1 == Type::TString && t2 == Type::TString {
+        ctx.add_line("invokevirtual java/lang/String/equals(Ljava/lang/Object;)Z".to_string());
+        if negate {
+            ctx.add_line("iconst_1".to_string());
+            ctx.add_line("ixor".to_string());
+        }
+        return (ctx.add_tmp(&Type::TBool), Type::TBool);
+    }
+    // objects of different classes are compared as references
+    let op = match negate {
+        false => Operator::OpEq,
+        true => Operator::OpNEq,
+    };
+    let jump = cmp_jump(&t1, op, ctx);
+    (ctx.add_cond_tmp(&jump), Type::TBool)
+}
+
+// lexicographic order of the contents, characters are bytes
+fn generate_str_cmp(lhs: &str, op: Operator, rhs: &str, ctx: &mut Context) -> (String, Type) {
+    ctx.add_line(lhs.to_string());
+    ctx.add_line(rhs.to_string());
+    ctx.add_line("invokevirtual java/lang/String/compareTo(Ljava/lang/String;)I".to_string());
+    ctx.add_line("iconst_0".to_string());
+    let jump = cmp_jump(&Type::TInt, op, ctx);
+    (ctx.add_cond_tmp(&jump), Type::TBool)
+}
+
+// Conversions are the same as in LLVM: doubles are truncated, chars are unsigned
+// and narrowing keeps the lowest bits. Objects cast to subclasses are checked.
+fn generate_cast(val: &str, from: &Type, to: &Type, ctx: &mut Context) -> String {
+    ctx.add_line(val.to_string());
+    let instr = match (type_prefix(from), type_prefix(to)) {
+        ("i", "l") => Some("i2l"),
+        ("i", "d") => Some("i2d"),
+        ("l", "i") => Some("l2i"),
+        ("l", "d") => Some("l2d"),
+        ("d", "i") => Some("d2i"),
+        ("d", "l") => Some("d2l"),
+        ("a", "a") if *from != Type::TNull => Some("checkcast"),
+        _ => None,
+    };
+    match instr {
+        Some("checkcast") => ctx.add_line(format!("checkcast {}", quote(&class_name(to)))),
+        Some(instr) => ctx.add_line(instr.to_string()),
+        None => {}
+    }
+    if *to == Type::TChar {
+        ctx.add_line(int_const(255));
+        ctx.add_line("iand".to_string());
+    }
+    ctx.add_tmp(to)
+}
+
+impl GenerateCode<(String, Type)> for Lit {
+    fn generate_code(&self, ctx: &mut Context) -> (String, Type) {
+        match *self {
+            Lit::LInt(x) => (int_const(x), Type::TInt),
+            Lit::LLong(x) => (long_const(x), Type::TLong),
+            Lit::LDouble(x) => {
+                let mut instrs = double_consts(x);
+                match instrs.len() {
+                    1 => (instrs.remove(0), Type::TDouble),
+                    _ => {
+                        ctx.add_lines(instrs);
+                        (ctx.add_tmp(&Type::TDouble), Type::TDouble)
+                    }
+                }
+            }
+            Lit::LChar(c) => (int_const(c as i32), Type::TChar),
+            Lit::LTrue => ("iconst_1".to_string(), Type::TBool),
+            Lit::LFalse => ("iconst_0".to_string(), Type::TBool),
+            // literals are interned, the same literal is the same string
+            Lit::LString(ref s) => (string_const(s.as_bytes()), Type::TString),
+            Lit::LNull(None) => ("aconst_null".to_string(), Type::TNull),
+            Lit::LNull(Some(ref cname)) => {
+                ("aconst_null".to_string(), Type::TObject(cname.clone()))
+            }
+        }
+    }
+}
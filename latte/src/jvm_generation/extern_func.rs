@@ -0,0 +1,35 @@
+use ast::{BuiltinFunc, Expr, Ident, Type};
+
+use jvm_generation::context::Context;
+use jvm_generation::expr::*;
+use jvm_generation::jvm_type::quote;
+
+// Extern functions are static methods of the Extern class, which has to be on the class
+// path when the program is run. Types are passed as they are, strings are Strings,
+// and a returned null is the empty string.
+pub fn add_extern_func(f: &BuiltinFunc, ctx: &mut Context) {
+    ctx.add_extern_func(&f.ident, f.args.clone(), f.ret_type.clone());
+}
+
+// The output of the program is flushed first, so that it's in order with the output
+// of the extern function.
+pub fn generate_extern_call(ident: &Ident, args: &[Expr], ctx: &mut Context) -> (String, Type) {
+    let mut vals: Vec<String> = Vec::new();
+    for (arg, t) in args.iter().zip(ctx.get_arg_types(ident)) {
+        vals.push(generate_init_expr(arg, &t, ctx));
+    }
+    ctx.call_runtime("flush()V");
+    for val in vals {
+        ctx.add_line(val);
+    }
+    let call = format!("invokestatic {}", quote(&ctx.get_func_symbol(ident)));
+    let ret_type = ctx.get_ret_type(ident);
+    match ret_type {
+        Type::TString => {
+            ctx.add_line(call);
+            ctx.call_runtime("nonNull(Ljava/lang/String;)Ljava/lang/String;");
+            (ctx.add_tmp(&ret_type), ret_type)
+        }
+        _ => add_call(call, &ret_type, ctx),
+    }
+}
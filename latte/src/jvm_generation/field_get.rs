@@ -0,0 +1,145 @@
+use ast::*;
+
+use jvm_generation::class_data::*;
+use jvm_generation::context::*;
+use jvm_generation::generate::*;
+
+// Returns an lvalue: a variable, a field or an element, whose object and index are
+// already evaluated.
+impl GenerateCode<(LValue, Type)> for FieldGet {
+    fn generate_code(&self, ctx: &mut Context) -> (LValue, Type) {
+        match *self {
+            FieldGet::Direct(ref ident) => {
+                if ctx.var_exists(ident) {
+                    let (slot, t) = ctx.get_var(ident);
+                    (LValue::Local(slot, t.clone()), t)
+                } else if let Some((name, t)) = get_static_field(ident, ctx) {
+                    (LValue::Static(name), t)
+                } else if ctx.global_exists(ident) && !is_self_field(ident, ctx) {
+                    let (name, t) = ctx.get_global(ident);
+                    (LValue::Static(name), t)
+                } else {
+                    self_access(ident).generate_code(ctx)
+                }
+            }
+            FieldGet::IdxAccess(ref arr, ref idx) => {
+                let (arr, arr_t) = arr.generate_code(ctx);
+                let (idx, _) = idx.generate_code(ctx);
+                // elements of lists are bounds-checked by the runtime on each access
+                match arr_t {
+                    Type::TList(ref elem_t) => {
+                        (LValue::ListElem(arr, idx, *elem_t.clone()), *elem_t.clone())
+                    }
+                    Type::TArray(ref elem_t) => {
+                        (LValue::Elem(arr, idx, *elem_t.clone()), *elem_t.clone())
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            FieldGet::Indirect(ref expr, ref field) => {
+                if let Some(id) = as_class_id(expr, ctx) {
+                    let (name, t) = ctx.find_static_field(id, field).unwrap();
+                    return (LValue::Static(name), t);
+                }
+                let (obj, obj_t) = expr.generate_code(ctx);
+                match obj_t {
+                    Type::TArray(..) |
+                    Type::TStringBuilder |
+                    Type::TList(..) |
+                    Type::TMap(..) => (LValue::Size(obj, obj_t.clone()), Type::TInt),
+                    Type::TObject(ref cname) => {
+                        // the field is referred to in the class which declares it
+                        let mut id = ctx.get_class_id(cname);
+                        while !ctx.get_class_data(id).has_field(field) {
+                            id = ctx.get_class_data(id).get_super();
+                        }
+                        let field_t = ctx.get_class_data(id).get_field_type(field);
+                        let field_ref = ctx.get_class_data(id).get_field_ref(field);
+                        (LValue::Field(obj, field_ref), field_t)
+                    }
+                    _ => unreachable!(),
+                }
+            }
+        }
+    }
+}
+
+// Returns the object of a method call and the name of the method or function,
+// as the key of its type in the context.
+impl GenerateCode<(Option<(String, Type)>, Ident)> for FieldGet {
+    fn generate_code(&self, ctx: &mut Context) -> (Option<(String, Type)>, Ident) {
+        match *self {
+            FieldGet::Direct(ref ident) => {
+                if let Some(func) = get_static_method(ident, ctx) {
+                    (None, func)
+                } else if ctx.func_exists(ident) {
+                    (None, ident.clone())
+                } else {
+                    self_access(ident).generate_code(ctx)
+                }
+            }
+            FieldGet::Indirect(ref expr, ref field) => {
+                if let Some(id) = as_class_id(expr, ctx) {
+                    return (None, ctx.find_static_method(id, field).unwrap());
+                }
+                let (val, obj_t) = expr.generate_code(ctx);
+                (Some((val, obj_t)), field.clone())
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn is_self_field(ident: &Ident, ctx: &Context) -> bool {
+    match ctx.class {
+        Some(id) => ctx.has_field(id, ident),
+        None => false,
+    }
+}
+
+fn get_static_field(ident: &Ident, ctx: &Context) -> Option<(String, Type)> {
+    ctx.static_class.and_then(|id| ctx.find_static_field(id, ident))
+}
+
+fn get_static_method(ident: &Ident, ctx: &Context) -> Option<Ident> {
+    ctx.static_class.and_then(|id| ctx.find_static_method(id, ident))
+}
+
+// <class name>.<static member>, unless the name is hidden by another identifier
+fn as_class_id(e: &Expr, ctx: &Context) -> Option<ClassId> {
+    match *e {
+        Expr::EVar(FieldGet::Direct(ref ident)) if !is_hidden(ident, ctx) &&
+                                                   ctx.class_exists(ident) => {
+            Some(ctx.get_class_id(ident))
+        }
+        _ => None,
+    }
+}
+
+// <enum name>.<variant>, unless the name is hidden by another identifier
+pub fn as_enum_variant(field: &FieldGet, ctx: &Context) -> Option<(i32, Type)> {
+    match *field {
+        FieldGet::Indirect(ref e, ref variant) => {
+            match **e {
+                Expr::EVar(FieldGet::Direct(ref ident)) if !is_hidden(ident, ctx) &&
+                                                           ctx.enum_exists(ident) => {
+                    let id = ctx.get_enum_id(ident);
+                    Some((ctx.get_variant_tag(id, variant), Type::TEnum(ident.clone())))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn is_hidden(ident: &Ident, ctx: &Context) -> bool {
+    ctx.var_exists(ident) || ctx.global_exists(ident) || ctx.func_exists(ident) ||
+    is_self_field(ident, ctx) || get_static_field(ident, ctx).is_some() ||
+    get_static_method(ident, ctx).is_some()
+}
+
+fn self_access(ident: &Ident) -> FieldGet {
+    let self_fg = FieldGet::Direct(Ident("self".to_string()));
+    FieldGet::Indirect(Box::new(Expr::EVar(self_fg)), ident.clone())
+}
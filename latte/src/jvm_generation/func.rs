@@ -0,0 +1,80 @@
+use ast::{Func, Ident, Type};
+
+use code_generation::has_self_tail_call;
+use static_analysis::has_return::*;
+
+use jvm_generation::context::*;
+use jvm_generation::generate::*;
+use jvm_generation::jvm_type::*;
+
+// Functions and static methods are static methods, methods of classes are instance
+// methods with self in the local 0.
+impl GenerateCode<()> for Func {
+    fn generate_code(&self, ctx: &mut Context) {
+        let arg_types: Vec<Type> = self.args.iter().map(|arg| arg.t.clone()).collect();
+        let desc = method_descriptor(&arg_types, &self.ret_type);
+        let (symbol, name, is_static) = match (ctx.class, ctx.static_class) {
+            (Some(id), _) => {
+                let symbol = format!("{}/{}{}", ctx.get_class_data(id).ident, self.ident, desc);
+                (symbol, self.ident.0.clone(), false)
+            }
+            (None, Some(id)) => {
+                let ident = Ident(format!("{}.{}", ctx.get_class_data(id).ident, self.ident));
+                (ctx.get_func_symbol(&ident), self.ident.0.clone(), true)
+            }
+            (None, None) => (ctx.get_func_symbol(&self.ident), get_method_name(&self.ident), true),
+        };
+        let modifiers = match is_static {
+            true => "public static",
+            false => "public",
+        };
+        let name = quote(&format!("{}{}", name, desc));
+        let limits_line = ctx.begin_method(format!(".method {} {}", modifiers, name));
+
+        // variable, its local and type
+        let mut params: Vec<(Ident, usize, Type)> = Vec::new();
+        if let Some(id) = ctx.class {
+            let self_t = Type::TObject(ctx.get_class_data(id).ident.clone());
+            params.push((Ident("self".to_string()), ctx.add_local(&self_t), self_t));
+        }
+        for arg in &self.args {
+            params.push((arg.ident.clone(), ctx.add_local(&arg.t), arg.t.clone()));
+        }
+
+        let is_main = ctx.static_class.is_none() && self.ident.0 == "main";
+        if is_main && ctx.has_globals() {
+            let init = format!("{}/$init_globals()V", ctx.program);
+            ctx.add_line(format!("invokestatic {}", quote(&init)));
+        }
+        let body_label = match has_self_tail_call(self) {
+            true => Some(ctx.next_label()),
+            false => None,
+        };
+        ctx.tail_target = Some(TailTarget {
+            symbol,
+            params: params.iter().map(|&(_, slot, ref t)| (slot, t.clone())).collect(),
+            body_label,
+        });
+        ctx.in_new_scope(|ctx| {
+            for &(ref ident, slot, ref t) in &params {
+                ctx.set_var(ident.clone(), slot, t.clone());
+            }
+            if let Some(label) = body_label {
+                ctx.add_label(label);
+            }
+            ctx.ret_type = self.ret_type.clone();
+            self.body.generate_code(ctx);
+        });
+        // the verifier rejects methods whose end can be reached, even if every path
+        // returns
+        match self.ret_type == Type::TVoid && !self.body.has_return() {
+            true => ctx.add_line("return".to_string()),
+            false => {
+                ctx.add_line("aconst_null".to_string());
+                ctx.add_line("athrow".to_string());
+            }
+        }
+        ctx.tail_target = None;
+        ctx.end_method(limits_line);
+    }
+}
@@ -0,0 +1,261 @@
+use ast::Type;
+
+pub const RUNTIME_CLASS: &str = "LatteRuntime";
+pub const MAP_CLASS: &str = "LatteMap";
+pub const EXTERN_CLASS: &str = "Extern";
+
+// Enums are ints, chars are kept in 0..255. Lists are ArrayLists of boxed elements,
+// maps are LatteMaps of the runtime.
+pub fn descriptor(t: &Type) -> String {
+    match *t {
+        Type::TInt | Type::TEnum(..) => "I".to_string(),
+        Type::TLong => "J".to_string(),
+        Type::TDouble => "D".to_string(),
+        Type::TBool => "Z".to_string(),
+        Type::TChar => "C".to_string(),
+        Type::TVoid => "V".to_string(),
+        Type::TArray(ref elem_t) => format!("[{}", descriptor(elem_t)),
+        Type::TString |
+        Type::TStringBuilder |
+        Type::TList(..) |
+        Type::TMap(..) |
+        Type::TObject(..) => format!("L{};", class_name(t)),
+        Type::TFunc(..) |
+        Type::TNull => unreachable!(),
+    }
+}
+
+pub fn method_descriptor(arg_types: &[Type], ret_type: &Type) -> String {
+    let args: Vec<String> = arg_types.iter().map(descriptor).collect();
+    format!("({}){}", args.concat(), descriptor(ret_type))
+}
+
+// Names in instructions and directives are quoted, so that names of the program
+// can't be taken for keywords of Jasmin, e.g. a class named dup.
+pub fn quote(name: &str) -> String {
+    format!("'{}'", name)
+}
+
+// name of the class of objects, as used by new, anewarray and checkcast
+pub fn class_name(t: &Type) -> String {
+    match *t {
+        Type::TString => "java/lang/String".to_string(),
+        Type::TStringBuilder => "java/lang/StringBuilder".to_string(),
+        Type::TList(..) => "java/util/ArrayList".to_string(),
+        Type::TMap(..) => MAP_CLASS.to_string(),
+        Type::TObject(ref cname) => format!("{}", cname),
+        Type::TArray(..) => descriptor(t),
+        _ => unreachable!(),
+    }
+}
+
+// prefix of instructions on locals and of returns
+pub fn type_prefix(t: &Type) -> &'static str {
+    match *t {
+        Type::TInt | Type::TEnum(..) | Type::TBool | Type::TChar => "i",
+        Type::TLong => "l",
+        Type::TDouble => "d",
+        _ => "a",
+    }
+}
+
+// prefix of instructions on elements of arrays, booleans are bytes
+pub fn array_prefix(elem_t: &Type) -> &'static str {
+    match *elem_t {
+        Type::TBool => "b",
+        Type::TChar => "c",
+        _ => type_prefix(elem_t),
+    }
+}
+
+// number of slots taken by a local of the type
+pub fn slot_size(t: &Type) -> usize {
+    match *t {
+        Type::TVoid => 0,
+        Type::TLong | Type::TDouble => 2,
+        _ => 1,
+    }
+}
+
+pub fn load_instr(t: &Type, slot: usize) -> String {
+    format!("{}load {}", type_prefix(t), slot)
+}
+
+pub fn store_instr(t: &Type, slot: usize) -> String {
+    format!("{}store {}", type_prefix(t), slot)
+}
+
+pub fn zero(t: &Type) -> String {
+    match type_prefix(t) {
+        "i" => "iconst_0".to_string(),
+        "l" => "lconst_0".to_string(),
+        "d" => "dconst_0".to_string(),
+        _ => "aconst_null".to_string(),
+    }
+}
+
+pub fn int_const(x: i32) -> String {
+    match x {
+        -1..=5 => format!("iconst_{}", x).replace('-', "m"),
+        -128..=127 => format!("bipush {}", x),
+        -32768..=32767 => format!("sipush {}", x),
+        _ => format!("ldc {}", x),
+    }
+}
+
+pub fn long_const(x: i64) -> String {
+    match x {
+        0 | 1 => format!("lconst_{}", x),
+        _ => format!("ldc2_w {}", x),
+    }
+}
+
+// Jasmin reads a double only with a fraction and the d suffix, the other values
+// are given by their bits.
+pub fn double_consts(x: f64) -> Vec<String> {
+    if x.to_bits() == 0 {
+        return vec![format!("dconst_0")];
+    }
+    if x == 1.0 {
+        return vec![format!("dconst_1")];
+    }
+    if !x.is_finite() {
+        let bits = long_const(x.to_bits() as i64);
+        return vec![bits, format!("invokestatic java/lang/Double/longBitsToDouble(J)D")];
+    }
+    let lit = format!("{:e}", x);
+    let lit = match lit.contains('.') {
+        true => lit,
+        false => lit.replace('e', ".0e"),
+    };
+    vec![format!("ldc2_w {}d", lit)]
+}
+
+// A string literal, the characters are the bytes of the string.
+pub fn string_const(bytes: &[u8]) -> String {
+    let mut res = String::new();
+    for &c in bytes {
+        match c {
+            b'"' | b'\\' => {
+                res.push('\\');
+                res.push(c as char);
+            }
+            _ if c.is_ascii_graphic() || c == b' ' => res.push(c as char),
+            _ => res.push_str(&format!("\\u{:04x}", c)),
+        }
+    }
+    format!("ldc \"{}\"", res)
+}
+
+// Elements of lists and maps are objects, primitives are boxed.
+pub fn boxed_class(t: &Type) -> Option<&'static str> {
+    match *t {
+        Type::TInt | Type::TEnum(..) => Some("java/lang/Integer"),
+        Type::TLong => Some("java/lang/Long"),
+        Type::TDouble => Some("java/lang/Double"),
+        Type::TBool => Some("java/lang/Boolean"),
+        Type::TChar => Some("java/lang/Character"),
+        _ => None,
+    }
+}
+
+pub fn box_instrs(t: &Type) -> Vec<String> {
+    match boxed_class(t) {
+        Some(class) => {
+            vec![format!("invokestatic {}/valueOf({}){}",
+                         class,
+                         descriptor(t),
+                         object_descriptor(class))]
+        }
+        None => vec![],
+    }
+}
+
+// the object on top of the stack as a value of the given type
+pub fn unbox_instrs(t: &Type) -> Vec<String> {
+    match boxed_class(t) {
+        Some(class) => {
+            let getter = match *t {
+                Type::TInt | Type::TEnum(..) => "intValue",
+                Type::TLong => "longValue",
+                Type::TDouble => "doubleValue",
+                Type::TBool => "booleanValue",
+                _ => "charValue",
+            };
+            vec![format!("checkcast {}", class),
+                 format!("invokevirtual {}/{}(){}", class, getter, descriptor(t))]
+        }
+        None => vec![format!("checkcast {}", quote(&class_name(t)))],
+    }
+}
+
+fn object_descriptor(class: &str) -> String {
+    format!("L{};", class)
+}
+
+// Change of the depth of the operand stack after the instruction, used to compute
+// .limit stack. Instructions not listed here don't change it.
+pub fn stack_effect(instr: &str) -> i32 {
+    let mut parts = instr.splitn(2, ' ');
+    let op = parts.next().unwrap();
+    let arg = parts.next().unwrap_or("");
+    match op {
+        "ldc2_w" | "lconst_0" | "lconst_1" | "dconst_0" | "dconst_1" | "lload" | "dload" |
+        "dup2" => 2,
+        "ldc" | "bipush" | "sipush" | "aconst_null" | "iload" | "aload" | "new" | "dup" |
+        "i2l" | "i2d" => 1,
+        _ if op.starts_with("iconst_") => 1,
+        "istore" | "astore" | "pop" | "l2i" | "d2i" | "iadd" | "isub" | "imul" | "idiv" |
+        "irem" | "iand" | "ior" | "ixor" | "ishl" | "ishr" | "ifeq" | "ifne" | "iflt" |
+        "ifge" | "ifgt" | "ifle" | "ireturn" | "areturn" | "athrow" | "iaload" | "baload" |
+        "caload" | "aaload" => -1,
+        "lstore" | "dstore" | "pop2" | "ladd" | "lsub" | "lmul" | "ldiv" | "lrem" | "land" |
+        "lor" | "lxor" | "dadd" | "dsub" | "dmul" | "ddiv" | "lreturn" | "dreturn" => -2,
+        _ if op.starts_with("if_") => -2,
+        "lcmp" | "dcmpl" | "dcmpg" | "iastore" | "bastore" | "castore" | "aastore" => -3,
+        "lastore" | "dastore" => -4,
+        "getstatic" => field_size(arg),
+        "putstatic" => -field_size(arg),
+        "getfield" => field_size(arg) - 1,
+        "putfield" => -field_size(arg) - 1,
+        "invokestatic" => method_effect(arg),
+        "invokevirtual" | "invokespecial" => method_effect(arg) - 1,
+        _ => 0,
+    }
+}
+
+// size of a value given by its descriptor
+fn desc_size(desc: &str) -> i32 {
+    match desc.chars().next() {
+        Some('V') => 0,
+        Some('J') | Some('D') => 2,
+        _ => 1,
+    }
+}
+
+// the field is given as <class>/<name> <descriptor>
+fn field_size(field: &str) -> i32 {
+    desc_size(field.rsplit(' ').next().unwrap())
+}
+
+// the method is given as <class>/<name>(<args>)<result>, args are popped
+fn method_effect(method: &str) -> i32 {
+    let start = method.find('(').unwrap();
+    let end = method.find(')').unwrap();
+    let mut args = &method[start + 1..end];
+    let mut effect = desc_size(&method[end + 1..]);
+    while !args.is_empty() {
+        let dims = args.len() - args.trim_start_matches('[').len();
+        args = &args[dims..];
+        let len = match args.starts_with('L') {
+            true => args.find(';').unwrap() + 1,
+            false => 1,
+        };
+        effect -= match dims {
+            0 => desc_size(args),
+            _ => 1,
+        };
+        args = &args[len..];
+    }
+    effect
+}
@@ -0,0 +1,247 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use ast::*;
+use builtins::*;
+use options::Options;
+
+mod class;
+mod class_data;
+mod collection;
+mod context;
+mod enum_def;
+mod expr;
+mod extern_func;
+mod field_get;
+mod func;
+mod generate;
+mod global;
+mod jvm_type;
+mod stmt;
+mod tail_call;
+
+use self::class_data::*;
+use self::context::*;
+use self::extern_func::*;
+use self::generate::*;
+use self::jvm_type::*;
+
+// Generates Jasmin assembly: a file for the program class, which holds functions
+// and globals as static members, and one for each class. They are assembled with
+// lib/jasmin.jar and run with the runtime classes (lib/LatteRuntime.java). Strings
+// are Java strings of bytes, objects are garbage collected.
+// Returns the written files and the report of devirtualized calls, which is empty,
+// as methods are always called with invokevirtual.
+pub fn gen_jvm(p: &Program,
+               opts: &Options,
+               program: &str,
+               out_dir: &Path)
+               -> Result<(Vec<PathBuf>, Vec<String>), io::Error> {
+    check_class_names(p, program)?;
+    let mut ctx = create_context(p, opts, program);
+
+    for def in &p.0 {
+        match *def {
+            Def::DClass(ref c) => c.generate_code(&mut ctx),
+            Def::DFunc(ref f) => f.generate_code(&mut ctx),
+            Def::DEnum(ref e) => e.generate_code(&mut ctx),
+            Def::DGlobal(..) |
+            Def::DExtern(..) => {}
+        }
+    }
+    if ctx.has_globals() {
+        global::generate_init(p, &mut ctx);
+    }
+    generate_main(&mut ctx);
+
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for (name, lines) in ctx.take_class_files() {
+        let path = out_dir.join(format!("{}.j", name));
+        let mut out_file = File::create(&path)?;
+        for line in lines {
+            writeln!(out_file, "{}", line)?;
+        }
+        paths.push(path);
+    }
+    Ok((paths, Vec::new()))
+}
+
+// Classes of the program share the class path with the program class and
+// the runtime classes.
+fn check_class_names(p: &Program, program: &str) -> Result<(), io::Error> {
+    let reserved = [program, RUNTIME_CLASS, MAP_CLASS, "LatteFile", EXTERN_CLASS];
+    for def in &p.0 {
+        if let Def::DClass(ref c) = *def {
+            if reserved.contains(&c.name.0.as_str()) {
+                let msg = format!("Class {} clashes with a class of the JVM program", c.name);
+                return Err(io::Error::other(msg));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Registers the program and emits the header of the program class with its globals.
+fn create_context(p: &Program, opts: &Options, program: &str) -> Context {
+    let mut ctx = Context::new(opts, program);
+    for f in get_builtin_functions() {
+        ctx.add_builtin_func(&f.ident, f.args.clone(), f.ret_type.clone());
+    }
+    for def in &p.0 {
+        if let Def::DExtern(ref f) = *def {
+            add_extern_func(f, &mut ctx);
+        }
+    }
+
+    for def in &p.0 {
+        if let Def::DEnum(ref e) = *def {
+            ctx.add_enum(&e.name, &e.variants);
+        }
+    }
+
+    add_classes(p, &mut ctx);
+    add_funcs(p, &mut ctx);
+
+    ctx.add_directive(format!(".class public {}", quote(program)));
+    ctx.add_directive(".super java/lang/Object".to_string());
+    ctx.add_empty_line();
+    ctx.add_comment("globals".to_string());
+    add_globals(p, &mut ctx);
+    ctx.add_empty_line();
+    ctx
+}
+
+fn add_classes(p: &Program, ctx: &mut Context) {
+    let mut classes: Vec<&Class> = Vec::new();
+    for def in &p.0 {
+        if let Def::DClass(ref c) = *def {
+            classes.push(c);
+        }
+    }
+
+    for (id, class) in classes.iter().enumerate() {
+        ctx.add_class_id(&class.name, id);
+    }
+
+    let mut added: HashSet<ClassId> = HashSet::new();
+    for id in 0..classes.len() {
+        add_class_data(id, &classes, &mut added, ctx);
+    }
+
+    let mut class_map: HashMap<Ident, &Class> = HashMap::new();
+    for class in &classes {
+        class_map.insert(class.name.clone(), class);
+    }
+    for class in &classes {
+        let vtable = get_vtable(class, &class_map, ctx);
+        let id = ctx.get_class_id(&class.name);
+        ctx.set_vtable(id, vtable);
+    }
+}
+
+fn add_class_data(id: ClassId,
+                  classes: &Vec<&Class>,
+                  added: &mut HashSet<ClassId>,
+                  ctx: &mut Context) {
+    if added.contains(&id) {
+        return;
+    }
+    let class = classes[id];
+    let mut class_data = ClassData::new(&class.name);
+    if let Some(ref super_name) = class.superclass {
+        let super_id = ctx.get_class_id(super_name);
+        add_class_data(super_id, classes, added, ctx);
+        class_data.set_super(super_id);
+    }
+    added.insert(id);
+
+    for v in &class.vars {
+        class_data.add_field(&v.ident, &v.t);
+    }
+    ctx.add_class(id, class_data);
+}
+
+fn get_vtable(c: &Class, classes: &HashMap<Ident, &Class>, ctx: &Context) -> VTable {
+    let mut vtable = VTable::new();
+    if let Some(ref super_ident) = c.superclass {
+        let superclass = classes.get(super_ident).unwrap();
+        vtable = get_vtable(superclass, classes, ctx);
+    }
+
+    let id = ctx.get_class_id(&c.name);
+    for f in &c.methods {
+        let entry = VTableEntry {
+            real_ident: format!("{}/{}", c.name, f.ident),
+            class_id: id,
+            ret_type: f.ret_type.clone(),
+            arg_types: f.args.iter().map(|v| v.t.clone()).collect(),
+        };
+        vtable.set_func(&f.ident, entry);
+    }
+
+    vtable
+}
+
+// Registers functions and static methods of classes, methods are found in vtables.
+fn add_funcs(p: &Program, ctx: &mut Context) {
+    for def in &p.0 {
+        match *def {
+            Def::DFunc(ref f) => {
+                let arg_types = f.args.iter().map(|arg| arg.t.clone()).collect();
+                ctx.add_func(&f.ident, arg_types, f.ret_type.clone());
+            }
+            Def::DClass(ref c) => {
+                let id = ctx.get_class_id(&c.name);
+                for f in &c.static_methods {
+                    let arg_types = f.args.iter().map(|arg| arg.t.clone()).collect();
+                    let ident = Ident(format!("{}.{}", c.name, f.ident));
+                    ctx.add_func(&ident, arg_types, f.ret_type.clone());
+                    ctx.add_static_method(id, &f.ident);
+                }
+            }
+            Def::DGlobal(..) |
+            Def::DEnum(..) |
+            Def::DExtern(..) => {}
+        }
+    }
+}
+
+fn add_globals(p: &Program, ctx: &mut Context) {
+    for def in &p.0 {
+        match *def {
+            Def::DGlobal(ref g) => {
+                for decl in &g.decls {
+                    ctx.add_global(decl.get_ident(), &g.t);
+                }
+            }
+            Def::DClass(ref c) => {
+                let id = ctx.get_class_id(&c.name);
+                for g in &c.static_vars {
+                    for decl in &g.decls {
+                        ctx.add_static_field(id, decl.get_ident(), &g.t);
+                    }
+                }
+            }
+            Def::DFunc(..) |
+            Def::DEnum(..) |
+            Def::DExtern(..) => {}
+        }
+    }
+}
+
+// The entry point of the JVM, the runtime runs main of the program in a thread
+// with a large stack and exits with its result.
+fn generate_main(ctx: &mut Context) {
+    let header = ".method public static main([Ljava/lang/String;)V";
+    let limits_line = ctx.begin_method(header.to_string());
+    let program = ctx.program.clone();
+    ctx.add_line(string_const(program.as_bytes()));
+    let args_t = Type::TArray(Box::new(Type::TString));
+    let args = load_instr(&args_t, ctx.add_local(&args_t));
+    ctx.add_line(args);
+    ctx.call_runtime("start(Ljava/lang/String;[Ljava/lang/String;)V");
+    ctx.add_line("return".to_string());
+    ctx.end_method(limits_line);
+}
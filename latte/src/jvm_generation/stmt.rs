@@ -0,0 +1,218 @@
+use ast::*;
+
+use code_generation::default_value;
+use static_analysis::has_return::*;
+
+use jvm_generation::collection::*;
+use jvm_generation::context::*;
+use jvm_generation::expr::*;
+use jvm_generation::generate::*;
+use jvm_generation::jvm_type::*;
+use jvm_generation::tail_call::*;
+
+impl GenerateCode<()> for Vec<Stmt> {
+    fn generate_code(&self, ctx: &mut Context) {
+        for stmt in self {
+            stmt.generate_code(ctx);
+            if stmt.has_return() {
+                break;
+            }
+        }
+    }
+}
+
+impl GenerateCode<()> for Stmt {
+    fn generate_code(&self, ctx: &mut Context) {
+        match self.kind {
+            StmtKind::SEmpty => {}
+            StmtKind::SBlock(ref stmts) => ctx.in_new_scope(|ctx| stmts.generate_code(ctx)),
+            StmtKind::SDecl(_, ref decls) => {
+                decls.generate_code(ctx);
+            }
+            StmtKind::SAssign(ref ident, ref e) => {
+                let (lvalue, t) = ident.generate_code(ctx);
+                let val = generate_init_expr(e, &t, ctx);
+                ctx.add_store(&lvalue, &val);
+            }
+            StmtKind::SInc(ref ident) => generate_step(ident, Operator::OpAdd, ctx),
+            StmtKind::SDec(ref ident) => generate_step(ident, Operator::OpSub, ctx),
+            StmtKind::SReturnE(Expr::ECall(ref ident, ref args)) => {
+                generate_tail_call(ident, args, ctx);
+            }
+            StmtKind::SReturnE(ref e) => {
+                let t = ctx.ret_type.clone();
+                let val = generate_init_expr(e, &t, ctx);
+                generate_return(&val, &t, ctx);
+            }
+            StmtKind::SReturn => ctx.add_line("return".to_string()),
+            StmtKind::SExpr(ref e) => {
+                e.generate_code(ctx);
+            }
+            StmtKind::SIf(ref cond, ref s) => {
+                let (cond_val, _) = cond.generate_code(ctx);
+                let end_label = ctx.next_label();
+                ctx.add_line(cond_val);
+                ctx.add_line(format!("ifeq L{}", end_label));
+                ctx.in_new_scope(|ctx| s.generate_code(ctx));
+                ctx.add_label(end_label);
+            }
+            StmtKind::SIfElse(ref cond, ref if_true, ref if_false) => {
+                let (cond_val, _) = cond.generate_code(ctx);
+                let (else_label, end_label) = (ctx.next_label(), ctx.next_label());
+                ctx.add_line(cond_val);
+                ctx.add_line(format!("ifeq L{}", else_label));
+                ctx.in_new_scope(|ctx| if_true.generate_code(ctx));
+                if !if_true.has_return() {
+                    ctx.add_line(format!("goto L{}", end_label));
+                }
+                ctx.add_label(else_label);
+                ctx.in_new_scope(|ctx| if_false.generate_code(ctx));
+                ctx.add_label(end_label);
+            }
+            StmtKind::SWhile(ref cond, ref s) => {
+                // the condition is evaluated at the beginning of each iteration
+                let (cond_label, end_label) = (ctx.next_label(), ctx.next_label());
+                ctx.add_label(cond_label);
+                let cond_val = ctx.in_new_scope(|ctx| cond.generate_code(ctx).0);
+                ctx.add_line(cond_val);
+                ctx.add_line(format!("ifeq L{}", end_label));
+                ctx.in_new_scope(|ctx| s.generate_code(ctx));
+                ctx.add_line(format!("goto L{}", cond_label));
+                ctx.add_label(end_label);
+            }
+            StmtKind::SSwitch(ref e, ref cases, ref default) => {
+                let (val, t) = e.generate_code(ctx);
+                // labels are evaluated first, in order, as in LLVM
+                let mut label_vals: Vec<(String, usize)> = Vec::new();
+                for (idx, case) in cases.iter().enumerate() {
+                    for label in &case.labels {
+                        label_vals.push((label.generate_code(ctx).0, idx));
+                    }
+                }
+                // the first matching label selects its case
+                let case_labels: Vec<usize> = cases.iter().map(|_| ctx.next_label()).collect();
+                let (default_label, end_label) = (ctx.next_label(), ctx.next_label());
+                for (label_val, idx) in label_vals {
+                    ctx.add_line(val.clone());
+                    ctx.add_line(label_val);
+                    match t == Type::TString {
+                        true => {
+                            ctx.add_line("invokevirtual java/lang/String/\
+                                          equals(Ljava/lang/Object;)Z"
+                                .to_string());
+                            ctx.add_line(format!("ifne L{}", case_labels[idx]));
+                        }
+                        false => {
+                            let jump = cmp_jump(&t, Operator::OpEq, ctx);
+                            ctx.add_line(format!("{} L{}", jump, case_labels[idx]));
+                        }
+                    }
+                }
+                ctx.add_line(format!("goto L{}", default_label));
+                for (case, case_label) in cases.iter().zip(case_labels) {
+                    ctx.add_label(case_label);
+                    generate_case(&case.body, end_label, ctx);
+                }
+                ctx.add_label(default_label);
+                let empty = Vec::new();
+                let default = default.as_ref().unwrap_or(&empty);
+                generate_case(default, end_label, ctx);
+                ctx.add_label(end_label);
+            }
+            StmtKind::SFor(ref t, ref ident, ref arr, ref stmt) => {
+                let (arr, arr_t) = match *arr {
+                    Expr::EArrayLit(..) => {
+                        let arr_t = Type::TArray(Box::new(t.clone()));
+                        (generate_init_expr(arr, &arr_t, ctx), arr_t)
+                    }
+                    _ => arr.generate_code(ctx),
+                };
+                // Maps are iterated over a snapshot of their keys.
+                let (arr, arr_t) = match arr_t {
+                    Type::TMap(ref key_t, _) => generate_map_keys(&arr, key_t, ctx),
+                    _ => (arr, arr_t),
+                };
+                // the size is read at the beginning
+                ctx.add_load(&LValue::Size(arr.clone(), arr_t.clone()));
+                let size = ctx.add_tmp(&Type::TInt);
+                let idx = ctx.add_local(&Type::TInt);
+
+                ctx.in_new_loop(idx, &size, |ctx| {
+                    let idx_val = load_instr(&Type::TInt, idx);
+                    let (elem, elem_t) = match arr_t {
+                        Type::TList(ref elem_t) => {
+                            let elem = LValue::ListElem(arr.clone(), idx_val, *elem_t.clone());
+                            (elem, *elem_t.clone())
+                        }
+                        _ => {
+                            let elem_t = arr_elem_t(&arr_t);
+                            (LValue::Elem(arr.clone(), idx_val, elem_t.clone()), elem_t)
+                        }
+                    };
+                    let slot = ctx.add_local(&elem_t);
+                    ctx.add_load(&elem);
+                    ctx.add_line(store_instr(&elem_t, slot));
+                    ctx.set_var(ident.clone(), slot, elem_t.clone());
+                    stmt.generate_code(ctx);
+                });
+            }
+        }
+    }
+}
+
+// Cases don't fall through, each one jumps to the end of the switch.
+fn generate_case(body: &Vec<Stmt>, end_label: usize, ctx: &mut Context) {
+    ctx.in_new_scope(|ctx| {
+        body.generate_code(ctx);
+        if !body.has_return() {
+            ctx.add_line(format!("goto L{}", end_label));
+        }
+    });
+}
+
+// the value is empty for void functions
+pub fn generate_return(val: &str, t: &Type, ctx: &mut Context) {
+    match *t {
+        Type::TVoid => ctx.add_line("return".to_string()),
+        _ => {
+            ctx.add_line(val.to_string());
+            ctx.add_line(format!("{}return", type_prefix(t)));
+        }
+    }
+}
+
+// ++ and --
+fn generate_step(ident: &FieldGet, op: Operator, ctx: &mut Context) {
+    let (lvalue, t) = ident.generate_code(ctx);
+    ctx.add_load(&lvalue);
+    let old_val = ctx.add_tmp(&t);
+    let one = match t == Type::TLong {
+        true => long_const(1),
+        false => int_const(1),
+    };
+    let val = generate_arith_op(&t, &old_val, op, &one, ctx);
+    ctx.add_store(&lvalue, &val);
+}
+
+impl GenerateCode<()> for Vec<VarDecl> {
+    fn generate_code(&self, ctx: &mut Context) {
+        for var_decl in self {
+            var_decl.generate_code(ctx);
+        }
+    }
+}
+
+impl GenerateCode<()> for VarDecl {
+    fn generate_code(&self, ctx: &mut Context) {
+        let (t, ident, val) = match *self {
+            VarDecl::Init(ref t, ref ident, ref e) => (t, ident, generate_init_expr(e, t, ctx)),
+            VarDecl::NoInit(ref t, ref ident) => {
+                let (val, _) = Expr::ELit(default_value(t)).generate_code(ctx);
+                (t, ident, val)
+            }
+        };
+        let slot = ctx.add_local(t);
+        ctx.add_store(&LValue::Local(slot, t.clone()), &val);
+        ctx.set_var(ident.clone(), slot, t.clone());
+    }
+}
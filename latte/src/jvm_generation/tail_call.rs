@@ -0,0 +1,34 @@
+use ast::*;
+
+use jvm_generation::context::Context;
+use jvm_generation::expr::*;
+use jvm_generation::jvm_type::*;
+use jvm_generation::stmt::generate_return;
+
+// Generates `return f(args)`. Self-recursive calls, which can't be dispatched to
+// an overriding method, set the params and jump to the body of the method. The JVM
+// has no tail calls, other calls return the result of a normal call.
+pub fn generate_tail_call(ident: &FieldGet, args: &[Expr], ctx: &mut Context) {
+    let call = match prepare_call(ident, args, ctx) {
+        PreparedCall::Generated(val, t) => return generate_return(&val, &t, ctx),
+        PreparedCall::Call(call) => call,
+    };
+
+    let target = ctx.tail_target.clone().unwrap();
+    let is_self_call = !call.is_overridden && call.func == target.symbol;
+    if let (true, Some(body_label)) = (is_self_call, target.body_label) {
+        ctx.add_comment("tail recursion".to_string());
+        // all args are pushed before the params are set, they can refer to the params
+        for (arg, _) in &call.args {
+            ctx.add_line(arg.clone());
+        }
+        for &(slot, ref t) in target.params.iter().rev() {
+            ctx.add_line(store_instr(t, slot));
+        }
+        ctx.add_line(format!("goto L{}", body_label));
+    } else {
+        let call_instr = push_call(&call, ctx);
+        let (val, t) = add_call(call_instr, &call.ret_type, ctx);
+        generate_return(&val, &t, ctx);
+    }
+}
@@ -4,7 +4,13 @@ extern crate either;
 pub mod ast;
 pub mod ast_print;
 pub mod builtins;
+pub mod c_generation;
 pub mod code_generation;
+pub mod jvm_generation;
+pub mod mangling;
 pub mod optimization;
+pub mod options;
 pub mod parser;
 pub mod static_analysis;
+pub mod wasm_generation;
+pub mod x86_generation;
@@ -1,15 +1,22 @@
 use std::env;
-use std::fs::File;
-use std::io::{self, Write};
-use std::process::{Command, exit};
+use std::fs::{self, File};
+use std::io::{self, BufRead, Write};
+use std::process::{Command, Stdio, exit};
 
 extern crate latte;
 
 use latte::ast::Program;
+use latte::c_generation;
 use latte::code_generation;
+use latte::jvm_generation;
+use latte::mangling;
 use latte::optimization;
+use latte::options::{Options, Target};
 use latte::parser;
 use latte::static_analysis;
+use latte::static_analysis::warning::{self, Warning};
+use latte::wasm_generation;
+use latte::x86_generation;
 
 macro_rules! println_stderr(
     ($($arg:tt)*) => { {
@@ -19,28 +26,55 @@ macro_rules! println_stderr(
 );
 
 fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() > 1 && args[1] == "--demangle" {
+        demangle(&args[2..]);
+        exit(0);
+    }
     match run() {
         Err(e) => {
             println_stderr!("ERROR\n{}", e);
             exit(-1);
         }
-        Ok(..) => {
+        Ok((warnings, devirtualized)) => {
             println_stderr!("OK");
+            for warning in warnings {
+                println_stderr!("{}", warning);
+            }
+            for call in devirtualized {
+                println_stderr!("Devirtualized: {}", call);
+            }
             exit(0);
         }
     }
 }
 
-fn run() -> Result<(), String> {
-    let args: std::vec::Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        return Err(format!("Usage: ./{} input_file", args[0]));
+// Prints the source names of the given symbols, or demangles the standard input
+// line by line, like c++filt.
+fn demangle(symbols: &[String]) {
+    if !symbols.is_empty() {
+        for symbol in symbols {
+            println!("{}", mangling::demangle_text(symbol));
+        }
+        return;
     }
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        match line {
+            Ok(line) => println!("{}", mangling::demangle_text(&line)),
+            Err(_) => break,
+        }
+    }
+}
 
-    let path = std::path::Path::new(&args[1]);
+fn run() -> Result<(Vec<Warning>, Vec<String>), String> {
+    let args: std::vec::Vec<String> = env::args().collect();
+    let (opts, input) = Options::from_args(&args)?;
+
+    let path = std::path::Path::new(&input);
     let program_file = match File::open(path) {
         Err(why) => {
-            return Err(format!("Couldn't open file {}: {}", &args[1], why));
+            return Err(format!("Couldn't open file {}: {}", &input, why));
         }
         Ok(r) => r,
     };
@@ -53,14 +87,15 @@ fn run() -> Result<(), String> {
         Ok(x) => x,
     };
 
-    match static_analysis::run(&program) {
+    let mut warnings = match static_analysis::run(&program) {
         Err(why) => {
             return Err(format!("{}", why));
         }
-        _ => {}
+        Ok(warnings) => warnings,
     };
 
-    let program = optimization::run(program);
+    let (program, opt_warnings) = optimization::run(program, &opts)?;
+    warnings.extend(opt_warnings);
 
     match static_analysis::check_returns(&program) {
         Err(why) => {
@@ -69,26 +104,40 @@ fn run() -> Result<(), String> {
         _ => {}
     }
 
-    match compile(&program, &path) {
+    warnings.extend(static_analysis::check_init(&program));
+    let warnings = warning::apply_options(warnings, &opts.warnings)?;
+    let program = optimization::remove_dead_code(program);
+
+    let compiled = match opts.target {
+        Target::Llvm => compile(&program, &opts, path),
+        Target::C => compile_c(&program, &opts, path),
+        Target::Wasm => compile_wasm(&program, &opts, path),
+        Target::X86 => compile_x86(&program, &opts, path),
+        Target::Jvm => compile_jvm(&program, &opts, path),
+    };
+    let devirtualized = match compiled {
         Err(why) => {
             return Err(format!("Compilation failed: {}", why));
         }
-        _ => {}
-    }
+        Ok(devirtualized) => devirtualized,
+    };
 
-    Ok(())
+    Ok((warnings, devirtualized))
 }
 
-fn compile(p: &Program, input: &std::path::Path) -> Result<(), io::Error> {
+fn compile(p: &Program,
+           opts: &Options,
+           input: &std::path::Path)
+           -> Result<Vec<String>, io::Error> {
     let err = "Something is wrong with file path";
     let filename = input.file_stem().expect(err).to_str().expect(err);
     let out_ll_path = input.with_file_name(filename.to_string() + ".ll");
     let out_bc_path_tmp = input.with_file_name(filename.to_string() + "_tmp.bc");
     let out_bc_path = input.with_file_name(filename.to_string() + ".bc");
-    {
+    let devirtualized = {
         let mut out_ll = File::create(out_ll_path.clone())?;
-        code_generation::gen_llvm(p, &mut out_ll)?;
-    }
+        code_generation::gen_llvm(p, opts, input, &mut out_ll)?
+    };
 
     // compile
     try!(execute_bash_command(Command::new("llvm-as")
@@ -98,18 +147,165 @@ fn compile(p: &Program, input: &std::path::Path) -> Result<(), io::Error> {
                               "Failed to translate to LLVM bitcode"));
 
     // link
+    let bitcode_files = opts.link.iter().filter(|f| !f.ends_with(".o"));
     try!(execute_bash_command(Command::new("llvm-link")
                                   .arg("-o")
                                   .arg(out_bc_path.to_str().expect(err))
                                   .arg(out_bc_path_tmp.to_str().expect(err))
-                                  .arg("lib/runtime.bc"),
+                                  .arg("lib/runtime.bc")
+                                  .args(bitcode_files),
                               "Failed to link with runtime.bc"));
 
     // rm temp .bc
     try!(execute_bash_command(Command::new("rm").arg(out_bc_path_tmp.to_str().expect(err)),
                               "Failed to clean up temporary .bc file"));
 
-    Ok(())
+    // native executable with the given object files
+    let objects = opts.native_objects();
+    if !objects.is_empty() {
+        let out_o_path = input.with_file_name(filename.to_string() + ".o");
+        let out_path = input.with_file_name(filename);
+        execute_bash_command(Command::new("llc")
+                                 .arg("-filetype=obj")
+                                 .arg("-relocation-model=pic")
+                                 .arg("-o")
+                                 .arg(out_o_path.to_str().expect(err))
+                                 .arg(out_bc_path.to_str().expect(err)),
+                             "Failed to compile to an object file")?;
+        execute_bash_command(Command::new("cc")
+                                 .arg("-o")
+                                 .arg(out_path.to_str().expect(err))
+                                 .arg(out_o_path.to_str().expect(err))
+                                 .args(objects)
+                                 .arg("-lm"),
+                             "Failed to link with object files")?;
+    }
+
+    Ok(devirtualized)
+}
+
+fn compile_c(p: &Program,
+             opts: &Options,
+             input: &std::path::Path)
+             -> Result<Vec<String>, io::Error> {
+    let err = "Something is wrong with file path";
+    let filename = input.file_stem().expect(err).to_str().expect(err);
+    let out_c_path = input.with_file_name(filename.to_string() + ".c");
+    let out_path = input.with_file_name(filename);
+    let devirtualized = {
+        let mut out_c = File::create(out_c_path.clone())?;
+        c_generation::gen_c(p, opts, &mut out_c)?
+    };
+
+    execute_bash_command(Command::new("cc")
+                             .arg("-std=c99")
+                             .arg("-O2")
+                             .arg("-o")
+                             .arg(out_path.to_str().expect(err))
+                             .arg(out_c_path.to_str().expect(err))
+                             .arg("lib/runtime.c")
+                             .args(opts.native_objects())
+                             .arg("-lm"),
+                         "Failed to compile C code")?;
+
+    Ok(devirtualized)
+}
+
+// The module is written in the text format and assembled in the compiler,
+// it's run with `node lib/runtime.js file.wasm`.
+fn compile_wasm(p: &Program,
+                opts: &Options,
+                input: &std::path::Path)
+                -> Result<Vec<String>, io::Error> {
+    let err = "Something is wrong with file path";
+    let filename = input.file_stem().expect(err).to_str().expect(err);
+    let out_wat_path = input.with_file_name(filename.to_string() + ".wat");
+    let out_wasm_path = input.with_file_name(filename.to_string() + ".wasm");
+    let runtime = fs::read_to_string("lib/runtime.wat")?;
+    let devirtualized = {
+        let mut out_wat = File::create(out_wat_path.clone())?;
+        wasm_generation::gen_wasm(p, opts, &runtime, &mut out_wat)?
+    };
+
+    let text = fs::read_to_string(out_wat_path)?;
+    let module = wasm_generation::assemble(&text)
+        .map_err(|e| io::Error::other(format!("Failed to assemble: {}", e)))?;
+    fs::write(out_wasm_path, module)?;
+
+    Ok(devirtualized)
+}
+
+fn compile_x86(p: &Program,
+               opts: &Options,
+               input: &std::path::Path)
+               -> Result<Vec<String>, io::Error> {
+    let err = "Something is wrong with file path";
+    let filename = input.file_stem().expect(err).to_str().expect(err);
+    let out_s_path = input.with_file_name(filename.to_string() + ".s");
+    let out_o_path = input.with_file_name(filename.to_string() + ".o");
+    let out_path = input.with_file_name(filename);
+    let devirtualized = {
+        let mut out_s = File::create(out_s_path.clone())?;
+        x86_generation::gen_x86(p, opts, &mut out_s)?
+    };
+
+    execute_bash_command(Command::new("as")
+                             .arg("-o")
+                             .arg(out_o_path.to_str().expect(err))
+                             .arg(out_s_path.to_str().expect(err)),
+                         "Failed to assemble")?;
+    execute_bash_command(Command::new("cc")
+                             .arg("-o")
+                             .arg(out_path.to_str().expect(err))
+                             .arg(out_o_path.to_str().expect(err))
+                             .arg("lib/runtime.c")
+                             .args(opts.native_objects())
+                             .arg("-lm"),
+                         "Failed to link with the runtime")?;
+
+    Ok(devirtualized)
+}
+
+// The classes are written next to the input together with the runtime classes,
+// the program is run with `java -cp <dir> <name>`.
+fn compile_jvm(p: &Program,
+               opts: &Options,
+               input: &std::path::Path)
+               -> Result<Vec<String>, io::Error> {
+    let err = "Something is wrong with file path";
+    let filename = input.file_stem().expect(err).to_str().expect(err);
+    let out_dir = match input.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => std::path::Path::new("."),
+    };
+    let (j_paths, devirtualized) = jvm_generation::gen_jvm(p, opts, filename, out_dir)?;
+
+    // jasmin doesn't fail on errors, so the classes are checked instead
+    let class_paths: Vec<std::path::PathBuf> =
+        j_paths.iter().map(|path| path.with_extension("class")).collect();
+    for path in &class_paths {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+    }
+    execute_bash_command(Command::new("java")
+                             .arg("-jar")
+                             .arg("lib/jasmin.jar")
+                             .arg("-d")
+                             .arg(out_dir.to_str().expect(err))
+                             .args(&j_paths)
+                             .stdout(Stdio::null()),
+                         "Failed to assemble")?;
+    if !class_paths.iter().all(|path| path.exists()) {
+        return Err(io::Error::other("Failed to assemble"));
+    }
+    execute_bash_command(Command::new("javac")
+                             .arg("-d")
+                             .arg(out_dir.to_str().expect(err))
+                             .arg("lib/LatteRuntime.java"),
+                         "Failed to compile the runtime")?;
+
+    Ok(devirtualized)
 }
 
 fn execute_bash_command(cmd: &mut Command, err: &'static str) -> Result<(), io::Error> {
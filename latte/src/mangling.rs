@@ -0,0 +1,322 @@
+use ast::{Func, Ident, Type};
+
+// Symbols of members of classes in generated code. Functions keep their names,
+// members are mangled, so that their symbols don't depend on the order of
+// declarations and can be mapped back to the source with demangle:
+//   _LM<class><name><args>  method, also name() of enums
+//   _LS<class><name><args>  static method
+//   _LN<class>              allocation of an object (new)
+//   _LI<class>              initialisation of the fields of an object
+// Names are prefixed with their lengths. Args are encoded one after another, v
+// stands for no args:
+//   i int, l long, d double, c char, b boolean, s string, B StringBuilder,
+//   C<class> object, E<enum> enum, A<t> array, L<t> list, M<key><value> map
+// e.g. Account.deposit(int, string) is _LM7Account7depositis.
+// The symbols are valid identifiers in C, assembly and LLVM.
+
+pub fn method_symbol(class: &Ident, f: &Func) -> String {
+    member_symbol('M', class, &f.ident, &arg_types(f))
+}
+
+pub fn static_method_symbol(class: &Ident, f: &Func) -> String {
+    member_symbol('S', class, &f.ident, &arg_types(f))
+}
+
+pub fn enum_name_symbol(enum_name: &Ident) -> String {
+    member_symbol('M', enum_name, &Ident("name".to_string()), &[])
+}
+
+pub fn new_symbol(class: &Ident) -> String {
+    format!("_LN{}", mangle_name(&class.0))
+}
+
+pub fn init_symbol(class: &Ident) -> String {
+    format!("_LI{}", mangle_name(&class.0))
+}
+
+fn member_symbol(kind: char, class: &Ident, name: &Ident, arg_types: &[Type]) -> String {
+    let args = match arg_types.is_empty() {
+        true => "v".to_string(),
+        false => arg_types.iter().map(mangle_type).collect(),
+    };
+    format!("_L{}{}{}{}", kind, mangle_name(&class.0), mangle_name(&name.0), args)
+}
+
+fn arg_types(f: &Func) -> Vec<Type> {
+    f.args.iter().map(|arg| arg.t.clone()).collect()
+}
+
+fn mangle_name(name: &str) -> String {
+    format!("{}{}", name.len(), name)
+}
+
+fn mangle_type(t: &Type) -> String {
+    match *t {
+        Type::TInt => "i".to_string(),
+        Type::TLong => "l".to_string(),
+        Type::TDouble => "d".to_string(),
+        Type::TChar => "c".to_string(),
+        Type::TBool => "b".to_string(),
+        Type::TString => "s".to_string(),
+        Type::TStringBuilder => "B".to_string(),
+        Type::TObject(ref name) => format!("C{}", mangle_name(&name.0)),
+        Type::TEnum(ref name) => format!("E{}", mangle_name(&name.0)),
+        Type::TArray(ref t) => format!("A{}", mangle_type(t)),
+        Type::TList(ref t) => format!("L{}", mangle_type(t)),
+        Type::TMap(ref key_t, ref value_t) => {
+            format!("M{}{}", mangle_type(key_t), mangle_type(value_t))
+        }
+        Type::TVoid | Type::TFunc(..) | Type::TNull => unreachable!(),
+    }
+}
+
+// Returns the source name of a mangled symbol, e.g. Account.deposit(int, string),
+// static Account.create(), new Account or Account.<init>.
+pub fn demangle(symbol: &str) -> Option<String> {
+    let mut d = Demangler {
+        s: symbol.as_bytes(),
+        pos: 0,
+    };
+    if !d.eat(b'_') || !d.eat(b'L') {
+        return None;
+    }
+    let kind = d.next()?;
+    let res = match kind {
+        b'M' | b'S' => {
+            let class = d.name()?;
+            let name = d.name()?;
+            let args = match d.eat(b'v') {
+                true => Vec::new(),
+                false => {
+                    let mut args = vec![format!("{}", d.parse_type()?)];
+                    while !d.at_end() {
+                        args.push(format!("{}", d.parse_type()?));
+                    }
+                    args
+                }
+            };
+            let prefix = match kind {
+                b'S' => "static ",
+                _ => "",
+            };
+            format!("{}{}.{}({})", prefix, class, name, args.join(", "))
+        }
+        b'N' => format!("new {}", d.name()?),
+        b'I' => format!("{}.<init>", d.name()?),
+        _ => return None,
+    };
+    match d.at_end() {
+        true => Some(res),
+        false => None,
+    }
+}
+
+// Replaces mangled symbols in the text, other words are left as they are.
+pub fn demangle_text(text: &str) -> String {
+    let mut res = String::new();
+    let mut word = String::new();
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            word.push(c);
+            continue;
+        }
+        res.push_str(&demangle(&word).unwrap_or_else(|| word.clone()));
+        word.clear();
+        res.push(c);
+    }
+    res.push_str(&demangle(&word).unwrap_or(word));
+    res
+}
+
+struct Demangler<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Demangler<'a> {
+    fn at_end(&self) -> bool {
+        self.pos == self.s.len()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let c = *self.s.get(self.pos)?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        match self.s.get(self.pos) == Some(&c) {
+            true => {
+                self.pos += 1;
+                true
+            }
+            false => false,
+        }
+    }
+
+    fn name(&mut self) -> Option<String> {
+        let begin = self.pos;
+        while self.pos < self.s.len() && self.s[self.pos].is_ascii_digit() {
+            self.pos += 1;
+        }
+        let len: usize = String::from_utf8_lossy(&self.s[begin..self.pos]).parse().ok()?;
+        let end = self.pos.checked_add(len)?;
+        if len == 0 || end > self.s.len() {
+            return None;
+        }
+        let name = String::from_utf8_lossy(&self.s[self.pos..end]).into_owned();
+        self.pos = end;
+        Some(name)
+    }
+
+    fn parse_type(&mut self) -> Option<Type> {
+        let t = match self.next()? {
+            b'i' => Type::TInt,
+            b'l' => Type::TLong,
+            b'd' => Type::TDouble,
+            b'c' => Type::TChar,
+            b'b' => Type::TBool,
+            b's' => Type::TString,
+            b'B' => Type::TStringBuilder,
+            b'C' => Type::TObject(Ident(self.name()?)),
+            b'E' => Type::TEnum(Ident(self.name()?)),
+            b'A' => Type::TArray(Box::new(self.parse_type()?)),
+            b'L' => Type::TList(Box::new(self.parse_type()?)),
+            b'M' => {
+                let key_t = self.parse_type()?;
+                Type::TMap(Box::new(key_t), Box::new(self.parse_type()?))
+            }
+            _ => return None,
+        };
+        Some(t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::{Access, Var};
+
+    fn func(name: &str, arg_types: Vec<Type>) -> Func {
+        let args = arg_types.into_iter()
+            .enumerate()
+            .map(|(i, t)| {
+                Var {
+                    t,
+                    ident: Ident(format!("a{}", i)),
+                    access: Access::Public,
+                }
+            })
+            .collect();
+        Func {
+            ident: Ident(name.to_string()),
+            args,
+            ret_type: Type::TVoid,
+            body: Vec::new(),
+            access: Access::Public,
+            line: 0,
+        }
+    }
+
+    fn class(name: &str) -> Ident {
+        Ident(name.to_string())
+    }
+
+    fn object(name: &str) -> Type {
+        Type::TObject(class(name))
+    }
+
+    #[test]
+    fn method_round_trip() {
+        let f = func("deposit", vec![Type::TInt, Type::TString]);
+        let symbol = method_symbol(&class("Account"), &f);
+        assert_eq!(symbol, "_LM7Account7depositis");
+        assert_eq!(demangle(&symbol).unwrap(), "Account.deposit(int, string)");
+    }
+
+    #[test]
+    fn no_args() {
+        let symbol = method_symbol(&class("Account"), &func("close", vec![]));
+        assert_eq!(symbol, "_LM7Account5closev");
+        assert_eq!(demangle(&symbol).unwrap(), "Account.close()");
+    }
+
+    #[test]
+    fn static_new_init_and_enum_name() {
+        let symbol = static_method_symbol(&class("Account"), &func("create", vec![]));
+        assert_eq!(symbol, "_LS7Account6createv");
+        assert_eq!(demangle(&symbol).unwrap(), "static Account.create()");
+        assert_eq!(new_symbol(&class("Account")), "_LN7Account");
+        assert_eq!(demangle("_LN7Account").unwrap(), "new Account");
+        assert_eq!(init_symbol(&class("Account")), "_LI7Account");
+        assert_eq!(demangle("_LI7Account").unwrap(), "Account.<init>");
+        assert_eq!(enum_name_symbol(&class("Color")), "_LM5Color4namev");
+        assert_eq!(demangle("_LM5Color4namev").unwrap(), "Color.name()");
+    }
+
+    #[test]
+    fn all_types_round_trip() {
+        let types = vec![Type::TInt,
+                         Type::TLong,
+                         Type::TDouble,
+                         Type::TChar,
+                         Type::TBool,
+                         Type::TString,
+                         Type::TStringBuilder,
+                         object("Point"),
+                         Type::TEnum(class("Color")),
+                         Type::TArray(Box::new(Type::TArray(Box::new(Type::TDouble)))),
+                         Type::TList(Box::new(object("Point"))),
+                         Type::TMap(Box::new(Type::TString),
+                                    Box::new(Type::TList(Box::new(Type::TInt))))];
+        let symbol = method_symbol(&class("Shape"), &func("f", types.clone()));
+        assert_eq!(symbol, "_LM5Shape1fildcbsBC5PointE5ColorAAdLC5PointMsLi");
+        let args: Vec<String> = types.iter().map(|t| format!("{}", t)).collect();
+        assert_eq!(demangle(&symbol).unwrap(),
+                   format!("Shape.f({})", args.join(", ")));
+    }
+
+    // lengths are followed by names which can contain digits and underscores
+    #[test]
+    fn names_with_digits_and_underscores() {
+        let f = func("get_2nd", vec![object("Vec3_i"), Type::TInt]);
+        let symbol = method_symbol(&class("A1_b2"), &f);
+        assert_eq!(symbol, "_LM5A1_b27get_2ndC6Vec3_ii");
+        assert_eq!(demangle(&symbol).unwrap(), "A1_b2.get_2nd(Vec3_i, int)");
+        let long_name = "a_very_long_class_name_2";
+        assert_eq!(demangle(&new_symbol(&class(long_name))).unwrap(),
+                   format!("new {}", long_name));
+    }
+
+    #[test]
+    fn malformed_symbols() {
+        for symbol in &["",
+                        "main",
+                        "_L",
+                        "_LX7Account",
+                        "_LN",
+                        "_LN0",
+                        "_LN8Account",
+                        "_LN7Accounts",
+                        "_LM7Account7deposit",
+                        "_LM7Account7depositx",
+                        "_LM7Account7depositvi",
+                        "_LM7Account7depositA",
+                        "_LM7Account7depositM",
+                        "_LM7Account7depositC",
+                        "_LN99999999999999999999999Account"] {
+            assert_eq!(demangle(symbol), None, "{}", symbol);
+        }
+    }
+
+    #[test]
+    fn demangle_text_replaces_symbols() {
+        let text = "call void @_LM7Account7depositis(%class_0* %r_1, i32 5) ; _LN7Account";
+        assert_eq!(demangle_text(text),
+                   "call void @Account.deposit(int, string)(%class_0* %r_1, i32 5) ; new \
+                    Account");
+        assert_eq!(demangle_text("main _LN _LI7Account."),
+                   "main _LN Account.<init>.");
+        assert_eq!(demangle_text(""), "");
+    }
+}
@@ -0,0 +1,373 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Deref;
+
+use ast::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Node {
+    Func(Ident),
+    Method(Ident /* class name */, Ident),
+}
+
+// Functions and methods reachable from main and initializers of globals.
+// Without types at hand, a call through an object or a call by a bare name
+// inside a class leads to every method with that name in a live class, which
+// covers every method a virtual call site may dispatch to.
+#[derive(Debug)]
+pub struct CallGraph {
+    pub calls: HashMap<Node, HashSet<Node>>, // reachable nodes only
+    pub classes: HashSet<Ident>,              // classes that are referenced anywhere
+}
+
+impl CallGraph {
+    pub fn new(p: &Program) -> CallGraph {
+        let mut funcs: HashMap<&Ident, &Func> = HashMap::new();
+        let mut classes: HashMap<&Ident, &Class> = HashMap::new();
+        for def in &p.0 {
+            match *def {
+                Def::DFunc(ref f) => {
+                    funcs.insert(&f.ident, f);
+                }
+                Def::DClass(ref c) => {
+                    classes.insert(&c.name, c);
+                }
+                Def::DGlobal(..) | Def::DEnum(..) | Def::DExtern(..) => {}
+            }
+        }
+
+        let mut builder = Builder {
+            funcs,
+            classes,
+            graph: CallGraph {
+                calls: HashMap::new(),
+                classes: HashSet::new(),
+            },
+            called: HashSet::new(),
+            visited: HashMap::new(),
+        };
+
+        let mut roots = Refs::new();
+        roots.calls.insert(Ident("main".to_string()));
+        for def in &p.0 {
+            match *def {
+                Def::DGlobal(ref g) => g.collect(&mut roots),
+                // static fields are initialized together with globals
+                Def::DClass(ref c) if !c.static_vars.is_empty() => {
+                    roots.names.insert(c.name.clone());
+                }
+                _ => {}
+            }
+        }
+        builder.add_refs(roots);
+        builder.run();
+        builder.graph
+    }
+
+    pub fn is_reachable(&self, node: &Node) -> bool {
+        self.calls.contains_key(node)
+    }
+
+    // whether the function can call itself, directly or not
+    pub fn is_recursive(&self, node: &Node) -> bool {
+        let mut visited: HashSet<&Node> = HashSet::new();
+        let mut stack: Vec<&Node> = self.calls.get(node).into_iter().flatten().collect();
+        while let Some(n) = stack.pop() {
+            if n == node {
+                return true;
+            }
+            if visited.insert(n) {
+                stack.extend(self.calls.get(n).into_iter().flatten());
+            }
+        }
+        false
+    }
+}
+
+struct Builder<'a> {
+    funcs: HashMap<&'a Ident, &'a Func>,
+    classes: HashMap<&'a Ident, &'a Class>,
+    graph: CallGraph,
+    called: HashSet<Ident>,
+    visited: HashMap<Node, HashSet<Ident>>, // names called in each node
+}
+
+impl<'a> Builder<'a> {
+    fn add_refs(&mut self, refs: Refs) {
+        for name in refs.names {
+            self.use_class(&name);
+        }
+        self.called.extend(refs.calls);
+    }
+
+    fn use_class(&mut self, name: &Ident) {
+        let c = match self.classes.get(name) {
+            Some(c) => *c,
+            None => return,
+        };
+        if !self.graph.classes.insert(name.clone()) {
+            return;
+        }
+        let mut refs = Refs::new();
+        for v in &c.vars {
+            v.t.collect(&mut refs);
+        }
+        c.static_vars.collect(&mut refs);
+        if let Some(ref super_name) = c.superclass {
+            refs.names.insert(super_name.clone());
+        }
+        self.add_refs(refs);
+    }
+
+    // visits functions and methods called so far until nothing new is reached
+    fn run(&mut self) {
+        loop {
+            let mut reached: Vec<(Node, &Func)> = Vec::new();
+            for (ident, f) in &self.funcs {
+                let node = Node::Func((*ident).clone());
+                if self.called.contains(*ident) && !self.visited.contains_key(&node) {
+                    reached.push((node, *f));
+                }
+            }
+            for cname in &self.graph.classes {
+                let c: &'a Class = self.classes.get(cname).unwrap();
+                for m in c.methods.iter().chain(&c.static_methods) {
+                    let node = Node::Method(cname.clone(), m.ident.clone());
+                    if self.called.contains(&m.ident) && !self.visited.contains_key(&node) {
+                        reached.push((node, m));
+                    }
+                }
+            }
+            if reached.is_empty() {
+                break;
+            }
+            for (node, f) in reached {
+                self.visit(node, f);
+            }
+        }
+        self.add_edges();
+    }
+
+    fn visit(&mut self, node: Node, f: &Func) {
+        let mut refs = Refs::new();
+        f.collect(&mut refs);
+        self.visited.insert(node, refs.calls.clone());
+        self.add_refs(refs);
+    }
+
+    // resolves called names to the nodes they may refer to
+    fn add_edges(&mut self) {
+        let mut methods: HashMap<Ident, Vec<Node>> = HashMap::new();
+        for cname in &self.graph.classes {
+            let c = self.classes.get(cname).unwrap();
+            for m in c.methods.iter().chain(&c.static_methods) {
+                methods.entry(m.ident.clone())
+                    .or_default()
+                    .push(Node::Method(cname.clone(), m.ident.clone()));
+            }
+        }
+        for (node, names) in self.visited.drain() {
+            let mut calls: HashSet<Node> = HashSet::new();
+            for name in names {
+                if let Some(nodes) = methods.get(&name) {
+                    calls.extend(nodes.iter().cloned());
+                }
+                if self.funcs.contains_key(&name) {
+                    calls.insert(Node::Func(name));
+                }
+            }
+            self.graph.calls.insert(node, calls);
+        }
+    }
+}
+
+// names of called functions and methods, and identifiers that may name a class
+struct Refs {
+    calls: HashSet<Ident>,
+    names: HashSet<Ident>,
+}
+
+impl Refs {
+    fn new() -> Refs {
+        Refs {
+            calls: HashSet::new(),
+            names: HashSet::new(),
+        }
+    }
+}
+
+trait CollectRefs {
+    fn collect(&self, refs: &mut Refs);
+}
+
+impl<T> CollectRefs for Vec<T>
+    where T: CollectRefs
+{
+    fn collect(&self, refs: &mut Refs) {
+        for e in self {
+            e.collect(refs);
+        }
+    }
+}
+
+impl<T: Sized> CollectRefs for Box<T>
+    where T: CollectRefs
+{
+    fn collect(&self, refs: &mut Refs) {
+        self.deref().collect(refs);
+    }
+}
+
+impl CollectRefs for Global {
+    fn collect(&self, refs: &mut Refs) {
+        self.t.collect(refs);
+        self.decls.collect(refs);
+    }
+}
+
+impl CollectRefs for Func {
+    fn collect(&self, refs: &mut Refs) {
+        self.ret_type.collect(refs);
+        for arg in &self.args {
+            arg.t.collect(refs);
+        }
+        self.body.collect(refs);
+    }
+}
+
+impl CollectRefs for Stmt {
+    fn collect(&self, refs: &mut Refs) {
+        match self.kind {
+            StmtKind::SEmpty | StmtKind::SReturn => {}
+            StmtKind::SBlock(ref stmts) => stmts.collect(refs),
+            StmtKind::SDecl(ref t, ref decls) => {
+                t.collect(refs);
+                decls.collect(refs);
+            }
+            StmtKind::SAssign(ref field, ref e) => {
+                field.collect(refs);
+                e.collect(refs);
+            }
+            StmtKind::SInc(ref field) |
+            StmtKind::SDec(ref field) => field.collect(refs),
+            StmtKind::SReturnE(ref e) |
+            StmtKind::SExpr(ref e) => e.collect(refs),
+            StmtKind::SIf(ref e, ref s) |
+            StmtKind::SWhile(ref e, ref s) => {
+                e.collect(refs);
+                s.collect(refs);
+            }
+            StmtKind::SIfElse(ref e, ref s1, ref s2) => {
+                e.collect(refs);
+                s1.collect(refs);
+                s2.collect(refs);
+            }
+            StmtKind::SFor(ref t, _, ref e, ref s) => {
+                t.collect(refs);
+                e.collect(refs);
+                s.collect(refs);
+            }
+            StmtKind::SSwitch(ref e, ref cases, ref default) => {
+                e.collect(refs);
+                for case in cases {
+                    case.labels.collect(refs);
+                    case.body.collect(refs);
+                }
+                if let Some(ref stmts) = *default {
+                    stmts.collect(refs);
+                }
+            }
+        }
+    }
+}
+
+impl CollectRefs for VarDecl {
+    fn collect(&self, refs: &mut Refs) {
+        if let VarDecl::Init(_, _, ref e) = *self {
+            e.collect(refs);
+        }
+    }
+}
+
+impl CollectRefs for Expr {
+    fn collect(&self, refs: &mut Refs) {
+        match *self {
+            Expr::EVar(ref field) => field.collect(refs),
+            Expr::ELit(Lit::LNull(Some(ref cname))) => {
+                refs.names.insert(cname.clone());
+            }
+            Expr::ELit(..) => {}
+            Expr::ECall(ref field, ref args) => {
+                match *field {
+                    FieldGet::Direct(ref ident) => {
+                        refs.calls.insert(ident.clone());
+                    }
+                    FieldGet::Indirect(ref e, ref ident) => {
+                        e.collect(refs);
+                        refs.calls.insert(ident.clone());
+                    }
+                    FieldGet::IdxAccess(..) => field.collect(refs),
+                }
+                args.collect(refs);
+            }
+            Expr::ENeg(ref e) |
+            Expr::ENot(ref e) |
+            Expr::EBitNot(ref e) => e.collect(refs),
+            Expr::ECast(ref t, ref e) => {
+                t.collect(refs);
+                e.collect(refs);
+            }
+            Expr::EBinOp(ref lhs, _, ref rhs) => {
+                lhs.collect(refs);
+                rhs.collect(refs);
+            }
+            Expr::ENew(ref t) => t.collect(refs),
+            Expr::ENewArray(ref t, ref es) => {
+                t.collect(refs);
+                es.collect(refs);
+            }
+            Expr::EArrayLit(ref es) => es.collect(refs),
+            Expr::ECond(ref cond, ref e1, ref e2) => {
+                cond.collect(refs);
+                e1.collect(refs);
+                e2.collect(refs);
+            }
+        }
+    }
+}
+
+impl CollectRefs for FieldGet {
+    fn collect(&self, refs: &mut Refs) {
+        match *self {
+            // may be the class of a static member
+            FieldGet::Direct(ref ident) => {
+                refs.names.insert(ident.clone());
+            }
+            FieldGet::Indirect(ref e, _) => e.collect(refs),
+            FieldGet::IdxAccess(ref e, ref idx) => {
+                e.collect(refs);
+                idx.collect(refs);
+            }
+        }
+    }
+}
+
+impl CollectRefs for Type {
+    fn collect(&self, refs: &mut Refs) {
+        match *self {
+            Type::TObject(ref cname) => {
+                refs.names.insert(cname.clone());
+            }
+            Type::TArray(ref t) |
+            Type::TList(ref t) => t.collect(refs),
+            Type::TMap(ref key_t, ref value_t) => {
+                key_t.collect(refs);
+                value_t.collect(refs);
+            }
+            Type::TFunc(ref args, ref ret_type) => {
+                args.collect(refs);
+                ret_type.collect(refs);
+            }
+            _ => {}
+        }
+    }
+}
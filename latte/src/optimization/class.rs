@@ -1,14 +1,34 @@
 use ast::*;
 
+use optimization::context::Context;
 use optimization::optimize::*;
 
 impl Optimize for Class {
-    fn optimize(self) -> Class {
+    fn optimize(self, ctx: &mut Context) -> Class {
+        let Class { name, superclass, vars, methods, static_vars, static_methods } = self;
+        let (static_vars, static_methods, methods) = ctx.in_new_scope(|ctx| {
+            for var in ctx.get_class_statics(&name) {
+                ctx.shadow(&var);
+            }
+            let static_vars: Vec<Global> =
+                static_vars.into_iter().map(|g| g.optimize(ctx)).collect();
+            let static_methods: Vec<Func> =
+                static_methods.into_iter().map(|f| f.optimize(ctx)).collect();
+            let methods = ctx.in_new_scope(|ctx| {
+                for var in ctx.get_class_vars(&name) {
+                    ctx.shadow(&var);
+                }
+                methods.into_iter().map(|f| f.optimize(ctx)).collect()
+            });
+            (static_vars, static_methods, methods)
+        });
         Class {
-            name: self.name,
-            superclass: self.superclass,
-            vars: self.vars,
-            methods: self.methods.into_iter().map(Func::optimize).collect(),
+            name,
+            superclass,
+            vars,
+            methods,
+            static_vars,
+            static_methods,
         }
     }
 }
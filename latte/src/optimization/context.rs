@@ -0,0 +1,239 @@
+use std::collections::{HashMap, HashSet};
+
+use ast::{Class, Ident, Lit, Type};
+use options::Options;
+use static_analysis::warning::Warning;
+
+#[derive(Debug)]
+pub struct Context {
+    pub check_overflow: bool,
+    errors: Vec<String>,
+    warnings: Vec<Warning>,
+
+    vars: Vec<HashMap<Ident, VarInfo>>, // scopes, constant globals are in the first one
+    propagated: usize,                   // number of uses of local variables replaced so far
+    propagate: bool,
+    tmp_count: usize,
+    class_vars: HashMap<Ident, (Option<Ident>, Vec<Ident>)>, // superclass, own variables
+    class_statics: HashMap<Ident, Vec<Ident>>, // own static fields and methods
+}
+
+impl Context {
+    pub fn new(opts: &Options) -> Context {
+        Context {
+            check_overflow: opts.check_overflow,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+
+            vars: vec![HashMap::new()],
+            propagated: 0,
+            propagate: true,
+            tmp_count: 0,
+            class_vars: HashMap::new(),
+            class_statics: HashMap::new(),
+        }
+    }
+
+    pub fn in_new_scope<F, R>(&mut self, f: F) -> R
+        where F: FnOnce(&mut Context) -> R
+    {
+        self.vars.push(HashMap::new());
+        let res = f(self);
+        self.vars.pop();
+        res
+    }
+
+    pub fn add_error(&mut self, err: String) {
+        self.errors.push(err);
+    }
+
+    pub fn errors_count(&self) -> usize {
+        self.errors.len()
+    }
+
+    // drops errors reported since the given count
+    pub fn truncate_errors(&mut self, count: usize) {
+        self.errors.truncate(count);
+    }
+
+    pub fn get_errors(&self) -> &Vec<String> {
+        &self.errors
+    }
+
+    pub fn add_warning(&mut self, warning: Warning) {
+        self.warnings.push(warning);
+    }
+
+    pub fn warnings_count(&self) -> usize {
+        self.warnings.len()
+    }
+
+    // adds the place to warnings reported since the given count
+    pub fn wrap_warnings(&mut self, since: usize, place: &String) {
+        let warnings: Vec<Warning> = self.warnings.drain(since..).collect();
+        self.warnings.extend(warnings.into_iter().map(|w| w.wrapped(place)));
+    }
+
+    // sets the line of warnings reported since the given count, unless they have one
+    pub fn set_warnings_line(&mut self, since: usize, line: i32) {
+        let warnings: Vec<Warning> = self.warnings.drain(since..).collect();
+        self.warnings.extend(warnings.into_iter().map(|w| w.at_line(line)));
+    }
+
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        self.warnings.drain(..).collect()
+    }
+
+    // constants
+
+    pub fn add_const(&mut self, ident: &Ident, value: Lit) {
+        self.declare(ident, VarInfo::new(None, Some(value)));
+    }
+
+    pub fn get_const(&mut self, ident: &Ident) -> Option<Lit> {
+        let info = self.get_var(ident)?.clone();
+        if info.local_type.is_some() && info.value.is_some() {
+            if !self.propagate {
+                return None;
+            }
+            self.propagated += 1;
+        }
+        info.value
+    }
+
+    // only constants are replaced in the function
+    pub fn without_propagation<F, R>(&mut self, f: F) -> R
+        where F: FnOnce(&mut Context) -> R
+    {
+        self.propagate = false;
+        let res = f(self);
+        self.propagate = true;
+        res
+    }
+
+    // declaration of a variable with the same name hides the constant
+    pub fn shadow(&mut self, ident: &Ident) {
+        self.declare(ident, VarInfo::new(None, None));
+    }
+
+    // only literals of the declared type are propagated
+    pub fn declare_local(&mut self, ident: &Ident, t: &Type, value: Option<Lit>) {
+        let value = value.filter(|lit| is_propagated(t, lit));
+        self.declare(ident, VarInfo::new(Some(t.clone()), value));
+    }
+
+    pub fn assign(&mut self, ident: &Ident, value: Option<Lit>) {
+        let info = self.vars.iter_mut().rev().filter_map(|vars| vars.get_mut(ident)).next();
+        if let Some(info) = info {
+            if let Some(ref t) = info.local_type {
+                info.value = value.filter(|lit| is_propagated(t, lit));
+            }
+        }
+    }
+
+    pub fn get_local_type(&self, ident: &Ident) -> Option<Type> {
+        self.get_var(ident).and_then(|info| info.local_type.clone())
+    }
+
+    pub fn propagated_count(&self) -> usize {
+        self.propagated
+    }
+
+    // names of variables introduced by the optimizer, not valid in the source
+    pub fn next_tmp(&mut self) -> Ident {
+        self.tmp_count += 1;
+        Ident(format!(".tmp{}", self.tmp_count))
+    }
+
+    fn declare(&mut self, ident: &Ident, info: VarInfo) {
+        self.vars.last_mut().unwrap().insert(ident.clone(), info);
+    }
+
+    fn get_var(&self, ident: &Ident) -> Option<&VarInfo> {
+        self.vars.iter().rev().filter_map(|vars| vars.get(ident)).next()
+    }
+
+    // Control flow: the state of variables is saved before branches and merged
+    // after them. Only values which are the same in all merged states are kept.
+
+    pub fn save_vars(&self) -> Vars {
+        Vars(self.vars.clone())
+    }
+
+    pub fn restore_vars(&mut self, vars: Vars) {
+        self.vars = vars.0;
+    }
+
+    pub fn join_vars(&mut self, other: &Vars) {
+        for (scope, other_scope) in self.vars.iter_mut().zip(&other.0) {
+            for (ident, info) in scope.iter_mut() {
+                if other_scope.get(ident).map(|other| &other.value) != Some(&info.value) {
+                    info.value = None;
+                }
+            }
+        }
+    }
+
+    // forgets values of the variables, e.g. ones assigned in a loop
+    pub fn kill(&mut self, idents: &HashSet<Ident>) {
+        for ident in idents {
+            self.assign(ident, None);
+        }
+    }
+
+    // classes
+
+    pub fn add_class(&mut self, c: &Class) {
+        let vars = c.vars.iter().map(|v| v.ident.clone()).collect();
+        self.class_vars.insert(c.name.clone(), (c.superclass.clone(), vars));
+        let mut statics: Vec<Ident> = c.static_methods.iter().map(|f| f.ident.clone()).collect();
+        for g in &c.static_vars {
+            statics.extend(g.decls.iter().map(|d| d.get_ident().clone()));
+        }
+        self.class_statics.insert(c.name.clone(), statics);
+    }
+
+    pub fn get_class_vars(&self, cname: &Ident) -> Vec<Ident> {
+        let (ref superclass, ref vars) = *self.class_vars.get(cname).unwrap();
+        let mut res = vars.clone();
+        if let Some(ref superclass) = *superclass {
+            res.extend(self.get_class_vars(superclass));
+        }
+        res
+    }
+
+    pub fn get_class_statics(&self, cname: &Ident) -> Vec<Ident> {
+        let mut res = self.class_statics.get(cname).unwrap().clone();
+        if let Some(ref superclass) = self.class_vars.get(cname).unwrap().0 {
+            res.extend(self.get_class_statics(superclass));
+        }
+        res
+    }
+}
+
+#[derive(Debug, Clone)]
+struct VarInfo {
+    local_type: Option<Type>, // none for globals and fields
+    value: Option<Lit>,
+}
+
+impl VarInfo {
+    fn new(local_type: Option<Type>, value: Option<Lit>) -> VarInfo {
+        VarInfo {
+            local_type,
+            value,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Vars(Vec<HashMap<Ident, VarInfo>>);
+
+fn is_propagated(t: &Type, lit: &Lit) -> bool {
+    matches!((t, lit), (&Type::TInt, &Lit::LInt(_)) |
+        (&Type::TLong, &Lit::LLong(_)) |
+        (&Type::TDouble, &Lit::LDouble(_)) |
+        (&Type::TChar, &Lit::LChar(_)) |
+        (&Type::TBool, &Lit::LTrue) |
+        (&Type::TBool, &Lit::LFalse))
+}
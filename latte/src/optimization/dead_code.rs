@@ -0,0 +1,37 @@
+use ast::*;
+
+use optimization::call_graph::*;
+
+// Removes functions, classes and methods that are unreachable from main.
+// Methods are kept or removed by name in the whole hierarchy, so vtables
+// of the remaining classes stay consistent.
+pub fn run(p: Program) -> Program {
+    let graph = CallGraph::new(&p);
+    let defs = p.0
+        .into_iter()
+        .filter_map(|def| match def {
+            Def::DFunc(f) => {
+                match graph.is_reachable(&Node::Func(f.ident.clone())) {
+                    true => Some(Def::DFunc(f)),
+                    false => None,
+                }
+            }
+            Def::DClass(c) => {
+                match graph.classes.contains(&c.name) {
+                    true => Some(Def::DClass(remove_methods(c, &graph))),
+                    false => None,
+                }
+            }
+            Def::DGlobal(..) | Def::DEnum(..) | Def::DExtern(..) => Some(def),
+        })
+        .collect();
+    Program(defs)
+}
+
+fn remove_methods(mut c: Class, graph: &CallGraph) -> Class {
+    let name = c.name.clone();
+    let is_reachable = |f: &Func| graph.is_reachable(&Node::Method(name.clone(), f.ident.clone()));
+    c.methods.retain(&is_reachable);
+    c.static_methods.retain(&is_reachable);
+    c
+}
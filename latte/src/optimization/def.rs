@@ -1,12 +1,17 @@
 use ast::*;
 
+use optimization::context::Context;
 use optimization::optimize::*;
 
 impl Optimize for Def {
-    fn optimize(self) -> Def {
+    fn optimize(self, ctx: &mut Context) -> Def {
         match self {
-            Def::DFunc(f) => Def::DFunc(f.optimize()),
-            Def::DClass(c) => Def::DClass(c.optimize()),
+            Def::DFunc(f) => Def::DFunc(f.optimize(ctx)),
+            Def::DClass(c) => Def::DClass(c.optimize(ctx)),
+            // already optimized, see optimization::run
+            Def::DGlobal(g) => Def::DGlobal(g),
+            Def::DEnum(e) => Def::DEnum(e),
+            Def::DExtern(f) => Def::DExtern(f),
         }
     }
 }
@@ -1,43 +1,118 @@
 use ast::*;
+use static_analysis::warning::Warning;
 
+use optimization::context::Context;
 use optimization::optimize::*;
 
 impl Optimize for Expr {
-    fn optimize(self) -> Expr {
+    fn optimize(self, ctx: &mut Context) -> Expr {
         match self {
             Expr::ENeg(e) => {
-                let e = e.optimize();
-                match is_int_lit(&*e) {
-                    true => Expr::ELit(Lit::LInt(-to_int(*e))),
-                    false => Expr::ENeg(e),
+                let propagated = ctx.propagated_count();
+                let e = e.optimize(ctx);
+                let is_const = ctx.propagated_count() == propagated;
+                match *e {
+                    Expr::ELit(Lit::LInt(x)) => {
+                        let (res, overflow) = x.overflowing_neg();
+                        checked_lit(Lit::LInt(res), overflow, is_const, Expr::ENeg(e), ctx)
+                    }
+                    Expr::ELit(Lit::LLong(x)) => {
+                        let (res, overflow) = x.overflowing_neg();
+                        checked_lit(Lit::LLong(res), overflow, is_const, Expr::ENeg(e), ctx)
+                    }
+                    Expr::ELit(Lit::LDouble(x)) => Expr::ELit(Lit::LDouble(-x)),
+                    _ => Expr::ENeg(e),
                 }
             }
             Expr::ENot(e) => {
-                let e = e.optimize();
+                let e = e.optimize(ctx);
                 match is_bool_lit(&*e) {
                     true => Expr::ELit(to_lit(!to_bool(*e))),
                     false => Expr::ENot(e),
                 }
             }
+            Expr::EBitNot(e) => {
+                let e = e.optimize(ctx);
+                match *e {
+                    Expr::ELit(Lit::LInt(x)) => Expr::ELit(Lit::LInt(!x)),
+                    _ => Expr::EBitNot(e),
+                }
+            }
             Expr::EBinOp(lhs, op, rhs) => {
-                let lhs = *lhs.optimize();
-                let rhs = *rhs.optimize();
+                let propagated = ctx.propagated_count();
+                let lhs = *lhs.optimize(ctx);
+                let rhs = *rhs.optimize(ctx);
+                let is_const = ctx.propagated_count() == propagated;
                 if is_int_lit(&lhs) && is_int_lit(&rhs) && is_safe_op(op, &rhs) {
                     let (l, r) = (to_int(lhs), to_int(rhs));
                     Expr::ELit(match op {
-                        Operator::OpAdd => Lit::LInt(l + r),
-                        Operator::OpSub => Lit::LInt(l - r),
-                        Operator::OpMul => Lit::LInt(l * r),
-                        Operator::OpDiv => Lit::LInt(l / r),
-                        Operator::OpMod => Lit::LInt(l % r),
+                        Operator::OpAdd | Operator::OpSub | Operator::OpMul | Operator::OpDiv |
+                        Operator::OpMod => {
+                            let (res, overflow) = overflowing_int_op(l, op, r);
+                            let e = Expr::EBinOp(Box::new(Expr::ELit(Lit::LInt(l))),
+                                                 op,
+                                                 Box::new(Expr::ELit(Lit::LInt(r))));
+                            return checked_lit(Lit::LInt(res), overflow, is_const, e, ctx);
+                        }
                         Operator::OpLess => to_lit(l < r),
                         Operator::OpLessE => to_lit(l <= r),
                         Operator::OpGreater => to_lit(l > r),
                         Operator::OpGreaterE => to_lit(l >= r),
                         Operator::OpEq => to_lit(l == r),
                         Operator::OpNEq => to_lit(l != r),
+                        Operator::OpBitAnd => Lit::LInt(l & r),
+                        Operator::OpBitOr => Lit::LInt(l | r),
+                        Operator::OpBitXor => Lit::LInt(l ^ r),
+                        // wrapping shifts use the lowest 5 bits of r, as generated code does
+                        Operator::OpShl => Lit::LInt(l.wrapping_shl(r as u32)),
+                        Operator::OpShr => Lit::LInt(l.wrapping_shr(r as u32)),
                         _ => unreachable!(),
                     })
+                } else if is_long_lit(&lhs) && is_long_lit(&rhs) && is_safe_op(op, &rhs) {
+                    let (l, r) = (to_long(lhs), to_long(rhs));
+                    Expr::ELit(match op {
+                        Operator::OpAdd | Operator::OpSub | Operator::OpMul | Operator::OpDiv |
+                        Operator::OpMod => {
+                            let (res, overflow) = overflowing_long_op(l, op, r);
+                            let e = Expr::EBinOp(Box::new(Expr::ELit(Lit::LLong(l))),
+                                                 op,
+                                                 Box::new(Expr::ELit(Lit::LLong(r))));
+                            return checked_lit(Lit::LLong(res), overflow, is_const, e, ctx);
+                        }
+                        Operator::OpLess => to_lit(l < r),
+                        Operator::OpLessE => to_lit(l <= r),
+                        Operator::OpGreater => to_lit(l > r),
+                        Operator::OpGreaterE => to_lit(l >= r),
+                        Operator::OpEq => to_lit(l == r),
+                        Operator::OpNEq => to_lit(l != r),
+                        _ => unreachable!(),
+                    })
+                } else if is_double_lit(&lhs) && is_double_lit(&rhs) {
+                    let (l, r) = (to_double(lhs), to_double(rhs));
+                    Expr::ELit(match op {
+                        Operator::OpAdd => Lit::LDouble(l + r),
+                        Operator::OpSub => Lit::LDouble(l - r),
+                        Operator::OpMul => Lit::LDouble(l * r),
+                        Operator::OpDiv => Lit::LDouble(l / r),
+                        Operator::OpLess => to_lit(l < r),
+                        Operator::OpLessE => to_lit(l <= r),
+                        Operator::OpGreater => to_lit(l > r),
+                        Operator::OpGreaterE => to_lit(l >= r),
+                        Operator::OpEq => to_lit(l == r),
+                        Operator::OpNEq => to_lit(l != r),
+                        _ => unreachable!(),
+                    })
+                } else if is_char_lit(&lhs) && is_char_lit(&rhs) {
+                    let (l, r) = (to_char(lhs), to_char(rhs));
+                    Expr::ELit(to_lit(match op {
+                        Operator::OpLess => l < r,
+                        Operator::OpLessE => l <= r,
+                        Operator::OpGreater => l > r,
+                        Operator::OpGreaterE => l >= r,
+                        Operator::OpEq => l == r,
+                        Operator::OpNEq => l != r,
+                        _ => unreachable!(),
+                    }))
                 } else if is_bool_lit(&lhs) && is_bool_lit(&rhs) {
                     let (l, r) = (to_bool(lhs), to_bool(rhs));
                     Expr::ELit(to_lit(match op {
@@ -78,28 +153,192 @@ impl Optimize for Expr {
                     }
                 } else if is_str_lit(&lhs) && is_str_lit(&rhs) && is_safe_str_op(op) {
                     let (l, r) = (to_str(lhs), to_str(rhs));
-                    match op {
-                        Operator::OpEq => Expr::ELit(to_lit(l == r)),
-                        Operator::OpNEq => Expr::ELit(to_lit(l != r)),
+                    Expr::ELit(to_lit(match op {
+                        Operator::OpLess => l < r,
+                        Operator::OpLessE => l <= r,
+                        Operator::OpGreater => l > r,
+                        Operator::OpGreaterE => l >= r,
+                        Operator::OpEq => l == r,
+                        Operator::OpNEq => l != r,
                         _ => unreachable!(),
-                    }
+                    }))
                 } else if is_null(&lhs) && is_null(&rhs) {
                     match op {
                         Operator::OpEq | Operator::OpNEq => Expr::ELit(to_lit(true)),
                         _ => unreachable!(),
                     }
                 } else {
-                    Expr::EBinOp(Box::new(lhs), op, Box::new(rhs))
+                    simplify(lhs, op, rhs, ctx)
+                }
+            }
+            Expr::EVar(FieldGet::Direct(ident)) => {
+                match ctx.get_const(&ident) {
+                    Some(lit) => Expr::ELit(lit),
+                    None => Expr::EVar(FieldGet::Direct(ident)),
                 }
             }
             Expr::ECall(ident, es) => {
-                Expr::ECall(ident, es.into_iter().map(Expr::optimize).collect())
+                Expr::ECall(ident, es.into_iter().map(|e| e.optimize(ctx)).collect())
+            }
+            Expr::ENewArray(t, sizes) => {
+                Expr::ENewArray(t, sizes.into_iter().map(|e| e.optimize(ctx)).collect())
+            }
+            Expr::EArrayLit(es) => {
+                Expr::EArrayLit(es.into_iter().map(|e| e.optimize(ctx)).collect())
+            }
+            Expr::ECast(t, e) => {
+                let e = e.optimize(ctx);
+                match *e {
+                    Expr::ELit(ref lit) => Expr::ELit(cast_lit(&t, lit)),
+                    _ => Expr::ECast(t, e),
+                }
+            }
+            Expr::ECond(cond, e1, e2) => {
+                let cond = Box::new(optimize_cond(*cond, ctx));
+                let e1 = e1.optimize(ctx);
+                let e2 = e2.optimize(ctx);
+                match is_bool_lit(&cond) {
+                    true => {
+                        match to_bool(*cond) {
+                            true => *e1,
+                            false => *e2,
+                        }
+                    }
+                    false => Expr::ECond(cond, e1, e2),
+                }
             }
             _ => self,
         }
     }
 }
 
+// Conditions which become constant only after optimization are likely mistakes,
+// unlike literal ones, e.g. while (true).
+// Branches are removed only for conditions which are constant without the
+// propagation of local variables, so it doesn't change which functions pass
+// the return check.
+pub fn optimize_cond(e: Expr, ctx: &mut Context) -> Expr {
+    let is_lit = is_bool_lit(&e);
+    let place = format!("{}\n", e);
+    let errors = ctx.errors_count();
+    let propagated = ctx.propagated_count();
+    let mut cond = e.clone().optimize(ctx);
+    if is_bool_lit(&cond) && ctx.propagated_count() != propagated {
+        ctx.truncate_errors(errors);
+        cond = ctx.without_propagation(|ctx| e.optimize(ctx));
+    }
+    match cond {
+        Expr::ELit(Lit::LTrue) if !is_lit => {
+            ctx.add_warning(Warning::const_condition(true).wrapped(&place))
+        }
+        Expr::ELit(Lit::LFalse) if !is_lit => {
+            ctx.add_warning(Warning::const_condition(false).wrapped(&place))
+        }
+        _ => {}
+    }
+    cond
+}
+
+// Arithmetic wraps around on overflow, unless overflow checking is enabled.
+// Then the original expression is kept, and the overflow is reported if the
+// expression is constant, not just computed from propagated variables.
+fn checked_lit(res: Lit, overflow: bool, is_const: bool, e: Expr, ctx: &mut Context) -> Expr {
+    if overflow && ctx.check_overflow {
+        if is_const {
+            ctx.add_error(format!("Integer overflow in constant expression: {}", e));
+        }
+        e
+    } else {
+        Expr::ELit(res)
+    }
+}
+
+// Algebraic identities and strength reduction, for operations with at most
+// one literal operand. Operands are dropped only if they can't have effects.
+fn simplify(lhs: Expr, op: Operator, rhs: Expr, ctx: &Context) -> Expr {
+    match (op, &lhs, &rhs) {
+        (Operator::OpAdd, _, _) if is_zero(&rhs) => lhs,
+        (Operator::OpAdd, _, _) if is_zero(&lhs) => rhs,
+        (Operator::OpSub, _, _) if is_zero(&rhs) => lhs,
+        (Operator::OpMul, _, _) | (Operator::OpDiv, _, _) if is_one(&rhs) => lhs,
+        (Operator::OpMul, _, _) if is_one(&lhs) => rhs,
+        (Operator::OpAnd, _, &Expr::ELit(Lit::LTrue)) |
+        (Operator::OpOr, _, &Expr::ELit(Lit::LFalse)) => lhs,
+        (Operator::OpSub,
+         &Expr::EVar(FieldGet::Direct(ref l)),
+         &Expr::EVar(FieldGet::Direct(ref r))) if l == r => {
+            match ctx.get_local_type(l) {
+                Some(Type::TInt) => Expr::ELit(Lit::LInt(0)),
+                Some(Type::TLong) => Expr::ELit(Lit::LLong(0)),
+                _ => Expr::EBinOp(Box::new(lhs), op, Box::new(rhs)),
+            }
+        }
+        (Operator::OpMul, &Expr::EVar(FieldGet::Direct(..)), &Expr::ELit(Lit::LInt(0))) |
+        (Operator::OpMul, &Expr::EVar(FieldGet::Direct(..)), &Expr::ELit(Lit::LLong(0))) => rhs,
+        (Operator::OpMul, &Expr::ELit(Lit::LInt(0)), &Expr::EVar(FieldGet::Direct(..))) |
+        (Operator::OpMul, &Expr::ELit(Lit::LLong(0)), &Expr::EVar(FieldGet::Direct(..))) => lhs,
+        // shifts don't report overflow
+        (Operator::OpMul, _, &Expr::ELit(Lit::LInt(x))) if !ctx.check_overflow &&
+                                                            log2(x).is_some() => {
+            shift_left(lhs, log2(x).unwrap())
+        }
+        (Operator::OpMul, &Expr::ELit(Lit::LInt(x)), _) if !ctx.check_overflow &&
+                                                            log2(x).is_some() => {
+            shift_left(rhs, log2(x).unwrap())
+        }
+        _ => Expr::EBinOp(Box::new(lhs), op, Box::new(rhs)),
+    }
+}
+
+fn shift_left(e: Expr, k: i32) -> Expr {
+    Expr::EBinOp(Box::new(e), Operator::OpShl, Box::new(Expr::ELit(Lit::LInt(k))))
+}
+
+// x + 0.0 is not x for x = -0.0
+fn is_zero(e: &Expr) -> bool {
+    matches!(*e, Expr::ELit(Lit::LInt(0)) |
+        Expr::ELit(Lit::LLong(0)))
+}
+
+fn is_one(e: &Expr) -> bool {
+    match *e {
+        Expr::ELit(Lit::LInt(1)) |
+        Expr::ELit(Lit::LLong(1)) => true,
+        Expr::ELit(Lit::LDouble(x)) => x == 1.0,
+        _ => false,
+    }
+}
+
+// exponent of a power of two greater than 1
+fn log2(x: i32) -> Option<i32> {
+    match x > 1 && x & (x - 1) == 0 {
+        true => Some(x.trailing_zeros() as i32),
+        false => None,
+    }
+}
+
+fn overflowing_int_op(l: i32, op: Operator, r: i32) -> (i32, bool) {
+    match op {
+        Operator::OpAdd => l.overflowing_add(r),
+        Operator::OpSub => l.overflowing_sub(r),
+        Operator::OpMul => l.overflowing_mul(r),
+        Operator::OpDiv => l.overflowing_div(r),
+        Operator::OpMod => l.overflowing_rem(r),
+        _ => unreachable!(),
+    }
+}
+
+fn overflowing_long_op(l: i64, op: Operator, r: i64) -> (i64, bool) {
+    match op {
+        Operator::OpAdd => l.overflowing_add(r),
+        Operator::OpSub => l.overflowing_sub(r),
+        Operator::OpMul => l.overflowing_mul(r),
+        Operator::OpDiv => l.overflowing_div(r),
+        Operator::OpMod => l.overflowing_rem(r),
+        _ => unreachable!(),
+    }
+}
+
 fn to_lit(b: bool) -> Lit {
     match b {
         true => Lit::LTrue,
@@ -137,6 +376,59 @@ fn to_int(e: Expr) -> i32 {
     }
 }
 
+fn is_long_lit(e: &Expr) -> bool {
+    matches!(*e, Expr::ELit(Lit::LLong(_)))
+}
+
+fn to_long(e: Expr) -> i64 {
+    match e {
+        Expr::ELit(Lit::LLong(x)) => x,
+        _ => unreachable!(),
+    }
+}
+
+fn is_double_lit(e: &Expr) -> bool {
+    matches!(*e, Expr::ELit(Lit::LDouble(_)))
+}
+
+fn to_double(e: Expr) -> f64 {
+    match e {
+        Expr::ELit(Lit::LDouble(x)) => x,
+        _ => unreachable!(),
+    }
+}
+
+fn is_char_lit(e: &Expr) -> bool {
+    matches!(*e, Expr::ELit(Lit::LChar(_)))
+}
+
+fn to_char(e: Expr) -> u8 {
+    match e {
+        Expr::ELit(Lit::LChar(c)) => c,
+        _ => unreachable!(),
+    }
+}
+
+// Same semantics as the conversions in generated code: doubles are truncated and
+// saturated (NaN is 0), chars are converted from doubles through int.
+fn cast_lit(t: &Type, lit: &Lit) -> Lit {
+    match (t, lit) {
+        (&Type::TInt, &Lit::LLong(x)) => Lit::LInt(x as i32),
+        (&Type::TInt, &Lit::LDouble(x)) => Lit::LInt(x as i32),
+        (&Type::TInt, &Lit::LChar(c)) => Lit::LInt(c as i32),
+        (&Type::TLong, &Lit::LInt(x)) => Lit::LLong(x as i64),
+        (&Type::TLong, &Lit::LDouble(x)) => Lit::LLong(x as i64),
+        (&Type::TLong, &Lit::LChar(c)) => Lit::LLong(c as i64),
+        (&Type::TDouble, &Lit::LInt(x)) => Lit::LDouble(x as f64),
+        (&Type::TDouble, &Lit::LLong(x)) => Lit::LDouble(x as f64),
+        (&Type::TDouble, &Lit::LChar(c)) => Lit::LDouble(c as f64),
+        (&Type::TChar, &Lit::LInt(x)) => Lit::LChar(x as u8),
+        (&Type::TChar, &Lit::LLong(x)) => Lit::LChar(x as u8),
+        (&Type::TChar, &Lit::LDouble(x)) => Lit::LChar(x as i32 as u8),
+        (_, _) => lit.clone(),
+    }
+}
+
 fn is_str_lit(e: &Expr) -> bool {
     match *e {
         Expr::ELit(Lit::LString(_)) => true,
@@ -153,13 +445,16 @@ fn to_str(e: Expr) -> String {
 
 fn is_safe_op(op: Operator, rhs: &Expr) -> bool {
     match *rhs {
-        Expr::ELit(Lit::LInt(0)) => op != Operator::OpDiv && op != Operator::OpMod,
+        Expr::ELit(Lit::LInt(0)) |
+        Expr::ELit(Lit::LLong(0)) => op != Operator::OpDiv && op != Operator::OpMod,
         _ => true,
     }
 }
 
+// comparisons of contents, byte by byte as in the runtime
 fn is_safe_str_op(op: Operator) -> bool {
-    op == Operator::OpEq || op == Operator::OpNEq
+    matches!(op, Operator::OpLess | Operator::OpLessE | Operator::OpGreater | Operator::OpGreaterE |
+        Operator::OpEq | Operator::OpNEq)
 }
 
 fn is_null(e: &Expr) -> bool {
@@ -1,14 +1,26 @@
 use ast::*;
 
+use optimization::context::Context;
 use optimization::optimize::*;
 
 impl Optimize for Func {
-    fn optimize(self) -> Func {
+    fn optimize(self, ctx: &mut Context) -> Func {
+        let Func { ident, args, ret_type, body, access, line } = self;
+        let first_warning = ctx.warnings_count();
+        let body = ctx.in_new_scope(|ctx| {
+            for arg in &args {
+                ctx.declare_local(&arg.ident, &arg.t, None);
+            }
+            body.optimize(ctx)
+        });
+        ctx.wrap_warnings(first_warning, &format!("function {}\n", ident));
         Func {
-            ident: self.ident,
-            args: self.args,
-            ret_type: self.ret_type,
-            body: self.body.optimize(),
+            ident,
+            args,
+            ret_type,
+            body,
+            access,
+            line,
         }
     }
 }
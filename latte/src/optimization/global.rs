@@ -0,0 +1,25 @@
+use ast::*;
+
+use optimization::context::Context;
+use optimization::optimize::*;
+
+impl Optimize for Global {
+    fn optimize(self, ctx: &mut Context) -> Global {
+        let decls: Vec<VarDecl> = self.decls.into_iter().map(|d| d.optimize(ctx)).collect();
+        // other globals can be changed in any function
+        for decl in &decls {
+            match *decl {
+                VarDecl::Init(_, ref ident, Expr::ELit(ref lit)) if self.is_const => {
+                    ctx.add_const(ident, lit.clone());
+                }
+                _ => ctx.shadow(decl.get_ident()),
+            }
+        }
+        Global {
+            t: self.t,
+            decls,
+            is_const: self.is_const,
+            access: self.access,
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use std::collections::HashSet;
+
+use ast::*;
+
+use optimization::call_graph::*;
+
+// max number of statements in an inlined function, nested ones included
+const MAX_INLINE_SIZE: usize = 8;
+
+// Small non-recursive functions and methods. Instance methods are inlined
+// only where their calls are devirtualized.
+pub fn candidates(p: &Program) -> HashSet<Node> {
+    let graph = CallGraph::new(p);
+    let mut res: HashSet<Node> = HashSet::new();
+    for def in &p.0 {
+        match *def {
+            Def::DFunc(ref f) if f.ident.0 != "main" => {
+                res.extend(candidate(Node::Func(f.ident.clone()), f, &graph));
+            }
+            Def::DClass(ref c) => {
+                for f in c.methods.iter().chain(&c.static_methods) {
+                    res.extend(candidate(Node::Method(c.name.clone(), f.ident.clone()), f, &graph));
+                }
+            }
+            _ => {}
+        }
+    }
+    res
+}
+
+fn candidate(node: Node, f: &Func, graph: &CallGraph) -> Option<Node> {
+    match size(&f.body) <= MAX_INLINE_SIZE && !graph.is_recursive(&node) {
+        true => Some(node),
+        false => None,
+    }
+}
+
+fn size(stmts: &[Stmt]) -> usize {
+    stmts.iter().map(stmt_size).sum()
+}
+
+fn stmt_size(stmt: &Stmt) -> usize {
+    1 +
+    match stmt.kind {
+        StmtKind::SBlock(ref stmts) => size(stmts),
+        StmtKind::SIf(_, ref s) |
+        StmtKind::SWhile(_, ref s) |
+        StmtKind::SFor(_, _, _, ref s) => stmt_size(s),
+        StmtKind::SIfElse(_, ref s1, ref s2) => stmt_size(s1) + stmt_size(s2),
+        StmtKind::SSwitch(_, ref cases, ref default) => {
+            cases.iter().map(|case| size(&case.body)).sum::<usize>() +
+            default.as_ref().map_or(0, |stmts| size(stmts))
+        }
+        _ => 0,
+    }
+}
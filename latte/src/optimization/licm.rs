@@ -0,0 +1,259 @@
+use std::collections::{HashMap, HashSet};
+
+use ast::*;
+
+use optimization::context::Context;
+
+// Loop-invariant code motion. Expressions in the condition and body of a loop
+// that only read local variables not changed in the loop are computed once,
+// before the loop. Such expressions have no effects and can't fail, so it's
+// safe to evaluate them even if the loop body doesn't run.
+pub fn hoist_invariants(cond: Option<Expr>,
+                        body: Stmt,
+                        loop_var: Option<&Ident>,
+                        ctx: &mut Context)
+                        -> (Vec<Stmt>, Option<Expr>, Stmt) {
+    let mut variant = assigned_vars(&body);
+    declared_vars(&body, &mut variant);
+    variant.extend(loop_var.cloned());
+    let mut hoister = Hoister {
+        variant,
+        hoisted: HashMap::new(),
+        decls: Vec::new(),
+    };
+    let cond = cond.map(|e| hoister.expr(e, ctx));
+    let body = hoister.stmt(body, ctx);
+    (hoister.decls, cond, body)
+}
+
+// variables assigned anywhere in the statement
+pub fn assigned_vars(s: &Stmt) -> HashSet<Ident> {
+    let mut res: HashSet<Ident> = HashSet::new();
+    collect_assigned(s, &mut res);
+    res
+}
+
+fn collect_assigned(s: &Stmt, res: &mut HashSet<Ident>) {
+    match s.kind {
+        StmtKind::SAssign(FieldGet::Direct(ref ident), _) |
+        StmtKind::SInc(FieldGet::Direct(ref ident)) |
+        StmtKind::SDec(FieldGet::Direct(ref ident)) => {
+            res.insert(ident.clone());
+        }
+        _ => for_each_nested(s, |s| collect_assigned(s, res)),
+    }
+}
+
+// names declared in the statement, they may hide variables of outer scopes
+fn declared_vars(s: &Stmt, res: &mut HashSet<Ident>) {
+    match s.kind {
+        StmtKind::SDecl(_, ref decls) => {
+            res.extend(decls.iter().map(|d| d.get_ident().clone()));
+        }
+        StmtKind::SFor(_, ref ident, _, ref s) => {
+            res.insert(ident.clone());
+            declared_vars(s, res);
+        }
+        _ => for_each_nested(s, |s| declared_vars(s, res)),
+    }
+}
+
+fn for_each_nested<F>(s: &Stmt, mut f: F)
+    where F: FnMut(&Stmt)
+{
+    match s.kind {
+        StmtKind::SBlock(ref stmts) => {
+            for s in stmts {
+                f(s);
+            }
+        }
+        StmtKind::SIf(_, ref s) |
+        StmtKind::SWhile(_, ref s) |
+        StmtKind::SFor(_, _, _, ref s) => f(s),
+        StmtKind::SIfElse(_, ref s1, ref s2) => {
+            f(s1);
+            f(s2);
+        }
+        StmtKind::SSwitch(_, ref cases, ref default) => {
+            for s in cases.iter().flat_map(|case| &case.body) {
+                f(s);
+            }
+            for s in default.iter().flatten() {
+                f(s);
+            }
+        }
+        _ => {}
+    }
+}
+
+struct Hoister {
+    variant: HashSet<Ident>,
+    hoisted: HashMap<String, Ident>, // debug representation of the expression
+    decls: Vec<Stmt>,
+}
+
+impl Hoister {
+    fn stmt(&mut self, s: Stmt, ctx: &mut Context) -> Stmt {
+        let kind = match s.kind {
+            StmtKind::SBlock(stmts) => {
+                StmtKind::SBlock(stmts.into_iter().map(|s| self.stmt(s, ctx)).collect())
+            }
+            StmtKind::SDecl(t, decls) => {
+                let decls = decls.into_iter()
+                    .map(|d| match d {
+                        VarDecl::Init(t, ident, e) => VarDecl::Init(t, ident, self.expr(e, ctx)),
+                        VarDecl::NoInit(..) => d,
+                    })
+                    .collect();
+                StmtKind::SDecl(t, decls)
+            }
+            StmtKind::SAssign(field, e) => StmtKind::SAssign(field, self.expr(e, ctx)),
+            StmtKind::SReturnE(e) => StmtKind::SReturnE(self.expr(e, ctx)),
+            StmtKind::SExpr(e) => StmtKind::SExpr(self.expr(e, ctx)),
+            StmtKind::SIf(e, s) => StmtKind::SIf(self.expr(e, ctx), Box::new(self.stmt(*s, ctx))),
+            StmtKind::SIfElse(e, s1, s2) => {
+                StmtKind::SIfElse(self.expr(e, ctx),
+                                  Box::new(self.stmt(*s1, ctx)),
+                                  Box::new(self.stmt(*s2, ctx)))
+            }
+            StmtKind::SWhile(e, s) => {
+                StmtKind::SWhile(self.expr(e, ctx), Box::new(self.stmt(*s, ctx)))
+            }
+            StmtKind::SFor(t, ident, e, s) => {
+                StmtKind::SFor(t, ident, self.expr(e, ctx), Box::new(self.stmt(*s, ctx)))
+            }
+            StmtKind::SSwitch(e, cases, default) => {
+                let e = self.expr(e, ctx);
+                let cases = cases.into_iter()
+                    .map(|Case { labels, body }| {
+                        Case {
+                            labels,
+                            body: body.into_iter().map(|s| self.stmt(s, ctx)).collect(),
+                        }
+                    })
+                    .collect();
+                let default =
+                    default.map(|stmts| stmts.into_iter().map(|s| self.stmt(s, ctx)).collect());
+                StmtKind::SSwitch(e, cases, default)
+            }
+            kind => kind,
+        };
+        Stmt::new(kind, s.line)
+    }
+
+    fn expr(&mut self, e: Expr, ctx: &mut Context) -> Expr {
+        if let Some(t) = self.invariant_type(&e, ctx) {
+            if is_computed(&e) {
+                return Expr::EVar(FieldGet::Direct(self.hoist(e, t, ctx)));
+            }
+            return e;
+        }
+        match e {
+            Expr::ENeg(e) => Expr::ENeg(Box::new(self.expr(*e, ctx))),
+            Expr::ENot(e) => Expr::ENot(Box::new(self.expr(*e, ctx))),
+            Expr::EBitNot(e) => Expr::EBitNot(Box::new(self.expr(*e, ctx))),
+            Expr::ECast(t, e) => Expr::ECast(t, Box::new(self.expr(*e, ctx))),
+            Expr::EBinOp(lhs, op, rhs) => {
+                let lhs = self.expr(*lhs, ctx);
+                Expr::EBinOp(Box::new(lhs), op, Box::new(self.expr(*rhs, ctx)))
+            }
+            Expr::ECall(field, args) => {
+                let field = match field {
+                    FieldGet::Indirect(obj, ident) => {
+                        FieldGet::Indirect(Box::new(self.expr(*obj, ctx)), ident)
+                    }
+                    _ => field,
+                };
+                Expr::ECall(field, args.into_iter().map(|e| self.expr(e, ctx)).collect())
+            }
+            Expr::EVar(FieldGet::IdxAccess(arr, idx)) => {
+                let arr = Box::new(self.expr(*arr, ctx));
+                Expr::EVar(FieldGet::IdxAccess(arr, Box::new(self.expr(*idx, ctx))))
+            }
+            Expr::ENewArray(t, sizes) => {
+                Expr::ENewArray(t, sizes.into_iter().map(|e| self.expr(e, ctx)).collect())
+            }
+            Expr::EArrayLit(es) => {
+                Expr::EArrayLit(es.into_iter().map(|e| self.expr(e, ctx)).collect())
+            }
+            Expr::ECond(cond, e1, e2) => {
+                let cond = Box::new(self.expr(*cond, ctx));
+                let e1 = Box::new(self.expr(*e1, ctx));
+                Expr::ECond(cond, e1, Box::new(self.expr(*e2, ctx)))
+            }
+            _ => e,
+        }
+    }
+
+    fn hoist(&mut self, e: Expr, t: Type, ctx: &mut Context) -> Ident {
+        let key = format!("{:?}", e);
+        if let Some(ident) = self.hoisted.get(&key) {
+            return ident.clone();
+        }
+        let ident = ctx.next_tmp();
+        let decl = StmtKind::SDecl(t.clone(), vec![VarDecl::Init(t, ident.clone(), e)]);
+        self.decls.push(Stmt::new(decl, 0));
+        self.hoisted.insert(key, ident.clone());
+        ident
+    }
+
+    // type of the expression, if it is invariant and can't fail
+    fn invariant_type(&self, e: &Expr, ctx: &Context) -> Option<Type> {
+        match *e {
+            Expr::ELit(ref lit) => lit_type(lit),
+            Expr::EVar(FieldGet::Direct(ref ident)) if !self.variant.contains(ident) => {
+                ctx.get_local_type(ident).filter(is_simple_type)
+            }
+            Expr::ENeg(ref e) if !ctx.check_overflow => self.invariant_type(e, ctx),
+            Expr::ENot(ref e) |
+            Expr::EBitNot(ref e) => self.invariant_type(e, ctx),
+            Expr::ECast(ref t, ref e) => {
+                self.invariant_type(e, ctx)?;
+                Some(t.clone()).filter(is_simple_type)
+            }
+            Expr::EBinOp(ref lhs, op, ref rhs) => {
+                let t = self.invariant_type(lhs, ctx)?;
+                self.invariant_type(rhs, ctx)?;
+                match op {
+                    Operator::OpAdd | Operator::OpSub | Operator::OpMul if ctx.check_overflow => {
+                        None
+                    }
+                    Operator::OpDiv | Operator::OpMod if !is_safe_divisor(rhs) => None,
+                    Operator::OpLess | Operator::OpLessE | Operator::OpGreater |
+                    Operator::OpGreaterE | Operator::OpEq | Operator::OpNEq => Some(Type::TBool),
+                    _ => Some(t),
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+fn is_computed(e: &Expr) -> bool {
+    !matches!(*e, Expr::ELit(..) | Expr::EVar(..))
+}
+
+fn is_simple_type(t: &Type) -> bool {
+    matches!(*t, Type::TInt | Type::TLong | Type::TDouble | Type::TChar | Type::TBool)
+}
+
+fn lit_type(lit: &Lit) -> Option<Type> {
+    match *lit {
+        Lit::LInt(_) => Some(Type::TInt),
+        Lit::LLong(_) => Some(Type::TLong),
+        Lit::LDouble(_) => Some(Type::TDouble),
+        Lit::LChar(_) => Some(Type::TChar),
+        Lit::LTrue | Lit::LFalse => Some(Type::TBool),
+        _ => None,
+    }
+}
+
+// neither zero nor -1, which overflows when dividing the minimal value
+fn is_safe_divisor(e: &Expr) -> bool {
+    match *e {
+        Expr::ELit(Lit::LInt(x)) => x != 0 && x != -1,
+        Expr::ELit(Lit::LLong(x)) => x != 0 && x != -1,
+        Expr::ELit(Lit::LDouble(_)) => true,
+        _ => false,
+    }
+}
@@ -1,14 +1,57 @@
+use std::collections::HashSet;
+
 use ast::{Def, Program};
+use options::Options;
+use static_analysis::warning::Warning;
 
+mod call_graph;
 mod def;
 mod class;
+mod context;
+mod dead_code;
 mod expr;
 mod func;
+mod global;
+mod inline;
+mod licm;
 mod optimize;
 mod stmt;
 
+pub use self::call_graph::Node;
+
+use self::context::*;
 use self::optimize::*;
 
-pub fn run(p: Program) -> Program {
-    Program(p.0.into_iter().map(Def::optimize).collect())
+// Returns the optimized program and warnings about constant conditions and unreachable code.
+pub fn run(p: Program, opts: &Options) -> Result<(Program, Vec<Warning>), String> {
+    let mut ctx = Context::new(opts);
+    for def in &p.0 {
+        if let Def::DClass(ref c) = *def {
+            ctx.add_class(c);
+        }
+    }
+    // globals go first, so that constants are known in every function
+    let defs: Vec<Def> = p.0
+        .into_iter()
+        .map(|d| match d {
+            Def::DGlobal(g) => Def::DGlobal(g.optimize(&mut ctx)),
+            _ => d,
+        })
+        .collect();
+    let p = Program(defs.into_iter().map(|d| d.optimize(&mut ctx)).collect());
+    match ctx.get_errors().is_empty() {
+        true => Ok((p, ctx.take_warnings())),
+        false => Err(ctx.get_errors().join("\n")),
+    }
+}
+
+// Removes functions, classes and methods that are never called. Has to run
+// after all checks, so that errors in unused code are still reported.
+pub fn remove_dead_code(p: Program) -> Program {
+    dead_code::run(p)
+}
+
+// Functions and methods whose direct calls should be replaced with their bodies.
+pub fn inline_candidates(p: &Program) -> HashSet<Node> {
+    inline::candidates(p)
 }
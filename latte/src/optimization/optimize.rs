@@ -1,12 +1,14 @@
+use optimization::context::Context;
+
 pub trait Optimize {
-    fn optimize(self) -> Self;
+    fn optimize(self, ctx: &mut Context) -> Self;
 }
 
 impl<T: Sized> Optimize for Box<T>
     where T: Optimize
 {
-    fn optimize(self) -> Box<T> {
-        Box::new((*self).optimize())
+    fn optimize(self, ctx: &mut Context) -> Box<T> {
+        Box::new((*self).optimize(ctx))
     }
 }
 
@@ -1,19 +1,27 @@
 use ast::*;
 use static_analysis::has_return::*;
+use static_analysis::warning::Warning;
 
+use optimization::context::*;
+use optimization::expr::optimize_cond;
+use optimization::licm::*;
 use optimization::optimize::*;
 
 impl Optimize for Vec<Stmt> {
-    fn optimize(self) -> Vec<Stmt> {
+    fn optimize(self, ctx: &mut Context) -> Vec<Stmt> {
         let mut res: Vec<Stmt> = Vec::new();
-        for stmt in self {
-            let stmt = stmt.optimize();
+        let mut stmts = self.into_iter();
+        while let Some(stmt) = stmts.next() {
+            let stmt = stmt.optimize(ctx);
             let has_return = stmt.has_return();
-            match stmt {
-                Stmt::SEmpty => {}
+            match stmt.kind {
+                StmtKind::SEmpty => {}
                 _ => res.push(stmt),
             }
             if has_return {
+                if let Some(next) = stmts.next() {
+                    ctx.add_warning(Warning::unreachable().at_line(next.line).wrapped(&next));
+                }
                 break;
             }
         }
@@ -22,62 +30,201 @@ impl Optimize for Vec<Stmt> {
 }
 
 impl Optimize for Stmt {
-    fn optimize(self) -> Stmt {
-        match self {
-            Stmt::SBlock(stmts) => {
-                let stmts = stmts.optimize();
+    fn optimize(self, ctx: &mut Context) -> Stmt {
+        let line = self.line;
+        let first_warning = ctx.warnings_count();
+        let kind = match self.kind {
+            StmtKind::SBlock(stmts) => {
+                let stmts = ctx.in_new_scope(|ctx| stmts.optimize(ctx));
                 if stmts.len() == 1 {
-                    match stmts[0].clone() {
-                        Stmt::SBlock(inner_stmts) => Stmt::SBlock(inner_stmts),
-                        _ => Stmt::SBlock(stmts),
+                    match stmts[0].kind.clone() {
+                        StmtKind::SBlock(inner_stmts) => StmtKind::SBlock(inner_stmts),
+                        _ => StmtKind::SBlock(stmts),
                     }
                 } else {
                     match stmts.is_empty() {
-                        true => Stmt::SEmpty,
-                        false => Stmt::SBlock(stmts),
+                        true => StmtKind::SEmpty,
+                        false => StmtKind::SBlock(stmts),
                     }
                 }
             }
-            Stmt::SDecl(t, decls) => {
-                Stmt::SDecl(t, decls.into_iter().map(VarDecl::optimize).collect())
+            StmtKind::SDecl(t, decls) => {
+                StmtKind::SDecl(t, decls.into_iter().map(|d| d.optimize(ctx)).collect())
             }
-            Stmt::SAssign(field, e) => Stmt::SAssign(field, e.optimize()),
-            Stmt::SReturnE(e) => Stmt::SReturnE(e.optimize()),
-            Stmt::SExpr(e) => Stmt::SExpr(e.optimize()),
-            Stmt::SIf(e, iftrue) => {
-                let cond = e.optimize();
+            StmtKind::SAssign(field, e) => {
+                let e = e.optimize(ctx);
+                if let FieldGet::Direct(ref ident) = field {
+                    ctx.assign(ident, as_lit(&e));
+                }
+                StmtKind::SAssign(field, e)
+            }
+            StmtKind::SInc(field) => {
+                if let FieldGet::Direct(ref ident) = field {
+                    ctx.assign(ident, None);
+                }
+                StmtKind::SInc(field)
+            }
+            StmtKind::SDec(field) => {
+                if let FieldGet::Direct(ref ident) = field {
+                    ctx.assign(ident, None);
+                }
+                StmtKind::SDec(field)
+            }
+            StmtKind::SReturnE(e) => StmtKind::SReturnE(e.optimize(ctx)),
+            StmtKind::SExpr(e) => StmtKind::SExpr(e.optimize(ctx)),
+            StmtKind::SIf(e, iftrue) => {
+                let cond = optimize_cond(e, ctx);
                 match cond {
-                    Expr::ELit(Lit::LTrue) => Stmt::SBlock(vec![*iftrue]).optimize(),
-                    Expr::ELit(Lit::LFalse) => Stmt::SEmpty,
-                    _ => Stmt::SIf(cond, iftrue.optimize()),
+                    Expr::ELit(Lit::LTrue) => optimize_taken(*iftrue, line, ctx),
+                    Expr::ELit(Lit::LFalse) => StmtKind::SEmpty,
+                    _ => {
+                        let before = ctx.save_vars();
+                        let (iftrue, after) = optimize_branch(iftrue, &before, ctx);
+                        join_branches(vec![Some(before), after], ctx);
+                        StmtKind::SIf(cond, iftrue)
+                    }
                 }
             }
-            Stmt::SIfElse(e, iftrue, iffalse) => {
-                let cond = e.optimize();
+            StmtKind::SIfElse(e, iftrue, iffalse) => {
+                let cond = optimize_cond(e, ctx);
                 match cond {
-                    Expr::ELit(Lit::LTrue) => Stmt::SBlock(vec![*iftrue]).optimize(),
-                    Expr::ELit(Lit::LFalse) => Stmt::SBlock(vec![*iffalse]).optimize(),
-                    _ => Stmt::SIfElse(cond, iftrue.optimize(), iffalse.optimize()),
+                    Expr::ELit(Lit::LTrue) => optimize_taken(*iftrue, line, ctx),
+                    Expr::ELit(Lit::LFalse) => optimize_taken(*iffalse, line, ctx),
+                    _ => {
+                        let before = ctx.save_vars();
+                        let (iftrue, after_true) = optimize_branch(iftrue, &before, ctx);
+                        let (iffalse, after_false) = optimize_branch(iffalse, &before, ctx);
+                        join_branches(vec![after_true, after_false], ctx);
+                        StmtKind::SIfElse(cond, iftrue, iffalse)
+                    }
                 }
             }
-            Stmt::SWhile(e, s) => {
-                let cond = e.optimize();
+            StmtKind::SWhile(e, s) => {
+                ctx.kill(&assigned_vars(&s));
+                let cond = optimize_cond(e, ctx);
                 match cond {
-                    Expr::ELit(Lit::LFalse) => Stmt::SEmpty,
-                    _ => Stmt::SWhile(cond, s.optimize()),
+                    Expr::ELit(Lit::LFalse) => StmtKind::SEmpty,
+                    _ => {
+                        let before = ctx.save_vars();
+                        let s = optimize_in_new_scope(s, ctx);
+                        ctx.restore_vars(before);
+                        let (decls, cond, s) = hoist_invariants(Some(cond), *s, None, ctx);
+                        let while_stmt = StmtKind::SWhile(cond.unwrap(), Box::new(s));
+                        with_decls(decls, Stmt::new(while_stmt, line))
+                    }
                 }
             }
-            Stmt::SFor(t, i, e, s) => Stmt::SFor(t, i, e.optimize(), s.optimize()),
-            _ => self,
+            StmtKind::SFor(t, i, e, s) => {
+                let e = e.optimize(ctx);
+                ctx.kill(&assigned_vars(&s));
+                let before = ctx.save_vars();
+                let s = ctx.in_new_scope(|ctx| {
+                    ctx.declare_local(&i, &t, None);
+                    s.optimize(ctx)
+                });
+                ctx.restore_vars(before);
+                let (decls, _, s) = hoist_invariants(None, *s, Some(&i), ctx);
+                with_decls(decls, Stmt::new(StmtKind::SFor(t, i, e, Box::new(s)), line))
+            }
+            StmtKind::SSwitch(e, cases, default) => {
+                let e = e.optimize(ctx);
+                let before = ctx.save_vars();
+                let mut states: Vec<Option<Vars>> = Vec::new();
+                let cases = cases.into_iter()
+                    .map(|Case { labels, body }| {
+                        ctx.restore_vars(before.clone());
+                        let labels = labels.into_iter().map(|l| l.optimize(ctx)).collect();
+                        let body = ctx.in_new_scope(|ctx| body.optimize(ctx));
+                        states.push(state_after(&body, ctx));
+                        Case {
+                            labels,
+                            body,
+                        }
+                    })
+                    .collect();
+                ctx.restore_vars(before.clone());
+                let default = default.map(|stmts| ctx.in_new_scope(|ctx| stmts.optimize(ctx)));
+                states.push(match default {
+                    Some(ref stmts) => state_after(stmts, ctx),
+                    None => Some(before),
+                });
+                join_branches(states, ctx);
+                StmtKind::SSwitch(e, cases, default)
+            }
+            kind => kind,
+        };
+        ctx.set_warnings_line(first_warning, line);
+        Stmt::new(kind, line)
+    }
+}
+
+// the branch that is always taken, in a block, which keeps its declarations local
+fn optimize_taken(s: Stmt, line: i32, ctx: &mut Context) -> StmtKind {
+    Stmt::new(StmtKind::SBlock(vec![s]), line).optimize(ctx).kind
+}
+
+// Optimizes the branch, starting in the given state of variables. Returns
+// the state after the branch, unless the branch always returns.
+fn optimize_branch(s: Box<Stmt>, before: &Vars, ctx: &mut Context) -> (Box<Stmt>, Option<Vars>) {
+    ctx.restore_vars(before.clone());
+    let s = optimize_in_new_scope(s, ctx);
+    let after = state_after(&*s, ctx);
+    (s, after)
+}
+
+fn state_after<T: HasReturn>(s: &T, ctx: &Context) -> Option<Vars> {
+    match s.has_return() {
+        true => None,
+        false => Some(ctx.save_vars()),
+    }
+}
+
+fn join_branches(states: Vec<Option<Vars>>, ctx: &mut Context) {
+    let mut states = states.into_iter().flatten();
+    if let Some(first) = states.next() {
+        ctx.restore_vars(first);
+        for state in states {
+            ctx.join_vars(&state);
         }
     }
 }
 
+// hoisted declarations are put in a block with the loop
+fn with_decls(mut decls: Vec<Stmt>, loop_stmt: Stmt) -> StmtKind {
+    match decls.is_empty() {
+        true => loop_stmt.kind,
+        false => {
+            decls.push(loop_stmt);
+            StmtKind::SBlock(decls)
+        }
+    }
+}
+
+fn as_lit(e: &Expr) -> Option<Lit> {
+    match *e {
+        Expr::ELit(ref lit) => Some(lit.clone()),
+        _ => None,
+    }
+}
+
+fn optimize_in_new_scope(s: Box<Stmt>, ctx: &mut Context) -> Box<Stmt> {
+    ctx.in_new_scope(|ctx| s.optimize(ctx))
+}
+
+// Declared variable hides constants with the same name in the rest of the scope.
+// Its value is propagated if it is initialized with a literal.
 impl Optimize for VarDecl {
-    fn optimize(self) -> VarDecl {
+    fn optimize(self, ctx: &mut Context) -> VarDecl {
         match self {
-            VarDecl::Init(t, i, e) => VarDecl::Init(t, i, e.optimize()),
-            _ => self,
+            VarDecl::Init(t, i, e) => {
+                let e = e.optimize(ctx);
+                ctx.declare_local(&i, &t, as_lit(&e));
+                VarDecl::Init(t, i, e)
+            }
+            VarDecl::NoInit(t, i) => {
+                ctx.declare_local(&i, &t, None);
+                VarDecl::NoInit(t, i)
+            }
         }
     }
 }
@@ -0,0 +1,133 @@
+use std::collections::{HashMap, HashSet};
+
+use static_analysis::warning::WarningKind;
+
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub check_overflow: bool,
+    pub report_devirt: bool,
+    pub warnings: WarningOptions,
+    pub link: Vec<String>,
+    pub target: Target,
+    pub debug_info: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Target {
+    #[default]
+    Llvm,
+    C,
+    Wasm,
+    X86,
+    Jvm,
+}
+
+// -W<name> enables a warning, -Wno-<name> disables it, -Wall enables all of them.
+// -Werror=<name> turns a warning into an error, -Werror does it for all enabled warnings.
+#[derive(Debug, Clone, Default)]
+pub struct WarningOptions {
+    enabled: HashMap<WarningKind, bool>, // overrides defaults
+    errors: HashSet<WarningKind>,
+    all_errors: bool,
+}
+
+impl Options {
+    pub fn native_objects(&self) -> Vec<&String> {
+        self.link.iter().filter(|f| f.ends_with(".o")).collect()
+    }
+
+    // returns options and the input file
+    pub fn from_args(args: &[String]) -> Result<(Options, String), String> {
+        let mut opts = Options::default();
+        let mut input: Option<String> = None;
+        for arg in args.iter().skip(1) {
+            match arg.as_ref() {
+                // int and long arithmetic wraps around on overflow, as does division
+                // (MIN / -1 is MIN, MIN % -1 is 0), unless it's checked: then it's
+                // a runtime error, or a compile error in constant expressions
+                "--check-overflow" => opts.check_overflow = true,
+                // lists method calls that were turned into direct calls
+                "--report-devirt" => opts.report_devirt = true,
+                "--target=llvm" => opts.target = Target::Llvm,
+                // compiled with cc
+                "--target=c" => opts.target = Target::C,
+                // extern functions are given by the host in JavaScript files
+                "--target=wasm" => opts.target = Target::Wasm,
+                // assembled with as and linked with cc
+                "--target=x86-64" => opts.target = Target::X86,
+                // extern functions are static methods of the Extern class on the class path
+                "--target=jvm" => opts.target = Target::Jvm,
+                // DWARF in the LLVM IR
+                "-g" => opts.debug_info = true,
+                _ if arg.starts_with("--") => return Err(format!("Unknown option: {}", arg)),
+                _ if arg.starts_with("-W") => opts.warnings.add_flag(&arg[2..])?,
+                _ if input.is_none() => input = Some(arg.clone()),
+                // .bc, .ll or .o files implementing extern functions, a native
+                // executable is built with .o files
+                _ if is_linkable(arg) => opts.link.push(arg.clone()),
+                _ => return Err(usage(&args[0])),
+            }
+        }
+        let is_native = opts.target == Target::C || opts.target == Target::X86;
+        if is_native && opts.link.len() != opts.native_objects().len() {
+            return Err("Only object files can be linked with native code".to_string());
+        }
+        if opts.target == Target::Wasm && !opts.link.is_empty() {
+            return Err("Files can't be linked with WebAssembly modules".to_string());
+        }
+        if opts.target == Target::Jvm && !opts.link.is_empty() {
+            return Err("Files can't be linked with JVM classes".to_string());
+        }
+        if opts.target != Target::Llvm && opts.debug_info {
+            return Err("Debug information is only emitted in LLVM IR".to_string());
+        }
+        match input {
+            Some(input) => Ok((opts, input)),
+            None => Err(usage(&args[0])),
+        }
+    }
+}
+
+impl WarningOptions {
+    pub fn is_enabled(&self, kind: WarningKind) -> bool {
+        *self.enabled.get(&kind).unwrap_or(&kind.is_default())
+    }
+
+    pub fn is_error(&self, kind: WarningKind) -> bool {
+        self.all_errors || self.errors.contains(&kind)
+    }
+
+    fn add_flag(&mut self, flag: &str) -> Result<(), String> {
+        if flag == "all" {
+            for kind in WarningKind::all() {
+                self.enabled.insert(kind, true);
+            }
+        } else if flag == "error" {
+            self.all_errors = true;
+        } else if let Some(name) = flag.strip_prefix("error=") {
+            let kind = warning_kind(name)?;
+            self.enabled.insert(kind, true);
+            self.errors.insert(kind);
+        } else if let Some(name) = flag.strip_prefix("no-") {
+            self.enabled.insert(warning_kind(name)?, false);
+        } else {
+            self.enabled.insert(warning_kind(flag)?, true);
+        }
+        Ok(())
+    }
+}
+
+fn warning_kind(name: &str) -> Result<WarningKind, String> {
+    WarningKind::from_name(name).ok_or(format!("Unknown warning: {}", name))
+}
+
+fn is_linkable(file: &str) -> bool {
+    file.ends_with(".bc") || file.ends_with(".ll") || file.ends_with(".o")
+}
+
+fn usage(program: &String) -> String {
+    format!("Usage: ./{0} [--check-overflow] [--report-devirt] [--target=llvm|c|wasm|x86-64|jvm] \
+             [-g] [-W<warning>] input_file [file.bc | file.ll | file.o]...\n       \
+             ./{0} --demangle [symbol]...",
+            program)
+}
@@ -34,10 +34,25 @@ impl ToAst<Type> for String {
         if self.starts_with("[") {
             let inner: Type = String::from(self.split_at(1).1).to_ast()?;
             Ok(Type::TArray(Box::new(inner)))
+        } else if self.starts_with("<") {
+            let inner: Type = String::from(self.split_at(1).1).to_ast()?;
+            Ok(Type::TList(Box::new(inner)))
+        } else if self.starts_with("{") {
+            let (key, value) = match self.find(':') {
+                Some(idx) => (&self[1..idx], &self[idx + 1..]),
+                None => return Err(format!("Invalid map type: {}", self)),
+            };
+            let key: Type = String::from(key).to_ast()?;
+            let value: Type = String::from(value).to_ast()?;
+            Ok(Type::TMap(Box::new(key), Box::new(value)))
         } else {
             let t = match self.as_ref() {
                 "int" => Type::TInt,
+                "long" => Type::TLong,
+                "double" => Type::TDouble,
+                "char" => Type::TChar,
                 "string" => Type::TString,
+                "StringBuilder" => Type::TStringBuilder,
                 "boolean" => Type::TBool,
                 "void" => Type::TVoid,
                 _ => Type::TObject(Ident(self.clone())),
@@ -2,9 +2,18 @@
 
 const int DEF_TYPE_FUNC = 1;
 const int DEF_TYPE_CLASS = 2;
+const int DEF_TYPE_GLOBAL = 3;
+const int DEF_TYPE_ENUM = 4;
+const int DEF_TYPE_EXTERN = 5;
 
 const int CLASS_MEMBER_TYPE_FUNC = 10;
 const int CLASS_MEMBER_TYPE_VAR = 11;
+const int CLASS_MEMBER_TYPE_STATIC_FUNC = 12;
+const int CLASS_MEMBER_TYPE_STATIC_VAR = 13;
+
+const int ACCESS_PUBLIC = 0;
+const int ACCESS_PROTECTED = 1;
+const int ACCESS_PRIVATE = 2;
 
 struct def_t *def_create(int32_t type, void *def);
 
@@ -16,6 +25,39 @@ struct def_t *def_class_create(struct class_t *c) {
   return def_create(DEF_TYPE_CLASS, c);
 }
 
+struct global_t *global_create(char *type, struct many_t *decls,
+                               int32_t is_const) {
+  struct global_t *g = malloc(sizeof(struct global_t));
+  CHECK_NULL(g);
+  g->type = type;
+  g->decls = decls;
+  g->is_const = is_const;
+  return g;
+}
+
+struct def_t *def_global_create(char *type, struct many_t *decls,
+                                int32_t is_const) {
+  return def_create(DEF_TYPE_GLOBAL, global_create(type, decls, is_const));
+}
+
+struct def_t *def_enum_create(char *name, struct many_t *variants) {
+  struct enum_t *e = malloc(sizeof(struct enum_t));
+  CHECK_NULL(e);
+  e->name = name;
+  e->variants = variants;
+  return def_create(DEF_TYPE_ENUM, e);
+}
+
+struct def_t *def_extern_create(char *ret_type, char *ident,
+                                struct many_t *arg_types) {
+  struct extern_t *e = malloc(sizeof(struct extern_t));
+  CHECK_NULL(e);
+  e->ret_type = ret_type;
+  e->ident = ident;
+  e->arg_types = arg_types;
+  return def_create(DEF_TYPE_EXTERN, e);
+}
+
 struct def_t *def_create(int32_t type, void *def) {
   struct def_t *d = malloc(sizeof(struct def_t));
   CHECK_NULL(d);
@@ -25,13 +67,14 @@ struct def_t *def_create(int32_t type, void *def) {
 }
 
 struct func_t *func_create(char *ret_type, char *ident, struct many_t *args,
-                           struct many_t *stmts) {
+                           struct many_t *stmts, int32_t line) {
   struct func_t *f = malloc(sizeof(struct func_t));
   CHECK_NULL(f);
   f->ret_type = ret_type;
   f->ident = ident;
   f->args = args;
   f->stmts = stmts;
+  f->line = line;
   return f;
 }
 
@@ -52,26 +95,46 @@ struct class_t *class_create(char *name, char *super, struct many_t *members) {
   return c;
 }
 
-struct class_member_t *class_member_create(int32_t type, void *m);
+struct class_member_t *class_member_create(int32_t type, int32_t access,
+                                           void *m);
+
+struct class_member_t *class_member_func_create(int32_t access,
+                                                struct func_t *f) {
+  return class_member_create(CLASS_MEMBER_TYPE_FUNC, access, f);
+}
+
+struct class_member_t *class_member_var_create(int32_t access,
+                                               struct var_t *v) {
+  return class_member_create(CLASS_MEMBER_TYPE_VAR, access, v);
+}
 
-struct class_member_t *class_member_func_create(struct func_t *f) {
-  return class_member_create(CLASS_MEMBER_TYPE_FUNC, f);
+struct class_member_t *class_member_static_func_create(int32_t access,
+                                                       struct func_t *f) {
+  return class_member_create(CLASS_MEMBER_TYPE_STATIC_FUNC, access, f);
 }
 
-struct class_member_t *class_member_var_create(struct var_t *v) {
-  return class_member_create(CLASS_MEMBER_TYPE_VAR, v);
+struct class_member_t *class_member_static_var_create(int32_t access,
+                                                      char *type,
+                                                      struct many_t *decls) {
+  return class_member_create(CLASS_MEMBER_TYPE_STATIC_VAR, access,
+                             global_create(type, decls, 0));
 }
 
-struct class_member_t *class_member_create(int32_t type, void *m) {
+struct class_member_t *class_member_create(int32_t type, int32_t access,
+                                           void *m) {
   struct class_member_t *cm = malloc(sizeof(struct class_member_t));
   CHECK_NULL(cm);
   cm->type = type;
+  cm->access = access;
   cm->m = m;
   return cm;
 }
 
 void func_free(void *ptr);
 void class_free(void *ptr);
+void global_free(void *ptr);
+void enum_free(void *ptr);
+void extern_free(void *ptr);
 void class_member_free(void *ptr);
 void var_free(void *ptr);
 
@@ -90,6 +153,12 @@ void def_free(void *ptr) {
     func_free(d);
   } else if (type == DEF_TYPE_CLASS) {
     class_free(d);
+  } else if (type == DEF_TYPE_GLOBAL) {
+    global_free(d);
+  } else if (type == DEF_TYPE_ENUM) {
+    enum_free(d);
+  } else if (type == DEF_TYPE_EXTERN) {
+    extern_free(d);
   } else {
     assert(0);
     exit(-1);
@@ -123,6 +192,40 @@ void class_free(void *ptr) {
   free(c);
 }
 
+void global_free(void *ptr) {
+  if (ptr == NULL) {
+    return;
+  }
+
+  struct global_t *g = ptr;
+  free(g->type);
+  many_free(g->decls, var_decl_free);
+  free(g);
+}
+
+void enum_free(void *ptr) {
+  if (ptr == NULL) {
+    return;
+  }
+
+  struct enum_t *e = ptr;
+  free(e->name);
+  many_free(e->variants, free);
+  free(e);
+}
+
+void extern_free(void *ptr) {
+  if (ptr == NULL) {
+    return;
+  }
+
+  struct extern_t *e = ptr;
+  free(e->ret_type);
+  free(e->ident);
+  many_free(e->arg_types, free);
+  free(e);
+}
+
 void class_member_free(void *ptr) {
   if (ptr == NULL) {
     return;
@@ -134,10 +237,13 @@ void class_member_free(void *ptr) {
     goto end;
   }
 
-  if (type == CLASS_MEMBER_TYPE_FUNC) {
+  if (type == CLASS_MEMBER_TYPE_FUNC ||
+      type == CLASS_MEMBER_TYPE_STATIC_FUNC) {
     func_free(m);
   } else if (type == CLASS_MEMBER_TYPE_VAR) {
     var_free(m);
+  } else if (type == CLASS_MEMBER_TYPE_STATIC_VAR) {
+    global_free(m);
   } else {
     assert(0);
     exit(-1);
@@ -5,14 +5,40 @@
 
 extern const int DEF_TYPE_FUNC;
 extern const int DEF_TYPE_CLASS;
+extern const int DEF_TYPE_GLOBAL;
+extern const int DEF_TYPE_ENUM;
+extern const int DEF_TYPE_EXTERN;
 
 struct def_t {
   int32_t type;
-  void *d;  // func_t | class_t
+  void *d;  // func_t | class_t | global_t | enum_t | extern_t
+};
+
+struct global_t {
+  char *type;
+  struct many_t *decls;  // var_decl_t
+  int32_t is_const;
+};
+
+struct enum_t {
+  char *name;
+  struct many_t *variants;  // char*
+};
+
+struct extern_t {
+  char *ret_type;
+  char *ident;
+  struct many_t *arg_types;  // char*
 };
 
 extern const int CLASS_MEMBER_TYPE_FUNC;
 extern const int CLASS_MEMBER_TYPE_VAR;
+extern const int CLASS_MEMBER_TYPE_STATIC_FUNC;
+extern const int CLASS_MEMBER_TYPE_STATIC_VAR;
+
+extern const int ACCESS_PUBLIC;
+extern const int ACCESS_PROTECTED;
+extern const int ACCESS_PRIVATE;
 
 struct class_t {
   char *name;
@@ -22,7 +48,8 @@ struct class_t {
 
 struct class_member_t {
   int32_t type;
-  void *m;  // func_t | var_t
+  int32_t access;
+  void *m;  // func_t | var_t | global_t
 };
 
 struct func_t {
@@ -30,6 +57,7 @@ struct func_t {
   char *ident;
   struct many_t *args;   // var_t
   struct many_t *stmts;  // stmt_t
+  int32_t line;
 };
 
 struct var_t {
@@ -41,14 +69,26 @@ extern void def_free(void *def);
 
 extern struct def_t *def_func_create(struct func_t *f);
 extern struct def_t *def_class_create(struct class_t *c);
+extern struct def_t *def_global_create(char *type, struct many_t *decls,
+                                       int32_t is_const);
+extern struct def_t *def_enum_create(char *name, struct many_t *variants);
+extern struct def_t *def_extern_create(char *ret_type, char *ident,
+                                       struct many_t *arg_types);
 
 extern struct func_t *func_create(char *ret_type, char *ident,
-                                  struct many_t *args, struct many_t *stmts);
+                                  struct many_t *args, struct many_t *stmts,
+                                  int32_t line);
 
 extern struct class_t *class_create(char *name, char *super,
                                     struct many_t *members);
-extern struct class_member_t *class_member_func_create(struct func_t *f);
-extern struct class_member_t *class_member_var_create(struct var_t *v);
+extern struct class_member_t *class_member_func_create(int32_t access,
+                                                       struct func_t *f);
+extern struct class_member_t *class_member_var_create(int32_t access,
+                                                      struct var_t *v);
+extern struct class_member_t *class_member_static_func_create(
+    int32_t access, struct func_t *f);
+extern struct class_member_t *class_member_static_var_create(
+    int32_t access, char *type, struct many_t *decls);
 
 extern struct var_t *var_create(char *type, char *ident);
 
@@ -1,7 +1,6 @@
 use libc::*;
-use either::Either;
 
-use ast::{Class, Def, Func, Ident, Var};
+use ast::{Access, BuiltinFunc, Class, Def, Enum, Func, Global, Ident, Type, Var};
 
 use parser::many::*;
 use parser::stmt::*;
@@ -11,9 +10,18 @@ use parser::to_ast::*;
 extern "C" {
     static DEF_TYPE_FUNC: c_int;
     static DEF_TYPE_CLASS: c_int;
+    static DEF_TYPE_GLOBAL: c_int;
+    static DEF_TYPE_ENUM: c_int;
+    static DEF_TYPE_EXTERN: c_int;
 
     static CLASS_MEMBER_TYPE_FUNC: c_int;
     static CLASS_MEMBER_TYPE_VAR: c_int;
+    static CLASS_MEMBER_TYPE_STATIC_FUNC: c_int;
+    static CLASS_MEMBER_TYPE_STATIC_VAR: c_int;
+
+    static ACCESS_PUBLIC: c_int;
+    static ACCESS_PROTECTED: c_int;
+    static ACCESS_PRIVATE: c_int;
 }
 
 #[repr(C)]
@@ -31,11 +39,73 @@ impl ToAst<Def> for def_t {
             if self.t == DEF_TYPE_CLASS {
                 return Ok(Def::DClass((self.ptr as *mut class_t).to_ast()?));
             }
+            if self.t == DEF_TYPE_GLOBAL {
+                return Ok(Def::DGlobal((self.ptr as *mut global_t).to_ast()?));
+            }
+            if self.t == DEF_TYPE_ENUM {
+                return Ok(Def::DEnum((self.ptr as *mut enum_t).to_ast()?));
+            }
+            if self.t == DEF_TYPE_EXTERN {
+                return Ok(Def::DExtern((self.ptr as *mut extern_t).to_ast()?));
+            }
             Err(format!("Unknown definition type: {}", self.t))
         }
     }
 }
 
+#[repr(C)]
+struct global_t {
+    t: *mut c_char,
+    decls: *mut many_t,
+    is_const: i32,
+}
+
+impl ToAst<Global> for global_t {
+    fn to_ast(&self) -> TAResult<Global> {
+        let t: Type = self.t.to_ast()?;
+        Ok(Global {
+            decls: many_t::to_vec(self.decls, |var_decl: &var_decl_t| var_decl.to_ast(&t))?,
+            t,
+            is_const: self.is_const != 0,
+            access: Access::Public,
+        })
+    }
+}
+
+#[repr(C)]
+struct enum_t {
+    name: *mut c_char,
+    variants: *mut many_t,
+}
+
+impl ToAst<Enum> for enum_t {
+    fn to_ast(&self) -> TAResult<Enum> {
+        Ok(Enum {
+            name: self.name.to_ast()?,
+            variants: many_t::to_vec(self.variants,
+                                     |v: &c_char| (v as *const c_char as *mut c_char).to_ast())?,
+        })
+    }
+}
+
+#[repr(C)]
+struct extern_t {
+    ret_type: *mut c_char,
+    ident: *mut c_char,
+    arg_types: *mut many_t,
+}
+
+impl ToAst<BuiltinFunc> for extern_t {
+    fn to_ast(&self) -> TAResult<BuiltinFunc> {
+        Ok(BuiltinFunc {
+            ident: self.ident.to_ast()?,
+            args: many_t::to_vec(self.arg_types,
+                                 |t: &c_char| (t as *const c_char as *mut c_char).to_ast())?,
+            ret_type: self.ret_type.to_ast()?,
+        })
+    }
+}
+
 #[repr(C)]
 struct class_t {
     name: *mut c_char,
@@ -50,35 +120,59 @@ impl ToAst<Class> for class_t {
             false => Some(self.superclass.to_ast()?),
         };
         let members = many_t::to_vec(self.members, class_member_t::to_ast)?;
-        let vars = members.iter()
-            .filter(|v| v.is_right())
-            .map(|v| v.clone().right().unwrap())
-            .collect();
-        let funcs =
-            members.iter().filter(|f| f.is_left()).map(|f| f.clone().left().unwrap()).collect();
-        Ok(Class {
+        let mut class = Class {
             name: self.name.to_ast()?,
             superclass: superclass,
-            vars: vars,
-            methods: funcs,
-        })
+            vars: Vec::new(),
+            methods: Vec::new(),
+            static_vars: Vec::new(),
+            static_methods: Vec::new(),
+        };
+        for member in members {
+            match member {
+                ClassMember::Method(f) => class.methods.push(f),
+                ClassMember::Var(v) => class.vars.push(v),
+                ClassMember::StaticMethod(f) => class.static_methods.push(f),
+                ClassMember::StaticVar(g) => class.static_vars.push(g),
+            }
+        }
+        Ok(class)
     }
 }
 
+enum ClassMember {
+    Method(Func),
+    Var(Var),
+    StaticMethod(Func),
+    StaticVar(Global),
+}
+
 #[repr(C)]
 struct class_member_t {
     t: i32,
+    access: i32,
     ptr: *mut c_void,
 }
 
-impl ToAst<Either<Func, Var>> for class_member_t {
-    fn to_ast(&self) -> TAResult<Either<Func, Var>> {
+impl ToAst<ClassMember> for class_member_t {
+    fn to_ast(&self) -> TAResult<ClassMember> {
         unsafe {
+            let access = self.access.to_ast()?;
             if self.t == CLASS_MEMBER_TYPE_FUNC {
-                return Ok(Either::Left((self.ptr as *mut func_t).to_ast()?));
+                let f = (self.ptr as *mut func_t).to_ast()?;
+                return Ok(ClassMember::Method(Func { access, ..f }));
             }
             if self.t == CLASS_MEMBER_TYPE_VAR {
-                return Ok(Either::Right((self.ptr as *mut var_t).to_ast()?));
+                let v = (self.ptr as *mut var_t).to_ast()?;
+                return Ok(ClassMember::Var(Var { access, ..v }));
+            }
+            if self.t == CLASS_MEMBER_TYPE_STATIC_FUNC {
+                let f = (self.ptr as *mut func_t).to_ast()?;
+                return Ok(ClassMember::StaticMethod(Func { access, ..f }));
+            }
+            if self.t == CLASS_MEMBER_TYPE_STATIC_VAR {
+                let g = (self.ptr as *mut global_t).to_ast()?;
+                return Ok(ClassMember::StaticVar(Global { access, ..g }));
             }
             Err(format!("Unknwon definition type: {}", self.t))
 
@@ -86,12 +180,30 @@ impl ToAst<Either<Func, Var>> for class_member_t {
     }
 }
 
+impl ToAst<Access> for i32 {
+    fn to_ast(&self) -> TAResult<Access> {
+        unsafe {
+            if *self == ACCESS_PUBLIC {
+                return Ok(Access::Public);
+            }
+            if *self == ACCESS_PROTECTED {
+                return Ok(Access::Protected);
+            }
+            if *self == ACCESS_PRIVATE {
+                return Ok(Access::Private);
+            }
+            Err(format!("Unknown access modifier: {}", self))
+        }
+    }
+}
+
 #[repr(C)]
 struct func_t {
     ret_type: *mut c_char,
     ident: *mut c_char,
     args: *mut many_t,
     body: *mut many_t,
+    line: i32,
 }
 
 impl ToAst<Func> for func_t {
@@ -101,6 +213,8 @@ impl ToAst<Func> for func_t {
             ident: self.ident.to_ast()?,
             args: many_t::to_vec(self.args, var_t::to_ast)?,
             body: many_t::to_vec(self.body, stmt_t::to_ast)?,
+            access: Access::Public,
+            line: self.line,
         })
     }
 }
@@ -116,6 +230,7 @@ impl ToAst<Var> for var_t {
         Ok(Var {
             t: self.t.to_ast()?,
             ident: self.ident.to_ast()?,
+            access: Access::Public,
         })
     }
 }
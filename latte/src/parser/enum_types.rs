@@ -0,0 +1,208 @@
+use std::collections::HashSet;
+
+use ast::*;
+
+// The grammar cannot tell class names from enum names, so every user defined
+// type is parsed as an object type and enum ones are fixed up here.
+pub fn resolve(p: &mut Program) {
+    let enums: HashSet<Ident> = p.0
+        .iter()
+        .filter_map(|def| match *def {
+            Def::DEnum(ref e) => Some(e.name.clone()),
+            _ => None,
+        })
+        .collect();
+    if !enums.is_empty() {
+        p.0.resolve(&enums);
+    }
+}
+
+trait ResolveEnums {
+    fn resolve(&mut self, enums: &HashSet<Ident>);
+}
+
+impl<T: ResolveEnums> ResolveEnums for Vec<T> {
+    fn resolve(&mut self, enums: &HashSet<Ident>) {
+        for x in self {
+            x.resolve(enums);
+        }
+    }
+}
+
+impl<T: ResolveEnums> ResolveEnums for Box<T> {
+    fn resolve(&mut self, enums: &HashSet<Ident>) {
+        (**self).resolve(enums);
+    }
+}
+
+impl ResolveEnums for Def {
+    fn resolve(&mut self, enums: &HashSet<Ident>) {
+        match *self {
+            Def::DClass(ref mut c) => {
+                c.vars.resolve(enums);
+                c.methods.resolve(enums);
+                c.static_vars.resolve(enums);
+                c.static_methods.resolve(enums);
+            }
+            Def::DFunc(ref mut f) => f.resolve(enums),
+            Def::DGlobal(ref mut g) => g.resolve(enums),
+            Def::DExtern(ref mut f) => {
+                f.args.resolve(enums);
+                f.ret_type.resolve(enums);
+            }
+            Def::DEnum(..) => {}
+        }
+    }
+}
+
+impl ResolveEnums for Global {
+    fn resolve(&mut self, enums: &HashSet<Ident>) {
+        self.t.resolve(enums);
+        self.decls.resolve(enums);
+    }
+}
+
+impl ResolveEnums for Func {
+    fn resolve(&mut self, enums: &HashSet<Ident>) {
+        self.ret_type.resolve(enums);
+        self.args.resolve(enums);
+        self.body.resolve(enums);
+    }
+}
+
+impl ResolveEnums for Var {
+    fn resolve(&mut self, enums: &HashSet<Ident>) {
+        self.t.resolve(enums);
+    }
+}
+
+impl ResolveEnums for VarDecl {
+    fn resolve(&mut self, enums: &HashSet<Ident>) {
+        match *self {
+            VarDecl::Init(ref mut t, _, ref mut e) => {
+                t.resolve(enums);
+                e.resolve(enums);
+            }
+            VarDecl::NoInit(ref mut t, _) => t.resolve(enums),
+        }
+    }
+}
+
+impl ResolveEnums for Stmt {
+    fn resolve(&mut self, enums: &HashSet<Ident>) {
+        match self.kind {
+            StmtKind::SEmpty | StmtKind::SReturn => {}
+            StmtKind::SBlock(ref mut stmts) => stmts.resolve(enums),
+            StmtKind::SDecl(ref mut t, ref mut decls) => {
+                t.resolve(enums);
+                decls.resolve(enums);
+            }
+            StmtKind::SAssign(ref mut field, ref mut e) => {
+                field.resolve(enums);
+                e.resolve(enums);
+            }
+            StmtKind::SInc(ref mut field) |
+            StmtKind::SDec(ref mut field) => field.resolve(enums),
+            StmtKind::SReturnE(ref mut e) |
+            StmtKind::SExpr(ref mut e) => e.resolve(enums),
+            StmtKind::SIf(ref mut cond, ref mut s) |
+            StmtKind::SWhile(ref mut cond, ref mut s) => {
+                cond.resolve(enums);
+                s.resolve(enums);
+            }
+            StmtKind::SIfElse(ref mut cond, ref mut s1, ref mut s2) => {
+                cond.resolve(enums);
+                s1.resolve(enums);
+                s2.resolve(enums);
+            }
+            StmtKind::SFor(ref mut t, _, ref mut e, ref mut s) => {
+                t.resolve(enums);
+                e.resolve(enums);
+                s.resolve(enums);
+            }
+            StmtKind::SSwitch(ref mut e, ref mut cases, ref mut default) => {
+                e.resolve(enums);
+                for case in cases {
+                    case.labels.resolve(enums);
+                    case.body.resolve(enums);
+                }
+                if let Some(ref mut stmts) = *default {
+                    stmts.resolve(enums);
+                }
+            }
+        }
+    }
+}
+
+impl ResolveEnums for Expr {
+    fn resolve(&mut self, enums: &HashSet<Ident>) {
+        match *self {
+            Expr::ELit(..) => {}
+            Expr::EVar(ref mut field) => field.resolve(enums),
+            Expr::ECall(ref mut field, ref mut args) => {
+                field.resolve(enums);
+                args.resolve(enums);
+            }
+            Expr::ENeg(ref mut e) |
+            Expr::ENot(ref mut e) |
+            Expr::EBitNot(ref mut e) => e.resolve(enums),
+            Expr::EBinOp(ref mut lhs, _, ref mut rhs) => {
+                lhs.resolve(enums);
+                rhs.resolve(enums);
+            }
+            Expr::ENew(ref mut t) => t.resolve(enums),
+            Expr::ENewArray(ref mut t, ref mut sizes) => {
+                t.resolve(enums);
+                sizes.resolve(enums);
+            }
+            Expr::EArrayLit(ref mut elems) => elems.resolve(enums),
+            Expr::ECast(ref mut t, ref mut e) => {
+                t.resolve(enums);
+                e.resolve(enums);
+            }
+            Expr::ECond(ref mut cond, ref mut e1, ref mut e2) => {
+                cond.resolve(enums);
+                e1.resolve(enums);
+                e2.resolve(enums);
+            }
+        }
+    }
+}
+
+impl ResolveEnums for FieldGet {
+    fn resolve(&mut self, enums: &HashSet<Ident>) {
+        match *self {
+            FieldGet::Direct(..) => {}
+            FieldGet::Indirect(ref mut e, _) => e.resolve(enums),
+            FieldGet::IdxAccess(ref mut e, ref mut idx) => {
+                e.resolve(enums);
+                idx.resolve(enums);
+            }
+        }
+    }
+}
+
+impl ResolveEnums for Type {
+    fn resolve(&mut self, enums: &HashSet<Ident>) {
+        let enum_name = match *self {
+            Type::TObject(ref name) if enums.contains(name) => name.clone(),
+            Type::TFunc(ref mut args, ref mut ret) => {
+                args.resolve(enums);
+                ret.resolve(enums);
+                return;
+            }
+            Type::TArray(ref mut t) |
+            Type::TList(ref mut t) => {
+                t.resolve(enums);
+                return;
+            }
+            Type::TMap(ref mut key, ref mut value) => {
+                key.resolve(enums);
+                value.resolve(enums);
+                return;
+            }
+            _ => return,
+        };
+        *self = Type::TEnum(enum_name);
+    }
+}
@@ -10,6 +10,12 @@ const int EXPR_TYPE_LIT_NULL = 6;
 const int EXPR_TYPE_LIT_STR = 7;
 const int EXPR_TYPE_NEW = 8;
 const int EXPR_TYPE_UNARY = 9;
+const int EXPR_TYPE_ARR_LIT = 10;
+const int EXPR_TYPE_LIT_DOUBLE = 11;
+const int EXPR_TYPE_LIT_LONG = 12;
+const int EXPR_TYPE_LIT_CHAR = 13;
+const int EXPR_TYPE_CAST = 14;
+const int EXPR_TYPE_COND = 15;
 
 struct expr_t *expr_create(int32_t type, void *e);
 
@@ -51,17 +57,39 @@ struct expr_t *expr_lit_create(int32_t type, char *lit) {
   return expr_create(EXPR_TYPE_LIT, e);
 }
 
-struct expr_t *expr_new_create(char *type, struct expr_t *size) {
+struct expr_t *expr_new_create(char *type, struct many_t *sizes) {
   struct expr_new_t *e = malloc(sizeof(struct expr_new_t));
   CHECK_NULL(e);
   e->type = type;
-  e->size = size;
+  e->sizes = sizes;
   return expr_create(EXPR_TYPE_NEW, e);
 }
 
+struct expr_t *expr_arr_lit_create(struct many_t *elems) {
+  return expr_create(EXPR_TYPE_ARR_LIT, elems);
+}
+
+struct expr_t *expr_cast_create(char *type, struct expr_t *expr) {
+  struct expr_cast_t *e = malloc(sizeof(struct expr_cast_t));
+  CHECK_NULL(e);
+  e->type = type;
+  e->e = expr;
+  return expr_create(EXPR_TYPE_CAST, e);
+}
+
+struct expr_t *expr_cond_create(struct expr_t *cond, struct expr_t *e1,
+                                struct expr_t *e2) {
+  struct expr_cond_t *e = malloc(sizeof(struct expr_cond_t));
+  CHECK_NULL(e);
+  e->cond = cond;
+  e->e1 = e1;
+  e->e2 = e2;
+  return expr_create(EXPR_TYPE_COND, e);
+}
+
 struct expr_t *expr_create(int32_t type, void *e) {
   struct expr_t *expr = malloc(sizeof(struct expr_t));
-  CHECK_NULL(e);
+  CHECK_NULL(expr);
   expr->type = type;
   expr->e = e;
   return expr;
@@ -97,7 +125,18 @@ void expr_free(void *ptr) {
   } else if (type == EXPR_TYPE_NEW) {
     struct expr_new_t *expr = (struct expr_new_t *)e;
     free(expr->type);
-    expr_free(expr->size);
+    many_free(expr->sizes, expr_free);
+  } else if (type == EXPR_TYPE_ARR_LIT) {
+    many_free(e, expr_free);
+  } else if (type == EXPR_TYPE_CAST) {
+    struct expr_cast_t *expr = (struct expr_cast_t *)e;
+    free(expr->type);
+    expr_free(expr->e);
+  } else if (type == EXPR_TYPE_COND) {
+    struct expr_cond_t *expr = (struct expr_cond_t *)e;
+    expr_free(expr->cond);
+    expr_free(expr->e1);
+    expr_free(expr->e2);
   } else {
     assert(0);
     exit(-1);
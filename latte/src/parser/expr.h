@@ -10,12 +10,18 @@ struct expr_t {
   void *e;
 };
 
+extern const int EXPR_TYPE_ARR_LIT;
 extern const int EXPR_TYPE_BINOP;
 extern const int EXPR_TYPE_CALL;
+extern const int EXPR_TYPE_CAST;
+extern const int EXPR_TYPE_COND;
 extern const int EXPR_TYPE_FIELD;
 extern const int EXPR_TYPE_LIT;
 extern const int EXPR_TYPE_LIT_BOOL;
+extern const int EXPR_TYPE_LIT_CHAR;
+extern const int EXPR_TYPE_LIT_DOUBLE;
 extern const int EXPR_TYPE_LIT_INT;
+extern const int EXPR_TYPE_LIT_LONG;
 extern const int EXPR_TYPE_LIT_NULL;
 extern const int EXPR_TYPE_LIT_STR;
 extern const int EXPR_TYPE_NEW;
@@ -42,9 +48,20 @@ struct expr_lit_t {
   char *lit; // class identifier for null literal
 };
 
-struct expr_new_t {
+struct expr_cast_t {
   char *type;
-  struct expr_t *size; // nullable for objects, !null for arrays
+  struct expr_t *e;
+};
+
+struct expr_cond_t {
+  struct expr_t *cond;
+  struct expr_t *e1;
+  struct expr_t *e2;
+};
+
+struct expr_new_t {
+  char *type;             // element type for arrays
+  struct many_t *sizes;  // expr_t, nullable for objects, !null for arrays
 };
 
 extern struct expr_t *expr_binop_create(struct expr_t *lhs, struct expr_t *rhs,
@@ -54,7 +71,11 @@ extern struct expr_t *expr_call_create(struct field_get_t *field,
                                        struct many_t *args);
 extern struct expr_t *expr_field_get_create(struct field_get_t *field);
 extern struct expr_t *expr_lit_create(int32_t type, char *lit);
-extern struct expr_t *expr_new_create(char *type, struct expr_t *size);
+extern struct expr_t *expr_new_create(char *type, struct many_t *sizes);
+extern struct expr_t *expr_arr_lit_create(struct many_t *elems);
+extern struct expr_t *expr_cast_create(char *type, struct expr_t *e);
+extern struct expr_t *expr_cond_create(struct expr_t *cond, struct expr_t *e1,
+                                       struct expr_t *e2);
 
 extern void expr_free(void *e);
 
@@ -8,12 +8,18 @@ use parser::to_ast::*;
 
 #[link(name = "parse", kind = "static")]
 extern "C" {
+    static EXPR_TYPE_ARR_LIT: c_int;
     static EXPR_TYPE_BINOP: c_int;
     static EXPR_TYPE_CALL: c_int;
+    static EXPR_TYPE_CAST: c_int;
+    static EXPR_TYPE_COND: c_int;
     static EXPR_TYPE_FIELD: c_int;
     static EXPR_TYPE_LIT: c_int;
     static EXPR_TYPE_LIT_BOOL: c_int;
+    static EXPR_TYPE_LIT_CHAR: c_int;
+    static EXPR_TYPE_LIT_DOUBLE: c_int;
     static EXPR_TYPE_LIT_INT: c_int;
+    static EXPR_TYPE_LIT_LONG: c_int;
     static EXPR_TYPE_LIT_NULL: c_int;
     static EXPR_TYPE_LIT_STR: c_int;
     static EXPR_TYPE_NEW: c_int;
@@ -48,6 +54,16 @@ impl ToAst<Expr> for expr_t {
             if self.t == EXPR_TYPE_NEW {
                 return (self.ptr as *mut expr_new_t).to_ast();
             }
+            if self.t == EXPR_TYPE_ARR_LIT {
+                let elems = many_t::to_vec(self.ptr as *mut many_t, expr_t::to_ast)?;
+                return Ok(Expr::EArrayLit(elems));
+            }
+            if self.t == EXPR_TYPE_CAST {
+                return (self.ptr as *mut expr_cast_t).to_ast();
+            }
+            if self.t == EXPR_TYPE_COND {
+                return (self.ptr as *mut expr_cond_t).to_ast();
+            }
         }
         return Err(format!("Unknown expression type: {}", self.t));
     }
@@ -70,6 +86,11 @@ impl ToAst<Operator> for *mut c_char {
             "!=" => Ok(Operator::OpNEq),
             "&&" => Ok(Operator::OpAnd),
             "||" => Ok(Operator::OpOr),
+            "&" => Ok(Operator::OpBitAnd),
+            "|" => Ok(Operator::OpBitOr),
+            "^" => Ok(Operator::OpBitXor),
+            "<<" => Ok(Operator::OpShl),
+            ">>" => Ok(Operator::OpShr),
             _ => Err(format!("Unknown operator: {}", op_str)),
         }
     }
@@ -104,6 +125,7 @@ impl ToAst<Expr> for expr_unary_t {
         match op {
             '-' => Ok(Expr::ENeg(Box::new(e))),
             '!' => Ok(Expr::ENot(Box::new(e))),
+            '~' => Ok(Expr::EBitNot(Box::new(e))),
             _ => Err(format!("Unknown unary operator: {}", op)),
         }
     }
@@ -146,6 +168,20 @@ impl ToAst<Expr> for expr_lit_t {
                         Err(x) => Err(format!("Cannot convert {} to i32: {}", lit_str, x)),
                     }?;
                     Lit::LInt(x)
+                } else if self.t == EXPR_TYPE_LIT_LONG {
+                    let x = match lit_str.parse::<i64>() {
+                        Ok(x) => Ok(x),
+                        Err(x) => Err(format!("Cannot convert {} to i64: {}", lit_str, x)),
+                    }?;
+                    Lit::LLong(x)
+                } else if self.t == EXPR_TYPE_LIT_DOUBLE {
+                    let x = match lit_str.parse::<f64>() {
+                        Ok(x) => Ok(x),
+                        Err(x) => Err(format!("Cannot convert {} to f64: {}", lit_str, x)),
+                    }?;
+                    Lit::LDouble(x)
+                } else if self.t == EXPR_TYPE_LIT_CHAR {
+                    Lit::LChar(parse_char(&lit_str)?)
                 } else if self.t == EXPR_TYPE_LIT_STR {
                     Lit::LString(lit_str)
                 } else if self.t == EXPR_TYPE_LIT_BOOL {
@@ -166,16 +202,67 @@ impl ToAst<Expr> for expr_lit_t {
 #[repr(C)]
 struct expr_new_t {
     t: *mut c_char,
-    size: *mut expr_t,
+    sizes: *mut many_t,
 }
 
 impl ToAst<Expr> for expr_new_t {
     fn to_ast(&self) -> TAResult<Expr> {
         let t = self.t.to_ast()?;
-        Ok(if self.size.is_null() {
+        Ok(if self.sizes.is_null() {
             Expr::ENew(t)
         } else {
-            Expr::ENewArray(t, Box::new(self.size.to_ast()?))
+            Expr::ENewArray(t, many_t::to_vec(self.sizes, expr_t::to_ast)?)
         })
     }
 }
+
+// contents of a char literal, without quotes
+fn parse_char(lit_str: &String) -> TAResult<u8> {
+    let bytes = lit_str.as_bytes();
+    match (bytes.len(), bytes.first()) {
+        (1, Some(&c)) => Ok(c),
+        (2, Some(&b'\\')) => {
+            match bytes[1] {
+                b'n' => Ok(b'\n'),
+                b't' => Ok(b'\t'),
+                b'r' => Ok(b'\r'),
+                b'0' => Ok(0),
+                b'\\' => Ok(b'\\'),
+                b'\'' => Ok(b'\''),
+                b'"' => Ok(b'"'),
+                _ => Err(format!("Unknown escape sequence in char literal: '{}'", lit_str)),
+            }
+        }
+        _ => Err(format!("Invalid char literal: '{}'", lit_str)),
+    }
+}
+
+#[repr(C)]
+struct expr_cast_t {
+    t: *mut c_char,
+    expr: *mut expr_t,
+}
+
+impl ToAst<Expr> for expr_cast_t {
+    fn to_ast(&self) -> TAResult<Expr> {
+        let t = self.t.to_ast()?;
+        let e = self.expr.to_ast()?;
+        Ok(Expr::ECast(t, Box::new(e)))
+    }
+}
+
+#[repr(C)]
+struct expr_cond_t {
+    cond: *mut expr_t,
+    e1: *mut expr_t,
+    e2: *mut expr_t,
+}
+
+impl ToAst<Expr> for expr_cond_t {
+    fn to_ast(&self) -> TAResult<Expr> {
+        let cond = self.cond.to_ast()?;
+        let e1 = self.e1.to_ast()?;
+        let e2 = self.e2.to_ast()?;
+        Ok(Expr::ECond(Box::new(cond), Box::new(e1), Box::new(e2)))
+    }
+}
@@ -19,8 +19,11 @@ void yyerror(const char *s);
 #include "ast.h"
 %}
 
+%locations
+
 %union {
   char *str;
+  int num;
   struct def_t *def;
   struct class_t *class;
   struct class_member_t *class_member;
@@ -31,6 +34,7 @@ void yyerror(const char *s);
   struct stmt_t *stmt;
   struct var_decl_t *var_decl;
   struct field_get_t *field_get;
+  struct switch_case_t *switch_case;
 }
 
 %token <str> IDENT "identifier"
@@ -40,6 +44,9 @@ void yyerror(const char *s);
 %token <str> LIT_INT "integer literal";
 %token <str> LIT_STR "string literal";
 %token <str> LIT_BOOL "boolean literal";
+%token <str> LIT_DOUBLE "double literal";
+%token <str> LIT_LONG "long literal";
+%token <str> LIT_CHAR "char literal";
 %token LIT_NULL "null literal"
 
 %token INCR "++"
@@ -51,32 +58,57 @@ void yyerror(const char *s);
 %token GE ">="
 %token EQ "=="
 %token NEQ "!="
+%token SHL "<<"
+%token SHR ">>" /* first '>' of ">>", the second one is lexed separately */
 
+%right '?' ':'
 %left OR
 %left AND
 %nonassoc '<' '>' LE GE EQ NEQ
+%left '|'
+%left '^'
+%left '&'
+%left SHL SHR
 %left '+' '-'
 %left '*' '/' '%'
-%left UNOT UNEG
+%left UNOT UNEG UBITNOT CAST
 %right '['
 %left '.'
 
 %token BRACKETS "[]"
+%token CASE "case"
 %token CLASS "class definition"
+%token CONST "const"
+%token DEFAULT "default"
 %token ELSE "else"
+%token ENUM "enum definition"
 %token EXTENDS "extends <superclass>"
+%token EXTERN "extern declaration"
 %token FOR "for"
 %token IF "if statement"
+%token LIST "list"
+%token MAP "map"
 %token NEW "new"
+%token PRIVATE "private"
+%token PROTECTED "protected"
+%token PUBLIC "public"
 %token RETURN "return statement"
+%token STATIC "static"
+%token SWITCH "switch statement"
 %token WHILE "while statement"
 
 %type <many> defs "list of definitions";
 %type <def> def "definition";
 
+%type <def> global_def "global variable definition"
+%type <def> enum_def "enum definition"
+%type <def> extern_def "extern declaration"
+%type <many> types "list of types"
+%type <many> idents "list of identifiers"
 %type <class> class_def "class definition"
 %type <many> class_members "class members";
 %type <class_member> class_member "class member";
+%type <num> access "access modifier";
 
 %type <func> func_def "function definition";
 %type <many> f_args "function arguments";
@@ -88,17 +120,26 @@ void yyerror(const char *s);
 %type <many> stmts "list of statements";
 %type <stmt> stmt_block "block of statements";
 %type <stmt> stmt "statement";
+%type <stmt> stmt_kind;
+%type <many> switch_cases "switch cases";
+%type <switch_case> switch_case "switch case";
+%type <many> case_labels "case labels";
+%type <many> case_body "case body";
 
 %type <many> var_inits;
 %type <var_decl> var_init;
 
 %type <many> exprs "list of expressions";
 %type <expr> expr "expression";
+%type <expr> init_expr "expression or array literal";
+%type <many> new_dims "array dimensions";
+%type <num> brackets;
 
 %type <field_get> field_get;
 
 %type <str> type "type";
 %type <str> simple_type "simple_type";
+%type <str> base_type "base_type";
 %%
 program: defs { parsed_defs = $1; }
 
@@ -107,6 +148,23 @@ defs: def { $$ = many_create($1); }
 
 def: func_def { $$ = def_func_create($1); }
    | class_def { $$ = def_class_create($1); }
+   | global_def { $$ = $1; }
+   | enum_def { $$ = $1; }
+   | extern_def { $$ = $1; }
+
+global_def: type var_inits ';' { $$ = def_global_create($1, $2, 0); }
+          | CONST type var_inits ';' { $$ = def_global_create($2, $3, 1); }
+
+enum_def: ENUM IDENT '{' idents '}' { $$ = def_enum_create($2, $4); }
+
+idents: IDENT { $$ = many_create($1); }
+      | IDENT ',' idents { $$ = many_add($1, $3); }
+
+extern_def: EXTERN type IDENT '(' types ')' ';' { $$ = def_extern_create($2, $3, $5); }
+
+types: /* empty */ { $$ = NULL; }
+     | type { $$ = many_create($1); }
+     | type ',' types { $$ = many_add($1, $3); }
 
 class_def: CLASS IDENT '{' class_members '}' {
             $$ = class_create($2, NULL, $4);
@@ -118,10 +176,19 @@ class_def: CLASS IDENT '{' class_members '}' {
 class_members: /* empty */ { $$ = NULL; }
              | class_member class_members { $$ = many_add($1, $2); }
 
-class_member: func_def { $$ = class_member_func_create($1); }
-            | var ';' { $$ = class_member_var_create($1); }
+class_member: access func_def { $$ = class_member_func_create($1, $2); }
+            | access var ';' { $$ = class_member_var_create($1, $2); }
+            | access STATIC func_def { $$ = class_member_static_func_create($1, $3); }
+            | access STATIC type var_inits ';' {
+                $$ = class_member_static_var_create($1, $3, $4);
+            }
+
+access: /* empty */ { $$ = ACCESS_PUBLIC; }
+      | PUBLIC { $$ = ACCESS_PUBLIC; }
+      | PROTECTED { $$ = ACCESS_PROTECTED; }
+      | PRIVATE { $$ = ACCESS_PRIVATE; }
 
-func_def: type IDENT '(' f_args ')' body { $$ = func_create($1, $2, $4, $6); }
+func_def: type IDENT '(' f_args ')' body { $$ = func_create($1, $2, $4, $6, @2.first_line); }
 
 f_args: /* empty */ { $$ = NULL; }
       | var { $$ = many_create($1); }
@@ -138,31 +205,51 @@ body: '{' '}' { $$ = NULL; }
 stmts: stmt { $$ = many_create($1); }
      | stmt stmts { $$ = many_add($1, $2); }
 
-stmt: /* empty */ ';' { $$ = stmt_empty_create(); }
-    | type var_inits ';' { $$ = stmt_var_decls_create($1, $2); }
-    | field_get '=' expr ';' { $$ = stmt_assign_create($1, $3); }
-    | field_get INCR ';' { $$ = stmt_postfix_create($1, 0); }
-    | field_get DECR ';' { $$ = stmt_postfix_create($1, 1); }
-    | RETURN ';' { $$ = stmt_return_create(NULL); }
-    | RETURN expr ';' { $$ = stmt_return_create($2); }
-    | stmt_block { $$ = $1; }
-    | expr ';' { $$ = stmt_expr_create($1); }
-    | IF '(' expr ')' stmt ELSE stmt { $$ = stmt_if_create($3, $5, $7); } /* shift/reduce */
-    | IF '(' expr ')' stmt { $$ = stmt_if_create($3, $5, NULL); }
-    | WHILE '(' expr ')' stmt { $$ = stmt_while_create($3, $5); }
-    | FOR '(' type IDENT ':' expr ')' stmt { $$ = stmt_for_create($3, $4, $6, $8); }
+/* the line of a statement is the line of its first token */
+stmt: stmt_kind { $$ = stmt_set_line($1, @1.first_line); }
+
+stmt_kind: /* empty */ ';' { $$ = stmt_empty_create(); }
+         | type var_inits ';' { $$ = stmt_var_decls_create($1, $2); }
+         | field_get '=' init_expr ';' { $$ = stmt_assign_create($1, $3); }
+         | field_get INCR ';' { $$ = stmt_postfix_create($1, 0); }
+         | field_get DECR ';' { $$ = stmt_postfix_create($1, 1); }
+         | RETURN ';' { $$ = stmt_return_create(NULL); }
+         | RETURN init_expr ';' { $$ = stmt_return_create($2); }
+         | stmt_block { $$ = $1; }
+         | expr ';' { $$ = stmt_expr_create($1); }
+         | IF '(' expr ')' stmt ELSE stmt { $$ = stmt_if_create($3, $5, $7); } /* shift/reduce */
+         | IF '(' expr ')' stmt { $$ = stmt_if_create($3, $5, NULL); }
+         | WHILE '(' expr ')' stmt { $$ = stmt_while_create($3, $5); }
+         | FOR '(' type IDENT ':' init_expr ')' stmt { $$ = stmt_for_create($3, $4, $6, $8); }
+         | SWITCH '(' expr ')' '{' switch_cases '}' { $$ = stmt_switch_create($3, $6); }
+
+switch_cases: /* empty */ { $$ = NULL; }
+            | switch_case switch_cases { $$ = many_add($1, $2); }
+
+switch_case: CASE case_labels ':' case_body { $$ = switch_case_create($2, $4); }
+           | DEFAULT ':' case_body { $$ = switch_case_create(NULL, $3); }
+
+case_labels: expr { $$ = many_create($1); }
+           | expr ',' case_labels { $$ = many_add($1, $3); }
+
+case_body: /* empty */ { $$ = NULL; }
+         | stmt case_body { $$ = many_add($1, $2); }
 
 var_inits: var_init { $$ = many_create($1); }
          | var_init ',' var_inits { $$ = many_add($1, $3); }
 
-var_init: IDENT '=' expr { $$ = var_decl_create($1, $3); }
+var_init: IDENT '=' init_expr { $$ = var_decl_create($1, $3); }
         | IDENT { $$ = var_decl_create($1, NULL); }
 
 exprs: /* empty */ { $$ = NULL; }
-     | expr { $$ = many_create($1); }
-     | expr ',' exprs { $$ = many_add($1, $3); }
+     | init_expr { $$ = many_create($1); }
+     | init_expr ',' exprs { $$ = many_add($1, $3); }
+
+init_expr: expr { $$ = $1; }
+         | '{' exprs '}' { $$ = expr_arr_lit_create($2); }
 
-expr: expr OR expr { $$ = expr_binop_create($1, $3, "||"); }
+expr: expr '?' expr ':' expr { $$ = expr_cond_create($1, $3, $5); }
+    | expr OR expr { $$ = expr_binop_create($1, $3, "||"); }
     | expr AND expr { $$ = expr_binop_create($1, $3, "&&"); }
     | expr '<' expr { $$ = expr_binop_create($1, $3, "<"); }
     | expr '>' expr { $$ = expr_binop_create($1, $3, ">"); }
@@ -175,10 +262,17 @@ expr: expr OR expr { $$ = expr_binop_create($1, $3, "||"); }
     | expr '*' expr { $$ = expr_binop_create($1, $3, "*"); }
     | expr '/' expr { $$ = expr_binop_create($1, $3, "/"); }
     | expr '%' expr { $$ = expr_binop_create($1, $3, "%"); }
+    | expr '&' expr { $$ = expr_binop_create($1, $3, "&"); }
+    | expr '|' expr { $$ = expr_binop_create($1, $3, "|"); }
+    | expr '^' expr { $$ = expr_binop_create($1, $3, "^"); }
+    | expr SHL expr { $$ = expr_binop_create($1, $3, "<<"); }
+    | expr SHR '>' expr { $$ = expr_binop_create($1, $4, ">>"); }
     | '-' expr %prec UNEG { $$ = expr_unary_create('-', $2); }
     | '!' expr %prec UNOT { $$ = expr_unary_create('!', $2); }
+    | '~' expr %prec UBITNOT { $$ = expr_unary_create('~', $2); }
     | '(' expr ')' { $$ = $2; }
-    | NEW simple_type '[' expr ']' { $$ = expr_new_create($2, $4); } /* shift/reduce */
+    | '(' BUILTIN_TYPE ')' expr %prec CAST { $$ = expr_cast_create($2, $4); }
+    | NEW simple_type new_dims brackets { $$ = expr_new_create(array_type_create_n($2, $4), $3); }
     | NEW simple_type { $$ = expr_new_create($2, NULL); }
     | field_get '(' exprs ')' { $$ = expr_call_create($1, $3); }
     | field_get { $$ = expr_field_get_create($1); }
@@ -186,17 +280,34 @@ expr: expr OR expr { $$ = expr_binop_create($1, $3, "||"); }
     | LIT_INT { $$ = expr_lit_create(EXPR_TYPE_LIT_INT, $1); }
     | LIT_STR { $$ = expr_lit_create(EXPR_TYPE_LIT_STR, $1); }
     | LIT_BOOL { $$ = expr_lit_create(EXPR_TYPE_LIT_BOOL, $1); }
+    | LIT_DOUBLE { $$ = expr_lit_create(EXPR_TYPE_LIT_DOUBLE, $1); }
+    | LIT_LONG { $$ = expr_lit_create(EXPR_TYPE_LIT_LONG, $1); }
+    | LIT_CHAR { $$ = expr_lit_create(EXPR_TYPE_LIT_CHAR, $1); }
     | LIT_NULL { $$ = expr_lit_create(EXPR_TYPE_LIT_NULL, NULL); }
 
+new_dims: '[' expr ']' { $$ = many_create($2); }
+        | '[' expr ']' new_dims { $$ = many_add($2, $4); } /* shift/reduce */
+
+brackets: /* empty */ { $$ = 0; }
+        | BRACKETS brackets { $$ = $2 + 1; }
+
 field_get: expr '.' IDENT { $$ = field_get_create($1, $3); }
          | expr '[' expr ']' { $$ = field_get_idx_create($1, $3); }
          | IDENT { $$ = field_get_create(NULL, $1); }
 
-type: simple_type BRACKETS { $$ = array_type_create($1); }
+type: type BRACKETS { $$ = array_type_create($1); }
     | simple_type
 
-simple_type: BUILTIN_TYPE { $$ = $1; }
-           | IDENT { $$ = $1; }
+simple_type: base_type
+           | LIST '<' type type_end { $$ = list_type_create($3); }
+           | MAP '<' base_type ',' type type_end { $$ = map_type_create($3, $5); }
+
+/* in list<list<int>> the inner '>' is lexed as the beginning of ">>" */
+type_end: '>'
+        | SHR
+
+base_type: BUILTIN_TYPE { $$ = $1; }
+         | IDENT { $$ = $1; }
 %%
 
 struct many_t *parsed_defs = NULL;
@@ -12,6 +12,7 @@ use self::to_ast::*;
 
 mod common;
 mod def;
+mod enum_types;
 mod expr;
 mod field_get;
 mod many;
@@ -36,5 +37,7 @@ pub fn run(f: File) -> Result<Program, String> {
         free_parsed_defs();
     }
 
-    defs.map(Program)
+    let mut program = defs.map(Program)?;
+    enum_types::resolve(&mut program);
+    Ok(program)
 }
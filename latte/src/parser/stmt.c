@@ -10,8 +10,10 @@ const int32_t STMT_TYPE_POSTFIX = 6;
 const int32_t STMT_TYPE_RETURN = 7;
 const int32_t STMT_TYPE_VAR_INIT = 8;
 const int32_t STMT_TYPE_WHILE = 9;
+const int32_t STMT_TYPE_SWITCH = 10;
 
 struct stmt_t *stmt_create(int32_t type, void *s);
+void switch_case_free(void *ptr);
 
 struct stmt_t *stmt_empty_create() {
   return stmt_create(STMT_TYPE_EMPTY, NULL);
@@ -90,14 +92,37 @@ struct stmt_t *stmt_for_create(char *type, char *ident, struct expr_t *e,
   return stmt_create(STMT_TYPE_FOR, s);
 }
 
+struct stmt_t *stmt_switch_create(struct expr_t *e, struct many_t *cases) {
+  struct stmt_switch_t *s = malloc(sizeof(struct stmt_switch_t));
+  CHECK_NULL(s);
+  s->e = e;
+  s->cases = cases;
+  return stmt_create(STMT_TYPE_SWITCH, s);
+}
+
+struct switch_case_t *switch_case_create(struct many_t *labels,
+                                         struct many_t *stmts) {
+  struct switch_case_t *c = malloc(sizeof(struct switch_case_t));
+  CHECK_NULL(c);
+  c->labels = labels;
+  c->stmts = stmts;
+  return c;
+}
+
 struct stmt_t *stmt_create(int32_t type, void *s) {
   struct stmt_t *stmt = malloc(sizeof(struct stmt_t));
   CHECK_NULL(stmt);
   stmt->type = type;
   stmt->s = s;
+  stmt->line = 0;
   return stmt;
 }
 
+struct stmt_t *stmt_set_line(struct stmt_t *s, int32_t line) {
+  s->line = line;
+  return s;
+}
+
 void stmt_free(void *ptr) {
   if (ptr == NULL) {
     return;
@@ -142,6 +167,10 @@ void stmt_free(void *ptr) {
     free(stmt->ident);
     expr_free(stmt->e);
     stmt_free(stmt->s);
+  } else if (type == STMT_TYPE_SWITCH) {
+    struct stmt_switch_t *stmt = (struct stmt_switch_t *)s;
+    expr_free(stmt->e);
+    many_free(stmt->cases, switch_case_free);
   } else {
     assert(0);
     exit(-1);
@@ -161,3 +190,14 @@ void var_decl_free(void *ptr) {
   expr_free(d->e);
   free(ptr);
 }
+
+void switch_case_free(void *ptr) {
+  if (ptr == NULL) {
+    return;
+  }
+
+  struct switch_case_t *c = (struct switch_case_t *)ptr;
+  many_free(c->labels, expr_free);
+  many_free(c->stmts, stmt_free);
+  free(ptr);
+}
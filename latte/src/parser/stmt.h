@@ -15,10 +15,12 @@ extern const int32_t STMT_TYPE_POSTFIX;
 extern const int32_t STMT_TYPE_RETURN;
 extern const int32_t STMT_TYPE_VAR_INIT;
 extern const int32_t STMT_TYPE_WHILE;
+extern const int32_t STMT_TYPE_SWITCH;
 
 struct stmt_t {
   int32_t type;
   void *s;  // nullable (empty statement ";")
+  int32_t line;
 };
 
 struct stmt_var_decls_t {
@@ -59,6 +61,18 @@ struct stmt_for_t {
   struct stmt_t *s;
 };
 
+struct stmt_switch_t {
+  struct expr_t *e;
+  struct many_t *cases;  // switch_case_t
+};
+
+struct switch_case_t {
+  struct many_t *labels;  // expr_t, NULL for the default case
+  struct many_t *stmts;   // stmt_t
+};
+
+extern struct stmt_t *stmt_set_line(struct stmt_t *s, int32_t line);
+
 extern struct stmt_t *stmt_empty_create();
 extern struct stmt_t *stmt_var_decls_create(char *type, struct many_t *decls);
 extern struct var_decl_t *var_decl_create(char *ident, struct expr_t *e);
@@ -76,6 +90,11 @@ extern struct stmt_t *stmt_while_create(struct expr_t *cond, struct stmt_t *s);
 extern struct stmt_t *stmt_for_create(char *type, char *ident, struct expr_t *e,
                                       struct stmt_t *s);
 
+extern struct stmt_t *stmt_switch_create(struct expr_t *e,
+                                         struct many_t *cases);
+extern struct switch_case_t *switch_case_create(struct many_t *labels,
+                                                struct many_t *stmts);
+
 extern void stmt_free(void *s);
 extern void var_decl_free(void *d);
 
@@ -1,6 +1,6 @@
 use libc::*;
 
-use ast::{Ident, Stmt, Type, VarDecl};
+use ast::{Case, Expr, Ident, Stmt, StmtKind, Type, VarDecl};
 
 use parser::expr::*;
 use parser::field_get::*;
@@ -19,19 +19,27 @@ extern "C" {
     static STMT_TYPE_RETURN: c_int;
     static STMT_TYPE_VAR_INIT: c_int;
     static STMT_TYPE_WHILE: c_int;
+    static STMT_TYPE_SWITCH: c_int;
 }
 
 #[repr(C)]
 pub struct stmt_t {
     t: i32,
     ptr: *mut c_void,
+    line: i32,
 }
 
 impl ToAst<Stmt> for stmt_t {
     fn to_ast(&self) -> TAResult<Stmt> {
+        Ok(Stmt::new(self.to_ast()?, self.line))
+    }
+}
+
+impl ToAst<StmtKind> for stmt_t {
+    fn to_ast(&self) -> TAResult<StmtKind> {
         unsafe {
             if self.t == STMT_TYPE_EMPTY {
-                return Ok(Stmt::SEmpty);
+                return Ok(StmtKind::SEmpty);
             }
             if self.t == STMT_TYPE_VAR_INIT {
                 return (self.ptr as *mut stmt_var_decls_t).to_ast();
@@ -44,19 +52,19 @@ impl ToAst<Stmt> for stmt_t {
             }
             if self.t == STMT_TYPE_RETURN {
                 return if self.ptr.is_null() {
-                    Ok(Stmt::SReturn)
+                    Ok(StmtKind::SReturn)
                 } else {
                     let e = (self.ptr as *mut expr_t).to_ast()?;
-                    Ok(Stmt::SReturnE(e))
+                    Ok(StmtKind::SReturnE(e))
                 };
             }
             if self.t == STMT_TYPE_BLOCK {
                 let stmts = many_t::to_vec(self.ptr as *mut many_t, stmt_t::to_ast)?;
-                return Ok(Stmt::SBlock(stmts));
+                return Ok(StmtKind::SBlock(stmts));
             }
             if self.t == STMT_TYPE_EXPR {
                 let e = (self.ptr as *mut expr_t).to_ast()?;
-                return Ok(Stmt::SExpr(e));
+                return Ok(StmtKind::SExpr(e));
             }
             if self.t == STMT_TYPE_IF {
                 return (self.ptr as *mut stmt_if_t).to_ast();
@@ -67,6 +75,9 @@ impl ToAst<Stmt> for stmt_t {
             if self.t == STMT_TYPE_FOR {
                 return (self.ptr as *mut stmt_for_t).to_ast();
             }
+            if self.t == STMT_TYPE_SWITCH {
+                return (self.ptr as *mut stmt_switch_t).to_ast();
+            }
         }
         Err(format!("Unknown statement type: {}", self.t))
     }
@@ -79,22 +90,22 @@ struct stmt_var_decls_t {
     inits: *mut many_t,
 }
 
-impl ToAst<Stmt> for stmt_var_decls_t {
-    fn to_ast(&self) -> TAResult<Stmt> {
+impl ToAst<StmtKind> for stmt_var_decls_t {
+    fn to_ast(&self) -> TAResult<StmtKind> {
         let t: Type = self.var_type.to_ast()?;
         let inits = many_t::to_vec(self.inits, |var_decl: &var_decl_t| var_decl.to_ast(&t))?;
-        Ok(Stmt::SDecl(t, inits))
+        Ok(StmtKind::SDecl(t, inits))
     }
 }
 
 #[repr(C)]
-struct var_decl_t {
+pub struct var_decl_t {
     ident: *mut c_char,
     expr: *mut expr_t,
 }
 
 impl var_decl_t {
-    fn to_ast(&self, t: &Type) -> TAResult<VarDecl> {
+    pub fn to_ast(&self, t: &Type) -> TAResult<VarDecl> {
         let ident: Ident = self.ident.to_ast()?;
         if self.expr.is_null() {
             return Ok(VarDecl::NoInit(t.clone(), ident));
@@ -111,11 +122,11 @@ struct stmt_assign_t {
     expr: *mut expr_t,
 }
 
-impl ToAst<Stmt> for stmt_assign_t {
-    fn to_ast(&self) -> TAResult<Stmt> {
+impl ToAst<StmtKind> for stmt_assign_t {
+    fn to_ast(&self) -> TAResult<StmtKind> {
         let field = self.field.to_ast()?;
         let expr = self.expr.to_ast()?;
-        Ok(Stmt::SAssign(field, expr))
+        Ok(StmtKind::SAssign(field, expr))
     }
 }
 
@@ -125,12 +136,12 @@ struct stmt_postfix_t {
     is_decr: i32,
 }
 
-impl ToAst<Stmt> for stmt_postfix_t {
-    fn to_ast(&self) -> TAResult<Stmt> {
+impl ToAst<StmtKind> for stmt_postfix_t {
+    fn to_ast(&self) -> TAResult<StmtKind> {
         let field = self.field.to_ast()?;
         match self.is_decr {
-            0 => Ok(Stmt::SInc(field)),
-            1 => Ok(Stmt::SDec(field)),
+            0 => Ok(StmtKind::SInc(field)),
+            1 => Ok(StmtKind::SDec(field)),
             _ => Err(format!("Unknown postfix operator flag: {}", self.is_decr)),
         }
     }
@@ -143,15 +154,15 @@ struct stmt_if_t {
     else_s: *mut stmt_t,
 }
 
-impl ToAst<Stmt> for stmt_if_t {
-    fn to_ast(&self) -> TAResult<Stmt> {
+impl ToAst<StmtKind> for stmt_if_t {
+    fn to_ast(&self) -> TAResult<StmtKind> {
         let cond = self.cond.to_ast()?;
         let if_s = self.if_s.to_ast()?;
         if self.else_s.is_null() {
-            Ok(Stmt::SIf(cond, Box::new(if_s)))
+            Ok(StmtKind::SIf(cond, Box::new(if_s)))
         } else {
             let else_s = self.else_s.to_ast()?;
-            Ok(Stmt::SIfElse(cond, Box::new(if_s), Box::new(else_s)))
+            Ok(StmtKind::SIfElse(cond, Box::new(if_s), Box::new(else_s)))
         }
     }
 }
@@ -162,11 +173,11 @@ struct stmt_while_t {
     stmt: *mut stmt_t,
 }
 
-impl ToAst<Stmt> for stmt_while_t {
-    fn to_ast(&self) -> TAResult<Stmt> {
+impl ToAst<StmtKind> for stmt_while_t {
+    fn to_ast(&self) -> TAResult<StmtKind> {
         let cond = self.cond.to_ast()?;
         let stmt = self.stmt.to_ast()?;
-        Ok(Stmt::SWhile(cond, Box::new(stmt)))
+        Ok(StmtKind::SWhile(cond, Box::new(stmt)))
     }
 }
 
@@ -178,12 +189,57 @@ struct stmt_for_t {
     stmt: *mut stmt_t,
 }
 
-impl ToAst<Stmt> for stmt_for_t {
-    fn to_ast(&self) -> TAResult<Stmt> {
+impl ToAst<StmtKind> for stmt_for_t {
+    fn to_ast(&self) -> TAResult<StmtKind> {
         let t = self.t.to_ast()?;
         let ident = self.ident.to_ast()?;
         let e = self.e.to_ast()?;
         let stmt = self.stmt.to_ast()?;
-        Ok(Stmt::SFor(t, ident, e, Box::new(stmt)))
+        Ok(StmtKind::SFor(t, ident, e, Box::new(stmt)))
+    }
+}
+
+#[repr(C)]
+struct stmt_switch_t {
+    e: *mut expr_t,
+    cases: *mut many_t,
+}
+
+impl ToAst<StmtKind> for stmt_switch_t {
+    fn to_ast(&self) -> TAResult<StmtKind> {
+        let e = self.e.to_ast()?;
+        let mut cases: Vec<Case> = Vec::new();
+        let mut default: Option<Vec<Stmt>> = None;
+        for (labels, body) in many_t::to_vec(self.cases, switch_case_t::to_ast)? {
+            match labels {
+                Some(labels) => {
+                    cases.push(Case {
+                        labels,
+                        body,
+                    })
+                }
+                None if default.is_none() => default = Some(body),
+                None => return Err("Multiple default labels in one switch".to_string()),
+            }
+        }
+        Ok(StmtKind::SSwitch(e, cases, default))
+    }
+}
+
+#[repr(C)]
+struct switch_case_t {
+    labels: *mut many_t,
+    stmts: *mut many_t,
+}
+
+impl switch_case_t {
+    // labels are None for the default case
+    fn to_ast(&self) -> TAResult<(Option<Vec<Expr>>, Vec<Stmt>)> {
+        let labels = match self.labels.is_null() {
+            true => None,
+            false => Some(many_t::to_vec(self.labels, expr_t::to_ast)?),
+        };
+        let body = many_t::to_vec(self.stmts, stmt_t::to_ast)?;
+        Ok((labels, body))
     }
 }
@@ -4,9 +4,39 @@
 #include "common.h"
 
 char *array_type_create(char *type) {
-  char *res = malloc(strlen(type) + 1);
+  char *res = malloc(strlen(type) + 2);
   CHECK_NULL(res);
   strcpy(res + 1, type);
   res[0] = '[';
+  free(type);
+  return res;
+}
+
+char *array_type_create_n(char *type, int dims) {
+  while (dims-- > 0 && type != NULL) {
+    type = array_type_create(type);
+  }
+  return type;
+}
+
+char *list_type_create(char *type) {
+  char *res = malloc(strlen(type) + 2);
+  CHECK_NULL(res);
+  strcpy(res + 1, type);
+  res[0] = '<';
+  free(type);
+  return res;
+}
+
+char *map_type_create(char *key_type, char *value_type) {
+  size_t key_len = strlen(key_type);
+  char *res = malloc(key_len + strlen(value_type) + 3);
+  CHECK_NULL(res);
+  res[0] = '{';
+  strcpy(res + 1, key_type);
+  res[key_len + 1] = ':';
+  strcpy(res + key_len + 2, value_type);
+  free(key_type);
+  free(value_type);
   return res;
 }
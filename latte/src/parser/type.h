@@ -2,5 +2,8 @@
 #define TYPE__H
 
 extern char *array_type_create(char *type);
+extern char *array_type_create_n(char *type, int dims);
+extern char *list_type_create(char *type);
+extern char *map_type_create(char *key_type, char *value_type);
 
 #endif
@@ -44,13 +44,36 @@ impl CollectStringLit for Def {
         match *self {
             Def::DClass(ref c) => c.collect(res),
             Def::DFunc(ref f) => f.collect(res),
+            Def::DGlobal(ref g) => g.collect(res),
+            Def::DEnum(ref e) => e.collect(res),
+            Def::DExtern(..) => {}
+        }
+    }
+}
+
+impl CollectStringLit for Global {
+    fn collect(&self, res: &mut HashSet<String>) {
+        // all globals are zero-initialized before their initializers run
+        if self.t == Type::TString {
+            res.insert(String::new());
+        }
+        self.decls.collect(res);
+    }
+}
+
+// variant names are returned by name()
+impl CollectStringLit for Enum {
+    fn collect(&self, res: &mut HashSet<String>) {
+        for variant in &self.variants {
+            res.insert(variant.0.clone());
         }
     }
 }
 
 impl CollectStringLit for Class {
     fn collect(&self, res: &mut HashSet<String>) {
-        for m in &self.methods {
+        self.static_vars.collect(res);
+        for m in self.methods.iter().chain(&self.static_methods) {
             m.collect(res);
         }
     }
@@ -64,26 +87,36 @@ impl CollectStringLit for Func {
 
 impl CollectStringLit for Stmt {
     fn collect(&self, res: &mut HashSet<String>) {
-        match *self {
-            Stmt::SIf(ref e, ref s) |
-            Stmt::SWhile(ref e, ref s) => {
+        match self.kind {
+            StmtKind::SIf(ref e, ref s) |
+            StmtKind::SWhile(ref e, ref s) => {
                 e.collect(res);
                 s.collect(res);
             }
-            Stmt::SBlock(ref stmts) => stmts.collect(res),
-            Stmt::SDecl(_, ref var_decls) => var_decls.collect(res),
-            Stmt::SAssign(_, ref e) |
-            Stmt::SReturnE(ref e) |
-            Stmt::SExpr(ref e) => e.collect(res),
-            Stmt::SIfElse(ref e, ref s1, ref s2) => {
+            StmtKind::SBlock(ref stmts) => stmts.collect(res),
+            StmtKind::SDecl(_, ref var_decls) => var_decls.collect(res),
+            StmtKind::SAssign(_, ref e) |
+            StmtKind::SReturnE(ref e) |
+            StmtKind::SExpr(ref e) => e.collect(res),
+            StmtKind::SIfElse(ref e, ref s1, ref s2) => {
                 e.collect(res);
                 s1.collect(res);
                 s2.collect(res);
             }
-            Stmt::SFor(_, _, ref e, ref stmt) => {
+            StmtKind::SFor(_, _, ref e, ref stmt) => {
                 e.collect(res);
                 stmt.collect(res);
             }
+            StmtKind::SSwitch(ref e, ref cases, ref default) => {
+                e.collect(res);
+                for case in cases {
+                    case.labels.collect(res);
+                    case.body.collect(res);
+                }
+                if let Some(ref stmts) = *default {
+                    stmts.collect(res);
+                }
+            }
             _ => {}
         }
     }
@@ -104,13 +137,22 @@ impl CollectStringLit for Expr {
     fn collect(&self, res: &mut HashSet<String>) {
         match *self {
             Expr::ELit(ref l) => l.collect(res),
-            Expr::ECall(_, ref exprs) => exprs.collect(res),
+            Expr::ECall(_, ref exprs) |
+            Expr::ENewArray(_, ref exprs) |
+            Expr::EArrayLit(ref exprs) => exprs.collect(res),
             Expr::ENeg(ref e) |
-            Expr::ENot(ref e) => e.collect(res),
+            Expr::ENot(ref e) |
+            Expr::EBitNot(ref e) |
+            Expr::ECast(_, ref e) => e.collect(res),
             Expr::EBinOp(ref e1, _, ref e2) => {
                 e1.collect(res);
                 e2.collect(res);
             }
+            Expr::ECond(ref cond, ref e1, ref e2) => {
+                cond.collect(res);
+                e1.collect(res);
+                e2.collect(res);
+            }
             _ => {}
         }
     }
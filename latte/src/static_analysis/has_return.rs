@@ -18,11 +18,14 @@ impl HasReturn for Vec<Stmt> {
 
 impl HasReturn for Stmt {
     fn has_return(&self) -> bool {
-        match *self {
-            Stmt::SReturn |
-            Stmt::SReturnE(_) => true,
-            Stmt::SBlock(ref stmts) => stmts.has_return(),
-            Stmt::SIfElse(_, ref s1, ref s2) => s1.has_return() && s2.has_return(),
+        match self.kind {
+            StmtKind::SReturn |
+            StmtKind::SReturnE(_) => true,
+            StmtKind::SBlock(ref stmts) => stmts.has_return(),
+            StmtKind::SIfElse(_, ref s1, ref s2) => s1.has_return() && s2.has_return(),
+            StmtKind::SSwitch(_, ref cases, Some(ref default)) => {
+                default.has_return() && cases.iter().all(|c| c.body.has_return())
+            }
             _ => false,
         }
     }
@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+
+use ast::*;
+use static_analysis::warning::Warning;
+
+// Dataflow analysis of local variables and fields, run on the optimized program.
+// Reports reads of locals which may be unassigned and dereferences of locals
+// and fields which are always null at that point.
+pub fn run(p: &Program) -> Vec<Warning> {
+    let mut warnings: Vec<Warning> = Vec::new();
+    for def in &p.0 {
+        match *def {
+            Def::DFunc(ref f) => warnings.extend(check_func(f, &HashMap::new())),
+            Def::DClass(ref c) => {
+                let place = format!("class {}\n", c.name);
+                for f in &c.static_methods {
+                    warnings.extend(check_func(f, &HashMap::new())
+                        .into_iter()
+                        .map(|w| w.wrapped(&place)));
+                }
+                let fields: HashMap<Ident, VarState> = c.vars
+                    .iter()
+                    .filter(|v| is_nullable(&v.t))
+                    .map(|v| (v.ident.clone(), VarState::field()))
+                    .collect();
+                for f in &c.methods {
+                    warnings.extend(check_func(f, &fields)
+                        .into_iter()
+                        .map(|w| w.wrapped(&place)));
+                }
+            }
+            Def::DGlobal(..) |
+            Def::DEnum(..) |
+            Def::DExtern(..) => {}
+        }
+    }
+    warnings
+}
+
+fn check_func(f: &Func, fields: &HashMap<Ident, VarState>) -> Vec<Warning> {
+    let mut vars = fields.clone();
+    // arguments hide fields
+    for arg in &f.args {
+        vars.remove(&arg.ident);
+    }
+    let mut analysis = Analysis {
+        fields: fields.keys().cloned().collect(),
+        report: true,
+        warnings: Vec::new(),
+    };
+    analysis.stmts(&f.body, Some(vars));
+    let place = format!("function {}\n", f.ident);
+    analysis.warnings.into_iter().map(|w| w.wrapped(&place)).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Nullness {
+    Null,
+    NotNull,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct VarState {
+    assigned: bool,
+    null: Nullness,
+}
+
+impl VarState {
+    fn field() -> VarState {
+        VarState {
+            assigned: true,
+            null: Nullness::Unknown,
+        }
+    }
+
+    fn join(self, other: VarState) -> VarState {
+        VarState {
+            assigned: self.assigned && other.assigned,
+            null: if self.null == other.null {
+                self.null
+            } else {
+                Nullness::Unknown
+            },
+        }
+    }
+}
+
+// Tracked variables in scope, None if the code is unreachable.
+// Arguments and globals are not tracked, they are always assigned.
+type State = Option<HashMap<Ident, VarState>>;
+
+fn join(s1: State, s2: State) -> State {
+    match (s1, s2) {
+        (None, s) | (s, None) => s,
+        (Some(mut s1), Some(s2)) => {
+            for (ident, var2) in s2 {
+                if let Some(var1) = s1.get_mut(&ident) {
+                    *var1 = var1.join(var2);
+                }
+            }
+            Some(s1)
+        }
+    }
+}
+
+struct Analysis {
+    fields: Vec<Ident>,
+    report: bool, // false while looking for the fixpoint of a loop
+    warnings: Vec<Warning>,
+}
+
+impl Analysis {
+    fn stmts(&mut self, stmts: &[Stmt], state: State) -> State {
+        stmts.iter().fold(state, |state, stmt| self.stmt(stmt, state))
+    }
+
+    // Declarations inside the block hide outer variables until its end.
+    fn block<F>(&mut self, state: State, f: F) -> State
+        where F: FnOnce(&mut Analysis, State) -> (State, Vec<Ident>)
+    {
+        let outer = state.clone();
+        let (state, declared) = f(self, state);
+        match (state, outer) {
+            (Some(mut state), Some(outer)) => {
+                for ident in declared {
+                    match outer.get(&ident) {
+                        Some(var) => state.insert(ident, *var),
+                        None => state.remove(&ident),
+                    };
+                }
+                Some(state)
+            }
+            (state, _) => state,
+        }
+    }
+
+    fn scoped_stmts(&mut self, stmts: &Vec<Stmt>, state: State) -> State {
+        self.block(state, |analysis, state| {
+            (analysis.stmts(stmts, state), declared_in(stmts))
+        })
+    }
+
+    fn scoped_stmt(&mut self, stmt: &Stmt, state: State) -> State {
+        self.block(state, |analysis, state| {
+            (analysis.stmt(stmt, state), declared_in(Some(stmt)))
+        })
+    }
+
+    fn stmt(&mut self, stmt: &Stmt, state: State) -> State {
+        let mut s = state?;
+        let first_warning = self.warnings.len();
+        let res = match stmt.kind {
+            StmtKind::SEmpty => Some(s),
+            StmtKind::SBlock(ref stmts) => self.scoped_stmts(stmts, Some(s)),
+            StmtKind::SDecl(ref t, ref decls) => {
+                for decl in decls {
+                    let var = match *decl {
+                        VarDecl::Init(_, _, ref e) => {
+                            self.expr(e, &mut s);
+                            VarState {
+                                assigned: true,
+                                null: nullness(e, &s),
+                            }
+                        }
+                        VarDecl::NoInit(..) => {
+                            VarState {
+                                assigned: false,
+                                null: match is_nullable(t) {
+                                    true => Nullness::Null,
+                                    false => Nullness::Unknown,
+                                },
+                            }
+                        }
+                    };
+                    s.insert(decl.get_ident().clone(), var);
+                }
+                Some(s)
+            }
+            StmtKind::SAssign(ref field, ref e) => {
+                self.expr(e, &mut s);
+                match tracked_var(&Expr::EVar(field.clone()), &self.fields) {
+                    Some(ident) if s.contains_key(&ident) => {
+                        let null = nullness(e, &s);
+                        s.insert(ident,
+                                 VarState {
+                                     assigned: true,
+                                     null,
+                                 });
+                    }
+                    _ => self.field(field, &mut s),
+                }
+                Some(s)
+            }
+            StmtKind::SInc(ref field) |
+            StmtKind::SDec(ref field) => {
+                self.field(field, &mut s);
+                Some(s)
+            }
+            StmtKind::SReturnE(ref e) => {
+                self.expr(e, &mut s);
+                None
+            }
+            StmtKind::SReturn => None,
+            StmtKind::SExpr(ref e) => {
+                self.expr(e, &mut s);
+                Some(s)
+            }
+            StmtKind::SIf(ref cond, ref body) => {
+                self.expr(cond, &mut s);
+                let (if_true, if_false) = refine(cond, s);
+                let after_body = self.scoped_stmt(body, Some(if_true));
+                join(after_body, Some(if_false))
+            }
+            StmtKind::SIfElse(ref cond, ref s1, ref s2) => {
+                self.expr(cond, &mut s);
+                let (if_true, if_false) = refine(cond, s);
+                let after_s1 = self.scoped_stmt(s1, Some(if_true));
+                let after_s2 = self.scoped_stmt(s2, Some(if_false));
+                join(after_s1, after_s2)
+            }
+            StmtKind::SWhile(ref cond, ref body) => {
+                let s = self.fixpoint(s, |analysis, mut s| {
+                    analysis.expr(cond, &mut s);
+                    let (if_true, if_false) = refine(cond, s);
+                    (analysis.scoped_stmt(body, Some(if_true)), Some(if_false))
+                });
+                // the loop can be left only by return
+                match *cond {
+                    Expr::ELit(Lit::LTrue) => None,
+                    _ => s,
+                }
+            }
+            StmtKind::SFor(_, ref ident, ref e, ref body) => {
+                self.expr(e, &mut s);
+                self.fixpoint(s, |analysis, s| {
+                    let after_body = analysis.block(Some(s.clone()), |analysis, state| {
+                        let mut state = state.unwrap();
+                        // iteration over objects can give nulls
+                        state.insert(ident.clone(), VarState::field());
+                        (analysis.scoped_stmt(body, Some(state)), vec![ident.clone()])
+                    });
+                    (after_body, Some(s))
+                })
+            }
+            StmtKind::SSwitch(ref e, ref cases, ref default) => {
+                self.expr(e, &mut s);
+                for case in cases {
+                    for label in &case.labels {
+                        self.expr(label, &mut s);
+                    }
+                }
+                let mut res: State = match *default {
+                    Some(ref stmts) => self.scoped_stmts(stmts, Some(s.clone())),
+                    None => Some(s.clone()),
+                };
+                for case in cases {
+                    let after_case = self.scoped_stmts(&case.body, Some(s.clone()));
+                    res = join(res, after_case);
+                }
+                res
+            }
+        };
+        let place = format!("{}", stmt);
+        let new_warnings: Vec<Warning> = self.warnings.drain(first_warning..).collect();
+        for w in new_warnings {
+            let w = w.at_line(stmt.line);
+            self.warnings.push(match stmt.kind {
+                StmtKind::SBlock(..) | StmtKind::SIf(..) | StmtKind::SIfElse(..) |
+                StmtKind::SWhile(..) | StmtKind::SFor(..) | StmtKind::SSwitch(..) => w,
+                _ => w.wrapped(&place),
+            });
+        }
+        res
+    }
+
+    // Finds the state at the beginning of the loop, then analyzes the loop once more
+    // reporting warnings. f returns states after the body and after leaving the loop.
+    fn fixpoint<F>(&mut self, s: HashMap<Ident, VarState>, f: F) -> State
+        where F: Fn(&mut Analysis, HashMap<Ident, VarState>) -> (State, State)
+    {
+        let report = self.report;
+        self.report = false;
+        let mut begin = s;
+        loop {
+            let (after_body, _) = f(self, begin.clone());
+            let next = join(Some(begin.clone()), after_body).unwrap();
+            if next == begin {
+                break;
+            }
+            begin = next;
+        }
+        self.report = report;
+        f(self, begin).1
+    }
+
+    fn field(&mut self, field: &FieldGet, s: &mut HashMap<Ident, VarState>) {
+        match *field {
+            FieldGet::Direct(ref ident) => self.read(ident, s),
+            FieldGet::Indirect(ref e, _) => self.deref(e, s),
+            FieldGet::IdxAccess(ref e, ref idx) => {
+                self.deref(e, s);
+                self.expr(idx, s);
+            }
+        }
+    }
+
+    // Null dereference is reported instead of the read of an unassigned variable.
+    fn deref(&mut self, e: &Expr, s: &mut HashMap<Ident, VarState>) {
+        if let Some(ident) = tracked_var(e, &self.fields) {
+            if let Some(var) = s.get_mut(&ident) {
+                if var.null == Nullness::Null {
+                    if self.report {
+                        self.warnings.push(Warning::null_deref(&ident));
+                    }
+                    // reported once
+                    *var = VarState::field();
+                }
+            }
+        }
+        self.expr(e, s);
+    }
+
+    fn read(&mut self, ident: &Ident, s: &mut HashMap<Ident, VarState>) {
+        if let Some(var) = s.get_mut(ident) {
+            if !var.assigned {
+                if self.report {
+                    self.warnings.push(Warning::unassigned(ident));
+                }
+                // reported once
+                var.assigned = true;
+            }
+        }
+    }
+
+    fn expr(&mut self, e: &Expr, s: &mut HashMap<Ident, VarState>) {
+        match *e {
+            Expr::ELit(..) | Expr::ENew(..) => {}
+            Expr::EVar(ref field) => self.field(field, s),
+            Expr::ECall(ref field, ref args) => {
+                if let FieldGet::Indirect(ref obj, _) = *field {
+                    self.deref(obj, s);
+                }
+                for arg in args {
+                    self.expr(arg, s);
+                }
+                // methods can change fields
+                for field in &self.fields {
+                    if let Some(var) = s.get_mut(field) {
+                        var.null = Nullness::Unknown;
+                    }
+                }
+            }
+            Expr::ENeg(ref e) |
+            Expr::ENot(ref e) |
+            Expr::EBitNot(ref e) |
+            Expr::ECast(_, ref e) => self.expr(e, s),
+            Expr::EBinOp(ref lhs, _, ref rhs) => {
+                self.expr(lhs, s);
+                self.expr(rhs, s);
+            }
+            Expr::ENewArray(_, ref es) |
+            Expr::EArrayLit(ref es) => {
+                for e in es {
+                    self.expr(e, s);
+                }
+            }
+            Expr::ECond(ref cond, ref e1, ref e2) => {
+                self.expr(cond, s);
+                self.expr(e1, s);
+                self.expr(e2, s);
+            }
+        }
+    }
+}
+
+// States in which the condition is true and false, using comparisons with null.
+fn refine(cond: &Expr,
+          s: HashMap<Ident, VarState>)
+          -> (HashMap<Ident, VarState>, HashMap<Ident, VarState>) {
+    let (e, op) = match *cond {
+        Expr::EBinOp(ref lhs, op @ Operator::OpEq, ref rhs) |
+        Expr::EBinOp(ref lhs, op @ Operator::OpNEq, ref rhs) if is_null(rhs) => (lhs, op),
+        Expr::EBinOp(ref lhs, op @ Operator::OpEq, ref rhs) |
+        Expr::EBinOp(ref lhs, op @ Operator::OpNEq, ref rhs) if is_null(lhs) => (rhs, op),
+        _ => return (s.clone(), s),
+    };
+    let ident = match *e.as_ref() {
+        Expr::EVar(FieldGet::Direct(ref ident)) if s.contains_key(ident) => ident,
+        _ => return (s.clone(), s),
+    };
+    let (mut if_null, mut if_not_null) = (s.clone(), s);
+    if_null.get_mut(ident).unwrap().null = Nullness::Null;
+    if_not_null.get_mut(ident).unwrap().null = Nullness::NotNull;
+    match op {
+        Operator::OpEq => (if_null, if_not_null),
+        _ => (if_not_null, if_null),
+    }
+}
+
+fn nullness(e: &Expr, s: &HashMap<Ident, VarState>) -> Nullness {
+    match *e {
+        _ if is_null(e) => Nullness::Null,
+        Expr::ENew(..) | Expr::ENewArray(..) | Expr::EArrayLit(..) => Nullness::NotNull,
+        Expr::EVar(FieldGet::Direct(ref ident)) => {
+            s.get(ident).map(|var| var.null).unwrap_or(Nullness::Unknown)
+        }
+        _ => Nullness::Unknown,
+    }
+}
+
+fn is_null(e: &Expr) -> bool {
+    match *e {
+        Expr::ELit(Lit::LNull(..)) => true,
+        Expr::ECast(_, ref e) => is_null(e),
+        _ => false,
+    }
+}
+
+fn is_nullable(t: &Type) -> bool {
+    matches!(*t, Type::TObject(..) | Type::TStringBuilder | Type::TArray(..) | Type::TList(..) |
+        Type::TMap(..))
+}
+
+// self.field is the same as field
+fn tracked_var(e: &Expr, fields: &[Ident]) -> Option<Ident> {
+    match *e {
+        Expr::EVar(FieldGet::Direct(ref ident)) => Some(ident.clone()),
+        Expr::EVar(FieldGet::Indirect(ref obj, ref field)) if fields.contains(field) => {
+            match **obj {
+                Expr::EVar(FieldGet::Direct(Ident(ref name))) if name == "self" => {
+                    Some(field.clone())
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn declared_in<'a, I>(stmts: I) -> Vec<Ident>
+    where I: IntoIterator<Item = &'a Stmt>
+{
+    let mut res = Vec::new();
+    for stmt in stmts {
+        if let StmtKind::SDecl(_, ref decls) = stmt.kind {
+            res.extend(decls.iter().map(|d| d.get_ident().clone()));
+        }
+    }
+    res
+}
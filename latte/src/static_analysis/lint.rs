@@ -0,0 +1,319 @@
+use std::collections::{HashMap, HashSet};
+
+use ast::*;
+use static_analysis::warning::Warning;
+
+// Reports unused variables, shadowing declarations and expression statements
+// without effects. Unreachable code and constant conditions are reported
+// by the optimizer, which folds and removes them.
+pub fn run(p: &Program) -> Vec<Warning> {
+    let mut globals: HashSet<Ident> = HashSet::new();
+    let mut classes: HashMap<&Ident, &Class> = HashMap::new();
+    for def in &p.0 {
+        match *def {
+            Def::DGlobal(ref g) => {
+                globals.extend(g.decls.iter().map(|d| d.get_ident().clone()));
+            }
+            Def::DClass(ref c) => {
+                classes.insert(&c.name, c);
+            }
+            Def::DFunc(..) | Def::DEnum(..) | Def::DExtern(..) => {}
+        }
+    }
+
+    let mut warnings: Vec<Warning> = Vec::new();
+    for def in &p.0 {
+        match *def {
+            Def::DFunc(ref f) => {
+                let mut lint = Lint::new(&globals, HashSet::new());
+                warnings.extend(lint.check_func(f));
+            }
+            Def::DClass(ref c) => {
+                let place = format!("class {}\n", c.name);
+                let static_fields = get_fields(c, &classes, true);
+                for f in &c.static_methods {
+                    let mut lint = Lint::new(&globals, static_fields.clone());
+                    warnings.extend(lint.check_func(f).into_iter().map(|w| w.wrapped(&place)));
+                }
+                let mut fields = static_fields;
+                fields.extend(get_fields(c, &classes, false));
+                for f in &c.methods {
+                    let mut lint = Lint::new(&globals, fields.clone());
+                    warnings.extend(lint.check_func(f).into_iter().map(|w| w.wrapped(&place)));
+                }
+            }
+            Def::DGlobal(..) | Def::DEnum(..) | Def::DExtern(..) => {}
+        }
+    }
+    warnings
+}
+
+// fields of the class and its superclasses
+fn get_fields(c: &Class, classes: &HashMap<&Ident, &Class>, is_static: bool) -> HashSet<Ident> {
+    let mut fields: HashSet<Ident> = match is_static {
+        true => {
+            c.static_vars
+                .iter()
+                .flat_map(|g| g.decls.iter().map(|d| d.get_ident().clone()))
+                .collect()
+        }
+        false => c.vars.iter().map(|v| v.ident.clone()).collect(),
+    };
+    if let Some(ref super_name) = c.superclass {
+        if let Some(superclass) = classes.get(super_name) {
+            fields.extend(get_fields(superclass, classes, is_static));
+        }
+    }
+    fields
+}
+
+struct LocalVar {
+    ident: Ident,
+    is_arg: bool,
+    used: bool,
+    line: i32,
+}
+
+struct Lint<'a> {
+    globals: &'a HashSet<Ident>,
+    fields: HashSet<Ident>,
+    scopes: Vec<Vec<LocalVar>>,
+    warnings: Vec<Warning>,
+    line: i32, // of the checked statement
+}
+
+impl<'a> Lint<'a> {
+    fn new(globals: &'a HashSet<Ident>, fields: HashSet<Ident>) -> Lint<'a> {
+        Lint {
+            globals,
+            fields,
+            scopes: Vec::new(),
+            warnings: Vec::new(),
+            line: 0,
+        }
+    }
+
+    fn check_func(&mut self, f: &Func) -> Vec<Warning> {
+        self.line = f.line;
+        self.scopes.push(Vec::new());
+        for arg in &f.args {
+            self.declare(&arg.ident, true, None);
+        }
+        self.in_new_scope(|lint| lint.check_stmts(&f.body));
+        self.end_scope();
+        let place = format!("function {}\n", f.ident);
+        self.warnings.drain(..).map(|w| w.wrapped(&place)).collect()
+    }
+
+    fn in_new_scope<F>(&mut self, f: F)
+        where F: FnOnce(&mut Lint)
+    {
+        self.scopes.push(Vec::new());
+        f(self);
+        self.end_scope();
+    }
+
+    fn end_scope(&mut self) {
+        for var in self.scopes.pop().unwrap() {
+            if !var.used {
+                let warning = match var.is_arg {
+                    true => Warning::unused_arg(&var.ident),
+                    false => Warning::unused_var(&var.ident),
+                };
+                self.warnings.push(warning.at_line(var.line));
+            }
+        }
+    }
+
+    fn declare(&mut self, ident: &Ident, is_arg: bool, place: Option<String>) {
+        if let Some(what) = self.shadowed(ident) {
+            let warning = Warning::shadows(ident, what).at_line(self.line);
+            self.warnings.push(match place {
+                Some(place) => warning.wrapped(&place),
+                None => warning,
+            });
+        }
+        self.scopes.last_mut().unwrap().push(LocalVar {
+            ident: ident.clone(),
+            is_arg,
+            used: false,
+            line: self.line,
+        });
+    }
+
+    // only names from outer scopes, redeclaration in the same scope is an error
+    fn shadowed(&self, ident: &Ident) -> Option<&'static str> {
+        let outer = &self.scopes[..self.scopes.len() - 1];
+        for var in outer.iter().flat_map(|scope| scope.iter()) {
+            if var.ident == *ident {
+                return Some(if var.is_arg { "parameter" } else { "local variable" });
+            }
+        }
+        if self.fields.contains(ident) {
+            Some("field")
+        } else if self.globals.contains(ident) {
+            Some("global variable")
+        } else {
+            None
+        }
+    }
+
+    fn use_var(&mut self, ident: &Ident) {
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(var) = scope.iter_mut().rev().find(|var| var.ident == *ident) {
+                var.used = true;
+                return;
+            }
+        }
+    }
+
+    fn check_stmts(&mut self, stmts: &Vec<Stmt>) {
+        for stmt in stmts {
+            self.check_stmt(stmt);
+        }
+    }
+
+    fn check_stmt(&mut self, stmt: &Stmt) {
+        self.line = stmt.line;
+        match stmt.kind {
+            StmtKind::SEmpty | StmtKind::SReturn => {}
+            StmtKind::SBlock(ref stmts) => self.in_new_scope(|lint| lint.check_stmts(stmts)),
+            StmtKind::SDecl(_, ref decls) => {
+                for decl in decls {
+                    if let VarDecl::Init(_, _, ref e) = *decl {
+                        self.check_expr(e);
+                    }
+                    self.declare(decl.get_ident(), false, Some(format!("{}", stmt)));
+                }
+            }
+            // assignment alone does not make a variable used
+            StmtKind::SAssign(ref field, ref e) => {
+                self.check_assigned(field);
+                self.check_expr(e);
+            }
+            StmtKind::SInc(ref field) |
+            StmtKind::SDec(ref field) => self.check_assigned(field),
+            StmtKind::SReturnE(ref e) => self.check_expr(e),
+            StmtKind::SExpr(ref e) => {
+                self.check_expr(e);
+                if !has_effect(e) {
+                    self.warnings.push(Warning::no_effect().at_line(stmt.line).wrapped(stmt));
+                }
+            }
+            StmtKind::SIf(ref cond, ref s) |
+            StmtKind::SWhile(ref cond, ref s) => {
+                self.check_expr(cond);
+                self.check_nested(s);
+            }
+            StmtKind::SIfElse(ref cond, ref s1, ref s2) => {
+                self.check_expr(cond);
+                self.check_nested(s1);
+                self.check_nested(s2);
+            }
+            StmtKind::SFor(ref t, ref ident, ref e, ref s) => {
+                self.check_expr(e);
+                self.in_new_scope(|lint| {
+                    let place = format!("for ({} {} : {})\n", t, ident, e);
+                    lint.declare(ident, false, Some(place));
+                    lint.check_nested(s);
+                });
+            }
+            StmtKind::SSwitch(ref e, ref cases, ref default) => {
+                self.check_expr(e);
+                for case in cases {
+                    for label in &case.labels {
+                        self.check_expr(label);
+                    }
+                    self.in_new_scope(|lint| lint.check_stmts(&case.body));
+                }
+                if let Some(ref stmts) = *default {
+                    self.in_new_scope(|lint| lint.check_stmts(stmts));
+                }
+            }
+        }
+    }
+
+    fn check_nested(&mut self, s: &Stmt) {
+        self.in_new_scope(|lint| lint.check_stmt(s));
+    }
+
+    fn check_assigned(&mut self, field: &FieldGet) {
+        match *field {
+            FieldGet::Direct(..) => {}
+            _ => self.check_field(field),
+        }
+    }
+
+    fn check_field(&mut self, field: &FieldGet) {
+        match *field {
+            FieldGet::Direct(ref ident) => self.use_var(ident),
+            FieldGet::Indirect(ref e, _) => self.check_expr(e),
+            FieldGet::IdxAccess(ref e, ref idx) => {
+                self.check_expr(e);
+                self.check_expr(idx);
+            }
+        }
+    }
+
+    fn check_expr(&mut self, e: &Expr) {
+        match *e {
+            Expr::ELit(..) => {}
+            Expr::EVar(ref field) => self.check_field(field),
+            Expr::ECall(ref field, ref args) => {
+                if let FieldGet::Indirect(ref obj, _) = *field {
+                    self.check_expr(obj);
+                }
+                for arg in args {
+                    self.check_expr(arg);
+                }
+            }
+            Expr::ENeg(ref e) |
+            Expr::ENot(ref e) |
+            Expr::EBitNot(ref e) |
+            Expr::ECast(_, ref e) => self.check_expr(e),
+            Expr::EBinOp(ref lhs, _, ref rhs) => {
+                self.check_expr(lhs);
+                self.check_expr(rhs);
+            }
+            Expr::ENew(..) => {}
+            Expr::ENewArray(_, ref es) |
+            Expr::EArrayLit(ref es) => {
+                for e in es {
+                    self.check_expr(e);
+                }
+            }
+            Expr::ECond(ref cond, ref e1, ref e2) => {
+                self.check_expr(cond);
+                self.check_expr(e1);
+                self.check_expr(e2);
+            }
+        }
+    }
+}
+
+// Only calls can have side effects.
+fn has_effect(e: &Expr) -> bool {
+    match *e {
+        Expr::ECall(..) => true,
+        Expr::ELit(..) | Expr::ENew(..) => false,
+        Expr::EVar(ref field) => field_has_effect(field),
+        Expr::ENeg(ref e) |
+        Expr::ENot(ref e) |
+        Expr::EBitNot(ref e) |
+        Expr::ECast(_, ref e) => has_effect(e),
+        Expr::EBinOp(ref lhs, _, ref rhs) => has_effect(lhs) || has_effect(rhs),
+        Expr::ENewArray(_, ref es) |
+        Expr::EArrayLit(ref es) => es.iter().any(has_effect),
+        Expr::ECond(ref cond, ref e1, ref e2) => {
+            has_effect(cond) || has_effect(e1) || has_effect(e2)
+        }
+    }
+}
+
+fn field_has_effect(field: &FieldGet) -> bool {
+    match *field {
+        FieldGet::Direct(..) => false,
+        FieldGet::Indirect(ref e, _) => has_effect(e),
+        FieldGet::IdxAccess(ref e, ref idx) => has_effect(e) || has_effect(idx),
+    }
+}
@@ -1,6 +1,10 @@
 use ast::Program;
 
+use self::warning::Warning;
+
 mod class_hierarchy_check;
+mod init_check;
+mod lint;
 mod result;
 mod return_check;
 mod return_error;
@@ -10,13 +14,20 @@ mod type_error;
 
 pub mod collect_string_lit;
 pub mod has_return;
+pub mod warning;
 
-pub fn run(p: &Program) -> result::Result {
+pub fn run(p: &Program) -> result::Result<Vec<Warning>> {
     class_hierarchy_check::run(p).map_err(|e| result::Error::Class(e))?;
-    type_check::run(p).map_err(|e| result::Error::Type(e))?;
-    Ok(())
+    let mut warnings = type_check::run(p).map_err(result::Error::Type)?;
+    warnings.extend(lint::run(p));
+    Ok(warnings)
 }
 
 pub fn check_returns(p: &Program) -> result::Result {
     return_check::run(p).map_err(|e| result::Error::Return(e))
 }
+
+// Optional dataflow analysis, reported as -Wuninitialized and -Wnull-deref warnings.
+pub fn check_init(p: &Program) -> Vec<Warning> {
+    init_check::run(p)
+}
@@ -3,7 +3,7 @@ use std::{self, fmt};
 use static_analysis::type_error::TypeError;
 use static_analysis::return_error::ReturnError;
 
-pub type Result = std::result::Result<(), Error>;
+pub type Result<T = ()> = std::result::Result<T, Error>;
 
 #[derive(Debug)]
 pub enum Error {
@@ -12,12 +12,15 @@ pub fn run(p: &Program) -> Result<(), ReturnError> {
                 }
             }
             Def::DClass(ref c) => {
-                for m in &c.methods {
+                for m in c.methods.iter().chain(&c.static_methods) {
                     if !m.has_return() {
                         return Err(ReturnError::method(&c.name, &m.ident));
                     }
                 }
             }
+            Def::DGlobal(..) |
+            Def::DEnum(..) |
+            Def::DExtern(..) => {}
         };
     }
     Ok(())
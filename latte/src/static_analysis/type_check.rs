@@ -5,13 +5,14 @@ use ast::*;
 use builtins::*;
 use static_analysis::type_context::TypeContext;
 use static_analysis::type_error::TypeError;
+use static_analysis::warning::Warning;
 
 type TypeResult<T> = Result<T, TypeError>;
 
-pub fn run(p: &Program) -> Result<(), TypeError> {
+pub fn run(p: &Program) -> Result<Vec<Warning>, TypeError> {
     let ctx: TypeContext = TypeContext::new();
-    let res = ctx.in_new_scope(|mut ctx| p.check_types(&mut ctx));
-    res.map(|_| ())
+    ctx.in_new_scope(|mut ctx| p.check_types(&mut ctx))?;
+    Ok(ctx.take_warnings())
 }
 
 trait HasType<Ret, Context> {
@@ -36,7 +37,10 @@ impl<'a> HasType<(), &'a mut TypeContext> for Program {
             add_ident(&builtin.ident, &builtin.get_type(), &mut ctx)?;
         }
 
-        let (classes, functions) = divide_definitions(&self.0);
+        let (classes, functions, globals, enums) = divide_definitions(&self.0);
+        for e in &enums {
+            add_enum(e, ctx)?;
+        }
         for c in &classes {
             add_class(c, &mut ctx)?;
         }
@@ -48,6 +52,16 @@ impl<'a> HasType<(), &'a mut TypeContext> for Program {
             f.check_signature(ctx)?;
             add_ident(&f.ident, &f.get_type(), &mut ctx)?;
         }
+        for def in &self.0 {
+            if let Def::DExtern(ref f) = *def {
+                check_extern(f)?;
+                add_ident(&f.ident, &f.get_type(), ctx)?;
+            }
+        }
+
+        for g in &globals {
+            g.check_types(ctx)?;
+        }
 
         for def in &self.0 {
             def.check_types(ctx)?;
@@ -57,43 +71,112 @@ impl<'a> HasType<(), &'a mut TypeContext> for Program {
     }
 }
 
-fn divide_definitions<'a>(defs: &'a Vec<Def>) -> (Vec<&'a Class>, Vec<&'a Func>) {
+fn divide_definitions<'a>(defs: &'a Vec<Def>)
+                          -> (Vec<&'a Class>, Vec<&'a Func>, Vec<&'a Global>, Vec<&'a Enum>) {
     let mut classes: Vec<&'a Class> = Vec::new();
     let mut functions: Vec<&'a Func> = Vec::new();
+    let mut globals: Vec<&'a Global> = Vec::new();
+    let mut enums: Vec<&'a Enum> = Vec::new();
     for def in defs {
         match *def {
             Def::DClass(ref c) => classes.push(c),
             Def::DFunc(ref f) => functions.push(f),
+            Def::DGlobal(ref g) => globals.push(g),
+            Def::DEnum(ref e) => enums.push(e),
+            Def::DExtern(..) => {}
+        }
+    }
+    (classes, functions, globals, enums)
+}
+
+// Names starting with _ are used by the runtime, as are these functions of the C library,
+// so extern functions with other types would be linked with them.
+const RUNTIME_SYMBOLS: &[&str] = &["calloc", "exit", "fclose", "fgetc", "fopen", "fputc", "fputs",
+                                   "fread", "free", "fseek", "ftell", "getc", "getchar",
+                                   "getenv", "getline", "malloc", "memcpy", "memmove", "printf",
+                                   "puts", "realloc", "scanf", "stderr", "stdin", "stdout",
+                                   "strcmp", "strcpy", "strlen", "ungetc"];
+
+// Only values with a C representation can be passed to extern functions:
+// primitives, strings (as char*) and arrays of numbers, chars and booleans.
+fn check_extern(f: &BuiltinFunc) -> TypeResult<()> {
+    do_check_extern(f).map_err(|e| e.wrapped(&format!("extern declaration {}\n", f.ident)))
+}
+
+fn do_check_extern(f: &BuiltinFunc) -> TypeResult<()> {
+    if f.ident.0.starts_with('_') || RUNTIME_SYMBOLS.contains(&f.ident.0.as_str()) {
+        return Err(TypeError::reserved_extern_name(&f.ident));
+    }
+    for t in &f.args {
+        match *t {
+            Type::TArray(ref elem_t) if is_extern_primitive(elem_t) => {}
+            Type::TString => {}
+            _ if is_extern_primitive(t) => {}
+            _ => return Err(TypeError::invalid_extern_type(t)),
+        }
+    }
+    match f.ret_type {
+        Type::TVoid | Type::TString => Ok(()),
+        ref t if is_extern_primitive(t) => Ok(()),
+        ref t => Err(TypeError::invalid_extern_type(t)),
+    }
+}
+
+fn is_extern_primitive(t: &Type) -> bool {
+    matches!(*t, Type::TInt | Type::TLong | Type::TDouble | Type::TChar | Type::TBool)
+}
+
+fn add_enum(e: &Enum, ctx: &mut TypeContext) -> TypeResult<()> {
+    if ctx.get_type(&e.name).is_some() || ctx.enum_exists(&e.name) {
+        return Err(TypeError::name_already_defined(&e.name));
+    }
+    for (i, variant) in e.variants.iter().enumerate() {
+        if e.variants[..i].contains(variant) {
+            return Err(TypeError::variant_already_defined(&e.name, variant));
         }
     }
-    (classes, functions)
+    ctx.add_enum(&e.name, &e.variants);
+    Ok(())
 }
 
 fn add_class(c: &Class, ctx: &mut TypeContext) -> TypeResult<()> {
-    if ctx.get_type(&c.name).is_some() {
+    if ctx.get_type(&c.name).is_some() || ctx.enum_exists(&c.name) {
         return Err(TypeError::name_already_defined(&c.name));
     }
 
     let mut fields: HashMap<Ident, Type> = HashMap::new();
-    for v in &c.vars {
-        if fields.contains_key(&v.ident) {
-            return Err(TypeError::field_already_defined(&c.name, &v.ident));
+    let mut static_fields: HashMap<Ident, Type> = HashMap::new();
+    let mut access: HashMap<Ident, Access> = HashMap::new();
+    let vars = c.vars.iter().map(|v| (&v.ident, v.get_type(), v.access));
+    let methods = c.methods.iter().map(|f| (&f.ident, f.get_type(), f.access));
+    for (ident, t, a) in vars.chain(methods) {
+        if fields.contains_key(ident) {
+            return Err(TypeError::field_already_defined(&c.name, ident));
         }
-        fields.insert(v.ident.clone(), v.get_type());
-    }
-    for f in &c.methods {
-        if fields.contains_key(&f.ident) {
-            return Err(TypeError::field_already_defined(&c.name, &f.ident));
+        fields.insert(ident.clone(), t);
+        access.insert(ident.clone(), a);
+    }
+    let static_vars = c.static_vars
+        .iter()
+        .flat_map(|g| g.decls.iter().map(move |d| (d.get_ident(), g.t.clone(), g.access)));
+    let static_methods = c.static_methods.iter().map(|f| (&f.ident, f.get_type(), f.access));
+    for (ident, t, a) in static_vars.chain(static_methods) {
+        if fields.contains_key(ident) || static_fields.contains_key(ident) {
+            return Err(TypeError::field_already_defined(&c.name, ident));
         }
-        fields.insert(f.ident.clone(), f.get_type());
+        static_fields.insert(ident.clone(), t);
+        access.insert(ident.clone(), a);
     }
-    ctx.add_class(&c.name, &c.superclass, fields);
+    ctx.add_class(&c.name, &c.superclass, fields, static_fields, access);
     Ok(())
 }
 
+// int main() or int main(string[] args)
 fn check_main_function(ctx: &TypeContext) -> TypeResult<()> {
     if let Some(func_type) = ctx.get_type(&Ident(format!("main"))) {
-        if *func_type != Type::TFunc(vec![], Box::new(Type::TInt)) {
+        let args_t = Type::TArray(Box::new(Type::TString));
+        if *func_type != Type::TFunc(vec![], Box::new(Type::TInt)) &&
+           *func_type != Type::TFunc(vec![args_t], Box::new(Type::TInt)) {
             Err(TypeError::invalid_main_type())
         } else {
             Ok(())
@@ -108,10 +191,38 @@ impl<'a> HasType<(), &'a TypeContext> for Def {
         match *self {
             Def::DClass(ref c) => c.check_types(ctx),
             Def::DFunc(ref f) => f.check_types(ctx),
+            // globals are added to the context before function bodies are checked
+            Def::DGlobal(..) => Ok(()),
+            Def::DEnum(..) => Ok(()),
+            Def::DExtern(..) => Ok(()),
         }
     }
 }
 
+// Initializers are checked in declaration order, so they can only use
+// globals declared earlier.
+impl HasType<(), &mut TypeContext> for Global {
+    fn check_types(&self, ctx: &mut TypeContext) -> TypeResult<()> {
+        self.do_check_types(ctx).map_err(|e| e.wrapped(self))
+    }
+
+    fn do_check_types(&self, ctx: &mut TypeContext) -> TypeResult<()> {
+        expect_declarable_type(&self.t, ctx)?;
+        for decl in &self.decls {
+            decl.check_types(ctx)?;
+            if self.is_const {
+                match *decl {
+                    VarDecl::Init(_, ref ident, _) => ctx.set_const(ident),
+                    VarDecl::NoInit(_, ref ident) => {
+                        return Err(TypeError::const_without_init(ident))
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 impl Class {
     fn check_fields(&self, ctx: &TypeContext) -> TypeResult<()> {
         self.do_check_fields(ctx).map_err(|e| e.wrapped(&format!("class {}\n", self.name)))
@@ -124,7 +235,10 @@ impl Class {
                 }
                 Ok(())
             })?;
-        for f in &self.methods {
+        for g in &self.static_vars {
+            expect_declarable_type(&g.t, ctx)?;
+        }
+        for f in self.methods.iter().chain(&self.static_methods) {
             f.check_signature(ctx)?;
         }
 
@@ -142,6 +256,31 @@ impl Class {
                     if actual != &expected {
                         return Err(TypeError::invalid_override(&f.ident, &expected, &actual));
                     }
+                    let (class, access) = ctx.get_access(superclass, &f.ident).unwrap();
+                    if f.access < access {
+                        return Err(TypeError::weaker_access(&f.ident, f.access, class, access));
+                    }
+                }
+            }
+
+            // static members cannot be overridden and cannot hide inherited members
+            let static_idents = self.static_vars
+                .iter()
+                .flat_map(|g| g.decls.iter().map(VarDecl::get_ident))
+                .chain(self.static_methods.iter().map(|f| &f.ident));
+            for ident in static_idents {
+                if ctx.get_field_type(superclass, ident).is_some() ||
+                   ctx.get_static_type(superclass, ident).is_some() {
+                    return Err(TypeError::static_override(ident));
+                }
+            }
+            let idents = self.vars
+                .iter()
+                .map(|v| &v.ident)
+                .chain(self.methods.iter().map(|f| &f.ident));
+            for ident in idents {
+                if ctx.get_static_type(superclass, ident).is_some() {
+                    return Err(TypeError::static_override(ident));
                 }
             }
         }
@@ -163,6 +302,19 @@ impl<'a> HasType<(), &'a TypeContext> for Class {
                 ctx.in_function_scope(&f.ret_type, |mut ctx| f.check_types(&mut ctx))?;
             }
             Ok(())
+        })?;
+        ctx.in_static_scope(&self.name, |ctx| {
+            for g in &self.static_vars {
+                for decl in &g.decls {
+                    if let VarDecl::Init(ref t, _, ref e) = *decl {
+                        check_init_types(t, e, &ctx).map_err(|e| e.wrapped(g))?;
+                    }
+                }
+            }
+            for f in &self.static_methods {
+                f.check_types(&ctx)?;
+            }
+            Ok(())
         })
     }
 }
@@ -229,57 +381,63 @@ impl<'a> HasType<(), &'a mut TypeContext> for Stmt {
     }
 
     fn do_check_types(&self, ctx: &mut TypeContext) -> TypeResult<()> {
-        match *self {
-            Stmt::SEmpty => {}
-            Stmt::SBlock(ref stmts) => {
+        match self.kind {
+            StmtKind::SEmpty => {}
+            StmtKind::SBlock(ref stmts) => {
                 ctx.in_new_scope(|mut ctx| stmts.check_types(&mut ctx))?;
             }
-            Stmt::SDecl(ref t, ref decls) => {
+            StmtKind::SDecl(ref t, ref decls) => {
                 expect_declarable_type(t, ctx)?;
                 for decl in decls {
                     decl.check_types(ctx)?;
                 }
             }
-            Stmt::SAssign(ref ident, ref expr) => {
+            StmtKind::SAssign(ref ident, ref expr) => {
+                expect_mutable(ident, ctx)?;
                 let itype = ident.check_types(ctx)?;
-                let etype = expr.check_types(ctx)?;
-                expect_type(&itype, &etype, ctx)?;
+                check_init_types(&itype, expr, ctx)?;
             }
-            Stmt::SInc(ref ident) |
-            Stmt::SDec(ref ident) => {
+            StmtKind::SInc(ref ident) |
+            StmtKind::SDec(ref ident) => {
+                expect_mutable(ident, ctx)?;
                 let itype = ident.check_types(ctx)?;
-                expect_type(&Type::TInt, &itype, ctx)?;
+                if itype != Type::TLong {
+                    expect_type(&Type::TInt, &itype, ctx)?;
+                }
             }
-            Stmt::SReturnE(ref expr) => {
-                let etype = expr.check_types(ctx)?;
-                expect_type(ctx.get_ret_type(), &etype, ctx)?;
+            StmtKind::SReturnE(ref expr) => {
+                check_init_types(ctx.get_ret_type(), expr, ctx)?;
                 if ctx.get_ret_type() == &Type::TVoid {
                     return Err(TypeError::return_void_expr());
                 }
             }
-            Stmt::SReturn => {
+            StmtKind::SReturn => {
                 expect_type(ctx.get_ret_type(), &Type::TVoid, ctx)?;
             }
-            Stmt::SExpr(ref expr) => {
+            StmtKind::SExpr(ref expr) => {
                 expr.check_types(ctx)?;
             }
-            Stmt::SIf(ref expr, ref stmt) |
-            Stmt::SWhile(ref expr, ref stmt) => {
+            StmtKind::SIf(ref expr, ref stmt) |
+            StmtKind::SWhile(ref expr, ref stmt) => {
                 let etype = expr.check_types(ctx)?;
                 expect_type(&Type::TBool, &etype, ctx)?;
                 ctx.in_new_scope(|mut ctx| stmt.check_types(&mut ctx))?;
             }
-            Stmt::SIfElse(ref expr, ref if_t, ref if_f) => {
+            StmtKind::SIfElse(ref expr, ref if_t, ref if_f) => {
                 let etype = expr.check_types(ctx)?;
                 expect_type(&Type::TBool, &etype, ctx)?;
                 ctx.in_new_scope(|mut ctx| if_t.check_types(&mut ctx))?;
                 ctx.in_new_scope(|mut ctx| if_f.check_types(&mut ctx))?;
             }
-            Stmt::SFor(ref t, ref ident, ref e, ref stmt) => {
+            StmtKind::SFor(ref t, ref ident, ref e, ref stmt) => {
                 let e_type = e.check_types(ctx)?;
                 expect_declarable_type(&e_type, ctx)?;
                 if let Type::TArray(ref elem_t) = e_type {
                     expect_type(t, &**elem_t, ctx)?;
+                } else if let Type::TList(ref elem_t) = e_type {
+                    expect_type(t, elem_t, ctx)?;
+                } else if let Type::TMap(ref key_t, _) = e_type {
+                    expect_type(t, key_t, ctx)?;
                 } else {
                     expect_type(&Type::TArray(Box::new(t.clone())), &e_type, ctx)?;
                 }
@@ -289,11 +447,72 @@ impl<'a> HasType<(), &'a mut TypeContext> for Stmt {
                         Ok(())
                     })?;
             }
+            StmtKind::SSwitch(ref e, ref cases, ref default) => {
+                let e_type = e.check_types(ctx)?;
+                match e_type {
+                    Type::TInt | Type::TString | Type::TEnum(..) => {}
+                    _ => return Err(TypeError::invalid_switch_type(&e_type)),
+                }
+                let mut labels: Vec<&Expr> = Vec::new();
+                for case in cases {
+                    for label in &case.labels {
+                        let label_type = label.check_types(ctx)?;
+                        expect_type(&e_type, &label_type, ctx)?;
+                        if !is_constant(label, ctx) {
+                            return Err(TypeError::non_constant_label(label));
+                        }
+                        let label_str = format!("{}", label);
+                        if labels.iter().any(|l| format!("{}", l) == label_str) {
+                            return Err(TypeError::duplicate_label(label));
+                        }
+                        labels.push(label);
+                    }
+                    ctx.in_new_scope(|mut ctx| case.body.check_types(&mut ctx))?;
+                }
+                if let Some(ref stmts) = *default {
+                    ctx.in_new_scope(|mut ctx| stmts.check_types(&mut ctx))?;
+                } else if let Type::TEnum(ref name) = e_type {
+                    check_exhaustive(name, &labels, e, self.line, ctx);
+                }
+            }
         };
         Ok(())
     }
 }
 
+// Labels are literals, constants or enum variants, which makes them known
+// at compile time after optimization.
+fn is_constant(e: &Expr, ctx: &TypeContext) -> bool {
+    match *e {
+        Expr::ELit(..) => true,
+        Expr::ENeg(ref e) => is_constant(e, ctx),
+        Expr::EVar(FieldGet::Direct(ref ident)) => ctx.is_const(ident),
+        Expr::EVar(FieldGet::Indirect(ref e, _)) => as_enum_name(e, ctx).is_some(),
+        _ => false,
+    }
+}
+
+fn check_exhaustive(enum_name: &Ident,
+                    labels: &Vec<&Expr>,
+                    e: &Expr,
+                    line: i32,
+                    ctx: &TypeContext) {
+    let missing: Vec<&Ident> = ctx.get_enum_variants(enum_name)
+        .iter()
+        .filter(|variant| {
+            !labels.iter().any(|label| match **label {
+                Expr::EVar(FieldGet::Indirect(_, ref v)) => v == *variant,
+                _ => false,
+            })
+        })
+        .collect();
+    if !missing.is_empty() {
+        ctx.warn(Warning::non_exhaustive_switch(enum_name, &missing)
+            .at_line(line)
+            .wrapped(&format!("switch ({})\n", e)));
+    }
+}
+
 impl<'a> HasType<(), &'a mut TypeContext> for VarDecl {
     fn check_types(&self, ctx: &mut TypeContext) -> TypeResult<()> {
         self.do_check_types(ctx).map_err(|e| e.wrapped(&format!("{}\n", self)))
@@ -302,8 +521,7 @@ impl<'a> HasType<(), &'a mut TypeContext> for VarDecl {
     fn do_check_types(&self, ctx: &mut TypeContext) -> TypeResult<()> {
         match *self {
             VarDecl::Init(ref t, ref ident, ref expr) => {
-                let etype = expr.check_types(ctx)?;
-                expect_type(t, &etype, ctx)?;
+                check_init_types(t, expr, ctx)?;
                 add_ident(ident, t, ctx)?;
             }
             VarDecl::NoInit(ref t, ref ident) => {
@@ -324,24 +542,25 @@ impl<'a> HasType<Type, &'a TypeContext> for Expr {
             Expr::EVar(ref ident) => ident.check_types(ctx),
             Expr::ELit(ref l) => l.check_types(ctx),
             Expr::ECall(ref f, ref args) => check_call_types(f, args, ctx),
-            Expr::ENeg(ref e) => expect_type(&Type::TInt, &e.check_types(ctx)?, ctx),
+            Expr::ENeg(ref e) => {
+                let t = e.check_types(ctx)?;
+                match is_numeric(&t) {
+                    true => Ok(t),
+                    false => Err(TypeError::no_neg_operator(&t)),
+                }
+            }
             Expr::ENot(ref e) => expect_type(&Type::TBool, &e.check_types(ctx)?, ctx),
+            Expr::EBitNot(ref e) => expect_type(&Type::TInt, &e.check_types(ctx)?, ctx),
             Expr::EBinOp(ref lhs, ref op, ref rhs) => {
                 let lhs_t = lhs.check_types(ctx)?;
                 let rhs_t = rhs.check_types(ctx)?;
                 match *op {
                     Operator::OpAdd => check_add_types(lhs_t, rhs_t),
                     Operator::OpSub | Operator::OpMul | Operator::OpDiv | Operator::OpMod => {
-                        expect_type(&Type::TInt, &lhs_t, ctx)?;
-                        expect_type(&Type::TInt, &rhs_t, ctx)?;
-                        Ok(Type::TInt)
+                        check_arith_types(*op, lhs_t, rhs_t)
                     }
                     Operator::OpLess | Operator::OpLessE | Operator::OpGreater |
-                    Operator::OpGreaterE => {
-                        expect_type(&Type::TInt, &lhs_t, ctx)?;
-                        expect_type(&Type::TInt, &rhs_t, ctx)?;
-                        Ok(Type::TBool)
-                    }
+                    Operator::OpGreaterE => check_cmp_types(*op, lhs_t, rhs_t),
                     Operator::OpEq | Operator::OpNEq => {
                         check_eq_types(lhs_t, rhs_t, ctx).map(|_| Type::TBool)
                     }
@@ -350,31 +569,95 @@ impl<'a> HasType<Type, &'a TypeContext> for Expr {
                         expect_type(&Type::TBool, &rhs_t, ctx)?;
                         Ok(Type::TBool)
                     }
+                    Operator::OpBitAnd | Operator::OpBitOr | Operator::OpBitXor |
+                    Operator::OpShl | Operator::OpShr => check_bitwise_types(*op, lhs_t, rhs_t),
                 }
             }
             Expr::ENew(ref t) => {
                 expect_declarable_type(t, ctx)?;
-                if let Type::TObject(..) = *t {
-                    Ok(t.clone())
-                } else {
-                    Err(TypeError::invalid_new(t))
+                match *t {
+                    Type::TObject(..) | Type::TStringBuilder | Type::TList(..) | Type::TMap(..) => {
+                        Ok(t.clone())
+                    }
+                    _ => Err(TypeError::invalid_new(t)),
                 }
             }
-            Expr::ENewArray(ref t, ref size) => {
+            Expr::ENewArray(ref t, ref sizes) => {
                 expect_declarable_type(t, ctx)?;
-                expect_type(&Type::TInt, &size.check_types(ctx)?, ctx)?;
-                Ok(Type::TArray(Box::new(t.clone())))
+                let mut arr_t = t.clone();
+                for size in sizes {
+                    expect_type(&Type::TInt, &size.check_types(ctx)?, ctx)?;
+                    arr_t = Type::TArray(Box::new(arr_t));
+                }
+                Ok(arr_t)
+            }
+            Expr::EArrayLit(ref elems) => {
+                let mut elem_t: Option<Type> = None;
+                for e in elems {
+                    let t = e.check_types(ctx)?;
+                    elem_t = match elem_t {
+                        None => Some(t),
+                        Some(prev) => {
+                            match common_supertype(&prev, &t, ctx) {
+                                Some(common) => Some(common),
+                                None => return Err(TypeError::no_common_type(&prev, &t)),
+                            }
+                        }
+                    };
+                }
+                match elem_t {
+                    Some(Type::TNull) | None => Err(TypeError::cannot_infer_arr_lit()),
+                    Some(t) => {
+                        expect_declarable_type(&t, ctx)?;
+                        Ok(Type::TArray(Box::new(t)))
+                    }
+                }
+            }
+            Expr::ECast(ref t, ref e) => {
+                let e_type = e.check_types(ctx)?;
+                match is_convertible(&e_type) && is_convertible(t) {
+                    true => Ok(t.clone()),
+                    false => Err(TypeError::invalid_cast(&e_type, t)),
+                }
+            }
+            Expr::ECond(ref cond, ref e1, ref e2) => {
+                expect_type(&Type::TBool, &cond.check_types(ctx)?, ctx)?;
+                let t1 = e1.check_types(ctx)?;
+                let t2 = e2.check_types(ctx)?;
+                match common_supertype(&t1, &t2, ctx) {
+                    Some(t) => Ok(t),
+                    None => Err(TypeError::no_common_cond_type(&t1, &t2)),
+                }
             }
         }
     }
 }
 
+// Array literals take their type from the context if it is known, so that
+// `{}` or `{null}` can be used wherever an array is expected.
+fn check_init_types(expected: &Type, expr: &Expr, ctx: &TypeContext) -> TypeResult<Type> {
+    match (expected, expr) {
+        (Type::TArray(elem_t), Expr::EArrayLit(elems)) => {
+            for e in elems {
+                check_init_types(elem_t, e, ctx)
+                    .map_err(|err| err.wrapped(&format!("{}\n", expr)))?;
+            }
+            Ok(expected.clone())
+        }
+        _ => expect_type(expected, &expr.check_types(ctx)?, ctx),
+    }
+}
+
 fn check_call_types(ident: &FieldGet, args: &Vec<Expr>, ctx: &TypeContext) -> TypeResult<Type> {
     if let Type::TFunc(ref arg_types, ref ret_type) = ident.check_types(ctx)? {
         if args.len() != arg_types.len() {
             return Err(TypeError::invalid_call_arg_num(arg_types.len(), args.len()));
         }
         for (index, (expected, expr)) in arg_types.iter().zip(args).enumerate() {
+            if let Expr::EArrayLit(..) = *expr {
+                check_init_types(expected, expr, ctx)?;
+                continue;
+            }
             let actual = expr.check_types(ctx)?;
             expect_type(expected, &actual, ctx).map_err(|_| {
                 TypeError::invalid_call_arg_type(index, expected, actual)
@@ -387,17 +670,58 @@ fn check_call_types(ident: &FieldGet, args: &Vec<Expr>, ctx: &TypeContext) -> Ty
 }
 
 fn check_add_types(lhs_t: Type, rhs_t: Type) -> TypeResult<Type> {
-    if lhs_t != rhs_t || (lhs_t != Type::TInt && lhs_t != Type::TString) {
+    if lhs_t != rhs_t || (!is_numeric(&lhs_t) && lhs_t != Type::TString) {
         Err(TypeError::no_operator(Operator::OpAdd, lhs_t, rhs_t))
     } else {
         Ok(lhs_t)
     }
 }
 
+fn check_arith_types(op: Operator, lhs_t: Type, rhs_t: Type) -> TypeResult<Type> {
+    if lhs_t != rhs_t || !is_numeric(&lhs_t) ||
+       (op == Operator::OpMod && lhs_t == Type::TDouble) {
+        Err(TypeError::no_operator(op, lhs_t, rhs_t))
+    } else {
+        Ok(lhs_t)
+    }
+}
+
+fn check_bitwise_types(op: Operator, lhs_t: Type, rhs_t: Type) -> TypeResult<Type> {
+    if lhs_t != Type::TInt || rhs_t != Type::TInt {
+        Err(TypeError::no_operator(op, lhs_t, rhs_t))
+    } else {
+        Ok(lhs_t)
+    }
+}
+
+// strings are ordered lexicographically
+fn check_cmp_types(op: Operator, lhs_t: Type, rhs_t: Type) -> TypeResult<Type> {
+    if lhs_t != rhs_t || !(is_convertible(&lhs_t) || lhs_t == Type::TString) {
+        Err(TypeError::no_operator(op, lhs_t, rhs_t))
+    } else {
+        Ok(Type::TBool)
+    }
+}
+
+fn is_numeric(t: &Type) -> bool {
+    matches!(*t, Type::TInt | Type::TLong | Type::TDouble)
+}
+
+// types which can be converted to each other with an explicit cast
+fn is_enum(t: &Type) -> bool {
+    matches!(*t, Type::TEnum(..))
+}
+
+fn is_convertible(t: &Type) -> bool {
+    is_numeric(t) || *t == Type::TChar
+}
+
 fn check_eq_types(lhs_t: Type, rhs_t: Type, ctx: &TypeContext) -> TypeResult<()> {
     if (lhs_t == Type::TNull && rhs_t == Type::TNull) || conforms_lsp(&lhs_t, &rhs_t, ctx) ||
        conforms_lsp(&rhs_t, &lhs_t, ctx) ||
-       (lhs_t == rhs_t && (lhs_t == Type::TInt || lhs_t == Type::TBool || lhs_t == Type::TString)) {
+       (lhs_t == rhs_t &&
+        (is_convertible(&lhs_t) || lhs_t == Type::TBool || lhs_t == Type::TString ||
+         is_enum(&lhs_t))) {
         Ok(())
     } else {
         Err(TypeError::no_operator(Operator::OpEq, lhs_t, rhs_t))
@@ -408,6 +732,9 @@ impl<'a> HasType<Type, &'a TypeContext> for Lit {
     fn do_check_types(&self, ctx: &TypeContext) -> TypeResult<Type> {
         Ok(match *self {
             Lit::LInt(_) => Type::TInt,
+            Lit::LLong(_) => Type::TLong,
+            Lit::LDouble(_) => Type::TDouble,
+            Lit::LChar(_) => Type::TChar,
             Lit::LTrue | Lit::LFalse => Type::TBool,
             Lit::LString(_) => Type::TString,
             Lit::LNull(None) => Type::TNull,
@@ -427,20 +754,66 @@ impl<'a> HasType<Type, &'a TypeContext> for FieldGet {
 
     fn do_check_types(&self, ctx: &TypeContext) -> TypeResult<Type> {
         match *self {
-            FieldGet::Direct(ref ident) => ident.check_types(ctx),
+            FieldGet::Direct(ref ident) => {
+                if let Some(cname) = ctx.get_member_class(ident) {
+                    expect_accessible(cname, ident, ctx)?;
+                }
+                ident.check_types(ctx)
+            }
             FieldGet::Indirect(ref e, ref field) => {
+                if let Some(name) = as_enum_name(e, ctx) {
+                    return match ctx.get_enum_variants(name).contains(field) {
+                        true => Ok(Type::TEnum(name.clone())),
+                        false => Err(TypeError::no_enum_variant(name, field)),
+                    };
+                }
+                if let Some(cname) = as_class_name(e, ctx) {
+                    return match ctx.get_static_type(cname, field) {
+                        Some(t) => {
+                            expect_accessible(cname, field, ctx)?;
+                            Ok(t.clone())
+                        }
+                        None => Err(TypeError::no_static_member(cname, field)),
+                    };
+                }
                 let e_type = e.check_types(ctx)?;
                 if let Type::TObject(ref cname) = e_type {
                     match ctx.get_field_type(cname, field) {
-                        Some(ref t) => Ok((*t).clone()),
+                        Some(t) => {
+                            expect_accessible(cname, field, ctx)?;
+                            Ok(t.clone())
+                        }
                         None => Err(TypeError::no_member(&e_type, field)),
                     }
+                } else if let Type::TEnum(..) = e_type {
+                    match *field == Ident("name".to_string()) {
+                        true => Ok(Type::TFunc(vec![], Box::new(Type::TString))),
+                        false => Err(TypeError::no_member(&e_type, field)),
+                    }
                 } else if let Type::TArray(..) = e_type {
                     if *field == Ident(format!("length")) {
                         Ok(Type::TInt)
                     } else {
                         Err(TypeError::no_member(&e_type, field))
                     }
+                } else if let Type::TList(ref elem_t) = e_type {
+                    if *field == Ident("length".to_string()) {
+                        return Ok(Type::TInt);
+                    }
+                    get_list_method(elem_t, field)
+                        .ok_or_else(|| TypeError::no_member(&e_type, field))
+                } else if let Type::TMap(ref key_t, ref value_t) = e_type {
+                    if *field == Ident("length".to_string()) {
+                        return Ok(Type::TInt);
+                    }
+                    get_map_method(key_t, value_t, field)
+                        .ok_or_else(|| TypeError::no_member(&e_type, field))
+                } else if let Type::TStringBuilder = e_type {
+                    if *field == Ident("length".to_string()) {
+                        return Ok(Type::TInt);
+                    }
+                    get_string_builder_method(field)
+                        .ok_or_else(|| TypeError::no_member(&e_type, field))
                 } else {
                     Err(TypeError::not_an_object(&e_type))
                 }
@@ -448,14 +821,10 @@ impl<'a> HasType<Type, &'a TypeContext> for FieldGet {
             FieldGet::IdxAccess(ref e, ref idx) => {
                 let e_type = e.check_types(ctx)?;
                 let idx_type = idx.check_types(ctx)?;
-                if let Type::TArray(ref t) = e_type {
-                    if idx_type == Type::TInt {
-                        Ok((**t).clone())
-                    } else {
-                        Err(TypeError::invalid_arr_subscript(&e_type, &idx_type))
-                    }
-                } else {
-                    Err(TypeError::invalid_arr_subscript(&e_type, &idx_type))
+                match e_type {
+                    Type::TArray(ref t) |
+                    Type::TList(ref t) if idx_type == Type::TInt => Ok((**t).clone()),
+                    _ => Err(TypeError::invalid_arr_subscript(&e_type, &idx_type)),
                 }
             }
         }
@@ -471,6 +840,33 @@ impl<'a> HasType<Type, &'a TypeContext> for Ident {
     }
 }
 
+// <class name>.<static member>, unless the name is hidden by a variable
+fn as_class_name<'a>(e: &'a Expr, ctx: &TypeContext) -> Option<&'a Ident> {
+    match *e {
+        Expr::EVar(FieldGet::Direct(ref ident)) if ctx.get_type(ident).is_none() &&
+                                                   ctx.class_exists(ident) => Some(ident),
+        _ => None,
+    }
+}
+
+fn expect_accessible(class_name: &Ident, field: &Ident, ctx: &TypeContext) -> TypeResult<()> {
+    match ctx.get_access(class_name, field) {
+        Some((class, access)) if !ctx.can_access(class, access) => {
+            Err(TypeError::inaccessible(class, field, access))
+        }
+        _ => Ok(()),
+    }
+}
+
+// <enum name>.<variant>, unless the name is hidden by a variable
+fn as_enum_name<'a>(e: &'a Expr, ctx: &TypeContext) -> Option<&'a Ident> {
+    match *e {
+        Expr::EVar(FieldGet::Direct(ref ident)) if ctx.get_type(ident).is_none() &&
+                                                   ctx.enum_exists(ident) => Some(ident),
+        _ => None,
+    }
+}
+
 fn add_ident(ident: &Ident, t: &Type, ctx: &mut TypeContext) -> TypeResult<()> {
     expect_valid_type(t, &ctx)?;
     if ctx.is_local(ident) {
@@ -481,6 +877,18 @@ fn add_ident(ident: &Ident, t: &Type, ctx: &mut TypeContext) -> TypeResult<()> {
     }
 }
 
+fn expect_mutable(field: &FieldGet, ctx: &TypeContext) -> TypeResult<()> {
+    match *field {
+        FieldGet::Direct(ref ident) if ctx.is_const(ident) => {
+            Err(TypeError::assign_to_const(ident))
+        }
+        FieldGet::Indirect(ref e, ref variant) if as_enum_name(e, ctx).is_some() => {
+            Err(TypeError::assign_to_const(variant))
+        }
+        _ => Ok(()),
+    }
+}
+
 fn expect_type(expected: &Type, actual: &Type, ctx: &TypeContext) -> TypeResult<Type> {
     match expected == actual || conforms_lsp(expected, actual, ctx) {
         true => Ok(expected.clone()),
@@ -492,10 +900,17 @@ fn expect_declarable_type(t: &Type, ctx: &TypeContext) -> TypeResult<()> {
     expect_valid_type(t, ctx)?;
     match *t {
         Type::TInt |
+        Type::TLong |
+        Type::TDouble |
+        Type::TChar |
         Type::TString |
+        Type::TStringBuilder |
         Type::TBool |
         Type::TArray(..) |
-        Type::TObject(..) => Ok(()),
+        Type::TList(..) |
+        Type::TMap(..) |
+        Type::TObject(..) |
+        Type::TEnum(..) => Ok(()),
         _ => Err(TypeError::non_declarable(t)),
     }
 }
@@ -508,15 +923,54 @@ fn expect_valid_type(t: &Type, ctx: &TypeContext) -> TypeResult<()> {
                 false => Err(TypeError::inexistent_type(t)),
             }
         }
+        Type::TEnum(ref name) => {
+            match ctx.enum_exists(name) {
+                true => Ok(()),
+                false => Err(TypeError::inexistent_type(t)),
+            }
+        }
         Type::TArray(ref t) => expect_valid_type(&**t, ctx),
+        Type::TList(ref t) => expect_declarable_type(t, ctx),
+        Type::TMap(ref key_t, ref value_t) => {
+            match **key_t {
+                Type::TInt | Type::TString | Type::TEnum(..) => {
+                    expect_valid_type(key_t, ctx)?;
+                    expect_declarable_type(value_t, ctx)
+                }
+                _ => Err(TypeError::invalid_map_key(key_t)),
+            }
+        }
         _ => Ok(()),
     }
 }
 
+fn common_supertype(t1: &Type, t2: &Type, ctx: &TypeContext) -> Option<Type> {
+    if t1 == t2 || conforms_lsp(t1, t2, ctx) {
+        return Some(t1.clone());
+    }
+    if conforms_lsp(t2, t1, ctx) {
+        return Some(t2.clone());
+    }
+    if let (Type::TObject(c1), Type::TObject(c2)) = (t1, t2) {
+        let mut sup = ctx.get_superclass(c1);
+        while let Some(cname) = sup {
+            if ctx.is_subclass_of(c2, cname) {
+                return Some(Type::TObject(cname.clone()));
+            }
+            sup = ctx.get_superclass(cname);
+        }
+    }
+    None
+}
+
 fn conforms_lsp(expected: &Type, actual: &Type, ctx: &TypeContext) -> bool {
     match (expected, actual) {
         (&Type::TObject(ref sup), &Type::TObject(ref sub)) => ctx.is_subclass_of(sub, sup),
-        (&Type::TObject(..), &Type::TNull) => true,
+        (&Type::TObject(..), &Type::TNull) |
+        (&Type::TStringBuilder, &Type::TNull) |
+        (&Type::TArray(..), &Type::TNull) |
+        (&Type::TList(..), &Type::TNull) |
+        (&Type::TMap(..), &Type::TNull) => true,
         (_, _) => false,
     }
 }
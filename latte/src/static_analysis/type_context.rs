@@ -1,14 +1,24 @@
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 
-use ast::{Ident, Type};
+use ast::{Access, Ident, Type};
+use static_analysis::warning::Warning;
 
 type IdentsMap<T> = HashMap<Ident, T>;
 
 #[derive(Debug)]
 pub struct TypeContext {
     idents: IdentsMap<(Type, bool)>,
+    consts: HashSet<Ident>,
     class_data: IdentsMap<ClassData>,
+    enums: IdentsMap<Vec<Ident>>,
     ret_type: Type,
+    class: Option<Ident>,
+    // class members visible without qualification, mapped to the declaring class
+    members: IdentsMap<Ident>,
+    // shared by all scopes
+    warnings: Rc<RefCell<Vec<Warning>>>,
 }
 
 #[derive(Debug, Clone)]
@@ -16,14 +26,21 @@ struct ClassData {
     name: Ident,
     superclass: Option<Ident>,
     fields: IdentsMap<Type>,
+    static_fields: IdentsMap<Type>,
+    access: IdentsMap<Access>,
 }
 
 impl TypeContext {
     pub fn new() -> TypeContext {
         TypeContext {
             idents: HashMap::new(),
+            consts: HashSet::new(),
             class_data: HashMap::new(),
+            enums: HashMap::new(),
             ret_type: Type::TVoid,
+            class: None,
+            members: HashMap::new(),
+            warnings: Rc::new(RefCell::new(Vec::new())),
         }
     }
 
@@ -54,6 +71,22 @@ impl TypeContext {
         for (ident, t) in self.get_fields(class_name) {
             ctx.set_type(&ident, &t);
         }
+        for (ident, t) in self.get_static_fields(class_name) {
+            ctx.set_type(&ident, &t);
+        }
+        ctx.set_members(class_name, true);
+        f(ctx)
+    }
+
+    // only static members of the class are visible, there is no self
+    pub fn in_static_scope<F, T>(&self, class_name: &Ident, f: F) -> T
+        where F: Fn(TypeContext) -> T
+    {
+        let mut ctx = self._new_scope();
+        for (ident, t) in self.get_static_fields(class_name) {
+            ctx.set_type(&ident, &t);
+        }
+        ctx.set_members(class_name, false);
         f(ctx)
     }
 
@@ -62,8 +95,25 @@ impl TypeContext {
         idents.iter_mut().map(|(_, ref mut e)| e.1 = false).collect::<Vec<()>>();
         TypeContext {
             idents: idents,
+            consts: self.consts.clone(),
             class_data: self.class_data.clone(),
+            enums: self.enums.clone(),
             ret_type: self.ret_type.clone(),
+            class: self.class.clone(),
+            members: self.members.clone(),
+            warnings: self.warnings.clone(),
+        }
+    }
+
+    fn set_members(&mut self, class_name: &Ident, with_instance_fields: bool) {
+        self.class = Some(class_name.clone());
+        let mut class_data = Some(self.get_class_data(class_name).clone());
+        while let Some(data) = class_data {
+            let fields = data.fields.keys().filter(|_| with_instance_fields);
+            for ident in fields.chain(data.static_fields.keys()) {
+                self.members.entry(ident.clone()).or_insert(data.name.clone());
+            }
+            class_data = data.superclass.as_ref().map(|s| self.get_class_data(s).clone());
         }
     }
 
@@ -72,12 +122,16 @@ impl TypeContext {
     pub fn add_class(&mut self,
                      name: &Ident,
                      superclass: &Option<Ident>,
-                     fields: IdentsMap<Type>) {
+                     fields: IdentsMap<Type>,
+                     static_fields: IdentsMap<Type>,
+                     access: IdentsMap<Access>) {
         self.class_data.insert(name.clone(),
                                ClassData {
                                    name: name.clone(),
                                    superclass: superclass.clone(),
                                    fields: fields,
+                                   static_fields,
+                                   access,
                                });
     }
 
@@ -96,6 +150,38 @@ impl TypeContext {
         }
     }
 
+    pub fn get_static_type(&self, class_name: &Ident, field: &Ident) -> Option<&Type> {
+        let class_data = self.get_class_data(class_name);
+        if let Some(t) = class_data.static_fields.get(field) {
+            Some(t)
+        } else if let Some(ref superclass) = class_data.superclass {
+            self.get_static_type(superclass, field)
+        } else {
+            None
+        }
+    }
+
+    // returns the class declaring the member together with its access modifier
+    pub fn get_access(&self, class_name: &Ident, field: &Ident) -> Option<(&Ident, Access)> {
+        let class_data = self.get_class_data(class_name);
+        if let Some(access) = class_data.access.get(field) {
+            Some((&class_data.name, *access))
+        } else if let Some(ref superclass) = class_data.superclass {
+            self.get_access(superclass, field)
+        } else {
+            None
+        }
+    }
+
+    pub fn can_access(&self, class_name: &Ident, access: Access) -> bool {
+        match (access, &self.class) {
+            (Access::Public, _) => true,
+            (Access::Protected, Some(current)) => self.is_subclass_of(current, class_name),
+            (Access::Private, Some(current)) => current == class_name,
+            (_, &None) => false,
+        }
+    }
+
     pub fn is_subclass_of(&self, sub_name: &Ident, sup_name: &Ident) -> bool {
         let mut subclass = self.get_class_data(sub_name);
         while &subclass.name != sup_name && subclass.superclass.is_some() {
@@ -104,6 +190,10 @@ impl TypeContext {
         &subclass.name == sup_name
     }
 
+    pub fn get_superclass(&self, class_name: &Ident) -> Option<&Ident> {
+        self.get_class_data(class_name).superclass.as_ref()
+    }
+
     fn get_fields(&self, class_name: &Ident) -> IdentsMap<Type> {
         let mut fields: IdentsMap<Type> = HashMap::new();
         self.do_get_fields(class_name, &mut fields);
@@ -118,10 +208,46 @@ impl TypeContext {
         }
     }
 
+    fn get_static_fields(&self, class_name: &Ident) -> IdentsMap<Type> {
+        let mut fields: IdentsMap<Type> = HashMap::new();
+        let mut class_data = Some(self.get_class_data(class_name));
+        while let Some(data) = class_data {
+            for (ident, t) in &data.static_fields {
+                fields.entry(ident.clone()).or_insert(t.clone());
+            }
+            class_data = data.superclass.as_ref().map(|s| self.get_class_data(s));
+        }
+        fields
+    }
+
     fn get_class_data(&self, ident: &Ident) -> &ClassData {
         self.class_data.get(ident).unwrap()
     }
 
+    // enums
+
+    pub fn add_enum(&mut self, name: &Ident, variants: &[Ident]) {
+        self.enums.insert(name.clone(), variants.to_owned());
+    }
+
+    pub fn enum_exists(&self, name: &Ident) -> bool {
+        self.enums.contains_key(name)
+    }
+
+    pub fn get_enum_variants(&self, name: &Ident) -> &Vec<Ident> {
+        self.enums.get(name).unwrap()
+    }
+
+    // warnings
+
+    pub fn warn(&self, w: Warning) {
+        self.warnings.borrow_mut().push(w);
+    }
+
+    pub fn take_warnings(&self) -> Vec<Warning> {
+        self.warnings.borrow_mut().drain(..).collect()
+    }
+
     // identifiers
 
     pub fn get_type(&self, ident: &Ident) -> Option<&Type> {
@@ -130,6 +256,20 @@ impl TypeContext {
 
     pub fn set_type(&mut self, ident: &Ident, t: &Type) {
         self.idents.insert(ident.clone(), (t.clone(), true));
+        self.consts.remove(ident);
+        self.members.remove(ident);
+    }
+
+    pub fn get_member_class(&self, ident: &Ident) -> Option<&Ident> {
+        self.members.get(ident)
+    }
+
+    pub fn set_const(&mut self, ident: &Ident) {
+        self.consts.insert(ident.clone());
+    }
+
+    pub fn is_const(&self, ident: &Ident) -> bool {
+        self.consts.contains(ident)
     }
 
     pub fn is_local(&self, ident: &Ident) -> bool {
@@ -1,6 +1,6 @@
 use std::fmt;
 
-use ast::{FieldGet, Ident, Operator, Type};
+use ast::{Access, Expr, FieldGet, Ident, Operator, Type};
 
 #[derive(Debug)]
 pub struct TypeError {
@@ -37,15 +37,43 @@ impl TypeError {
         Self::new(format!("{} is not a valid type", t))
     }
 
+    pub fn invalid_map_key(t: &Type) -> TypeError {
+        Self::new(format!("Map keys must be of type int or string, got: {}", t))
+    }
+
     pub fn no_operator(op: Operator, lhs_t: Type, rhs_t: Type) -> TypeError {
         Self::new(format!("No {} operator for types: {} and {}", op, lhs_t, rhs_t))
     }
 
+    pub fn no_neg_operator(t: &Type) -> TypeError {
+        Self::new(format!("No unary - operator for type: {}", t))
+    }
+
+    pub fn invalid_cast(from: &Type, to: &Type) -> TypeError {
+        Self::new(format!("Cannot convert {} to {}", from, to))
+    }
+
     pub fn invalid_arr_subscript(t: &Type, idx_t: &Type) -> TypeError {
         Self::new(format!("Invalid types '{}[{}]' for array subscription", t, idx_t))
     }
 
-    // int main()
+    pub fn cannot_infer_arr_lit() -> TypeError {
+        Self::new("Cannot infer element type of array literal".to_string())
+    }
+
+    pub fn no_common_type(t1: &Type, t2: &Type) -> TypeError {
+        Self::new(format!("Array literal elements have incompatible types: {} and {}",
+                          t1,
+                          t2))
+    }
+
+    pub fn no_common_cond_type(t1: &Type, t2: &Type) -> TypeError {
+        Self::new(format!("Branches of conditional expression have incompatible types: {} and {}",
+                          t1,
+                          t2))
+    }
+
+    // int main() or int main(string[] args)
 
     pub fn no_main() -> TypeError {
         Self::new(format!("No main function"))
@@ -55,6 +83,16 @@ impl TypeError {
         Self::new(format!("Invalid type of main function"))
     }
 
+    // extern declarations
+
+    pub fn invalid_extern_type(t: &Type) -> TypeError {
+        Self::new(format!("Type {} cannot be passed to or returned from extern functions", t))
+    }
+
+    pub fn reserved_extern_name(ident: &Ident) -> TypeError {
+        Self::new(format!("{} is a function of the runtime or the C library", ident))
+    }
+
     // Identifier
 
     pub fn undefined(ident: &Ident) -> TypeError {
@@ -66,12 +104,32 @@ impl TypeError {
                           ident))
     }
 
+    pub fn const_without_init(ident: &Ident) -> TypeError {
+        Self::new(format!("Constant {} must be initialized", ident))
+    }
+
+    pub fn assign_to_const(ident: &Ident) -> TypeError {
+        Self::new(format!("Cannot assign to constant {}", ident))
+    }
+
     // Function
 
     pub fn not_a_function(field: &FieldGet) -> TypeError {
         Self::new(format!("{} is not a function", field))
     }
 
+    pub fn invalid_switch_type(t: &Type) -> TypeError {
+        Self::new(format!("Cannot switch over type {}, expected int, string or enum", t))
+    }
+
+    pub fn non_constant_label(label: &Expr) -> TypeError {
+        Self::new(format!("Case label {} is not a constant", label))
+    }
+
+    pub fn duplicate_label(label: &Expr) -> TypeError {
+        Self::new(format!("Duplicate case label {}", label))
+    }
+
     pub fn return_void_expr() -> TypeError {
         Self::new(format!("Cannot return expression result from void function"))
     }
@@ -99,6 +157,10 @@ impl TypeError {
         Self::new(format!("Multiple fields with name {} in class {}", ident, class))
     }
 
+    pub fn variant_already_defined(enum_name: &Ident, ident: &Ident) -> TypeError {
+        Self::new(format!("Multiple variants with name {} in enum {}", ident, enum_name))
+    }
+
     pub fn var_override(ident: &Ident) -> TypeError {
         Self::new(format!("Cannot override variable {}", ident))
     }
@@ -110,6 +172,38 @@ impl TypeError {
                           actual))
     }
 
+    pub fn static_override(ident: &Ident) -> TypeError {
+        Self::new(format!("Cannot override {}: static members cannot be overridden or hidden",
+                          ident))
+    }
+
+    pub fn no_static_member(class: &Ident, field: &Ident) -> TypeError {
+        Self::new(format!("Class {} has no static member '{}'", class, field))
+    }
+
+    pub fn inaccessible(class: &Ident, field: &Ident, access: Access) -> TypeError {
+        Self::new(format!("Cannot access '{}': it is declared {} in class {}",
+                          field,
+                          access,
+                          class))
+    }
+
+    pub fn weaker_access(ident: &Ident,
+                         access: Access,
+                         class: &Ident,
+                         expected: Access)
+                         -> TypeError {
+        Self::new(format!("Cannot override {} as {}: it is declared {} in class {}",
+                          ident,
+                          access,
+                          expected,
+                          class))
+    }
+
+    pub fn no_enum_variant(name: &Ident, variant: &Ident) -> TypeError {
+        Self::new(format!("Enum {} has no variant '{}'", name, variant))
+    }
+
     pub fn no_member(t: &Type, field: &Ident) -> TypeError {
         Self::new(format!("Cannot access field '{}' for type '{}'", field, t))
     }
@@ -0,0 +1,179 @@
+use std::fmt;
+
+use ast::Ident;
+use options::WarningOptions;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WarningKind {
+    NonExhaustive,
+    Unused,
+    Unreachable,
+    Shadow,
+    ConstCondition,
+    UnusedValue,
+    Uninitialized,
+    NullDeref,
+}
+
+#[derive(Debug)]
+pub struct Warning {
+    pub kind: WarningKind,
+    msg: String,
+    line: i32, // of the innermost statement, 0 if it's unknown
+    stack: Vec<String>,
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            WarningKind::NonExhaustive => "non-exhaustive",
+            WarningKind::Unused => "unused",
+            WarningKind::Unreachable => "unreachable",
+            WarningKind::Shadow => "shadow",
+            WarningKind::ConstCondition => "const-condition",
+            WarningKind::UnusedValue => "unused-value",
+            WarningKind::Uninitialized => "uninitialized",
+            WarningKind::NullDeref => "null-deref",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl WarningKind {
+    pub fn all() -> Vec<WarningKind> {
+        vec![WarningKind::NonExhaustive,
+             WarningKind::Unused,
+             WarningKind::Unreachable,
+             WarningKind::Shadow,
+             WarningKind::ConstCondition,
+             WarningKind::UnusedValue,
+             WarningKind::Uninitialized,
+             WarningKind::NullDeref]
+    }
+
+    pub fn from_name(name: &str) -> Option<WarningKind> {
+        Self::all().into_iter().find(|kind| format!("{}", kind) == name)
+    }
+
+    // Shadowing is often intended and the dataflow analysis is optional,
+    // so these have to be enabled explicitly.
+    pub fn is_default(self) -> bool {
+        !matches!(self, WarningKind::Shadow |
+            WarningKind::Uninitialized |
+            WarningKind::NullDeref)
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Warning: {}{} [-W{}]", self.location(), self.msg, self.kind)?;
+        self.fmt_stack(f)
+    }
+}
+
+impl Warning {
+    fn location(&self) -> String {
+        match self.line {
+            0 => String::new(),
+            line => format!("line {}: ", line),
+        }
+    }
+
+    fn fmt_stack(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for place in &self.stack {
+            writeln!(f, "in:")?;
+            write!(f, "{}", place)?;
+        }
+        Ok(())
+    }
+
+    pub fn wrapped<T: fmt::Display>(mut self, inside: &T) -> Warning {
+        self.stack.push(format!("{}", inside));
+        self
+    }
+
+    // the line of the innermost statement is kept
+    pub fn at_line(mut self, line: i32) -> Warning {
+        if self.line == 0 {
+            self.line = line;
+        }
+        self
+    }
+
+    pub fn non_exhaustive_switch(enum_name: &Ident, missing: &Vec<&Ident>) -> Warning {
+        let missing: Vec<String> = missing.iter().map(|v| format!("{}", v)).collect();
+        Self::new(WarningKind::NonExhaustive,
+                  format!("Switch over {} does not handle: {}", enum_name, missing.join(", ")))
+    }
+
+    pub fn unused_var(ident: &Ident) -> Warning {
+        Self::new(WarningKind::Unused, format!("Unused variable {}", ident))
+    }
+
+    pub fn unused_arg(ident: &Ident) -> Warning {
+        Self::new(WarningKind::Unused, format!("Unused parameter {}", ident))
+    }
+
+    pub fn unreachable() -> Warning {
+        Self::new(WarningKind::Unreachable, "Unreachable code".to_string())
+    }
+
+    pub fn shadows(ident: &Ident, what: &str) -> Warning {
+        Self::new(WarningKind::Shadow,
+                  format!("Declaration of {} shadows a {}", ident, what))
+    }
+
+    pub fn const_condition(value: bool) -> Warning {
+        Self::new(WarningKind::ConstCondition,
+                  format!("Condition is always {}", value))
+    }
+
+    pub fn no_effect() -> Warning {
+        Self::new(WarningKind::UnusedValue, "Expression statement has no effect".to_string())
+    }
+
+    pub fn unassigned(ident: &Ident) -> Warning {
+        Self::new(WarningKind::Uninitialized,
+                  format!("Variable {} may be used before being assigned", ident))
+    }
+
+    pub fn null_deref(ident: &Ident) -> Warning {
+        Self::new(WarningKind::NullDeref,
+                  format!("Dereference of {}, which is always null here", ident))
+    }
+
+    fn new(kind: WarningKind, msg: String) -> Warning {
+        Warning {
+            kind,
+            msg,
+            line: 0,
+            stack: Vec::new(),
+        }
+    }
+}
+
+struct PromotedWarning<'a>(&'a Warning);
+
+impl<'a> fmt::Display for PromotedWarning<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Error: {}{} [-Werror={}]", self.0.location(), self.0.msg, self.0.kind)?;
+        self.0.fmt_stack(f)
+    }
+}
+
+// Drops disabled warnings. Returns an error if any of the remaining ones
+// is promoted to an error.
+pub fn apply_options(warnings: Vec<Warning>,
+                     opts: &WarningOptions)
+                     -> Result<Vec<Warning>, String> {
+    let warnings: Vec<Warning> =
+        warnings.into_iter().filter(|w| opts.is_enabled(w.kind)).collect();
+    let errors: Vec<String> = warnings.iter()
+        .filter(|w| opts.is_error(w.kind))
+        .map(|w| format!("{}", PromotedWarning(w)))
+        .collect();
+    match errors.is_empty() {
+        true => Ok(warnings),
+        false => Err(errors.concat()),
+    }
+}
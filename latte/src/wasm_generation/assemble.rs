@@ -0,0 +1,869 @@
+use std::collections::HashMap;
+
+// Translates the text format of WebAssembly into the binary format, so that no
+// external tools are needed. Only the subset used by the generated code and
+// lib/runtime.wat is supported: instructions are written in the flat form,
+// block types and types of indirect calls are given inline.
+
+#[derive(Debug, Clone, PartialEq)]
+enum SExpr {
+    List(Vec<SExpr>),
+    Atom(String),
+    Str(Vec<u8>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ValType {
+    I32,
+    I64,
+    F64,
+}
+
+type FuncType = (Vec<ValType>, Vec<ValType>);
+
+type Result<T> = ::std::result::Result<T, String>;
+
+pub fn assemble(text: &str) -> Result<Vec<u8>> {
+    let tokens = tokenize(text)?;
+    let mut pos = 0;
+    let module = parse_sexpr(&tokens, &mut pos)?;
+    let fields = match module {
+        SExpr::List(ref items) if items.first() == Some(&atom("module")) => &items[1..],
+        _ => return Err("Expected a module".to_string()),
+    };
+    let mut module = Module::new();
+    module.collect(fields)?;
+    module.encode(fields)
+}
+
+fn atom(s: &str) -> SExpr {
+    SExpr::Atom(s.to_string())
+}
+
+// tokens
+
+#[derive(Debug, Clone)]
+enum Token {
+    LParen,
+    RParen,
+    Atom(String),
+    Str(Vec<u8>),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            b')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            b';' if bytes.get(i + 1) == Some(&b';') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'"' => {
+                let (s, end) = parse_str(bytes, i + 1)?;
+                tokens.push(Token::Str(s));
+                i = end;
+            }
+            c if c.is_ascii_whitespace() => i += 1,
+            _ => {
+                let start = i;
+                while i < bytes.len() && !bytes[i].is_ascii_whitespace() && bytes[i] != b'(' &&
+                      bytes[i] != b')' {
+                    i += 1;
+                }
+                tokens.push(Token::Atom(text[start..i].to_string()));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// Returns the contents of the string starting at i and the position after it.
+// Escapes are \\, \", \n, \t and two hex digits.
+fn parse_str(bytes: &[u8], mut i: usize) -> Result<(Vec<u8>, usize)> {
+    let mut s = Vec::new();
+    loop {
+        match bytes.get(i) {
+            None => return Err("Unterminated string".to_string()),
+            Some(&b'"') => return Ok((s, i + 1)),
+            Some(&b'\\') => {
+                let c = bytes.get(i + 1).cloned().unwrap_or(0);
+                match c {
+                    b'n' => s.push(b'\n'),
+                    b't' => s.push(b'\t'),
+                    b'\\' | b'"' | b'\'' => s.push(c),
+                    _ => {
+                        let hex = bytes.get(i + 1..i + 3).ok_or("Bad escape".to_string())?;
+                        let hex = ::std::str::from_utf8(hex).map_err(|e| format!("{}", e))?;
+                        let v = u8::from_str_radix(hex, 16)
+                            .map_err(|_| format!("Bad escape: \\{}", hex))?;
+                        s.push(v);
+                        i += 1;
+                    }
+                }
+                i += 2;
+            }
+            Some(&c) => {
+                s.push(c);
+                i += 1;
+            }
+        }
+    }
+}
+
+fn parse_sexpr(tokens: &[Token], pos: &mut usize) -> Result<SExpr> {
+    let token = tokens.get(*pos).ok_or("Unexpected end of input".to_string())?.clone();
+    *pos += 1;
+    match token {
+        Token::LParen => {
+            let mut items = Vec::new();
+            loop {
+                match tokens.get(*pos) {
+                    None => return Err("Missing ')'".to_string()),
+                    Some(&Token::RParen) => {
+                        *pos += 1;
+                        return Ok(SExpr::List(items));
+                    }
+                    _ => items.push(parse_sexpr(tokens, pos)?),
+                }
+            }
+        }
+        Token::RParen => Err("Unexpected ')'".to_string()),
+        Token::Atom(s) => Ok(SExpr::Atom(s)),
+        Token::Str(s) => Ok(SExpr::Str(s)),
+    }
+}
+
+// module
+
+struct Import {
+    module: Vec<u8>,
+    name: Vec<u8>,
+    type_idx: u32,
+}
+
+struct Module {
+    types: Vec<FuncType>,
+    imports: Vec<Import>,
+    funcs: HashMap<String, u32>, // imported functions come first
+    func_types: Vec<u32>,        // of the defined functions
+    globals: HashMap<String, (u32, ValType)>,
+    memory_pages: u32,
+    table_size: Option<u32>,
+}
+
+impl Module {
+    fn new() -> Module {
+        Module {
+            types: Vec::new(),
+            imports: Vec::new(),
+            funcs: HashMap::new(),
+            func_types: Vec::new(),
+            globals: HashMap::new(),
+            memory_pages: 0,
+            table_size: None,
+        }
+    }
+
+    fn type_idx(&mut self, t: FuncType) -> u32 {
+        match self.types.iter().position(|other| *other == t) {
+            Some(idx) => idx as u32,
+            None => {
+                self.types.push(t);
+                self.types.len() as u32 - 1
+            }
+        }
+    }
+
+    // indices of functions and globals, which can be used before their definitions
+    fn collect(&mut self, fields: &[SExpr]) -> Result<()> {
+        for field in fields {
+            let items = as_list(field)?;
+            if head(items) == "import" {
+                let func = as_list(items.get(3).ok_or("Bad import".to_string())?)?;
+                let name = as_atom(&func[1])?;
+                let t = parse_func_type(&func[2..])?;
+                let type_idx = self.type_idx(t);
+                self.imports.push(Import {
+                    module: as_str(&items[1])?,
+                    name: as_str(&items[2])?,
+                    type_idx,
+                });
+                let idx = self.funcs.len() as u32;
+                self.funcs.insert(name, idx);
+            }
+        }
+        for field in fields {
+            let items = as_list(field)?;
+            match head(items).as_ref() {
+                "func" => {
+                    let name = as_atom(&items[1])?;
+                    let t = parse_func_type(&items[2..])?;
+                    let type_idx = self.type_idx(t);
+                    self.func_types.push(type_idx);
+                    let idx = self.funcs.len() as u32;
+                    self.funcs.insert(name, idx);
+                }
+                "global" => {
+                    let name = as_atom(&items[1])?;
+                    let t = match items[2] {
+                        SExpr::List(ref mut_t) => parse_val_type(&mut_t[1])?,
+                        ref t => parse_val_type(t)?,
+                    };
+                    let idx = self.globals.len() as u32;
+                    self.globals.insert(name, (idx, t));
+                }
+                "memory" => self.memory_pages = parse_u32(items.last().unwrap())?,
+                "table" => self.table_size = Some(parse_u32(&items[1])?),
+                "import" | "export" | "elem" | "data" => {}
+                other => return Err(format!("Unsupported module field: {}", other)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&mut self, fields: &[SExpr]) -> Result<Vec<u8>> {
+        let mut exports: Vec<u8> = Vec::new();
+        let mut exports_cnt = 0;
+        let mut globals: Vec<u8> = Vec::new();
+        let mut elems: Vec<u8> = Vec::new();
+        let mut elems_cnt = 0;
+        let mut datas: Vec<u8> = Vec::new();
+        let mut datas_cnt = 0;
+        let mut codes: Vec<u8> = Vec::new();
+        for field in fields {
+            let items = as_list(field)?;
+            match head(items).as_ref() {
+                "func" => {
+                    let name = as_atom(&items[1])?;
+                    for item in &items[2..] {
+                        if let SExpr::List(ref export) = *item {
+                            if head(export) == "export" {
+                                write_name(&mut exports, &as_str(&export[1])?);
+                                exports.push(0x00);
+                                write_u32(&mut exports, self.funcs[&name]);
+                                exports_cnt += 1;
+                            }
+                        }
+                    }
+                    let body = self.encode_func(&items[2..])?;
+                    write_u32(&mut codes, body.len() as u32);
+                    codes.extend(body);
+                }
+                "export" => {
+                    write_name(&mut exports, &as_str(&items[1])?);
+                    let desc = as_list(&items[2])?;
+                    match head(desc).as_ref() {
+                        "func" => {
+                            exports.push(0x00);
+                            let idx = self.func_idx(&desc[1])?;
+                            write_u32(&mut exports, idx);
+                        }
+                        "memory" => {
+                            exports.push(0x02);
+                            write_u32(&mut exports, 0);
+                        }
+                        other => return Err(format!("Unsupported export: {}", other)),
+                    }
+                    exports_cnt += 1;
+                }
+                "global" => {
+                    let (t, mutable) = match items[2] {
+                        SExpr::List(ref mut_t) => (parse_val_type(&mut_t[1])?, true),
+                        ref t => (parse_val_type(t)?, false),
+                    };
+                    globals.push(val_type_code(t));
+                    globals.push(if mutable { 1 } else { 0 });
+                    globals.extend(self.encode_const_expr(&items[3])?);
+                }
+                "elem" => {
+                    write_u32(&mut elems, 0);
+                    elems.extend(self.encode_const_expr(&items[1])?);
+                    write_u32(&mut elems, items.len() as u32 - 2);
+                    for f in &items[2..] {
+                        let idx = self.func_idx(f)?;
+                        write_u32(&mut elems, idx);
+                    }
+                    elems_cnt += 1;
+                }
+                "data" => {
+                    write_u32(&mut datas, 0);
+                    datas.extend(self.encode_const_expr(&items[1])?);
+                    let mut bytes = Vec::new();
+                    for s in &items[2..] {
+                        bytes.extend(as_str(s)?);
+                    }
+                    write_u32(&mut datas, bytes.len() as u32);
+                    datas.extend(bytes);
+                    datas_cnt += 1;
+                }
+                _ => {}
+            }
+        }
+
+        let mut out = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+
+        let mut types = Vec::new();
+        for (params, results) in &self.types {
+            types.push(0x60);
+            write_vec_types(&mut types, params);
+            write_vec_types(&mut types, results);
+        }
+        write_section(&mut out, 1, self.types.len() as u32, &types);
+
+        let mut imports = Vec::new();
+        for import in &self.imports {
+            write_name(&mut imports, &import.module);
+            write_name(&mut imports, &import.name);
+            imports.push(0x00);
+            write_u32(&mut imports, import.type_idx);
+        }
+        write_section(&mut out, 2, self.imports.len() as u32, &imports);
+
+        let mut funcs = Vec::new();
+        for &type_idx in &self.func_types {
+            write_u32(&mut funcs, type_idx);
+        }
+        write_section(&mut out, 3, self.func_types.len() as u32, &funcs);
+
+        if let Some(size) = self.table_size {
+            let mut table = vec![0x70, 0x01];
+            write_u32(&mut table, size);
+            write_u32(&mut table, size);
+            write_section(&mut out, 4, 1, &table);
+        }
+
+        let mut memory = vec![0x00];
+        write_u32(&mut memory, self.memory_pages);
+        write_section(&mut out, 5, 1, &memory);
+
+        write_section(&mut out, 6, self.globals.len() as u32, &globals);
+        write_section(&mut out, 7, exports_cnt, &exports);
+        write_section(&mut out, 9, elems_cnt, &elems);
+        write_section(&mut out, 10, self.func_types.len() as u32, &codes);
+        write_section(&mut out, 11, datas_cnt, &datas);
+        Ok(out)
+    }
+
+    fn func_idx(&self, e: &SExpr) -> Result<u32> {
+        let name = as_atom(e)?;
+        self.funcs.get(&name).cloned().ok_or(format!("Unknown function: {}", name))
+    }
+
+    fn global_idx(&self, e: &SExpr) -> Result<u32> {
+        let name = as_atom(e)?;
+        self.globals.get(&name).map(|g| g.0).ok_or(format!("Unknown global: {}", name))
+    }
+
+    // initializers of globals and offsets of segments
+    fn encode_const_expr(&self, e: &SExpr) -> Result<Vec<u8>> {
+        let items = as_list(e)?;
+        let mut code = Vec::new();
+        match head(items).as_ref() {
+            "i32.const" => {
+                code.push(0x41);
+                write_i64(&mut code, parse_i32(&items[1])? as i64);
+            }
+            "i64.const" => {
+                code.push(0x42);
+                write_i64(&mut code, parse_i64(&items[1])?);
+            }
+            "f64.const" => {
+                code.push(0x44);
+                code.extend(&parse_f64(&items[1])?.to_bits().to_le_bytes());
+            }
+            other => return Err(format!("Unsupported constant: {}", other)),
+        }
+        code.push(0x0b);
+        Ok(code)
+    }
+
+    fn encode_func(&mut self, items: &[SExpr]) -> Result<Vec<u8>> {
+        let mut locals: HashMap<String, u32> = HashMap::new();
+        let mut local_types: Vec<ValType> = Vec::new();
+        let mut params_cnt = 0;
+        let mut body_start = items.len();
+        for (i, item) in items.iter().enumerate() {
+            let decl = match *item {
+                SExpr::List(ref decl) => decl,
+                _ => {
+                    body_start = i;
+                    break;
+                }
+            };
+            match head(decl).as_ref() {
+                "export" | "result" => {}
+                kind @ "param" | kind @ "local" => {
+                    let types = match decl.get(1) {
+                        Some(SExpr::Atom(name)) if name.starts_with('$') => {
+                            locals.insert(name.clone(), params_cnt + local_types.len() as u32);
+                            &decl[2..]
+                        }
+                        _ => &decl[1..],
+                    };
+                    for t in types {
+                        match kind == "param" {
+                            true => params_cnt += 1,
+                            false => local_types.push(parse_val_type(t)?),
+                        }
+                    }
+                }
+                _ => {
+                    body_start = i;
+                    break;
+                }
+            }
+        }
+        let mut out = Vec::new();
+        let mut runs: Vec<(u32, ValType)> = Vec::new();
+        for &t in &local_types {
+            match runs.last_mut() {
+                Some(ref mut run) if run.1 == t => run.0 += 1,
+                _ => runs.push((1, t)),
+            }
+        }
+        write_u32(&mut out, runs.len() as u32);
+        for (cnt, t) in runs {
+            write_u32(&mut out, cnt);
+            out.push(val_type_code(t));
+        }
+
+        let mut labels: Vec<Option<String>> = Vec::new();
+        let mut i = body_start;
+        while i < items.len() {
+            let op = as_atom(&items[i])?;
+            i += 1;
+            self.encode_instr(&op, items, &mut i, &locals, &mut labels, &mut out)?;
+        }
+        out.push(0x0b);
+        Ok(out)
+    }
+
+    fn encode_instr(&mut self,
+                    op: &str,
+                    items: &[SExpr],
+                    i: &mut usize,
+                    locals: &HashMap<String, u32>,
+                    labels: &mut Vec<Option<String>>,
+                    out: &mut Vec<u8>)
+                    -> Result<()> {
+        if let Some(code) = simple_opcode(op) {
+            out.extend(code);
+            return Ok(());
+        }
+        if let Some((code, align)) = memory_opcode(op) {
+            out.push(code);
+            let (align, offset) = parse_memarg(items, i, align)?;
+            write_u32(out, align);
+            write_u32(out, offset);
+            return Ok(());
+        }
+        match op {
+            "block" | "loop" | "if" => {
+                let label = match items.get(*i) {
+                    Some(SExpr::Atom(name)) if name.starts_with('$') => {
+                        *i += 1;
+                        Some(name.clone())
+                    }
+                    _ => None,
+                };
+                labels.push(label);
+                out.push(match op {
+                    "block" => 0x02,
+                    "loop" => 0x03,
+                    _ => 0x04,
+                });
+                match items.get(*i) {
+                    Some(SExpr::List(result)) if head(result) == "result" => {
+                        out.push(val_type_code(parse_val_type(&result[1])?));
+                        *i += 1;
+                    }
+                    _ => out.push(0x40),
+                }
+            }
+            "else" => out.push(0x05),
+            "end" => {
+                labels.pop().ok_or("Unexpected end".to_string())?;
+                out.push(0x0b);
+            }
+            "br" | "br_if" => {
+                out.push(if op == "br" { 0x0c } else { 0x0d });
+                let depth = label_depth(&items[*i], labels)?;
+                write_u32(out, depth);
+                *i += 1;
+            }
+            "br_table" => {
+                out.push(0x0e);
+                let mut depths = Vec::new();
+                while let Some(SExpr::Atom(label)) = items.get(*i) {
+                    if !label.starts_with('$') {
+                        break;
+                    }
+                    depths.push(label_depth(&items[*i], labels)?);
+                    *i += 1;
+                }
+                let default = depths.pop().ok_or("br_table without labels".to_string())?;
+                write_u32(out, depths.len() as u32);
+                for depth in depths {
+                    write_u32(out, depth);
+                }
+                write_u32(out, default);
+            }
+            "call" | "return_call" => {
+                out.push(if op == "call" { 0x10 } else { 0x12 });
+                let idx = self.func_idx(&items[*i])?;
+                write_u32(out, idx);
+                *i += 1;
+            }
+            "call_indirect" | "return_call_indirect" => {
+                out.push(if op == "call_indirect" { 0x11 } else { 0x13 });
+                let start = *i;
+                while let Some(&SExpr::List(_)) = items.get(*i) {
+                    *i += 1;
+                }
+                let t = parse_func_type(&items[start..*i])?;
+                let type_idx = self.type_idx(t);
+                write_u32(out, type_idx);
+                out.push(0x00);
+            }
+            "local.get" | "local.set" | "local.tee" => {
+                out.push(match op {
+                    "local.get" => 0x20,
+                    "local.set" => 0x21,
+                    _ => 0x22,
+                });
+                let name = as_atom(&items[*i])?;
+                let idx = locals.get(&name).cloned().ok_or(format!("Unknown local: {}", name))?;
+                write_u32(out, idx);
+                *i += 1;
+            }
+            "global.get" | "global.set" => {
+                out.push(if op == "global.get" { 0x23 } else { 0x24 });
+                let idx = self.global_idx(&items[*i])?;
+                write_u32(out, idx);
+                *i += 1;
+            }
+            "i32.const" => {
+                out.push(0x41);
+                write_i64(out, parse_i32(&items[*i])? as i64);
+                *i += 1;
+            }
+            "i64.const" => {
+                out.push(0x42);
+                write_i64(out, parse_i64(&items[*i])?);
+                *i += 1;
+            }
+            "f64.const" => {
+                out.push(0x44);
+                out.extend(&parse_f64(&items[*i])?.to_bits().to_le_bytes());
+                *i += 1;
+            }
+            _ => return Err(format!("Unsupported instruction: {}", op)),
+        }
+        Ok(())
+    }
+}
+
+fn label_depth(e: &SExpr, labels: &[Option<String>]) -> Result<u32> {
+    let name = as_atom(e)?;
+    match labels.iter().rev().position(|l| l.as_ref() == Some(&name)) {
+        Some(depth) => Ok(depth as u32),
+        None => Err(format!("Unknown label: {}", name)),
+    }
+}
+
+// offset=N and align=N, the alignment is given in bytes
+fn parse_memarg(items: &[SExpr], i: &mut usize, natural_align: u32) -> Result<(u32, u32)> {
+    let mut align = natural_align;
+    let mut offset = 0;
+    while let Some(SExpr::Atom(arg)) = items.get(*i) {
+        if let Some(value) = arg.strip_prefix("offset=") {
+            offset = value.parse().map_err(|_| format!("Bad offset: {}", arg))?;
+        } else if let Some(value) = arg.strip_prefix("align=") {
+            let bytes: u32 = value.parse().map_err(|_| format!("Bad align: {}", arg))?;
+            align = bytes.trailing_zeros();
+        } else {
+            break;
+        }
+        *i += 1;
+    }
+    Ok((align, offset))
+}
+
+fn simple_opcode(op: &str) -> Option<Vec<u8>> {
+    let code: u8 = match op {
+        "unreachable" => 0x00,
+        "nop" => 0x01,
+        "return" => 0x0f,
+        "drop" => 0x1a,
+        "select" => 0x1b,
+        "i32.eqz" => 0x45,
+        "i32.eq" => 0x46,
+        "i32.ne" => 0x47,
+        "i32.lt_s" => 0x48,
+        "i32.lt_u" => 0x49,
+        "i32.gt_s" => 0x4a,
+        "i32.gt_u" => 0x4b,
+        "i32.le_s" => 0x4c,
+        "i32.le_u" => 0x4d,
+        "i32.ge_s" => 0x4e,
+        "i32.ge_u" => 0x4f,
+        "i64.eqz" => 0x50,
+        "i64.eq" => 0x51,
+        "i64.ne" => 0x52,
+        "i64.lt_s" => 0x53,
+        "i64.lt_u" => 0x54,
+        "i64.gt_s" => 0x55,
+        "i64.gt_u" => 0x56,
+        "i64.le_s" => 0x57,
+        "i64.le_u" => 0x58,
+        "i64.ge_s" => 0x59,
+        "i64.ge_u" => 0x5a,
+        "f64.eq" => 0x61,
+        "f64.ne" => 0x62,
+        "f64.lt" => 0x63,
+        "f64.gt" => 0x64,
+        "f64.le" => 0x65,
+        "f64.ge" => 0x66,
+        "i32.clz" => 0x67,
+        "i32.ctz" => 0x68,
+        "i32.add" => 0x6a,
+        "i32.sub" => 0x6b,
+        "i32.mul" => 0x6c,
+        "i32.div_s" => 0x6d,
+        "i32.div_u" => 0x6e,
+        "i32.rem_s" => 0x6f,
+        "i32.rem_u" => 0x70,
+        "i32.and" => 0x71,
+        "i32.or" => 0x72,
+        "i32.xor" => 0x73,
+        "i32.shl" => 0x74,
+        "i32.shr_s" => 0x75,
+        "i32.shr_u" => 0x76,
+        "i64.clz" => 0x79,
+        "i64.ctz" => 0x7a,
+        "i64.add" => 0x7c,
+        "i64.sub" => 0x7d,
+        "i64.mul" => 0x7e,
+        "i64.div_s" => 0x7f,
+        "i64.div_u" => 0x80,
+        "i64.rem_s" => 0x81,
+        "i64.rem_u" => 0x82,
+        "i64.and" => 0x83,
+        "i64.or" => 0x84,
+        "i64.xor" => 0x85,
+        "i64.shl" => 0x86,
+        "i64.shr_s" => 0x87,
+        "i64.shr_u" => 0x88,
+        "f64.abs" => 0x99,
+        "f64.neg" => 0x9a,
+        "f64.floor" => 0x9c,
+        "f64.trunc" => 0x9d,
+        "f64.sqrt" => 0x9f,
+        "f64.add" => 0xa0,
+        "f64.sub" => 0xa1,
+        "f64.mul" => 0xa2,
+        "f64.div" => 0xa3,
+        "i32.wrap_i64" => 0xa7,
+        "i32.trunc_f64_s" => 0xaa,
+        "i64.extend_i32_s" => 0xac,
+        "i64.extend_i32_u" => 0xad,
+        "i64.trunc_f64_s" => 0xb0,
+        "f64.convert_i32_s" => 0xb7,
+        "f64.convert_i64_s" => 0xb9,
+        "i64.reinterpret_f64" => 0xbd,
+        "f64.reinterpret_i64" => 0xbf,
+        "memory.size" => return Some(vec![0x3f, 0x00]),
+        "memory.grow" => return Some(vec![0x40, 0x00]),
+        "i32.trunc_sat_f64_s" => return Some(vec![0xfc, 0x02]),
+        "i64.trunc_sat_f64_s" => return Some(vec![0xfc, 0x06]),
+        "memory.copy" => return Some(vec![0xfc, 0x0a, 0x00, 0x00]),
+        "memory.fill" => return Some(vec![0xfc, 0x0b, 0x00]),
+        _ => return None,
+    };
+    Some(vec![code])
+}
+
+// opcode and the natural alignment (log2 of bytes)
+fn memory_opcode(op: &str) -> Option<(u8, u32)> {
+    match op {
+        "i32.load" => Some((0x28, 2)),
+        "i64.load" => Some((0x29, 3)),
+        "f64.load" => Some((0x2b, 3)),
+        "i32.load8_u" => Some((0x2d, 0)),
+        "i32.store" => Some((0x36, 2)),
+        "i64.store" => Some((0x37, 3)),
+        "f64.store" => Some((0x39, 3)),
+        "i32.store8" => Some((0x3a, 0)),
+        _ => None,
+    }
+}
+
+// (param ...)* (result ...)*, other items are skipped
+fn parse_func_type(items: &[SExpr]) -> Result<FuncType> {
+    let mut params = Vec::new();
+    let mut results = Vec::new();
+    for item in items {
+        let list = match *item {
+            SExpr::List(ref list) => list,
+            _ => break,
+        };
+        match head(list).as_ref() {
+            "param" => {
+                let types = match list.get(1) {
+                    Some(SExpr::Atom(name)) if name.starts_with('$') => &list[2..],
+                    _ => &list[1..],
+                };
+                for t in types {
+                    params.push(parse_val_type(t)?);
+                }
+            }
+            "result" => {
+                for t in &list[1..] {
+                    results.push(parse_val_type(t)?);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok((params, results))
+}
+
+fn parse_val_type(e: &SExpr) -> Result<ValType> {
+    match as_atom(e)?.as_ref() {
+        "i32" => Ok(ValType::I32),
+        "i64" => Ok(ValType::I64),
+        "f64" => Ok(ValType::F64),
+        t => Err(format!("Unsupported type: {}", t)),
+    }
+}
+
+fn val_type_code(t: ValType) -> u8 {
+    match t {
+        ValType::I32 => 0x7f,
+        ValType::I64 => 0x7e,
+        ValType::F64 => 0x7c,
+    }
+}
+
+fn parse_u32(e: &SExpr) -> Result<u32> {
+    let s = as_atom(e)?;
+    s.parse().map_err(|_| format!("Bad number: {}", s))
+}
+
+// integers are written in decimal, they may be given as unsigned
+fn parse_i32(e: &SExpr) -> Result<i32> {
+    let s = as_atom(e)?;
+    match s.parse::<i32>() {
+        Ok(x) => Ok(x),
+        Err(_) => s.parse::<u32>().map(|x| x as i32).map_err(|_| format!("Bad i32: {}", s)),
+    }
+}
+
+fn parse_i64(e: &SExpr) -> Result<i64> {
+    let s = as_atom(e)?;
+    match s.parse::<i64>() {
+        Ok(x) => Ok(x),
+        Err(_) => s.parse::<u64>().map(|x| x as i64).map_err(|_| format!("Bad i64: {}", s)),
+    }
+}
+
+fn parse_f64(e: &SExpr) -> Result<f64> {
+    let s = as_atom(e)?;
+    match s.as_ref() {
+        "nan" => Ok(f64::NAN),
+        "inf" => Ok(f64::INFINITY),
+        "-inf" => Ok(f64::NEG_INFINITY),
+        _ => s.parse().map_err(|_| format!("Bad f64: {}", s)),
+    }
+}
+
+fn as_list(e: &SExpr) -> Result<&Vec<SExpr>> {
+    match *e {
+        SExpr::List(ref items) if !items.is_empty() => Ok(items),
+        _ => Err(format!("Expected a list: {:?}", e)),
+    }
+}
+
+fn as_atom(e: &SExpr) -> Result<String> {
+    match *e {
+        SExpr::Atom(ref s) => Ok(s.clone()),
+        _ => Err(format!("Expected an atom: {:?}", e)),
+    }
+}
+
+fn as_str(e: &SExpr) -> Result<Vec<u8>> {
+    match *e {
+        SExpr::Str(ref s) => Ok(s.clone()),
+        _ => Err(format!("Expected a string: {:?}", e)),
+    }
+}
+
+fn head(items: &[SExpr]) -> String {
+    match items.first() {
+        Some(SExpr::Atom(s)) => s.clone(),
+        _ => String::new(),
+    }
+}
+
+// binary encoding
+
+fn write_u32(out: &mut Vec<u8>, mut x: u32) {
+    loop {
+        let byte = (x & 0x7f) as u8;
+        x >>= 7;
+        if x == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn write_i64(out: &mut Vec<u8>, mut x: i64) {
+    loop {
+        let byte = (x & 0x7f) as u8;
+        x >>= 7;
+        let done = (x == 0 && byte & 0x40 == 0) || (x == -1 && byte & 0x40 != 0);
+        if done {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn write_name(out: &mut Vec<u8>, name: &[u8]) {
+    write_u32(out, name.len() as u32);
+    out.extend(name);
+}
+
+fn write_vec_types(out: &mut Vec<u8>, types: &[ValType]) {
+    write_u32(out, types.len() as u32);
+    for &t in types {
+        out.push(val_type_code(t));
+    }
+}
+
+// empty sections are skipped
+fn write_section(out: &mut Vec<u8>, id: u8, cnt: u32, contents: &[u8]) {
+    if cnt == 0 {
+        return;
+    }
+    let mut section = Vec::new();
+    write_u32(&mut section, cnt);
+    section.extend(contents);
+    out.push(id);
+    write_u32(out, section.len() as u32);
+    out.extend(section);
+}
@@ -0,0 +1,99 @@
+use ast::{Class, Type};
+use mangling::*;
+
+use wasm_generation::class_data::*;
+use wasm_generation::context::*;
+use wasm_generation::generate::*;
+use wasm_generation::wasm_type::*;
+
+impl GenerateCode<()> for Class {
+    fn generate_code(&self, ctx: &mut Context) {
+        ctx.in_new_scope(|ctx| {
+            let id = ctx.get_class_id(&self.name);
+            generate_new(id, ctx);
+            generate_init(id, ctx);
+
+            ctx.class = Some(id);
+            ctx.static_class = Some(id);
+            for m in &self.methods {
+                m.generate_code(ctx);
+            }
+            ctx.class = None;
+            for m in &self.static_methods {
+                m.generate_code(ctx);
+            }
+            ctx.static_class = None;
+        });
+    }
+}
+
+fn generate_new(id: ClassId, ctx: &mut Context) {
+    let size = ctx.get_class_data(id).get_size();
+    let class = ctx.get_class_data(id).ident.clone();
+    ctx.begin_block(format!("(func ${} (result i32)", new_symbol(&class)));
+    ctx.add_line("(local $obj i32)".to_string());
+    ctx.add_line(format!("i32.const {}", size));
+    ctx.add_line("call $_new_object".to_string());
+    ctx.add_line("local.tee $obj".to_string());
+    ctx.add_line(format!("call ${}", init_symbol(&class)));
+    ctx.add_line("local.get $obj".to_string());
+    ctx.end_field();
+    ctx.add_empty_line();
+}
+
+fn generate_init(id: ClassId, ctx: &mut Context) {
+    let init = init_symbol(&ctx.get_class_data(id).ident);
+    ctx.begin_block(format!("(func ${} (param $obj i32)", init));
+    let has_strings = ctx.get_class_data(id).fields.iter().any(|(_, t)| *t == Type::TString);
+    if has_strings {
+        ctx.add_line("(local $empty_str i32)".to_string());
+    }
+    if let Some(super_id) = ctx.get_class_data(id).super_id {
+        ctx.add_line("local.get $obj".to_string());
+        let super_init = init_symbol(&ctx.get_class_data(super_id).ident);
+        ctx.add_line(format!("call ${}", super_init));
+    }
+
+    let vtable_base = ctx.get_class_data(id).vtable_base;
+    ctx.add_store(&obj_field(0, &Type::TInt), &format!("i32.const {}", vtable_base));
+
+    init_strings(id, ctx);
+    init_vars(id, ctx);
+
+    ctx.end_field();
+    ctx.add_empty_line();
+}
+
+fn obj_field(offset: u32, t: &Type) -> LValue {
+    LValue::Memory("local.get $obj".to_string(), offset, t.clone())
+}
+
+// all string fields share one empty string
+fn init_strings(id: ClassId, ctx: &mut Context) {
+    let fields = ctx.get_class_data(id).fields.clone();
+    if !fields.iter().any(|(_, t)| *t == Type::TString) {
+        return;
+    }
+
+    ctx.add_line("call $_alloc_str".to_string());
+    ctx.add_line("local.set $empty_str".to_string());
+    for (field, t) in fields {
+        if t != Type::TString {
+            continue;
+        }
+        let offset = ctx.get_class_data(id).get_field_offset(&field);
+        ctx.add_store(&obj_field(offset, &t), "local.get $empty_str");
+        ctx.retain_string("local.get $empty_str");
+    }
+}
+
+fn init_vars(id: ClassId, ctx: &mut Context) {
+    let fields = ctx.get_class_data(id).fields.clone();
+    for (field, t) in fields {
+        if t == Type::TString {
+            continue;
+        }
+        let offset = ctx.get_class_data(id).get_field_offset(&field);
+        ctx.add_store(&obj_field(offset, &t), &zero(&t));
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+use ast::{Ident, Type};
+
+use wasm_generation::wasm_type::*;
+
+pub type ClassId = usize;
+pub type EnumId = usize;
+
+// Same layout as in LLVM: a root class starts with its vtable, which is the index
+// of its first method in the table, a subclass with its superclass, followed by
+// the fields of the class. Fields are aligned to their sizes.
+#[derive(Debug, Clone)]
+pub struct ClassData {
+    pub super_id: Option<ClassId>,
+    pub ident: Ident,
+    pub fields: Vec<(Ident, Type)>,
+    field_ids: HashMap<Ident, usize>,
+    offsets: Vec<u32>,
+    pub size: u32,
+    pub vtable: VTable,
+    pub vtable_base: u32,
+}
+
+impl ClassData {
+    pub fn new(ident: &Ident) -> ClassData {
+        ClassData {
+            super_id: None,
+            ident: ident.clone(),
+            fields: Vec::new(),
+            field_ids: HashMap::new(),
+            offsets: Vec::new(),
+            size: 4,
+            vtable: VTable::new(),
+            vtable_base: 0,
+        }
+    }
+
+    // the superclass is set before the fields
+    pub fn set_super(&mut self, id: ClassId, size: u32) {
+        self.super_id = Some(id);
+        self.size = size;
+    }
+
+    pub fn get_super(&self) -> ClassId {
+        self.super_id.unwrap()
+    }
+
+    pub fn has_field(&self, ident: &Ident) -> bool {
+        self.field_ids.contains_key(ident)
+    }
+
+    pub fn add_field(&mut self, ident: &Ident, t: &Type) {
+        let id = self.fields.len();
+        let size = size_of(t);
+        let offset = align(self.size, size);
+        self.size = offset + size;
+        self.field_ids.insert(ident.clone(), id);
+        self.fields.push((ident.clone(), t.clone()));
+        self.offsets.push(offset);
+    }
+
+    pub fn get_field_type(&self, ident: &Ident) -> Type {
+        self.fields[*self.field_ids.get(ident).unwrap()].1.clone()
+    }
+
+    pub fn get_field_offset(&self, ident: &Ident) -> u32 {
+        self.offsets[*self.field_ids.get(ident).unwrap()]
+    }
+
+    // size of the object, a multiple of 8
+    pub fn get_size(&self) -> u32 {
+        align(self.size, 8)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VTable {
+    pub fs: Vec<VTableEntry>,
+    pub idxs: HashMap<Ident, usize>,
+}
+
+impl VTable {
+    pub fn new() -> VTable {
+        VTable {
+            fs: Vec::new(),
+            idxs: HashMap::new(),
+        }
+    }
+
+    pub fn set_func(&mut self, ident: &Ident, f: VTableEntry) {
+        let cur_idx = self.idxs.get(ident).copied();
+        match cur_idx {
+            None => {
+                let idx = self.fs.len();
+                self.idxs.insert(ident.clone(), idx);
+                self.fs.push(f);
+            }
+            Some(idx) => {
+                self.fs[idx] = f;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VTableEntry {
+    pub real_ident: String, // name of the wasm function
+    pub class_id: ClassId,  // class which defines the method
+    pub ret_type: Type,
+    pub arg_types: Vec<Type>, // without self
+}
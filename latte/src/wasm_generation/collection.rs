@@ -0,0 +1,172 @@
+use ast::{Expr, Ident, Type};
+
+use wasm_generation::context::*;
+use wasm_generation::expr::*;
+use wasm_generation::generate::*;
+use wasm_generation::wasm_type::*;
+
+pub fn generate_new_collection(t: &Type, ctx: &mut Context) -> String {
+    match *t {
+        Type::TStringBuilder => ctx.add_line("call $_builder_new".to_string()),
+        Type::TList(ref elem_t) => {
+            ctx.add_line(format!("i32.const {}", (**elem_t == Type::TString) as i32));
+            ctx.add_line("call $_list_new".to_string());
+        }
+        Type::TMap(ref key_t, ref value_t) => {
+            ctx.add_line(format!("i32.const {}", (**key_t == Type::TString) as i32));
+            ctx.add_line(format!("i32.const {}", (**value_t == Type::TString) as i32));
+            ctx.add_line("call $_map_new".to_string());
+        }
+        _ => unreachable!(),
+    }
+    ctx.add_tmp(t)
+}
+
+pub fn generate_collection_call(obj: &str,
+                                obj_t: &Type,
+                                method: &Ident,
+                                args: &[Expr],
+                                ctx: &mut Context)
+                                -> (String, Type) {
+    match *obj_t {
+        Type::TStringBuilder => generate_builder_call(obj, method, args, ctx),
+        Type::TList(ref elem_t) => generate_list_call(obj, elem_t, method, args, ctx),
+        Type::TMap(ref key_t, ref value_t) => {
+            generate_map_call(obj, key_t, value_t, method, args, ctx)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// Strings passed to collections are retained by the runtime. Elements returned by pop
+// and remove are owned by the caller.
+fn generate_list_call(list: &str,
+                      elem_t: &Type,
+                      method: &Ident,
+                      args: &[Expr],
+                      ctx: &mut Context)
+                      -> (String, Type) {
+    match method.0.as_ref() {
+        "push" => {
+            let elem = generate_init_expr(&args[0], elem_t, ctx);
+            ctx.add_line(list.to_string());
+            push_slot(&elem, elem_t, ctx);
+            add_call("call $_list_push".to_string(), &Type::TVoid, ctx)
+        }
+        "pop" => {
+            ctx.add_line(list.to_string());
+            ctx.add_line("call $_list_pop".to_string());
+            (from_slot_tmp(elem_t, ctx), elem_t.clone())
+        }
+        "insert" => {
+            let (idx, _) = args[0].generate_code(ctx);
+            let elem = generate_init_expr(&args[1], elem_t, ctx);
+            ctx.add_line(list.to_string());
+            ctx.add_line(idx);
+            push_slot(&elem, elem_t, ctx);
+            add_call("call $_list_insert".to_string(), &Type::TVoid, ctx)
+        }
+        "remove" => {
+            let (idx, _) = args[0].generate_code(ctx);
+            ctx.add_line(list.to_string());
+            ctx.add_line(idx);
+            ctx.add_line("call $_list_remove".to_string());
+            (from_slot_tmp(elem_t, ctx), elem_t.clone())
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn generate_map_call(map: &str,
+                     key_t: &Type,
+                     value_t: &Type,
+                     method: &Ident,
+                     args: &[Expr],
+                     ctx: &mut Context)
+                     -> (String, Type) {
+    match method.0.as_ref() {
+        "put" => {
+            let key = generate_init_expr(&args[0], key_t, ctx);
+            let value = generate_init_expr(&args[1], value_t, ctx);
+            ctx.add_line(map.to_string());
+            push_slot(&key, key_t, ctx);
+            push_slot(&value, value_t, ctx);
+            add_call("call $_map_put".to_string(), &Type::TVoid, ctx)
+        }
+        "get" => {
+            let key = generate_init_expr(&args[0], key_t, ctx);
+            ctx.add_line(map.to_string());
+            push_slot(&key, key_t, ctx);
+            ctx.add_line("call $_map_get".to_string());
+            let value = from_slot_tmp(value_t, ctx);
+            if *value_t == Type::TString {
+                ctx.retain_string(&value);
+            }
+            (value, value_t.clone())
+        }
+        "contains" => {
+            let key = generate_init_expr(&args[0], key_t, ctx);
+            ctx.add_line(map.to_string());
+            push_slot(&key, key_t, ctx);
+            add_call("call $_map_contains".to_string(), &Type::TBool, ctx)
+        }
+        "remove" => {
+            let key = generate_init_expr(&args[0], key_t, ctx);
+            ctx.add_line(map.to_string());
+            push_slot(&key, key_t, ctx);
+            add_call("call $_map_remove".to_string(), &Type::TVoid, ctx)
+        }
+        "keys" => generate_map_keys(map, key_t, ctx),
+        _ => unreachable!(),
+    }
+}
+
+// The characters of appended strings are copied, the result of toString is owned by
+// the caller.
+fn generate_builder_call(builder: &str,
+                         method: &Ident,
+                         args: &[Expr],
+                         ctx: &mut Context)
+                         -> (String, Type) {
+    match method.0.as_ref() {
+        "append" => {
+            let (s, _) = args[0].generate_code(ctx);
+            ctx.add_line(builder.to_string());
+            ctx.add_line(s);
+            add_call("call $_builder_append".to_string(), &Type::TVoid, ctx)
+        }
+        "appendChar" => {
+            let (c, _) = args[0].generate_code(ctx);
+            ctx.add_line(builder.to_string());
+            ctx.add_line(c);
+            add_call("call $_builder_append_char".to_string(), &Type::TVoid, ctx)
+        }
+        "toString" => {
+            ctx.add_line(builder.to_string());
+            add_call("call $_builder_to_string".to_string(), &Type::TString, ctx)
+        }
+        _ => unreachable!(),
+    }
+}
+
+pub fn generate_map_keys(map: &str, key_t: &Type, ctx: &mut Context) -> (String, Type) {
+    let keys_t = Type::TList(Box::new(key_t.clone()));
+    ctx.add_line(map.to_string());
+    ctx.add_line("call $_map_keys".to_string());
+    (ctx.add_tmp(&keys_t), keys_t)
+}
+
+fn push_slot(val: &str, t: &Type, ctx: &mut Context) {
+    ctx.add_line(val.to_string());
+    if let Some(conv) = to_slot(t) {
+        ctx.add_line(conv.to_string());
+    }
+}
+
+// the slot on top of the stack as a value of the given type
+fn from_slot_tmp(t: &Type, ctx: &mut Context) -> String {
+    if let Some(conv) = from_slot(t) {
+        ctx.add_line(conv.to_string());
+    }
+    ctx.add_tmp(t)
+}
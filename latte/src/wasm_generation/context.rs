@@ -0,0 +1,550 @@
+use std::collections::{HashMap, HashSet};
+
+use ast::{Expr, Ident, Type};
+use mangling::demangle;
+use options::Options;
+
+use wasm_generation::class_data::*;
+use wasm_generation::wasm_type::*;
+
+// Location of a value: a local, a global or an address with a constant offset.
+// Elements of lists are in 64-bit slots.
+#[derive(Debug, Clone)]
+pub enum LValue {
+    Local(String),
+    Global(String),
+    Memory(String, u32, Type),
+    Slot(String, Type),
+}
+
+#[derive(Debug)]
+pub struct Context {
+    vars: HashMap<Ident, (String, Type)>,
+    globals: HashMap<Ident, (String, Type)>,
+    static_fields: HashMap<(ClassId, Ident), (String, Type)>,
+    static_methods: HashMap<(ClassId, Ident), Ident>, // to their symbols
+    func_types: HashMap<Ident, (Vec<Type>, Type)>,
+    builtins: HashSet<Ident>, // functions of the runtime, called by their own names
+    externs: HashSet<Ident>,
+    string_lits: HashMap<String, u32>,
+    pub ret_type: Type,
+    pub class: Option<ClassId>,
+    pub static_class: Option<ClassId>, // class whose static members are accessible directly
+    pub opts: Options,
+    pub current_func: String,
+    pub tail_target: Option<TailTarget>,
+    devirtualized: Vec<String>, // report of calls that don't use vtables
+
+    classes: HashMap<ClassId, ClassData>,
+    class_ids: HashMap<Ident, ClassId>,
+    enum_ids: HashMap<Ident, EnumId>,
+    enum_variants: Vec<Vec<Ident>>,
+
+    string_tmps: Vec<String>,
+    local_string_tmps: Vec<String>,
+    string_vars: Vec<String>,
+    local_string_vars: Vec<String>,
+
+    local_names: HashMap<Ident, usize>, // declarations of each name in the current function
+    locals: Vec<(String, &'static str)>, // declared at the beginning of the function
+    tmps: usize,
+    labels: usize,
+    indent: usize,
+    out: Vec<String>,
+}
+
+// The function being generated, self-recursive tail calls set the params
+// and jump to the beginning of the body.
+#[derive(Debug, Clone)]
+pub struct TailTarget {
+    pub symbol: String,
+    pub params: Vec<String>, // with self first for methods
+    pub body_label: Option<String>,
+}
+
+impl Context {
+    pub fn new(opts: &Options) -> Context {
+        Context {
+            vars: HashMap::new(),
+            globals: HashMap::new(),
+            static_fields: HashMap::new(),
+            static_methods: HashMap::new(),
+            func_types: HashMap::new(),
+            builtins: HashSet::new(),
+            externs: HashSet::new(),
+            string_lits: HashMap::new(),
+            ret_type: Type::TVoid,
+            class: None,
+            static_class: None,
+            opts: opts.clone(),
+            current_func: String::new(),
+            tail_target: None,
+            devirtualized: Vec::new(),
+
+            classes: HashMap::new(),
+            class_ids: HashMap::new(),
+            enum_ids: HashMap::new(),
+            enum_variants: Vec::new(),
+
+            string_tmps: Vec::new(),
+            local_string_tmps: Vec::new(),
+            string_vars: Vec::new(),
+            local_string_vars: Vec::new(),
+
+            local_names: HashMap::new(),
+            locals: Vec::new(),
+            tmps: 0,
+            labels: 0,
+            indent: 0,
+            out: Vec::new(),
+        }
+    }
+
+    pub fn in_new_scope<F, R>(&mut self, f: F) -> R
+        where F: Fn(&mut Context) -> R
+    {
+        let old_vars = self.vars.clone();
+        let old_string_tmps = self.string_tmps.clone();
+        let old_local_string_tmps = self.local_string_tmps.clone();
+        let old_string_vars = self.string_vars.clone();
+        let old_local_string_vars = self.local_string_vars.clone();
+
+        self.local_string_tmps.clear();
+        self.local_string_vars.clear();
+        let res = f(self);
+
+        self.local_string_vars = old_local_string_vars;
+        self.string_vars = old_string_vars;
+        self.local_string_tmps = old_local_string_tmps;
+        self.string_tmps = old_string_tmps;
+        self.vars = old_vars;
+        res
+    }
+
+    // A structured instruction (block, loop or if) with a new scope inside.
+    pub fn in_new_block<F, R>(&mut self, header: String, f: F) -> R
+        where F: Fn(&mut Context) -> R
+    {
+        self.begin_block(header);
+        let res = self.in_new_scope(f);
+        self.end_block();
+        res
+    }
+
+    // Loop over indexes from 0 to size - 1, the index is a new i32 local.
+    pub fn in_new_loop<F, R>(&mut self, idx: &str, size: &str, f: F) -> R
+        where F: Fn(&mut Context) -> R
+    {
+        let label = self.next_label();
+        self.add_local(idx, &Type::TInt);
+        self.add_line("i32.const 0".to_string());
+        self.add_line(format!("local.set {}", idx));
+        self.begin_block(format!("block $b{}", label));
+        self.begin_block(format!("loop $l{}", label));
+        self.add_line(format!("local.get {}", idx));
+        self.add_line(size.to_string());
+        self.add_line("i32.ge_s".to_string());
+        self.add_line(format!("br_if $b{}", label));
+        let res = self.in_new_scope(f);
+        self.add_line(format!("local.get {}", idx));
+        self.add_line("i32.const 1".to_string());
+        self.add_line("i32.add".to_string());
+        self.add_line(format!("local.set {}", idx));
+        self.add_line(format!("br $l{}", label));
+        self.end_block();
+        self.end_block();
+        res
+    }
+
+    pub fn begin_block(&mut self, header: String) {
+        self.add_line(header);
+        self.indent += 1;
+    }
+
+    pub fn end_block(&mut self) {
+        self.indent -= 1;
+        self.add_line("end".to_string());
+    }
+
+    // ends a function or another field of the module
+    pub fn end_field(&mut self) {
+        self.indent -= 1;
+        self.add_line(")".to_string());
+    }
+
+    // ends a block and begins the next one, i.e. else
+    pub fn next_block(&mut self, header: String) {
+        self.indent -= 1;
+        self.add_line(header);
+        self.indent += 1;
+    }
+
+    pub fn func_exists(&self, ident: &Ident) -> bool {
+        self.func_types.contains_key(ident)
+    }
+
+    pub fn get_arg_types(&self, ident: &Ident) -> Vec<Type> {
+        self.func_types.get(ident).unwrap().0.clone()
+    }
+
+    pub fn get_ret_type(&self, ident: &Ident) -> Type {
+        self.func_types.get(ident).unwrap().1.clone()
+    }
+
+    // Methods are named by their mangled symbols in the context, as in LLVM,
+    // other functions are prefixed, so that they can't clash with them.
+    pub fn get_func_symbol(&self, ident: &Ident) -> String {
+        if self.builtins.contains(ident) || demangle(&ident.0).is_some() {
+            format!("${}", ident)
+        } else if self.externs.contains(ident) {
+            format!("$e_{}", ident)
+        } else {
+            format!("$f_{}", ident)
+        }
+    }
+
+    pub fn get_str_const(&self, s: &String) -> u32 {
+        *self.string_lits.get(s).unwrap()
+    }
+
+    pub fn set_str_const(&mut self, s: String, addr: u32) {
+        self.string_lits.insert(s, addr);
+    }
+
+    pub fn var_exists(&self, ident: &Ident) -> bool {
+        self.vars.contains_key(ident)
+    }
+
+    pub fn get_var(&self, ident: &Ident) -> (String, Type) {
+        self.vars.get(ident).unwrap().clone()
+    }
+
+    pub fn set_var(&mut self, ident: Ident, name: String, t: Type) {
+        self.vars.insert(ident, (name.clone(), t.clone()));
+        if t == Type::TString {
+            self.string_vars.push(format!("local.get {}", name));
+            self.local_string_vars.push(format!("local.get {}", name));
+        }
+    }
+
+    // Names of locals are unique in a function, an initializer can refer to
+    // a variable of an outer block with the same name.
+    pub fn new_local_name(&mut self, ident: &Ident) -> String {
+        let cnt = self.local_names.entry(ident.clone()).or_insert(0);
+        *cnt += 1;
+        // variables introduced by optimizations start with a dot
+        let (prefix, name) = match ident.0.starts_with('.') {
+            true => ("h", &ident.0[1..]),
+            false => ("l", &ident.0[..]),
+        };
+        match *cnt {
+            1 => format!("${}_{}", prefix, name),
+            n => format!("${}{}_{}", prefix, n, name),
+        }
+    }
+
+    pub fn has_globals(&self) -> bool {
+        !self.globals.is_empty() || !self.static_fields.is_empty()
+    }
+
+    pub fn global_exists(&self, ident: &Ident) -> bool {
+        self.globals.contains_key(ident)
+    }
+
+    pub fn get_global(&self, ident: &Ident) -> (String, Type) {
+        self.globals.get(ident).unwrap().clone()
+    }
+
+    pub fn add_global(&mut self, ident: &Ident, t: &Type) {
+        let name = format!("$g_{}", ident);
+        self.add_line(format!("(global {} (mut {}) ({}))", name, wasm_type(t), zero(t)));
+        self.globals.insert(ident.clone(), (name, t.clone()));
+    }
+
+    pub fn add_static_field(&mut self, class: ClassId, ident: &Ident, t: &Type) {
+        let name = format!("$static{}_{}", class, ident);
+        self.add_line(format!("(global {} (mut {}) ({}))", name, wasm_type(t), zero(t)));
+        self.static_fields.insert((class, ident.clone()), (name, t.clone()));
+    }
+
+    pub fn add_static_method(&mut self, class: ClassId, ident: &Ident, symbol: &Ident) {
+        self.static_methods.insert((class, ident.clone()), symbol.clone());
+    }
+
+    // static members are inherited
+
+    pub fn find_static_field(&self, mut id: ClassId, ident: &Ident) -> Option<(String, Type)> {
+        loop {
+            if let Some(field) = self.static_fields.get(&(id, ident.clone())) {
+                return Some(field.clone());
+            }
+            id = self.get_class_data(id).super_id?;
+        }
+    }
+
+    // returns the name of the generated function
+    pub fn find_static_method(&self, mut id: ClassId, ident: &Ident) -> Option<Ident> {
+        loop {
+            if let Some(symbol) = self.static_methods.get(&(id, ident.clone())) {
+                return Some(symbol.clone());
+            }
+            id = self.get_class_data(id).super_id?;
+        }
+    }
+
+    pub fn add_func(&mut self, ident: &Ident, arg_types: Vec<Type>, ret_type: Type) {
+        self.func_types.insert(ident.clone(), (arg_types, ret_type));
+    }
+
+    pub fn add_builtin_func(&mut self, ident: &Ident, arg_types: Vec<Type>, ret_type: Type) {
+        self.add_func(ident, arg_types, ret_type);
+        self.builtins.insert(ident.clone());
+    }
+
+    pub fn add_extern_func(&mut self, ident: &Ident, arg_types: Vec<Type>, ret_type: Type) {
+        self.add_func(ident, arg_types, ret_type);
+        self.externs.insert(ident.clone());
+    }
+
+    pub fn is_extern(&self, ident: &Ident) -> bool {
+        self.externs.contains(ident)
+    }
+
+    // string reference counting, strings are given by instructions which push them
+    pub fn add_string_tmp(&mut self, val: String) {
+        self.string_tmps.push(val.clone());
+        self.local_string_tmps.push(val);
+    }
+
+    pub fn release_local_strings(&mut self) {
+        let strs = self.local_string_tmps.clone();
+        self.release_strings(strs);
+        let strs = self.local_string_vars.clone();
+        self.release_strings(strs);
+    }
+
+    pub fn release_all_strings(&mut self) {
+        let strs = self.string_tmps.clone();
+        self.release_strings(strs);
+        let strs = self.string_vars.clone();
+        self.release_strings(strs);
+    }
+
+    fn release_strings(&mut self, vals: Vec<String>) {
+        for val in vals {
+            self.add_line(val);
+            self.add_line("call $_release_str".to_string());
+        }
+    }
+
+    pub fn retain_string(&mut self, val: &str) {
+        self.add_line(val.to_string());
+        self.add_line("call $_retain_str".to_string());
+    }
+
+    // class
+    pub fn add_class_id(&mut self, cname: &Ident, id: ClassId) {
+        self.class_ids.insert(cname.clone(), id);
+    }
+
+    pub fn add_class(&mut self, id: ClassId, cdata: ClassData) {
+        self.classes.insert(id, cdata);
+    }
+
+    pub fn set_vtable(&mut self, id: ClassId, vtable: VTable, base: u32) {
+        let cdata = self.classes.get_mut(&id).unwrap();
+        cdata.vtable = vtable;
+        cdata.vtable_base = base;
+    }
+
+    pub fn class_exists(&self, cname: &Ident) -> bool {
+        self.class_ids.contains_key(cname)
+    }
+
+    pub fn get_class_id(&self, cname: &Ident) -> ClassId {
+        *self.class_ids.get(cname).unwrap()
+    }
+
+    pub fn get_class_data(&self, id: ClassId) -> &ClassData {
+        self.classes.get(&id).unwrap()
+    }
+
+    pub fn has_field(&self, mut id: ClassId, field: &Ident) -> bool {
+        while !self.get_class_data(id).has_field(field) {
+            match self.get_class_data(id).super_id {
+                Some(super_id) => id = super_id,
+                None => return false,
+            }
+        }
+        true
+    }
+
+    // Class hierarchy analysis: whether a subclass of the class replaces
+    // the method at the given position of its vtable.
+    pub fn is_overridden(&self, id: ClassId, vtable_pos: usize) -> bool {
+        let real_ident = &self.get_class_data(id).vtable.fs[vtable_pos].real_ident;
+        self.classes.iter().any(|(&sub_id, cdata)| {
+            sub_id != id && self.is_subclass_of(sub_id, id) &&
+            cdata.vtable.fs[vtable_pos].real_ident != *real_ident
+        })
+    }
+
+    pub fn add_devirtualized(&mut self, call: &Expr, method_class: ClassId, method: &Ident) {
+        let msg = format!("{}: {} calls {}.{}",
+                          self.current_func,
+                          call,
+                          self.get_class_data(method_class).ident,
+                          method);
+        self.devirtualized.push(msg);
+    }
+
+    pub fn take_devirtualized(&mut self) -> Vec<String> {
+        self.devirtualized.drain(..).collect()
+    }
+
+    pub fn is_subclass_of(&self, mut id: ClassId, super_id: ClassId) -> bool {
+        while self.get_class_data(id).super_id.is_some() && id != super_id {
+            id = self.get_class_data(id).get_super();
+        }
+        id == super_id
+    }
+
+    // enums
+    pub fn add_enum(&mut self, name: &Ident, variants: &[Ident]) -> EnumId {
+        let id = self.enum_variants.len();
+        self.enum_ids.insert(name.clone(), id);
+        self.enum_variants.push(variants.to_owned());
+        id
+    }
+
+    pub fn enum_exists(&self, name: &Ident) -> bool {
+        self.enum_ids.contains_key(name)
+    }
+
+    pub fn get_enum_id(&self, name: &Ident) -> EnumId {
+        *self.enum_ids.get(name).unwrap()
+    }
+
+    pub fn get_variant_tag(&self, id: EnumId, variant: &Ident) -> i32 {
+        self.enum_variants[id].iter().position(|v| v == variant).unwrap() as i32
+    }
+
+    // lvalues
+
+    // pushes the value
+    pub fn add_load(&mut self, lvalue: &LValue) {
+        match *lvalue {
+            LValue::Local(ref name) => self.add_line(format!("local.get {}", name)),
+            LValue::Global(ref name) => self.add_line(format!("global.get {}", name)),
+            LValue::Memory(ref addr, offset, ref t) => {
+                self.add_line(addr.to_string());
+                self.add_line(format!("{}.load{}", wasm_type(t), offset_str(offset)));
+            }
+            LValue::Slot(ref addr, ref t) => {
+                self.add_line(addr.to_string());
+                self.add_line("i64.load".to_string());
+                if let Some(conv) = from_slot(t) {
+                    self.add_line(conv.to_string());
+                }
+            }
+        }
+    }
+
+    pub fn add_store(&mut self, lvalue: &LValue, val: &str) {
+        match *lvalue {
+            LValue::Local(ref name) => {
+                self.add_line(val.to_string());
+                self.add_line(format!("local.set {}", name));
+            }
+            LValue::Global(ref name) => {
+                self.add_line(val.to_string());
+                self.add_line(format!("global.set {}", name));
+            }
+            LValue::Memory(ref addr, offset, ref t) => {
+                self.add_line(addr.to_string());
+                self.add_line(val.to_string());
+                self.add_line(format!("{}.store{}", wasm_type(t), offset_str(offset)));
+            }
+            LValue::Slot(ref addr, ref t) => {
+                self.add_line(addr.to_string());
+                self.add_line(val.to_string());
+                if let Some(conv) = to_slot(t) {
+                    self.add_line(conv.to_string());
+                }
+                self.add_line("i64.store".to_string());
+            }
+        }
+    }
+
+    // output
+
+    // Stores the value on top of the stack in a new temporary, so that it's
+    // evaluated in order. Returns the instruction which pushes it.
+    pub fn add_tmp(&mut self, t: &Type) -> String {
+        let name = self.next_tmp();
+        self.add_local(&name, t);
+        self.add_line(format!("local.set {}", name));
+        format!("local.get {}", name)
+    }
+
+    pub fn next_tmp(&mut self) -> String {
+        self.tmps += 1;
+        format!("$t{}", self.tmps)
+    }
+
+    pub fn next_label(&mut self) -> usize {
+        self.labels += 1;
+        self.labels
+    }
+
+    pub fn add_local(&mut self, name: &str, t: &Type) {
+        self.locals.push((name.to_string(), wasm_type(t)));
+    }
+
+    // the names of temporaries, labels and locals start over in each function
+    pub fn reset(&mut self) {
+        self.tmps = 0;
+        self.labels = 0;
+        self.local_names.clear();
+        self.locals.clear();
+    }
+
+    // A line where the locals of the function are declared once its body is generated.
+    pub fn reserve_line(&mut self) -> usize {
+        self.out.push(String::new());
+        self.out.len() - 1
+    }
+
+    pub fn fill_locals(&mut self, idx: usize) {
+        let indent = "  ".repeat(self.indent);
+        let decls: Vec<String> = self.locals
+            .iter()
+            .map(|&(ref name, t)| format!("{}(local {} {})", indent, name, t))
+            .collect();
+        self.out.splice(idx..idx + 1, decls);
+    }
+
+    pub fn add_line(&mut self, s: String) {
+        let indent = "  ".repeat(self.indent);
+        self.out.push(format!("{}{}", indent, s));
+    }
+
+    pub fn add_comment(&mut self, s: String) {
+        self.add_line(format!(";; {}", s));
+    }
+
+    pub fn add_empty_line(&mut self) {
+        self.out.push(String::new());
+    }
+
+    pub fn get_out(&self) -> &Vec<String> {
+        &self.out
+    }
+}
+
+pub fn offset_str(offset: u32) -> String {
+    match offset {
+        0 => String::new(),
+        n => format!(" offset={}", n),
+    }
+}
@@ -0,0 +1,32 @@
+use ast::Enum;
+use mangling::*;
+
+use wasm_generation::context::Context;
+use wasm_generation::generate::*;
+
+// Generates name() of the enum, which maps tags to names of the variants.
+// Tags out of range are not possible, so the first variant is the default.
+impl GenerateCode<()> for Enum {
+    fn generate_code(&self, ctx: &mut Context) {
+        let name = enum_name_symbol(&self.name);
+        ctx.begin_block(format!("(func ${} (param $tag i32) (result i32)", name));
+        ctx.add_line("(local $name i32)".to_string());
+        let first = ctx.get_str_const(&self.variants[0].0);
+        ctx.add_line(format!("i32.const {}", first));
+        ctx.add_line("local.set $name".to_string());
+        for (tag, variant) in self.variants.iter().enumerate().skip(1) {
+            let name = ctx.get_str_const(&variant.0);
+            ctx.add_line("local.get $tag".to_string());
+            ctx.add_line(format!("i32.const {}", tag));
+            ctx.add_line("i32.eq".to_string());
+            ctx.begin_block("if".to_string());
+            ctx.add_line(format!("i32.const {}", name));
+            ctx.add_line("local.set $name".to_string());
+            ctx.end_block();
+        }
+        ctx.retain_string("local.get $name");
+        ctx.add_line("local.get $name".to_string());
+        ctx.end_field();
+        ctx.add_empty_line();
+    }
+}
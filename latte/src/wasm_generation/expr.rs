@@ -0,0 +1,618 @@
+use ast::*;
+use mangling::*;
+
+use wasm_generation::collection::*;
+use wasm_generation::context::*;
+use wasm_generation::extern_func::*;
+use wasm_generation::field_get::*;
+use wasm_generation::generate::*;
+use wasm_generation::wasm_type::*;
+
+// Values are instructions which push them. Every value which isn't a constant
+// or a local is stored in a temporary, so that the order of evaluation is the same
+// as in Latte.
+impl GenerateCode<(String, Type)> for Expr {
+    fn generate_code(&self, ctx: &mut Context) -> (String, Type) {
+        let (val, t) = match *self {
+            Expr::EVar(ref ident) if as_enum_variant(ident, ctx).is_some() => {
+                let (tag, t) = as_enum_variant(ident, ctx).unwrap();
+                (format!("i32.const {}", tag), t)
+            }
+            // locals can't be changed by calls, strings are retained
+            Expr::EVar(FieldGet::Direct(ref ident)) if ctx.var_exists(ident) &&
+                                                       ctx.get_var(ident).1 != Type::TString => {
+                let (name, t) = ctx.get_var(ident);
+                (format!("local.get {}", name), t)
+            }
+            Expr::EVar(ref ident) => {
+                let (lvalue, t) = ident.generate_code(ctx);
+                ctx.add_load(&lvalue);
+                let val = ctx.add_tmp(&t);
+                if t == Type::TString {
+                    ctx.retain_string(&val);
+                }
+                (val, t)
+            }
+            Expr::ELit(ref lit) => lit.generate_code(ctx),
+            Expr::ECall(ref ident, ref args) => generate_call(ident, args, ctx),
+            Expr::ENeg(ref e) => {
+                let (val, t) = e.generate_code(ctx);
+                if t == Type::TDouble {
+                    ctx.add_line(val);
+                    ctx.add_line("f64.neg".to_string());
+                    (ctx.add_tmp(&t), t)
+                } else {
+                    (generate_arith_op(&t, &zero(&t), Operator::OpSub, &val, ctx), t)
+                }
+            }
+            Expr::ENot(ref e) => {
+                let (val, t) = e.generate_code(ctx);
+                ctx.add_line(val);
+                ctx.add_line("i32.eqz".to_string());
+                (ctx.add_tmp(&t), t)
+            }
+            Expr::EBitNot(ref e) => {
+                let (val, t) = e.generate_code(ctx);
+                ctx.add_line(val);
+                ctx.add_line(format!("{}.const -1", wasm_type(&t)));
+                ctx.add_line(format!("{}.xor", wasm_type(&t)));
+                (ctx.add_tmp(&t), t)
+            }
+            Expr::EBinOp(ref lhs, Operator::OpOr, ref rhs) => generate_or(lhs, rhs, ctx),
+            Expr::EBinOp(ref lhs, Operator::OpAnd, ref rhs) => generate_and(lhs, rhs, ctx),
+            Expr::EBinOp(ref lhs, Operator::OpNEq, ref rhs) => generate_eq(lhs, rhs, true, ctx),
+            Expr::EBinOp(ref lhs, Operator::OpEq, ref rhs) => generate_eq(lhs, rhs, false, ctx),
+            Expr::EBinOp(ref lhs, Operator::OpAdd, ref rhs) => generate_add(lhs, rhs, ctx),
+            Expr::EBinOp(ref lhs, ref op, ref rhs) => {
+                let (lhs_val, lhs_t) = lhs.generate_code(ctx);
+                let (rhs_val, _) = rhs.generate_code(ctx);
+                if lhs_t == Type::TString {
+                    return generate_str_cmp(&lhs_val, *op, &rhs_val, ctx);
+                }
+                // shifts use only the lowest 5 bits of the count, as i32.shl does
+                let t = match *op {
+                    Operator::OpLess | Operator::OpLessE | Operator::OpGreater |
+                    Operator::OpGreaterE => Type::TBool,
+                    Operator::OpAdd | Operator::OpSub | Operator::OpMul | Operator::OpDiv |
+                    Operator::OpMod | Operator::OpBitAnd | Operator::OpBitOr |
+                    Operator::OpBitXor | Operator::OpShl | Operator::OpShr => lhs_t.clone(),
+                    _ => unreachable!(),
+                };
+                (generate_arith_op(&lhs_t, &lhs_val, *op, &rhs_val, ctx), t)
+            }
+            Expr::ENew(ref t) => {
+                match *t {
+                    Type::TObject(ref cname) => {
+                        ctx.add_line(format!("call ${}", new_symbol(cname)));
+                        (ctx.add_tmp(t), t.clone())
+                    }
+                    _ => (generate_new_collection(t, ctx), t.clone()),
+                }
+            }
+            Expr::ENewArray(ref t, ref sizes) => {
+                let mut arr_t = t.clone();
+                let mut size_vals: Vec<String> = Vec::new();
+                for size in sizes {
+                    size_vals.push(size.generate_code(ctx).0);
+                    arr_t = Type::TArray(Box::new(arr_t));
+                }
+                (new_arr(&arr_t, &size_vals, ctx), arr_t)
+            }
+            Expr::EArrayLit(..) => unreachable!("array literal without destination type"),
+            Expr::ECast(ref t, ref e) => {
+                let (val, e_t) = e.generate_code(ctx);
+                match e_t == *t {
+                    true => (val, e_t),
+                    false => (generate_cast(&val, &e_t, t, ctx), t.clone()),
+                }
+            }
+            Expr::ECond(ref cond, ref e1, ref e2) => generate_cond(cond, e1, e2, ctx),
+        };
+        if t == Type::TString {
+            ctx.add_string_tmp(val.clone());
+        }
+        (val, t)
+    }
+}
+
+fn generate_call(ident: &FieldGet, args: &[Expr], ctx: &mut Context) -> (String, Type) {
+    let call = match prepare_call(ident, args, ctx) {
+        PreparedCall::Generated(val, t) => return (val, t),
+        PreparedCall::Call(call) => call,
+    };
+    let call_instr = push_call(&call, "call", ctx);
+    add_call(call_instr, &call.ret_type, ctx)
+}
+
+// Adds the call instruction, whose args are already pushed. The result of a void
+// call is empty.
+pub fn add_call(call: String, ret_type: &Type, ctx: &mut Context) -> (String, Type) {
+    ctx.add_line(call);
+    match *ret_type {
+        Type::TVoid => (String::new(), Type::TVoid),
+        _ => (ctx.add_tmp(ret_type), ret_type.clone()),
+    }
+}
+
+pub struct Call {
+    pub func: String, // name of the function, or its type if it's called through the vtable
+    pub vtable_pos: Option<usize>,
+    pub ret_type: Type,
+    pub args: Vec<(String, Type)>, // evaluated, with self first for methods
+}
+
+pub enum PreparedCall {
+    Generated(String, Type), // methods of collections and enums are generated in place
+    Call(Call),
+}
+
+// Pushes the args and the index of a virtual method in the table, returns
+// the call instruction: call or return_call.
+pub fn push_call(call: &Call, instr: &str, ctx: &mut Context) -> String {
+    for (arg, _) in &call.args {
+        ctx.add_line(arg.clone());
+    }
+    match call.vtable_pos {
+        Some(pos) => {
+            // the vtable is at the beginning of every object
+            ctx.add_line(call.args[0].0.clone());
+            ctx.add_line("i32.load".to_string());
+            ctx.add_line(format!("i32.const {}", pos));
+            ctx.add_line("i32.add".to_string());
+            format!("{}_indirect {}", instr, call.func)
+        }
+        None => format!("{} {}", instr, call.func),
+    }
+}
+
+// Evaluates the object and args of the call.
+pub fn prepare_call(ident: &FieldGet, args: &[Expr], ctx: &mut Context) -> PreparedCall {
+    let (obj, func_name): (Option<(String, Type)>, Ident) = ident.generate_code(ctx);
+    let obj = match obj {
+        Some((val, Type::TObject(ref cname))) => Some((val, ctx.get_class_id(cname))),
+        // the only method of enums is name()
+        Some((val, Type::TEnum(ref name))) => {
+            ctx.add_line(val);
+            let func = format!("call ${}", enum_name_symbol(name));
+            let (val, t) = add_call(func, &Type::TString, ctx);
+            return PreparedCall::Generated(val, t);
+        }
+        Some((val, obj_t)) => {
+            let (val, t) = generate_collection_call(&val, &obj_t, &func_name, args, ctx);
+            return PreparedCall::Generated(val, t);
+        }
+        None if ctx.is_extern(&func_name) => {
+            let (val, t) = generate_extern_call(&func_name, args, ctx);
+            return PreparedCall::Generated(val, t);
+        }
+        None => None,
+    };
+    let ret_type;
+    let arg_types;
+    let func;
+    let mut vtable_pos = None;
+    let mut final_args: Vec<(String, Type)> = Vec::new();
+    if let Some((obj, id)) = obj {
+        let pos = *ctx.get_class_data(id).vtable.idxs.get(&func_name).unwrap();
+        let f_info = ctx.get_class_data(id).vtable.fs[pos].clone();
+
+        ret_type = f_info.ret_type.clone();
+        arg_types = f_info.arg_types.clone();
+        if ctx.is_overridden(id, pos) {
+            let mut params = vec!["i32"];
+            params.extend(arg_types.iter().map(wasm_type));
+            func = format!("(param {}){}", params.join(" "), result_decl(&ret_type));
+            vtable_pos = Some(pos);
+        } else {
+            if ctx.opts.report_devirt {
+                let call = Expr::ECall(ident.clone(), args.to_owned());
+                ctx.add_devirtualized(&call, f_info.class_id, &func_name);
+            }
+            func = format!("${}", f_info.real_ident);
+        }
+        let obj_t = Type::TObject(ctx.get_class_data(f_info.class_id).ident.clone());
+        final_args.push((obj, obj_t));
+    } else {
+        ret_type = ctx.get_ret_type(&func_name);
+        arg_types = ctx.get_arg_types(&func_name);
+        func = ctx.get_func_symbol(&func_name);
+    }
+
+    for (arg, arg_dst_t) in args.iter().zip(arg_types) {
+        final_args.push((generate_init_expr(arg, &arg_dst_t, ctx), arg_dst_t));
+    }
+
+    PreparedCall::Call(Call {
+        func,
+        vtable_pos,
+        ret_type,
+        args: final_args,
+    })
+}
+
+// Generates value to be stored in a location of type t (variable, argument etc.),
+// objects of subclasses have the same addresses as their superclasses.
+pub fn generate_init_expr(e: &Expr, t: &Type, ctx: &mut Context) -> String {
+    match *e {
+        Expr::EArrayLit(ref elems) => generate_array_lit(elems, t, ctx),
+        _ => e.generate_code(ctx).0,
+    }
+}
+
+pub fn arr_elem_t(arr_t: &Type) -> Type {
+    match *arr_t {
+        Type::TArray(ref elem_t) => *elem_t.clone(),
+        _ => unreachable!(),
+    }
+}
+
+// elements are zeroed
+fn alloc_arr(arr_t: &Type, size: &str, ctx: &mut Context) -> String {
+    let elem_t = arr_elem_t(arr_t);
+    ctx.add_line(size.to_string());
+    ctx.add_line(format!("i32.const {}", size_of(&elem_t)));
+    ctx.add_line("call $_new_array".to_string());
+    ctx.add_tmp(arr_t)
+}
+
+// sizes of subsequent dimensions, subarrays of missing dimensions are null
+fn new_arr(arr_t: &Type, sizes: &[String], ctx: &mut Context) -> String {
+    let arr = alloc_arr(arr_t, &sizes[0], ctx);
+    let elem_t = arr_elem_t(arr_t);
+    if sizes.len() > 1 {
+        let idx = ctx.next_tmp();
+        ctx.in_new_loop(&idx, &sizes[0], |ctx| {
+            let subarr = new_arr(&elem_t, &sizes[1..], ctx);
+            let addr = elem_addr(&arr, &format!("local.get {}", idx), &elem_t, ctx);
+            ctx.add_store(&LValue::Memory(addr, 8, elem_t.clone()), &subarr);
+        });
+    } else if elem_t == Type::TString {
+        ctx.add_line(arr.to_string());
+        ctx.add_line("call $_init_str_arr".to_string());
+    }
+    arr
+}
+
+fn generate_array_lit(elems: &[Expr], arr_t: &Type, ctx: &mut Context) -> String {
+    let elem_t = arr_elem_t(arr_t);
+    let arr = alloc_arr(arr_t, &format!("i32.const {}", elems.len()), ctx);
+    for (idx, e) in elems.iter().enumerate() {
+        let val = generate_init_expr(e, &elem_t, ctx);
+        if elem_t == Type::TString {
+            ctx.retain_string(&val);
+        }
+        let offset = 8 + idx as u32 * size_of(&elem_t);
+        ctx.add_store(&LValue::Memory(arr.clone(), offset, elem_t.clone()), &val);
+    }
+    arr
+}
+
+fn generate_or(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> (String, Type) {
+    generate_short_circuit(lhs, rhs, true, ctx)
+}
+
+fn generate_and(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> (String, Type) {
+    generate_short_circuit(lhs, rhs, false, ctx)
+}
+
+// rhs is evaluated if lhs (negated for ||) is true
+fn generate_short_circuit(lhs: &Expr,
+                          rhs: &Expr,
+                          negate: bool,
+                          ctx: &mut Context)
+                          -> (String, Type) {
+    let lhs_val = ctx.in_new_scope(|ctx| {
+        let (lhs_val, _) = lhs.generate_code(ctx);
+        ctx.release_local_strings();
+        lhs_val
+    });
+    let res = ctx.next_tmp();
+    ctx.add_local(&res, &Type::TBool);
+    ctx.add_store(&LValue::Local(res.clone()), &lhs_val);
+    ctx.add_line(format!("local.get {}", res));
+    if negate {
+        ctx.add_line("i32.eqz".to_string());
+    }
+    ctx.in_new_block("if".to_string(), |ctx| {
+        let (rhs_val, _) = rhs.generate_code(ctx);
+        ctx.release_local_strings();
+        ctx.add_store(&LValue::Local(res.clone()), &rhs_val);
+    });
+    (format!("local.get {}", res), Type::TBool)
+}
+
+// Both branches store their values in the same local, null and objects of all
+// classes have the same type.
+fn generate_cond(cond: &Expr, e1: &Expr, e2: &Expr, ctx: &mut Context) -> (String, Type) {
+    let (cond_val, _) = cond.generate_code(ctx);
+    let res = ctx.next_tmp();
+
+    ctx.add_line(cond_val);
+    ctx.begin_block("if".to_string());
+    let e1_t = ctx.in_new_scope(|ctx| generate_cond_branch(e1, &res, ctx));
+    ctx.next_block("else".to_string());
+    let e2_t = ctx.in_new_scope(|ctx| generate_cond_branch(e2, &res, ctx));
+    ctx.end_block();
+
+    let t = common_type(&e1_t, &e2_t, ctx);
+    if t == Type::TVoid {
+        return (String::new(), t);
+    }
+    ctx.add_local(&res, &t);
+    (format!("local.get {}", res), t)
+}
+
+fn generate_cond_branch(e: &Expr, res: &str, ctx: &mut Context) -> Type {
+    let (val, t) = e.generate_code(ctx);
+    if t == Type::TString {
+        ctx.retain_string(&val);
+    }
+    ctx.release_local_strings();
+    if t != Type::TVoid {
+        ctx.add_store(&LValue::Local(res.to_string()), &val);
+    }
+    t
+}
+
+// least common supertype of types accepted by the type checker
+fn common_type(t1: &Type, t2: &Type, ctx: &Context) -> Type {
+    if t1 == t2 || *t2 == Type::TNull {
+        return t1.clone();
+    }
+    if *t1 == Type::TNull {
+        return t2.clone();
+    }
+    let (mut id, id2) = match (t1, t2) {
+        (Type::TObject(c1), Type::TObject(c2)) => {
+            (ctx.get_class_id(c1), ctx.get_class_id(c2))
+        }
+        _ => unreachable!(),
+    };
+    while !ctx.is_subclass_of(id2, id) {
+        id = ctx.get_class_data(id).get_super();
+    }
+    Type::TObject(ctx.get_class_data(id).ident.clone())
+}
+
+enum AddOperand {
+    Strings(Vec<String>), // parts of a concatenation, in order
+    Value(String, Type),
+}
+
+// Concatenations of more than two strings are done at once, the types are known
+// only here, so the chains are flattened during generation. The parts are passed
+// in a temporary array.
+fn generate_add(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> (String, Type) {
+    let parts = match generate_add_operands(lhs, rhs, ctx) {
+        AddOperand::Strings(parts) => parts,
+        AddOperand::Value(val, t) => return (val, t),
+    };
+    if parts.len() == 2 {
+        ctx.add_line(parts[0].clone());
+        ctx.add_line(parts[1].clone());
+        ctx.add_line("call $_concatenate".to_string());
+        return (ctx.add_tmp(&Type::TString), Type::TString);
+    }
+    ctx.add_line(format!("i32.const {}", 4 * parts.len()));
+    ctx.add_line("call $_malloc".to_string());
+    let arr = ctx.add_tmp(&Type::TInt);
+    for (idx, part) in parts.iter().enumerate() {
+        ctx.add_store(&LValue::Memory(arr.clone(), 4 * idx as u32, Type::TString), part);
+    }
+    ctx.add_line(arr.clone());
+    ctx.add_line(format!("i32.const {}", parts.len()));
+    ctx.add_line("call $_concatenate_n".to_string());
+    let res = ctx.add_tmp(&Type::TString);
+    ctx.add_line(arr);
+    ctx.add_line("call $_free".to_string());
+    (res, Type::TString)
+}
+
+fn generate_add_operands(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> AddOperand {
+    match generate_add_operand(lhs, ctx) {
+        AddOperand::Strings(mut parts) => {
+            match generate_add_operand(rhs, ctx) {
+                AddOperand::Strings(rhs_parts) => parts.extend(rhs_parts),
+                AddOperand::Value(val, _) => parts.push(val),
+            }
+            AddOperand::Strings(parts)
+        }
+        AddOperand::Value(lhs_val, t) => {
+            let (rhs_val, _) = rhs.generate_code(ctx);
+            let val = generate_arith_op(&t, &lhs_val, Operator::OpAdd, &rhs_val, ctx);
+            AddOperand::Value(val, t)
+        }
+    }
+}
+
+fn generate_add_operand(e: &Expr, ctx: &mut Context) -> AddOperand {
+    match *e {
+        Expr::EBinOp(ref lhs, Operator::OpAdd, ref rhs) => generate_add_operands(lhs, rhs, ctx),
+        _ => {
+            let (val, t) = e.generate_code(ctx);
+            match t == Type::TString {
+                true => AddOperand::Strings(vec![val]),
+                false => AddOperand::Value(val, t),
+            }
+        }
+    }
+}
+
+// Integer arithmetic wraps around on overflow, unless overflow checking is enabled.
+pub fn generate_arith_op(t: &Type,
+                         lhs: &str,
+                         op: Operator,
+                         rhs: &str,
+                         ctx: &mut Context)
+                         -> String {
+    let is_div = op == Operator::OpDiv || op == Operator::OpMod;
+    let is_checked_op = op == Operator::OpAdd || op == Operator::OpSub || op == Operator::OpMul ||
+                        (is_div && may_be_minus_one(rhs));
+    let is_int = *t == Type::TInt || *t == Type::TLong;
+    let res_t = match op {
+        Operator::OpLess | Operator::OpLessE | Operator::OpGreater | Operator::OpGreaterE |
+        Operator::OpEq => Type::TBool,
+        _ => t.clone(),
+    };
+    ctx.add_line(lhs.to_string());
+    ctx.add_line(rhs.to_string());
+    if ctx.opts.check_overflow && is_checked_op && is_int {
+        let suffix = wasm_type(t);
+        ctx.add_line(format!("call $_checked_{}_{}", checked_op_name(op), suffix));
+    } else if is_int && op == Operator::OpDiv && is_checked_op {
+        // MIN / -1 traps in div_s, MIN % -1 is 0 in rem_s
+        ctx.add_line(format!("call $_div_{}", wasm_type(t)));
+    } else {
+        ctx.add_line(format!("{}.{}", wasm_type(t), op_instr(t, op)));
+    }
+    ctx.add_tmp(&res_t)
+}
+
+fn checked_op_name(op: Operator) -> &'static str {
+    match op {
+        Operator::OpAdd => "add",
+        Operator::OpSub => "sub",
+        Operator::OpMul => "mul",
+        Operator::OpDiv => "div",
+        Operator::OpMod => "mod",
+        _ => unreachable!(),
+    }
+}
+
+// unless it's a constant
+fn may_be_minus_one(val: &str) -> bool {
+    let words: Vec<&str> = val.split(' ').collect();
+    match (words.len(), words[0]) {
+        (2, "i32.const") | (2, "i64.const") => words[1] == "-1",
+        _ => true,
+    }
+}
+
+// Integers are signed, except chars, which are compared as unsigned as in C.
+fn op_instr(t: &Type, op: Operator) -> &'static str {
+    let is_double = *t == Type::TDouble;
+    let is_char = *t == Type::TChar;
+    match op {
+        Operator::OpAdd => "add",
+        Operator::OpSub => "sub",
+        Operator::OpMul => "mul",
+        Operator::OpDiv if is_double => "div",
+        Operator::OpDiv => "div_s",
+        Operator::OpMod => "rem_s",
+        Operator::OpEq => "eq",
+        Operator::OpLess if is_double => "lt",
+        Operator::OpLess if is_char => "lt_u",
+        Operator::OpLess => "lt_s",
+        Operator::OpLessE if is_double => "le",
+        Operator::OpLessE if is_char => "le_u",
+        Operator::OpLessE => "le_s",
+        Operator::OpGreater if is_double => "gt",
+        Operator::OpGreater if is_char => "gt_u",
+        Operator::OpGreater => "gt_s",
+        Operator::OpGreaterE if is_double => "ge",
+        Operator::OpGreaterE if is_char => "ge_u",
+        Operator::OpGreaterE => "ge_s",
+        Operator::OpBitAnd => "and",
+        Operator::OpBitOr => "or",
+        Operator::OpBitXor => "xor",
+        Operator::OpShl => "shl",
+        Operator::OpShr => "shr_s",
+        _ => unreachable!(),
+    }
+}
+
+fn generate_eq(lhs: &Expr, rhs: &Expr, negate: bool, ctx: &mut Context) -> (String, Type) {
+    let (lhs_val, t1) = lhs.generate_code(ctx);
+    let (rhs_val, t2) = rhs.generate_code(ctx);
+    if t1 == Type::TNull && t2 == Type::TNull {
+        return (format!("i32.const {}", !negate as i32), Type::TBool);
+    }
+    ctx.add_line(lhs_val);
+    ctx.add_line(rhs_val);
+    if t1 == Type::TString && t2 == Type::TString {
+        ctx.add_line("call $_str_eq".to_string());
+        if negate {
+            ctx.add_line("i32.eqz".to_string());
+        }
+    } else {
+        // objects of different classes are compared as addresses of their beginnings
+        let instr = match negate {
+            false => "eq",
+            true => "ne",
+        };
+        ctx.add_line(format!("{}.{}", wasm_type(&t1), instr));
+    }
+    (ctx.add_tmp(&Type::TBool), Type::TBool)
+}
+
+// lexicographic order of the contents
+fn generate_str_cmp(lhs: &str, op: Operator, rhs: &str, ctx: &mut Context) -> (String, Type) {
+    ctx.add_line(lhs.to_string());
+    ctx.add_line(rhs.to_string());
+    ctx.add_line("call $_str_cmp".to_string());
+    ctx.add_line("i32.const 0".to_string());
+    ctx.add_line(format!("i32.{}", op_instr(&Type::TInt, op)));
+    (ctx.add_tmp(&Type::TBool), Type::TBool)
+}
+
+// Conversions are the same as in LLVM: doubles are truncated, chars are unsigned
+// and narrowing keeps the lowest bits.
+fn generate_cast(val: &str, from: &Type, to: &Type, ctx: &mut Context) -> String {
+    ctx.add_line(val.to_string());
+    let instr = match (wasm_type(from), wasm_type(to)) {
+        ("i32", "i64") if *from == Type::TChar => Some("i64.extend_i32_u"),
+        ("i32", "i64") => Some("i64.extend_i32_s"),
+        ("i64", "i32") => Some("i32.wrap_i64"),
+        ("f64", "i32") => Some("i32.trunc_sat_f64_s"),
+        ("f64", "i64") => Some("i64.trunc_sat_f64_s"),
+        ("i32", "f64") if *from == Type::TChar => Some("f64.convert_i32_u"),
+        ("i32", "f64") => Some("f64.convert_i32_s"),
+        ("i64", "f64") => Some("f64.convert_i64_s"),
+        _ => None,
+    };
+    if let Some(instr) = instr {
+        ctx.add_line(instr.to_string());
+    }
+    if *to == Type::TChar {
+        ctx.add_line("i32.const 255".to_string());
+        ctx.add_line("i32.and".to_string());
+    }
+    ctx.add_tmp(to)
+}
+
+impl GenerateCode<(String, Type)> for Lit {
+    fn generate_code(&self, ctx: &mut Context) -> (String, Type) {
+        match *self {
+            Lit::LInt(x) => (format!("i32.const {}", x), Type::TInt),
+            Lit::LLong(x) => (format!("i64.const {}", x), Type::TLong),
+            Lit::LDouble(x) => (format!("f64.const {}", double_lit(x)), Type::TDouble),
+            Lit::LChar(c) => (format!("i32.const {}", c), Type::TChar),
+            Lit::LTrue => ("i32.const 1".to_string(), Type::TBool),
+            Lit::LFalse => ("i32.const 0".to_string(), Type::TBool),
+            // constant strings are never freed, their addresses are values
+            Lit::LString(ref s) => {
+                let val = format!("i32.const {}", ctx.get_str_const(s));
+                ctx.retain_string(&val);
+                (val, Type::TString)
+            }
+            Lit::LNull(None) => ("i32.const 0".to_string(), Type::TNull),
+            Lit::LNull(Some(ref cname)) => {
+                ("i32.const 0".to_string(), Type::TObject(cname.clone()))
+            }
+        }
+    }
+}
+
+// the shortest representation which is read back as the same number
+fn double_lit(x: f64) -> String {
+    if x.is_nan() {
+        "nan".to_string()
+    } else if x.is_infinite() {
+        match x > 0.0 {
+            true => "inf".to_string(),
+            false => "-inf".to_string(),
+        }
+    } else {
+        format!("{:?}", x)
+    }
+}
@@ -0,0 +1,53 @@
+use ast::{BuiltinFunc, Expr, Ident, Type};
+
+use wasm_generation::context::Context;
+use wasm_generation::expr::*;
+use wasm_generation::wasm_type::*;
+
+// Extern functions are imported from the "extern" module, named <name>:<args>:<result>
+// with a letter for each type, so that the host can convert the values, see runtime.js.
+// Strings and arrays are passed as addresses, valid only during the call, and returned
+// strings are owned by the caller.
+pub fn add_extern_func(f: &BuiltinFunc, ctx: &mut Context) {
+    let letters: String = f.args.iter().map(type_letter).collect();
+    let params: Vec<&str> = f.args.iter().map(wasm_type).collect();
+    let params = match params.is_empty() {
+        true => String::new(),
+        false => format!(" (param {})", params.join(" ")),
+    };
+    ctx.add_line(format!("(import \"extern\" \"{}:{}:{}\" (func $e_{}{}{}))",
+                         f.ident,
+                         letters,
+                         type_letter(&f.ret_type),
+                         f.ident,
+                         params,
+                         result_decl(&f.ret_type)));
+    ctx.add_extern_func(&f.ident, f.args.clone(), f.ret_type.clone());
+}
+
+pub fn generate_extern_call(ident: &Ident, args: &[Expr], ctx: &mut Context) -> (String, Type) {
+    let mut vals: Vec<String> = Vec::new();
+    for (arg, t) in args.iter().zip(ctx.get_arg_types(ident)) {
+        vals.push(generate_init_expr(arg, &t, ctx));
+    }
+    for val in vals {
+        ctx.add_line(val);
+    }
+    let call = format!("call {}", ctx.get_func_symbol(ident));
+    add_call(call, &ctx.get_ret_type(ident), ctx)
+}
+
+fn type_letter(t: &Type) -> char {
+    match *t {
+        Type::TInt => 'i',
+        Type::TLong => 'l',
+        Type::TDouble => 'd',
+        Type::TBool => 'b',
+        Type::TChar => 'c',
+        Type::TString => 's',
+        Type::TVoid => 'v',
+        // arrays of the primitive types
+        Type::TArray(ref elem_t) => type_letter(elem_t).to_ascii_uppercase(),
+        _ => unreachable!(),
+    }
+}
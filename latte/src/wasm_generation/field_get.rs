@@ -0,0 +1,160 @@
+use ast::*;
+
+use wasm_generation::class_data::*;
+use wasm_generation::context::*;
+use wasm_generation::generate::*;
+use wasm_generation::wasm_type::*;
+
+// Returns an lvalue: a variable, a field or an element, whose object and index are
+// already evaluated.
+impl GenerateCode<(LValue, Type)> for FieldGet {
+    fn generate_code(&self, ctx: &mut Context) -> (LValue, Type) {
+        match *self {
+            FieldGet::Direct(ref ident) => {
+                if ctx.var_exists(ident) {
+                    let (name, t) = ctx.get_var(ident);
+                    (LValue::Local(name), t)
+                } else if let Some((name, t)) = get_static_field(ident, ctx) {
+                    (LValue::Global(name), t)
+                } else if ctx.global_exists(ident) && !is_self_field(ident, ctx) {
+                    let (name, t) = ctx.get_global(ident);
+                    (LValue::Global(name), t)
+                } else {
+                    self_access(ident).generate_code(ctx)
+                }
+            }
+            FieldGet::IdxAccess(ref arr, ref idx) => {
+                let (arr, arr_t) = arr.generate_code(ctx);
+                let (idx, _) = idx.generate_code(ctx);
+                match arr_t {
+                    Type::TList(ref elem_t) => {
+                        // the slot is bounds-checked once, the lvalue can be used many times
+                        ctx.add_line(arr);
+                        ctx.add_line(idx);
+                        ctx.add_line("call $_list_at".to_string());
+                        let slot = ctx.add_tmp(&Type::TInt);
+                        (LValue::Slot(slot, *elem_t.clone()), *elem_t.clone())
+                    }
+                    Type::TArray(ref elem_t) => {
+                        let addr = elem_addr(&arr, &idx, elem_t, ctx);
+                        (LValue::Memory(addr, 8, *elem_t.clone()), *elem_t.clone())
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            FieldGet::Indirect(ref expr, ref field) => {
+                if let Some(id) = as_class_id(expr, ctx) {
+                    let (name, t) = ctx.find_static_field(id, field).unwrap();
+                    return (LValue::Global(name), t);
+                }
+                let (obj, obj_t) = expr.generate_code(ctx);
+                match obj_t {
+                    Type::TArray(..) |
+                    Type::TStringBuilder |
+                    Type::TList(..) |
+                    Type::TMap(..) => (LValue::Memory(obj, 0, Type::TInt), Type::TInt),
+                    Type::TObject(ref cname) => {
+                        let mut id = ctx.get_class_id(cname);
+                        while !ctx.get_class_data(id).has_field(field) {
+                            id = ctx.get_class_data(id).get_super();
+                        }
+                        let field_t = ctx.get_class_data(id).get_field_type(field);
+                        let offset = ctx.get_class_data(id).get_field_offset(field);
+                        (LValue::Memory(obj, offset, field_t.clone()), field_t)
+                    }
+                    _ => unreachable!(),
+                }
+            }
+        }
+    }
+}
+
+// Returns the object of a method call and the name of the method or function,
+// as the key of its type in the context.
+impl GenerateCode<(Option<(String, Type)>, Ident)> for FieldGet {
+    fn generate_code(&self, ctx: &mut Context) -> (Option<(String, Type)>, Ident) {
+        match *self {
+            FieldGet::Direct(ref ident) => {
+                if let Some(func) = get_static_method(ident, ctx) {
+                    (None, func)
+                } else if ctx.func_exists(ident) {
+                    (None, ident.clone())
+                } else {
+                    self_access(ident).generate_code(ctx)
+                }
+            }
+            FieldGet::Indirect(ref expr, ref field) => {
+                if let Some(id) = as_class_id(expr, ctx) {
+                    return (None, ctx.find_static_method(id, field).unwrap());
+                }
+                let (val, obj_t) = expr.generate_code(ctx);
+                (Some((val, obj_t)), field.clone())
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+// Address of an element of an array, without the offset of the elements.
+pub fn elem_addr(arr: &str, idx: &str, elem_t: &Type, ctx: &mut Context) -> String {
+    ctx.add_line(arr.to_string());
+    ctx.add_line(idx.to_string());
+    ctx.add_line(format!("i32.const {}", size_of(elem_t)));
+    ctx.add_line("i32.mul".to_string());
+    ctx.add_line("i32.add".to_string());
+    ctx.add_tmp(&Type::TInt)
+}
+
+fn is_self_field(ident: &Ident, ctx: &Context) -> bool {
+    match ctx.class {
+        Some(id) => ctx.has_field(id, ident),
+        None => false,
+    }
+}
+
+fn get_static_field(ident: &Ident, ctx: &Context) -> Option<(String, Type)> {
+    ctx.static_class.and_then(|id| ctx.find_static_field(id, ident))
+}
+
+fn get_static_method(ident: &Ident, ctx: &Context) -> Option<Ident> {
+    ctx.static_class.and_then(|id| ctx.find_static_method(id, ident))
+}
+
+// <class name>.<static member>, unless the name is hidden by another identifier
+fn as_class_id(e: &Expr, ctx: &Context) -> Option<ClassId> {
+    match *e {
+        Expr::EVar(FieldGet::Direct(ref ident)) if !is_hidden(ident, ctx) &&
+                                                   ctx.class_exists(ident) => {
+            Some(ctx.get_class_id(ident))
+        }
+        _ => None,
+    }
+}
+
+// <enum name>.<variant>, unless the name is hidden by another identifier
+pub fn as_enum_variant(field: &FieldGet, ctx: &Context) -> Option<(i32, Type)> {
+    match *field {
+        FieldGet::Indirect(ref e, ref variant) => {
+            match **e {
+                Expr::EVar(FieldGet::Direct(ref ident)) if !is_hidden(ident, ctx) &&
+                                                           ctx.enum_exists(ident) => {
+                    let id = ctx.get_enum_id(ident);
+                    Some((ctx.get_variant_tag(id, variant), Type::TEnum(ident.clone())))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn is_hidden(ident: &Ident, ctx: &Context) -> bool {
+    ctx.var_exists(ident) || ctx.global_exists(ident) || ctx.func_exists(ident) ||
+    is_self_field(ident, ctx) || get_static_field(ident, ctx).is_some() ||
+    get_static_method(ident, ctx).is_some()
+}
+
+fn self_access(ident: &Ident) -> FieldGet {
+    let self_fg = FieldGet::Direct(Ident("self".to_string()));
+    FieldGet::Indirect(Box::new(Expr::EVar(self_fg)), ident.clone())
+}
@@ -0,0 +1,95 @@
+use ast::{Func, Ident, Type};
+
+use code_generation::has_self_tail_call;
+use mangling::*;
+use static_analysis::has_return::*;
+
+use wasm_generation::context::*;
+use wasm_generation::generate::*;
+use wasm_generation::wasm_type::*;
+
+impl GenerateCode<()> for Func {
+    fn generate_code(&self, ctx: &mut Context) {
+        ctx.reset();
+        let mut name = ctx.get_func_symbol(&self.ident);
+        ctx.current_func = self.ident.0.clone();
+        if let Some(id) = ctx.static_class {
+            let class = ctx.get_class_data(id).ident.clone();
+            ctx.current_func = format!("{}.{}", class, self.ident);
+            name = match ctx.class {
+                Some(_) => format!("${}", method_symbol(&class, self)),
+                None => format!("${}", static_method_symbol(&class, self)),
+            };
+        }
+
+        // variable, its local and type
+        let mut params: Vec<(Ident, String, Type)> = Vec::new();
+        if let Some(id) = ctx.class {
+            let self_t = Type::TObject(ctx.get_class_data(id).ident.clone());
+            params.push((Ident("self".to_string()), "$self".to_string(), self_t));
+        }
+        for arg in &self.args {
+            let arg_name = ctx.new_local_name(&arg.ident);
+            params.push((arg.ident.clone(), arg_name, arg.t.clone()));
+        }
+        let decls = params.iter().map(|(_, name, t)| param_decl(name, t)).collect();
+
+        ctx.begin_block(func_header(&self.ret_type, &name, decls));
+        let locals_line = ctx.reserve_line();
+        let is_main = ctx.static_class.is_none() && self.ident.0 == "main";
+        if is_main && ctx.has_globals() {
+            ctx.add_line("call $init_globals".to_string());
+        }
+        let body_label = match has_self_tail_call(self) {
+            true => Some("$body".to_string()),
+            false => None,
+        };
+        ctx.tail_target = Some(TailTarget {
+            symbol: name.clone(),
+            params: params.iter().map(|(_, name, _)| name.clone()).collect(),
+            body_label: body_label.clone(),
+        });
+        ctx.in_new_scope(|ctx| {
+            // args are set in the function scope, so that string args
+            // don't leak into the functions generated later
+            for (ident, name, t) in &params {
+                ctx.set_var(ident.clone(), name.clone(), t.clone());
+                if *t == Type::TString {
+                    ctx.retain_string(&format!("local.get {}", name));
+                }
+            }
+            if let Some(ref label) = body_label {
+                ctx.begin_block(format!("loop {}", label));
+            }
+            ctx.ret_type = self.ret_type.clone();
+            self.body.generate_code(ctx);
+            if !self.body.has_return() {
+                ctx.release_all_strings();
+            }
+            if body_label.is_some() {
+                ctx.end_block();
+            }
+        });
+        // the end is reachable for wasm, even if every path returns
+        if self.ret_type != Type::TVoid {
+            ctx.add_line("unreachable".to_string());
+        }
+        ctx.tail_target = None;
+        ctx.fill_locals(locals_line);
+        ctx.end_field();
+        ctx.add_empty_line();
+    }
+}
+
+pub fn param_decl(name: &str, t: &Type) -> String {
+    format!("(param {} {})", name, wasm_type(t))
+}
+
+pub fn func_header(ret_type: &Type, name: &str, params: Vec<String>) -> String {
+    let mut header = format!("(func {}", name);
+    for param in params {
+        header.push(' ');
+        header.push_str(&param);
+    }
+    format!("{}{}", header, result_decl(ret_type))
+}
@@ -0,0 +1,5 @@
+use wasm_generation::context::*;
+
+pub trait GenerateCode<T> {
+    fn generate_code(&self, ctx: &mut Context) -> T;
+}
@@ -0,0 +1,292 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use std::fs::File;
+
+use ast::*;
+use builtins::*;
+use mangling::*;
+use options::Options;
+use static_analysis::collect_string_lit::*;
+
+mod assemble;
+mod class;
+mod class_data;
+mod collection;
+mod context;
+mod enum_def;
+mod expr;
+mod extern_func;
+mod field_get;
+mod func;
+mod generate;
+mod global;
+mod stmt;
+mod tail_call;
+mod wasm_type;
+
+pub use self::assemble::assemble;
+
+use self::class_data::*;
+use self::context::*;
+use self::extern_func::*;
+use self::generate::*;
+use self::wasm_type::*;
+
+// static data of the module starts after the area reserved for the runtime
+const DATA_START: u32 = 1024;
+
+// Generates a WebAssembly module in the text format, which contains the runtime
+// (lib/runtime.wat) and imports the builtin functions from the host (lib/runtime.js).
+// Objects have the same layout and strings the same reference counting as in LLVM.
+// Returns the report of devirtualized calls.
+pub fn gen_wasm(p: &Program,
+                opts: &Options,
+                runtime: &str,
+                out_file: &mut File)
+                -> Result<Vec<String>, io::Error> {
+    let mut ctx = create_context(p, opts, runtime);
+
+    for def in &p.0 {
+        match *def {
+            Def::DClass(ref c) => c.generate_code(&mut ctx),
+            Def::DFunc(ref f) => f.generate_code(&mut ctx),
+            Def::DEnum(ref e) => e.generate_code(&mut ctx),
+            Def::DGlobal(..) |
+            Def::DExtern(..) => {}
+        }
+    }
+    if ctx.has_globals() {
+        global::generate_init(p, &mut ctx);
+    }
+    generate_start(p, &mut ctx);
+
+    writeln!(out_file, "(module")?;
+    for line in ctx.get_out() {
+        writeln!(out_file, "{}", line)?;
+    }
+    writeln!(out_file, ")")?;
+    Ok(ctx.take_devirtualized())
+}
+
+// Imports of extern functions precede the runtime, which begins with the imports
+// of builtin functions.
+fn create_context(p: &Program, opts: &Options, runtime: &str) -> Context {
+    let mut ctx = Context::new(opts);
+    ctx.add_comment("extern functions".to_string());
+    for def in &p.0 {
+        if let Def::DExtern(ref f) = *def {
+            add_extern_func(f, &mut ctx);
+        }
+    }
+    ctx.add_empty_line();
+
+    for line in runtime.lines() {
+        ctx.add_line(line.to_string());
+    }
+    ctx.add_empty_line();
+    for f in get_builtin_functions() {
+        ctx.add_builtin_func(&f.ident, f.args.clone(), f.ret_type.clone());
+    }
+
+    ctx.add_comment("string literals".to_string());
+    add_string_lits(p, &mut ctx);
+    ctx.add_empty_line();
+
+    for def in &p.0 {
+        if let Def::DEnum(ref e) = *def {
+            ctx.add_enum(&e.name, &e.variants);
+        }
+    }
+
+    add_classes(p, &mut ctx);
+    add_funcs(p, &mut ctx);
+    add_vtables(p, &mut ctx);
+
+    ctx.add_comment("globals".to_string());
+    add_globals(p, &mut ctx);
+    ctx.add_empty_line();
+    ctx
+}
+
+// Literals are constant strings in the static data, they are never freed.
+fn add_string_lits(p: &Program, ctx: &mut Context) {
+    let mut lits: Vec<String> = collect_string_lit(p).into_iter().collect();
+    lits.sort();
+    let mut addr = DATA_START;
+    for lit in lits {
+        let mut bytes: Vec<u8> = Vec::new();
+        for field in &[1, 1, lit.len() as u32] {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        bytes.extend_from_slice(lit.as_bytes());
+        ctx.add_line(format!("(data (i32.const {}) \"{}\")", addr, escape_str(&bytes)));
+        ctx.set_str_const(lit, addr);
+        addr += align(bytes.len() as u32, 4);
+    }
+    let data_end = align(addr, 8);
+    ctx.add_line(format!("(global $_data_end i32 (i32.const {}))", data_end));
+}
+
+fn add_classes(p: &Program, ctx: &mut Context) {
+    let mut classes: Vec<&Class> = Vec::new();
+    for def in &p.0 {
+        if let Def::DClass(ref c) = *def {
+            classes.push(c);
+        }
+    }
+
+    for (id, class) in classes.iter().enumerate() {
+        ctx.add_class_id(&class.name, id);
+    }
+
+    // superclasses are laid out before their subclasses, which contain them
+    let mut added: HashSet<ClassId> = HashSet::new();
+    for id in 0..classes.len() {
+        add_class_data(id, &classes, &mut added, ctx);
+    }
+
+    let mut class_map: HashMap<Ident, &Class> = HashMap::new();
+    for class in &classes {
+        class_map.insert(class.name.clone(), class);
+    }
+    // vtables are consecutive in the table of functions
+    let mut vtable_base = 0;
+    for class in &classes {
+        let vtable = get_vtable(class, &class_map, ctx);
+        let id = ctx.get_class_id(&class.name);
+        let size = vtable.fs.len() as u32;
+        ctx.set_vtable(id, vtable, vtable_base);
+        vtable_base += size;
+    }
+}
+
+fn add_class_data(id: ClassId,
+                  classes: &Vec<&Class>,
+                  added: &mut HashSet<ClassId>,
+                  ctx: &mut Context) {
+    if added.contains(&id) {
+        return;
+    }
+    let class = classes[id];
+    let mut class_data = ClassData::new(&class.name);
+    if let Some(ref super_name) = class.superclass {
+        let super_id = ctx.get_class_id(super_name);
+        add_class_data(super_id, classes, added, ctx);
+        class_data.set_super(super_id, ctx.get_class_data(super_id).get_size());
+    }
+    added.insert(id);
+
+    for v in &class.vars {
+        class_data.add_field(&v.ident, &v.t);
+    }
+    ctx.add_class(id, class_data);
+}
+
+fn get_vtable(c: &Class, classes: &HashMap<Ident, &Class>, ctx: &Context) -> VTable {
+    let mut vtable = VTable::new();
+    if let Some(ref super_ident) = c.superclass {
+        let superclass = classes.get(super_ident).unwrap();
+        vtable = get_vtable(superclass, classes, ctx);
+    }
+
+    let id = ctx.get_class_id(&c.name);
+    for f in &c.methods {
+        let entry = VTableEntry {
+            real_ident: method_symbol(&c.name, f),
+            class_id: id,
+            ret_type: f.ret_type.clone(),
+            arg_types: f.args.iter().map(|v| v.t.clone()).collect(),
+        };
+        vtable.set_func(&f.ident, entry);
+    }
+
+    vtable
+}
+
+fn add_vtables(p: &Program, ctx: &mut Context) {
+    let mut funcs: Vec<String> = Vec::new();
+    for def in &p.0 {
+        if let Def::DClass(ref c) = *def {
+            let id = ctx.get_class_id(&c.name);
+            let vtable = &ctx.get_class_data(id).vtable;
+            funcs.extend(vtable.fs.iter().map(|f| format!("${}", f.real_ident)));
+        }
+    }
+    if funcs.is_empty() {
+        return;
+    }
+    ctx.add_comment("vtables".to_string());
+    ctx.add_line(format!("(table {} funcref)", funcs.len()));
+    ctx.add_line(format!("(elem (i32.const 0) {})", funcs.join(" ")));
+    ctx.add_empty_line();
+}
+
+// Registers functions and methods, together with static methods of classes.
+fn add_funcs(p: &Program, ctx: &mut Context) {
+    for def in &p.0 {
+        match *def {
+            Def::DFunc(ref f) => {
+                let arg_types = f.args.iter().map(|arg| arg.t.clone()).collect();
+                ctx.add_func(&f.ident, arg_types, f.ret_type.clone());
+            }
+            Def::DClass(ref c) => {
+                let id = ctx.get_class_id(&c.name);
+                for f in &c.methods {
+                    let obj_t = Type::TObject(c.name.clone());
+                    let mut arg_types: Vec<Type> = f.args.iter().map(|arg| arg.t.clone()).collect();
+                    arg_types.insert(0, obj_t);
+                    let ident = Ident(method_symbol(&c.name, f));
+                    ctx.add_func(&ident, arg_types, f.ret_type.clone());
+                }
+                for f in &c.static_methods {
+                    let arg_types = f.args.iter().map(|arg| arg.t.clone()).collect();
+                    let ident = Ident(static_method_symbol(&c.name, f));
+                    ctx.add_func(&ident, arg_types, f.ret_type.clone());
+                    ctx.add_static_method(id, &f.ident, &ident);
+                }
+            }
+            Def::DGlobal(..) |
+            Def::DEnum(..) |
+            Def::DExtern(..) => {}
+        }
+    }
+}
+
+fn add_globals(p: &Program, ctx: &mut Context) {
+    for def in &p.0 {
+        match *def {
+            Def::DGlobal(ref g) => {
+                for decl in &g.decls {
+                    ctx.add_global(decl.get_ident(), &g.t);
+                }
+            }
+            Def::DClass(ref c) => {
+                let id = ctx.get_class_id(&c.name);
+                for g in &c.static_vars {
+                    for decl in &g.decls {
+                        ctx.add_static_field(id, decl.get_ident(), &g.t);
+                    }
+                }
+            }
+            Def::DFunc(..) |
+            Def::DEnum(..) |
+            Def::DExtern(..) => {}
+        }
+    }
+}
+
+// The host calls _start, main of the program may take its arguments as an array
+// of strings.
+fn generate_start(p: &Program, ctx: &mut Context) {
+    let takes_args = p.0.iter().any(|def| match *def {
+        Def::DFunc(ref f) => f.ident.0 == "main" && !f.args.is_empty(),
+        _ => false,
+    });
+    let main_name = ctx.get_func_symbol(&Ident("main".to_string()));
+    ctx.begin_block("(func $_start (export \"_start\") (result i32)".to_string());
+    if takes_args {
+        ctx.add_line("call $_new_args".to_string());
+    }
+    ctx.add_line(format!("call {}", main_name));
+    ctx.end_field();
+}
@@ -0,0 +1,279 @@
+use ast::*;
+
+use code_generation::default_value;
+use static_analysis::has_return::*;
+
+use wasm_generation::collection::*;
+use wasm_generation::context::*;
+use wasm_generation::expr::*;
+use wasm_generation::field_get::*;
+use wasm_generation::generate::*;
+use wasm_generation::tail_call::*;
+use wasm_generation::wasm_type::*;
+
+impl GenerateCode<()> for Vec<Stmt> {
+    fn generate_code(&self, ctx: &mut Context) {
+        for stmt in self {
+            stmt.generate_code(ctx);
+            if stmt.has_return() {
+                break;
+            }
+        }
+    }
+}
+
+impl GenerateCode<()> for Stmt {
+    fn generate_code(&self, ctx: &mut Context) {
+        match self.kind {
+            StmtKind::SEmpty => {}
+            StmtKind::SBlock(ref stmts) => {
+                ctx.in_new_scope(|ctx| {
+                    stmts.generate_code(ctx);
+                    if !stmts.has_return() {
+                        ctx.release_local_strings();
+                    }
+                })
+            }
+            StmtKind::SDecl(_, ref decls) => {
+                decls.generate_code(ctx);
+            }
+            StmtKind::SAssign(ref ident, ref e) => {
+                let (lvalue, t) = ident.generate_code(ctx);
+                let val = generate_init_expr(e, &t, ctx);
+                if t == Type::TString {
+                    ctx.add_load(&lvalue);
+                    let old_val = ctx.add_tmp(&t);
+                    ctx.retain_string(&val);
+                    ctx.add_line(old_val);
+                    ctx.add_line("call $_release_str".to_string());
+                }
+                ctx.add_store(&lvalue, &val);
+            }
+            StmtKind::SInc(ref ident) => generate_step(ident, Operator::OpAdd, ctx),
+            StmtKind::SDec(ref ident) => generate_step(ident, Operator::OpSub, ctx),
+            StmtKind::SReturnE(Expr::ECall(ref ident, ref args)) => {
+                generate_tail_call(ident, args, ctx);
+            }
+            StmtKind::SReturnE(ref e) => {
+                let t = ctx.ret_type.clone();
+                let val = generate_init_expr(e, &t, ctx);
+                if t == Type::TString {
+                    ctx.retain_string(&val);
+                }
+                ctx.release_all_strings();
+                ctx.add_line(val);
+                ctx.add_line("return".to_string());
+            }
+            StmtKind::SReturn => {
+                ctx.release_all_strings();
+                ctx.add_line("return".to_string());
+            }
+            StmtKind::SExpr(ref e) => {
+                e.generate_code(ctx);
+            }
+            StmtKind::SIf(ref cond, ref s) => {
+                let (cond_val, _) = cond.generate_code(ctx);
+                ctx.add_line(cond_val);
+                ctx.in_new_block("if".to_string(), |ctx| {
+                    s.generate_code(ctx);
+                    if !s.has_return() {
+                        ctx.release_local_strings();
+                    }
+                });
+            }
+            StmtKind::SIfElse(ref cond, ref if_true, ref if_false) => {
+                let (cond_val, _) = cond.generate_code(ctx);
+                ctx.add_line(cond_val);
+                ctx.begin_block("if".to_string());
+                ctx.in_new_scope(|ctx| {
+                    if_true.generate_code(ctx);
+                    if !if_true.has_return() {
+                        ctx.release_local_strings();
+                    }
+                });
+                ctx.next_block("else".to_string());
+                ctx.in_new_scope(|ctx| {
+                    if_false.generate_code(ctx);
+                    if !if_false.has_return() {
+                        ctx.release_local_strings();
+                    }
+                });
+                ctx.end_block();
+            }
+            StmtKind::SWhile(ref cond, ref s) => {
+                // the condition is evaluated at the beginning of each iteration
+                let label = ctx.next_label();
+                ctx.begin_block(format!("block $b{}", label));
+                ctx.in_new_block(format!("loop $l{}", label), |ctx| {
+                    let cond_val = ctx.in_new_scope(|ctx| {
+                        let (cond_val, _) = cond.generate_code(ctx);
+                        ctx.release_local_strings();
+                        cond_val
+                    });
+                    ctx.add_line(cond_val);
+                    ctx.add_line("i32.eqz".to_string());
+                    ctx.add_line(format!("br_if $b{}", label));
+                    ctx.in_new_scope(|ctx| {
+                        s.generate_code(ctx);
+                        if !s.has_return() {
+                            ctx.release_local_strings();
+                        }
+                    });
+                    ctx.add_line(format!("br $l{}", label));
+                });
+                ctx.end_block();
+            }
+            StmtKind::SSwitch(ref e, ref cases, ref default) => {
+                let (val, t) = e.generate_code(ctx);
+                // labels are evaluated first, so that their strings can be released
+                // on every path
+                let mut label_vals: Vec<(String, usize)> = Vec::new();
+                for (idx, case) in cases.iter().enumerate() {
+                    for label in &case.labels {
+                        label_vals.push((label.generate_code(ctx).0, idx));
+                    }
+                }
+                // Comparisons select the case, they are checked in the reverse order,
+                // so that the first matching label wins.
+                let selected = ctx.next_tmp();
+                ctx.add_local(&selected, &Type::TInt);
+                ctx.add_store(&LValue::Local(selected.clone()), "i32.const -1");
+                for (label_val, idx) in label_vals.into_iter().rev() {
+                    ctx.add_line(val.clone());
+                    ctx.add_line(label_val);
+                    match t == Type::TString {
+                        true => ctx.add_line("call $_str_eq".to_string()),
+                        false => ctx.add_line(format!("{}.eq", wasm_type(&t))),
+                    }
+                    ctx.in_new_block("if".to_string(), |ctx| {
+                        let idx_val = format!("i32.const {}", idx);
+                        ctx.add_store(&LValue::Local(selected.clone()), &idx_val);
+                    });
+                }
+
+                // Each case is a block, the index of the case is the depth of its end,
+                // -1 selects the default.
+                let label = ctx.next_label();
+                let end = format!("$s{}", label);
+                let case_labels: Vec<String> = (0..cases.len())
+                    .map(|idx| format!("$s{}_{}", label, idx))
+                    .collect();
+                ctx.begin_block(format!("block {}", end));
+                ctx.begin_block(format!("block $s{}_default", label));
+                for case_label in case_labels.iter().rev() {
+                    ctx.begin_block(format!("block {}", case_label));
+                }
+                ctx.add_line(format!("local.get {}", selected));
+                ctx.add_line(format!("br_table {} $s{}_default", case_labels.join(" "), label));
+                for case in cases {
+                    ctx.end_block();
+                    generate_case(&case.body, &end, ctx);
+                }
+                ctx.end_block();
+                let empty = Vec::new();
+                let default = default.as_ref().unwrap_or(&empty);
+                generate_case(default, &end, ctx);
+                ctx.end_block();
+            }
+            StmtKind::SFor(ref t, ref ident, ref arr, ref stmt) => {
+                let (arr, arr_t) = match *arr {
+                    Expr::EArrayLit(..) => {
+                        let arr_t = Type::TArray(Box::new(t.clone()));
+                        (generate_init_expr(arr, &arr_t, ctx), arr_t)
+                    }
+                    _ => arr.generate_code(ctx),
+                };
+                // Maps are iterated over a snapshot of their keys.
+                let (arr, arr_t) = match arr_t {
+                    Type::TMap(ref key_t, _) => generate_map_keys(&arr, key_t, ctx),
+                    _ => (arr, arr_t),
+                };
+                // the size of a list and the length of an array are at the beginning
+                ctx.add_line(arr.clone());
+                ctx.add_line("i32.load".to_string());
+                let size = ctx.add_tmp(&Type::TInt);
+                let idx = ctx.next_tmp();
+
+                ctx.in_new_loop(&idx, &size, |ctx| {
+                    let idx_val = format!("local.get {}", idx);
+                    let (elem, elem_t) = match arr_t {
+                        Type::TList(ref elem_t) => {
+                            ctx.add_line(arr.clone());
+                            ctx.add_line(idx_val);
+                            ctx.add_line("call $_list_at".to_string());
+                            let slot = ctx.add_tmp(&Type::TInt);
+                            (LValue::Slot(slot, *elem_t.clone()), *elem_t.clone())
+                        }
+                        _ => {
+                            let elem_t = arr_elem_t(&arr_t);
+                            let addr = elem_addr(&arr, &idx_val, &elem_t, ctx);
+                            (LValue::Memory(addr, 8, elem_t.clone()), elem_t)
+                        }
+                    };
+                    let name = ctx.new_local_name(ident);
+                    ctx.add_local(&name, &elem_t);
+                    ctx.add_load(&elem);
+                    ctx.add_line(format!("local.set {}", name));
+                    ctx.set_var(ident.clone(), name.clone(), elem_t.clone());
+
+                    if elem_t == Type::TString {
+                        ctx.retain_string(&format!("local.get {}", name));
+                    }
+
+                    stmt.generate_code(ctx);
+                    if !stmt.has_return() {
+                        ctx.release_local_strings();
+                    }
+                });
+            }
+        }
+    }
+}
+
+// Cases don't fall through, each one jumps to the end of the switch.
+fn generate_case(body: &Vec<Stmt>, end: &str, ctx: &mut Context) {
+    ctx.in_new_scope(|ctx| {
+        body.generate_code(ctx);
+        if !body.has_return() {
+            ctx.release_local_strings();
+            ctx.add_line(format!("br {}", end));
+        }
+    });
+}
+
+// ++ and --
+fn generate_step(ident: &FieldGet, op: Operator, ctx: &mut Context) {
+    let (lvalue, t) = ident.generate_code(ctx);
+    ctx.add_load(&lvalue);
+    let old_val = ctx.add_tmp(&t);
+    let one = format!("{}.const 1", wasm_type(&t));
+    let val = generate_arith_op(&t, &old_val, op, &one, ctx);
+    ctx.add_store(&lvalue, &val);
+}
+
+impl GenerateCode<()> for Vec<VarDecl> {
+    fn generate_code(&self, ctx: &mut Context) {
+        for var_decl in self {
+            var_decl.generate_code(ctx);
+        }
+    }
+}
+
+impl GenerateCode<()> for VarDecl {
+    fn generate_code(&self, ctx: &mut Context) {
+        let (t, ident, val) = match *self {
+            VarDecl::Init(ref t, ref ident, ref e) => (t, ident, generate_init_expr(e, t, ctx)),
+            VarDecl::NoInit(ref t, ref ident) => {
+                let (val, _) = Expr::ELit(default_value(t)).generate_code(ctx);
+                (t, ident, val)
+            }
+        };
+        if *t == Type::TString {
+            ctx.retain_string(&val);
+        }
+        let name = ctx.new_local_name(ident);
+        ctx.add_local(&name, t);
+        ctx.add_store(&LValue::Local(name.clone()), &val);
+        ctx.set_var(ident.clone(), name, t.clone());
+    }
+}
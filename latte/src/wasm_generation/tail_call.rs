@@ -0,0 +1,52 @@
+use ast::*;
+
+use wasm_generation::context::Context;
+use wasm_generation::expr::*;
+
+// Generates `return f(args)`, as in LLVM. Self-recursive calls set the params and
+// jump to the body of the function. Other calls use return_call if the strings of
+// the function can be released before the call, i.e. no string is passed to it.
+// The result of such a call is returned without retaining it.
+pub fn generate_tail_call(ident: &FieldGet, args: &[Expr], ctx: &mut Context) {
+    let call = match prepare_call(ident, args, ctx) {
+        PreparedCall::Generated(val, _) => return generate_return(&val, ctx),
+        PreparedCall::Call(call) => call,
+    };
+
+    let target = ctx.tail_target.clone().unwrap();
+    let is_self_call = call.vtable_pos.is_none() && call.func == target.symbol;
+    if let (true, Some(body_label)) = (is_self_call, target.body_label) {
+        ctx.add_comment("tail recursion".to_string());
+        for (arg, t) in &call.args {
+            if *t == Type::TString {
+                ctx.retain_string(arg);
+            }
+        }
+        ctx.release_all_strings();
+        // all args are pushed before the params are set, they can refer to the params
+        for (arg, _) in &call.args {
+            ctx.add_line(arg.clone());
+        }
+        for param in target.params.iter().rev() {
+            ctx.add_line(format!("local.set {}", param));
+        }
+        ctx.add_line(format!("br {}", body_label));
+    } else if call.args.iter().all(|(_, t)| *t != Type::TString) {
+        ctx.release_all_strings();
+        let call_instr = push_call(&call, "return_call", ctx);
+        ctx.add_line(call_instr);
+    } else {
+        let call_instr = push_call(&call, "call", ctx);
+        let (val, _) = add_call(call_instr, &call.ret_type, ctx);
+        generate_return(&val, ctx);
+    }
+}
+
+// Returns the result of the call, the function owns it if it's a string.
+fn generate_return(val: &str, ctx: &mut Context) {
+    ctx.release_all_strings();
+    if !val.is_empty() {
+        ctx.add_line(val.to_string());
+    }
+    ctx.add_line("return".to_string());
+}
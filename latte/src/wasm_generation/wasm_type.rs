@@ -0,0 +1,74 @@
+use ast::Type;
+
+// Enums, booleans and chars are i32, chars are kept in 0..255. Pointers are
+// addresses in the linear memory, null is 0.
+pub fn wasm_type(t: &Type) -> &'static str {
+    match *t {
+        Type::TLong => "i64",
+        Type::TDouble => "f64",
+        Type::TVoid |
+        Type::TFunc(..) => unreachable!(),
+        _ => "i32",
+    }
+}
+
+// result of a function or a block
+pub fn result_decl(t: &Type) -> String {
+    match *t {
+        Type::TVoid => String::new(),
+        _ => format!(" (result {})", wasm_type(t)),
+    }
+}
+
+// size of fields and elements of arrays
+pub fn size_of(t: &Type) -> u32 {
+    match wasm_type(t) {
+        "i32" => 4,
+        _ => 8,
+    }
+}
+
+// the smallest multiple of n which is not less than x
+pub fn align(x: u32, n: u32) -> u32 {
+    match x % n {
+        0 => x,
+        r => x + n - r,
+    }
+}
+
+pub fn zero(t: &Type) -> String {
+    format!("{}.const 0", wasm_type(t))
+}
+
+// Elements of lists and maps are stored in 64-bit slots, as in the LLVM runtime.
+pub fn to_slot(t: &Type) -> Option<&'static str> {
+    match *t {
+        Type::TLong => None,
+        Type::TDouble => Some("i64.reinterpret_f64"),
+        _ => Some("i64.extend_i32_s"),
+    }
+}
+
+pub fn from_slot(t: &Type) -> Option<&'static str> {
+    match *t {
+        Type::TLong => None,
+        Type::TDouble => Some("f64.reinterpret_i64"),
+        _ => Some("i32.wrap_i64"),
+    }
+}
+
+// characters of a string in the text format, non-printable ones are escaped
+pub fn escape_str(bytes: &[u8]) -> String {
+    let mut res = String::new();
+    for &c in bytes {
+        match c {
+            b'"' | b'\\' => {
+                res.push('\\');
+                res.push(c as char);
+            }
+            _ if c.is_ascii_graphic() || c == b' ' => res.push(c as char),
+            _ => res.push_str(&format!("\\{:02x}", c)),
+        }
+    }
+    res
+}
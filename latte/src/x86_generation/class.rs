@@ -0,0 +1,110 @@
+use ast::{Class, Type};
+use mangling::*;
+
+use x86_generation::class_data::*;
+use x86_generation::context::*;
+use x86_generation::generate::*;
+use x86_generation::ir::*;
+
+impl GenerateCode<()> for Class {
+    fn generate_code(&self, ctx: &mut Context) {
+        ctx.in_new_scope(|ctx| {
+            let id = ctx.get_class_id(&self.name);
+            generate_new(id, ctx);
+            generate_init(id, ctx);
+
+            ctx.class = Some(id);
+            ctx.static_class = Some(id);
+            for m in &self.methods {
+                m.generate_code(ctx);
+            }
+            ctx.class = None;
+            for m in &self.static_methods {
+                m.generate_code(ctx);
+            }
+            ctx.static_class = None;
+        });
+    }
+}
+
+fn generate_new(id: ClassId, ctx: &mut Context) {
+    let size = ctx.get_class_data(id).get_size();
+    let obj_t = Type::TObject(ctx.get_class_data(id).ident.clone());
+    ctx.begin_function();
+    ctx.add_instr(Instr::Params(vec![]));
+    let class = ctx.get_class_data(id).ident.clone();
+    let obj = ctx.call_runtime("_new_object", vec![Val::Imm(size as i64)], &obj_t);
+    ctx.call_runtime(&init_symbol(&class), vec![obj.clone()], &Type::TVoid);
+    ctx.add_instr(Instr::Ret(Some(obj)));
+    ctx.end_function(&new_symbol(&class));
+}
+
+fn generate_init(id: ClassId, ctx: &mut Context) {
+    let obj_t = Type::TObject(ctx.get_class_data(id).ident.clone());
+    ctx.begin_function();
+    let obj = ctx.new_reg(&obj_t);
+    ctx.add_instr(Instr::Params(vec![obj]));
+    if let Some(super_id) = ctx.get_class_data(id).super_id {
+        let super_init = init_symbol(&ctx.get_class_data(super_id).ident);
+        ctx.call_runtime(&super_init, vec![Val::Reg(obj)], &Type::TVoid);
+    }
+
+    // classes without methods have no vtable
+    let vtable = match ctx.get_class_data(id).vtable.fs.is_empty() {
+        true => Val::Imm(0),
+        false => {
+            let r = ctx.new_reg(&Type::TLong);
+            ctx.add_instr(Instr::Lea(r, format!("vtable_{}", id)));
+            Val::Reg(r)
+        }
+    };
+    ctx.add_store(&obj_field(obj, 0, &Type::TLong), &vtable);
+
+    init_strings(id, obj, ctx);
+    init_vars(id, obj, ctx);
+
+    ctx.add_instr(Instr::Ret(None));
+    let init = init_symbol(&ctx.get_class_data(id).ident);
+    ctx.end_function(&init);
+}
+
+fn obj_field(obj: VReg, offset: u32, t: &Type) -> LValue {
+    LValue::Memory(Mem::Base(obj, offset as i32), t.clone())
+}
+
+// all string fields share one empty string
+fn init_strings(id: ClassId, obj: VReg, ctx: &mut Context) {
+    let fields = ctx.get_class_data(id).fields.clone();
+    if !fields.iter().any(|(_, t)| *t == Type::TString) {
+        return;
+    }
+
+    let empty_str = ctx.call_runtime("_alloc_str", vec![], &Type::TString);
+    for (field, t) in fields {
+        if t != Type::TString {
+            continue;
+        }
+        let offset = ctx.get_class_data(id).get_field_offset(&field);
+        ctx.add_store(&obj_field(obj, offset, &t), &empty_str);
+        ctx.retain_string(&empty_str);
+    }
+}
+
+fn init_vars(id: ClassId, obj: VReg, ctx: &mut Context) {
+    let fields = ctx.get_class_data(id).fields.clone();
+    for (field, t) in fields {
+        if t == Type::TString {
+            continue;
+        }
+        let offset = ctx.get_class_data(id).get_field_offset(&field);
+        let zero = match t == Type::TDouble {
+            true => {
+                let r = ctx.new_reg(&t);
+                ctx.add_instr(Instr::DoubleConst(r, 0.0));
+                Val::Reg(r)
+            }
+            false => Val::Imm(0),
+        };
+        ctx.add_store(&obj_field(obj, offset, &t), &zero);
+    }
+}
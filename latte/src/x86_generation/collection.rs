@@ -0,0 +1,147 @@
+use ast::{Expr, Ident, Type};
+
+use x86_generation::context::*;
+use x86_generation::expr::*;
+use x86_generation::generate::*;
+use x86_generation::ir::*;
+
+pub fn generate_new_collection(t: &Type, ctx: &mut Context) -> Val {
+    match *t {
+        Type::TStringBuilder => ctx.call_runtime("_builder_new", vec![], t),
+        Type::TList(ref elem_t) => {
+            let is_str = Val::Imm((**elem_t == Type::TString) as i64);
+            ctx.call_runtime("_list_new", vec![is_str], t)
+        }
+        Type::TMap(ref key_t, ref value_t) => {
+            let str_keys = Val::Imm((**key_t == Type::TString) as i64);
+            let str_values = Val::Imm((**value_t == Type::TString) as i64);
+            ctx.call_runtime("_map_new", vec![str_keys, str_values], t)
+        }
+        _ => unreachable!(),
+    }
+}
+
+pub fn generate_collection_call(obj: &Val,
+                                obj_t: &Type,
+                                method: &Ident,
+                                args: &[Expr],
+                                ctx: &mut Context)
+                                -> (Val, Type) {
+    match *obj_t {
+        Type::TStringBuilder => generate_builder_call(obj, method, args, ctx),
+        Type::TList(ref elem_t) => generate_list_call(obj, elem_t, method, args, ctx),
+        Type::TMap(ref key_t, ref value_t) => {
+            generate_map_call(obj, key_t, value_t, method, args, ctx)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// Strings passed to collections are retained by the runtime. Elements returned by pop
+// and remove are owned by the caller.
+fn generate_list_call(list: &Val,
+                      elem_t: &Type,
+                      method: &Ident,
+                      args: &[Expr],
+                      ctx: &mut Context)
+                      -> (Val, Type) {
+    match method.0.as_ref() {
+        "push" => {
+            let elem = generate_init_expr(&args[0], elem_t, ctx);
+            let elem = ctx.add_to_slot(&elem, elem_t);
+            ctx.call_runtime("_list_push", vec![list.clone(), elem], &Type::TVoid);
+            (Val::Imm(0), Type::TVoid)
+        }
+        "pop" => {
+            let slot = ctx.call_runtime("_list_pop", vec![list.clone()], &Type::TLong);
+            (ctx.add_from_slot(slot, elem_t), elem_t.clone())
+        }
+        "insert" => {
+            let (idx, _) = args[0].generate_code(ctx);
+            let elem = generate_init_expr(&args[1], elem_t, ctx);
+            let elem = ctx.add_to_slot(&elem, elem_t);
+            ctx.call_runtime("_list_insert", vec![list.clone(), idx, elem], &Type::TVoid);
+            (Val::Imm(0), Type::TVoid)
+        }
+        "remove" => {
+            let (idx, _) = args[0].generate_code(ctx);
+            let slot = ctx.call_runtime("_list_remove", vec![list.clone(), idx], &Type::TLong);
+            (ctx.add_from_slot(slot, elem_t), elem_t.clone())
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn generate_map_call(map: &Val,
+                     key_t: &Type,
+                     value_t: &Type,
+                     method: &Ident,
+                     args: &[Expr],
+                     ctx: &mut Context)
+                     -> (Val, Type) {
+    match method.0.as_ref() {
+        "put" => {
+            let key = generate_init_expr(&args[0], key_t, ctx);
+            let value = generate_init_expr(&args[1], value_t, ctx);
+            let key = ctx.add_to_slot(&key, key_t);
+            let value = ctx.add_to_slot(&value, value_t);
+            ctx.call_runtime("_map_put", vec![map.clone(), key, value], &Type::TVoid);
+            (Val::Imm(0), Type::TVoid)
+        }
+        "get" => {
+            let key = generate_init_expr(&args[0], key_t, ctx);
+            let key = ctx.add_to_slot(&key, key_t);
+            let slot = ctx.call_runtime("_map_get", vec![map.clone(), key], &Type::TLong);
+            let value = ctx.add_from_slot(slot, value_t);
+            if *value_t == Type::TString {
+                ctx.retain_string(&value);
+            }
+            (value, value_t.clone())
+        }
+        "contains" => {
+            let key = generate_init_expr(&args[0], key_t, ctx);
+            let key = ctx.add_to_slot(&key, key_t);
+            let res = ctx.call_runtime("_map_contains", vec![map.clone(), key], &Type::TBool);
+            (res, Type::TBool)
+        }
+        "remove" => {
+            let key = generate_init_expr(&args[0], key_t, ctx);
+            let key = ctx.add_to_slot(&key, key_t);
+            ctx.call_runtime("_map_remove", vec![map.clone(), key], &Type::TVoid);
+            (Val::Imm(0), Type::TVoid)
+        }
+        "keys" => generate_map_keys(map, key_t, ctx),
+        _ => unreachable!(),
+    }
+}
+
+// The characters of appended strings are copied, the result of toString is owned by
+// the caller.
+fn generate_builder_call(builder: &Val,
+                         method: &Ident,
+                         args: &[Expr],
+                         ctx: &mut Context)
+                         -> (Val, Type) {
+    match method.0.as_ref() {
+        "append" => {
+            let (s, _) = args[0].generate_code(ctx);
+            ctx.call_runtime("_builder_append", vec![builder.clone(), s], &Type::TVoid);
+            (Val::Imm(0), Type::TVoid)
+        }
+        "appendChar" => {
+            let (c, _) = args[0].generate_code(ctx);
+            ctx.call_runtime("_builder_append_char", vec![builder.clone(), c], &Type::TVoid);
+            (Val::Imm(0), Type::TVoid)
+        }
+        "toString" => {
+            let s = ctx.call_runtime("_builder_to_string", vec![builder.clone()], &Type::TString);
+            (s, Type::TString)
+        }
+        _ => unreachable!(),
+    }
+}
+
+pub fn generate_map_keys(map: &Val, key_t: &Type, ctx: &mut Context) -> (Val, Type) {
+    let keys_t = Type::TList(Box::new(key_t.clone()));
+    (ctx.call_runtime("_map_keys", vec![map.clone()], &keys_t), keys_t)
+}
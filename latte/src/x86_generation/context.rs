@@ -0,0 +1,540 @@
+use std::collections::{HashMap, HashSet};
+
+use ast::{Expr, Ident, Type};
+use mangling::demangle;
+use options::Options;
+
+use x86_generation::class_data::*;
+use x86_generation::emit::*;
+use x86_generation::ir::*;
+use x86_generation::reg_alloc::*;
+use x86_generation::x86_type::*;
+
+// Location of a value: a local, a global or an address in memory. Elements
+// of lists are in 64-bit slots.
+#[derive(Debug, Clone)]
+pub enum LValue {
+    Local(VReg),
+    Global(String, Type),
+    Memory(Mem, Type),
+    Slot(Mem, Type),
+}
+
+#[derive(Debug)]
+pub struct Context {
+    vars: HashMap<Ident, (VReg, Type)>,
+    globals: HashMap<Ident, (String, Type)>,
+    static_fields: HashMap<(ClassId, Ident), (String, Type)>,
+    static_methods: HashMap<(ClassId, Ident), Ident>, // to their symbols
+    func_types: HashMap<Ident, (Vec<Type>, Type)>,
+    builtins: HashSet<Ident>, // functions of the runtime, called by their own names
+    externs: HashSet<Ident>,
+    string_lits: HashMap<String, String>,
+    pub ret_type: Type,
+    pub class: Option<ClassId>,
+    pub static_class: Option<ClassId>, // class whose static members are accessible directly
+    pub opts: Options,
+    pub current_func: String,
+    pub tail_target: Option<TailTarget>,
+    devirtualized: Vec<String>, // report of calls that don't use vtables
+
+    classes: HashMap<ClassId, ClassData>,
+    class_ids: HashMap<Ident, ClassId>,
+    enum_ids: HashMap<Ident, EnumId>,
+    enum_variants: Vec<Vec<Ident>>,
+
+    string_tmps: Vec<Val>,
+    local_string_tmps: Vec<Val>,
+    string_vars: Vec<Val>,
+    local_string_vars: Vec<Val>,
+
+    instrs: Vec<Instr>, // of the current function
+    reg_classes: Vec<RegClass>,
+    labels: usize,
+    out: Vec<String>,
+}
+
+// The function being generated, self-recursive tail calls set the params
+// and jump to the beginning of the body.
+#[derive(Debug, Clone)]
+pub struct TailTarget {
+    pub symbol: String,
+    pub params: Vec<VReg>, // with self first for methods
+    pub body_label: Option<Label>,
+}
+
+impl Context {
+    pub fn new(opts: &Options) -> Context {
+        Context {
+            vars: HashMap::new(),
+            globals: HashMap::new(),
+            static_fields: HashMap::new(),
+            static_methods: HashMap::new(),
+            func_types: HashMap::new(),
+            builtins: HashSet::new(),
+            externs: HashSet::new(),
+            string_lits: HashMap::new(),
+            ret_type: Type::TVoid,
+            class: None,
+            static_class: None,
+            opts: opts.clone(),
+            current_func: String::new(),
+            tail_target: None,
+            devirtualized: Vec::new(),
+
+            classes: HashMap::new(),
+            class_ids: HashMap::new(),
+            enum_ids: HashMap::new(),
+            enum_variants: Vec::new(),
+
+            string_tmps: Vec::new(),
+            local_string_tmps: Vec::new(),
+            string_vars: Vec::new(),
+            local_string_vars: Vec::new(),
+
+            instrs: Vec::new(),
+            reg_classes: Vec::new(),
+            labels: 0,
+            out: Vec::new(),
+        }
+    }
+
+    pub fn in_new_scope<F, R>(&mut self, f: F) -> R
+        where F: Fn(&mut Context) -> R
+    {
+        let old_vars = self.vars.clone();
+        let old_string_tmps = self.string_tmps.clone();
+        let old_local_string_tmps = self.local_string_tmps.clone();
+        let old_string_vars = self.string_vars.clone();
+        let old_local_string_vars = self.local_string_vars.clone();
+
+        self.local_string_tmps.clear();
+        self.local_string_vars.clear();
+        let res = f(self);
+
+        self.local_string_vars = old_local_string_vars;
+        self.string_vars = old_string_vars;
+        self.local_string_tmps = old_local_string_tmps;
+        self.string_tmps = old_string_tmps;
+        self.vars = old_vars;
+        res
+    }
+
+    // Loop over indexes from 0 to size - 1, the index is a new register.
+    pub fn in_new_loop<F, R>(&mut self, size: &Val, f: F) -> R
+        where F: Fn(&mut Context, VReg) -> R
+    {
+        let (begin, end) = (self.next_label(), self.next_label());
+        let idx = self.new_reg(&Type::TInt);
+        self.add_instr(Instr::Mov(idx, Val::Imm(0)));
+        self.add_instr(Instr::Label(begin));
+        let is_end = self.new_reg(&Type::TBool);
+        self.add_instr(Instr::Cmp(Cond::Ge, Width::Long, is_end, idx, size.clone()));
+        self.add_instr(Instr::Branch(is_end, true, end));
+        let res = self.in_new_scope(|ctx| f(ctx, idx));
+        self.add_instr(Instr::Bin(BinOp::Add, Width::Long, idx, idx, Val::Imm(1)));
+        self.add_instr(Instr::Jump(begin));
+        self.add_instr(Instr::Label(end));
+        res
+    }
+
+    pub fn func_exists(&self, ident: &Ident) -> bool {
+        self.func_types.contains_key(ident)
+    }
+
+    pub fn get_arg_types(&self, ident: &Ident) -> Vec<Type> {
+        self.func_types.get(ident).unwrap().0.clone()
+    }
+
+    pub fn get_ret_type(&self, ident: &Ident) -> Type {
+        self.func_types.get(ident).unwrap().1.clone()
+    }
+
+    // Methods are named by their mangled symbols in the context, as in LLVM,
+    // other functions are prefixed, so that they can't clash with them.
+    pub fn get_func_symbol(&self, ident: &Ident) -> String {
+        let is_own = self.builtins.contains(ident) || self.externs.contains(ident);
+        if is_own || demangle(&ident.0).is_some() {
+            ident.0.clone()
+        } else {
+            format!("f_{}", ident)
+        }
+    }
+
+    pub fn get_str_const(&self, s: &String) -> String {
+        self.string_lits.get(s).unwrap().clone()
+    }
+
+    pub fn set_str_const(&mut self, s: String, name: String) {
+        self.string_lits.insert(s, name);
+    }
+
+    pub fn var_exists(&self, ident: &Ident) -> bool {
+        self.vars.contains_key(ident)
+    }
+
+    pub fn get_var(&self, ident: &Ident) -> (VReg, Type) {
+        self.vars.get(ident).unwrap().clone()
+    }
+
+    pub fn set_var(&mut self, ident: Ident, r: VReg, t: Type) {
+        self.vars.insert(ident, (r, t.clone()));
+        if t == Type::TString {
+            self.string_vars.push(Val::Reg(r));
+            self.local_string_vars.push(Val::Reg(r));
+        }
+    }
+
+    pub fn has_globals(&self) -> bool {
+        !self.globals.is_empty() || !self.static_fields.is_empty()
+    }
+
+    pub fn global_exists(&self, ident: &Ident) -> bool {
+        self.globals.contains_key(ident)
+    }
+
+    pub fn get_global(&self, ident: &Ident) -> (String, Type) {
+        self.globals.get(ident).unwrap().clone()
+    }
+
+    // globals take 8 bytes each in .bss, so they are zeroed
+    pub fn add_global(&mut self, ident: &Ident, t: &Type) {
+        let name = format!("g_{}", ident);
+        self.add_zeroed_var(&name);
+        self.globals.insert(ident.clone(), (name, t.clone()));
+    }
+
+    pub fn add_static_field(&mut self, class: ClassId, ident: &Ident, t: &Type) {
+        let name = format!("static{}_{}", class, ident);
+        self.add_zeroed_var(&name);
+        self.static_fields.insert((class, ident.clone()), (name, t.clone()));
+    }
+
+    fn add_zeroed_var(&mut self, name: &str) {
+        self.add_label(name);
+        self.add_line(".zero 8".to_string());
+    }
+
+    pub fn add_static_method(&mut self, class: ClassId, ident: &Ident, symbol: &Ident) {
+        self.static_methods.insert((class, ident.clone()), symbol.clone());
+    }
+
+    // static members are inherited
+
+    pub fn find_static_field(&self, mut id: ClassId, ident: &Ident) -> Option<(String, Type)> {
+        loop {
+            if let Some(field) = self.static_fields.get(&(id, ident.clone())) {
+                return Some(field.clone());
+            }
+            id = self.get_class_data(id).super_id?;
+        }
+    }
+
+    // returns the name of the generated function
+    pub fn find_static_method(&self, mut id: ClassId, ident: &Ident) -> Option<Ident> {
+        loop {
+            if let Some(symbol) = self.static_methods.get(&(id, ident.clone())) {
+                return Some(symbol.clone());
+            }
+            id = self.get_class_data(id).super_id?;
+        }
+    }
+
+    pub fn add_func(&mut self, ident: &Ident, arg_types: Vec<Type>, ret_type: Type) {
+        self.func_types.insert(ident.clone(), (arg_types, ret_type));
+    }
+
+    pub fn add_builtin_func(&mut self, ident: &Ident, arg_types: Vec<Type>, ret_type: Type) {
+        self.add_func(ident, arg_types, ret_type);
+        self.builtins.insert(ident.clone());
+    }
+
+    pub fn add_extern_func(&mut self, ident: &Ident, arg_types: Vec<Type>, ret_type: Type) {
+        self.add_func(ident, arg_types, ret_type);
+        self.externs.insert(ident.clone());
+    }
+
+    pub fn is_builtin(&self, ident: &Ident) -> bool {
+        self.builtins.contains(ident)
+    }
+
+    pub fn is_extern(&self, ident: &Ident) -> bool {
+        self.externs.contains(ident)
+    }
+
+    // string reference counting
+    pub fn add_string_tmp(&mut self, val: Val) {
+        self.string_tmps.push(val.clone());
+        self.local_string_tmps.push(val);
+    }
+
+    pub fn release_local_strings(&mut self) {
+        let strs = self.local_string_tmps.clone();
+        self.release_strings(strs);
+        let strs = self.local_string_vars.clone();
+        self.release_strings(strs);
+    }
+
+    pub fn release_all_strings(&mut self) {
+        let strs = self.string_tmps.clone();
+        self.release_strings(strs);
+        let strs = self.string_vars.clone();
+        self.release_strings(strs);
+    }
+
+    fn release_strings(&mut self, vals: Vec<Val>) {
+        for val in vals {
+            self.release_string(&val);
+        }
+    }
+
+    pub fn retain_string(&mut self, val: &Val) {
+        self.call_runtime("_retain_str", vec![val.clone()], &Type::TVoid);
+    }
+
+    pub fn release_string(&mut self, val: &Val) {
+        self.call_runtime("_release_str", vec![val.clone()], &Type::TVoid);
+    }
+
+    // class
+    pub fn add_class_id(&mut self, cname: &Ident, id: ClassId) {
+        self.class_ids.insert(cname.clone(), id);
+    }
+
+    pub fn add_class(&mut self, id: ClassId, cdata: ClassData) {
+        self.classes.insert(id, cdata);
+    }
+
+    pub fn set_vtable(&mut self, id: ClassId, vtable: VTable) {
+        self.classes.get_mut(&id).unwrap().vtable = vtable;
+    }
+
+    pub fn class_exists(&self, cname: &Ident) -> bool {
+        self.class_ids.contains_key(cname)
+    }
+
+    pub fn get_class_id(&self, cname: &Ident) -> ClassId {
+        *self.class_ids.get(cname).unwrap()
+    }
+
+    pub fn get_class_data(&self, id: ClassId) -> &ClassData {
+        self.classes.get(&id).unwrap()
+    }
+
+    pub fn has_field(&self, mut id: ClassId, field: &Ident) -> bool {
+        while !self.get_class_data(id).has_field(field) {
+            match self.get_class_data(id).super_id {
+                Some(super_id) => id = super_id,
+                None => return false,
+            }
+        }
+        true
+    }
+
+    // Class hierarchy analysis: whether a subclass of the class replaces
+    // the method at the given position of its vtable.
+    pub fn is_overridden(&self, id: ClassId, vtable_pos: usize) -> bool {
+        let real_ident = &self.get_class_data(id).vtable.fs[vtable_pos].real_ident;
+        self.classes.iter().any(|(&sub_id, cdata)| {
+            sub_id != id && self.is_subclass_of(sub_id, id) &&
+            cdata.vtable.fs[vtable_pos].real_ident != *real_ident
+        })
+    }
+
+    pub fn add_devirtualized(&mut self, call: &Expr, method_class: ClassId, method: &Ident) {
+        let msg = format!("{}: {} calls {}.{}",
+                          self.current_func,
+                          call,
+                          self.get_class_data(method_class).ident,
+                          method);
+        self.devirtualized.push(msg);
+    }
+
+    pub fn take_devirtualized(&mut self) -> Vec<String> {
+        self.devirtualized.drain(..).collect()
+    }
+
+    pub fn is_subclass_of(&self, mut id: ClassId, super_id: ClassId) -> bool {
+        while self.get_class_data(id).super_id.is_some() && id != super_id {
+            id = self.get_class_data(id).get_super();
+        }
+        id == super_id
+    }
+
+    // enums
+    pub fn add_enum(&mut self, name: &Ident, variants: &[Ident]) -> EnumId {
+        let id = self.enum_variants.len();
+        self.enum_ids.insert(name.clone(), id);
+        self.enum_variants.push(variants.to_owned());
+        id
+    }
+
+    pub fn enum_exists(&self, name: &Ident) -> bool {
+        self.enum_ids.contains_key(name)
+    }
+
+    pub fn get_enum_id(&self, name: &Ident) -> EnumId {
+        *self.enum_ids.get(name).unwrap()
+    }
+
+    pub fn get_variant_tag(&self, id: EnumId, variant: &Ident) -> i32 {
+        self.enum_variants[id].iter().position(|v| v == variant).unwrap() as i32
+    }
+
+    // lvalues
+
+    // loads the value to a new register
+    pub fn add_load(&mut self, lvalue: &LValue, t: &Type) -> VReg {
+        let r = self.new_reg(t);
+        match *lvalue {
+            LValue::Local(src) => self.add_instr(Instr::Mov(r, Val::Reg(src))),
+            LValue::Global(ref name, ref t) => {
+                self.add_instr(Instr::Load(r, Mem::Sym(name.clone()), mem_width(t)));
+            }
+            LValue::Memory(ref mem, ref t) => {
+                self.add_instr(Instr::Load(r, mem.clone(), mem_width(t)));
+            }
+            LValue::Slot(ref mem, ref t) => {
+                match from_slot(t) {
+                    Some(conv) => {
+                        let slot = self.new_reg(&Type::TLong);
+                        self.add_instr(Instr::Load(slot, mem.clone(), Width::Quad));
+                        self.add_instr(Instr::Conv(conv, r, slot));
+                    }
+                    None => self.add_instr(Instr::Load(r, mem.clone(), Width::Quad)),
+                }
+            }
+        }
+        r
+    }
+
+    pub fn add_store(&mut self, lvalue: &LValue, val: &Val) {
+        match *lvalue {
+            LValue::Local(r) => self.add_instr(Instr::Mov(r, val.clone())),
+            LValue::Global(ref name, ref t) => {
+                self.add_instr(Instr::Store(val.clone(), Mem::Sym(name.clone()), mem_width(t)));
+            }
+            LValue::Memory(ref mem, ref t) => {
+                self.add_instr(Instr::Store(val.clone(), mem.clone(), mem_width(t)));
+            }
+            LValue::Slot(ref mem, ref t) => {
+                let slot = self.add_to_slot(val, t);
+                self.add_instr(Instr::Store(slot, mem.clone(), Width::Quad));
+            }
+        }
+    }
+
+    // the value as a 64-bit slot of lists and maps
+    pub fn add_to_slot(&mut self, val: &Val, t: &Type) -> Val {
+        match (to_slot(t), val) {
+            (None, _) => val.clone(),
+            // constants are already extended, except negative chars which don't exist
+            (Some(_), &Val::Imm(x)) => Val::Imm(x),
+            (Some(conv), &Val::Reg(r)) => {
+                let slot = self.new_reg(&Type::TLong);
+                self.add_instr(Instr::Conv(conv, slot, r));
+                Val::Reg(slot)
+            }
+        }
+    }
+
+    // the slot returned by the runtime as a value of the given type
+    pub fn add_from_slot(&mut self, slot: Val, t: &Type) -> Val {
+        match (from_slot(t), slot) {
+            (Some(conv), Val::Reg(r)) => {
+                let val = self.new_reg(t);
+                self.add_instr(Instr::Conv(conv, val, r));
+                Val::Reg(val)
+            }
+            (_, slot) => slot,
+        }
+    }
+
+    // functions
+
+    // Calls the function, the result of a void call is 0. Booleans and chars are
+    // zero-extended, C functions set only the lowest byte of the result.
+    pub fn add_call(&mut self, callee: Callee, args: Vec<Val>, ret_type: &Type) -> Val {
+        if *ret_type == Type::TVoid {
+            self.add_instr(Instr::Call(callee, args, None));
+            return Val::Imm(0);
+        }
+        let res = self.new_reg(ret_type);
+        self.add_instr(Instr::Call(callee, args, Some(res)));
+        if *ret_type == Type::TBool || *ret_type == Type::TChar {
+            self.add_instr(Instr::Conv(Conv::ZeroExtendByte, res, res));
+        }
+        Val::Reg(res)
+    }
+
+    pub fn call_runtime(&mut self, func: &str, args: Vec<Val>, ret_type: &Type) -> Val {
+        self.add_call(Callee::Direct(func.to_string()), args, ret_type)
+    }
+
+    // the value in a register, constants are moved to a new one
+    pub fn reg_of(&mut self, val: &Val, t: &Type) -> VReg {
+        match *val {
+            Val::Reg(r) => r,
+            Val::Imm(_) => {
+                let r = self.new_reg(t);
+                self.add_instr(Instr::Mov(r, val.clone()));
+                r
+            }
+        }
+    }
+
+    pub fn new_reg(&mut self, t: &Type) -> VReg {
+        self.reg_classes.push(reg_class(t));
+        self.reg_classes.len() - 1
+    }
+
+    pub fn add_instr(&mut self, instr: Instr) {
+        self.instrs.push(instr);
+    }
+
+    pub fn next_label(&mut self) -> Label {
+        self.labels += 1;
+        self.labels
+    }
+
+    // registers and labels start over in each function
+    pub fn begin_function(&mut self) {
+        self.instrs.clear();
+        self.reg_classes.clear();
+        self.labels = 0;
+    }
+
+    // Allocates registers of the function and emits its code.
+    pub fn end_function(&mut self, name: &str) {
+        let f = Function {
+            name: name.to_string(),
+            instrs: self.instrs.drain(..).collect(),
+            classes: self.reg_classes.drain(..).collect(),
+        };
+        let alloc = allocate(&f);
+        self.out.extend(emit_function(&f, &alloc));
+        self.add_empty_line();
+    }
+
+    // output
+
+    pub fn add_label(&mut self, name: &str) {
+        self.out.push(format!("{}:", name));
+    }
+
+    pub fn add_line(&mut self, s: String) {
+        self.out.push(format!("    {}", s));
+    }
+
+    pub fn add_comment(&mut self, s: String) {
+        self.out.push(format!("# {}", s));
+    }
+
+    pub fn add_empty_line(&mut self) {
+        self.out.push(String::new());
+    }
+
+    pub fn get_out(&self) -> &Vec<String> {
+        &self.out
+    }
+}
@@ -0,0 +1,618 @@
+use x86_generation::ir::*;
+use x86_generation::reg_alloc::*;
+use x86_generation::x86_type::align;
+
+// registers of arguments in the System V calling convention
+const INT_ARG_REGS: [&str; 6] = ["%rdi", "%rsi", "%rdx", "%rcx", "%r8", "%r9"];
+const FLOAT_ARG_REGS: [&str; 8] = ["%xmm0", "%xmm1", "%xmm2", "%xmm3", "%xmm4", "%xmm5", "%xmm6",
+                                   "%xmm7"];
+
+// Translates the function to AT&T assembly. The frame holds the saved callee-saved
+// registers and the spill slots, below them %rsp is aligned to 16 bytes.
+pub fn emit_function(f: &Function, alloc: &Allocation) -> Vec<String> {
+    let mut e = Emitter {
+        f,
+        alloc,
+        out: Vec::new(),
+    };
+    e.prologue();
+    for instr in &f.instrs {
+        e.emit(instr);
+    }
+    e.out
+}
+
+struct Emitter<'a> {
+    f: &'a Function,
+    alloc: &'a Allocation,
+    out: Vec<String>,
+}
+
+impl<'a> Emitter<'a> {
+    fn add(&mut self, s: String) {
+        self.out.push(format!("    {}", s));
+    }
+
+    fn prologue(&mut self) {
+        self.out.push(format!("{}:", self.f.name));
+        self.add("pushq %rbp".to_string());
+        self.add("movq %rsp, %rbp".to_string());
+        for reg in self.alloc.callee_saved.clone() {
+            self.add(format!("pushq {}", reg));
+        }
+        let saved_size = 8 * self.alloc.callee_saved.len();
+        let spill_size = 8 * self.alloc.spill_slots;
+        let frame_size = align((saved_size + spill_size) as u32, 16) as usize - saved_size;
+        if frame_size > 0 {
+            self.add(format!("subq ${}, %rsp", frame_size));
+        }
+    }
+
+    fn epilogue(&mut self) {
+        if self.alloc.callee_saved.is_empty() {
+            self.add("leave".to_string());
+            return;
+        }
+        self.add(format!("leaq -{}(%rbp), %rsp", 8 * self.alloc.callee_saved.len()));
+        for reg in self.alloc.callee_saved.clone().into_iter().rev() {
+            self.add(format!("popq {}", reg));
+        }
+        self.add("popq %rbp".to_string());
+    }
+
+    fn label(&self, label: Label) -> String {
+        format!(".L{}_{}", self.f.name, label)
+    }
+
+    fn loc(&self, r: VReg) -> Loc {
+        self.alloc.locs[r].unwrap()
+    }
+
+    fn slot(&self, idx: usize) -> String {
+        format!("-{}(%rbp)", 8 * (self.alloc.callee_saved.len() + 1 + idx))
+    }
+
+    // the register or the spill slot
+    fn op(&self, r: VReg, w: Width) -> String {
+        match self.loc(r) {
+            Loc::Reg(reg) => reg_name(reg, w),
+            Loc::Stack(idx) => self.slot(idx),
+        }
+    }
+
+    fn val_class(&self, v: &Val) -> RegClass {
+        match *v {
+            Val::Reg(r) => self.f.classes[r],
+            Val::Imm(_) => RegClass::Int,
+        }
+    }
+
+    // register which holds the value, a spilled one is loaded to the scratch register
+    fn in_reg(&mut self, r: VReg, scratch: &'static str) -> &'static str {
+        match self.loc(r) {
+            Loc::Reg(reg) => reg,
+            Loc::Stack(idx) => {
+                let slot = self.slot(idx);
+                match self.f.classes[r] {
+                    RegClass::Int => self.add(format!("movq {}, {}", slot, scratch)),
+                    RegClass::Float => self.add(format!("movsd {}, {}", slot, scratch)),
+                }
+                scratch
+            }
+        }
+    }
+
+    // Source operand of an integer instruction. Immediates which don't fit
+    // in 32 bits are moved to the scratch register.
+    fn src(&mut self, v: &Val, w: Width, scratch: &'static str) -> String {
+        match *v {
+            Val::Reg(r) => self.op(r, w),
+            Val::Imm(x) if fits_imm(x) => format!("${}", x),
+            Val::Imm(x) => {
+                self.add(format!("movabsq ${}, {}", x, scratch));
+                reg_name(scratch, w)
+            }
+        }
+    }
+
+    // register where the result is computed, its own one unless it's spilled
+    fn dst_reg(&self, dst: VReg, scratch: &'static str) -> &'static str {
+        match self.loc(dst) {
+            Loc::Reg(reg) => reg,
+            Loc::Stack(_) => scratch,
+        }
+    }
+
+    fn set_result(&mut self, dst: VReg, reg: &'static str) {
+        if self.loc(dst) == Loc::Reg(reg) {
+            return;
+        }
+        let dst_op = self.op(dst, Width::Quad);
+        match (self.f.classes[dst], self.loc(dst)) {
+            (RegClass::Int, _) => self.add(format!("movq {}, {}", reg, dst_op)),
+            (RegClass::Float, Loc::Reg(_)) => self.add(format!("movapd {}, {}", reg, dst_op)),
+            (RegClass::Float, Loc::Stack(_)) => self.add(format!("movsd {}, {}", reg, dst_op)),
+        }
+    }
+
+    // moves the value to the register of the given class
+    fn load_to(&mut self, reg: &'static str, v: &Val) {
+        match *v {
+            Val::Imm(x) if fits_imm(x) => self.add(format!("movq ${}, {}", x, reg)),
+            Val::Imm(x) => self.add(format!("movabsq ${}, {}", x, reg)),
+            Val::Reg(r) if self.loc(r) == Loc::Reg(reg) => {}
+            Val::Reg(r) => {
+                let src = self.op(r, Width::Quad);
+                match (self.f.classes[r], self.loc(r)) {
+                    (RegClass::Int, _) => self.add(format!("movq {}, {}", src, reg)),
+                    (RegClass::Float, Loc::Reg(_)) => self.add(format!("movapd {}, {}", src, reg)),
+                    (RegClass::Float, Loc::Stack(_)) => self.add(format!("movsd {}, {}", src, reg)),
+                }
+            }
+        }
+    }
+
+    fn addr(&mut self, mem: &Mem) -> String {
+        match *mem {
+            Mem::Base(base, 0) => format!("({})", self.in_reg(base, "%r10")),
+            Mem::Base(base, offset) => format!("{}({})", offset, self.in_reg(base, "%r10")),
+            Mem::Index(base, idx, scale) => {
+                let base = self.in_reg(base, "%r10");
+                let idx = self.in_reg(idx, "%r11");
+                format!("({},{},{})", base, idx, scale)
+            }
+            Mem::Sym(ref sym) => format!("{}(%rip)", sym),
+        }
+    }
+
+    fn emit(&mut self, instr: &Instr) {
+        match *instr {
+            Instr::Params(ref regs) => self.emit_params(regs),
+            Instr::Label(label) => {
+                let label = self.label(label);
+                self.out.push(format!("{}:", label));
+            }
+            Instr::Jump(label) => {
+                let label = self.label(label);
+                self.add(format!("jmp {}", label));
+            }
+            Instr::Branch(r, if_set, label) => {
+                let op = self.op(r, Width::Long);
+                self.add(format!("cmpl $0, {}", op));
+                let label = self.label(label);
+                match if_set {
+                    true => self.add(format!("jne {}", label)),
+                    false => self.add(format!("je {}", label)),
+                }
+            }
+            Instr::Mov(dst, ref v) => self.emit_mov(dst, v),
+            Instr::DoubleConst(dst, x) => {
+                self.add(format!("movabsq ${}, %rax", x.to_bits() as i64));
+                let dst_op = self.op(dst, Width::Quad);
+                self.add(format!("movq %rax, {}", dst_op));
+            }
+            Instr::Lea(dst, ref sym) => {
+                let d = self.dst_reg(dst, "%rax");
+                self.add(format!("leaq {}(%rip), {}", sym, d));
+                self.set_result(dst, d);
+            }
+            Instr::Load(dst, ref mem, w) => {
+                let addr = self.addr(mem);
+                match self.f.classes[dst] {
+                    RegClass::Int => {
+                        let d = self.dst_reg(dst, "%rax");
+                        let d_long = reg_name(d, Width::Long);
+                        match w {
+                            Width::Byte => self.add(format!("movzbl {}, {}", addr, d_long)),
+                            Width::Long => self.add(format!("movl {}, {}", addr, d_long)),
+                            Width::Quad => self.add(format!("movq {}, {}", addr, d)),
+                        }
+                        self.set_result(dst, d);
+                    }
+                    RegClass::Float => {
+                        let d = self.dst_reg(dst, "%xmm15");
+                        self.add(format!("movsd {}, {}", addr, d));
+                        self.set_result(dst, d);
+                    }
+                }
+            }
+            Instr::Store(ref v, ref mem, w) => self.emit_store(v, mem, w),
+            Instr::Bin(op, w, dst, lhs, ref rhs) => self.emit_bin(op, w, dst, lhs, rhs),
+            Instr::Div(w, is_rem, dst, lhs, rhs) => {
+                let lhs_op = self.op(lhs, w);
+                self.add(format!("mov{} {}, {}", suffix(w), lhs_op, reg_name("%rax", w)));
+                match w {
+                    Width::Quad => self.add("cqto".to_string()),
+                    _ => self.add("cltd".to_string()),
+                }
+                let rhs_op = self.op(rhs, w);
+                self.add(format!("idiv{} {}", suffix(w), rhs_op));
+                match is_rem {
+                    true => self.set_result(dst, "%rdx"),
+                    false => self.set_result(dst, "%rax"),
+                }
+            }
+            Instr::Neg(w, dst, src) |
+            Instr::Not(w, dst, src) => {
+                let name = match *instr {
+                    Instr::Neg(..) => "neg",
+                    _ => "not",
+                };
+                let d = self.dst_reg(dst, "%rax");
+                self.load_to(d, &Val::Reg(src));
+                self.add(format!("{}{} {}", name, suffix(w), reg_name(d, w)));
+                self.set_result(dst, d);
+            }
+            Instr::FBin(op, dst, lhs, rhs) => {
+                let d = match self.loc(dst) {
+                    Loc::Reg(reg) if dst != rhs => reg,
+                    _ => "%xmm15",
+                };
+                self.load_to(d, &Val::Reg(lhs));
+                let name = match op {
+                    FBinOp::Add => "addsd",
+                    FBinOp::Sub => "subsd",
+                    FBinOp::Mul => "mulsd",
+                    FBinOp::Div => "divsd",
+                };
+                let rhs_op = self.op(rhs, Width::Quad);
+                self.add(format!("{} {}, {}", name, rhs_op, d));
+                self.set_result(dst, d);
+            }
+            Instr::FNeg(dst, src) => {
+                // the sign bit is flipped, as in LLVM
+                let src_op = self.op(src, Width::Quad);
+                self.add(format!("movq {}, %rax", src_op));
+                self.add("btcq $63, %rax".to_string());
+                let dst_op = self.op(dst, Width::Quad);
+                self.add(format!("movq %rax, {}", dst_op));
+            }
+            Instr::Cmp(cond, w, dst, lhs, ref rhs) => {
+                let lhs_reg = self.in_reg(lhs, "%r10");
+                let rhs_op = self.src(rhs, w, "%r11");
+                self.add(format!("cmp{} {}, {}", suffix(w), rhs_op, reg_name(lhs_reg, w)));
+                self.add(format!("set{} %al", cond_suffix(cond)));
+                self.add("movzbl %al, %eax".to_string());
+                self.set_result(dst, "%rax");
+            }
+            Instr::FCmp(cond, dst, lhs, rhs) => self.emit_fcmp(cond, dst, lhs, rhs),
+            Instr::Conv(conv, dst, src) => self.emit_conv(conv, dst, src),
+            Instr::Call(ref callee, ref args, dst) => {
+                self.emit_call(callee, args, false);
+                if let Some(dst) = dst {
+                    match self.f.classes[dst] {
+                        RegClass::Int => self.set_result(dst, "%rax"),
+                        RegClass::Float => self.set_result(dst, "%xmm0"),
+                    }
+                }
+            }
+            Instr::TailCall(ref callee, ref args) => self.emit_call(callee, args, true),
+            Instr::Ret(ref v) => {
+                match *v {
+                    Some(ref v) if self.val_class(v) == RegClass::Float => self.load_to("%xmm0", v),
+                    Some(ref v) => self.load_to("%rax", v),
+                    None => {}
+                }
+                self.epilogue();
+                self.add("ret".to_string());
+            }
+        }
+    }
+
+    // Args in registers are pushed and popped to the registers of their values,
+    // which can be any of them. Others are in the frame of the caller.
+    fn emit_params(&mut self, regs: &[VReg]) {
+        let classes: Vec<RegClass> = regs.iter().map(|&r| self.f.classes[r]).collect();
+        let (reg_args, stack_args) = split_args(&classes);
+        for &(_, reg) in &reg_args {
+            self.push_reg(reg);
+        }
+        for &(idx, _) in reg_args.iter().rev() {
+            let r = regs[idx];
+            match self.alloc.locs[r] {
+                None => self.add("addq $8, %rsp".to_string()),
+                Some(Loc::Stack(slot)) => {
+                    let slot = self.slot(slot);
+                    self.add(format!("popq {}", slot));
+                }
+                Some(Loc::Reg(reg)) => self.pop_reg(reg),
+            }
+        }
+        for (pos, &idx) in stack_args.iter().enumerate() {
+            let r = regs[idx];
+            let addr = format!("{}(%rbp)", 16 + 8 * pos);
+            match (self.alloc.locs[r], self.f.classes[r]) {
+                (None, _) => {}
+                (Some(Loc::Reg(reg)), RegClass::Int) => {
+                    self.add(format!("movq {}, {}", addr, reg));
+                }
+                (Some(Loc::Reg(reg)), RegClass::Float) => {
+                    self.add(format!("movsd {}, {}", addr, reg));
+                }
+                (Some(Loc::Stack(slot)), _) => {
+                    self.add(format!("movq {}, %rax", addr));
+                    let slot = self.slot(slot);
+                    self.add(format!("movq %rax, {}", slot));
+                }
+            }
+        }
+    }
+
+    fn push_reg(&mut self, reg: &str) {
+        match reg.starts_with("%xmm") {
+            true => {
+                self.add("subq $8, %rsp".to_string());
+                self.add(format!("movsd {}, (%rsp)", reg));
+            }
+            false => self.add(format!("pushq {}", reg)),
+        }
+    }
+
+    fn pop_reg(&mut self, reg: &str) {
+        match reg.starts_with("%xmm") {
+            true => {
+                self.add(format!("movsd (%rsp), {}", reg));
+                self.add("addq $8, %rsp".to_string());
+            }
+            false => self.add(format!("popq {}", reg)),
+        }
+    }
+
+    fn push_val(&mut self, v: &Val) {
+        match *v {
+            Val::Imm(x) if fits_imm(x) => self.add(format!("pushq ${}", x)),
+            Val::Imm(x) => {
+                self.add(format!("movabsq ${}, %r11", x));
+                self.add("pushq %r11".to_string());
+            }
+            Val::Reg(r) => {
+                match self.loc(r) {
+                    Loc::Reg(reg) => self.push_reg(reg),
+                    Loc::Stack(idx) => {
+                        let slot = self.slot(idx);
+                        self.add(format!("pushq {}", slot));
+                    }
+                }
+            }
+        }
+    }
+
+    // A tail call leaves the frame before the jump, it has no args on the stack.
+    fn emit_call(&mut self, callee: &Callee, args: &[Val], is_tail: bool) {
+        let classes: Vec<RegClass> = args.iter().map(|v| self.val_class(v)).collect();
+        let (reg_args, stack_args) = split_args(&classes);
+        let padding = stack_args.len() % 2 * 8;
+        if padding > 0 {
+            self.add("subq $8, %rsp".to_string());
+        }
+        for &idx in stack_args.iter().rev() {
+            self.push_val(&args[idx]);
+        }
+        for &(idx, _) in &reg_args {
+            self.push_val(&args[idx]);
+        }
+        let target = match *callee {
+            Callee::Direct(ref name) => format!("{}@PLT", name),
+            Callee::Indirect(r) => {
+                let op = self.op(r, Width::Quad);
+                self.add(format!("movq {}, %r11", op));
+                "*%r11".to_string()
+            }
+        };
+        for &(_, reg) in reg_args.iter().rev() {
+            self.pop_reg(reg);
+        }
+        if is_tail {
+            self.epilogue();
+            self.add(format!("jmp {}", target));
+            return;
+        }
+        self.add(format!("call {}", target));
+        let args_size = 8 * stack_args.len() + padding;
+        if args_size > 0 {
+            self.add(format!("addq ${}, %rsp", args_size));
+        }
+    }
+
+    fn emit_mov(&mut self, dst: VReg, v: &Val) {
+        match (self.loc(dst), v) {
+            (Loc::Reg(reg), _) => self.load_to(reg, v),
+            (Loc::Stack(_), &Val::Reg(src)) if self.loc(src) == self.loc(dst) => {}
+            (Loc::Stack(idx), &Val::Imm(x)) if fits_imm(x) => {
+                let slot = self.slot(idx);
+                self.add(format!("movq ${}, {}", x, slot));
+            }
+            (Loc::Stack(_), &Val::Reg(src)) if self.f.classes[src] == RegClass::Float => {
+                let reg = self.in_reg(src, "%xmm15");
+                self.set_result(dst, reg);
+            }
+            (Loc::Stack(_), _) => {
+                self.load_to("%rax", v);
+                self.set_result(dst, "%rax");
+            }
+        }
+    }
+
+    fn emit_store(&mut self, v: &Val, mem: &Mem, w: Width) {
+        let addr = self.addr(mem);
+        match *v {
+            Val::Imm(x) if fits_imm(x) => {
+                self.add(format!("mov{} ${}, {}", suffix(w), x, addr));
+            }
+            Val::Reg(r) if self.f.classes[r] == RegClass::Float => {
+                let reg = self.in_reg(r, "%xmm15");
+                self.add(format!("movsd {}, {}", reg, addr));
+            }
+            _ => {
+                let reg = match *v {
+                    Val::Reg(r) => self.in_reg(r, "%rax"),
+                    Val::Imm(_) => {
+                        self.load_to("%rax", v);
+                        "%rax"
+                    }
+                };
+                self.add(format!("mov{} {}, {}", suffix(w), reg_name(reg, w), addr));
+            }
+        }
+    }
+
+    fn emit_bin(&mut self, op: BinOp, w: Width, dst: VReg, lhs: VReg, rhs: &Val) {
+        // the result can't be computed in the register of rhs
+        let d = match self.loc(dst) {
+            Loc::Reg(reg) if *rhs != Val::Reg(dst) => reg,
+            _ => "%rax",
+        };
+        let lhs_op = self.op(lhs, w);
+        let d_op = reg_name(d, w);
+        if lhs_op != d_op {
+            self.add(format!("mov{} {}, {}", suffix(w), lhs_op, d_op));
+        }
+        let name = match op {
+            BinOp::Add => "add",
+            BinOp::Sub => "sub",
+            BinOp::Mul => "imul",
+            BinOp::And => "and",
+            BinOp::Or => "or",
+            BinOp::Xor => "xor",
+            BinOp::Shl => "sal",
+            BinOp::Sar => "sar",
+        };
+        match (op, rhs) {
+            (BinOp::Shl, &Val::Reg(r)) |
+            (BinOp::Sar, &Val::Reg(r)) => {
+                let count = self.op(r, Width::Long);
+                self.add(format!("movl {}, %ecx", count));
+                self.add(format!("{}{} %cl, {}", name, suffix(w), d_op));
+            }
+            _ => {
+                let src = self.src(rhs, w, "%r11");
+                self.add(format!("{}{} {}, {}", name, suffix(w), src, d_op));
+            }
+        }
+        self.set_result(dst, d);
+    }
+
+    // Comparisons with NaN are false, except !=. Less is done as greater with
+    // swapped operands, so that unordered operands give false.
+    fn emit_fcmp(&mut self, cond: Cond, dst: VReg, lhs: VReg, rhs: VReg) {
+        let (lhs, rhs) = match cond {
+            Cond::Lt | Cond::Le => (rhs, lhs),
+            _ => (lhs, rhs),
+        };
+        let lhs_reg = self.in_reg(lhs, "%xmm15");
+        let rhs_op = self.op(rhs, Width::Quad);
+        self.add(format!("ucomisd {}, {}", rhs_op, lhs_reg));
+        match cond {
+            Cond::Gt | Cond::Lt => self.add("seta %al".to_string()),
+            Cond::Ge | Cond::Le => self.add("setae %al".to_string()),
+            Cond::Eq => {
+                self.add("sete %al".to_string());
+                self.add("setnp %cl".to_string());
+                self.add("andb %cl, %al".to_string());
+            }
+            Cond::Ne => {
+                self.add("setne %al".to_string());
+                self.add("setp %cl".to_string());
+                self.add("orb %cl, %al".to_string());
+            }
+        }
+        self.add("movzbl %al, %eax".to_string());
+        self.set_result(dst, "%rax");
+    }
+
+    fn emit_conv(&mut self, conv: Conv, dst: VReg, src: VReg) {
+        let d = match self.f.classes[dst] {
+            RegClass::Int => self.dst_reg(dst, "%rax"),
+            RegClass::Float => self.dst_reg(dst, "%xmm15"),
+        };
+        match conv {
+            Conv::SignExtend => {
+                let src_op = self.op(src, Width::Long);
+                self.add(format!("movslq {}, {}", src_op, d));
+            }
+            Conv::ZeroExtend => {
+                let src_op = self.op(src, Width::Long);
+                self.add(format!("movl {}, {}", src_op, reg_name(d, Width::Long)));
+            }
+            Conv::ZeroExtendByte => {
+                let src_op = self.op(src, Width::Byte);
+                self.add(format!("movzbl {}, {}", src_op, reg_name(d, Width::Long)));
+            }
+            Conv::IntToDouble(w) => {
+                let src_op = self.op(src, w);
+                self.add(format!("cvtsi2sd{} {}, {}", suffix(w), src_op, d));
+            }
+            Conv::BitsToDouble |
+            Conv::DoubleToBits => {
+                let src_op = self.op(src, Width::Quad);
+                self.add(format!("movq {}, {}", src_op, d));
+            }
+        }
+        self.set_result(dst, d);
+    }
+}
+
+// Registers of args in order, other args are passed on the stack.
+fn split_args(classes: &[RegClass]) -> (Vec<(usize, &'static str)>, Vec<usize>) {
+    let mut reg_args = Vec::new();
+    let mut stack_args = Vec::new();
+    let (mut ints, mut floats) = (0, 0);
+    for (idx, class) in classes.iter().enumerate() {
+        match *class {
+            RegClass::Int if ints < INT_ARG_REGS.len() => {
+                reg_args.push((idx, INT_ARG_REGS[ints]));
+                ints += 1;
+            }
+            RegClass::Float if floats < FLOAT_ARG_REGS.len() => {
+                reg_args.push((idx, FLOAT_ARG_REGS[floats]));
+                floats += 1;
+            }
+            _ => stack_args.push(idx),
+        }
+    }
+    (reg_args, stack_args)
+}
+
+// whether the args of a call can be passed in registers only
+pub fn fits_in_regs(classes: &[RegClass]) -> bool {
+    split_args(classes).1.is_empty()
+}
+
+fn fits_imm(x: i64) -> bool {
+    x as i32 as i64 == x
+}
+
+fn suffix(w: Width) -> &'static str {
+    match w {
+        Width::Byte => "b",
+        Width::Long => "l",
+        Width::Quad => "q",
+    }
+}
+
+fn cond_suffix(cond: Cond) -> &'static str {
+    match cond {
+        Cond::Eq => "e",
+        Cond::Ne => "ne",
+        Cond::Lt => "l",
+        Cond::Le => "le",
+        Cond::Gt => "g",
+        Cond::Ge => "ge",
+    }
+}
+
+// name of the lower part of the register, e.g. %eax or %al of %rax
+fn reg_name(reg: &str, w: Width) -> String {
+    if w == Width::Quad || reg.starts_with("%xmm") || !reg.starts_with("%r") {
+        return reg.to_string();
+    }
+    let name = &reg[2..];
+    let is_numbered = name.as_bytes()[0].is_ascii_digit();
+    match (is_numbered, w) {
+        (true, Width::Long) => format!("{}d", reg),
+        (true, _) => format!("{}b", reg),
+        (false, Width::Long) => format!("%e{}", name),
+        (false, _) if name.ends_with('x') => format!("%{}l", &name[..1]),
+        (false, _) => format!("%{}l", name),
+    }
+}
@@ -0,0 +1,30 @@
+use ast::{Enum, Type};
+use mangling::*;
+
+use x86_generation::context::Context;
+use x86_generation::generate::*;
+use x86_generation::ir::*;
+
+// Generates name() of the enum, which maps tags to names of the variants.
+// Tags out of range are not possible, so the first variant is the default.
+impl GenerateCode<()> for Enum {
+    fn generate_code(&self, ctx: &mut Context) {
+        ctx.begin_function();
+        let tag = ctx.new_reg(&Type::TInt);
+        ctx.add_instr(Instr::Params(vec![tag]));
+        let name = ctx.new_reg(&Type::TString);
+        let first = ctx.get_str_const(&self.variants[0].0);
+        ctx.add_instr(Instr::Lea(name, first));
+        for (idx, variant) in self.variants.iter().enumerate().skip(1) {
+            let variant_name = ctx.get_str_const(&variant.0);
+            let (is_tag, next) = (ctx.new_reg(&Type::TBool), ctx.next_label());
+            ctx.add_instr(Instr::Cmp(Cond::Eq, Width::Long, is_tag, tag, Val::Imm(idx as i64)));
+            ctx.add_instr(Instr::Branch(is_tag, false, next));
+            ctx.add_instr(Instr::Lea(name, variant_name));
+            ctx.add_instr(Instr::Label(next));
+        }
+        ctx.retain_string(&Val::Reg(name));
+        ctx.add_instr(Instr::Ret(Some(Val::Reg(name))));
+        ctx.end_function(&enum_name_symbol(&self.name));
+    }
+}
@@ -0,0 +1,660 @@
+use ast::*;
+use mangling::*;
+
+use x86_generation::collection::*;
+use x86_generation::context::*;
+use x86_generation::extern_func::*;
+use x86_generation::field_get::*;
+use x86_generation::generate::*;
+use x86_generation::ir::*;
+use x86_generation::x86_type::*;
+
+// Values are constants or registers. Every value which isn't a constant or a local
+// is computed in a new register, so that the order of evaluation is the same as
+// in Latte.
+impl GenerateCode<(Val, Type)> for Expr {
+    fn generate_code(&self, ctx: &mut Context) -> (Val, Type) {
+        let (val, t) = match *self {
+            Expr::EVar(ref ident) if as_enum_variant(ident, ctx).is_some() => {
+                let (tag, t) = as_enum_variant(ident, ctx).unwrap();
+                (Val::Imm(tag as i64), t)
+            }
+            // locals can't be changed by calls, strings are retained
+            Expr::EVar(FieldGet::Direct(ref ident)) if ctx.var_exists(ident) &&
+                                                       ctx.get_var(ident).1 != Type::TString => {
+                let (r, t) = ctx.get_var(ident);
+                (Val::Reg(r), t)
+            }
+            Expr::EVar(ref ident) => {
+                let (lvalue, t) = ident.generate_code(ctx);
+                let val = Val::Reg(ctx.add_load(&lvalue, &t));
+                if t == Type::TString {
+                    ctx.retain_string(&val);
+                }
+                (val, t)
+            }
+            Expr::ELit(ref lit) => lit.generate_code(ctx),
+            Expr::ECall(ref ident, ref args) => generate_call(ident, args, ctx),
+            Expr::ENeg(ref e) => {
+                let (val, t) = e.generate_code(ctx);
+                if t == Type::TDouble {
+                    let src = ctx.reg_of(&val, &t);
+                    let res = ctx.new_reg(&t);
+                    ctx.add_instr(Instr::FNeg(res, src));
+                    (Val::Reg(res), t)
+                } else if ctx.opts.check_overflow {
+                    (generate_arith_op(&t, &Val::Imm(0), Operator::OpSub, &val, ctx), t)
+                } else {
+                    let src = ctx.reg_of(&val, &t);
+                    let res = ctx.new_reg(&t);
+                    ctx.add_instr(Instr::Neg(op_width(&t), res, src));
+                    (Val::Reg(res), t)
+                }
+            }
+            Expr::ENot(ref e) => {
+                let (val, t) = e.generate_code(ctx);
+                let src = ctx.reg_of(&val, &t);
+                let res = ctx.new_reg(&t);
+                ctx.add_instr(Instr::Bin(BinOp::Xor, Width::Long, res, src, Val::Imm(1)));
+                (Val::Reg(res), t)
+            }
+            Expr::EBitNot(ref e) => {
+                let (val, t) = e.generate_code(ctx);
+                let src = ctx.reg_of(&val, &t);
+                let res = ctx.new_reg(&t);
+                ctx.add_instr(Instr::Not(op_width(&t), res, src));
+                (Val::Reg(res), t)
+            }
+            Expr::EBinOp(ref lhs, Operator::OpOr, ref rhs) => generate_or(lhs, rhs, ctx),
+            Expr::EBinOp(ref lhs, Operator::OpAnd, ref rhs) => generate_and(lhs, rhs, ctx),
+            Expr::EBinOp(ref lhs, Operator::OpNEq, ref rhs) => generate_eq(lhs, rhs, true, ctx),
+            Expr::EBinOp(ref lhs, Operator::OpEq, ref rhs) => generate_eq(lhs, rhs, false, ctx),
+            Expr::EBinOp(ref lhs, Operator::OpAdd, ref rhs) => generate_add(lhs, rhs, ctx),
+            Expr::EBinOp(ref lhs, ref op, ref rhs) => {
+                let (lhs_val, lhs_t) = lhs.generate_code(ctx);
+                let (mut rhs_val, rhs_t) = rhs.generate_code(ctx);
+                if lhs_t == Type::TString {
+                    return generate_str_cmp(&lhs_val, *op, &rhs_val, ctx);
+                }
+                let t = match *op {
+                    Operator::OpLess | Operator::OpLessE | Operator::OpGreater |
+                    Operator::OpGreaterE => Type::TBool,
+                    Operator::OpAdd | Operator::OpSub | Operator::OpMul | Operator::OpDiv |
+                    Operator::OpMod | Operator::OpBitAnd | Operator::OpBitOr |
+                    Operator::OpBitXor => lhs_t.clone(),
+                    Operator::OpShl | Operator::OpShr => {
+                        // only the lowest 5 bits of the shift count are used
+                        rhs_val = generate_arith_op(&rhs_t,
+                                                    &rhs_val,
+                                                    Operator::OpBitAnd,
+                                                    &Val::Imm(31),
+                                                    ctx);
+                        lhs_t.clone()
+                    }
+                    _ => unreachable!(),
+                };
+                (generate_arith_op(&lhs_t, &lhs_val, *op, &rhs_val, ctx), t)
+            }
+            Expr::ENew(ref t) => {
+                match *t {
+                    Type::TObject(ref cname) => {
+                        (ctx.call_runtime(&new_symbol(cname), vec![], t), t.clone())
+                    }
+                    _ => (generate_new_collection(t, ctx), t.clone()),
+                }
+            }
+            Expr::ENewArray(ref t, ref sizes) => {
+                let mut arr_t = t.clone();
+                let mut size_vals: Vec<Val> = Vec::new();
+                for size in sizes {
+                    size_vals.push(size.generate_code(ctx).0);
+                    arr_t = Type::TArray(Box::new(arr_t));
+                }
+                (new_arr(&arr_t, &size_vals, ctx), arr_t)
+            }
+            Expr::EArrayLit(..) => unreachable!("array literal without destination type"),
+            Expr::ECast(ref t, ref e) => {
+                let (val, e_t) = e.generate_code(ctx);
+                match e_t == *t {
+                    true => (val, e_t),
+                    false => (generate_cast(&val, &e_t, t, ctx), t.clone()),
+                }
+            }
+            Expr::ECond(ref cond, ref e1, ref e2) => generate_cond(cond, e1, e2, ctx),
+        };
+        if t == Type::TString {
+            ctx.add_string_tmp(val.clone());
+        }
+        (val, t)
+    }
+}
+
+fn generate_call(ident: &FieldGet, args: &[Expr], ctx: &mut Context) -> (Val, Type) {
+    let call = match prepare_call(ident, args, ctx) {
+        PreparedCall::Generated(val, t) => return (val, t),
+        PreparedCall::Call(call) => call,
+    };
+    let callee = get_callee(&call, ctx);
+    let args = call.args.iter().map(|(arg, _)| arg.clone()).collect();
+    (ctx.add_call(callee, args, &call.ret_type), call.ret_type)
+}
+
+pub struct Call {
+    pub func: String, // symbol of the function, unless it's called through the vtable
+    pub vtable_pos: Option<usize>,
+    pub ret_type: Type,
+    pub args: Vec<(Val, Type)>, // evaluated, with self first for methods
+}
+
+pub enum PreparedCall {
+    Generated(Val, Type), // methods of collections and enums are generated in place
+    Call(Call),
+}
+
+// The function called directly, or its address loaded from the vtable.
+pub fn get_callee(call: &Call, ctx: &mut Context) -> Callee {
+    match call.vtable_pos {
+        Some(pos) => {
+            // the vtable pointer is at the beginning of every object
+            let (ref obj, ref obj_t) = call.args[0];
+            let obj = ctx.reg_of(obj, obj_t);
+            let vtable = ctx.new_reg(&Type::TLong);
+            ctx.add_instr(Instr::Load(vtable, Mem::Base(obj, 0), Width::Quad));
+            let func = ctx.new_reg(&Type::TLong);
+            ctx.add_instr(Instr::Load(func, Mem::Base(vtable, 8 * pos as i32), Width::Quad));
+            Callee::Indirect(func)
+        }
+        None => Callee::Direct(call.func.clone()),
+    }
+}
+
+// Evaluates the object and args of the call.
+pub fn prepare_call(ident: &FieldGet, args: &[Expr], ctx: &mut Context) -> PreparedCall {
+    let (obj, func_name): (Option<(Val, Type)>, Ident) = ident.generate_code(ctx);
+    let obj = match obj {
+        Some((val, Type::TObject(ref cname))) => Some((val, ctx.get_class_id(cname))),
+        // the only method of enums is name()
+        Some((val, Type::TEnum(ref name))) => {
+            let val = ctx.call_runtime(&enum_name_symbol(name), vec![val], &Type::TString);
+            return PreparedCall::Generated(val, Type::TString);
+        }
+        Some((val, obj_t)) => {
+            let (val, t) = generate_collection_call(&val, &obj_t, &func_name, args, ctx);
+            return PreparedCall::Generated(val, t);
+        }
+        None if ctx.is_extern(&func_name) => {
+            let (val, t) = generate_extern_call(&func_name, args, ctx);
+            return PreparedCall::Generated(val, t);
+        }
+        None => None,
+    };
+    let ret_type;
+    let arg_types;
+    let mut func = String::new();
+    let mut vtable_pos = None;
+    let mut final_args: Vec<(Val, Type)> = Vec::new();
+    if let Some((obj, id)) = obj {
+        let pos = *ctx.get_class_data(id).vtable.idxs.get(&func_name).unwrap();
+        let f_info = ctx.get_class_data(id).vtable.fs[pos].clone();
+
+        ret_type = f_info.ret_type.clone();
+        arg_types = f_info.arg_types.clone();
+        if ctx.is_overridden(id, pos) {
+            vtable_pos = Some(pos);
+        } else {
+            if ctx.opts.report_devirt {
+                let call = Expr::ECall(ident.clone(), args.to_owned());
+                ctx.add_devirtualized(&call, f_info.class_id, &func_name);
+            }
+            func = f_info.real_ident.clone();
+        }
+        let obj_t = Type::TObject(ctx.get_class_data(f_info.class_id).ident.clone());
+        final_args.push((obj, obj_t));
+    } else {
+        ret_type = ctx.get_ret_type(&func_name);
+        arg_types = ctx.get_arg_types(&func_name);
+        func = ctx.get_func_symbol(&func_name);
+    }
+
+    for (arg, arg_dst_t) in args.iter().zip(arg_types) {
+        final_args.push((generate_init_expr(arg, &arg_dst_t, ctx), arg_dst_t));
+    }
+
+    PreparedCall::Call(Call {
+        func,
+        vtable_pos,
+        ret_type,
+        args: final_args,
+    })
+}
+
+// Generates value to be stored in a location of type t (variable, argument etc.),
+// objects of subclasses have the same addresses as their superclasses.
+pub fn generate_init_expr(e: &Expr, t: &Type, ctx: &mut Context) -> Val {
+    match *e {
+        Expr::EArrayLit(ref elems) => generate_array_lit(elems, t, ctx),
+        _ => e.generate_code(ctx).0,
+    }
+}
+
+pub fn arr_elem_t(arr_t: &Type) -> Type {
+    match *arr_t {
+        Type::TArray(ref elem_t) => *elem_t.clone(),
+        _ => unreachable!(),
+    }
+}
+
+// elements are zeroed
+fn alloc_arr(arr_t: &Type, size: &Val, ctx: &mut Context) -> Val {
+    let elem_size = Val::Imm(size_of(&arr_elem_t(arr_t)) as i64);
+    ctx.call_runtime("_new_array", vec![size.clone(), elem_size], arr_t)
+}
+
+// sizes of subsequent dimensions, subarrays of missing dimensions are null
+fn new_arr(arr_t: &Type, sizes: &[Val], ctx: &mut Context) -> Val {
+    let arr = alloc_arr(arr_t, &sizes[0], ctx);
+    let elem_t = arr_elem_t(arr_t);
+    if sizes.len() > 1 {
+        ctx.in_new_loop(&sizes[0], |ctx, idx| {
+            let subarr = new_arr(&elem_t, &sizes[1..], ctx);
+            let elem = elem_lvalue(&arr, &Val::Reg(idx), &elem_t, ctx);
+            ctx.add_store(&elem, &subarr);
+        });
+    } else if elem_t == Type::TString {
+        ctx.call_runtime("_init_str_arr", vec![arr.clone()], &Type::TVoid);
+    }
+    arr
+}
+
+fn generate_array_lit(elems: &[Expr], arr_t: &Type, ctx: &mut Context) -> Val {
+    let elem_t = arr_elem_t(arr_t);
+    let arr = alloc_arr(arr_t, &Val::Imm(elems.len() as i64), ctx);
+    for (idx, e) in elems.iter().enumerate() {
+        let val = generate_init_expr(e, &elem_t, ctx);
+        if elem_t == Type::TString {
+            ctx.retain_string(&val);
+        }
+        let elem = elem_lvalue(&arr, &Val::Imm(idx as i64), &elem_t, ctx);
+        ctx.add_store(&elem, &val);
+    }
+    arr
+}
+
+fn generate_or(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> (Val, Type) {
+    generate_short_circuit(lhs, rhs, true, ctx)
+}
+
+fn generate_and(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> (Val, Type) {
+    generate_short_circuit(lhs, rhs, false, ctx)
+}
+
+// rhs is evaluated if lhs is true, or false for ||
+fn generate_short_circuit(lhs: &Expr, rhs: &Expr, is_or: bool, ctx: &mut Context) -> (Val, Type) {
+    let lhs_val = ctx.in_new_scope(|ctx| {
+        let (lhs_val, _) = lhs.generate_code(ctx);
+        ctx.release_local_strings();
+        lhs_val
+    });
+    let res = ctx.new_reg(&Type::TBool);
+    ctx.add_instr(Instr::Mov(res, lhs_val));
+    let end = ctx.next_label();
+    ctx.add_instr(Instr::Branch(res, is_or, end));
+    ctx.in_new_scope(|ctx| {
+        let (rhs_val, _) = rhs.generate_code(ctx);
+        ctx.release_local_strings();
+        ctx.add_instr(Instr::Mov(res, rhs_val));
+    });
+    ctx.add_instr(Instr::Label(end));
+    (Val::Reg(res), Type::TBool)
+}
+
+// Both branches store their values in the same register, null and objects of all
+// classes have the same type.
+fn generate_cond(cond: &Expr, e1: &Expr, e2: &Expr, ctx: &mut Context) -> (Val, Type) {
+    let (cond_val, _) = cond.generate_code(ctx);
+    let cond_reg = ctx.reg_of(&cond_val, &Type::TBool);
+    let (if_false, end) = (ctx.next_label(), ctx.next_label());
+    ctx.add_instr(Instr::Branch(cond_reg, false, if_false));
+
+    let (e1_val, e1_t) = ctx.in_new_scope(|ctx| generate_cond_branch(e1, ctx));
+    // both branches have the same class of registers
+    let res = match e1_t {
+        Type::TVoid => None,
+        _ => Some(ctx.new_reg(&e1_t)),
+    };
+    if let Some(res) = res {
+        ctx.add_instr(Instr::Mov(res, e1_val));
+    }
+    ctx.add_instr(Instr::Jump(end));
+    ctx.add_instr(Instr::Label(if_false));
+    let (e2_val, e2_t) = ctx.in_new_scope(|ctx| generate_cond_branch(e2, ctx));
+    if let Some(res) = res {
+        ctx.add_instr(Instr::Mov(res, e2_val));
+    }
+    ctx.add_instr(Instr::Label(end));
+
+    let t = common_type(&e1_t, &e2_t, ctx);
+    match res {
+        Some(res) => (Val::Reg(res), t),
+        None => (Val::Imm(0), t),
+    }
+}
+
+fn generate_cond_branch(e: &Expr, ctx: &mut Context) -> (Val, Type) {
+    let (val, t) = e.generate_code(ctx);
+    if t == Type::TString {
+        ctx.retain_string(&val);
+    }
+    ctx.release_local_strings();
+    (val, t)
+}
+
+// least common supertype of types accepted by the type checker
+fn common_type(t1: &Type, t2: &Type, ctx: &Context) -> Type {
+    if t1 == t2 || *t2 == Type::TNull {
+        return t1.clone();
+    }
+    if *t1 == Type::TNull {
+        return t2.clone();
+    }
+    let (mut id, id2) = match (t1, t2) {
+        (Type::TObject(c1), Type::TObject(c2)) => {
+            (ctx.get_class_id(c1), ctx.get_class_id(c2))
+        }
+        _ => unreachable!(),
+    };
+    while !ctx.is_subclass_of(id2, id) {
+        id = ctx.get_class_data(id).get_super();
+    }
+    Type::TObject(ctx.get_class_data(id).ident.clone())
+}
+
+enum AddOperand {
+    Strings(Vec<Val>), // parts of a concatenation, in order
+    Value(Val, Type),
+}
+
+// Concatenations of more than two strings are done at once, the types are known
+// only here, so the chains are flattened during generation. The parts are passed
+// in a temporary array.
+fn generate_add(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> (Val, Type) {
+    let parts = match generate_add_operands(lhs, rhs, ctx) {
+        AddOperand::Strings(parts) => parts,
+        AddOperand::Value(val, t) => return (val, t),
+    };
+    if parts.len() == 2 {
+        let res = ctx.call_runtime("_concatenate", parts, &Type::TString);
+        return (res, Type::TString);
+    }
+    let size = Val::Imm(8 * parts.len() as i64);
+    let arr = ctx.call_runtime("malloc", vec![size], &Type::TLong);
+    let arr_reg = ctx.reg_of(&arr, &Type::TLong);
+    for (idx, part) in parts.iter().enumerate() {
+        let part_lvalue = LValue::Memory(Mem::Base(arr_reg, 8 * idx as i32), Type::TString);
+        ctx.add_store(&part_lvalue, part);
+    }
+    let parts_cnt = Val::Imm(parts.len() as i64);
+    let res = ctx.call_runtime("_concatenate_n", vec![arr.clone(), parts_cnt], &Type::TString);
+    ctx.call_runtime("free", vec![arr], &Type::TVoid);
+    (res, Type::TString)
+}
+
+fn generate_add_operands(lhs: &Expr, rhs: &Expr, ctx: &mut Context) -> AddOperand {
+    match generate_add_operand(lhs, ctx) {
+        AddOperand::Strings(mut parts) => {
+            match generate_add_operand(rhs, ctx) {
+                AddOperand::Strings(rhs_parts) => parts.extend(rhs_parts),
+                AddOperand::Value(val, _) => parts.push(val),
+            }
+            AddOperand::Strings(parts)
+        }
+        AddOperand::Value(lhs_val, t) => {
+            let (rhs_val, _) = rhs.generate_code(ctx);
+            let val = generate_arith_op(&t, &lhs_val, Operator::OpAdd, &rhs_val, ctx);
+            AddOperand::Value(val, t)
+        }
+    }
+}
+
+fn generate_add_operand(e: &Expr, ctx: &mut Context) -> AddOperand {
+    match *e {
+        Expr::EBinOp(ref lhs, Operator::OpAdd, ref rhs) => generate_add_operands(lhs, rhs, ctx),
+        _ => {
+            let (val, t) = e.generate_code(ctx);
+            match t == Type::TString {
+                true => AddOperand::Strings(vec![val]),
+                false => AddOperand::Value(val, t),
+            }
+        }
+    }
+}
+
+// Integer arithmetic wraps around on overflow, unless overflow checking is enabled.
+// Chars are zero-extended, so they are compared as unsigned, as in C.
+pub fn generate_arith_op(t: &Type, lhs: &Val, op: Operator, rhs: &Val, ctx: &mut Context) -> Val {
+    let is_div = op == Operator::OpDiv || op == Operator::OpMod;
+    let is_checked_op = op == Operator::OpAdd || op == Operator::OpSub || op == Operator::OpMul ||
+                        (is_div && may_be_minus_one(rhs));
+    let suffix = match *t {
+        Type::TInt => "i32",
+        Type::TLong => "i64",
+        _ => "",
+    };
+    if ctx.opts.check_overflow && is_checked_op && !suffix.is_empty() {
+        let func = format!("_checked_{}_{}", checked_op_name(op), suffix);
+        return ctx.call_runtime(&func, vec![lhs.clone(), rhs.clone()], t);
+    }
+
+    let lhs = ctx.reg_of(lhs, t);
+    if *t == Type::TDouble {
+        let rhs = ctx.reg_of(rhs, t);
+        let res = match as_cond(op) {
+            Some(cond) => {
+                let res = ctx.new_reg(&Type::TBool);
+                ctx.add_instr(Instr::FCmp(cond, res, lhs, rhs));
+                res
+            }
+            None => {
+                let res = ctx.new_reg(t);
+                ctx.add_instr(Instr::FBin(float_op(op), res, lhs, rhs));
+                res
+            }
+        };
+        return Val::Reg(res);
+    }
+
+    let w = op_width(t);
+    let res = match (as_cond(op), op) {
+        (Some(cond), _) => {
+            let res = ctx.new_reg(&Type::TBool);
+            ctx.add_instr(Instr::Cmp(cond, w, res, lhs, rhs.clone()));
+            res
+        }
+        (None, Operator::OpDiv) |
+        (None, Operator::OpMod) if is_checked_op && !suffix.is_empty() => {
+            generate_div(w, op, lhs, rhs, t, ctx)
+        }
+        (None, Operator::OpDiv) |
+        (None, Operator::OpMod) => {
+            let rhs = ctx.reg_of(rhs, t);
+            let res = ctx.new_reg(t);
+            ctx.add_instr(Instr::Div(w, op == Operator::OpMod, res, lhs, rhs));
+            res
+        }
+        (None, _) => {
+            let res = ctx.new_reg(t);
+            ctx.add_instr(Instr::Bin(int_op(op), w, res, lhs, rhs.clone()));
+            res
+        }
+    };
+    Val::Reg(res)
+}
+
+// idiv traps on MIN / -1, so x / -1 is computed as -x, which wraps around to MIN,
+// and x % -1 as 0, as in LLVM IR
+fn generate_div(w: Width, op: Operator, lhs: VReg, rhs: &Val, t: &Type, ctx: &mut Context) -> VReg {
+    let rhs = ctx.reg_of(rhs, t);
+    let (res, minus_one) = (ctx.new_reg(t), ctx.new_reg(&Type::TBool));
+    let (if_minus_one, end) = (ctx.next_label(), ctx.next_label());
+    ctx.add_instr(Instr::Cmp(Cond::Eq, w, minus_one, rhs, Val::Imm(-1)));
+    ctx.add_instr(Instr::Branch(minus_one, true, if_minus_one));
+    ctx.add_instr(Instr::Div(w, op == Operator::OpMod, res, lhs, rhs));
+    ctx.add_instr(Instr::Jump(end));
+    ctx.add_instr(Instr::Label(if_minus_one));
+    match op {
+        Operator::OpDiv => ctx.add_instr(Instr::Neg(w, res, lhs)),
+        _ => ctx.add_instr(Instr::Mov(res, Val::Imm(0))),
+    }
+    ctx.add_instr(Instr::Label(end));
+    res
+}
+
+fn checked_op_name(op: Operator) -> &'static str {
+    match op {
+        Operator::OpAdd => "add",
+        Operator::OpSub => "sub",
+        Operator::OpMul => "mul",
+        Operator::OpDiv => "div",
+        Operator::OpMod => "mod",
+        _ => unreachable!(),
+    }
+}
+
+// unless it's a constant
+fn may_be_minus_one(val: &Val) -> bool {
+    match *val {
+        Val::Imm(x) => x == -1,
+        Val::Reg(_) => true,
+    }
+}
+
+fn as_cond(op: Operator) -> Option<Cond> {
+    match op {
+        Operator::OpEq => Some(Cond::Eq),
+        Operator::OpNEq => Some(Cond::Ne),
+        Operator::OpLess => Some(Cond::Lt),
+        Operator::OpLessE => Some(Cond::Le),
+        Operator::OpGreater => Some(Cond::Gt),
+        Operator::OpGreaterE => Some(Cond::Ge),
+        _ => None,
+    }
+}
+
+fn int_op(op: Operator) -> BinOp {
+    match op {
+        Operator::OpAdd => BinOp::Add,
+        Operator::OpSub => BinOp::Sub,
+        Operator::OpMul => BinOp::Mul,
+        Operator::OpBitAnd => BinOp::And,
+        Operator::OpBitOr => BinOp::Or,
+        Operator::OpBitXor => BinOp::Xor,
+        Operator::OpShl => BinOp::Shl,
+        Operator::OpShr => BinOp::Sar,
+        _ => unreachable!(),
+    }
+}
+
+fn float_op(op: Operator) -> FBinOp {
+    match op {
+        Operator::OpAdd => FBinOp::Add,
+        Operator::OpSub => FBinOp::Sub,
+        Operator::OpMul => FBinOp::Mul,
+        Operator::OpDiv => FBinOp::Div,
+        _ => unreachable!(),
+    }
+}
+
+fn generate_eq(lhs: &Expr, rhs: &Expr, negate: bool, ctx: &mut Context) -> (Val, Type) {
+    let (lhs_val, t1) = lhs.generate_code(ctx);
+    let (rhs_val, t2) = rhs.generate_code(ctx);
+    let op = match negate {
+        false => Operator::OpEq,
+        true => Operator::OpNEq,
+    };
+    if t1 == Type::TNull && t2 == Type::TNull {
+        return (Val::Imm(!negate as i64), Type::TBool);
+    }
+    if t1 == Type::TString && t2 == Type::TString {
+        let eq = ctx.call_runtime("_str_eq", vec![lhs_val, rhs_val], &Type::TBool);
+        return match negate {
+            false => (eq, Type::TBool),
+            true => {
+                let eq = ctx.reg_of(&eq, &Type::TBool);
+                let res = ctx.new_reg(&Type::TBool);
+                ctx.add_instr(Instr::Bin(BinOp::Xor, Width::Long, res, eq, Val::Imm(1)));
+                (Val::Reg(res), Type::TBool)
+            }
+        };
+    }
+    // objects of different classes are compared as addresses of their beginnings,
+    // null has the width of pointers
+    let t = match t1 {
+        Type::TNull => t2,
+        _ => t1,
+    };
+    (generate_arith_op(&t, &lhs_val, op, &rhs_val, ctx), Type::TBool)
+}
+
+// lexicographic order of the contents
+fn generate_str_cmp(lhs: &Val, op: Operator, rhs: &Val, ctx: &mut Context) -> (Val, Type) {
+    let cmp = ctx.call_runtime("_str_cmp", vec![lhs.clone(), rhs.clone()], &Type::TInt);
+    (generate_arith_op(&Type::TInt, &cmp, op, &Val::Imm(0), ctx), Type::TBool)
+}
+
+// Conversions are the same as in LLVM: doubles are truncated and saturated, which
+// is done in the runtime, chars are unsigned and narrowing keeps the lowest bits.
+fn generate_cast(val: &Val, from: &Type, to: &Type, ctx: &mut Context) -> Val {
+    let res = ctx.new_reg(to);
+    if *from == Type::TDouble {
+        let (func, t) = match *to {
+            Type::TLong => ("_double_to_i64", Type::TLong),
+            _ => ("_double_to_i32", Type::TInt),
+        };
+        let int = ctx.call_runtime(func, vec![val.clone()], &t);
+        ctx.add_instr(Instr::Mov(res, int));
+    } else {
+        let src = ctx.reg_of(val, from);
+        let conv = match (from, to) {
+            (&Type::TChar, &Type::TLong) => Some(Conv::ZeroExtend),
+            (_, &Type::TLong) => Some(Conv::SignExtend),
+            (&Type::TLong, &Type::TDouble) => Some(Conv::IntToDouble(Width::Quad)),
+            (_, &Type::TDouble) => Some(Conv::IntToDouble(Width::Long)),
+            _ => None,
+        };
+        match conv {
+            Some(conv) => ctx.add_instr(Instr::Conv(conv, res, src)),
+            None => ctx.add_instr(Instr::Mov(res, Val::Reg(src))),
+        }
+    }
+    if *to == Type::TChar {
+        ctx.add_instr(Instr::Bin(BinOp::And, Width::Long, res, res, Val::Imm(255)));
+    }
+    Val::Reg(res)
+}
+
+impl GenerateCode<(Val, Type)> for Lit {
+    fn generate_code(&self, ctx: &mut Context) -> (Val, Type) {
+        match *self {
+            Lit::LInt(x) => (Val::Imm(x as i64), Type::TInt),
+            Lit::LLong(x) => (Val::Imm(x), Type::TLong),
+            Lit::LDouble(x) => {
+                let r = ctx.new_reg(&Type::TDouble);
+                ctx.add_instr(Instr::DoubleConst(r, x));
+                (Val::Reg(r), Type::TDouble)
+            }
+            Lit::LChar(c) => (Val::Imm(c as i64), Type::TChar),
+            Lit::LTrue => (Val::Imm(1), Type::TBool),
+            Lit::LFalse => (Val::Imm(0), Type::TBool),
+            // constant strings are never freed, their addresses are values
+            Lit::LString(ref s) => {
+                let name = ctx.get_str_const(s);
+                let r = ctx.new_reg(&Type::TString);
+                ctx.add_instr(Instr::Lea(r, name));
+                ctx.retain_string(&Val::Reg(r));
+                (Val::Reg(r), Type::TString)
+            }
+            Lit::LNull(None) => (Val::Imm(0), Type::TNull),
+            Lit::LNull(Some(ref cname)) => (Val::Imm(0), Type::TObject(cname.clone())),
+        }
+    }
+}
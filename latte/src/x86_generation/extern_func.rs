@@ -0,0 +1,30 @@
+use ast::{BuiltinFunc, Expr, Ident, Type};
+
+use x86_generation::context::Context;
+use x86_generation::expr::*;
+use x86_generation::ir::*;
+
+// Extern functions are called with the C calling convention, as in LLVM. Strings are
+// passed as char *, valid only during the call, and returned characters are copied.
+pub fn add_extern_func(f: &BuiltinFunc, ctx: &mut Context) {
+    ctx.add_extern_func(&f.ident, f.args.clone(), f.ret_type.clone());
+}
+
+pub fn generate_extern_call(ident: &Ident, args: &[Expr], ctx: &mut Context) -> (Val, Type) {
+    let mut vals: Vec<Val> = Vec::new();
+    for (arg, t) in args.iter().zip(ctx.get_arg_types(ident)) {
+        let val = generate_init_expr(arg, &t, ctx);
+        match t == Type::TString {
+            true => vals.push(ctx.call_runtime("_str_chars", vec![val], &Type::TLong)),
+            false => vals.push(val),
+        }
+    }
+    let symbol = ctx.get_func_symbol(ident);
+    match ctx.get_ret_type(ident) {
+        Type::TString => {
+            let chars = ctx.call_runtime(&symbol, vals, &Type::TLong);
+            (ctx.call_runtime("_copy_str", vec![chars], &Type::TString), Type::TString)
+        }
+        ret_type => (ctx.call_runtime(&symbol, vals, &ret_type), ret_type),
+    }
+}
@@ -0,0 +1,74 @@
+use ast::{Func, Ident, Type};
+
+use code_generation::has_self_tail_call;
+use mangling::*;
+use static_analysis::has_return::*;
+
+use x86_generation::context::*;
+use x86_generation::generate::*;
+use x86_generation::ir::*;
+
+impl GenerateCode<()> for Func {
+    fn generate_code(&self, ctx: &mut Context) {
+        ctx.begin_function();
+        let mut name = ctx.get_func_symbol(&self.ident);
+        ctx.current_func = self.ident.0.clone();
+        if let Some(id) = ctx.static_class {
+            let class = ctx.get_class_data(id).ident.clone();
+            ctx.current_func = format!("{}.{}", class, self.ident);
+            name = match ctx.class {
+                Some(_) => method_symbol(&class, self).to_string(),
+                None => static_method_symbol(&class, self).to_string(),
+            };
+        }
+
+        // variable, its register and type
+        let mut params: Vec<(Ident, VReg, Type)> = Vec::new();
+        if let Some(id) = ctx.class {
+            let self_t = Type::TObject(ctx.get_class_data(id).ident.clone());
+            let r = ctx.new_reg(&self_t);
+            params.push((Ident("self".to_string()), r, self_t));
+        }
+        for arg in &self.args {
+            let r = ctx.new_reg(&arg.t);
+            params.push((arg.ident.clone(), r, arg.t.clone()));
+        }
+        let regs: Vec<VReg> = params.iter().map(|&(_, r, _)| r).collect();
+        ctx.add_instr(Instr::Params(regs.clone()));
+
+        let is_main = ctx.static_class.is_none() && self.ident.0 == "main";
+        if is_main && ctx.has_globals() {
+            ctx.call_runtime("init_globals", vec![], &Type::TVoid);
+        }
+        let body_label = match has_self_tail_call(self) {
+            true => Some(ctx.next_label()),
+            false => None,
+        };
+        ctx.tail_target = Some(TailTarget {
+            symbol: name.clone(),
+            params: regs,
+            body_label,
+        });
+        ctx.in_new_scope(|ctx| {
+            // args are set in the function scope, so that string args
+            // don't leak into the functions generated later
+            for &(ref ident, r, ref t) in &params {
+                ctx.set_var(ident.clone(), r, t.clone());
+                if *t == Type::TString {
+                    ctx.retain_string(&Val::Reg(r));
+                }
+            }
+            if let Some(label) = body_label {
+                ctx.add_instr(Instr::Label(label));
+            }
+            ctx.ret_type = self.ret_type.clone();
+            self.body.generate_code(ctx);
+            if !self.body.has_return() {
+                ctx.release_all_strings();
+                ctx.add_instr(Instr::Ret(None));
+            }
+        });
+        ctx.tail_target = None;
+        ctx.end_function(&name);
+    }
+}
@@ -0,0 +1,218 @@
+// Lowered representation of a function: instructions close to x86-64 over an
+// unlimited number of virtual registers, which are assigned to machine registers
+// or stack slots by the register allocator.
+
+pub type VReg = usize;
+pub type Label = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RegClass {
+    Int, // integers, booleans, characters and pointers
+    Float, // doubles
+}
+
+// Width of integer operations and memory accesses. Values narrower than 64 bits
+// are kept in the lower part of registers, characters and booleans zero-extended
+// to 32 bits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Width {
+    Byte,
+    Long,
+    Quad,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Val {
+    Reg(VReg),
+    Imm(i64),
+}
+
+#[derive(Debug, Clone)]
+pub enum Mem {
+    Base(VReg, i32), // address in the register with an offset
+    Index(VReg, VReg, u8), // base + 64-bit index * scale
+    Sym(String),
+}
+
+#[derive(Debug, Clone)]
+pub enum Callee {
+    Direct(String),
+    Indirect(VReg),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Sar,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FBinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+// signed for integers, ordered for doubles except Ne
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cond {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Conv {
+    SignExtend, // 32 to 64 bits
+    ZeroExtend, // 32 to 64 bits
+    ZeroExtendByte, // 8 to 32 bits
+    IntToDouble(Width),
+    BitsToDouble,
+    DoubleToBits,
+}
+
+#[derive(Debug, Clone)]
+pub enum Instr {
+    Params(Vec<VReg>), // the args of the function, at its beginning
+    Label(Label),
+    Jump(Label),
+    Branch(VReg, bool, Label), // jumps if the value is non-zero, or zero if false
+    Mov(VReg, Val),
+    DoubleConst(VReg, f64),
+    Lea(VReg, String),
+    Load(VReg, Mem, Width),
+    Store(Val, Mem, Width),
+    Bin(BinOp, Width, VReg, VReg, Val),
+    Div(Width, bool, VReg, VReg, VReg), // the remainder if true
+    Neg(Width, VReg, VReg),
+    Not(Width, VReg, VReg),
+    FBin(FBinOp, VReg, VReg, VReg),
+    FNeg(VReg, VReg),
+    Cmp(Cond, Width, VReg, VReg, Val),
+    FCmp(Cond, VReg, VReg, VReg),
+    Conv(Conv, VReg, VReg),
+    Call(Callee, Vec<Val>, Option<VReg>),
+    TailCall(Callee, Vec<Val>), // args are passed in registers only
+    Ret(Option<Val>),
+}
+
+impl Instr {
+    // registers read by the instruction
+    pub fn uses(&self) -> Vec<VReg> {
+        let mut regs = Vec::new();
+        match *self {
+            Instr::Params(..) |
+            Instr::Label(..) |
+            Instr::Jump(..) |
+            Instr::DoubleConst(..) |
+            Instr::Lea(..) => {}
+            Instr::Branch(r, _, _) => regs.push(r),
+            Instr::Mov(_, ref v) |
+            Instr::Ret(Some(ref v)) => add_val(v, &mut regs),
+            Instr::Ret(None) => {}
+            Instr::Load(_, ref mem, _) => add_mem(mem, &mut regs),
+            Instr::Store(ref v, ref mem, _) => {
+                add_val(v, &mut regs);
+                add_mem(mem, &mut regs);
+            }
+            Instr::Bin(_, _, _, lhs, ref rhs) |
+            Instr::Cmp(_, _, _, lhs, ref rhs) => {
+                regs.push(lhs);
+                add_val(rhs, &mut regs);
+            }
+            Instr::Div(_, _, _, lhs, rhs) |
+            Instr::FBin(_, _, lhs, rhs) |
+            Instr::FCmp(_, _, lhs, rhs) => {
+                regs.push(lhs);
+                regs.push(rhs);
+            }
+            Instr::Neg(_, _, src) |
+            Instr::Not(_, _, src) |
+            Instr::FNeg(_, src) |
+            Instr::Conv(_, _, src) => regs.push(src),
+            Instr::Call(ref callee, ref args, _) |
+            Instr::TailCall(ref callee, ref args) => {
+                if let Callee::Indirect(r) = *callee {
+                    regs.push(r);
+                }
+                for arg in args {
+                    add_val(arg, &mut regs);
+                }
+            }
+        }
+        regs
+    }
+
+    // registers written by the instruction
+    pub fn defs(&self) -> Vec<VReg> {
+        match *self {
+            Instr::Params(ref regs) => regs.clone(),
+            Instr::Mov(dst, _) |
+            Instr::DoubleConst(dst, _) |
+            Instr::Lea(dst, _) |
+            Instr::Load(dst, _, _) |
+            Instr::Bin(_, _, dst, _, _) |
+            Instr::Div(_, _, dst, _, _) |
+            Instr::Neg(_, dst, _) |
+            Instr::Not(_, dst, _) |
+            Instr::FBin(_, dst, _, _) |
+            Instr::FNeg(dst, _) |
+            Instr::Cmp(_, _, dst, _, _) |
+            Instr::FCmp(_, dst, _, _) |
+            Instr::Conv(_, dst, _) |
+            Instr::Call(_, _, Some(dst)) => vec![dst],
+            _ => Vec::new(),
+        }
+    }
+
+    // labels the instruction can jump to, besides the next instruction
+    pub fn targets(&self) -> Vec<Label> {
+        match *self {
+            Instr::Jump(label) |
+            Instr::Branch(_, _, label) => vec![label],
+            _ => Vec::new(),
+        }
+    }
+
+    // whether the next instruction is never executed after this one
+    pub fn ends_block(&self) -> bool {
+        matches!(*self, Instr::Jump(..) |
+            Instr::TailCall(..) |
+            Instr::Ret(..))
+    }
+}
+
+fn add_val(v: &Val, regs: &mut Vec<VReg>) {
+    if let Val::Reg(r) = *v {
+        regs.push(r);
+    }
+}
+
+fn add_mem(mem: &Mem, regs: &mut Vec<VReg>) {
+    match *mem {
+        Mem::Base(base, _) => regs.push(base),
+        Mem::Index(base, idx, _) => {
+            regs.push(base);
+            regs.push(idx);
+        }
+        Mem::Sym(..) => {}
+    }
+}
+
+// Code of a function, with the class of each virtual register.
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: String,
+    pub instrs: Vec<Instr>,
+    pub classes: Vec<RegClass>,
+}
@@ -0,0 +1,188 @@
+use std::cmp;
+use std::collections::HashMap;
+
+use x86_generation::ir::*;
+
+// Positions of instructions where a register is live, from its first definition
+// to its last use, holes are ignored. The interval crosses a call if the register
+// is live before and after it.
+#[derive(Debug, Clone)]
+pub struct Interval {
+    pub reg: VReg,
+    pub start: usize,
+    pub end: usize,
+    pub crosses_call: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct RegSet(Vec<u64>);
+
+impl RegSet {
+    fn new(size: usize) -> RegSet {
+        RegSet(vec![0; size / 64 + 1])
+    }
+
+    fn insert(&mut self, r: VReg) {
+        self.0[r / 64] |= 1 << (r % 64);
+    }
+
+    fn remove(&mut self, r: VReg) {
+        self.0[r / 64] &= !(1 << (r % 64));
+    }
+
+    fn union_with(&mut self, other: &RegSet) {
+        for (word, other_word) in self.0.iter_mut().zip(&other.0) {
+            *word |= *other_word;
+        }
+    }
+
+    fn regs(&self) -> Vec<VReg> {
+        let mut regs = Vec::new();
+        for (idx, &word) in self.0.iter().enumerate() {
+            for bit in 0..64 {
+                if word & (1 << bit) != 0 {
+                    regs.push(idx * 64 + bit);
+                }
+            }
+        }
+        regs
+    }
+}
+
+// Basic block as the range of positions of its instructions.
+struct Block {
+    start: usize,
+    end: usize,
+    succs: Vec<usize>,
+}
+
+pub fn live_intervals(f: &Function) -> Vec<Interval> {
+    let blocks = split_blocks(&f.instrs);
+    let regs_cnt = f.classes.len();
+
+    // registers used before being defined in the block, and defined in it
+    let mut uses: Vec<RegSet> = Vec::new();
+    let mut defs: Vec<RegSet> = Vec::new();
+    for block in &blocks {
+        let mut block_uses = RegSet::new(regs_cnt);
+        let mut block_defs = RegSet::new(regs_cnt);
+        for instr in f.instrs[block.start..block.end].iter().rev() {
+            for r in instr.defs() {
+                block_defs.insert(r);
+                block_uses.remove(r);
+            }
+            for r in instr.uses() {
+                block_uses.insert(r);
+            }
+        }
+        uses.push(block_uses);
+        defs.push(block_defs);
+    }
+
+    let mut live_in: Vec<RegSet> = vec![RegSet::new(regs_cnt); blocks.len()];
+    let mut live_out: Vec<RegSet> = vec![RegSet::new(regs_cnt); blocks.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for idx in (0..blocks.len()).rev() {
+            let mut out = RegSet::new(regs_cnt);
+            for &succ in &blocks[idx].succs {
+                out.union_with(&live_in[succ]);
+            }
+            let mut new_in = out.clone();
+            for r in defs[idx].regs() {
+                new_in.remove(r);
+            }
+            new_in.union_with(&uses[idx]);
+            if new_in != live_in[idx] || out != live_out[idx] {
+                changed = true;
+                live_in[idx] = new_in;
+                live_out[idx] = out;
+            }
+        }
+    }
+
+    let mut ranges: Vec<Option<(usize, usize)>> = vec![None; regs_cnt];
+    for (idx, block) in blocks.iter().enumerate() {
+        for r in live_out[idx].regs() {
+            extend(&mut ranges, r, block.end - 1);
+        }
+        for pos in (block.start..block.end).rev() {
+            let instr = &f.instrs[pos];
+            for r in instr.defs().into_iter().chain(instr.uses()) {
+                extend(&mut ranges, r, pos);
+            }
+        }
+        for r in live_in[idx].regs() {
+            extend(&mut ranges, r, block.start);
+        }
+    }
+
+    let calls: Vec<usize> = f.instrs
+        .iter()
+        .enumerate()
+        .filter(|&(_, instr)| matches!(*instr, Instr::Call(..)))
+        .map(|(pos, _)| pos)
+        .collect();
+    let mut intervals = Vec::new();
+    for (r, range) in ranges.into_iter().enumerate() {
+        if let Some((start, end)) = range {
+            intervals.push(Interval {
+                reg: r,
+                start,
+                end,
+                crosses_call: calls.iter().any(|&pos| start < pos && pos < end),
+            });
+        }
+    }
+    intervals
+}
+
+fn extend(ranges: &mut [Option<(usize, usize)>], r: VReg, pos: usize) {
+    ranges[r] = match ranges[r] {
+        None => Some((pos, pos)),
+        Some((start, end)) => Some((cmp::min(start, pos), cmp::max(end, pos))),
+    };
+}
+
+// Blocks begin at labels and after jumps, the function begins with the first one.
+fn split_blocks(instrs: &[Instr]) -> Vec<Block> {
+    let mut starts: Vec<usize> = vec![0];
+    for (pos, instr) in instrs.iter().enumerate() {
+        if let Instr::Label(..) = *instr {
+            starts.push(pos);
+        }
+        if instr.ends_block() || !instr.targets().is_empty() {
+            starts.push(pos + 1);
+        }
+    }
+    starts.retain(|&pos| pos < instrs.len());
+    starts.sort();
+    starts.dedup();
+
+    let mut label_blocks: HashMap<Label, usize> = HashMap::new();
+    for (idx, &start) in starts.iter().enumerate() {
+        if let Instr::Label(label) = instrs[start] {
+            label_blocks.insert(label, idx);
+        }
+    }
+
+    let mut blocks = Vec::new();
+    for (idx, &start) in starts.iter().enumerate() {
+        let end = match starts.get(idx + 1) {
+            Some(&next) => next,
+            None => instrs.len(),
+        };
+        let last = &instrs[end - 1];
+        let mut succs: Vec<usize> = last.targets().iter().map(|l| label_blocks[l]).collect();
+        if !last.ends_block() && end < instrs.len() {
+            succs.push(idx + 1);
+        }
+        blocks.push(Block {
+            start,
+            end,
+            succs,
+        });
+    }
+    blocks
+}
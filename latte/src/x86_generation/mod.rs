@@ -0,0 +1,296 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use std::fs::File;
+
+use ast::*;
+use builtins::*;
+use mangling::*;
+use options::Options;
+use static_analysis::collect_string_lit::*;
+
+mod class;
+mod class_data;
+mod collection;
+mod context;
+mod emit;
+mod enum_def;
+mod expr;
+mod extern_func;
+mod field_get;
+mod func;
+mod generate;
+mod global;
+mod ir;
+mod liveness;
+mod reg_alloc;
+mod stmt;
+mod tail_call;
+mod x86_type;
+
+use self::class_data::*;
+use self::context::*;
+use self::extern_func::*;
+use self::generate::*;
+use self::ir::*;
+use self::x86_type::*;
+
+// Generates x86-64 assembly in the AT&T syntax for the System V ABI, which is linked
+// with the C runtime (lib/runtime.c). Functions are lowered to instructions over
+// virtual registers, which are allocated with linear scan. Objects have the same layout
+// and strings the same reference counting as in C. Returns the report of devirtualized
+// calls.
+pub fn gen_x86(p: &Program, opts: &Options, out_file: &mut File) -> Result<Vec<String>, io::Error> {
+    let mut ctx = create_context(p, opts);
+
+    ctx.add_line(".text".to_string());
+    for def in &p.0 {
+        match *def {
+            Def::DClass(ref c) => c.generate_code(&mut ctx),
+            Def::DFunc(ref f) => f.generate_code(&mut ctx),
+            Def::DEnum(ref e) => e.generate_code(&mut ctx),
+            Def::DGlobal(..) |
+            Def::DExtern(..) => {}
+        }
+    }
+    if ctx.has_globals() {
+        global::generate_init(p, &mut ctx);
+    }
+    generate_main(p, &mut ctx);
+    ctx.add_line(".section .note.GNU-stack,\"\",@progbits".to_string());
+
+    for line in ctx.get_out() {
+        writeln!(out_file, "{}", line)?;
+    }
+    Ok(ctx.take_devirtualized())
+}
+
+// Registers the program and emits its data: string literals, vtables and globals.
+fn create_context(p: &Program, opts: &Options) -> Context {
+    let mut ctx = Context::new(opts);
+    for f in get_builtin_functions() {
+        ctx.add_builtin_func(&f.ident, f.args.clone(), f.ret_type.clone());
+    }
+    for def in &p.0 {
+        if let Def::DExtern(ref f) = *def {
+            add_extern_func(f, &mut ctx);
+        }
+    }
+
+    ctx.add_comment("string literals".to_string());
+    add_string_lits(p, &mut ctx);
+    ctx.add_empty_line();
+
+    for def in &p.0 {
+        if let Def::DEnum(ref e) = *def {
+            ctx.add_enum(&e.name, &e.variants);
+        }
+    }
+
+    add_classes(p, &mut ctx);
+    add_funcs(p, &mut ctx);
+    add_vtables(p, &mut ctx);
+
+    ctx.add_comment("globals".to_string());
+    ctx.add_line(".bss".to_string());
+    ctx.add_line(".p2align 3".to_string());
+    add_globals(p, &mut ctx);
+    ctx.add_empty_line();
+    ctx
+}
+
+// Literals are constant strings in the static data, they are never freed.
+// Their layout is the one of string_t: the reference count, the characters and
+// whether they are constant.
+fn add_string_lits(p: &Program, ctx: &mut Context) {
+    let mut lits: Vec<String> = collect_string_lit(p).into_iter().collect();
+    lits.sort();
+    ctx.add_line(".section .rodata".to_string());
+    for (id, lit) in lits.iter().enumerate() {
+        ctx.add_label(&format!("str_{}_chars", id));
+        ctx.add_line(format!(".string \"{}\"", escape_str(lit)));
+    }
+    ctx.add_line(".data".to_string());
+    ctx.add_line(".p2align 3".to_string());
+    for (id, lit) in lits.into_iter().enumerate() {
+        let name = format!("str_{}", id);
+        ctx.add_label(&name);
+        ctx.add_line(".long 1".to_string());
+        ctx.add_line(".zero 4".to_string());
+        ctx.add_line(format!(".quad {}_chars", name));
+        ctx.add_line(".byte 1".to_string());
+        ctx.add_line(".zero 7".to_string());
+        ctx.set_str_const(lit, name);
+    }
+}
+
+fn add_classes(p: &Program, ctx: &mut Context) {
+    let mut classes: Vec<&Class> = Vec::new();
+    for def in &p.0 {
+        if let Def::DClass(ref c) = *def {
+            classes.push(c);
+        }
+    }
+
+    for (id, class) in classes.iter().enumerate() {
+        ctx.add_class_id(&class.name, id);
+    }
+
+    // superclasses are laid out before their subclasses, which contain them
+    let mut added: HashSet<ClassId> = HashSet::new();
+    for id in 0..classes.len() {
+        add_class_data(id, &classes, &mut added, ctx);
+    }
+
+    let mut class_map: HashMap<Ident, &Class> = HashMap::new();
+    for class in &classes {
+        class_map.insert(class.name.clone(), class);
+    }
+    for class in &classes {
+        let vtable = get_vtable(class, &class_map, ctx);
+        let id = ctx.get_class_id(&class.name);
+        ctx.set_vtable(id, vtable);
+    }
+}
+
+fn add_class_data(id: ClassId,
+                  classes: &Vec<&Class>,
+                  added: &mut HashSet<ClassId>,
+                  ctx: &mut Context) {
+    if added.contains(&id) {
+        return;
+    }
+    let class = classes[id];
+    let mut class_data = ClassData::new(&class.name);
+    if let Some(ref super_name) = class.superclass {
+        let super_id = ctx.get_class_id(super_name);
+        add_class_data(super_id, classes, added, ctx);
+        class_data.set_super(super_id, ctx.get_class_data(super_id).get_size());
+    }
+    added.insert(id);
+
+    for v in &class.vars {
+        class_data.add_field(&v.ident, &v.t);
+    }
+    ctx.add_class(id, class_data);
+}
+
+fn get_vtable(c: &Class, classes: &HashMap<Ident, &Class>, ctx: &Context) -> VTable {
+    let mut vtable = VTable::new();
+    if let Some(ref super_ident) = c.superclass {
+        let superclass = classes.get(super_ident).unwrap();
+        vtable = get_vtable(superclass, classes, ctx);
+    }
+
+    let id = ctx.get_class_id(&c.name);
+    for f in &c.methods {
+        let entry = VTableEntry {
+            real_ident: method_symbol(&c.name, f),
+            class_id: id,
+            ret_type: f.ret_type.clone(),
+            arg_types: f.args.iter().map(|v| v.t.clone()).collect(),
+        };
+        vtable.set_func(&f.ident, entry);
+    }
+
+    vtable
+}
+
+// vtables hold addresses of functions, which are relocated at load time
+fn add_vtables(p: &Program, ctx: &mut Context) {
+    ctx.add_comment("vtables".to_string());
+    ctx.add_line(".section .data.rel.ro,\"aw\"".to_string());
+    ctx.add_line(".p2align 3".to_string());
+    for def in &p.0 {
+        if let Def::DClass(ref c) = *def {
+            let id = ctx.get_class_id(&c.name);
+            let vtable = ctx.get_class_data(id).vtable.clone();
+            if vtable.fs.is_empty() {
+                continue;
+            }
+            ctx.add_label(&format!("vtable_{}", id));
+            for f in &vtable.fs {
+                ctx.add_line(format!(".quad {}", f.real_ident));
+            }
+        }
+    }
+    ctx.add_empty_line();
+}
+
+// Registers functions and methods, together with static methods of classes.
+fn add_funcs(p: &Program, ctx: &mut Context) {
+    for def in &p.0 {
+        match *def {
+            Def::DFunc(ref f) => {
+                let arg_types = f.args.iter().map(|arg| arg.t.clone()).collect();
+                ctx.add_func(&f.ident, arg_types, f.ret_type.clone());
+            }
+            Def::DClass(ref c) => {
+                let id = ctx.get_class_id(&c.name);
+                for f in &c.methods {
+                    let obj_t = Type::TObject(c.name.clone());
+                    let mut arg_types: Vec<Type> = f.args.iter().map(|arg| arg.t.clone()).collect();
+                    arg_types.insert(0, obj_t);
+                    let ident = Ident(method_symbol(&c.name, f));
+                    ctx.add_func(&ident, arg_types, f.ret_type.clone());
+                }
+                for f in &c.static_methods {
+                    let arg_types = f.args.iter().map(|arg| arg.t.clone()).collect();
+                    let ident = Ident(static_method_symbol(&c.name, f));
+                    ctx.add_func(&ident, arg_types, f.ret_type.clone());
+                    ctx.add_static_method(id, &f.ident, &ident);
+                }
+            }
+            Def::DGlobal(..) |
+            Def::DEnum(..) |
+            Def::DExtern(..) => {}
+        }
+    }
+}
+
+fn add_globals(p: &Program, ctx: &mut Context) {
+    for def in &p.0 {
+        match *def {
+            Def::DGlobal(ref g) => {
+                for decl in &g.decls {
+                    ctx.add_global(decl.get_ident(), &g.t);
+                }
+            }
+            Def::DClass(ref c) => {
+                let id = ctx.get_class_id(&c.name);
+                for g in &c.static_vars {
+                    for decl in &g.decls {
+                        ctx.add_static_field(id, decl.get_ident(), &g.t);
+                    }
+                }
+            }
+            Def::DFunc(..) |
+            Def::DEnum(..) |
+            Def::DExtern(..) => {}
+        }
+    }
+}
+
+// The entry point calls main of the program, which may take its arguments
+// as an array of strings.
+fn generate_main(p: &Program, ctx: &mut Context) {
+    let takes_args = p.0.iter().any(|def| match *def {
+        Def::DFunc(ref f) => f.ident.0 == "main" && !f.args.is_empty(),
+        _ => false,
+    });
+    let main_name = ctx.get_func_symbol(&Ident("main".to_string()));
+    ctx.add_line(".globl main".to_string());
+    ctx.begin_function();
+    let (argc, argv) = (ctx.new_reg(&Type::TInt), ctx.new_reg(&Type::TLong));
+    ctx.add_instr(Instr::Params(vec![argc, argv]));
+    let args = match takes_args {
+        true => {
+            let arr_t = Type::TArray(Box::new(Type::TString));
+            let args = vec![Val::Reg(argc), Val::Reg(argv)];
+            vec![ctx.call_runtime("_new_args", args, &arr_t)]
+        }
+        false => vec![],
+    };
+    let res = ctx.call_runtime(&main_name, args, &Type::TInt);
+    ctx.add_instr(Instr::Ret(Some(res)));
+    ctx.end_function("main");
+}
@@ -0,0 +1,101 @@
+use x86_generation::ir::*;
+use x86_generation::liveness::*;
+
+// Registers preserved by calls, they hold values which live across calls.
+pub const CALLEE_SAVED: [&str; 5] = ["%rbx", "%r12", "%r13", "%r14", "%r15"];
+
+// Other registers available to the allocator. Arguments are passed through the
+// stack to their registers, so that they can be moved in any order. %rax, %rcx,
+// %rdx, %r10 and %r11 are scratch registers of instructions.
+const CALLER_SAVED: [&str; 4] = ["%rsi", "%rdi", "%r8", "%r9"];
+
+// All of them are clobbered by calls, %xmm14 and %xmm15 are scratch registers.
+const FLOAT_REGS: [&str; 14] = ["%xmm0", "%xmm1", "%xmm2", "%xmm3", "%xmm4", "%xmm5", "%xmm6",
+                                "%xmm7", "%xmm8", "%xmm9", "%xmm10", "%xmm11", "%xmm12",
+                                "%xmm13"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Loc {
+    Reg(&'static str),
+    Stack(usize), // index of the spill slot
+}
+
+#[derive(Debug)]
+pub struct Allocation {
+    pub locs: Vec<Option<Loc>>, // None for registers which are never used
+    pub spill_slots: usize,
+    pub callee_saved: Vec<&'static str>, // used by the function, in order of CALLEE_SAVED
+}
+
+// Linear scan over the live intervals, in order of their beginnings. Intervals
+// which overlap, even at a single instruction, get different registers, so that
+// the result of an instruction never overwrites its operands. If no register is
+// free, the interval which ends last is spilled.
+pub fn allocate(f: &Function) -> Allocation {
+    let mut intervals = live_intervals(f);
+    intervals.sort_by_key(|i| (i.start, i.reg));
+
+    let mut locs: Vec<Option<Loc>> = vec![None; f.classes.len()];
+    let mut spill_slots = 0;
+    let mut free: Vec<&'static str> = Vec::new();
+    free.extend(CALLER_SAVED.iter());
+    free.extend(CALLEE_SAVED.iter());
+    free.extend(FLOAT_REGS.iter());
+    let mut active: Vec<(Interval, &'static str)> = Vec::new();
+
+    for cur in intervals {
+        let mut expired: Vec<&'static str> = Vec::new();
+        active.retain(|&(ref i, reg)| {
+            let is_live = i.end >= cur.start;
+            if !is_live {
+                expired.push(reg);
+            }
+            is_live
+        });
+        free.extend(expired);
+
+        let allowed: Vec<&'static str> = match (f.classes[cur.reg], cur.crosses_call) {
+            (RegClass::Int, true) => CALLEE_SAVED.to_vec(),
+            (RegClass::Int, false) => {
+                CALLER_SAVED.iter().chain(CALLEE_SAVED.iter()).cloned().collect()
+            }
+            (RegClass::Float, true) => Vec::new(),
+            (RegClass::Float, false) => FLOAT_REGS.to_vec(),
+        };
+        if let Some(reg) = allowed.iter().cloned().find(|reg| free.contains(reg)) {
+            free.retain(|&r| r != reg);
+            locs[cur.reg] = Some(Loc::Reg(reg));
+            active.push((cur, reg));
+            continue;
+        }
+
+        let victim = active.iter()
+            .enumerate()
+            .filter(|&(_, &(_, reg))| allowed.contains(&reg))
+            .max_by_key(|&(_, (i, _))| i.end)
+            .map(|(idx, (i, _))| (idx, i.end));
+        match victim {
+            Some((idx, end)) if end > cur.end => {
+                let (spilled, reg) = active.remove(idx);
+                locs[spilled.reg] = Some(Loc::Stack(spill_slots));
+                spill_slots += 1;
+                locs[cur.reg] = Some(Loc::Reg(reg));
+                active.push((cur, reg));
+            }
+            _ => {
+                locs[cur.reg] = Some(Loc::Stack(spill_slots));
+                spill_slots += 1;
+            }
+        }
+    }
+
+    let callee_saved = CALLEE_SAVED.iter()
+        .cloned()
+        .filter(|&reg| locs.contains(&Some(Loc::Reg(reg))))
+        .collect();
+    Allocation {
+        locs,
+        spill_slots,
+        callee_saved,
+    }
+}
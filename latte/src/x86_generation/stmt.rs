@@ -0,0 +1,259 @@
+use ast::*;
+
+use code_generation::default_value;
+use static_analysis::has_return::*;
+
+use x86_generation::collection::*;
+use x86_generation::context::*;
+use x86_generation::expr::*;
+use x86_generation::field_get::*;
+use x86_generation::generate::*;
+use x86_generation::ir::*;
+use x86_generation::tail_call::*;
+use x86_generation::x86_type::*;
+
+impl GenerateCode<()> for Vec<Stmt> {
+    fn generate_code(&self, ctx: &mut Context) {
+        for stmt in self {
+            stmt.generate_code(ctx);
+            if stmt.has_return() {
+                break;
+            }
+        }
+    }
+}
+
+impl GenerateCode<()> for Stmt {
+    fn generate_code(&self, ctx: &mut Context) {
+        match self.kind {
+            StmtKind::SEmpty => {}
+            StmtKind::SBlock(ref stmts) => {
+                ctx.in_new_scope(|ctx| {
+                    stmts.generate_code(ctx);
+                    if !stmts.has_return() {
+                        ctx.release_local_strings();
+                    }
+                })
+            }
+            StmtKind::SDecl(_, ref decls) => {
+                decls.generate_code(ctx);
+            }
+            StmtKind::SAssign(ref ident, ref e) => {
+                let (lvalue, t) = ident.generate_code(ctx);
+                let val = generate_init_expr(e, &t, ctx);
+                if t == Type::TString {
+                    let old_val = Val::Reg(ctx.add_load(&lvalue, &t));
+                    ctx.retain_string(&val);
+                    ctx.release_string(&old_val);
+                }
+                ctx.add_store(&lvalue, &val);
+            }
+            StmtKind::SInc(ref ident) => generate_step(ident, Operator::OpAdd, ctx),
+            StmtKind::SDec(ref ident) => generate_step(ident, Operator::OpSub, ctx),
+            StmtKind::SReturnE(Expr::ECall(ref ident, ref args)) => {
+                generate_tail_call(ident, args, ctx);
+            }
+            StmtKind::SReturnE(ref e) => {
+                let t = ctx.ret_type.clone();
+                let val = generate_init_expr(e, &t, ctx);
+                if t == Type::TString {
+                    ctx.retain_string(&val);
+                }
+                ctx.release_all_strings();
+                ctx.add_instr(Instr::Ret(Some(val)));
+            }
+            StmtKind::SReturn => {
+                ctx.release_all_strings();
+                ctx.add_instr(Instr::Ret(None));
+            }
+            StmtKind::SExpr(ref e) => {
+                e.generate_code(ctx);
+            }
+            StmtKind::SIf(ref cond, ref s) => {
+                let end = ctx.next_label();
+                generate_branch(cond, false, end, ctx);
+                generate_body(s, ctx);
+                ctx.add_instr(Instr::Label(end));
+            }
+            StmtKind::SIfElse(ref cond, ref if_true, ref if_false) => {
+                let (else_label, end) = (ctx.next_label(), ctx.next_label());
+                generate_branch(cond, false, else_label, ctx);
+                generate_body(if_true, ctx);
+                if !if_true.has_return() {
+                    ctx.add_instr(Instr::Jump(end));
+                }
+                ctx.add_instr(Instr::Label(else_label));
+                generate_body(if_false, ctx);
+                ctx.add_instr(Instr::Label(end));
+            }
+            StmtKind::SWhile(ref cond, ref s) => {
+                // the condition is evaluated at the beginning of each iteration
+                let (begin, end) = (ctx.next_label(), ctx.next_label());
+                ctx.add_instr(Instr::Label(begin));
+                ctx.in_new_scope(|ctx| {
+                    let (cond_val, _) = cond.generate_code(ctx);
+                    let cond_reg = ctx.reg_of(&cond_val, &Type::TBool);
+                    ctx.release_local_strings();
+                    ctx.add_instr(Instr::Branch(cond_reg, false, end));
+                });
+                generate_body(s, ctx);
+                ctx.add_instr(Instr::Jump(begin));
+                ctx.add_instr(Instr::Label(end));
+            }
+            StmtKind::SSwitch(ref e, ref cases, ref default) => {
+                let (val, t) = e.generate_code(ctx);
+                let val = ctx.reg_of(&val, &t);
+                // labels are evaluated first, so that their strings can be released
+                // on every path
+                let mut label_vals: Vec<(Val, usize)> = Vec::new();
+                for (idx, case) in cases.iter().enumerate() {
+                    for label in &case.labels {
+                        label_vals.push((label.generate_code(ctx).0, idx));
+                    }
+                }
+
+                // the first matching label selects its case
+                let case_labels: Vec<Label> = cases.iter().map(|_| ctx.next_label()).collect();
+                let (default_label, end) = (ctx.next_label(), ctx.next_label());
+                for (label_val, idx) in label_vals {
+                    let eq = match t == Type::TString {
+                        true => {
+                            let eq = ctx.call_runtime("_str_eq",
+                                                      vec![Val::Reg(val), label_val],
+                                                      &Type::TBool);
+                            ctx.reg_of(&eq, &Type::TBool)
+                        }
+                        false => {
+                            let eq = ctx.new_reg(&Type::TBool);
+                            ctx.add_instr(Instr::Cmp(Cond::Eq, op_width(&t), eq, val, label_val));
+                            eq
+                        }
+                    };
+                    ctx.add_instr(Instr::Branch(eq, true, case_labels[idx]));
+                }
+                ctx.add_instr(Instr::Jump(default_label));
+
+                for (case, &label) in cases.iter().zip(&case_labels) {
+                    ctx.add_instr(Instr::Label(label));
+                    generate_case(&case.body, end, ctx);
+                }
+                ctx.add_instr(Instr::Label(default_label));
+                let empty = Vec::new();
+                let default = default.as_ref().unwrap_or(&empty);
+                generate_case(default, end, ctx);
+                ctx.add_instr(Instr::Label(end));
+            }
+            StmtKind::SFor(ref t, ref ident, ref arr, ref stmt) => {
+                let (arr, arr_t) = match *arr {
+                    Expr::EArrayLit(..) => {
+                        let arr_t = Type::TArray(Box::new(t.clone()));
+                        (generate_init_expr(arr, &arr_t, ctx), arr_t)
+                    }
+                    _ => arr.generate_code(ctx),
+                };
+                // Maps are iterated over a snapshot of their keys.
+                let (arr, arr_t) = match arr_t {
+                    Type::TMap(ref key_t, _) => generate_map_keys(&arr, key_t, ctx),
+                    _ => (arr, arr_t),
+                };
+                // the size of a list and the length of an array are at the beginning
+                let arr_reg = ctx.reg_of(&arr, &arr_t);
+                let size = ctx.new_reg(&Type::TInt);
+                ctx.add_instr(Instr::Load(size, Mem::Base(arr_reg, 0), Width::Long));
+
+                ctx.in_new_loop(&Val::Reg(size), |ctx, idx| {
+                    let (elem, elem_t) = match arr_t {
+                        Type::TList(ref elem_t) => {
+                            let slot = list_slot(&arr, &Val::Reg(idx), ctx);
+                            (LValue::Slot(slot, *elem_t.clone()), *elem_t.clone())
+                        }
+                        _ => {
+                            let elem_t = arr_elem_t(&arr_t);
+                            (elem_lvalue(&arr, &Val::Reg(idx), &elem_t, ctx), elem_t)
+                        }
+                    };
+                    let r = ctx.add_load(&elem, &elem_t);
+                    ctx.set_var(ident.clone(), r, elem_t.clone());
+
+                    if elem_t == Type::TString {
+                        ctx.retain_string(&Val::Reg(r));
+                    }
+
+                    stmt.generate_code(ctx);
+                    if !stmt.has_return() {
+                        ctx.release_local_strings();
+                    }
+                });
+            }
+        }
+    }
+}
+
+// jumps to the label if the condition has the given value
+fn generate_branch(cond: &Expr, value: bool, label: Label, ctx: &mut Context) {
+    let (cond_val, _) = cond.generate_code(ctx);
+    let cond_reg = ctx.reg_of(&cond_val, &Type::TBool);
+    ctx.add_instr(Instr::Branch(cond_reg, value, label));
+}
+
+fn generate_body(s: &Stmt, ctx: &mut Context) {
+    ctx.in_new_scope(|ctx| {
+        s.generate_code(ctx);
+        if !s.has_return() {
+            ctx.release_local_strings();
+        }
+    });
+}
+
+// Cases don't fall through, each one jumps to the end of the switch.
+fn generate_case(body: &Vec<Stmt>, end: Label, ctx: &mut Context) {
+    ctx.in_new_scope(|ctx| {
+        body.generate_code(ctx);
+        if !body.has_return() {
+            ctx.release_local_strings();
+            ctx.add_instr(Instr::Jump(end));
+        }
+    });
+}
+
+// ++ and --
+fn generate_step(ident: &FieldGet, op: Operator, ctx: &mut Context) {
+    let (lvalue, t) = ident.generate_code(ctx);
+    let old_val = Val::Reg(ctx.add_load(&lvalue, &t));
+    let one = match t == Type::TDouble {
+        true => {
+            let one = ctx.new_reg(&t);
+            ctx.add_instr(Instr::DoubleConst(one, 1.0));
+            Val::Reg(one)
+        }
+        false => Val::Imm(1),
+    };
+    let val = generate_arith_op(&t, &old_val, op, &one, ctx);
+    ctx.add_store(&lvalue, &val);
+}
+
+impl GenerateCode<()> for Vec<VarDecl> {
+    fn generate_code(&self, ctx: &mut Context) {
+        for var_decl in self {
+            var_decl.generate_code(ctx);
+        }
+    }
+}
+
+impl GenerateCode<()> for VarDecl {
+    fn generate_code(&self, ctx: &mut Context) {
+        let (t, ident, val) = match *self {
+            VarDecl::Init(ref t, ref ident, ref e) => (t, ident, generate_init_expr(e, t, ctx)),
+            VarDecl::NoInit(ref t, ref ident) => {
+                let (val, _) = Expr::ELit(default_value(t)).generate_code(ctx);
+                (t, ident, val)
+            }
+        };
+        if *t == Type::TString {
+            ctx.retain_string(&val);
+        }
+        let r = ctx.new_reg(t);
+        ctx.add_instr(Instr::Mov(r, val));
+        ctx.set_var(ident.clone(), r, t.clone());
+    }
+}
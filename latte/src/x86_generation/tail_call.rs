@@ -0,0 +1,65 @@
+use ast::*;
+
+use x86_generation::context::Context;
+use x86_generation::emit::*;
+use x86_generation::expr::*;
+use x86_generation::ir::*;
+use x86_generation::x86_type::*;
+
+// Generates `return f(args)`, as in LLVM. Self-recursive calls set the params and
+// jump to the body of the function. Other calls jump to the function if the strings
+// of the caller can be released before the call, i.e. no string is passed to it, and
+// all args are passed in registers. The result of such a call is returned without
+// retaining it.
+pub fn generate_tail_call(ident: &FieldGet, args: &[Expr], ctx: &mut Context) {
+    let call = match prepare_call(ident, args, ctx) {
+        PreparedCall::Generated(val, t) => return generate_return(&val, &t, ctx),
+        PreparedCall::Call(call) => call,
+    };
+
+    let target = ctx.tail_target.clone().unwrap();
+    let is_self_call = call.vtable_pos.is_none() && call.func == target.symbol;
+    let classes: Vec<RegClass> = call.args.iter().map(|(_, t)| reg_class(t)).collect();
+    // builtins set only the lowest byte of booleans and chars, as C functions
+    let is_byte = call.ret_type == Type::TBool || call.ret_type == Type::TChar;
+    let is_byte_builtin = is_byte && ctx.is_builtin(&Ident(call.func.clone()));
+    if let (true, Some(body_label)) = (is_self_call, target.body_label) {
+        for (arg, t) in &call.args {
+            if *t == Type::TString {
+                ctx.retain_string(arg);
+            }
+        }
+        // args are copied before the params are set, they can refer to the params
+        let mut vals: Vec<VReg> = Vec::new();
+        for (arg, t) in &call.args {
+            let r = ctx.new_reg(t);
+            ctx.add_instr(Instr::Mov(r, arg.clone()));
+            vals.push(r);
+        }
+        ctx.release_all_strings();
+        for (&param, val) in target.params.iter().zip(vals) {
+            ctx.add_instr(Instr::Mov(param, Val::Reg(val)));
+        }
+        ctx.add_instr(Instr::Jump(body_label));
+    } else if call.args.iter().all(|(_, t)| *t != Type::TString) && fits_in_regs(&classes) &&
+              !is_byte_builtin {
+        let callee = get_callee(&call, ctx);
+        ctx.release_all_strings();
+        let args = call.args.iter().map(|(arg, _)| arg.clone()).collect();
+        ctx.add_instr(Instr::TailCall(callee, args));
+    } else {
+        let callee = get_callee(&call, ctx);
+        let args = call.args.iter().map(|(arg, _)| arg.clone()).collect();
+        let val = ctx.add_call(callee, args, &call.ret_type);
+        generate_return(&val, &call.ret_type, ctx);
+    }
+}
+
+// Returns the result of the call, the function owns it if it's a string.
+fn generate_return(val: &Val, t: &Type, ctx: &mut Context) {
+    ctx.release_all_strings();
+    match *t {
+        Type::TVoid => ctx.add_instr(Instr::Ret(None)),
+        _ => ctx.add_instr(Instr::Ret(Some(val.clone()))),
+    }
+}
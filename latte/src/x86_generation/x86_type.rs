@@ -0,0 +1,82 @@
+use ast::Type;
+
+use x86_generation::ir::*;
+
+pub fn reg_class(t: &Type) -> RegClass {
+    match *t {
+        Type::TDouble => RegClass::Float,
+        Type::TVoid |
+        Type::TFunc(..) => unreachable!(),
+        _ => RegClass::Int,
+    }
+}
+
+// Width of values of the type in memory and of operations on them. Characters and
+// booleans take one byte, as in C.
+pub fn mem_width(t: &Type) -> Width {
+    match *t {
+        Type::TChar |
+        Type::TBool => Width::Byte,
+        Type::TInt |
+        Type::TEnum(..) => Width::Long,
+        _ => Width::Quad,
+    }
+}
+
+pub fn op_width(t: &Type) -> Width {
+    match mem_width(t) {
+        Width::Byte => Width::Long,
+        w => w,
+    }
+}
+
+pub fn size_of(t: &Type) -> u32 {
+    match mem_width(t) {
+        Width::Byte => 1,
+        Width::Long => 4,
+        Width::Quad => 8,
+    }
+}
+
+// the smallest multiple of n which is not less than x
+pub fn align(x: u32, n: u32) -> u32 {
+    match x % n {
+        0 => x,
+        r => x + n - r,
+    }
+}
+
+// Elements of lists and maps are stored in 64-bit slots, as in the LLVM runtime.
+pub fn to_slot(t: &Type) -> Option<Conv> {
+    match *t {
+        Type::TInt |
+        Type::TEnum(..) => Some(Conv::SignExtend),
+        Type::TChar |
+        Type::TBool => Some(Conv::ZeroExtend),
+        Type::TDouble => Some(Conv::DoubleToBits),
+        _ => None,
+    }
+}
+
+pub fn from_slot(t: &Type) -> Option<Conv> {
+    match *t {
+        Type::TDouble => Some(Conv::BitsToDouble),
+        _ => None,
+    }
+}
+
+// characters of a string in the assembler, non-printable ones are escaped
+pub fn escape_str(s: &str) -> String {
+    let mut res = String::new();
+    for c in s.bytes() {
+        match c {
+            b'"' | b'\\' => {
+                res.push('\\');
+                res.push(c as char);
+            }
+            _ if c.is_ascii_graphic() || c == b' ' => res.push(c as char),
+            _ => res.push_str(&format!("\\{:03o}", c)),
+        }
+    }
+    res
+}
@@ -0,0 +1,78 @@
+#!/bin/bash
+# Compiles the programs in tests/debug with -g and checks the lines of the debug
+# information: each call of a function named in the program (one of its functions or a
+# builtin) has to be in the statement starting on the line of its location. Run in the latte directory
+# after make:
+#
+#   tests/debug_lines.sh [file.lat]...
+#
+# The compiler is $LATC, ./latc by default.
+
+latc=$(realpath "${LATC:-./latc}")
+files=("$@")
+if [ ${#files[@]} -eq 0 ]; then
+  files=(tests/debug/*.lat)
+fi
+
+dir=$(mktemp -d)
+trap 'rm -rf "$dir"' EXIT
+
+fails=0
+for f in "${files[@]}"; do
+  name=$(basename "$f" .lat)
+  rm -rf "${dir:?}"/*
+  cp "$f" "$dir"
+  if ! "$latc" -g "$dir/$name.lat" > "$dir/compiler.txt" 2>&1; then
+    echo "FAIL $f: compilation"
+    head -5 "$dir/compiler.txt"
+    fails=$((fails + 1))
+    continue
+  fi
+  # prints "<line> <function>" for calls whose statement doesn't mention the function,
+  # the runtime, the C library and mangled methods aren't named in the program
+  wrong=$(awk '
+    FNR == NR { source[FNR] = $0; program = program $0 "\n"; next }
+    match($0, /^![0-9]+ = !DILocation\(line: [0-9]+/) {
+      split(substr($0, RSTART, RLENGTH), parts, /[ :(]+/)
+      lines[parts[1]] = parts[5]
+      next
+    }
+    match($0, /call [^@]*@[A-Za-z_][A-Za-z_0-9]*\(.*!dbg ![0-9]+$/) {
+      callee = $0
+      sub(/^[^@]*@/, "", callee)
+      sub(/\(.*/, "", callee)
+      if (index(program, callee "(") == 0) {
+        next
+      }
+      loc = $0
+      sub(/.*!dbg /, "", loc)
+      calls[++n] = loc " " callee
+    }
+    END {
+      if (n == 0) {
+        print "no calls"
+      }
+      for (i = 1; i <= n; i++) {
+        split(calls[i], call, " ")
+        line = lines[call[1]]
+        found = 0
+        for (j = line; j != "" && j in source && !found; j++) {
+          found = index(source[j], call[2]) > 0
+          if (source[j] ~ /[;{}][ \t]*$/) {
+            break
+          }
+        }
+        if (!found) {
+          print line " " call[2]
+        }
+      }
+    }' "$f" "$dir/$name.ll")
+  if [ -n "$wrong" ]; then
+    echo "FAIL $f: calls on other lines"
+    echo "$wrong" | head -5
+    fails=$((fails + 1))
+  fi
+done
+
+echo "${#files[@]} programs, $fails failed"
+[ $fails -eq 0 ]
@@ -0,0 +1,41 @@
+// The functions of the C library used by extern.lat, for --target=jvm.
+class Extern {
+    public static int abs(int x) {
+        return Math.abs(x);
+    }
+
+    public static int atoi(String s) {
+        try {
+            return Integer.parseInt(s);
+        } catch (NumberFormatException e) {
+            return 0;
+        }
+    }
+
+    public static int toupper(int c) {
+        return Character.toUpperCase(c);
+    }
+
+    public static double floor(double x) {
+        return Math.floor(x);
+    }
+
+    public static double pow(double x, double y) {
+        return Math.pow(x, y);
+    }
+
+    public static int strncmp(String a, String b, long n) {
+        String x = a.substring(0, (int) Math.min(n, a.length()));
+        String y = b.substring(0, (int) Math.min(n, b.length()));
+        return Integer.signum(x.compareTo(y));
+    }
+
+    public static String strpbrk(String s, String chars) {
+        for (int i = 0; i < s.length(); i++) {
+            if (chars.indexOf(s.charAt(i)) >= 0) {
+                return s.substring(i);
+            }
+        }
+        return null;
+    }
+}
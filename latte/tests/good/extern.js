@@ -0,0 +1,16 @@
+// the functions of the C library used by extern.lat, for --target=wasm
+'use strict';
+
+exports.abs = (x) => Math.abs(x);
+exports.atoi = (s) => parseInt(s, 10) || 0;
+exports.toupper = (c) => String.fromCharCode(c).toUpperCase().charCodeAt(0);
+exports.floor = Math.floor;
+exports.pow = Math.pow;
+exports.strncmp = (a, b, n) => {
+  const [x, y] = [a.slice(0, Number(n)), b.slice(0, Number(n))];
+  return x < y ? -1 : x > y ? 1 : 0;
+};
+exports.strpbrk = (s, chars) => {
+  const i = [...s].findIndex((c) => chars.includes(c));
+  return i < 0 ? null : s.slice(i);
+};
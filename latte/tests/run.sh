@@ -0,0 +1,82 @@
+#!/bin/bash
+# Compiles the programs in tests/good for a target, runs them and compares their output
+# with <name>.output. The input of a program is <name>.input and its exit code is
+# <name>.exit, 0 if it doesn't exist. Extern functions are in <name>.js for WebAssembly
+# and in the Extern class of <name>.java for the JVM. Run in the latte directory after make:
+#
+#   tests/run.sh llvm|c|wasm|x86-64|jvm [file.lat]...
+#
+# The compiler is $LATC, ./latc by default.
+
+latc=$(realpath "${LATC:-./latc}")
+runtime_js=$(realpath lib/runtime.js)
+target=$1
+shift
+case $target in
+  llvm|c|wasm|x86-64|jvm) ;;
+  *)
+    echo "Usage: tests/run.sh llvm|c|wasm|x86-64|jvm [file.lat]..." >&2
+    exit 2
+    ;;
+esac
+
+files=("$@")
+if [ ${#files[@]} -eq 0 ]; then
+  files=(tests/good/*.lat)
+fi
+
+# the outputs are written next to the input, so each program is compiled in a copy
+dir=$(mktemp -d)
+trap 'rm -rf "$dir"' EXIT
+
+fails=0
+for f in "${files[@]}"; do
+  name=$(basename "$f" .lat)
+  input=${f%.lat}.input
+  if [ ! -f "$input" ]; then
+    input=/dev/null
+  fi
+  expected_exit=0
+  if [ -f "${f%.lat}.exit" ]; then
+    expected_exit=$(cat "${f%.lat}.exit")
+  fi
+
+  rm -rf "${dir:?}"/*
+  cp "$f" "$dir"
+  if ! "$latc" --target="$target" "$dir/$name.lat" > "$dir/compiler.txt" 2>&1; then
+    echo "FAIL $f: compilation"
+    head -5 "$dir/compiler.txt"
+    fails=$((fails + 1))
+    continue
+  fi
+  case $target in
+    llvm) cmd=(lli "$name.bc") ;;
+    c|x86-64) cmd=("./$name") ;;
+    wasm)
+      cmd=(node "$runtime_js")
+      if [ -f "${f%.lat}.js" ]; then
+        cmd+=(--extern="$(realpath "${f%.lat}.js")")
+      fi
+      cmd+=("$name.wasm")
+      ;;
+    jvm)
+      if [ -f "${f%.lat}.java" ]; then
+        javac -d "$dir" "${f%.lat}.java"
+      fi
+      cmd=(java -cp . "$name")
+      ;;
+  esac
+  (cd "$dir" && timeout 10 "${cmd[@]}") < "$input" > "$dir/output.txt" 2> /dev/null
+  exit_code=$?
+  if ! diff -q "${f%.lat}.output" "$dir/output.txt" > /dev/null; then
+    echo "FAIL $f: different output"
+    diff "${f%.lat}.output" "$dir/output.txt" | head -10
+    fails=$((fails + 1))
+  elif [ "$exit_code" != "$expected_exit" ]; then
+    echo "FAIL $f: exit code $exit_code instead of $expected_exit"
+    fails=$((fails + 1))
+  fi
+done
+
+echo "$target: ${#files[@]} programs, $fails failed"
+[ $fails -eq 0 ]